@@ -0,0 +1,170 @@
+// Field, point and signature costs, to put numbers on changes to the
+// arithmetic. Big integer code is slow enough that the default 100 samples
+// take minutes for the heavier groups, so those use fewer. With the
+// `parallel` feature, the rayon variants run on pools of 1 to 8 threads to
+// show how they scale
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use num_bigint::{BigInt, BigUint};
+
+use moneda::arithmetic::{FieldElement, Polynomial};
+use moneda::crypto::kzg::{fr, Setup};
+use moneda::crypto::schnorr;
+#[cfg(feature = "parallel")]
+use moneda::curves::secp256k1::par_multi_scalar_mul;
+use moneda::curves::secp256k1::{generator, multi_scalar_mul, order, prime, PrivateKey};
+
+fn scalar(byte: u8) -> BigUint {
+    BigUint::from_bytes_be(&[byte; 32]) % order()
+}
+
+fn field(c: &mut Criterion) {
+    let x = FieldElement::from(BigInt::from(scalar(0x11)), prime()).unwrap();
+    let y = FieldElement::from(BigInt::from(scalar(0x22)), prime()).unwrap();
+    let one = FieldElement::from(BigInt::from(1_u8), prime()).unwrap();
+
+    let mut group = c.benchmark_group("field");
+    group.bench_function("mul", |b| b.iter(|| black_box(x.clone()) * black_box(y.clone())));
+    group.bench_function("inv", |b| b.iter(|| black_box(one.clone()) / black_box(x.clone())));
+    group.finish();
+}
+
+fn point(c: &mut Criterion) {
+    let g = generator();
+    let p = BigInt::from(scalar(0x33)) * g.clone();
+    let k = scalar(0x44);
+
+    let mut group = c.benchmark_group("point");
+    group.bench_function("add", |b| b.iter(|| black_box(g.clone()) + black_box(p.clone())));
+    group.bench_function("double", |b| b.iter(|| black_box(p.clone()) + black_box(p.clone())));
+    group.sample_size(20);
+    group.bench_function("scalar_mul/double_and_add", |b| {
+        b.iter(|| BigInt::from(black_box(k.clone())) * g.clone())
+    });
+    group.bench_function("scalar_mul/straus", |b| {
+        b.iter(|| multi_scalar_mul(&[black_box(k.clone())], std::slice::from_ref(&g)))
+    });
+    // the shared doublings are where Straus pays off
+    let scalars: Vec<BigUint> = (1..=8).map(scalar).collect();
+    let points: Vec<_> = (1..=8).map(|i| BigInt::from(scalar(i + 0x80)) * g.clone()).collect();
+    group.bench_function("multi_scalar_mul/8/separate", |b| {
+        b.iter(|| {
+            scalars
+                .iter()
+                .zip(&points)
+                .map(|(k, p)| BigInt::from(k.clone()) * p.clone())
+                .reduce(|a, b| a + b)
+        })
+    });
+    group.bench_function("multi_scalar_mul/8/straus", |b| {
+        b.iter(|| multi_scalar_mul(black_box(&scalars), &points))
+    });
+    group.finish();
+}
+
+fn signatures(c: &mut Criterion) {
+    let key = PrivateKey::from_bytes(&[0x55; 32]).unwrap();
+    let public = key.public_key();
+    let z = scalar(0x66);
+    let signature = key.sign(&z);
+    let xonly = schnorr::xonly(&public);
+    let sig = schnorr::sign(&key, b"moneda", &[0; 32]);
+
+    let mut group = c.benchmark_group("signatures");
+    group.sample_size(20);
+    group.bench_function("ecdsa/sign", |b| b.iter(|| key.sign(black_box(&z))));
+    group.bench_function("ecdsa/verify", |b| b.iter(|| public.verify(black_box(&z), &signature)));
+    group.bench_function("schnorr/sign", |b| {
+        b.iter(|| schnorr::sign(&key, black_box(b"moneda"), &[0; 32]))
+    });
+    group.bench_function("schnorr/verify", |b| {
+        b.iter(|| schnorr::verify(&xonly, black_box(b"moneda"), &sig))
+    });
+    let batch = schnorr_batch(16);
+    group.bench_function("schnorr/verify/16/separate", |b| {
+        b.iter(|| {
+            batch
+                .iter()
+                .all(|(p, msg, sig)| schnorr::verify(p, black_box(msg), sig))
+        })
+    });
+    group.bench_function("schnorr/verify/16/batch", |b| {
+        b.iter(|| schnorr::verify_batch(black_box(&batch)))
+    });
+    group.finish();
+}
+
+fn schnorr_batch(size: u8) -> Vec<([u8; 32], &'static [u8], [u8; 64])> {
+    (1..=size)
+        .map(|i| {
+            let key = PrivateKey::from_bytes(&[i; 32]).unwrap();
+            (
+                schnorr::xonly(&key.public_key()),
+                &b"moneda"[..],
+                schnorr::sign(&key, b"moneda", &[0; 32]),
+            )
+        })
+        .collect()
+}
+
+fn batch_verification(c: &mut Criterion) {
+    let setup = Setup::from_secret(&BigInt::from(0x1234_5678_u32), 8, 1);
+    // r, the BN254 scalar field's modulus
+    let prime: BigInt = fr(-1).num() + 1;
+    let openings: Vec<_> = (0..4_i64)
+        .map(|i| {
+            let poly = Polynomial::new(prime.clone(), (0..8).map(|j| fr(i * 8 + j + 1)).collect()).unwrap();
+            let commitment = setup.commit(&poly).unwrap();
+            (commitment, setup.open(&poly, &fr(i + 2)).unwrap())
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("kzg");
+    group.sample_size(10);
+    group.bench_function("verify/4/separate", |b| {
+        b.iter(|| {
+            openings
+                .iter()
+                .all(|(commitment, opening)| setup.verify(commitment, opening))
+        })
+    });
+    group.bench_function("verify/4/batch", |b| {
+        b.iter(|| setup.verify_batch(black_box(&openings)))
+    });
+    group.finish();
+}
+
+#[cfg(feature = "parallel")]
+fn parallel(c: &mut Criterion) {
+    let g = generator();
+    let scalars: Vec<BigUint> = (1..=64).map(scalar).collect();
+    let points: Vec<_> = (1..=64).map(|i| BigInt::from(scalar(i + 0x80)) * g.clone()).collect();
+    let batch = schnorr_batch(64);
+
+    let mut group = c.benchmark_group("parallel");
+    group.sample_size(10);
+    group.bench_function("multi_scalar_mul/64/straus", |b| {
+        b.iter(|| multi_scalar_mul(black_box(&scalars), &points))
+    });
+    group.bench_function("schnorr/verify_batch/64", |b| {
+        b.iter(|| schnorr::verify_batch(black_box(&batch)))
+    });
+    for threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_function(format!("multi_scalar_mul/64/pippenger/{}", threads), |b| {
+            b.iter(|| pool.install(|| par_multi_scalar_mul(black_box(&scalars), &points)))
+        });
+        group.bench_function(format!("schnorr/par_verify_batch/64/{}", threads), |b| {
+            b.iter(|| pool.install(|| schnorr::par_verify_batch(black_box(&batch))))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, field, point, signatures, batch_verification);
+#[cfg(feature = "parallel")]
+criterion_group!(parallel_benches, parallel);
+#[cfg(feature = "parallel")]
+criterion_main!(benches, parallel_benches);
+#[cfg(not(feature = "parallel"))]
+criterion_main!(benches);
@@ -0,0 +1,6 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use moneda::prelude::Transaction;
+
+fuzz_target!(|tx: Transaction| moneda::fuzz::transaction_roundtrip(tx));
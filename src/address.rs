@@ -0,0 +1,209 @@
+use crate::crypto::hash::hash160;
+use crate::curves::secp256k1::ecc::PublicKey;
+use crate::encoding::{base58, bech32};
+use crate::script::{Opcode, Script};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    /// The signet run by Bitcoin Core developers, whose blocks need a
+    /// signature as well as work. Shares testnet's address formats
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    pub fn p2pkh_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet | Network::Signet | Network::Regtest => 0x6f,
+        }
+    }
+
+    pub fn p2sh_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            Network::Testnet | Network::Signet | Network::Regtest => 0xc4,
+        }
+    }
+
+    /// Human readable part of segwit addresses
+    pub fn hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+
+    /// Start bytes of every P2P message on the network
+    pub fn magic(self) -> [u8; 4] {
+        match self {
+            Network::Mainnet => [0xf9, 0xbe, 0xb4, 0xd9],
+            Network::Testnet => [0x0b, 0x11, 0x09, 0x07],
+            Network::Signet => [0x0a, 0x03, 0xcf, 0x40],
+            Network::Regtest => [0xfa, 0xbf, 0xb5, 0xda],
+        }
+    }
+
+    /// Compact form of the easiest target blocks may have
+    pub fn pow_limit_bits(self) -> u32 {
+        match self {
+            Network::Mainnet | Network::Testnet => 0x1d00ffff,
+            Network::Signet => 0x1e0377ae,
+            Network::Regtest => 0x207fffff,
+        }
+    }
+
+    pub fn default_port(self) -> u16 {
+        match self {
+            Network::Mainnet => 8333,
+            Network::Testnet => 18333,
+            Network::Signet => 38333,
+            Network::Regtest => 18444,
+        }
+    }
+}
+
+/// Legacy pay-to-pubkey-hash address of the compressed key
+pub fn p2pkh(public_key: &PublicKey, network: Network) -> String {
+    let mut payload = vec![network.p2pkh_prefix()];
+    payload.extend_from_slice(&hash160(&public_key.sec(true)));
+    base58::encode_check(&payload)
+}
+
+/// Native segwit v0 pay-to-witness-pubkey-hash address
+pub fn p2wpkh(public_key: &PublicKey, network: Network) -> String {
+    bech32::encode_segwit(network.hrp(), 0, &hash160(&public_key.sec(true)))
+        .expect("a 20-byte v0 program is always valid")
+}
+
+/// The address that pays to `script_pubkey`, for the standard output types
+/// that have one
+pub fn from_script_pubkey(script_pubkey: &Script, network: Network) -> Option<String> {
+    let bytes = script_pubkey.as_bytes();
+    let prefix = if script_pubkey.is_p2pkh() {
+        network.p2pkh_prefix()
+    } else if script_pubkey.is_p2sh() {
+        network.p2sh_prefix()
+    } else {
+        let (version, program) = script_pubkey.witness_program()?;
+        return bech32::encode_segwit(network.hrp(), version, program).ok();
+    };
+    let hash = if script_pubkey.is_p2pkh() {
+        &bytes[3..23]
+    } else {
+        &bytes[2..22]
+    };
+    let mut payload = vec![prefix];
+    payload.extend_from_slice(hash);
+    Some(base58::encode_check(&payload))
+}
+
+/// The script_pubkey `address` pays to, the inverse of `from_script_pubkey`.
+/// None if it's malformed or for another network
+pub fn to_script_pubkey(address: &str, network: Network) -> Option<Script> {
+    if let Ok((version, program)) = bech32::decode_segwit(network.hrp(), address) {
+        let version = Opcode::from_small_int(version)?;
+        return Some(Script::new().push_opcode(version).push_slice(&program));
+    }
+    let payload = base58::decode_check(address).ok()?;
+    let (&prefix, hash) = payload.split_first()?;
+    let hash = hash.try_into().ok()?;
+    if prefix == network.p2pkh_prefix() {
+        Some(Script::p2pkh(hash))
+    } else if prefix == network.p2sh_prefix() {
+        Some(Script::p2sh(hash))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod address_tests {
+    use super::*;
+    use crate::curves::secp256k1::ecc::PrivateKey;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_p2pkh() {
+        let key = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+
+        assert_eq!(p2pkh(&key, Network::Mainnet), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+        assert_eq!(p2pkh(&key, Network::Testnet), "mrCDrCybB6J1vRfbwM5hemdJz73FwDBC8r");
+    }
+
+    #[test]
+    fn test_p2wpkh() {
+        let key = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+
+        assert_eq!(
+            p2wpkh(&key, Network::Mainnet),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert_eq!(
+            p2wpkh(&key, Network::Regtest),
+            "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"
+        );
+        assert_eq!(
+            p2wpkh(&key, Network::Signet),
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+        );
+    }
+
+    #[test]
+    fn test_from_script_pubkey() {
+        let key = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+        let hash = hash160(&key.sec(true));
+        assert_eq!(
+            from_script_pubkey(&Script::p2pkh(&hash), Network::Mainnet),
+            Some(p2pkh(&key, Network::Mainnet))
+        );
+        assert_eq!(
+            from_script_pubkey(&Script::p2wpkh(&hash), Network::Testnet),
+            Some(p2wpkh(&key, Network::Testnet))
+        );
+        assert_eq!(
+            from_script_pubkey(&Script::p2sh(&[0; 20]), Network::Mainnet).as_deref(),
+            Some("31h1vYVSYuKP6AhS86fbRdMw9XHieotbST")
+        );
+        assert_eq!(
+            from_script_pubkey(&Script::new().push_slice(&key.sec(true)), Network::Mainnet),
+            None
+        );
+    }
+
+    #[test]
+    fn test_to_script_pubkey() {
+        let key = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+        let hash = hash160(&key.sec(true));
+        for network in [Network::Mainnet, Network::Testnet, Network::Regtest] {
+            assert_eq!(
+                to_script_pubkey(&p2pkh(&key, network), network),
+                Some(Script::p2pkh(&hash))
+            );
+            assert_eq!(
+                to_script_pubkey(&p2wpkh(&key, network), network),
+                Some(Script::p2wpkh(&hash))
+            );
+        }
+        let p2tr = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+        let script = to_script_pubkey(p2tr, Network::Mainnet).unwrap();
+        assert_eq!(from_script_pubkey(&script, Network::Mainnet).as_deref(), Some(p2tr));
+        assert_eq!(
+            to_script_pubkey("31h1vYVSYuKP6AhS86fbRdMw9XHieotbST", Network::Mainnet),
+            Some(Script::p2sh(&[0; 20]))
+        );
+        // wrong network, bad checksum
+        assert_eq!(to_script_pubkey(&p2pkh(&key, Network::Mainnet), Network::Testnet), None);
+        assert_eq!(
+            to_script_pubkey(&p2wpkh(&key, Network::Mainnet), Network::Testnet),
+            None
+        );
+        assert_eq!(
+            to_script_pubkey("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMJ", Network::Mainnet),
+            None
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use num_bigint::{BigInt, BigUint};
+use num_traits::Zero;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+
+use crate::contract::{
+    ecdsa_sign_with_original_nonce, schnorr_sign_with_original_nonce, verify_ecdsa_contract, verify_schnorr_contract,
+};
+use crate::crypto::hash::tagged_hash;
+use crate::crypto::hmac_drbg::HmacDrbg;
+use crate::crypto::schnorr;
+use crate::curves::secp256k1::ecc::{order, to_32_bytes, PrivateKey, PublicKey, Signature};
+
+// A signer that picks its own nonces can hide data in them: by grinding k
+// until r has the bits it wants, a compromised hardware wallet can leak
+// its key a few bits per signature through signatures that all verify.
+// Anti-exfil takes that choice away:
+//
+// 1. the host picks randomness ρ and sends the signer H(ρ)
+// 2. the signer derives k0 from its key, the message and H(ρ), and sends
+//    R0 = k0 G
+// 3. the host sends ρ, and the signer signs with k = k0 + H(R0 || ρ),
+//    sign-to-contract with ρ as the contract
+// 4. the host checks the signature and that R0 and ρ open its nonce
+//
+// The signer is bound to R0 before it sees ρ and the host only learns ρ's
+// effect on R after the signer is bound, so neither controls R alone
+
+/// The host's side: its randomness and the checks on what comes back
+#[derive(Debug, Clone, PartialEq)]
+pub struct Host {
+    randomness: [u8; 32],
+}
+
+impl Host {
+    pub fn new() -> Self {
+        let mut randomness = [0_u8; 32];
+        OsRng.fill_bytes(&mut randomness);
+        Self { randomness }
+    }
+
+    pub fn from_randomness(randomness: [u8; 32]) -> Self {
+        Self { randomness }
+    }
+
+    /// H(ρ), sent to the signer first
+    pub fn commitment(&self) -> [u8; 32] {
+        host_commitment(&self.randomness)
+    }
+
+    /// ρ, sent once the signer has committed to R0
+    pub fn randomness(&self) -> &[u8; 32] {
+        &self.randomness
+    }
+
+    pub fn verify_ecdsa(
+        &self,
+        public_key: &PublicKey,
+        z: &BigUint,
+        signature: &Signature,
+        nonce_commitment: &PublicKey,
+    ) -> bool {
+        public_key.verify(z, signature) && verify_ecdsa_contract(signature, nonce_commitment, &self.randomness)
+    }
+
+    pub fn verify_schnorr(
+        &self,
+        public_key: &[u8; 32],
+        msg: &[u8],
+        sig: &[u8; 64],
+        nonce_commitment: &PublicKey,
+    ) -> bool {
+        schnorr::verify(public_key, msg, sig) && verify_schnorr_contract(sig, nonce_commitment, &self.randomness)
+    }
+}
+
+impl Default for Host {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The signer's R0 for an ECDSA signature over `z`. k0 is rederived when
+/// signing, so the signer keeps no state between the two rounds
+pub fn ecdsa_nonce_commitment(private_key: &PrivateKey, z: &BigUint, host_commitment: &[u8; 32]) -> PublicKey {
+    PrivateKey::from(ecdsa_original_nonce(private_key, z, host_commitment))
+        .expect("nonce is in [1, n)")
+        .public_key()
+}
+
+/// Signs once the host has revealed ρ, with the nonce committed to in
+/// `ecdsa_nonce_commitment` tweaked by ρ
+pub fn ecdsa_sign(private_key: &PrivateKey, z: &BigUint, host_randomness: &[u8; 32]) -> Signature {
+    let k0 = ecdsa_original_nonce(private_key, z, &host_commitment(host_randomness));
+    // only fails with negligible probability
+    ecdsa_sign_with_original_nonce(private_key, z, &k0, host_randomness)
+        .expect("tweaked nonce is out of range")
+        .0
+}
+
+/// The signer's R0 for a BIP-340 signature over `msg`
+pub fn schnorr_nonce_commitment(private_key: &PrivateKey, msg: &[u8], host_commitment: &[u8; 32]) -> PublicKey {
+    PrivateKey::from(schnorr_original_nonce(private_key, msg, host_commitment))
+        .expect("nonce is in [1, n)")
+        .public_key()
+}
+
+pub fn schnorr_sign(private_key: &PrivateKey, msg: &[u8], host_randomness: &[u8; 32]) -> [u8; 64] {
+    let k0 = schnorr_original_nonce(private_key, msg, &host_commitment(host_randomness));
+    schnorr_sign_with_original_nonce(private_key, msg, &k0, host_randomness)
+        .expect("tweaked nonce is out of range")
+        .0
+}
+
+fn host_commitment(randomness: &[u8; 32]) -> [u8; 32] {
+    tagged_hash("moneda/anti-exfil/host", randomness)
+}
+
+/// RFC 6979 personalized by the host's commitment, which makes k0 depend
+/// on something the signer can't pick
+fn ecdsa_original_nonce(private_key: &PrivateKey, z: &BigUint, host_commitment: &[u8; 32]) -> BigUint {
+    let n = order();
+    let z = to_32_bytes(&BigInt::from(z % &n));
+    let mut drbg = HmacDrbg::<Sha256>::new(&private_key.to_bytes(), &z, host_commitment);
+    loop {
+        let mut k_bytes = [0_u8; 32];
+        drbg.generate(&mut k_bytes, &[]).unwrap();
+        let k0 = BigUint::from_bytes_be(&k_bytes);
+        if !k0.is_zero() && k0 < n {
+            return k0;
+        }
+    }
+}
+
+fn schnorr_original_nonce(private_key: &PrivateKey, msg: &[u8], host_commitment: &[u8; 32]) -> BigUint {
+    let data = [&private_key.to_bytes()[..], msg, host_commitment].concat();
+    let k0 = BigUint::from_bytes_be(&tagged_hash("moneda/anti-exfil/nonce", &data)) % order();
+    // only happens with negligible probability
+    assert!(!k0.is_zero(), "anti-exfil nonce is zero");
+    k0
+}
+
+#[cfg(test)]
+mod anti_exfil_tests {
+    use super::*;
+    use crate::crypto::hash::sha256;
+
+    fn key(byte: u8) -> PrivateKey {
+        PrivateKey::from_bytes(&[byte; 32]).unwrap()
+    }
+
+    fn z(msg: &[u8]) -> BigUint {
+        BigUint::from_bytes_be(&sha256(msg))
+    }
+
+    #[test]
+    fn test_ecdsa_protocol() {
+        let signer = key(0x11);
+        let host = Host::from_randomness([7; 32]);
+        let r0 = ecdsa_nonce_commitment(&signer, &z(b"tx"), &host.commitment());
+        let signature = ecdsa_sign(&signer, &z(b"tx"), host.randomness());
+        assert!(host.verify_ecdsa(&signer.public_key(), &z(b"tx"), &signature, &r0));
+
+        // a signature with any other nonce is caught, even though it verifies
+        let plain = signer.sign(&z(b"tx"));
+        assert!(signer.public_key().verify(&z(b"tx"), &plain));
+        assert!(!host.verify_ecdsa(&signer.public_key(), &z(b"tx"), &plain, &r0));
+        // as is a nonce committed to for a different host
+        let other = Host::from_randomness([8; 32]);
+        let signature = ecdsa_sign(&signer, &z(b"tx"), other.randomness());
+        assert!(!host.verify_ecdsa(&signer.public_key(), &z(b"tx"), &signature, &r0));
+    }
+
+    #[test]
+    fn test_schnorr_protocol() {
+        let signer = key(0x22);
+        let public = schnorr::xonly(&signer.public_key());
+        let host = Host::new();
+        let r0 = schnorr_nonce_commitment(&signer, b"msg", &host.commitment());
+        let sig = schnorr_sign(&signer, b"msg", host.randomness());
+        assert!(host.verify_schnorr(&public, b"msg", &sig, &r0));
+        assert!(!host.verify_schnorr(&public, b"other", &sig, &r0));
+        let plain = schnorr::sign(&signer, b"msg", &[0; 32]);
+        assert!(!host.verify_schnorr(&public, b"msg", &plain, &r0));
+    }
+
+    /// A malicious signer leaks one key bit per signature by grinding k
+    /// until r's low bit matches. The host sees only valid signatures,
+    /// unless it runs the protocol, which rejects every ground nonce
+    #[test]
+    fn test_exfiltration() {
+        let signer = key(0x5a);
+        let secret = signer.secret().clone();
+        let grind = |i: u64, host: Option<&Host>| {
+            let want = secret.bit(i);
+            (1_u32..)
+                .map(|k| {
+                    let k = BigUint::from(k) + i * 1000;
+                    match host {
+                        None => signer.sign_with_nonce(&z(b"tx"), &k).unwrap(),
+                        Some(host) => {
+                            ecdsa_sign_with_original_nonce(&signer, &z(b"tx"), &k, host.randomness())
+                                .unwrap()
+                                .0
+                        }
+                    }
+                })
+                .find(|signature| signature.r().bit(0) == want)
+                .unwrap()
+        };
+
+        let leaked: Vec<bool> = (0..8).map(|i| grind(i, None).r().bit(0)).collect();
+        assert_eq!(leaked, (0..8).map(|i| secret.bit(i)).collect::<Vec<_>>());
+
+        // the signer can still grind after seeing ρ, but then its nonce
+        // isn't the R0 it committed to
+        let host = Host::from_randomness([9; 32]);
+        let r0 = ecdsa_nonce_commitment(&signer, &z(b"tx"), &host.commitment());
+        let ground = grind(0, Some(&host));
+        assert!(signer.public_key().verify(&z(b"tx"), &ground));
+        assert!(!host.verify_ecdsa(&signer.public_key(), &z(b"tx"), &ground, &r0));
+    }
+}
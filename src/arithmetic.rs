@@ -0,0 +1,9 @@
+// Finite field and polynomial arithmetic, the layer everything else is
+// built on. Elements carry their modulus, so one type serves secp256k1's
+// base field, its scalar field and the toy fields of the tests
+
+pub(crate) mod field_element;
+pub(crate) mod polynomial;
+
+pub use field_element::{FieldElement, FieldError};
+pub use polynomial::Polynomial;
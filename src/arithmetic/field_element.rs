@@ -0,0 +1,709 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::ops::{Add, Div, Mul, Sub};
+use std::thread::LocalKey;
+
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::{Uint, U256, U384};
+use num_bigint::{BigInt, Sign};
+
+use crate::crypto::prime::factor;
+use crate::encoding::hex_fmt::{impl_hex_fmt, readable};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+    /// Elements are integers in [0, prime)
+    OutOfRange {
+        num: BigInt,
+        prime: BigInt,
+    },
+    /// The modulus must be odd, at least 3 and at most 384 bits
+    InvalidModulus(BigInt),
+    /// The operands are elements of different fields
+    FieldMismatch,
+    DivisionByZero,
+    InvalidHex,
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldError::OutOfRange { num, prime } => write!(f, "{} not in field of order {}", num, prime),
+            FieldError::InvalidModulus(prime) => write!(f, "{} is not a valid field modulus", prime),
+            FieldError::FieldMismatch => write!(f, "elements must be in the same field"),
+            FieldError::DivisionByZero => write!(f, "division by zero"),
+            FieldError::InvalidHex => write!(f, "invalid field element hex"),
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// An element of F_p, held as a fixed-size Montgomery residue so the
+/// arithmetic in point operations and pairings never allocates. The
+/// modulus travels with every element; `BigInt` is only the interface
+#[derive(Clone)]
+pub struct FieldElement {
+    residue: Residue,
+}
+
+// Every field in the crate fits in 256 bits except those of the 384-bit
+// curves. Those get a wider residue rather than widening all of them,
+// which would slow down every multiplication on the common curves by
+// half again; each operation matches on the width instead
+#[derive(Clone, Copy, PartialEq)]
+enum Residue {
+    Narrow(DynResidue<{ U256::LIMBS }>),
+    Wide(DynResidue<{ U384::LIMBS }>),
+}
+
+/// Evaluates `$body` with `$r` bound to the residue of either width
+macro_rules! each {
+    ($residue:expr, $r:ident => $body:expr) => {
+        match $residue {
+            Residue::Narrow($r) => $body,
+            Residue::Wide($r) => $body,
+        }
+    };
+}
+
+/// Like `each!`, for a body that makes a residue of the same width
+macro_rules! map_each {
+    ($residue:expr, $r:ident => $body:expr) => {
+        match $residue {
+            Residue::Narrow($r) => Residue::Narrow($body),
+            Residue::Wide($r) => Residue::Wide($body),
+        }
+    };
+}
+
+/// Applies a binary operator to residues of one field, which
+/// `assert_same_field` has checked, so they also have one width
+macro_rules! binary {
+    ($lhs:expr, $rhs:expr, $op:tt) => {
+        match ($lhs, $rhs) {
+            (Residue::Narrow(a), Residue::Narrow(b)) => Residue::Narrow(a $op b),
+            (Residue::Wide(a), Residue::Wide(b)) => Residue::Wide(a $op b),
+            _ => unreachable!("the fields were checked to agree"),
+        }
+    };
+}
+
+impl Residue {
+    /// For a valid modulus and `num` in [0, prime)
+    fn new(num: &BigInt, prime: &BigInt) -> Self {
+        if prime.bits() <= 256 {
+            Residue::Narrow(DynResidue::new(
+                &to_uint(num),
+                cached_params(&NARROW_PARAMS, to_uint(prime)),
+            ))
+        } else {
+            Residue::Wide(DynResidue::new(
+                &to_uint(num),
+                cached_params(&WIDE_PARAMS, to_uint(prime)),
+            ))
+        }
+    }
+
+    fn num(&self) -> BigInt {
+        each!(self, r => to_bigint(&r.retrieve()))
+    }
+
+    fn prime(&self) -> BigInt {
+        each!(self, r => to_bigint(r.params().modulus()))
+    }
+
+    fn same_field(&self, rhs: &Self) -> bool {
+        match (self, rhs) {
+            (Residue::Narrow(a), Residue::Narrow(b)) => a.params() == b.params(),
+            (Residue::Wide(a), Residue::Wide(b)) => a.params() == b.params(),
+            _ => false,
+        }
+    }
+}
+
+impl FieldElement {
+    /// `num` in [0, prime). The modulus must be odd, at least 3 and at most
+    /// 384 bits, since the arithmetic is in Montgomery form at one of two
+    /// fixed widths: every curve in the crate fits, but F_2 and fields past
+    /// 384 bits are `InvalidModulus`
+    pub fn from(num: BigInt, prime: BigInt) -> Result<Self, FieldError> {
+        check_modulus(&prime)?;
+        if num >= prime || num < BigInt::from(0_u8) {
+            return Err(FieldError::OutOfRange { num, prime });
+        }
+        Ok(Self {
+            residue: Residue::new(&num, &prime),
+        })
+    }
+
+    /// From little-endian 64-bit limbs of the element and the prime, such
+    /// as `ecc::P`, with no BigInt in between
+    pub fn from_limbs(num: [u64; 4], prime: [u64; 4]) -> Result<Self, FieldError> {
+        let (num, modulus) = (from_limbs(num), from_limbs(prime));
+        if modulus < U256::from_u8(3) || !modulus.bit_vartime(0) {
+            return Err(FieldError::InvalidModulus(to_bigint(&modulus)));
+        }
+        if num >= modulus {
+            return Err(FieldError::OutOfRange {
+                num: to_bigint(&num),
+                prime: to_bigint(&modulus),
+            });
+        }
+        Ok(Self {
+            residue: Residue::Narrow(DynResidue::new(&num, cached_params(&NARROW_PARAMS, modulus))),
+        })
+    }
+
+    /// The element congruent to any integer, negative ones included. The
+    /// modulus has the limits of `from`
+    pub fn reduce(num: BigInt, prime: BigInt) -> Result<Self, FieldError> {
+        check_modulus(&prime)?;
+        let num = (num % &prime + &prime) % &prime;
+        Ok(Self {
+            residue: Residue::new(&num, &prime),
+        })
+    }
+
+    pub fn num(&self) -> BigInt {
+        self.residue.num()
+    }
+
+    pub fn prime(&self) -> BigInt {
+        self.residue.prime()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        each!(self.residue, r => r.retrieve() == Uint::ZERO)
+    }
+
+    pub fn pow(self, exp: BigInt) -> Self {
+        // Fermat's little theorem lets us reduce the exponent mod p - 1,
+        // which also turns negative exponents into positive ones
+        let order = self.prime() - 1_u8;
+        let n: BigInt = ((exp % &order) + &order) % &order;
+        // the exponent is as wide as the residue, so the ladder runs over
+        // no more bits than the field has
+        let residue = match self.residue {
+            Residue::Narrow(r) => Residue::Narrow(r.pow(&to_uint::<{ U256::LIMBS }>(&n))),
+            Residue::Wide(r) => Residue::Wide(r.pow(&to_uint::<{ U384::LIMBS }>(&n))),
+        };
+        Self { residue }
+    }
+
+    /// The least k > 0 with self^k = 1, a divisor of p - 1 by Lagrange's
+    /// theorem: starting from p - 1, each prime factor is divided out for
+    /// as long as the power stays 1. None for zero, which has no inverse
+    pub fn multiplicative_order(&self) -> Option<BigInt> {
+        if self.is_zero() {
+            return None;
+        }
+        let group_order = self.prime() - 1_u8;
+        let mut order = group_order.clone();
+        for (q, _) in factor(&group_order.to_biguint().expect("p - 1 is positive")) {
+            let q = BigInt::from(q);
+            while (&order % &q).sign() == Sign::NoSign && self.clone().pow(&order / &q).num() == BigInt::from(1_u8) {
+                order /= &q;
+            }
+        }
+        Some(order)
+    }
+
+    pub fn is_odd(&self) -> bool {
+        each!(self.residue, r => r.retrieve().bit_vartime(0))
+    }
+
+    /// Big-endian, as wide as the prime, so secp256k1 elements are 32 bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let width = (self.prime().bits() as usize).div_ceil(8);
+        let (_, bytes) = self.num().to_bytes_be();
+        let mut padded = vec![0_u8; width - bytes.len()];
+        padded.extend(bytes);
+        padded
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    /// Big-endian hex of any width, such as `to_hex` writes
+    pub fn from_hex(s: &str, prime: BigInt) -> Result<Self, FieldError> {
+        let bytes = hex::decode(s).map_err(|_| FieldError::InvalidHex)?;
+        Self::from(BigInt::from_bytes_be(Sign::Plus, &bytes), prime)
+    }
+
+    // The operators panic on elements of different fields, which only a
+    // bug can produce once elements come from the fallible constructors.
+    // These are the variants for when the fields aren't known to agree
+
+    pub fn checked_add(self, rhs: Self) -> Result<Self, FieldError> {
+        self.same_field(&rhs)?;
+        Ok(self + rhs)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Result<Self, FieldError> {
+        self.same_field(&rhs)?;
+        Ok(self - rhs)
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Result<Self, FieldError> {
+        self.same_field(&rhs)?;
+        Ok(self * rhs)
+    }
+
+    /// Also rejects zero, which `/` maps to zero
+    pub fn checked_div(self, rhs: Self) -> Result<Self, FieldError> {
+        self.same_field(&rhs)?;
+        if rhs.is_zero() {
+            return Err(FieldError::DivisionByZero);
+        }
+        Ok(self / rhs)
+    }
+
+    fn same_field(&self, rhs: &Self) -> Result<(), FieldError> {
+        if !self.residue.same_field(&rhs.residue) {
+            return Err(FieldError::FieldMismatch);
+        }
+        Ok(())
+    }
+
+    fn assert_same_field(&self, rhs: &Self) {
+        if self.same_field(rhs).is_err() {
+            panic!("Elements must be in the same field")
+        }
+    }
+}
+
+// the bridge to BigInt: Montgomery form needs an odd modulus, and all the
+// fields in the crate fit in 384 bits
+
+fn check_modulus(prime: &BigInt) -> Result<(), FieldError> {
+    if *prime < BigInt::from(3_u8) || !prime.bit(0) || prime.bits() > 384 {
+        return Err(FieldError::InvalidModulus(prime.clone()));
+    }
+    Ok(())
+}
+
+type ParamsCache<const LIMBS: usize> = RefCell<Vec<(Uint<LIMBS>, DynResidueParams<LIMBS>)>>;
+
+fn cached_params<const LIMBS: usize>(
+    cache: &'static LocalKey<ParamsCache<LIMBS>>,
+    modulus: Uint<LIMBS>,
+) -> DynResidueParams<LIMBS> {
+    // the Montgomery constants take a double-width division to set up, and
+    // a program only ever uses a handful of fields, so they're kept per thread
+    cache.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some((_, params)) = cache.iter().find(|(m, _)| *m == modulus) {
+            return *params;
+        }
+        let params = DynResidueParams::new(&modulus);
+        if cache.len() == PARAMS_CACHE_SIZE {
+            cache.remove(0);
+        }
+        cache.push((modulus, params));
+        params
+    })
+}
+
+const PARAMS_CACHE_SIZE: usize = 8;
+
+thread_local! {
+    static NARROW_PARAMS: ParamsCache<{ U256::LIMBS }> = const { RefCell::new(Vec::new()) };
+    static WIDE_PARAMS: ParamsCache<{ U384::LIMBS }> = const { RefCell::new(Vec::new()) };
+}
+
+/// For integers in [0, 2^384) that fit the width
+fn to_uint<const LIMBS: usize>(n: &BigInt) -> Uint<LIMBS> {
+    let (_, bytes) = n.to_bytes_be();
+    let width = Uint::<LIMBS>::BYTES;
+    let mut padded = [0_u8; 48];
+    padded[width - bytes.len()..width].copy_from_slice(&bytes);
+    Uint::from_be_slice(&padded[..width])
+}
+
+fn from_limbs(limbs: [u64; 4]) -> U256 {
+    // through bytes, since crypto-bigint's words are 32 bits on wasm32
+    let mut bytes = [0_u8; 32];
+    for (chunk, limb) in bytes.chunks_exact_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    U256::from_le_slice(&bytes)
+}
+
+fn to_bigint<const LIMBS: usize>(n: &Uint<LIMBS>) -> BigInt {
+    let bytes: Vec<u8> = n
+        .as_limbs()
+        .iter()
+        .rev()
+        .flat_map(|limb| limb.0.to_be_bytes())
+        .collect();
+    BigInt::from_bytes_be(Sign::Plus, &bytes)
+}
+
+impl std::fmt::Debug for FieldElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FieldElement")
+            .field("num", &self.num())
+            .field("prime", &self.prime())
+            .finish()
+    }
+}
+
+impl PartialEq for FieldElement {
+    fn eq(&self, other: &Self) -> bool {
+        self.residue == other.residue
+    }
+}
+
+/// `{:#}` is the element as it's written by hand, "43 (mod 223)", with
+/// numbers past 64 bits in hex
+impl Display for FieldElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return write!(f, "{} (mod {})", readable(&self.num()), readable(&self.prime()));
+        }
+        write!(f, "FieldElement_{}({})", self.prime(), self.num())
+    }
+}
+
+impl_hex_fmt!(FieldElement, FieldElement::to_bytes);
+
+impl Add for FieldElement {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.assert_same_field(&rhs);
+        Self {
+            residue: binary!(self.residue, rhs.residue, +),
+        }
+    }
+}
+
+impl Sub for FieldElement {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.assert_same_field(&rhs);
+        Self {
+            residue: binary!(self.residue, rhs.residue, -),
+        }
+    }
+}
+
+impl Mul for FieldElement {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.assert_same_field(&rhs);
+        Self {
+            residue: binary!(self.residue, rhs.residue, *),
+        }
+    }
+}
+
+impl Mul<BigInt> for FieldElement {
+    type Output = Self;
+
+    fn mul(self, rhs: BigInt) -> Self::Output {
+        let rhs = FieldElement::reduce(rhs, self.prime()).expect("the modulus is valid");
+        self * rhs
+    }
+}
+
+impl Mul<FieldElement> for BigInt {
+    type Output = FieldElement;
+
+    fn mul(self, rhs: FieldElement) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl Div for FieldElement {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.assert_same_field(&rhs);
+        // zero has no inverse; dividing by it gives zero, as a^(p-2) would
+        let inverse = map_each!(rhs.residue, r => {
+            let (inverse, invertible) = r.invert();
+            if bool::from(invertible) { inverse } else { DynResidue::zero(*r.params()) }
+        });
+        Self {
+            residue: binary!(self.residue, inverse, *),
+        }
+    }
+}
+
+#[cfg(test)]
+mod field_elem_tests {
+    use super::*;
+
+    #[test]
+    fn test_field_elem_eq() {
+        let a = FieldElement::from(BigInt::from(7_u8), BigInt::from(13_u8)).unwrap();
+        let b = FieldElement::from(BigInt::from(6_u8), BigInt::from(13_u8)).unwrap();
+
+        assert_eq!(a, a);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_field_add() {
+        let prime = BigInt::from(13_u8);
+        let a = FieldElement::from(BigInt::from(7_u8), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(12_u8), prime.clone()).unwrap();
+        let c = FieldElement::from(BigInt::from(6_u8), prime.clone()).unwrap();
+
+        assert_eq!(a + b, c);
+    }
+
+    #[test]
+    fn test_field_sub() {
+        let prime = BigInt::from(19_u8);
+        let a = FieldElement::from(BigInt::from(6_u8), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(13_u8), prime.clone()).unwrap();
+        let c = FieldElement::from(BigInt::from(12_u8), prime.clone()).unwrap();
+
+        assert_eq!(a - b, c);
+    }
+
+    #[test]
+    fn test_field_mul() {
+        let prime = BigInt::from(13_u8);
+        let a = FieldElement::from(BigInt::from(3_u8), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(12_u8), prime.clone()).unwrap();
+        let c = FieldElement::from(BigInt::from(10_u8), prime.clone()).unwrap();
+
+        assert_eq!(a * b, c);
+    }
+
+    #[test]
+    fn test_field_pow() {
+        let prime = BigInt::from(13_u8);
+        let a = FieldElement::from(BigInt::from(3_u8), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(1_u8), prime.clone()).unwrap();
+
+        assert_eq!(a.pow(BigInt::from(3_u8)), b);
+    }
+
+    #[test]
+    fn test_field_pow_negative() {
+        let prime = BigInt::from(31_u8);
+        let a = FieldElement::from(BigInt::from(17_u8), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(29_u8), prime.clone()).unwrap();
+
+        assert_eq!(a.pow(BigInt::from(-3)), b);
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let prime = BigInt::from(13_u8);
+        assert_eq!(
+            FieldElement::from(BigInt::from(13_u8), prime.clone()),
+            Err(FieldError::OutOfRange {
+                num: BigInt::from(13_u8),
+                prime: prime.clone()
+            })
+        );
+        assert!(FieldElement::from(BigInt::from(-1), prime.clone()).is_err());
+        assert_eq!(
+            FieldElement::from(BigInt::from(0_u8), BigInt::from(1_u8)),
+            Err(FieldError::InvalidModulus(BigInt::from(1_u8)))
+        );
+        assert_eq!(
+            FieldElement::from(BigInt::from(0_u8), BigInt::from(10_u8)),
+            Err(FieldError::InvalidModulus(BigInt::from(10_u8)))
+        );
+        let too_big = BigInt::from(1_u8) << 384_u32 | BigInt::from(1_u8);
+        assert_eq!(
+            FieldElement::from(BigInt::from(0_u8), too_big.clone()),
+            Err(FieldError::InvalidModulus(too_big))
+        );
+        assert_eq!(
+            FieldElement::reduce(BigInt::from(-1), prime.clone()),
+            FieldElement::from(BigInt::from(12_u8), prime.clone())
+        );
+
+        let a = FieldElement::from(BigInt::from(3_u8), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(3_u8), BigInt::from(7_u8)).unwrap();
+        let zero = FieldElement::from(BigInt::from(0_u8), prime).unwrap();
+        assert_eq!(a.clone().checked_add(b.clone()), Err(FieldError::FieldMismatch));
+        assert_eq!(a.clone().checked_mul(b), Err(FieldError::FieldMismatch));
+        assert_eq!(a.clone().checked_div(zero.clone()), Err(FieldError::DivisionByZero));
+        assert_eq!(a.clone().checked_sub(a), Ok(zero));
+    }
+
+    #[test]
+    fn test_field_modulus_limits() {
+        let zero = BigInt::from(0_u8);
+        let two = BigInt::from(2_u8);
+        assert_eq!(
+            FieldElement::from(zero.clone(), two.clone()),
+            Err(FieldError::InvalidModulus(two.clone()))
+        );
+        assert_eq!(
+            FieldElement::reduce(BigInt::from(5_u8), two.clone()),
+            Err(FieldError::InvalidModulus(two))
+        );
+        assert!(FieldElement::from(zero.clone(), BigInt::from(3_u8)).is_ok());
+
+        // 384 bits is the widest modulus, one bit more is too many
+        let widest = (BigInt::from(1_u8) << 384_u32) - 1_u8;
+        let a = FieldElement::from(&widest - 1_u8, widest.clone()).unwrap();
+        assert_eq!(a.clone() * a, FieldElement::from(BigInt::from(1_u8), widest).unwrap());
+        let too_wide = (BigInt::from(1_u8) << 385_u32) - 1_u8;
+        assert_eq!(
+            FieldElement::from(zero, too_wide.clone()),
+            Err(FieldError::InvalidModulus(too_wide))
+        );
+    }
+
+    #[test]
+    fn test_field_bigint_bridge() {
+        // secp256k1's p, which uses all 256 bits
+        let prime = (BigInt::from(1_u8) << 256_u32) - BigInt::from(0x1000003d1_u64);
+        let num = &prime - 2_u8;
+        let a = FieldElement::from(num.clone(), prime.clone()).unwrap();
+        assert_eq!(a.num(), num);
+        assert_eq!(a.prime(), prime);
+        assert!(a.is_odd());
+        assert_eq!(
+            a.clone() * BigInt::from(-1),
+            FieldElement::from(BigInt::from(2_u8), prime.clone()).unwrap()
+        );
+        assert_eq!(
+            BigInt::from(3_u8) * a.clone() + a.clone() * BigInt::from(-3),
+            FieldElement::reduce(BigInt::from(0_u8), prime.clone()).unwrap()
+        );
+        let zero = FieldElement::from(BigInt::from(0_u8), prime).unwrap();
+        assert!(zero.is_zero());
+        assert_eq!(a / zero.clone(), zero);
+    }
+
+    #[test]
+    fn test_field_wide() {
+        // brainpoolP384r1's p, past the narrow residue's 256 bits
+        let prime = BigInt::parse_bytes(
+            b"8cb91e82a3386d280f5d6f7e50e641df152f7109ed5456b412b1da197fb71123acd3a729901d1a71874700133107ec53",
+            16,
+        )
+        .unwrap();
+        let num = &prime - 2_u8;
+        let a = FieldElement::from(num.clone(), prime.clone()).unwrap();
+        assert_eq!((a.num(), a.prime()), (num, prime.clone()));
+        assert_eq!(a.to_bytes().len(), 48);
+        assert_eq!(
+            a.clone() * a.clone(),
+            FieldElement::from(BigInt::from(4_u8), prime.clone()).unwrap()
+        );
+        assert_eq!(a.clone() + a.clone() - a.clone(), a);
+        assert_eq!(
+            a.clone().pow(BigInt::from(-1)) * a.clone(),
+            FieldElement::from(BigInt::from(1_u8), prime.clone()).unwrap()
+        );
+        assert_eq!(FieldElement::from_hex(&a.to_hex(), prime.clone()), Ok(a.clone()));
+        let zero = FieldElement::from(BigInt::from(0_u8), prime).unwrap();
+        assert!(zero.is_zero());
+        assert_eq!(a.clone() / zero.clone(), zero);
+
+        // the same number in a narrow field is a different element
+        let narrow = FieldElement::from(BigInt::from(5_u8), BigInt::from(7_u8)).unwrap();
+        assert_eq!(a.checked_add(narrow), Err(FieldError::FieldMismatch));
+    }
+
+    #[test]
+    fn test_field_from_limbs() {
+        let prime = [0xffff_fffe_ffff_fc2f, u64::MAX, u64::MAX, u64::MAX];
+        let big_prime = (BigInt::from(1_u8) << 256_u32) - BigInt::from(0x1000003d1_u64);
+        let a = FieldElement::from_limbs([5, 0, 0, 1], prime).unwrap();
+        assert_eq!(
+            a,
+            FieldElement::from((BigInt::from(1_u8) << 192_u32) + 5_u8, big_prime.clone()).unwrap()
+        );
+        assert_eq!(
+            FieldElement::from_limbs([13, 0, 0, 0], [13, 0, 0, 0]),
+            Err(FieldError::OutOfRange {
+                num: BigInt::from(13_u8),
+                prime: BigInt::from(13_u8)
+            })
+        );
+        assert_eq!(
+            FieldElement::from_limbs([0; 4], [12, 0, 0, 0]),
+            Err(FieldError::InvalidModulus(BigInt::from(12_u8)))
+        );
+        assert_eq!(
+            FieldElement::from_limbs([0; 4], [1, 0, 0, 0]),
+            Err(FieldError::InvalidModulus(BigInt::from(1_u8)))
+        );
+        assert_eq!(
+            FieldElement::from_limbs(prime, prime),
+            Err(FieldError::OutOfRange {
+                num: big_prime.clone(),
+                prime: big_prime
+            })
+        );
+    }
+
+    #[test]
+    fn test_field_hex() {
+        let prime = (BigInt::from(1_u8) << 256_u32) - BigInt::from(0x1000003d1_u64);
+        let a = FieldElement::from(BigInt::from(0xabc_u32), prime.clone()).unwrap();
+        assert_eq!(a.to_bytes().len(), 32);
+        assert_eq!(format!("{:x}", a), format!("{:064x}", 0xabc));
+        assert_eq!(format!("{:#X}", a), format!("{:#066X}", 0xabc));
+        assert_eq!(FieldElement::from_hex(&a.to_hex(), prime.clone()), Ok(a.clone()));
+        assert_eq!(FieldElement::from_hex("0abc", prime.clone()), Ok(a));
+        assert_eq!(
+            FieldElement::from_hex("0xabc", prime.clone()),
+            Err(FieldError::InvalidHex)
+        );
+        assert!(matches!(
+            FieldElement::from_hex(&"ff".repeat(32), prime),
+            Err(FieldError::OutOfRange { .. })
+        ));
+
+        let small = FieldElement::from(BigInt::from(10_u8), BigInt::from(223_u8)).unwrap();
+        assert_eq!(format!("{:x}", small), "0a");
+    }
+
+    #[test]
+    fn test_field_div() {
+        let prime = BigInt::from(19_u8);
+        let a = FieldElement::from(BigInt::from(2_u8), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7_u8), prime.clone()).unwrap();
+        let c = FieldElement::from(BigInt::from(3_u8), prime.clone()).unwrap();
+
+        assert_eq!(a / b, c);
+    }
+
+    #[test]
+    fn test_multiplicative_order() {
+        let element = |n: u32, p: u32| FieldElement::from(BigInt::from(n), BigInt::from(p)).unwrap();
+        let orders: Vec<Option<BigInt>> = (0..7).map(|n| element(n, 7).multiplicative_order()).collect();
+        let expected = [None, Some(1), Some(3), Some(6), Some(3), Some(6), Some(2)];
+        assert_eq!(orders, expected.map(|o| o.map(BigInt::from)));
+
+        // Lagrange: every order divides 222, and the cyclic group has
+        // phi(d) elements of each order d
+        let mut counts = std::collections::BTreeMap::new();
+        for n in 1..223 {
+            *counts
+                .entry(element(n, 223).multiplicative_order().unwrap())
+                .or_insert(0) += 1;
+        }
+        let phi = [(1, 1), (2, 1), (3, 2), (6, 2), (37, 36), (74, 36), (111, 72), (222, 72)];
+        assert_eq!(counts, phi.into_iter().map(|(d, n)| (BigInt::from(d), n)).collect());
+
+        // in secp256k1's field 3 generates everything and 2 an index-14 subgroup
+        let p = crate::curves::secp256k1::ecc::prime();
+        let order = |n: u8| {
+            FieldElement::from(BigInt::from(n), p.clone())
+                .unwrap()
+                .multiplicative_order()
+                .unwrap()
+        };
+        assert_eq!(order(3), &p - 1_u8);
+        assert_eq!(order(2), (&p - 1_u8) / 14_u8);
+    }
+}
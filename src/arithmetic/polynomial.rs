@@ -0,0 +1,239 @@
+use std::ops::{Add, Mul, Sub};
+
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+use crate::arithmetic::field_element::{FieldElement, FieldError};
+
+/// A polynomial over the prime field of `prime`, lowest coefficient first
+/// and without trailing zeros, so the zero polynomial has none
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    prime: BigInt,
+    coeffs: Vec<FieldElement>,
+}
+
+impl Polynomial {
+    /// Rejects coefficients from other fields
+    pub fn new(prime: BigInt, coeffs: Vec<FieldElement>) -> Result<Self, FieldError> {
+        if coeffs.iter().any(|c| c.prime() != prime) {
+            return Err(FieldError::FieldMismatch);
+        }
+        Ok(Self::trimmed(prime, coeffs))
+    }
+
+    /// Drops the trailing zeros of coefficients already in the field
+    fn trimmed(prime: BigInt, mut coeffs: Vec<FieldElement>) -> Self {
+        while coeffs.last().is_some_and(|c| c.num().is_zero()) {
+            coeffs.pop();
+        }
+        Self { prime, coeffs }
+    }
+
+    /// Reduces each integer into the field
+    pub fn from_ints(prime: BigInt, coeffs: &[i64]) -> Self {
+        let coeffs = coeffs.iter().map(|&c| element(&prime, BigInt::from(c))).collect();
+        Self::trimmed(prime, coeffs)
+    }
+
+    pub fn zero(prime: BigInt) -> Self {
+        Self::trimmed(prime, Vec::new())
+    }
+
+    pub fn prime(&self) -> &BigInt {
+        &self.prime
+    }
+
+    pub fn coeffs(&self) -> &[FieldElement] {
+        &self.coeffs
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    /// None for the zero polynomial
+    pub fn degree(&self) -> Option<usize> {
+        self.coeffs.len().checked_sub(1)
+    }
+
+    /// Horner's rule
+    pub fn evaluate(&self, x: &FieldElement) -> FieldElement {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(element(&self.prime, BigInt::zero()), |acc, c| {
+                acc * x.clone() + c.clone()
+            })
+    }
+
+    pub fn scale(&self, k: &FieldElement) -> Self {
+        Self::trimmed(
+            self.prime.clone(),
+            self.coeffs.iter().map(|c| c.clone() * k.clone()).collect(),
+        )
+    }
+
+    /// Long division, panicking on a zero divisor
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        let divisor_degree = divisor.degree().expect("division by the zero polynomial");
+        let lead_inv = divisor.coeffs[divisor_degree].clone().pow(BigInt::from(-1));
+        let mut rem = self.coeffs.clone();
+        let quotient_len = (self.coeffs.len() + 1).saturating_sub(divisor.coeffs.len());
+        let mut quotient = vec![element(&self.prime, BigInt::zero()); quotient_len];
+        for i in (0..quotient_len).rev() {
+            let q = rem[i + divisor_degree].clone() * lead_inv.clone();
+            for (j, d) in divisor.coeffs.iter().enumerate() {
+                rem[i + j] = rem[i + j].clone() - q.clone() * d.clone();
+            }
+            quotient[i] = q;
+        }
+        rem.truncate(divisor_degree);
+        (
+            Self::trimmed(self.prime.clone(), quotient),
+            Self::trimmed(self.prime.clone(), rem),
+        )
+    }
+
+    /// Π (X - r) over the roots
+    pub fn vanishing(prime: BigInt, roots: &[FieldElement]) -> Self {
+        let one = Self::trimmed(prime.clone(), vec![element(&prime, BigInt::from(1))]);
+        roots.iter().fold(one, |acc, r| {
+            acc * Self::trimmed(
+                prime.clone(),
+                vec![
+                    element(&prime, BigInt::zero()) - r.clone(),
+                    element(&prime, BigInt::from(1)),
+                ],
+            )
+        })
+    }
+
+    /// The Lagrange interpolation of the lowest degree through the points,
+    /// or None if two share an x coordinate
+    pub fn interpolate(prime: BigInt, points: &[(FieldElement, FieldElement)]) -> Option<Self> {
+        let mut res = Self::zero(prime.clone());
+        for (i, (xi, yi)) in points.iter().enumerate() {
+            let others: Vec<FieldElement> = points
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, (xj, _))| xj.clone())
+                .collect();
+            if others.contains(xi) {
+                return None;
+            }
+            let basis = Self::vanishing(prime.clone(), &others);
+            let denominator = basis.evaluate(xi);
+            res = res + basis.scale(&(yi.clone() / denominator));
+        }
+        Some(res)
+    }
+}
+
+fn element(prime: &BigInt, num: BigInt) -> FieldElement {
+    FieldElement::reduce(num, prime.clone()).expect("prime is at least 2")
+}
+
+impl Add for Polynomial {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let (long, short) = if self.coeffs.len() >= rhs.coeffs.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+        let mut coeffs = long.coeffs;
+        for (i, c) in short.coeffs.into_iter().enumerate() {
+            coeffs[i] = coeffs[i].clone() + c;
+        }
+        Self::trimmed(long.prime, coeffs)
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let minus_one = element(&rhs.prime, BigInt::from(-1));
+        self + rhs.scale(&minus_one)
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        if self.is_zero() || rhs.is_zero() {
+            return Self::zero(self.prime);
+        }
+        let mut coeffs = vec![element(&self.prime, BigInt::zero()); self.coeffs.len() + rhs.coeffs.len() - 1];
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in rhs.coeffs.iter().enumerate() {
+                coeffs[i + j] = coeffs[i + j].clone() + a.clone() * b.clone();
+            }
+        }
+        Self::trimmed(self.prime, coeffs)
+    }
+}
+
+#[cfg(test)]
+mod polynomial_tests {
+    use super::*;
+
+    fn prime() -> BigInt {
+        BigInt::from(97)
+    }
+
+    fn fe(n: i64) -> FieldElement {
+        element(&prime(), BigInt::from(n))
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let f = Polynomial::from_ints(prime(), &[1, 2, 3]);
+        let g = Polynomial::from_ints(prime(), &[-1, 1]);
+        assert_eq!(f.degree(), Some(2));
+        assert_eq!(Polynomial::from_ints(prime(), &[4, 0, 0]).degree(), Some(0));
+        assert_eq!(Polynomial::zero(prime()).degree(), None);
+        // 1 + 2x + 3x² at 5
+        assert_eq!(f.evaluate(&fe(5)), fe(86));
+        assert_eq!(f.clone() + g.clone(), Polynomial::from_ints(prime(), &[0, 3, 3]));
+        assert_eq!(f.clone() - f.clone(), Polynomial::zero(prime()));
+        assert_eq!(f.clone() * g.clone(), Polynomial::from_ints(prime(), &[-1, -1, -1, 3]));
+
+        let (q, r) = (f.clone() * g.clone() + Polynomial::from_ints(prime(), &[7])).div_rem(&g);
+        assert_eq!((q, r), (f.clone(), Polynomial::from_ints(prime(), &[7])));
+        let (q, r) = g.div_rem(&f);
+        assert_eq!((q, r), (Polynomial::zero(prime()), g));
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(
+            Polynomial::new(prime(), vec![fe(1), fe(2), fe(0)]),
+            Ok(Polynomial::from_ints(prime(), &[1, 2]))
+        );
+        let other = element(&BigInt::from(101), BigInt::from(2));
+        assert_eq!(
+            Polynomial::new(prime(), vec![fe(1), other]),
+            Err(FieldError::FieldMismatch)
+        );
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let f = Polynomial::from_ints(prime(), &[5, -3, 0, 2]);
+        let points: Vec<_> = [0, 1, 2, 40].iter().map(|&x| (fe(x), f.evaluate(&fe(x)))).collect();
+        assert_eq!(Polynomial::interpolate(prime(), &points), Some(f));
+        assert_eq!(
+            Polynomial::interpolate(prime(), &[(fe(1), fe(2)), (fe(1), fe(3))]),
+            None
+        );
+
+        let z = Polynomial::vanishing(prime(), &[fe(3), fe(4)]);
+        assert_eq!(z, Polynomial::from_ints(prime(), &[12, -7, 1]));
+        assert_eq!(z.evaluate(&fe(4)), fe(0));
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use crate::block::Block;
+use crate::crypto::hash::{hash256, siphash24};
+use crate::encoding::compact_size;
+use crate::script::Script;
+use crate::tx::OutPoint;
+
+/// Golomb-Rice parameter of basic filters: remainders take P bits
+pub const P: u8 = 19;
+/// Inverse false positive rate of basic filters, 1/M per query element
+pub const M: u64 = 784931;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterError {
+    /// The filter ended before all its elements were decoded
+    Truncated,
+    /// The script of an outpoint spent in the block wasn't provided
+    MissingUtxo(OutPoint),
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterError::Truncated => write!(f, "compact filter is truncated"),
+            FilterError::MissingUtxo(outpoint) => {
+                let mut txid = outpoint.txid;
+                txid.reverse();
+                write!(f, "no script for spent output {}:{}", hex::encode(txid), outpoint.vout)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// Bits are packed most significant first
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    fn write_bit(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 0x80 >> (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    fn write_bits(&mut self, value: u64, count: u8) {
+        for i in (0..count).rev() {
+            self.write_bit(value >> i & 1 == 1);
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn read_bit(&mut self) -> Result<bool, FilterError> {
+        let byte = self.bytes.get(self.pos / 8).ok_or(FilterError::Truncated)?;
+        let bit = byte & (0x80 >> (self.pos % 8)) != 0;
+        self.pos += 1;
+        Ok(bit)
+    }
+
+    fn read_bits(&mut self, count: u8) -> Result<u64, FilterError> {
+        (0..count).try_fold(0, |value, _| Ok((value << 1) | self.read_bit()? as u64))
+    }
+}
+
+/// The quotient n >> P in unary, then the remainder in P bits
+fn golomb_encode(writer: &mut BitWriter, n: u64) {
+    for _ in 0..n >> P {
+        writer.write_bit(true);
+    }
+    writer.write_bit(false);
+    writer.write_bits(n, P);
+}
+
+fn golomb_decode(reader: &mut BitReader) -> Result<u64, FilterError> {
+    let mut quotient = 0;
+    while reader.read_bit()? {
+        quotient += 1;
+    }
+    Ok((quotient << P) | reader.read_bits(P)?)
+}
+
+/// SipHash keys: the first 16 bytes of the block hash in internal order
+fn keys(block_hash: &[u8; 32]) -> (u64, u64) {
+    (
+        u64::from_le_bytes(block_hash[..8].try_into().unwrap()),
+        u64::from_le_bytes(block_hash[8..16].try_into().unwrap()),
+    )
+}
+
+/// Maps an element uniformly onto [0, range) without a division
+fn hash_to_range(keys: (u64, u64), element: &[u8], range: u64) -> u64 {
+    ((siphash24(keys.0, keys.1, element) as u128 * range as u128) >> 64) as u64
+}
+
+/// A BIP-158 compact block filter: a Golomb-coded set of the block's
+/// elements hashed to [0, N * M), sent sorted as Golomb-Rice coded deltas.
+/// Light clients download these and test their own scripts against them
+/// locally, so unlike BIP-37 the node learns nothing about the wallet
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockFilter {
+    /// Element count as a compact size, then the bit stream
+    pub content: Vec<u8>,
+}
+
+impl BlockFilter {
+    pub fn new(content: Vec<u8>) -> Self {
+        Self { content }
+    }
+
+    /// Builds a filter over the distinct non-empty elements
+    pub fn from_elements<'a, I>(block_hash: &[u8; 32], elements: I) -> Self
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let elements: BTreeSet<&[u8]> = elements.into_iter().filter(|e| !e.is_empty()).collect();
+        let range = elements.len() as u64 * M;
+        let mut values: Vec<u64> = elements
+            .iter()
+            .map(|e| hash_to_range(keys(block_hash), e, range))
+            .collect();
+        values.sort_unstable();
+
+        let mut content = Vec::new();
+        compact_size::write(&mut content, values.len() as u64).unwrap();
+        let mut writer = BitWriter::default();
+        let mut last = 0;
+        for value in values {
+            golomb_encode(&mut writer, value - last);
+            last = value;
+        }
+        content.extend(writer.bytes);
+        Self { content }
+    }
+
+    /// The basic filter: every output script of the block except OP_RETURN
+    /// ones, and the script of every output it spends, looked up through
+    /// `prevout_script` as blocks don't carry them
+    pub fn basic<F>(block: &Block, mut prevout_script: F) -> Result<Self, FilterError>
+    where
+        F: FnMut(&OutPoint) -> Option<Script>,
+    {
+        let mut scripts: Vec<Script> = block
+            .txdata
+            .iter()
+            .flat_map(|tx| &tx.outputs)
+            .filter(|output| !output.script_pubkey.is_op_return())
+            .map(|output| output.script_pubkey.clone())
+            .collect();
+        for input in block.txdata.iter().skip(1).flat_map(|tx| &tx.inputs) {
+            let outpoint = &input.previous_output;
+            scripts.push(prevout_script(outpoint).ok_or(FilterError::MissingUtxo(*outpoint))?);
+        }
+        Ok(Self::from_elements(&block.hash(), scripts.iter().map(Script::as_bytes)))
+    }
+
+    /// The sorted set of hashed elements and the range they were hashed to
+    fn decode(&self) -> Result<(Vec<u64>, u64), FilterError> {
+        let mut reader = &self.content[..];
+        let count = compact_size::read(&mut reader).map_err(|_| FilterError::Truncated)?;
+        let mut bits = BitReader { bytes: reader, pos: 0 };
+        let mut values = Vec::new();
+        let mut last = 0_u64;
+        for _ in 0..count {
+            last = last.wrapping_add(golomb_decode(&mut bits)?);
+            values.push(last);
+        }
+        Ok((values, count.saturating_mul(M)))
+    }
+
+    fn hashed_queries(block_hash: &[u8; 32], queries: &[&[u8]], range: u64) -> Vec<u64> {
+        let mut res: Vec<u64> = queries
+            .iter()
+            .map(|q| hash_to_range(keys(block_hash), q, range))
+            .collect();
+        res.sort_unstable();
+        res
+    }
+
+    /// Whether any query may be in the block, with false positives at a
+    /// rate of 1/M per query
+    pub fn match_any(&self, block_hash: &[u8; 32], queries: &[&[u8]]) -> Result<bool, FilterError> {
+        let (values, range) = self.decode()?;
+        let queries = Self::hashed_queries(block_hash, queries, range);
+        // both sides are sorted, so one merge pass finds any overlap
+        let (mut i, mut j) = (0, 0);
+        while i < values.len() && j < queries.len() {
+            match values[i].cmp(&queries[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => return Ok(true),
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether every query may be in the block
+    pub fn match_all(&self, block_hash: &[u8; 32], queries: &[&[u8]]) -> Result<bool, FilterError> {
+        let (values, range) = self.decode()?;
+        let queries = Self::hashed_queries(block_hash, queries, range);
+        Ok(queries.iter().all(|q| values.binary_search(q).is_ok()))
+    }
+
+    pub fn filter_hash(&self) -> [u8; 32] {
+        hash256(&self.content)
+    }
+
+    /// The header committing to this filter and, through `prev_header`, to
+    /// every filter before it, so filters from one peer can be checked
+    /// against headers agreed on by several
+    pub fn filter_header(&self, prev_header: &[u8; 32]) -> [u8; 32] {
+        let mut data = [0_u8; 64];
+        data[..32].copy_from_slice(&self.filter_hash());
+        data[32..].copy_from_slice(prev_header);
+        hash256(&data)
+    }
+}
+
+/// Filter headers of consecutive blocks from the genesis block, whose
+/// previous filter header is all zeros
+pub fn filter_header_chain<'a, I>(filters: I) -> Vec<[u8; 32]>
+where
+    I: IntoIterator<Item = &'a BlockFilter>,
+{
+    let mut prev = [0_u8; 32];
+    filters
+        .into_iter()
+        .map(|filter| {
+            prev = filter.filter_header(&prev);
+            prev
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod bip158_tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const TESTNET_GENESIS: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b2\
+        7ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff001d1aa4ae1801010000000100000000000000000000000000000000\
+        00000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c\
+        6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a0100000043410467\
+        8afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c\
+        702b6bf11d5fac00000000";
+
+    /// A block after it spending three outputs, with a repeated script, an
+    /// OP_RETURN, an empty output script and an empty spent script
+    const BLOCK: &str = "0000002043497fd7f826957108f4a30fd9cec3aeba79972084e90ead01ea3309000000005fdef9f292db5ab1ab\
+        0dbf855b77f43f9c055cfde8f194c76b01f156ccbd9114dae5494dffff7f20000000000301000000010000000000000000000000000000000000\
+        000000000000000000000000000000ffffffff020101ffffffff02e803000000000000160014751e76e8199196d454941c45d1b3a323f1433bd6\
+        e803000000000000266a24aa21a9ed00000000000000000000000000000000000000000000000000000000000000000000000002000000028190\
+        a1b2c3d4e5f6a7b8c9d0f1e2b4a6a3c7e5d7e0b5c5a1a6e4e7e1d5b6c9d60000000000ffffffff8190a1b2c3d4e5f6a7b8c9d0f1e2b4a6a3c7e5\
+        d7e0b5c5a1a6e4e7e1d5b6c9d60100000000ffffffff03e8030000000000001976a914751e76e8199196d454941c45d1b3a323f1433bd688ace8\
+        03000000000000225120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684ce8030000000000000000000000020000\
+        000111111111111111111111111111111111111111111111111111111111111111110700000000ffffffff01e80300000000000016001475\
+        1e76e8199196d454941c45d1b3a323f1433bd600000000";
+
+    fn script(s: &str) -> Script {
+        Script::from_hex(s).unwrap()
+    }
+
+    #[test]
+    fn test_basic_filters() {
+        // filters and headers from rust-bitcoin; the genesis one is also
+        // BIP-158's first test vector
+        let genesis = Block::from_hex(TESTNET_GENESIS).unwrap();
+        let genesis_filter = BlockFilter::basic(&genesis, |_| None).unwrap();
+        assert_eq!(hex::encode(&genesis_filter.content), "019dfca8");
+
+        let block = Block::from_hex(BLOCK).unwrap();
+        let spent = &block.txdata[1].inputs;
+        let prevouts: HashMap<OutPoint, Script> = [
+            (
+                spent[0].previous_output,
+                script("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"),
+            ),
+            (
+                spent[1].previous_output,
+                script("0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+            ),
+            (block.txdata[2].inputs[0].previous_output, Script::new()),
+        ]
+        .into();
+        let filter = BlockFilter::basic(&block, |outpoint| prevouts.get(outpoint).cloned()).unwrap();
+        assert_eq!(hex::encode(&filter.content), "043e29ed23ca59489c5e5880");
+
+        // filter headers are displayed byte-reversed, like block hashes
+        let headers: Vec<_> = filter_header_chain([&genesis_filter, &filter])
+            .into_iter()
+            .map(|mut header| {
+                header.reverse();
+                hex::encode(header)
+            })
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                "21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750",
+                "29cbc362f7ac555cd5b3e8d3a32fa02c7063535289e450494888a9dfd434fbec",
+            ]
+        );
+
+        let missing = BlockFilter::basic(&block, |_| None);
+        assert_eq!(missing, Err(FilterError::MissingUtxo(spent[0].previous_output)));
+    }
+
+    #[test]
+    fn test_match() {
+        let block = Block::from_hex(BLOCK).unwrap();
+        let filter = BlockFilter::new(hex::decode("043e29ed23ca59489c5e5880").unwrap());
+        let hash = block.hash();
+        let p2wpkh = script("0014751e76e8199196d454941c45d1b3a323f1433bd6");
+        let p2sh = script("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87");
+        let op_return = script("6a24aa21a9ed0000000000000000000000000000000000000000000000000000000000000000");
+        let unrelated = script("76a914000000000000000000000000000000000000000088ac");
+
+        assert!(filter.match_any(&hash, &[p2wpkh.as_bytes()]).unwrap());
+        assert!(filter
+            .match_any(&hash, &[unrelated.as_bytes(), p2sh.as_bytes()])
+            .unwrap());
+        assert!(!filter
+            .match_any(&hash, &[op_return.as_bytes(), unrelated.as_bytes()])
+            .unwrap());
+        assert!(filter.match_all(&hash, &[p2wpkh.as_bytes(), p2sh.as_bytes()]).unwrap());
+        assert!(!filter
+            .match_all(&hash, &[p2wpkh.as_bytes(), unrelated.as_bytes()])
+            .unwrap());
+        // the keys come from the block hash, so another block's filter misses
+        assert!(!filter.match_any(&[0; 32], &[p2wpkh.as_bytes()]).unwrap());
+
+        let empty = BlockFilter::from_elements(&hash, []);
+        assert_eq!(empty.content, [0]);
+        assert!(!empty.match_any(&hash, &[p2wpkh.as_bytes()]).unwrap());
+
+        let truncated = BlockFilter::new(hex::decode("043e29ed23").unwrap());
+        assert_eq!(
+            truncated.match_any(&hash, &[p2wpkh.as_bytes()]),
+            Err(FilterError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::fmt::Display;
+
+use crate::address::{self, Network};
+use crate::script::Script;
+
+const SCHEME: &str = "bitcoin:";
+const SATS_PER_BTC: u64 = 100_000_000;
+const MAX_MONEY: u64 = 21_000_000 * SATS_PER_BTC;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bip21Error {
+    /// Doesn't start with `bitcoin:`
+    InvalidScheme,
+    /// Not an address on any network
+    InvalidAddress(String),
+    /// Not a decimal BTC amount with at most 8 decimals, or above 21M
+    InvalidAmount(String),
+    /// A `%` not followed by two hex digits, or escapes that aren't UTF-8
+    InvalidPercentEncoding,
+    /// A `req-` parameter we don't understand, which the URI says we must
+    UnknownRequired(String),
+    DuplicateParameter(String),
+}
+
+impl Display for Bip21Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bip21Error::InvalidScheme => write!(f, "URI scheme is not bitcoin:"),
+            Bip21Error::InvalidAddress(address) => write!(f, "invalid address {}", address),
+            Bip21Error::InvalidAmount(amount) => write!(f, "invalid amount {}", amount),
+            Bip21Error::InvalidPercentEncoding => write!(f, "invalid percent encoding"),
+            Bip21Error::UnknownRequired(key) => write!(f, "unknown required parameter {}", key),
+            Bip21Error::DuplicateParameter(key) => write!(f, "duplicate parameter {}", key),
+        }
+    }
+}
+
+impl std::error::Error for Bip21Error {}
+
+/// A BIP-21 `bitcoin:` payment request
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentUri {
+    pub address: String,
+    /// In satoshis
+    pub amount: Option<u64>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    /// Other parameters, in order. Parsing rejects unknown `req-` ones
+    pub extras: Vec<(String, String)>,
+}
+
+impl PaymentUri {
+    pub fn new(address: &str) -> Result<Self, Bip21Error> {
+        check_address(address)?;
+        Ok(Self {
+            address: address.to_string(),
+            amount: None,
+            label: None,
+            message: None,
+            extras: Vec::new(),
+        })
+    }
+
+    pub fn amount(mut self, amount: u64) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    /// Any other parameter; a `req-` prefix tells payers to refuse the
+    /// request unless they understand it
+    pub fn param(mut self, key: &str, value: &str) -> Self {
+        self.extras.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn parse(s: &str) -> Result<Self, Bip21Error> {
+        let rest = match s.get(..SCHEME.len()) {
+            Some(scheme) if scheme.eq_ignore_ascii_case(SCHEME) => &s[SCHEME.len()..],
+            _ => return Err(Bip21Error::InvalidScheme),
+        };
+        let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut uri = Self::new(address)?;
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let (key, value) = (percent_decode(key)?, percent_decode(value)?);
+            let duplicate = || Bip21Error::DuplicateParameter(key.clone());
+            match key.as_str() {
+                "amount" if uri.amount.is_some() => return Err(duplicate()),
+                "amount" => uri.amount = Some(parse_amount(&value)?),
+                "label" if uri.label.is_some() => return Err(duplicate()),
+                "label" => uri.label = Some(value),
+                "message" if uri.message.is_some() => return Err(duplicate()),
+                "message" => uri.message = Some(value),
+                _ if key.starts_with("req-") => return Err(Bip21Error::UnknownRequired(key)),
+                _ => uri.extras.push((key, value)),
+            }
+        }
+        Ok(uri)
+    }
+
+    /// The output script to pay, if the address is for `network`
+    pub fn script_pubkey(&self, network: Network) -> Option<Script> {
+        address::to_script_pubkey(&self.address, network)
+    }
+}
+
+impl Display for PaymentUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", SCHEME, self.address)?;
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            params.push(format!("amount={}", format_amount(amount)));
+        }
+        if let Some(label) = &self.label {
+            params.push(format!("label={}", percent_encode(label)));
+        }
+        if let Some(message) = &self.message {
+            params.push(format!("message={}", percent_encode(message)));
+        }
+        for (key, value) in &self.extras {
+            params.push(format!("{}={}", percent_encode(key), percent_encode(value)));
+        }
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+fn check_address(address: &str) -> Result<(), Bip21Error> {
+    let networks = [Network::Mainnet, Network::Testnet, Network::Regtest];
+    if !networks
+        .iter()
+        .any(|&network| address::to_script_pubkey(address, network).is_some())
+    {
+        return Err(Bip21Error::InvalidAddress(address.to_string()));
+    }
+    Ok(())
+}
+
+/// Decimal BTC, e.g. "20.3", to satoshis. No signs, exponents or
+/// separators
+fn parse_amount(s: &str) -> Result<u64, Bip21Error> {
+    let invalid = || Bip21Error::InvalidAmount(s.to_string());
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) || fraction.len() > 8 {
+        return Err(invalid());
+    }
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let fraction: u64 = format!("{:0<8}", fraction).parse().map_err(|_| invalid())?;
+    whole
+        .checked_mul(SATS_PER_BTC)
+        .and_then(|sats| sats.checked_add(fraction))
+        .filter(|&sats| sats <= MAX_MONEY)
+        .ok_or_else(invalid)
+}
+
+/// Satoshis as BTC without trailing zeros
+fn format_amount(sats: u64) -> String {
+    let fraction = format!("{:08}", sats % SATS_PER_BTC);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (sats / SATS_PER_BTC).to_string()
+    } else {
+        format!("{}.{}", sats / SATS_PER_BTC, fraction)
+    }
+}
+
+/// RFC 3986: everything but unreserved characters as %XX of its UTF-8 bytes
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> Result<String, Bip21Error> {
+    let bytes = s.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .ok_or(Bip21Error::InvalidPercentEncoding)?;
+            res.push(u8::from_str_radix(hex, 16).unwrap());
+            i += 3;
+        } else {
+            res.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(res).map_err(|_| Bip21Error::InvalidPercentEncoding)
+}
+
+#[cfg(test)]
+mod bip21_tests {
+    use super::*;
+
+    // the BIP's own example address fails its checksum, so its examples are
+    // rewritten around this one
+    const ADDRESS: &str = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+
+    #[test]
+    fn test_parse_bip21_examples() {
+        let uri = PaymentUri::parse("bitcoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").unwrap();
+        assert_eq!(uri, PaymentUri::new(ADDRESS).unwrap());
+
+        let uri = PaymentUri::parse("bitcoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH?amount=20.3&label=Luke-Jr").unwrap();
+        assert_eq!(uri.amount, Some(2_030_000_000));
+        assert_eq!(uri.label.as_deref(), Some("Luke-Jr"));
+
+        let uri = PaymentUri::parse(
+            "bitcoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH?amount=50&label=Luke-Jr&message=Donation%20for%20project%20xyz",
+        )
+        .unwrap();
+        assert_eq!(uri.amount, Some(50 * SATS_PER_BTC));
+        assert_eq!(uri.message.as_deref(), Some("Donation for project xyz"));
+
+        // unknown parameters are kept, unless they're required
+        let uri = PaymentUri::parse(
+            "bitcoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH?somethingyoudontunderstand=50&somethingelseyoudontget=999",
+        )
+        .unwrap();
+        assert_eq!(
+            uri.extras,
+            vec![
+                ("somethingyoudontunderstand".to_string(), "50".to_string()),
+                ("somethingelseyoudontget".to_string(), "999".to_string()),
+            ]
+        );
+        assert_eq!(
+            PaymentUri::parse(
+                "bitcoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH?req-somethingyoudontunderstand=50&req-somethingelseyoudontget=999"
+            ),
+            Err(Bip21Error::UnknownRequired("req-somethingyoudontunderstand".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            PaymentUri::parse("litecoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"),
+            Err(Bip21Error::InvalidScheme)
+        );
+        assert!(PaymentUri::parse("BITCOIN:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").is_ok());
+        assert_eq!(
+            PaymentUri::parse("bitcoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMJ"),
+            Err(Bip21Error::InvalidAddress(
+                "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMJ".to_string()
+            ))
+        );
+        for amount in ["", ".", "1,5", "-1", "1e3", "0.000000001", "21000000.00000001"] {
+            let uri = format!("bitcoin:{}?amount={}", ADDRESS, amount);
+            assert_eq!(
+                PaymentUri::parse(&uri),
+                Err(Bip21Error::InvalidAmount(amount.to_string())),
+                "{}",
+                amount
+            );
+        }
+        let uri = format!("bitcoin:{}?label=a&label=b", ADDRESS);
+        assert_eq!(
+            PaymentUri::parse(&uri),
+            Err(Bip21Error::DuplicateParameter("label".to_string()))
+        );
+        let uri = format!("bitcoin:{}?message=100%", ADDRESS);
+        assert_eq!(PaymentUri::parse(&uri), Err(Bip21Error::InvalidPercentEncoding));
+    }
+
+    #[test]
+    fn test_build() {
+        let uri = PaymentUri::new(ADDRESS)
+            .unwrap()
+            .amount(150_000)
+            .label("Café & Co")
+            .message("order #42")
+            .param("req-pj", "https://example.com/pj");
+        let s = uri.to_string();
+        assert_eq!(
+            s,
+            "bitcoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH?amount=0.0015&label=Caf%C3%A9%20%26%20Co\
+             &message=order%20%2342&req-pj=https%3A%2F%2Fexample.com%2Fpj"
+        );
+        // we can write required parameters, but not read ones we don't know
+        assert_eq!(
+            PaymentUri::parse(&s),
+            Err(Bip21Error::UnknownRequired("req-pj".to_string()))
+        );
+        let uri = PaymentUri::new(ADDRESS)
+            .unwrap()
+            .amount(20 * SATS_PER_BTC)
+            .label("Luke-Jr");
+        assert_eq!(PaymentUri::parse(&uri.to_string()), Ok(uri));
+
+        let uri = PaymentUri::new("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        assert_eq!(
+            uri.script_pubkey(Network::Mainnet),
+            Some(Script::p2wpkh(&[
+                0x75, 0x1e, 0x76, 0xe8, 0x19, 0x91, 0x96, 0xd4, 0x54, 0x94, 0x1c, 0x45, 0xd1, 0xb3, 0xa3, 0x23, 0xf1,
+                0x43, 0x3b, 0xd6,
+            ]))
+        );
+        assert_eq!(uri.script_pubkey(Network::Testnet), None);
+    }
+}
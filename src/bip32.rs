@@ -0,0 +1,418 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+
+use crate::address::Network;
+use crate::crypto::hash::{hash160, hmac_sha512};
+use crate::curves::secp256k1::ecc::{KeyError, PrivateKey, PublicKey};
+use crate::encoding::base58::{self, Base58Error};
+#[cfg(feature = "serde")]
+use crate::encoding::serde_codec::impl_serde_string;
+use crate::trace::trace_span;
+
+/// Child indices at or above this value use hardened derivation
+pub const HARDENED: u32 = 0x8000_0000;
+
+pub const XPRV: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+pub const XPUB: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+pub const TPRV: [u8; 4] = [0x04, 0x35, 0x83, 0x94];
+pub const TPUB: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+// SLIP-132 prefixes for native segwit account keys
+pub const ZPRV: [u8; 4] = [0x04, 0xb2, 0x43, 0x0c];
+pub const ZPUB: [u8; 4] = [0x04, 0xb2, 0x47, 0x46];
+pub const VPRV: [u8; 4] = [0x04, 0x5f, 0x18, 0xbc];
+pub const VPUB: [u8; 4] = [0x04, 0x5f, 0x1c, 0xf6];
+
+// private/public version pairs, including the SLIP-132 y/z/u/v prefixes
+const VERSION_PAIRS: [([u8; 4], [u8; 4]); 6] = [
+    (XPRV, XPUB),
+    (TPRV, TPUB),
+    ([0x04, 0x9d, 0x78, 0x78], [0x04, 0x9d, 0x7c, 0xb2]),
+    (ZPRV, ZPUB),
+    ([0x04, 0x4a, 0x4e, 0x28], [0x04, 0x4a, 0x52, 0x62]),
+    (VPRV, VPUB),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bip32Error {
+    Base58(Base58Error),
+    InvalidLength(usize),
+    InvalidKey(KeyError),
+    /// Version bytes that don't belong to any known private key prefix
+    UnknownVersion([u8; 4]),
+    /// Hardened children cannot be derived from a public key
+    HardenedDerivation(u32),
+    /// The derived tweak is >= n or the child key is the point at infinity,
+    /// callers should skip to the next index
+    InvalidChild(u32),
+    /// The depth byte stops at 255, so a key that deep has no children
+    MaxDepth,
+}
+
+impl Display for Bip32Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bip32Error::Base58(e) => write!(f, "{}", e),
+            Bip32Error::InvalidLength(l) => write!(f, "extended key must be 78 bytes, got {}", l),
+            Bip32Error::InvalidKey(e) => write!(f, "{}", e),
+            Bip32Error::UnknownVersion(v) => write!(f, "unknown extended key version {:02x?}", v),
+            Bip32Error::HardenedDerivation(i) => write!(f, "cannot derive hardened child {} from a public key", i),
+            Bip32Error::InvalidChild(i) => write!(f, "child {} is invalid", i),
+            Bip32Error::MaxDepth => write!(f, "cannot derive below depth 255"),
+        }
+    }
+}
+
+impl std::error::Error for Bip32Error {}
+
+impl From<Base58Error> for Bip32Error {
+    fn from(e: Base58Error) -> Self {
+        Bip32Error::Base58(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedPublicKey {
+    /// Version bytes are kept verbatim so xpub/tpub/zpub-style prefixes round-trip
+    pub version: [u8; 4],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub public_key: PublicKey,
+}
+
+impl ExtendedPublicKey {
+    pub fn parse(s: &str) -> Result<Self, Bip32Error> {
+        trace_span!("bip32.parse", len = s.len());
+        let data = base58::decode_check(s)?;
+        if data.len() != 78 {
+            return Err(Bip32Error::InvalidLength(data.len()));
+        }
+        let public_key = PublicKey::parse(&data[45..78]).map_err(Bip32Error::InvalidKey)?;
+        Ok(Self {
+            version: data[0..4].try_into().unwrap(),
+            depth: data[4],
+            parent_fingerprint: data[5..9].try_into().unwrap(),
+            child_number: u32::from_be_bytes(data[9..13].try_into().unwrap()),
+            chain_code: data[13..45].try_into().unwrap(),
+            public_key,
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(78);
+        res.extend_from_slice(&self.version);
+        res.push(self.depth);
+        res.extend_from_slice(&self.parent_fingerprint);
+        res.extend_from_slice(&self.child_number.to_be_bytes());
+        res.extend_from_slice(&self.chain_code);
+        res.extend_from_slice(&self.public_key.sec(true));
+        res
+    }
+
+    /// First four bytes of the key's hash160, identifying it as a parent
+    pub fn fingerprint(&self) -> [u8; 4] {
+        hash160(&self.public_key.sec(true))[0..4].try_into().unwrap()
+    }
+
+    /// CKDpub: derives the non-hardened child at `index`
+    pub fn derive_child(&self, index: u32) -> Result<Self, Bip32Error> {
+        trace_span!("bip32.derive", index, depth = self.depth, public = true);
+        if index >= HARDENED {
+            return Err(Bip32Error::HardenedDerivation(index));
+        }
+        let depth = self.depth.checked_add(1).ok_or(Bip32Error::MaxDepth)?;
+        let mut data = self.public_key.sec(true);
+        data.extend_from_slice(&index.to_be_bytes());
+        let i = hmac_sha512(&self.chain_code, &data);
+
+        let tweak = BigUint::from_bytes_be(&i[..32]);
+        let public_key = self
+            .public_key
+            .add_tweak(&tweak)
+            .map_err(|_| Bip32Error::InvalidChild(index))?;
+        Ok(Self {
+            version: self.version,
+            depth,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code: i[32..].try_into().unwrap(),
+            public_key,
+        })
+    }
+
+    pub fn derive_path(&self, path: &[u32]) -> Result<Self, Bip32Error> {
+        path.iter()
+            .try_fold(self.clone(), |key, &index| key.derive_child(index))
+    }
+}
+
+impl Display for ExtendedPublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", base58::encode_check(&self.serialize()))
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ExtendedPrivateKey {
+    pub version: [u8; 4],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub private_key: PrivateKey,
+}
+
+/// Leaves out the chain code, which with any child's secret gives away the
+/// parent's, and shows the key through `PrivateKey`'s redacted form
+impl std::fmt::Debug for ExtendedPrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExtendedPrivateKey")
+            .field("version", &self.version)
+            .field("depth", &self.depth)
+            .field("parent_fingerprint", &self.parent_fingerprint)
+            .field("child_number", &self.child_number)
+            .field("private_key", &self.private_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExtendedPrivateKey {
+    /// Master key generation: I = HMAC-SHA512("Bitcoin seed", seed)
+    pub fn from_seed(seed: &[u8], network: Network) -> Result<Self, Bip32Error> {
+        trace_span!("bip32.master", seed_len = seed.len(), network = ?network);
+        let i = hmac_sha512(b"Bitcoin seed", seed);
+        let private_key = PrivateKey::from_bytes(&i[..32]).map_err(Bip32Error::InvalidKey)?;
+        Ok(Self {
+            version: match network {
+                Network::Mainnet => XPRV,
+                Network::Testnet | Network::Signet | Network::Regtest => TPRV,
+            },
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: 0,
+            chain_code: i[32..].try_into().unwrap(),
+            private_key,
+        })
+    }
+
+    pub fn parse(s: &str) -> Result<Self, Bip32Error> {
+        trace_span!("bip32.parse", len = s.len());
+        let data = base58::decode_check(s)?;
+        if data.len() != 78 {
+            return Err(Bip32Error::InvalidLength(data.len()));
+        }
+        if data[45] != 0 {
+            return Err(Bip32Error::InvalidKey(KeyError::InvalidSecret));
+        }
+        let private_key = PrivateKey::from_bytes(&data[46..78]).map_err(Bip32Error::InvalidKey)?;
+        Ok(Self {
+            version: data[0..4].try_into().unwrap(),
+            depth: data[4],
+            parent_fingerprint: data[5..9].try_into().unwrap(),
+            child_number: u32::from_be_bytes(data[9..13].try_into().unwrap()),
+            chain_code: data[13..45].try_into().unwrap(),
+            private_key,
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(78);
+        res.extend_from_slice(&self.version);
+        res.push(self.depth);
+        res.extend_from_slice(&self.parent_fingerprint);
+        res.extend_from_slice(&self.child_number.to_be_bytes());
+        res.extend_from_slice(&self.chain_code);
+        res.push(0);
+        res.extend_from_slice(&self.private_key.to_bytes());
+        res
+    }
+
+    /// Neutered counterpart with the matching public version bytes
+    pub fn to_public(&self) -> Result<ExtendedPublicKey, Bip32Error> {
+        let version = VERSION_PAIRS
+            .iter()
+            .find(|(private, _)| *private == self.version)
+            .map(|(_, public)| *public)
+            .ok_or(Bip32Error::UnknownVersion(self.version))?;
+        Ok(ExtendedPublicKey {
+            version,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+            chain_code: self.chain_code,
+            public_key: self.private_key.public_key(),
+        })
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        hash160(&self.private_key.public_key().sec(true))[0..4]
+            .try_into()
+            .unwrap()
+    }
+
+    /// CKDpriv: derives the child at `index`, hardened when index >= 2^31
+    pub fn derive_child(&self, index: u32) -> Result<Self, Bip32Error> {
+        trace_span!("bip32.derive", index, depth = self.depth, public = false);
+        let depth = self.depth.checked_add(1).ok_or(Bip32Error::MaxDepth)?;
+        let mut data = if index >= HARDENED {
+            let mut data = vec![0];
+            data.extend_from_slice(&self.private_key.to_bytes());
+            data
+        } else {
+            self.private_key.public_key().sec(true)
+        };
+        data.extend_from_slice(&index.to_be_bytes());
+        let i = hmac_sha512(&self.chain_code, &data);
+
+        let tweak = BigUint::from_bytes_be(&i[..32]);
+        let private_key = self
+            .private_key
+            .add_tweak(&tweak)
+            .map_err(|_| Bip32Error::InvalidChild(index))?;
+        Ok(Self {
+            version: self.version,
+            depth,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code: i[32..].try_into().unwrap(),
+            private_key,
+        })
+    }
+
+    pub fn derive_path(&self, path: &[u32]) -> Result<Self, Bip32Error> {
+        path.iter()
+            .try_fold(self.clone(), |key, &index| key.derive_child(index))
+    }
+}
+
+impl Display for ExtendedPrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", base58::encode_check(&self.serialize()))
+    }
+}
+
+// as the base58 strings they're always exchanged as, even in binary formats
+#[cfg(feature = "serde")]
+impl_serde_string!(ExtendedPublicKey, ExtendedPublicKey::parse);
+#[cfg(feature = "serde")]
+impl_serde_string!(ExtendedPrivateKey, ExtendedPrivateKey::parse);
+
+#[cfg(test)]
+mod bip32_tests {
+    use super::*;
+
+    // BIP32 test vector 1: m, m/0H and m/0H/1
+    const SEED: &str = "000102030405060708090a0b0c0d0e0f";
+    const XPRV_M: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+    const XPRV_0H_1_2H: &str = "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM";
+    const XPUB_0H: &str = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+    const XPUB_0H_1: &str = "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ";
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let xpub = ExtendedPublicKey::parse(XPUB_0H).unwrap();
+
+        assert_eq!(xpub.depth, 1);
+        assert_eq!(xpub.child_number, HARDENED);
+        assert_eq!(xpub.to_string(), XPUB_0H);
+    }
+
+    #[test]
+    fn test_derive_child() {
+        let xpub = ExtendedPublicKey::parse(XPUB_0H).unwrap();
+        let child = xpub.derive_child(1).unwrap();
+
+        assert_eq!(child.to_string(), XPUB_0H_1);
+        assert_eq!(xpub.derive_path(&[1]).unwrap(), child);
+    }
+
+    #[test]
+    fn test_derive_hardened() {
+        let xpub = ExtendedPublicKey::parse(XPUB_0H).unwrap();
+
+        assert_eq!(
+            xpub.derive_child(HARDENED + 1),
+            Err(Bip32Error::HardenedDerivation(HARDENED + 1))
+        );
+    }
+
+    #[test]
+    fn test_derive_max_depth() {
+        let mut xpub = ExtendedPublicKey::parse(XPUB_0H).unwrap();
+        xpub.depth = 254;
+        let deepest = xpub.derive_child(1).unwrap();
+
+        assert_eq!(deepest.depth, 255);
+        assert_eq!(deepest.derive_child(1), Err(Bip32Error::MaxDepth));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let mut data = base58::decode_check(XPUB_0H).unwrap();
+        data.pop();
+
+        assert_eq!(
+            ExtendedPublicKey::parse(&base58::encode_check(&data)),
+            Err(Bip32Error::InvalidLength(77))
+        );
+    }
+
+    #[test]
+    fn test_master_from_seed() {
+        let master = ExtendedPrivateKey::from_seed(&hex::decode(SEED).unwrap(), Network::Mainnet).unwrap();
+
+        assert_eq!(master.to_string(), XPRV_M);
+        assert_eq!(ExtendedPrivateKey::parse(XPRV_M).unwrap(), master);
+    }
+
+    #[test]
+    fn test_derive_private() {
+        let master = ExtendedPrivateKey::parse(XPRV_M).unwrap();
+        let child = master.derive_path(&[HARDENED, 1, HARDENED + 2]).unwrap();
+
+        assert_eq!(child.to_string(), XPRV_0H_1_2H);
+    }
+
+    #[test]
+    fn test_derive_private_max_depth() {
+        let mut master = ExtendedPrivateKey::parse(XPRV_M).unwrap();
+        master.depth = 254;
+        let deepest = master.derive_child(HARDENED).unwrap();
+
+        assert_eq!(deepest.depth, 255);
+        assert_eq!(deepest.derive_child(HARDENED), Err(Bip32Error::MaxDepth));
+        assert_eq!(deepest.derive_child(0), Err(Bip32Error::MaxDepth));
+    }
+
+    #[test]
+    fn test_private_public_agree() {
+        let master = ExtendedPrivateKey::parse(XPRV_M).unwrap();
+        let account = master.derive_child(HARDENED).unwrap();
+
+        assert_eq!(account.to_public().unwrap().to_string(), XPUB_0H);
+        assert_eq!(
+            account.derive_child(1).unwrap().to_public().unwrap(),
+            account.to_public().unwrap().derive_child(1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unknown_version() {
+        let mut master = ExtendedPrivateKey::parse(XPRV_M).unwrap();
+        master.version = [1, 2, 3, 4];
+
+        assert_eq!(master.to_public(), Err(Bip32Error::UnknownVersion([1, 2, 3, 4])));
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let master = ExtendedPrivateKey::parse(XPRV_M).unwrap();
+        let debug = format!("{:?}", master);
+
+        assert!(debug.starts_with("ExtendedPrivateKey { version: ["));
+        assert!(!debug.contains("chain_code"));
+        assert!(!debug.contains(&master.private_key.secret().to_string()));
+    }
+}
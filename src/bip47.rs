@@ -0,0 +1,291 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+
+use crate::address::{self, Network};
+use crate::bip32::{Bip32Error, ExtendedPrivateKey, ExtendedPublicKey, HARDENED, XPUB};
+use crate::crypto::ecdh::shared_secret;
+use crate::crypto::hash::sha256;
+use crate::curves::secp256k1::ecc::{order, KeyError, PrivateKey, PublicKey};
+use crate::encoding::base58::{self, Base58Error};
+
+/// Base58Check prefix that makes serialized payment codes start with "PM8T"
+const PREFIX: u8 = 0x47;
+const VERSION: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bip47Error {
+    Base58(Base58Error),
+    Bip32(Bip32Error),
+    InvalidLength(usize),
+    InvalidPrefix(u8),
+    UnsupportedVersion(u8),
+    InvalidKey(KeyError),
+    /// SHA256 of the shared secret is not a valid scalar; BIP47 says to
+    /// move on to the next index
+    InvalidSharedSecret(u32),
+}
+
+impl Display for Bip47Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bip47Error::Base58(e) => write!(f, "{}", e),
+            Bip47Error::Bip32(e) => write!(f, "{}", e),
+            Bip47Error::InvalidLength(l) => write!(f, "payment code must be 80 bytes, got {}", l),
+            Bip47Error::InvalidPrefix(p) => write!(f, "invalid payment code prefix {:#04x}", p),
+            Bip47Error::UnsupportedVersion(v) => write!(f, "unsupported payment code version {}", v),
+            Bip47Error::InvalidKey(e) => write!(f, "{}", e),
+            Bip47Error::InvalidSharedSecret(i) => write!(f, "shared secret for index {} is invalid", i),
+        }
+    }
+}
+
+impl std::error::Error for Bip47Error {}
+
+impl From<Base58Error> for Bip47Error {
+    fn from(e: Base58Error) -> Self {
+        Bip47Error::Base58(e)
+    }
+}
+
+impl From<Bip32Error> for Bip47Error {
+    fn from(e: Bip32Error) -> Self {
+        Bip47Error::Bip32(e)
+    }
+}
+
+/// Derives the payment code account key m/47'/coin'/account'
+pub fn account_key(seed: &[u8], network: Network, account: u32) -> Result<ExtendedPrivateKey, Bip47Error> {
+    let coin = match network {
+        Network::Mainnet => 0,
+        Network::Testnet | Network::Signet | Network::Regtest => 1,
+    };
+    let master = ExtendedPrivateKey::from_seed(seed, network)?;
+    Ok(master.derive_path(&[HARDENED + 47, HARDENED + coin, HARDENED + account])?)
+}
+
+/// Version 1 reusable payment code: the account public key and chain code
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentCode {
+    public_key: PublicKey,
+    chain_code: [u8; 32],
+}
+
+impl PaymentCode {
+    pub fn from(account: &ExtendedPrivateKey) -> Self {
+        Self {
+            public_key: account.private_key.public_key(),
+            chain_code: account.chain_code,
+        }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    /// version || features || sign || x || chain code || 13 reserved bytes
+    pub fn serialize(&self) -> [u8; 80] {
+        let mut res = [0_u8; 80];
+        res[0] = VERSION;
+        res[2..35].copy_from_slice(&self.public_key.sec(true));
+        res[35..67].copy_from_slice(&self.chain_code);
+        res
+    }
+
+    pub fn parse(s: &str) -> Result<Self, Bip47Error> {
+        let data = base58::decode_check(s)?;
+        if data.len() != 81 {
+            return Err(Bip47Error::InvalidLength(data.len().saturating_sub(1)));
+        }
+        if data[0] != PREFIX {
+            return Err(Bip47Error::InvalidPrefix(data[0]));
+        }
+        if data[1] != VERSION {
+            return Err(Bip47Error::UnsupportedVersion(data[1]));
+        }
+        Ok(Self {
+            public_key: PublicKey::parse(&data[3..36]).map_err(Bip47Error::InvalidKey)?,
+            chain_code: data[36..68].try_into().unwrap(),
+        })
+    }
+
+    /// The code viewed as an xpub, whose children are the payee keys
+    fn extended_key(&self) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            version: XPUB,
+            depth: 3,
+            parent_fingerprint: [0; 4],
+            child_number: HARDENED,
+            chain_code: self.chain_code,
+            public_key: self.public_key.clone(),
+        }
+    }
+
+    pub fn derive_key(&self, index: u32) -> Result<PublicKey, Bip47Error> {
+        Ok(self.extended_key().derive_child(index)?.public_key)
+    }
+
+    /// Key of the address that notification transactions are sent to
+    pub fn notification_key(&self) -> Result<PublicKey, Bip47Error> {
+        self.derive_key(0)
+    }
+
+    pub fn notification_address(&self, network: Network) -> Result<String, Bip47Error> {
+        Ok(address::p2pkh(&self.notification_key()?, network))
+    }
+}
+
+impl Display for PaymentCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut data = vec![PREFIX];
+        data.extend_from_slice(&self.serialize());
+        write!(f, "{}", base58::encode_check(&data))
+    }
+}
+
+fn shared_tweak(private_key: &PrivateKey, public_key: &PublicKey, index: u32) -> Result<BigUint, Bip47Error> {
+    let s = BigUint::from_bytes_be(&sha256(&shared_secret(private_key, public_key)));
+    if s >= order() {
+        return Err(Bip47Error::InvalidSharedSecret(index));
+    }
+    Ok(s)
+}
+
+/// Public key of the `index`-th address the owner of `account` pays to
+/// `recipient`: B' = B_i + SHA256(S) * G with S = a_0 * B_i
+pub fn send_key(account: &ExtendedPrivateKey, recipient: &PaymentCode, index: u32) -> Result<PublicKey, Bip47Error> {
+    let a0 = account.derive_child(0)?.private_key;
+    let bi = recipient.derive_key(index)?;
+    let s = shared_tweak(&a0, &bi, index)?;
+    bi.add_tweak(&s).map_err(Bip47Error::InvalidKey)
+}
+
+pub fn send_address(
+    account: &ExtendedPrivateKey,
+    recipient: &PaymentCode,
+    index: u32,
+    network: Network,
+) -> Result<String, Bip47Error> {
+    Ok(address::p2pkh(&send_key(account, recipient, index)?, network))
+}
+
+/// Private key spending the `index`-th payment from `sender`:
+/// b' = b_i + SHA256(S) with S = b_i * A_0
+pub fn receive_key(account: &ExtendedPrivateKey, sender: &PaymentCode, index: u32) -> Result<PrivateKey, Bip47Error> {
+    let bi = account.derive_child(index)?.private_key;
+    let a0 = sender.notification_key()?;
+    let s = shared_tweak(&bi, &a0, index)?;
+    bi.add_tweak(&s).map_err(Bip47Error::InvalidKey)
+}
+
+#[cfg(test)]
+mod bip47_tests {
+    use super::*;
+
+    // BIP47 test vectors for Alice and Bob
+    const ALICE_SEED: &str = "64dca76abc9c6f0cf3d212d248c380c4622c8f93b2c425ec6a5567fd5db57e10\
+                              d3e6f94a2f6af4ac2edb8998072aad92098db73558c323777abf5bd1082d970a";
+    const BOB_SEED: &str = "87eaaac5a539ab028df44d9110defbef3797ddb805ca309f61a69ff96dbaa7ab\
+                            5b24038cf029edec5235d933110f0aea8aeecf939ed14fc20730bba71e4b1110";
+    const ALICE_CODE: &str = "PM8TJTLJbPRGxSbc8EJi42Wrr6QbNSaSSVJ5Y3E4pbCYiTHUskHg13935Ubb7q8tx9GVbh2UuRnBc3WSyJHhUrw8KhprKnn9eDznYGieTzFcwQRya4GA";
+    const BOB_CODE: &str = "PM8TJS2JxQ5ztXUpBBRnpTbcUXbUHy2T1abfrb3KkAAtMEGNbey4oumH7Hc578WgQJhPjBxteQ5GHHToTYHE3A1w6p7tU6KSoFmWBVbFGjKPisZDbP97";
+
+    fn account(seed: &str) -> ExtendedPrivateKey {
+        account_key(&hex::decode(seed).unwrap(), Network::Mainnet, 0).unwrap()
+    }
+
+    #[test]
+    fn test_payment_codes() {
+        assert_eq!(PaymentCode::from(&account(ALICE_SEED)).to_string(), ALICE_CODE);
+        assert_eq!(PaymentCode::from(&account(BOB_SEED)).to_string(), BOB_CODE);
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let code = PaymentCode::parse(BOB_CODE).unwrap();
+
+        assert_eq!(code.to_string(), BOB_CODE);
+        assert_eq!(code, PaymentCode::from(&account(BOB_SEED)));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let mut data = base58::decode_check(ALICE_CODE).unwrap();
+        data[1] = 0x02;
+        assert_eq!(
+            PaymentCode::parse(&base58::encode_check(&data)),
+            Err(Bip47Error::UnsupportedVersion(2))
+        );
+
+        data[0] = 0x00;
+        assert_eq!(
+            PaymentCode::parse(&base58::encode_check(&data)),
+            Err(Bip47Error::InvalidPrefix(0))
+        );
+
+        assert_eq!(
+            PaymentCode::parse("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"),
+            Err(Bip47Error::InvalidLength(20))
+        );
+    }
+
+    #[test]
+    fn test_notification_addresses() {
+        let alice = PaymentCode::parse(ALICE_CODE).unwrap();
+        let bob = PaymentCode::parse(BOB_CODE).unwrap();
+
+        assert_eq!(
+            alice.notification_address(Network::Mainnet).unwrap(),
+            "1JDdmqFLhpzcUwPeinhJbUPw4Co3aWLyzW"
+        );
+        assert_eq!(
+            bob.notification_address(Network::Mainnet).unwrap(),
+            "1ChvUUvht2hUQufHBXF8NgLhW8SwE2ecGV"
+        );
+    }
+
+    #[test]
+    fn test_send_addresses() {
+        let alice = account(ALICE_SEED);
+        let bob = PaymentCode::parse(BOB_CODE).unwrap();
+        let expected = [
+            "141fi7TY3h936vRUKh1qfUZr8rSBuYbVBK",
+            "12u3Uued2fuko2nY4SoSFGCoGLCBUGPkk6",
+            "1FsBVhT5dQutGwaPePTYMe5qvYqqjxyftc",
+        ];
+
+        for (i, address) in expected.iter().enumerate() {
+            assert_eq!(
+                send_address(&alice, &bob, i as u32, Network::Mainnet).unwrap(),
+                *address
+            );
+        }
+    }
+
+    #[test]
+    fn test_shared_secret() {
+        let alice = account(ALICE_SEED);
+        let bob = PaymentCode::parse(BOB_CODE).unwrap();
+        let secret = shared_secret(&alice.derive_child(0).unwrap().private_key, &bob.derive_key(0).unwrap());
+
+        assert_eq!(
+            hex::encode(secret),
+            "f5bb84706ee366052471e6139e6a9a969d586e5fe6471a9b96c3d8caefe86fef"
+        );
+    }
+
+    #[test]
+    fn test_receive_key_matches_send_key() {
+        let alice = account(ALICE_SEED);
+        let bob = account(BOB_SEED);
+        let alice_code = PaymentCode::from(&alice);
+        let bob_code = PaymentCode::from(&bob);
+
+        let received = receive_key(&bob, &alice_code, 1).unwrap();
+        assert_eq!(received.public_key(), send_key(&alice, &bob_code, 1).unwrap());
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+use num_bigint::BigUint;
+
+use crate::address::Network;
+use crate::coinbase::{witness_commitment, witness_commitment_hash, witness_merkle_root};
+use crate::crypto::hash::hash256;
+use crate::encoding::compact_size;
+use crate::encoding::consensus::impl_consensus_codec;
+use crate::encoding::decode::{decode_slice, Context, DecodeError};
+use crate::merkle::{DoubleSha256, MerkleError, MerkleHasher, PartialMerkleTree};
+use crate::pow::{self, PowError};
+use crate::tx::{read_array, read_u32, Transaction, TxError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockError {
+    NoTransactions,
+    /// The first transaction must be the only coinbase
+    BadCoinbase,
+    MerkleRootMismatch,
+    /// Two transactions share a txid, or the txid list was padded by
+    /// repeating its tail, which leaves the merkle root unchanged
+    /// (CVE-2012-2459)
+    DuplicateTransaction,
+    /// Transactions have witnesses but the coinbase doesn't commit to them
+    MissingWitnessCommitment,
+    WitnessCommitmentMismatch,
+}
+
+impl Display for BlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockError::NoTransactions => write!(f, "block has no transactions"),
+            BlockError::BadCoinbase => write!(f, "block must start with its only coinbase"),
+            BlockError::MerkleRootMismatch => write!(f, "transactions do not match the header's merkle root"),
+            BlockError::DuplicateTransaction => write!(f, "block contains a duplicate transaction"),
+            BlockError::MissingWitnessCommitment => write!(f, "witness data without a witness commitment"),
+            BlockError::WitnessCommitmentMismatch => write!(f, "witnesses do not match the witness commitment"),
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+/// The 80-byte block header. Hashes are kept in internal byte order
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockHeader {
+    pub version: u32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub const SIZE: usize = 80;
+
+    /// The hardcoded first block every chain of the network builds on
+    pub fn genesis(network: Network) -> Self {
+        // the coinbase's txid, which is the merkle root of a one-tx block
+        let merkle_root = "3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a";
+        let (time, bits, nonce) = match network {
+            Network::Mainnet => (1231006505, 0x1d00ffff, 2083236893),
+            Network::Testnet => (1296688602, 0x1d00ffff, 414098458),
+            Network::Signet => (1598918400, 0x1e0377ae, 52613770),
+            Network::Regtest => (1296688602, 0x207fffff, 2),
+        };
+        Self {
+            version: 1,
+            prev_blockhash: [0; 32],
+            merkle_root: hex::decode(merkle_root).unwrap().try_into().unwrap(),
+            time,
+            bits,
+            nonce,
+        }
+    }
+
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(Self {
+            version: read_u32(reader).context("version")?,
+            prev_blockhash: read_array(reader).context("prev_blockhash")?,
+            merkle_root: read_array(reader).context("merkle_root")?,
+            time: read_u32(reader).context("time")?,
+            bits: read_u32(reader).context("bits")?,
+            nonce: read_u32(reader).context("nonce")?,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&self.prev_blockhash)?;
+        writer.write_all(&self.merkle_root)?;
+        writer.write_all(&self.time.to_le_bytes())?;
+        writer.write_all(&self.bits.to_le_bytes())?;
+        writer.write_all(&self.nonce.to_le_bytes())
+    }
+
+    pub fn serialize(&self) -> [u8; Self::SIZE] {
+        let mut res = [0_u8; Self::SIZE];
+        self.write(&mut &mut res[..]).unwrap();
+        res
+    }
+
+    /// hash256 of the header, in internal byte order
+    pub fn hash(&self) -> [u8; 32] {
+        hash256(&self.serialize())
+    }
+
+    /// The block hash as block explorers show it: byte-reversed hex
+    pub fn id(&self) -> String {
+        let mut hash = self.hash();
+        hash.reverse();
+        hex::encode(hash)
+    }
+
+    pub fn target(&self) -> Result<BigUint, PowError> {
+        pow::bits_to_target(self.bits)
+    }
+
+    /// Whether the header's hash meets its own claimed target
+    pub fn check_pow(&self) -> bool {
+        pow::check_pow(&self.hash(), self.bits)
+    }
+}
+
+/// A full block: its header and transactions, the first being the coinbase
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub txdata: Vec<Transaction>,
+}
+
+impl Block {
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        let header = BlockHeader::parse(reader).context("header")?;
+        let txdata = (0..compact_size::read_size(reader).context("txdata")?)
+            .map(|i| Transaction::parse(reader).context(format_args!("txdata[{}]", i)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { header, txdata })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError<TxError>> {
+        decode_slice(bytes, Self::parse)
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, DecodeError<TxError>> {
+        Self::from_bytes(&hex::decode(s).map_err(|_| TxError::InvalidHex)?)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.header.write(writer)?;
+        compact_size::write(writer, self.txdata.len() as u64)?;
+        for tx in &self.txdata {
+            tx.write(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write(&mut res).unwrap();
+        res
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        self.header.hash()
+    }
+
+    /// The merkle root of the txids, as the header should commit to, and
+    /// whether any level paired a node with an identical sibling. That
+    /// happens when the list was padded by repeating its tail, so a
+    /// mutated block can have the right root (CVE-2012-2459)
+    pub fn compute_merkle_root(&self) -> Option<([u8; 32], bool)> {
+        let mut level: Vec<[u8; 32]> = self.txdata.iter().map(|tx| tx.txid()).collect();
+        if level.is_empty() {
+            return None;
+        }
+        let mut mutated = false;
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| {
+                    let (left, right) = match pair {
+                        [left, right] => {
+                            mutated |= left == right;
+                            (left, right)
+                        }
+                        [last] => (last, last),
+                        _ => unreachable!(),
+                    };
+                    DoubleSha256::combine(left, right)
+                })
+                .collect();
+        }
+        Some((level[0], mutated))
+    }
+
+    /// BIP-141 commitment checks. Blocks without witness data needn't
+    /// commit; otherwise the coinbase witness is the 32-byte reserved value
+    /// and its commitment output hashes it with the wtxid root
+    pub fn check_witness_commitment(&self) -> Result<(), BlockError> {
+        let (coinbase, rest) = self.txdata.split_first().ok_or(BlockError::NoTransactions)?;
+        let Some(commitment) = witness_commitment(coinbase) else {
+            if self.txdata.iter().any(Transaction::has_witness) {
+                return Err(BlockError::MissingWitnessCommitment);
+            }
+            return Ok(());
+        };
+        let reserved: [u8; 32] = match &coinbase.inputs[0].witness[..] {
+            [reserved] => reserved
+                .as_slice()
+                .try_into()
+                .map_err(|_| BlockError::WitnessCommitmentMismatch)?,
+            _ => return Err(BlockError::WitnessCommitmentMismatch),
+        };
+        let wtxids: Vec<[u8; 32]> = rest.iter().map(|tx| tx.wtxid()).collect();
+        if witness_commitment_hash(&witness_merkle_root(&wtxids), &reserved) != commitment {
+            return Err(BlockError::WitnessCommitmentMismatch);
+        }
+        Ok(())
+    }
+
+    /// Checks that the transactions are the ones the header commits to:
+    /// the coinbase comes first and only there, there are no duplicates,
+    /// and both the merkle root and the witness commitment match. Proof of
+    /// work and the transactions' own validity are separate matters
+    pub fn validate(&self) -> Result<(), BlockError> {
+        let (coinbase, rest) = self.txdata.split_first().ok_or(BlockError::NoTransactions)?;
+        if !coinbase.is_coinbase() || rest.iter().any(Transaction::is_coinbase) {
+            return Err(BlockError::BadCoinbase);
+        }
+        let (root, mutated) = self.compute_merkle_root().ok_or(BlockError::NoTransactions)?;
+        if root != self.header.merkle_root {
+            return Err(BlockError::MerkleRootMismatch);
+        }
+        let mut txids = HashSet::new();
+        if mutated || !self.txdata.iter().all(|tx| txids.insert(tx.txid())) {
+            return Err(BlockError::DuplicateTransaction);
+        }
+        self.check_witness_commitment()
+    }
+}
+
+/// A header with a partial merkle tree proving some of the block's
+/// transactions, as sent to BIP-37 filtered clients
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerkleBlock {
+    pub header: BlockHeader,
+    pub txn: PartialMerkleTree,
+}
+
+impl MerkleBlock {
+    /// Proves the txids whose `matches` flag is set. `header` must be the
+    /// header of the block the txids belong to
+    pub fn from_txids(header: BlockHeader, txids: &[[u8; 32]], matches: &[bool]) -> Self {
+        Self {
+            header,
+            txn: PartialMerkleTree::from_txids(txids, matches),
+        }
+    }
+
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(Self {
+            header: BlockHeader::parse(reader).context("header")?,
+            txn: PartialMerkleTree::parse(reader).context("txn")?,
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError<TxError>> {
+        decode_slice(bytes, Self::parse)
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, DecodeError<TxError>> {
+        Self::from_bytes(&hex::decode(s).map_err(|_| TxError::InvalidHex)?)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.header.write(writer)?;
+        self.txn.write(writer)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write(&mut res).unwrap();
+        res
+    }
+
+    /// The matched txids with their positions in the block, once the
+    /// partial tree is checked against the header's merkle root
+    pub fn extract_matches(&self) -> Result<Vec<(u32, [u8; 32])>, MerkleError> {
+        let (root, matches) = self.txn.extract_matches()?;
+        if root != self.header.merkle_root {
+            return Err(MerkleError::RootMismatch);
+        }
+        Ok(matches)
+    }
+}
+
+impl_consensus_codec!(BlockHeader, TxError);
+impl_consensus_codec!(Block, TxError);
+impl_consensus_codec!(MerkleBlock, TxError);
+
+#[cfg(test)]
+mod block_tests {
+    use super::*;
+
+    fn reversed(s: &str) -> [u8; 32] {
+        let mut bytes = hex::decode(s).unwrap();
+        bytes.reverse();
+        bytes.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_genesis_header() {
+        let header = BlockHeader::genesis(Network::Mainnet);
+        assert_eq!(
+            header.id(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(
+            header.merkle_root,
+            reversed("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b")
+        );
+        assert!(header.check_pow());
+        let bytes = header.serialize();
+        assert_eq!(BlockHeader::parse(&mut &bytes[..]).unwrap(), header);
+
+        let mut bad = header.clone();
+        bad.nonce += 1;
+        assert!(!bad.check_pow());
+
+        assert_eq!(
+            BlockHeader::genesis(Network::Testnet).id(),
+            "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943"
+        );
+        assert_eq!(
+            BlockHeader::genesis(Network::Signet).id(),
+            "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6"
+        );
+        assert_eq!(
+            BlockHeader::genesis(Network::Regtest).id(),
+            "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206"
+        );
+    }
+
+    /// A mainnet block from rust-bitcoin's tests: a coinbase and one
+    /// transaction
+    const BLOCK: &str = "010000004ddccd549d28f385ab457e98d1b11ce80bfea2c5ab93015ade4973e400000000bf4473e53794beae34\
+        e64fccc471dace6ae544180816f89591894e0f417a914cd74d6e49ffff001d323b3a7b02010000000100000000000000000000000000\
+        00000000000000000000000000000000000000ffffffff0804ffff001d026e04ffffffff0100f2052a0100000043410446ef0102d1ec\
+        5240f0d061a4246c1bdef63fc3dbab7733052fbbf0ecd8f41fc26bf049ebb4f9527f374280259e7cfa99c48b0e3f39c51347a19a5819\
+        651503a5ac00000000010000000321f75f3139a013f50f315b23b0c9a2b6eac31e2bec98e5891c924664889942260000000049483045\
+        022100cb2c6b346a978ab8c61b18b5e9397755cbd17d6eb2fe0083ef32e067fa6c785a02206ce44e613f31d9a6b0517e46f3db1576e9\
+        812cc98d159bfdaf759a5014081b5c01ffffffff79cda0945903627c3da1f85fc95d0b8ee3e76ae0cfdc9a65d09744b1f8fc85430000\
+        000049483045022047957cdd957cfd0becd642f6b84d82f49b6cb4c51a91f49246908af7c3cfdf4a022100e96b46621f1bffcf5ea598\
+        2f88cef651e9354f5791602369bf5a82a6cd61a62501fffffffffe09f5fe3ffbf5ee97a54eb5e5069e9da6b4856ee86fc52938c2f979\
+        b0f38e82000000004847304402204165be9a4cbab8049e1af9723b96199bfd3e85f44c6b4c0177e3962686b26073022028f638da23fc\
+        003760861ad481ead4099312c60030d4cb57820ce4d33812a5ce01ffffffff01009d966b01000000434104ea1feff861b51fe3f5f8a3\
+        b12d0f4712db80e919548a80839fc47c6a21e66d957e9c5d8cd108c7a2d2324bad71f9904ac0ae7336507d785b17a2c115e427a32fac\
+        00000000";
+
+    #[test]
+    fn test_block_merkle_root() {
+        let block = Block::from_hex(BLOCK).unwrap();
+        assert_eq!(block.txdata.len(), 2);
+        assert_eq!(hex::encode(block.serialize()), BLOCK);
+        assert_eq!(block.compute_merkle_root(), Some((block.header.merkle_root, false)));
+        assert_eq!(block.validate(), Ok(()));
+
+        let mut tampered = block.clone();
+        tampered.txdata[1].locktime = 1;
+        assert_eq!(tampered.validate(), Err(BlockError::MerkleRootMismatch));
+
+        // [a, b, c] and [a, b, c, c] share a root, but the repeat is caught
+        let (cb, tx) = (block.txdata[0].clone(), block.txdata[1].clone());
+        let three = Block {
+            header: block.header.clone(),
+            txdata: vec![cb.clone(), tx.clone(), tx.clone()],
+        };
+        let mut padded = Block {
+            header: block.header.clone(),
+            txdata: vec![cb, tx.clone(), tx.clone(), tx],
+        };
+        let (root, mutated) = padded.compute_merkle_root().unwrap();
+        assert_eq!(three.compute_merkle_root(), Some((root, false)));
+        assert!(mutated);
+        padded.header.merkle_root = root;
+        assert_eq!(padded.validate(), Err(BlockError::DuplicateTransaction));
+
+        let mut no_coinbase = block;
+        no_coinbase.txdata.swap(0, 1);
+        assert_eq!(no_coinbase.validate(), Err(BlockError::BadCoinbase));
+    }
+
+    #[test]
+    fn test_witness_commitment() {
+        use crate::coinbase::{add_witness_commitment, coinbase};
+        use crate::script::Script;
+        use crate::tx::{OutPoint, TxIn, TxOut};
+
+        let spend = Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                previous_output: OutPoint { txid: [1; 32], vout: 0 },
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: vec![vec![0x30; 71], vec![0x02; 33]],
+            }],
+            outputs: vec![TxOut {
+                amount: 1_000,
+                script_pubkey: Script::p2wpkh(&[2; 20]),
+            }],
+            locktime: 0,
+        };
+        let mut coinbase_tx = coinbase(
+            1,
+            &[],
+            vec![TxOut {
+                amount: 1_000,
+                script_pubkey: Script::p2wpkh(&[3; 20]),
+            }],
+        )
+        .unwrap();
+        let unsigned = coinbase_tx.clone();
+        add_witness_commitment(&mut coinbase_tx, &[spend.wtxid()]);
+
+        let mut block = Block {
+            header: BlockHeader::genesis(Network::Regtest),
+            txdata: vec![coinbase_tx, spend],
+        };
+        block.header.merkle_root = block.compute_merkle_root().unwrap().0;
+        assert_eq!(block.validate(), Ok(()));
+
+        // a changed witness leaves the merkle root alone but not the commitment
+        let mut tampered = block.clone();
+        tampered.txdata[1].inputs[0].witness[0][5] ^= 1;
+        assert_eq!(tampered.compute_merkle_root(), block.compute_merkle_root());
+        assert_eq!(tampered.validate(), Err(BlockError::WitnessCommitmentMismatch));
+
+        let mut uncommitted = block.clone();
+        uncommitted.txdata[0] = unsigned;
+        uncommitted.header.merkle_root = uncommitted.compute_merkle_root().unwrap().0;
+        assert_eq!(uncommitted.validate(), Err(BlockError::MissingWitnessCommitment));
+    }
+
+    #[test]
+    fn test_merkle_block() {
+        // bitcoind gettxoutproof output, from rust-bitcoin's docs
+        let s = "01000000ba8b9cda965dd8e536670f9ddec10e53aab14b20bacad27b9137190000000000190760b278fe7b8565fda3b968\
+                 b918d5fd997f993b23674c0af3b6fde300b38f33a5914ce6ed5b1b01e32f570200000002252bf9d75c4f481ebb6278d7\
+                 08257d1f12beb6dd30301d26c623f789b2ba6fc0e2d32adb5f8ca820731dff234a84e78ec30bce4ec69dbd562d0b2b82\
+                 66bf4e5a0105";
+        let block = MerkleBlock::from_hex(s).unwrap();
+        assert!(block.header.check_pow());
+        assert_eq!(hex::encode(block.serialize()), s);
+        let txid = reversed("5a4ebf66822b0b2d56bd9dc64ece0bc38ee7844a23ff1d7320a88c5fdb2ad3e2");
+        assert_eq!(block.extract_matches(), Ok(vec![(1, txid)]));
+
+        let mut tampered = block.clone();
+        tampered.header.merkle_root[0] ^= 1;
+        assert_eq!(tampered.extract_matches(), Err(MerkleError::RootMismatch));
+
+        // rebuilding from the two txids gives the same message
+        let first = block.txn.hashes[0];
+        let rebuilt = MerkleBlock::from_txids(block.header.clone(), &[first, txid], &[false, true]);
+        assert_eq!(rebuilt.serialize(), block.serialize());
+        assert_eq!(
+            MerkleBlock::from_hex(&format!("{}00", s)).unwrap_err().into_kind(),
+            TxError::TrailingBytes(1)
+        );
+    }
+}
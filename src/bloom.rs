@@ -0,0 +1,361 @@
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+use crate::crypto::hash::murmur3_32;
+use crate::encoding::consensus::impl_consensus_codec;
+use crate::encoding::decode::{Context, DecodeError};
+use crate::script::{Instruction, Opcode, Script};
+use crate::tx::{read_array, read_u32, read_var_bytes, write_var_bytes, OutPoint, Transaction, TxError};
+
+/// Largest filter a node accepts in filterload, in bytes
+pub const MAX_FILTER_SIZE: usize = 36_000;
+pub const MAX_HASH_FUNCS: u32 = 50;
+
+/// Spreads the seeds of the hash functions apart
+const SEED_MULTIPLIER: u32 = 0xfba4c795;
+
+/// What a node adds to the filter when an output matches, so the client
+/// also hears about the transaction spending it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BloomFlags {
+    /// Never update the filter
+    None = 0,
+    /// Add the outpoint of every matching output
+    All = 1,
+    /// Only add outpoints of matching pay-to-pubkey and bare multisig
+    /// outputs, whose spends don't reveal the data pushed in the output
+    PubkeyOnly = 2,
+}
+
+impl BloomFlags {
+    pub fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::None),
+            1 => Some(Self::All),
+            2 => Some(Self::PubkeyOnly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BloomError {
+    Tx(TxError),
+    /// The filter is larger than MAX_FILTER_SIZE bytes
+    TooLarge(usize),
+    TooManyHashFuncs(u32),
+    InvalidFlags(u8),
+}
+
+impl Display for BloomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BloomError::Tx(e) => write!(f, "{}", e),
+            BloomError::TooLarge(size) => write!(f, "bloom filter of {} bytes exceeds {}", size, MAX_FILTER_SIZE),
+            BloomError::TooManyHashFuncs(n) => write!(f, "{} hash functions exceed {}", n, MAX_HASH_FUNCS),
+            BloomError::InvalidFlags(flags) => write!(f, "invalid bloom flags {:#04x}", flags),
+        }
+    }
+}
+
+impl std::error::Error for BloomError {}
+
+impl From<TxError> for BloomError {
+    fn from(e: TxError) -> Self {
+        BloomError::Tx(e)
+    }
+}
+
+/// A BIP-37 bloom filter, which an SPV client loads into its peers so they
+/// only relay transactions touching its scripts, keys and outpoints. The
+/// tweak seeds the hash functions so filters for the same data differ
+/// between peers
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    data: Vec<u8>,
+    hash_funcs: u32,
+    tweak: u32,
+    flags: BloomFlags,
+}
+
+impl BloomFilter {
+    /// Sizes the filter so that `elements` insertions give a false positive
+    /// rate of about `fp_rate`, capped at the protocol's limits
+    pub fn new(elements: usize, fp_rate: f64, tweak: u32, flags: BloomFlags) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-1.0 / (ln2 * ln2) * elements as f64 * fp_rate.ln()) as usize;
+        let size = bits.min(MAX_FILTER_SIZE * 8) / 8;
+        let hash_funcs = ((size * 8) as f64 / elements as f64 * ln2) as u32;
+        Self {
+            data: vec![0; size],
+            hash_funcs: hash_funcs.min(MAX_HASH_FUNCS),
+            tweak,
+            flags,
+        }
+    }
+
+    pub fn tweak(&self) -> u32 {
+        self.tweak
+    }
+
+    pub fn flags(&self) -> BloomFlags {
+        self.flags
+    }
+
+    pub fn hash_funcs(&self) -> u32 {
+        self.hash_funcs
+    }
+
+    fn bit_index(&self, n: u32, data: &[u8]) -> usize {
+        let seed = n.wrapping_mul(SEED_MULTIPLIER).wrapping_add(self.tweak);
+        murmur3_32(seed, data) as usize % (self.data.len() * 8)
+    }
+
+    pub fn insert(&mut self, data: &[u8]) {
+        if self.data.is_empty() {
+            return;
+        }
+        for n in 0..self.hash_funcs {
+            let i = self.bit_index(n, data);
+            self.data[i / 8] |= 1 << (i % 8);
+        }
+    }
+
+    /// May return false positives, never false negatives. An empty filter
+    /// matches everything
+    pub fn contains(&self, data: &[u8]) -> bool {
+        if self.data.is_empty() {
+            return true;
+        }
+        (0..self.hash_funcs).all(|n| {
+            let i = self.bit_index(n, data);
+            self.data[i / 8] & (1 << (i % 8)) != 0
+        })
+    }
+
+    pub fn insert_outpoint(&mut self, outpoint: &OutPoint) {
+        self.insert(&outpoint_bytes(outpoint));
+    }
+
+    pub fn contains_outpoint(&self, outpoint: &OutPoint) -> bool {
+        self.contains(&outpoint_bytes(outpoint))
+    }
+
+    /// Whether a node would relay `tx` to the filter's owner: its txid, a
+    /// data push in one of its scripts, or one of the outpoints it spends is
+    /// in the filter. Matching outputs are added according to the flags,
+    /// as a node does
+    pub fn is_relevant_and_update(&mut self, tx: &Transaction) -> bool {
+        if self.data.is_empty() {
+            return true;
+        }
+        let txid = tx.txid();
+        let mut found = self.contains(&txid);
+        for (vout, output) in tx.outputs.iter().enumerate() {
+            if !self.contains_push(&output.script_pubkey) {
+                continue;
+            }
+            found = true;
+            let update = match self.flags {
+                BloomFlags::None => false,
+                BloomFlags::All => true,
+                BloomFlags::PubkeyOnly => is_pubkey_script(&output.script_pubkey),
+            };
+            if update {
+                self.insert_outpoint(&OutPoint {
+                    txid,
+                    vout: vout as u32,
+                });
+            }
+        }
+        found
+            || tx
+                .inputs
+                .iter()
+                .any(|input| self.contains_outpoint(&input.previous_output) || self.contains_push(&input.script_sig))
+    }
+
+    /// Whether any non-empty push up to the first parse error is in the filter
+    fn contains_push(&self, script: &Script) -> bool {
+        script
+            .instructions()
+            .map_while(Result::ok)
+            .any(|i| matches!(i, Instruction::Push(_, data) if !data.is_empty() && self.contains(data)))
+    }
+
+    /// Parses a filterload payload, enforcing the size limits nodes apply
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<BloomError>> {
+        let data = read_var_bytes(reader).map_err(BloomError::from).context("data")?;
+        if data.len() > MAX_FILTER_SIZE {
+            return Err(BloomError::TooLarge(data.len())).context("data");
+        }
+        let hash_funcs = read_u32(reader).map_err(BloomError::from).context("hash_funcs")?;
+        if hash_funcs > MAX_HASH_FUNCS {
+            return Err(BloomError::TooManyHashFuncs(hash_funcs)).context("hash_funcs");
+        }
+        let tweak = read_u32(reader).map_err(BloomError::from).context("tweak")?;
+        let [flags] = read_array(reader).map_err(BloomError::from).context("flags")?;
+        let flags = BloomFlags::from_u8(flags)
+            .ok_or(BloomError::InvalidFlags(flags))
+            .context("flags")?;
+        Ok(Self {
+            data,
+            hash_funcs,
+            tweak,
+            flags,
+        })
+    }
+
+    /// Writes the filterload payload
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_var_bytes(writer, &self.data)?;
+        writer.write_all(&self.hash_funcs.to_le_bytes())?;
+        writer.write_all(&self.tweak.to_le_bytes())?;
+        writer.write_all(&[self.flags as u8])
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write(&mut res).unwrap();
+        res
+    }
+}
+
+fn outpoint_bytes(outpoint: &OutPoint) -> Vec<u8> {
+    let mut res = Vec::with_capacity(36);
+    outpoint.write(&mut res).unwrap();
+    res
+}
+
+/// `<key> OP_CHECKSIG` or `OP_m <keys> OP_n OP_CHECKMULTISIG`
+fn is_pubkey_script(script: &Script) -> bool {
+    let Ok(instructions) = script.instructions().collect::<Result<Vec<_>, _>>() else {
+        return false;
+    };
+    let is_key = |i: &Instruction| matches!(i, Instruction::Push(_, key) if key.len() == 33 || key.len() == 65);
+    match &instructions[..] {
+        [key, Instruction::Op(Opcode::CheckSig)] => is_key(key),
+        [Instruction::Op(m), keys @ .., Instruction::Op(n), Instruction::Op(Opcode::CheckMultiSig)] => {
+            match (m.small_int(), n.small_int()) {
+                (Some(m), Some(n)) => 1 <= m && m <= n && keys.len() == n as usize && keys.iter().all(is_key),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+impl_consensus_codec!(BloomFilter, BloomError);
+
+#[cfg(test)]
+mod bloom_tests {
+    use super::*;
+    use crate::encoding::consensus::ConsensusDecode;
+    use crate::tx::{TxIn, TxOut};
+
+    #[test]
+    fn test_insert_serialize() {
+        // Bitcoin Core's bloom_create_insert_serialize(_with_tweak)
+        let items = [
+            "99108ad8ed9bb6274d3980bab5a85c048f0950c8",
+            "b5a2c786d9ef4658287ced5914b37a1b4aa32eee",
+            "b9300670b4c5366e95b2699e8b18bc75e5f729c5",
+        ];
+        for (tweak, expected) in [
+            (0, "03614e9b050000000000000001"),
+            (2147483649, "03ce4299050000000100008001"),
+        ] {
+            let mut filter = BloomFilter::new(3, 0.01, tweak, BloomFlags::All);
+            for item in items {
+                filter.insert(&hex::decode(item).unwrap());
+                assert!(filter.contains(&hex::decode(item).unwrap()));
+            }
+            assert!(!filter.contains(&hex::decode("19108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()));
+            assert_eq!(hex::encode(filter.serialize()), expected);
+            assert_eq!(BloomFilter::parse(&mut &filter.serialize()[..]).unwrap(), filter);
+        }
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let mut too_large = vec![0xfd, 0xa1, 0x8c];
+        too_large.extend(vec![0; 36_001]);
+        let kind = |bytes: &[u8]| BloomFilter::parse(&mut &bytes[..]).unwrap_err().into_kind();
+        assert_eq!(kind(&too_large), BloomError::TooLarge(36_001));
+        let bytes = hex::decode("03614e9b330000000000000001").unwrap();
+        assert_eq!(kind(&bytes), BloomError::TooManyHashFuncs(51));
+        let bytes = hex::decode("03614e9b050000000000000003").unwrap();
+        assert_eq!(kind(&bytes), BloomError::InvalidFlags(3));
+        let err = BloomFilter::consensus_deserialize(&bytes[..12]).unwrap_err();
+        assert_eq!(err.to_string(), "transaction is truncated at byte 12 in flags");
+    }
+
+    fn paying_to(script_pubkey: Script) -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                previous_output: OutPoint { txid: [1; 32], vout: 0 },
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: Vec::new(),
+            }],
+            outputs: vec![TxOut {
+                amount: 50_000,
+                script_pubkey,
+            }],
+            locktime: 0,
+        }
+    }
+
+    fn spending(tx: &Transaction) -> Transaction {
+        let mut spend = paying_to(Script::p2pkh(&[9; 20]));
+        spend.inputs[0].previous_output = OutPoint {
+            txid: tx.txid(),
+            vout: 0,
+        };
+        spend
+    }
+
+    #[test]
+    fn test_relevant_and_update() {
+        let hash = [7; 20];
+        let key = [2; 33];
+        let p2pkh = paying_to(Script::p2pkh(&hash));
+        let p2pk = paying_to(Script::new().push_slice(&key).push_opcode(Opcode::CheckSig));
+
+        for (flags, follows_p2pkh, follows_p2pk) in [
+            (BloomFlags::None, false, false),
+            (BloomFlags::All, true, true),
+            (BloomFlags::PubkeyOnly, false, true),
+        ] {
+            let mut filter = BloomFilter::new(10, 0.000001, 0, flags);
+            filter.insert(&hash);
+            filter.insert(&key);
+            assert!(filter.is_relevant_and_update(&p2pkh));
+            assert!(filter.is_relevant_and_update(&p2pk));
+            assert_eq!(
+                filter.is_relevant_and_update(&spending(&p2pkh)),
+                follows_p2pkh,
+                "{:?}",
+                flags
+            );
+            assert_eq!(
+                filter.is_relevant_and_update(&spending(&p2pk)),
+                follows_p2pk,
+                "{:?}",
+                flags
+            );
+        }
+
+        // matched by txid and by a push in the script_sig
+        let mut filter = BloomFilter::new(10, 0.000001, 0, BloomFlags::None);
+        let other = paying_to(Script::p2pkh(&[8; 20]));
+        assert!(!filter.is_relevant_and_update(&other));
+        filter.insert(&other.txid());
+        assert!(filter.is_relevant_and_update(&other));
+        let mut signed = paying_to(Script::p2pkh(&[8; 20]));
+        signed.inputs[0].script_sig = Script::new().push_slice(&[0x30; 71]).push_slice(&key);
+        filter.insert(&key);
+        assert!(filter.is_relevant_and_update(&signed));
+    }
+}
@@ -0,0 +1,264 @@
+use std::ops::{Add, Mul, Neg};
+use std::sync::{Arc, OnceLock};
+
+use num_bigint::{BigInt, Sign};
+
+use crate::arithmetic::field_element::{FieldElement, FieldError};
+use crate::bn254::fields::{fq, Fq2};
+use crate::bn254::{curve_order, field_modulus};
+use crate::curves::ec_point::{Curve, Point, PointError};
+use crate::curves::secp256k1::ecc::to_32_bytes;
+
+/// G1 is y² = x³ + 3 over Fq, on the crate's generic curve points
+pub fn g1_curve() -> &'static Arc<Curve> {
+    static CURVE: OnceLock<Arc<Curve>> = OnceLock::new();
+    CURVE.get_or_init(|| Curve::new(fq(0), fq(3)).expect("one field"))
+}
+
+pub fn g1_generator() -> Point {
+    Point::on_curve(Some(fq(1)), Some(fq(2)), g1_curve()).expect("the generator is on the curve")
+}
+
+pub fn g1_infinity() -> Point {
+    Point::infinity_on(g1_curve())
+}
+
+pub fn g1_neg(point: &Point) -> Point {
+    match (point.x(), point.y()) {
+        (Some(x), Some(y)) => {
+            Point::on_curve(Some(x.clone()), Some(fq(0) - y.clone()), g1_curve()).expect("-P is on the curve")
+        }
+        _ => g1_infinity(),
+    }
+}
+
+/// The EIP-196 encoding: x and y as 32 big-endian bytes each, with the
+/// point at infinity as all zeros
+pub fn g1_to_bytes(point: &Point) -> [u8; 64] {
+    let mut res = [0_u8; 64];
+    if let (Some(x), Some(y)) = (point.x(), point.y()) {
+        res[..32].copy_from_slice(&to_32_bytes(&x.num()));
+        res[32..].copy_from_slice(&to_32_bytes(&y.num()));
+    }
+    res
+}
+
+/// None for coordinates outside the field or off the curve
+pub fn g1_from_bytes(bytes: &[u8; 64]) -> Option<Point> {
+    if bytes.iter().all(|&b| b == 0) {
+        return Some(g1_infinity());
+    }
+    let (x, y) = (coordinate(&bytes[..32])?, coordinate(&bytes[32..])?);
+    Point::on_curve(Some(x), Some(y), g1_curve()).ok()
+}
+
+fn coordinate(bytes: &[u8]) -> Option<FieldElement> {
+    let num = BigInt::from_bytes_be(Sign::Plus, bytes);
+    (num < field_modulus()).then(|| fq(num))
+}
+
+/// A point of G2, on the sextic twist y² = x³ + 3/ξ over Fq2
+#[derive(Debug, Clone, PartialEq)]
+pub struct G2Point {
+    coords: Option<(Fq2, Fq2)>,
+}
+
+impl G2Point {
+    /// Errors for coordinates outside Fq2 or off the twist. The point may
+    /// still lie outside the order-r subgroup; see `is_torsion_free`
+    pub fn from(x: Fq2, y: Fq2) -> Result<Self, PointError> {
+        if [&x.c0, &x.c1, &y.c0, &y.c1]
+            .iter()
+            .any(|c| c.prime() != field_modulus())
+        {
+            return Err(PointError::Field(FieldError::FieldMismatch));
+        }
+        if y.square() != x.square() * x.clone() + Self::b() {
+            return Err(PointError::NotOnCurve);
+        }
+        Ok(Self { coords: Some((x, y)) })
+    }
+
+    pub fn infinity() -> Self {
+        Self { coords: None }
+    }
+
+    /// The generator fixed by EIP-197
+    pub fn generator() -> Self {
+        let fq2 = |c0: &[u8], c1: &[u8]| {
+            Fq2::new(
+                fq(BigInt::parse_bytes(c0, 10).unwrap()),
+                fq(BigInt::parse_bytes(c1, 10).unwrap()),
+            )
+        };
+        Self::from(
+            fq2(
+                b"10857046999023057135944570762232829481370756359578518086990519993285655852781",
+                b"11559732032986387107991004021392285783925812861821192530917403151452391805634",
+            ),
+            fq2(
+                b"8495653923123431417604973247489272438418190587263600148770280649306958101930",
+                b"4082367875863433681332203403145435568316851327593401208105741076214120093531",
+            ),
+        )
+        .expect("the generator is on the twist")
+    }
+
+    /// 3 / ξ
+    pub fn b() -> Fq2 {
+        Fq2::new(fq(3), fq(0)) * Fq2::xi().inverse()
+    }
+
+    pub fn x(&self) -> Option<&Fq2> {
+        self.coords.as_ref().map(|(x, _)| x)
+    }
+
+    pub fn y(&self) -> Option<&Fq2> {
+        self.coords.as_ref().map(|(_, y)| y)
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.coords.is_none()
+    }
+
+    /// The EIP-197 encoding: x then y, each as the imaginary part then the
+    /// real part in 32 big-endian bytes, with infinity as all zeros
+    pub fn to_bytes(&self) -> [u8; 128] {
+        let mut res = [0_u8; 128];
+        if let Some((x, y)) = &self.coords {
+            for (i, c) in [&x.c1, &x.c0, &y.c1, &y.c0].into_iter().enumerate() {
+                res[32 * i..32 * (i + 1)].copy_from_slice(&to_32_bytes(&c.num()));
+            }
+        }
+        res
+    }
+
+    /// None for coordinates outside the field, points off the twist, or
+    /// points outside the order-r subgroup
+    pub fn from_bytes(bytes: &[u8; 128]) -> Option<Self> {
+        if bytes.iter().all(|&b| b == 0) {
+            return Some(Self::infinity());
+        }
+        let c: Vec<FieldElement> = bytes.chunks(32).map(coordinate).collect::<Option<_>>()?;
+        let (x, y) = (
+            Fq2::new(c[1].clone(), c[0].clone()),
+            Fq2::new(c[3].clone(), c[2].clone()),
+        );
+        let point = Self::from(x, y).ok()?;
+        point.is_torsion_free().then_some(point)
+    }
+
+    /// Whether the point is in the order-r subgroup. The twist has other
+    /// points too, which on-curve checks alone don't rule out
+    pub fn is_torsion_free(&self) -> bool {
+        (curve_order() * self.clone()).is_infinity()
+    }
+}
+
+impl Add for G2Point {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let ((x1, y1), (x2, y2)) = match (self.coords, rhs.coords) {
+            (None, other) | (other, None) => return Self { coords: other },
+            (Some(p), Some(q)) => (p, q),
+        };
+        let m = if x1 == x2 {
+            if y1 != y2 || y1.is_zero() {
+                return Self::infinity();
+            }
+            // the tangent, 3x² / 2y
+            x1.square().scale(&fq(3)) * y1.scale(&fq(2)).inverse()
+        } else {
+            (y2 - y1.clone()) * (x2.clone() - x1.clone()).inverse()
+        };
+        let x3 = m.square() - x1.clone() - x2;
+        let y3 = m * (x1 - x3.clone()) - y1;
+        Self { coords: Some((x3, y3)) }
+    }
+}
+
+impl Neg for G2Point {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            coords: self.coords.map(|(x, y)| (x, -y)),
+        }
+    }
+}
+
+impl Mul<G2Point> for BigInt {
+    type Output = G2Point;
+
+    fn mul(self, rhs: G2Point) -> Self::Output {
+        let mut res = G2Point::infinity();
+        for i in (0..self.bits()).rev() {
+            res = res.clone() + res;
+            if self.bit(i) {
+                res = res + rhs.clone();
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod curve_tests {
+    use super::*;
+
+    #[test]
+    fn test_groups() {
+        let r = curve_order();
+        assert!((r.clone() * g1_generator()).is_infinity());
+        assert!((r.clone() - 1) * g1_generator() == g1_neg(&g1_generator()));
+        let g2 = G2Point::generator();
+        assert!(g2.is_torsion_free());
+        assert_eq!((r - 1) * g2.clone(), -g2.clone());
+        assert_eq!(BigInt::from(2) * g2.clone() + g2.clone(), BigInt::from(3) * g2.clone());
+        assert_eq!(g2.clone() + -g2, G2Point::infinity());
+    }
+
+    #[test]
+    fn test_encoding() {
+        let g1 = g1_generator();
+        let bytes = g1_to_bytes(&g1);
+        assert_eq!(bytes[31], 1);
+        assert_eq!(bytes[63], 2);
+        assert_eq!(g1_from_bytes(&bytes), Some(g1));
+        assert_eq!(g1_from_bytes(&[0; 64]), Some(g1_infinity()));
+        let mut off_curve = bytes;
+        off_curve[63] = 3;
+        assert_eq!(g1_from_bytes(&off_curve), None);
+        let mut too_big = [0xff; 64];
+        too_big[32..].copy_from_slice(&bytes[32..]);
+        assert_eq!(g1_from_bytes(&too_big), None);
+
+        let g2 = G2Point::generator();
+        let bytes = g2.to_bytes();
+        // the imaginary part of x comes first
+        assert_eq!(
+            hex::encode(&bytes[..32]),
+            "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2"
+        );
+        assert_eq!(G2Point::from_bytes(&bytes), Some(g2));
+        assert_eq!(G2Point::from_bytes(&[0; 128]), Some(G2Point::infinity()));
+        let mut swapped = bytes;
+        swapped[..32].copy_from_slice(&bytes[32..64]);
+        swapped[32..64].copy_from_slice(&bytes[..32]);
+        assert_eq!(G2Point::from_bytes(&swapped), None);
+    }
+
+    #[test]
+    fn test_g2_from() {
+        let g2 = G2Point::generator();
+        let (x, y) = (g2.x().unwrap().clone(), g2.y().unwrap().clone());
+        assert_eq!(G2Point::from(x.clone(), y.clone()), Ok(g2));
+        assert_eq!(G2Point::from(x.clone(), y + Fq2::one()), Err(PointError::NotOnCurve));
+        let small = FieldElement::from(BigInt::from(1), BigInt::from(97)).unwrap();
+        assert_eq!(
+            G2Point::from(x, Fq2::new(small.clone(), small)),
+            Err(PointError::Field(FieldError::FieldMismatch))
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::OnceLock;
+
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::bn254::field_modulus;
+
+/// An element of the base field, reduced from any integer
+pub fn fq(num: impl Into<BigInt>) -> FieldElement {
+    FieldElement::reduce(num.into(), field_modulus()).unwrap()
+}
+
+fn fq_neg(x: FieldElement) -> FieldElement {
+    fq(0) - x
+}
+
+fn fq_is_zero(x: &FieldElement) -> bool {
+    x.num().is_zero()
+}
+
+/// `Fq2 = Fq[u] / (u² + 1)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fq2 {
+    pub c0: FieldElement,
+    pub c1: FieldElement,
+}
+
+impl Fq2 {
+    pub fn new(c0: FieldElement, c1: FieldElement) -> Self {
+        Self { c0, c1 }
+    }
+
+    pub fn zero() -> Self {
+        Self::new(fq(0), fq(0))
+    }
+
+    pub fn one() -> Self {
+        Self::new(fq(1), fq(0))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        fq_is_zero(&self.c0) && fq_is_zero(&self.c1)
+    }
+
+    /// ξ = 9 + u, the non-residue Fq6 and the twist are built on
+    pub fn xi() -> Self {
+        Self::new(fq(9), fq(1))
+    }
+
+    /// The Frobenius map x -> x^p, which negates u
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.c0.clone(), fq_neg(self.c1.clone()))
+    }
+
+    pub fn scale(&self, k: &FieldElement) -> Self {
+        Self::new(self.c0.clone() * k.clone(), self.c1.clone() * k.clone())
+    }
+
+    pub fn square(&self) -> Self {
+        self.clone() * self.clone()
+    }
+
+    /// 1 / (a + bu) = (a - bu) / (a² + b²)
+    pub fn inverse(&self) -> Self {
+        let norm = self.c0.clone().pow(BigInt::from(2)) + self.c1.clone().pow(BigInt::from(2));
+        self.conjugate().scale(&norm.pow(BigInt::from(-1)))
+    }
+
+    pub fn pow(&self, exp: &BigInt) -> Self {
+        pow(self.clone(), exp, Self::one())
+    }
+}
+
+impl Add for Fq2 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.c0 + rhs.c0, self.c1 + rhs.c1)
+    }
+}
+
+impl Sub for Fq2 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.c0 - rhs.c0, self.c1 - rhs.c1)
+    }
+}
+
+impl Neg for Fq2 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(fq_neg(self.c0), fq_neg(self.c1))
+    }
+}
+
+impl Mul for Fq2 {
+    type Output = Self;
+
+    // Karatsuba: three multiplications instead of four
+    fn mul(self, rhs: Self) -> Self::Output {
+        let aa = self.c0.clone() * rhs.c0.clone();
+        let bb = self.c1.clone() * rhs.c1.clone();
+        let cross = (self.c0 + self.c1) * (rhs.c0 + rhs.c1);
+        Self::new(aa.clone() - bb.clone(), cross - aa - bb)
+    }
+}
+
+/// `Fq6 = Fq2[v] / (v³ - ξ)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fq6 {
+    pub c0: Fq2,
+    pub c1: Fq2,
+    pub c2: Fq2,
+}
+
+impl Fq6 {
+    pub fn new(c0: Fq2, c1: Fq2, c2: Fq2) -> Self {
+        Self { c0, c1, c2 }
+    }
+
+    pub fn zero() -> Self {
+        Self::new(Fq2::zero(), Fq2::zero(), Fq2::zero())
+    }
+
+    pub fn one() -> Self {
+        Self::new(Fq2::one(), Fq2::zero(), Fq2::zero())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.c0.is_zero() && self.c1.is_zero() && self.c2.is_zero()
+    }
+
+    /// Multiplication by v, the non-residue Fq12 is built on
+    pub fn mul_by_v(&self) -> Self {
+        Self::new(self.c2.clone() * Fq2::xi(), self.c0.clone(), self.c1.clone())
+    }
+
+    pub fn inverse(&self) -> Self {
+        let (a, b, c) = (self.c0.clone(), self.c1.clone(), self.c2.clone());
+        // the adjugate over the norm, as in "Multiplication and Squaring on
+        // Pairing-Friendly Fields" (Devegili et al.)
+        let t0 = a.square() - b.clone() * c.clone() * Fq2::xi();
+        let t1 = c.square() * Fq2::xi() - a.clone() * b.clone();
+        let t2 = b.square() - a.clone() * c.clone();
+        let norm = a * t0.clone() + (c * t1.clone() + b * t2.clone()) * Fq2::xi();
+        let norm_inv = norm.inverse();
+        Self::new(t0 * norm_inv.clone(), t1 * norm_inv.clone(), t2 * norm_inv)
+    }
+}
+
+impl Add for Fq6 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.c0 + rhs.c0, self.c1 + rhs.c1, self.c2 + rhs.c2)
+    }
+}
+
+impl Sub for Fq6 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.c0 - rhs.c0, self.c1 - rhs.c1, self.c2 - rhs.c2)
+    }
+}
+
+impl Neg for Fq6 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(-self.c0, -self.c1, -self.c2)
+    }
+}
+
+impl Mul for Fq6 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (a0, a1, a2) = (self.c0, self.c1, self.c2);
+        let (b0, b1, b2) = (rhs.c0, rhs.c1, rhs.c2);
+        let (t0, t1, t2) = (
+            a0.clone() * b0.clone(),
+            a1.clone() * b1.clone(),
+            a2.clone() * b2.clone(),
+        );
+        // v³ = ξ folds the high terms back down
+        let c0 =
+            ((a1.clone() + a2.clone()) * (b1.clone() + b2.clone()) - t1.clone() - t2.clone()) * Fq2::xi() + t0.clone();
+        let c1 = (a0.clone() + a1) * (b0.clone() + b1) - t0.clone() - t1.clone() + t2.clone() * Fq2::xi();
+        let c2 = (a0 + a2) * (b0 + b2) - t0 - t2 + t1;
+        Self::new(c0, c1, c2)
+    }
+}
+
+/// `Fq12 = Fq6[w] / (w² - v)`, the field pairings land in. Since w⁶ = ξ,
+/// an element is also Σ x_i wⁱ with x_i in Fq2, taking c0 to the even
+/// powers and c1 to the odd ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fq12 {
+    pub c0: Fq6,
+    pub c1: Fq6,
+}
+
+impl Fq12 {
+    pub fn new(c0: Fq6, c1: Fq6) -> Self {
+        Self { c0, c1 }
+    }
+
+    pub fn one() -> Self {
+        Self::new(Fq6::one(), Fq6::zero())
+    }
+
+    pub fn is_one(&self) -> bool {
+        *self == Self::one()
+    }
+
+    pub fn square(&self) -> Self {
+        self.clone() * self.clone()
+    }
+
+    /// x^(p⁶), which negates w
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.c0.clone(), -self.c1.clone())
+    }
+
+    /// 1 / (a + bw) = (a - bw) / (a² - b² v)
+    pub fn inverse(&self) -> Self {
+        let norm = self.c0.clone() * self.c0.clone() - (self.c1.clone() * self.c1.clone()).mul_by_v();
+        let norm_inv = norm.inverse();
+        Self::new(self.c0.clone() * norm_inv.clone(), -(self.c1.clone() * norm_inv))
+    }
+
+    /// x -> x^p: conjugates each Fq2 coefficient x_i and multiplies it by
+    /// γⁱ, since w^p = ξ^((p-1)/6) w
+    pub fn frobenius(&self) -> Self {
+        static GAMMA: OnceLock<Vec<Fq2>> = OnceLock::new();
+        let gamma = GAMMA.get_or_init(|| {
+            let g = Fq2::xi().pow(&((field_modulus() - 1) / 6));
+            let mut powers = vec![Fq2::one()];
+            for i in 1..6 {
+                powers.push(powers[i - 1].clone() * g.clone());
+            }
+            powers
+        });
+        let map = |x: &Fq2, i: usize| x.conjugate() * gamma[i].clone();
+        Self::new(
+            Fq6::new(map(&self.c0.c0, 0), map(&self.c0.c1, 2), map(&self.c0.c2, 4)),
+            Fq6::new(map(&self.c1.c0, 1), map(&self.c1.c1, 3), map(&self.c1.c2, 5)),
+        )
+    }
+
+    pub fn pow(&self, exp: &BigInt) -> Self {
+        pow(self.clone(), exp, Self::one())
+    }
+}
+
+impl Mul for Fq12 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let t0 = self.c0.clone() * rhs.c0.clone();
+        let t1 = self.c1.clone() * rhs.c1.clone();
+        let cross = (self.c0 + self.c1) * (rhs.c0 + rhs.c1);
+        Self::new(t0.clone() + t1.clone().mul_by_v(), cross - t0 - t1)
+    }
+}
+
+/// Square-and-multiply for non-negative exponents
+fn pow<T: Clone + Mul<Output = T>>(base: T, exp: &BigInt, one: T) -> T {
+    assert!(*exp >= BigInt::zero(), "negative exponent");
+    let mut res = one;
+    for i in (0..exp.bits()).rev() {
+        res = res.clone() * res;
+        if exp.bit(i) {
+            res = res * base.clone();
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod fields_tests {
+    use super::*;
+
+    fn fq2(c0: i64, c1: i64) -> Fq2 {
+        Fq2::new(fq(c0), fq(c1))
+    }
+
+    fn fq12() -> Fq12 {
+        let fq6 = |k: i64| Fq6::new(fq2(k, k + 1), fq2(-k, 3), fq2(7, k * k));
+        Fq12::new(fq6(2), fq6(5))
+    }
+
+    #[test]
+    fn test_fq2() {
+        // u² = -1
+        assert_eq!(fq2(0, 1).square(), fq2(-1, 0));
+        let x = fq2(3, 4);
+        assert_eq!(x.clone() * x.inverse(), Fq2::one());
+        assert_eq!(x.pow(&field_modulus()), x.conjugate());
+        assert_eq!(x.pow(&BigInt::from(3)), x.clone() * x.clone() * x);
+    }
+
+    #[test]
+    fn test_fq6() {
+        // v³ = ξ
+        let v = Fq6::new(Fq2::zero(), Fq2::one(), Fq2::zero());
+        assert_eq!(
+            v.clone() * v.clone() * v.clone(),
+            Fq6::new(Fq2::xi(), Fq2::zero(), Fq2::zero())
+        );
+        assert_eq!(v.mul_by_v(), v.clone() * v);
+        let x = Fq6::new(fq2(1, 2), fq2(3, 4), fq2(5, 6));
+        assert_eq!(x.clone() * x.inverse(), Fq6::one());
+    }
+
+    #[test]
+    fn test_fq12() {
+        let x = fq12();
+        assert!((x.clone() * x.inverse()).is_one());
+        // w² = v
+        let w = Fq12::new(Fq6::zero(), Fq6::one());
+        assert_eq!(
+            w.square(),
+            Fq12::new(Fq6::new(Fq2::zero(), Fq2::one(), Fq2::zero()), Fq6::zero())
+        );
+        let p = field_modulus();
+        assert_eq!(x.frobenius(), x.pow(&p));
+        assert_eq!(x.conjugate(), (0..6).fold(x.clone(), |acc, _| acc.frobenius()));
+    }
+}
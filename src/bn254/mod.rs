@@ -0,0 +1,24 @@
+pub mod curve;
+pub mod fields;
+pub mod pairing;
+
+use num_bigint::BigInt;
+
+/// p, the base field prime of BN254 (alt_bn128, as in Ethereum's
+/// precompiles): G1 is y² = x³ + 3 over Fq, G2 its twist over Fq2
+pub fn field_modulus() -> BigInt {
+    BigInt::parse_bytes(
+        b"21888242871839275222246405745257275088696311157297823662689037894645226208583",
+        10,
+    )
+    .unwrap()
+}
+
+/// r, the prime order of both groups and the scalar field
+pub fn curve_order() -> BigInt {
+    BigInt::parse_bytes(
+        b"21888242871839275222246405745257275088548364400416034343698204186575808495617",
+        10,
+    )
+    .unwrap()
+}
@@ -0,0 +1,128 @@
+use std::sync::OnceLock;
+
+use num_bigint::BigInt;
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::bn254::curve::G2Point;
+use crate::bn254::fields::{fq, Fq12, Fq2, Fq6};
+use crate::bn254::{curve_order, field_modulus};
+use crate::curves::ec_point::Point;
+
+/// 6x + 2 for the BN parameter x = 4965661367192848881
+fn ate_loop_count() -> BigInt {
+    BigInt::parse_bytes(b"29793968203157093288", 10).unwrap()
+}
+
+/// The optimal ate pairing e: G1 x G2 -> Fq12, bilinear and
+/// non-degenerate. Either point at infinity gives 1
+pub fn pairing(p: &Point, q: &G2Point) -> Fq12 {
+    final_exponentiation(&miller_loop(p, q))
+}
+
+/// Whether Π e(p_i, q_i) = 1, sharing one final exponentiation between
+/// all the pairs. Pairing equations are checked in this form
+pub fn pairing_check(pairs: &[(Point, G2Point)]) -> bool {
+    let f = pairs.iter().fold(Fq12::one(), |acc, (p, q)| acc * miller_loop(p, q));
+    final_exponentiation(&f).is_one()
+}
+
+pub fn miller_loop(p: &Point, q: &G2Point) -> Fq12 {
+    let (Some(xp), Some(yp)) = (p.x(), p.y()) else {
+        return Fq12::one();
+    };
+    if q.is_infinity() {
+        return Fq12::one();
+    }
+    let (xp, yp) = (xp.clone(), yp.clone());
+    let n = ate_loop_count();
+    let mut r = q.clone();
+    let mut f = Fq12::one();
+    for i in (0..n.bits() - 1).rev() {
+        f = f.square() * line(&r, &r, &xp, &yp);
+        r = r.clone() + r;
+        if n.bit(i) {
+            f = f * line(&r, q, &xp, &yp);
+            r = r + q.clone();
+        }
+    }
+    // the two extra lines of the optimal ate pairing, through π(Q) and
+    // -π²(Q) where π is the Frobenius map
+    let q1 = frobenius(q);
+    let q2 = -frobenius(&q1);
+    f = f * line(&r, &q1, &xp, &yp);
+    r = r + q1;
+    f * line(&r, &q2, &xp, &yp)
+}
+
+/// The line through T and Q (the tangent if they're equal) evaluated at
+/// P. With the twist's points untwisted to (x w², y w³) the line has a
+/// slope of λw, for λ the slope on the twist, so
+/// l(P) = yP - λ xP w + (λ xT - yT) w³
+fn line(t: &G2Point, q: &G2Point, xp: &FieldElement, yp: &FieldElement) -> Fq12 {
+    let (xt, yt) = (t.x().unwrap().clone(), t.y().unwrap().clone());
+    let (xq, yq) = (q.x().unwrap().clone(), q.y().unwrap().clone());
+    let slope = if xt != xq {
+        (yq - yt.clone()) * (xq - xt.clone()).inverse()
+    } else if yt == yq {
+        xt.square().scale(&fq(3)) * yt.scale(&fq(2)).inverse()
+    } else {
+        // vertical: xP - xT w², which lies in Fq6 and so vanishes in the
+        // final exponentiation, but is the right value all the same
+        return Fq12::new(Fq6::new(Fq2::new(xp.clone(), fq(0)), -xt, Fq2::zero()), Fq6::zero());
+    };
+    Fq12::new(
+        Fq6::new(Fq2::new(yp.clone(), fq(0)), Fq2::zero(), Fq2::zero()),
+        Fq6::new(-slope.scale(xp), slope * xt - yt, Fq2::zero()),
+    )
+}
+
+/// π(x, y) = (x^p, y^p) on the untwisted curve, which on the twist is
+/// (x̄ ξ^((p-1)/3), ȳ ξ^((p-1)/2))
+fn frobenius(q: &G2Point) -> G2Point {
+    static COEFFS: OnceLock<(Fq2, Fq2)> = OnceLock::new();
+    let (cx, cy) = COEFFS.get_or_init(|| {
+        let p = field_modulus();
+        (Fq2::xi().pow(&((&p - 1) / 3)), Fq2::xi().pow(&((&p - 1) / 2)))
+    });
+    match (q.x(), q.y()) {
+        (Some(x), Some(y)) => G2Point::from(x.conjugate() * cx.clone(), y.conjugate() * cy.clone())
+            .expect("the Frobenius map keeps points on the twist"),
+        _ => G2Point::infinity(),
+    }
+}
+
+/// f^((p¹² - 1) / r), splitting the exponent as (p⁶ - 1)(p² + 1) times
+/// (p⁴ - p² + 1) / r. The first factor is cheap by conjugation and
+/// Frobenius; the second is a plain exponentiation
+pub fn final_exponentiation(f: &Fq12) -> Fq12 {
+    let f = f.conjugate() * f.inverse();
+    let f = f.frobenius().frobenius() * f;
+    let p = field_modulus();
+    let hard = (p.pow(4) - p.pow(2) + 1) / curve_order();
+    f.pow(&hard)
+}
+
+#[cfg(test)]
+mod pairing_tests {
+    use super::*;
+    use crate::bn254::curve::{g1_generator, g1_neg};
+
+    #[test]
+    fn test_pairing() {
+        let (g1, g2) = (g1_generator(), G2Point::generator());
+        let e = pairing(&g1, &g2);
+        assert!(!e.is_one());
+        assert!(e.pow(&curve_order()).is_one());
+
+        // bilinearity
+        let (a, b) = (BigInt::from(5), BigInt::from(7));
+        let e_ab = pairing(&(a.clone() * g1.clone()), &(b.clone() * g2.clone()));
+        assert_eq!(e_ab, e.pow(&(&a * &b)));
+        assert_eq!(e_ab, pairing(&((a * b) * g1.clone()), &g2));
+
+        assert!(pairing_check(&[(g1.clone(), g2.clone()), (g1_neg(&g1), g2.clone())]));
+        assert!(pairing_check(&[(g1.clone(), -g2.clone()), (g1.clone(), g2.clone())]));
+        assert!(!pairing_check(&[(g1.clone(), g2.clone()), (g1.clone(), g2.clone())]));
+        assert!(pairing(&crate::bn254::curve::g1_infinity(), &g2).is_one());
+    }
+}
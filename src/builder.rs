@@ -0,0 +1,602 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use crate::curves::secp256k1::ecc::PrivateKey;
+use crate::multisig::Multisig;
+use crate::script::Script;
+use crate::sighash::TapSighashType;
+use crate::sign::{
+    sign_p2pkh_input, sign_p2sh_multisig_input, sign_p2wpkh_input, sign_p2wsh_multisig_input, sign_taproot_keypath,
+    SignError,
+};
+use crate::timelock::{LockTime, Sequence};
+use crate::tx::{OutPoint, Transaction, TxIn, TxOut};
+use crate::wallet::coin_select::{fee, Selection};
+
+/// Change below this is worth less than spending it, so goes to the fee
+pub const DEFAULT_MIN_CHANGE: u64 = 546;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuilderError {
+    NoInputs,
+    NoOutputs,
+    /// The same output is spent twice
+    DuplicateInput(OutPoint),
+    /// Input `index` has a relative lock, which version 1 ignores
+    RelativeLockNeedsVersion2(usize),
+    /// A locktime is set but every input is final, so it would be ignored
+    LockTimeNotEnforced,
+    /// The inputs don't cover the outputs and the fee
+    InsufficientFunds {
+        needed: u64,
+        available: u64,
+    },
+    /// Signing input `index` failed
+    Sign {
+        index: usize,
+        error: SignError,
+    },
+}
+
+impl Display for BuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuilderError::NoInputs => write!(f, "transaction has no inputs"),
+            BuilderError::NoOutputs => write!(f, "transaction has no outputs"),
+            BuilderError::DuplicateInput(outpoint) => {
+                let mut txid = outpoint.txid;
+                txid.reverse();
+                write!(f, "{}:{} is spent twice", hex::encode(txid), outpoint.vout)
+            }
+            BuilderError::RelativeLockNeedsVersion2(index) => {
+                write!(f, "input {} has a relative lock but the version is below 2", index)
+            }
+            BuilderError::LockTimeNotEnforced => write!(f, "locktime is set but every input sequence is final"),
+            BuilderError::InsufficientFunds { needed, available } => {
+                write!(
+                    f,
+                    "insufficient funds: needed {} sat, available {} sat",
+                    needed, available
+                )
+            }
+            BuilderError::Sign { index, error } => write!(f, "signing input {} failed: {}", index, error),
+        }
+    }
+}
+
+impl std::error::Error for BuilderError {}
+
+/// Assembles an unsigned transaction from typed sequences and locktime,
+/// checking on `build` that they mean what they say
+#[derive(Debug, Clone)]
+pub struct TxBuilder {
+    version: u32,
+    inputs: Vec<TxIn>,
+    outputs: Vec<TxOut>,
+    lock_time: LockTime,
+}
+
+impl Default for TxBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TxBuilder {
+    /// Version 2, so relative locks work, and no locktime
+    pub fn new() -> Self {
+        Self {
+            version: 2,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            lock_time: LockTime::ZERO,
+        }
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn add_input(mut self, previous_output: OutPoint, sequence: Sequence) -> Self {
+        self.inputs.push(TxIn {
+            previous_output,
+            script_sig: Script::new(),
+            sequence: sequence.to_consensus(),
+            witness: Vec::new(),
+        });
+        self
+    }
+
+    /// Spends every coin of a coin selection with the same sequence
+    pub fn add_selection(self, selection: &Selection, sequence: Sequence) -> Self {
+        selection
+            .selected
+            .iter()
+            .fold(self, |builder, utxo| builder.add_input(utxo.outpoint, sequence))
+    }
+
+    pub fn add_output(mut self, output: TxOut) -> Self {
+        self.outputs.push(output);
+        self
+    }
+
+    pub fn lock_time(mut self, lock_time: LockTime) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Lets the transaction be replaced by a higher-fee one (BIP-125):
+    /// final inputs, and those only enabling the locktime, signal RBF
+    /// instead. Inputs with relative locks already signal it
+    pub fn enable_rbf(mut self) -> Self {
+        for input in &mut self.inputs {
+            let sequence = Sequence::from_consensus(input.sequence);
+            if !sequence.signals_rbf() {
+                input.sequence = Sequence::ENABLE_RBF_NO_LOCKTIME.to_consensus();
+            }
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<Transaction, BuilderError> {
+        if self.inputs.is_empty() {
+            return Err(BuilderError::NoInputs);
+        }
+        if self.outputs.is_empty() {
+            return Err(BuilderError::NoOutputs);
+        }
+        let mut spent = HashSet::new();
+        for (index, input) in self.inputs.iter().enumerate() {
+            if !spent.insert(input.previous_output) {
+                return Err(BuilderError::DuplicateInput(input.previous_output));
+            }
+            let sequence = Sequence::from_consensus(input.sequence);
+            if self.version < 2 && sequence.relative_lock().is_some() {
+                return Err(BuilderError::RelativeLockNeedsVersion2(index));
+            }
+        }
+        let enforced = self
+            .inputs
+            .iter()
+            .any(|input| Sequence::from_consensus(input.sequence).enables_locktime());
+        if self.lock_time != LockTime::ZERO && !enforced {
+            return Err(BuilderError::LockTimeNotEnforced);
+        }
+        Ok(Transaction {
+            version: self.version,
+            inputs: self.inputs,
+            outputs: self.outputs,
+            locktime: self.lock_time.to_consensus(),
+        })
+    }
+}
+
+/// The key material for an input and the kind of output it spends
+#[derive(Debug, Clone)]
+pub enum InputKey {
+    P2pkh(PrivateKey),
+    P2wpkh(PrivateKey),
+    P2shMultisig(Multisig, Vec<PrivateKey>),
+    P2wshMultisig(Multisig, Vec<PrivateKey>),
+    /// Key-path spend; the merkle root is None for outputs without scripts
+    TaprootKeyPath {
+        internal_key: PrivateKey,
+        merkle_root: Option<[u8; 32]>,
+    },
+}
+
+/// Builds and signs a transaction in one go: inputs come with the outputs
+/// they spend and their keys, and `finalize` works out the fee and change
+/// from the size of the signed transaction
+#[derive(Debug, Clone)]
+pub struct TransactionBuilder {
+    tx: TxBuilder,
+    prevouts: Vec<TxOut>,
+    keys: Vec<InputKey>,
+    /// In sat/vB
+    fee_rate: f64,
+    change_script: Option<Script>,
+    min_change: u64,
+}
+
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransactionBuilder {
+    /// 1 sat/vB and no change: whatever the outputs leave goes to the fee
+    pub fn new() -> Self {
+        Self {
+            tx: TxBuilder::new(),
+            prevouts: Vec::new(),
+            keys: Vec::new(),
+            fee_rate: 1.0,
+            change_script: None,
+            min_change: DEFAULT_MIN_CHANGE,
+        }
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.tx = self.tx.version(version);
+        self
+    }
+
+    pub fn lock_time(mut self, lock_time: LockTime) -> Self {
+        self.tx = self.tx.lock_time(lock_time);
+        self
+    }
+
+    pub fn enable_rbf(mut self) -> Self {
+        self.tx = self.tx.enable_rbf();
+        self
+    }
+
+    pub fn add_input(mut self, previous_output: OutPoint, prevout: TxOut, key: InputKey, sequence: Sequence) -> Self {
+        self.tx = self.tx.add_input(previous_output, sequence);
+        self.prevouts.push(prevout);
+        self.keys.push(key);
+        self
+    }
+
+    pub fn add_output(mut self, output: TxOut) -> Self {
+        self.tx = self.tx.add_output(output);
+        self
+    }
+
+    pub fn fee_rate(mut self, sat_per_vbyte: f64) -> Self {
+        self.fee_rate = sat_per_vbyte;
+        self
+    }
+
+    /// Sends what's left after the outputs and the fee to `script_pubkey`,
+    /// as the last output
+    pub fn change_to(mut self, script_pubkey: Script) -> Self {
+        self.change_script = Some(script_pubkey);
+        self
+    }
+
+    /// Change below `amount` is dropped and goes to the fee
+    pub fn min_change(mut self, amount: u64) -> Self {
+        self.min_change = amount;
+        self
+    }
+
+    fn sign(&self, tx: &mut Transaction) -> Result<(), BuilderError> {
+        for (index, (prevout, key)) in self.prevouts.iter().zip(&self.keys).enumerate() {
+            let signed = match key {
+                InputKey::P2pkh(key) => sign_p2pkh_input(tx, index, prevout, key),
+                InputKey::P2wpkh(key) => sign_p2wpkh_input(tx, index, prevout, key),
+                InputKey::P2shMultisig(multisig, keys) => sign_p2sh_multisig_input(tx, index, prevout, multisig, keys),
+                InputKey::P2wshMultisig(multisig, keys) => {
+                    sign_p2wsh_multisig_input(tx, index, prevout, multisig, keys)
+                }
+                InputKey::TaprootKeyPath {
+                    internal_key,
+                    merkle_root,
+                } => sign_taproot_keypath(
+                    tx,
+                    index,
+                    &self.prevouts,
+                    internal_key,
+                    merkle_root.as_ref(),
+                    TapSighashType::Default,
+                ),
+            };
+            signed.map_err(|error| BuilderError::Sign { index, error })?;
+        }
+        Ok(())
+    }
+
+    /// Signs with change set to `amount`, lowering it until the signed
+    /// transaction pays the fee rate. ECDSA signatures vary by a byte, so
+    /// the size can shift once the amount changes
+    fn sign_with_change(
+        &self,
+        mut tx: Transaction,
+        available: u64,
+        mut amount: u64,
+    ) -> Result<Transaction, BuilderError> {
+        loop {
+            tx.outputs.last_mut().unwrap().amount = amount;
+            self.sign(&mut tx)?;
+            let paid = available - amount;
+            let needed = fee(tx.vsize() as u64, self.fee_rate);
+            if paid >= needed {
+                return Ok(tx);
+            }
+            amount = amount.saturating_sub(needed - paid);
+        }
+    }
+
+    /// Validates, adds change if it is worth keeping and signs every input
+    pub fn finalize(self) -> Result<Transaction, BuilderError> {
+        let available: u64 = self.prevouts.iter().map(|prevout| prevout.amount).sum();
+        let spent: u64 = self.tx.outputs.iter().map(|output| output.amount).sum();
+        let insufficient = |fee: u64| BuilderError::InsufficientFunds {
+            needed: spent + fee,
+            available,
+        };
+        if spent > available {
+            return Err(insufficient(0));
+        }
+
+        if let Some(change_script) = &self.change_script {
+            let mut tx = self
+                .tx
+                .clone()
+                .add_output(TxOut {
+                    amount: available - spent,
+                    script_pubkey: change_script.clone(),
+                })
+                .build()?;
+            self.sign(&mut tx)?;
+            let with_change = fee(tx.vsize() as u64, self.fee_rate);
+            let change = (available - spent).saturating_sub(with_change);
+            if change >= self.min_change {
+                let tx = self.sign_with_change(tx, available - spent, change)?;
+                if tx.outputs.last().unwrap().amount >= self.min_change {
+                    return Ok(tx);
+                }
+            }
+        }
+
+        let mut tx = self.tx.clone().build()?;
+        self.sign(&mut tx)?;
+        let needed = fee(tx.vsize() as u64, self.fee_rate);
+        if available - spent < needed {
+            return Err(insufficient(needed));
+        }
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use super::*;
+    use crate::crypto::hash::hash160;
+    use crate::crypto::schnorr::xonly;
+    use crate::taproot::tweak_public_key;
+    use crate::timelock::RelativeLock;
+    use crate::wallet::coin_select::{largest_first, SelectionParams, Utxo};
+    use num_bigint::BigUint;
+
+    fn outpoint(n: u8) -> OutPoint {
+        OutPoint {
+            txid: [n; 32],
+            vout: n as u32,
+        }
+    }
+
+    fn output() -> TxOut {
+        TxOut {
+            amount: 10_000,
+            script_pubkey: Script::p2wpkh(&[1; 20]),
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let tx = TxBuilder::new()
+            .add_input(outpoint(1), Sequence::ENABLE_LOCKTIME_NO_RBF)
+            .add_input(outpoint(2), RelativeLock::Blocks(144).into())
+            .add_output(output())
+            .lock_time(LockTime::from_height(840_000).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(tx.version, 2);
+        assert_eq!(tx.locktime, 840_000);
+        assert_eq!(tx.inputs[0].sequence, 0xfffffffe);
+        assert_eq!(tx.inputs[1].sequence, 144);
+        assert!(Transaction::from_hex(&tx.to_hex()).is_ok());
+    }
+
+    #[test]
+    fn test_enable_rbf() {
+        let tx = TxBuilder::new()
+            .add_input(outpoint(1), Sequence::MAX)
+            .add_input(outpoint(2), Sequence::ENABLE_LOCKTIME_NO_RBF)
+            .add_input(outpoint(3), RelativeLock::Intervals(10).into())
+            .add_output(output())
+            .enable_rbf()
+            .build()
+            .unwrap();
+        let sequences: Vec<u32> = tx.inputs.iter().map(|input| input.sequence).collect();
+        assert_eq!(sequences, [0xfffffffd, 0xfffffffd, 0x0040000a]);
+        assert!(tx
+            .inputs
+            .iter()
+            .all(|input| Sequence::from_consensus(input.sequence).signals_rbf()));
+    }
+
+    #[test]
+    fn test_from_selection() {
+        let utxos: Vec<Utxo> = (1..=3)
+            .map(|n| Utxo {
+                outpoint: outpoint(n),
+                amount: n as u64 * 10_000,
+                input_vsize: 68,
+            })
+            .collect();
+        let params = SelectionParams {
+            target: 45_000,
+            fee_rate: 1.0,
+            long_term_fee_rate: 1.0,
+            change_output_vsize: 31,
+            change_input_vsize: 68,
+            min_change: 294,
+        };
+        let selection = largest_first(&utxos, &params).unwrap();
+        let tx = TxBuilder::new()
+            .add_selection(&selection, Sequence::MAX)
+            .add_output(output())
+            .build()
+            .unwrap();
+        let spent: Vec<OutPoint> = tx.inputs.iter().map(|input| input.previous_output).collect();
+        assert_eq!(spent, [outpoint(3), outpoint(2)]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let valid = TxBuilder::new()
+            .add_input(outpoint(1), Sequence::MAX)
+            .add_output(output());
+        assert_eq!(
+            TxBuilder::new().add_output(output()).build(),
+            Err(BuilderError::NoInputs)
+        );
+        assert_eq!(
+            TxBuilder::new().add_input(outpoint(1), Sequence::MAX).build(),
+            Err(BuilderError::NoOutputs)
+        );
+        assert_eq!(
+            valid.clone().add_input(outpoint(1), Sequence::MAX).build(),
+            Err(BuilderError::DuplicateInput(outpoint(1)))
+        );
+        assert_eq!(
+            valid
+                .clone()
+                .add_input(outpoint(2), RelativeLock::Blocks(6).into())
+                .version(1)
+                .build(),
+            Err(BuilderError::RelativeLockNeedsVersion2(1))
+        );
+        assert_eq!(
+            valid.clone().lock_time(LockTime::from_height(1).unwrap()).build(),
+            Err(BuilderError::LockTimeNotEnforced)
+        );
+        // signalling RBF enables the locktime too
+        assert!(valid
+            .lock_time(LockTime::from_height(1).unwrap())
+            .enable_rbf()
+            .build()
+            .is_ok());
+    }
+
+    fn key(secret: u32) -> PrivateKey {
+        PrivateKey::from(BigUint::from(secret)).unwrap()
+    }
+
+    fn p2wpkh(key: &PrivateKey) -> Script {
+        Script::p2wpkh(&hash160(&key.public_key().sec(true)))
+    }
+
+    fn fee_paid(tx: &Transaction, prevouts: &[TxOut]) -> u64 {
+        prevouts.iter().map(|prevout| prevout.amount).sum::<u64>()
+            - tx.outputs.iter().map(|output| output.amount).sum::<u64>()
+    }
+
+    #[test]
+    fn test_finalize() {
+        let (segwit, legacy, taproot) = (key(1), key(2), key(3));
+        let (output_key, _) = tweak_public_key(&xonly(&taproot.public_key()), None).unwrap();
+        let prevouts = vec![
+            TxOut {
+                amount: 50_000,
+                script_pubkey: p2wpkh(&segwit),
+            },
+            TxOut {
+                amount: 30_000,
+                script_pubkey: Script::p2pkh(&hash160(&legacy.public_key().sec(true))),
+            },
+            TxOut {
+                amount: 20_000,
+                script_pubkey: Script::p2tr(&output_key),
+            },
+        ];
+        let change_script = p2wpkh(&key(4));
+        let tx = TransactionBuilder::new()
+            .add_input(
+                outpoint(1),
+                prevouts[0].clone(),
+                InputKey::P2wpkh(segwit.clone()),
+                Sequence::MAX,
+            )
+            .add_input(outpoint(2), prevouts[1].clone(), InputKey::P2pkh(legacy), Sequence::MAX)
+            .add_input(
+                outpoint(3),
+                prevouts[2].clone(),
+                InputKey::TaprootKeyPath {
+                    internal_key: taproot,
+                    merkle_root: None,
+                },
+                Sequence::MAX,
+            )
+            .add_output(TxOut {
+                amount: 60_000,
+                script_pubkey: Script::p2wpkh(&[9; 20]),
+            })
+            .fee_rate(5.0)
+            .change_to(change_script.clone())
+            .finalize()
+            .unwrap();
+
+        assert_eq!(tx.outputs.len(), 2);
+        assert_eq!(tx.outputs[1].script_pubkey, change_script);
+        let paid = fee_paid(&tx, &prevouts);
+        assert!(paid >= fee(tx.vsize() as u64, 5.0) && paid <= fee(tx.vsize() as u64 + 2, 5.0));
+        assert_eq!(tx.inputs[0].witness.len(), 2);
+        assert_eq!(tx.inputs[1].script_sig.instructions().count(), 2);
+        assert_eq!(tx.inputs[2].witness[0].len(), 64);
+
+        // the same signature the low-level call gives
+        let mut unsigned = tx.clone();
+        unsigned.inputs[0].witness.clear();
+        sign_p2wpkh_input(&mut unsigned, 0, &prevouts[0], &segwit).unwrap();
+        assert_eq!(unsigned.inputs[0].witness, tx.inputs[0].witness);
+    }
+
+    #[test]
+    fn test_finalize_change_policy() {
+        let signer = key(1);
+        let prevout = TxOut {
+            amount: 10_000,
+            script_pubkey: p2wpkh(&signer),
+        };
+        let builder = |amount: u64| {
+            TransactionBuilder::new()
+                .add_input(
+                    outpoint(1),
+                    prevout.clone(),
+                    InputKey::P2wpkh(signer.clone()),
+                    Sequence::MAX,
+                )
+                .add_output(TxOut {
+                    amount,
+                    script_pubkey: Script::p2wpkh(&[9; 20]),
+                })
+                .fee_rate(2.0)
+                .change_to(p2wpkh(&key(2)))
+        };
+
+        // change that would be dust goes to the fee
+        let tx = builder(9_400).finalize().unwrap();
+        assert_eq!(tx.outputs.len(), 1);
+        assert_eq!(fee_paid(&tx, std::slice::from_ref(&prevout)), 600);
+        assert_eq!(builder(9_400).min_change(100).finalize().unwrap().outputs.len(), 2);
+
+        assert!(matches!(
+            builder(9_900).finalize(),
+            Err(BuilderError::InsufficientFunds { available: 10_000, .. })
+        ));
+        assert_eq!(
+            builder(20_000).finalize(),
+            Err(BuilderError::InsufficientFunds {
+                needed: 20_000,
+                available: 10_000
+            })
+        );
+        assert_eq!(
+            TransactionBuilder::new()
+                .add_input(outpoint(1), prevout.clone(), InputKey::P2wpkh(key(5)), Sequence::MAX)
+                .add_output(output())
+                .finalize(),
+            Err(BuilderError::Sign {
+                index: 0,
+                error: SignError::KeyMismatch
+            })
+        );
+    }
+}
@@ -0,0 +1,605 @@
+use std::fmt::Display;
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::hash::tagged_hash;
+use crate::crypto::rng::random_scalar;
+use crate::crypto::schnorr;
+use crate::crypto::transcript::Transcript;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::ecc::{generator, inverse_mod_order, multi_scalar_mul, order, PublicKey};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeProofError {
+    /// Ranges are 8, 16, 32 or 64 bits
+    InvalidBitSize(usize),
+    /// The number of values aggregated must be a power of two
+    InvalidAggregation(usize),
+    /// Each value needs exactly one blinding factor
+    BlindingMismatch {
+        values: usize,
+        blindings: usize,
+    },
+    ValueOutOfRange(u64),
+    VerificationFailed,
+}
+
+impl Display for RangeProofError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RangeProofError::InvalidBitSize(n) => write!(f, "invalid range size {} bits", n),
+            RangeProofError::InvalidAggregation(m) => write!(f, "cannot aggregate {} values", m),
+            RangeProofError::BlindingMismatch { values, blindings } => {
+                write!(f, "{} values but {} blinding factors", values, blindings)
+            }
+            RangeProofError::ValueOutOfRange(v) => write!(f, "value {} out of range", v),
+            RangeProofError::VerificationFailed => write!(f, "range proof verification failed"),
+        }
+    }
+}
+
+impl std::error::Error for RangeProofError {}
+
+/// Generators nobody knows discrete logs between: hashes of a label and an
+/// index, incremented until they land on the curve
+pub fn hash_to_point(label: &str, index: u32) -> Point {
+    let mut data = label.as_bytes().to_vec();
+    data.extend_from_slice(&index.to_be_bytes());
+    let mut counter = 0_u32;
+    loop {
+        let mut attempt = data.clone();
+        attempt.extend_from_slice(&counter.to_be_bytes());
+        if let Ok(key) = schnorr::lift_x(&tagged_hash("moneda/bulletproofs", &attempt)) {
+            return key.point().clone();
+        }
+        counter += 1;
+    }
+}
+
+/// The bases of Pedersen commitments v B + γ B̃
+#[derive(Debug, Clone, PartialEq)]
+pub struct PedersenGens {
+    pub b: Point,
+    pub b_blinding: Point,
+}
+
+impl PedersenGens {
+    pub fn new() -> Self {
+        Self {
+            b: generator(),
+            b_blinding: hash_to_point("blinding", 0),
+        }
+    }
+
+    pub fn commit(&self, value: &BigUint, blinding: &BigUint) -> Point {
+        multi_scalar_mul(
+            &[value.clone(), blinding.clone()],
+            &[self.b.clone(), self.b_blinding.clone()],
+        )
+    }
+}
+
+impl Default for PedersenGens {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The vectors of bases G and H the inner-product argument commits to
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulletproofGens {
+    pub g: Vec<Point>,
+    pub h: Vec<Point>,
+}
+
+impl BulletproofGens {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            g: (0..capacity as u32).map(|i| hash_to_point("G", i)).collect(),
+            h: (0..capacity as u32).map(|i| hash_to_point("H", i)).collect(),
+        }
+    }
+}
+
+/// A proof that P = <a, G> + <b, H> + <a, b> Q, in 2 log n points and two
+/// scalars. Each round halves the vectors, committing to the cross terms
+/// in L and R before a challenge u folds them together
+#[derive(Debug, Clone, PartialEq)]
+pub struct InnerProductProof {
+    pub l: Vec<PublicKey>,
+    pub r: Vec<PublicKey>,
+    pub a: BigUint,
+    pub b: BigUint,
+}
+
+impl InnerProductProof {
+    /// The length of the vectors must be a power of two
+    pub fn prove(
+        transcript: &mut Transcript,
+        q: &Point,
+        mut g: Vec<Point>,
+        mut h: Vec<Point>,
+        mut a: Vec<BigUint>,
+        mut b: Vec<BigUint>,
+    ) -> Self {
+        let n = order();
+        assert!(a.len().is_power_of_two(), "vector length must be a power of two");
+        assert!(
+            g.len() == a.len() && h.len() == a.len() && b.len() == a.len(),
+            "vector lengths differ"
+        );
+        transcript.append("ipa n", &(a.len() as u64).to_be_bytes());
+
+        let (mut ls, mut rs) = (Vec::new(), Vec::new());
+        while a.len() > 1 {
+            let half = a.len() / 2;
+            let (a_lo, a_hi) = a.split_at(half);
+            let (b_lo, b_hi) = b.split_at(half);
+            let (g_lo, g_hi) = g.split_at(half);
+            let (h_lo, h_hi) = h.split_at(half);
+
+            // L = <a_lo, G_hi> + <b_hi, H_lo> + <a_lo, b_hi> Q, R the mirror
+            let cross = |a: &[BigUint], g: &[Point], b: &[BigUint], h: &[Point]| {
+                let scalars: Vec<BigUint> = a.iter().chain(b).cloned().chain([inner_product(a, b)]).collect();
+                let points: Vec<Point> = g.iter().chain(h).cloned().chain([q.clone()]).collect();
+                PublicKey::from(multi_scalar_mul(&scalars, &points)).expect("commitment is not the identity")
+            };
+            let l = cross(a_lo, g_hi, b_hi, h_lo);
+            let r = cross(a_hi, g_lo, b_lo, h_hi);
+            transcript.append_point("L", &l);
+            transcript.append_point("R", &r);
+            let u = transcript.challenge_scalar("u");
+            let u_inv = inverse_mod_order(&u);
+
+            let fold = |lo: &[BigUint], hi: &[BigUint], x: &BigUint, y: &BigUint| -> Vec<BigUint> {
+                lo.iter().zip(hi).map(|(lo, hi)| (lo * x + hi * y) % &n).collect()
+            };
+            let fold_points = |lo: &[Point], hi: &[Point], x: &BigUint, y: &BigUint| -> Vec<Point> {
+                lo.iter()
+                    .zip(hi)
+                    .map(|(lo, hi)| multi_scalar_mul(&[x.clone(), y.clone()], &[lo.clone(), hi.clone()]))
+                    .collect()
+            };
+            let (a_next, b_next) = (fold(a_lo, a_hi, &u, &u_inv), fold(b_lo, b_hi, &u_inv, &u));
+            let (g_next, h_next) = (fold_points(g_lo, g_hi, &u_inv, &u), fold_points(h_lo, h_hi, &u, &u_inv));
+            (a, b, g, h) = (a_next, b_next, g_next, h_next);
+            ls.push(l);
+            rs.push(r);
+        }
+        Self {
+            l: ls,
+            r: rs,
+            a: a.remove(0),
+            b: b.remove(0),
+        }
+    }
+
+    /// Checks the proof against P, with the H bases scaled by `h_factors`
+    /// (all ones for plain H). Unrolling the folds, the final bases are
+    /// <s, G> and <s^-1, H> where s_i multiplies u_j or u_j^-1 by the bits
+    /// of i, so the whole check is one multi-scalar multiplication
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        q: &Point,
+        p: &Point,
+        g: &[Point],
+        h: &[Point],
+        h_factors: &[BigUint],
+    ) -> bool {
+        let n = order();
+        let size = g.len();
+        if !size.is_power_of_two()
+            || h.len() != size
+            || h_factors.len() != size
+            || self.l.len() != size.trailing_zeros() as usize
+            || self.r.len() != self.l.len()
+            || self.a >= n
+            || self.b >= n
+        {
+            return false;
+        }
+        transcript.append("ipa n", &(size as u64).to_be_bytes());
+        let mut challenges = Vec::with_capacity(self.l.len());
+        for (l, r) in self.l.iter().zip(&self.r) {
+            transcript.append_point("L", l);
+            transcript.append_point("R", r);
+            challenges.push(transcript.challenge_scalar("u"));
+        }
+        let inverses: Vec<BigUint> = challenges.iter().map(inverse_mod_order).collect();
+
+        // the first challenge folds the top bit of the index
+        let rounds = challenges.len();
+        let s: Vec<BigUint> = (0..size)
+            .map(|i| {
+                (0..rounds).fold(BigUint::one(), |acc, j| {
+                    let bit = (i >> (rounds - 1 - j)) & 1 == 1;
+                    acc * if bit { &challenges[j] } else { &inverses[j] } % &n
+                })
+            })
+            .collect();
+
+        // a <s, G> + b <s^-1, H'> + ab Q - P - Σ (u² L + u^-2 R) = 0
+        let mut scalars: Vec<BigUint> = s.iter().map(|s| &self.a * s % &n).collect();
+        scalars.extend(
+            s.iter()
+                .zip(h_factors)
+                .map(|(s, f)| &self.b * inverse_mod_order(s) % &n * f % &n),
+        );
+        scalars.push(&self.a * &self.b % &n);
+        scalars.push(&n - 1_u8);
+        let mut points: Vec<Point> = g.iter().chain(h).cloned().chain([q.clone(), p.clone()]).collect();
+        for ((l, r), (u, u_inv)) in self.l.iter().zip(&self.r).zip(challenges.iter().zip(&inverses)) {
+            scalars.push(negate(&(u * u % &n)));
+            scalars.push(negate(&(u_inv * u_inv % &n)));
+            points.push(l.point().clone());
+            points.push(r.point().clone());
+        }
+        multi_scalar_mul(&scalars, &points).is_infinity()
+    }
+}
+
+/// An aggregated proof that each of m committed values lies in [0, 2^n),
+/// in 2 log(nm) + 4 points and five scalars. The bits a_L of the values
+/// and a_R = a_L - 1 are committed in A and blinded by S; the verifier's
+/// challenges collapse every constraint into one inner product t̂ = <l, r>,
+/// which the inner-product argument then proves
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeProof {
+    pub a: PublicKey,
+    pub s: PublicKey,
+    pub t1: PublicKey,
+    pub t2: PublicKey,
+    pub t_hat: BigUint,
+    pub tau_x: BigUint,
+    pub mu: BigUint,
+    pub ipp: InnerProductProof,
+}
+
+impl RangeProof {
+    /// Proves each value is a `bits`-bit number, returning the proof and
+    /// the commitments v B + γ B̃ it is checked against
+    pub fn prove(
+        transcript: &mut Transcript,
+        values: &[u64],
+        blindings: &[BigUint],
+        bits: usize,
+    ) -> Result<(Self, Vec<PublicKey>), RangeProofError> {
+        Self::prove_with_rng(transcript, values, blindings, bits, &mut OsRng)
+    }
+
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        values: &[u64],
+        blindings: &[BigUint],
+        bits: usize,
+        rng: &mut R,
+    ) -> Result<(Self, Vec<PublicKey>), RangeProofError> {
+        check_sizes(bits, values.len())?;
+        if blindings.len() != values.len() {
+            return Err(RangeProofError::BlindingMismatch {
+                values: values.len(),
+                blindings: blindings.len(),
+            });
+        }
+        if let Some(&v) = values.iter().find(|&&v| bits < 64 && v >> bits != 0) {
+            return Err(RangeProofError::ValueOutOfRange(v));
+        }
+        let n = order();
+        let size = bits * values.len();
+        let (bp_gens, pc_gens) = (BulletproofGens::new(size), PedersenGens::new());
+
+        let commitments: Vec<PublicKey> = values
+            .iter()
+            .zip(blindings)
+            .map(|(&v, gamma)| {
+                PublicKey::from(pc_gens.commit(&BigUint::from(v), gamma)).expect("commitment is not the identity")
+            })
+            .collect();
+
+        // a_L holds the bits, a_R = a_L - 1, so a_L ∘ a_R = 0
+        let a_l: Vec<BigUint> = (0..size)
+            .map(|i| BigUint::from((values[i / bits] >> (i % bits)) & 1))
+            .collect();
+        let a_r: Vec<BigUint> = a_l.iter().map(|bit| (bit + &n - 1_u8) % &n).collect();
+        let (alpha, rho) = (random_scalar(rng), random_scalar(rng));
+        let s_l: Vec<BigUint> = (0..size).map(|_| random_scalar(rng)).collect();
+        let s_r: Vec<BigUint> = (0..size).map(|_| random_scalar(rng)).collect();
+        let vector_commit = |blinding: &BigUint, l: &[BigUint], r: &[BigUint]| {
+            let scalars: Vec<BigUint> = [blinding.clone()]
+                .into_iter()
+                .chain(l.iter().cloned())
+                .chain(r.iter().cloned())
+                .collect();
+            let points: Vec<Point> = [pc_gens.b_blinding.clone()]
+                .into_iter()
+                .chain(bp_gens.g.iter().cloned())
+                .chain(bp_gens.h.iter().cloned())
+                .collect();
+            PublicKey::from(multi_scalar_mul(&scalars, &points)).expect("commitment is not the identity")
+        };
+        let a = vector_commit(&alpha, &a_l, &a_r);
+        let s = vector_commit(&rho, &s_l, &s_r);
+
+        append_sizes(transcript, bits, &commitments);
+        transcript.append_point("A", &a);
+        transcript.append_point("S", &s);
+        let y = transcript.challenge_scalar("y");
+        let z = transcript.challenge_scalar("z");
+        let y_powers = powers(&y, size);
+        let z_powers = powers(&z, values.len() + 3);
+        let two_powers = powers(&BigUint::from(2_u8), bits);
+
+        // l(X) = l0 + l1 X, r(X) = r0 + r1 X with
+        // l0 = a_L - z, r0 = yⁱ (a_R + z) + z^(2+j) 2^(i mod n)
+        let l0: Vec<BigUint> = a_l.iter().map(|bit| (bit + &n - &z) % &n).collect();
+        let r0: Vec<BigUint> = (0..size)
+            .map(|i| (&y_powers[i] * (&a_r[i] + &z) + &z_powers[2 + i / bits] * &two_powers[i % bits]) % &n)
+            .collect();
+        let r1: Vec<BigUint> = (0..size).map(|i| &y_powers[i] * &s_r[i] % &n).collect();
+        let t1 = (inner_product(&l0, &r1) + inner_product(&s_l, &r0)) % &n;
+        let t2 = inner_product(&s_l, &r1);
+
+        let (tau1, tau2) = (random_scalar(rng), random_scalar(rng));
+        let t1_point = PublicKey::from(pc_gens.commit(&t1, &tau1)).expect("commitment is not the identity");
+        let t2_point = PublicKey::from(pc_gens.commit(&t2, &tau2)).expect("commitment is not the identity");
+        transcript.append_point("T1", &t1_point);
+        transcript.append_point("T2", &t2_point);
+        let x = transcript.challenge_scalar("x");
+
+        let l: Vec<BigUint> = l0.iter().zip(&s_l).map(|(l0, l1)| (l0 + l1 * &x) % &n).collect();
+        let r: Vec<BigUint> = r0.iter().zip(&r1).map(|(r0, r1)| (r0 + r1 * &x) % &n).collect();
+        let t_hat = inner_product(&l, &r);
+        let blinding_sum = blindings
+            .iter()
+            .enumerate()
+            .fold(BigUint::zero(), |acc, (j, gamma)| acc + &z_powers[2 + j] * gamma);
+        let tau_x = (&tau2 * &x * &x + &tau1 * &x + blinding_sum) % &n;
+        let mu = (&alpha + &rho * &x) % &n;
+        transcript.append_scalar("t_hat", &t_hat);
+        transcript.append_scalar("tau_x", &tau_x);
+        transcript.append_scalar("mu", &mu);
+        let w = transcript.challenge_scalar("w");
+
+        // the argument runs over H'_i = y^-i H_i, so that <l, r> is the
+        // plain inner product
+        let q = BigInt::from(w) * pc_gens.b.clone();
+        let y_inv_powers = powers(&inverse_mod_order(&y), size);
+        let h_prime: Vec<Point> = bp_gens
+            .h
+            .iter()
+            .zip(&y_inv_powers)
+            .map(|(h, y_inv)| BigInt::from(y_inv.clone()) * h.clone())
+            .collect();
+        let ipp = InnerProductProof::prove(transcript, &q, bp_gens.g, h_prime, l, r);
+
+        let proof = Self {
+            a,
+            s,
+            t1: t1_point,
+            t2: t2_point,
+            t_hat,
+            tau_x,
+            mu,
+            ipp,
+        };
+        Ok((proof, commitments))
+    }
+
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        commitments: &[PublicKey],
+        bits: usize,
+    ) -> Result<(), RangeProofError> {
+        check_sizes(bits, commitments.len())?;
+        let n = order();
+        if self.t_hat >= n || self.tau_x >= n || self.mu >= n {
+            return Err(RangeProofError::VerificationFailed);
+        }
+        let size = bits * commitments.len();
+        let (bp_gens, pc_gens) = (BulletproofGens::new(size), PedersenGens::new());
+
+        append_sizes(transcript, bits, commitments);
+        transcript.append_point("A", &self.a);
+        transcript.append_point("S", &self.s);
+        let y = transcript.challenge_scalar("y");
+        let z = transcript.challenge_scalar("z");
+        transcript.append_point("T1", &self.t1);
+        transcript.append_point("T2", &self.t2);
+        let x = transcript.challenge_scalar("x");
+        transcript.append_scalar("t_hat", &self.t_hat);
+        transcript.append_scalar("tau_x", &self.tau_x);
+        transcript.append_scalar("mu", &self.mu);
+        let w = transcript.challenge_scalar("w");
+
+        let y_powers = powers(&y, size);
+        let z_powers = powers(&z, commitments.len() + 3);
+        let two_powers = powers(&BigUint::from(2_u8), bits);
+        let x_sq = &x * &x % &n;
+
+        // t̂ B + τx B̃ = Σ z^(2+j) V_j + δ B + x T1 + x² T2 with
+        // δ = (z - z²) <1, yᴺ> - Σ z^(3+j) <1, 2ⁿ>
+        let sum_y = y_powers.iter().fold(BigUint::zero(), |acc, y| acc + y) % &n;
+        let sum_two = (BigUint::one() << bits) - 1_u8;
+        let sum_z = z_powers[3..].iter().fold(BigUint::zero(), |acc, z| acc + z) % &n;
+        let delta = ((&z + &n - &z_powers[2]) * sum_y % &n + &n - sum_z * sum_two % &n) % &n;
+        let mut scalars = vec![
+            (&self.t_hat + &n - delta) % &n,
+            self.tau_x.clone(),
+            negate(&x),
+            negate(&x_sq),
+        ];
+        let mut points = vec![
+            pc_gens.b.clone(),
+            pc_gens.b_blinding.clone(),
+            self.t1.point().clone(),
+            self.t2.point().clone(),
+        ];
+        for (j, v) in commitments.iter().enumerate() {
+            scalars.push(negate(&z_powers[2 + j]));
+            points.push(v.point().clone());
+        }
+        if !multi_scalar_mul(&scalars, &points).is_infinity() {
+            return Err(RangeProofError::VerificationFailed);
+        }
+
+        // P = A + x S - z <1, G> + <z yⁱ + z^(2+j) 2^(i mod n), H'> - μ B̃ + t̂ Q,
+        // written over the unscaled H so H'_i = y^-i H_i folds into the scalar
+        let y_inv_powers = powers(&inverse_mod_order(&y), size);
+        let mut scalars = vec![BigUint::one(), x.clone(), negate(&self.mu), &w * &self.t_hat % &n];
+        let mut points = vec![
+            self.a.point().clone(),
+            self.s.point().clone(),
+            pc_gens.b_blinding.clone(),
+            pc_gens.b.clone(),
+        ];
+        scalars.extend((0..size).map(|_| negate(&z)));
+        points.extend(bp_gens.g.iter().cloned());
+        scalars
+            .extend((0..size).map(|i| (&z + &z_powers[2 + i / bits] * &two_powers[i % bits] * &y_inv_powers[i]) % &n));
+        points.extend(bp_gens.h.iter().cloned());
+        let p = multi_scalar_mul(&scalars, &points);
+
+        let q = BigInt::from(w) * pc_gens.b;
+        if self
+            .ipp
+            .verify(transcript, &q, &p, &bp_gens.g, &bp_gens.h, &y_inv_powers)
+        {
+            Ok(())
+        } else {
+            Err(RangeProofError::VerificationFailed)
+        }
+    }
+}
+
+fn check_sizes(bits: usize, values: usize) -> Result<(), RangeProofError> {
+    if ![8, 16, 32, 64].contains(&bits) {
+        return Err(RangeProofError::InvalidBitSize(bits));
+    }
+    if !values.is_power_of_two() {
+        return Err(RangeProofError::InvalidAggregation(values));
+    }
+    Ok(())
+}
+
+fn append_sizes(transcript: &mut Transcript, bits: usize, commitments: &[PublicKey]) {
+    transcript.append("n", &(bits as u64).to_be_bytes());
+    transcript.append("m", &(commitments.len() as u64).to_be_bytes());
+    for v in commitments {
+        transcript.append_point("V", v);
+    }
+}
+
+fn inner_product(a: &[BigUint], b: &[BigUint]) -> BigUint {
+    a.iter().zip(b).fold(BigUint::zero(), |acc, (a, b)| acc + a * b) % order()
+}
+
+/// 1, x, x², ..., x^(count-1)
+fn powers(x: &BigUint, count: usize) -> Vec<BigUint> {
+    let n = order();
+    let mut res = Vec::with_capacity(count);
+    let mut acc = BigUint::one();
+    for _ in 0..count {
+        res.push(acc.clone());
+        acc = acc * x % &n;
+    }
+    res
+}
+
+fn negate(k: &BigUint) -> BigUint {
+    let n = order();
+    (&n - k % &n) % &n
+}
+
+#[cfg(test)]
+mod bulletproofs_tests {
+    use super::*;
+
+    #[test]
+    fn test_inner_product_proof() {
+        let gens = BulletproofGens::new(4);
+        let q = hash_to_point("Q", 0);
+        let a: Vec<BigUint> = [1_u8, 2, 3, 4].iter().map(|&v| BigUint::from(v)).collect();
+        let b: Vec<BigUint> = [5_u8, 6, 7, 8].iter().map(|&v| BigUint::from(v)).collect();
+        // P = <a, G> + <b, H> + <a, b> Q
+        let scalars: Vec<BigUint> = a.iter().chain(&b).cloned().chain([BigUint::from(70_u8)]).collect();
+        let points: Vec<Point> = gens.g.iter().chain(&gens.h).cloned().chain([q.clone()]).collect();
+        let p = multi_scalar_mul(&scalars, &points);
+
+        let proof = InnerProductProof::prove(&mut Transcript::new("test"), &q, gens.g.clone(), gens.h.clone(), a, b);
+        assert_eq!(proof.l.len(), 2);
+        let ones = vec![BigUint::one(); 4];
+        assert!(proof.verify(&mut Transcript::new("test"), &q, &p, &gens.g, &gens.h, &ones));
+        assert!(!proof.verify(
+            &mut Transcript::new("test"),
+            &q,
+            &(p.clone() + q.clone()),
+            &gens.g,
+            &gens.h,
+            &ones
+        ));
+        assert!(!proof.verify(&mut Transcript::new("other"), &q, &p, &gens.g, &gens.h, &ones));
+        let tampered = InnerProductProof {
+            a: &proof.a + 1_u8,
+            ..proof.clone()
+        };
+        assert!(!tampered.verify(&mut Transcript::new("test"), &q, &p, &gens.g, &gens.h, &ones));
+    }
+
+    #[test]
+    fn test_range_proof() {
+        let blindings = [BigUint::from(7_u8), BigUint::from(11_u8)];
+        let (proof, commitments) = RangeProof::prove(&mut Transcript::new("test"), &[0, 255], &blindings, 8).unwrap();
+        assert_eq!(
+            commitments[1].point(),
+            &PedersenGens::new().commit(&BigUint::from(255_u8), &blindings[1])
+        );
+        assert_eq!(proof.ipp.l.len(), 4);
+        assert_eq!(proof.verify(&mut Transcript::new("test"), &commitments, 8), Ok(()));
+
+        let fail = Err(RangeProofError::VerificationFailed);
+        let swapped = vec![commitments[1].clone(), commitments[0].clone()];
+        assert_eq!(proof.verify(&mut Transcript::new("test"), &swapped, 8), fail);
+        assert_eq!(proof.verify(&mut Transcript::new("other"), &commitments, 8), fail);
+        let tampered = RangeProof {
+            t_hat: &proof.t_hat + 1_u8,
+            ..proof.clone()
+        };
+        assert_eq!(tampered.verify(&mut Transcript::new("test"), &commitments, 8), fail);
+        // a commitment to 256 = 255 + 1 under the same blinding
+        let shifted = PublicKey::from(commitments[1].point().clone() + generator()).unwrap();
+        assert_eq!(
+            proof.verify(&mut Transcript::new("test"), &[commitments[0].clone(), shifted], 8),
+            fail
+        );
+        assert_eq!(proof.verify(&mut Transcript::new("test"), &commitments, 16), fail);
+
+        let prove = |values: &[u64], bits| {
+            RangeProof::prove(&mut Transcript::new("test"), values, &blindings[..values.len()], bits)
+        };
+        assert_eq!(prove(&[256, 1], 8).unwrap_err(), RangeProofError::ValueOutOfRange(256));
+        assert_eq!(prove(&[1, 1], 12).unwrap_err(), RangeProofError::InvalidBitSize(12));
+        assert_eq!(
+            RangeProof::prove(
+                &mut Transcript::new("test"),
+                &[1, 2, 3],
+                &[BigUint::one(), BigUint::one(), BigUint::one()],
+                8
+            )
+            .unwrap_err(),
+            RangeProofError::InvalidAggregation(3)
+        );
+    }
+
+    #[test]
+    fn test_range_proof_64() {
+        let blinding = [BigUint::from(0xdead_beef_u32)];
+        let (proof, commitments) = RangeProof::prove(&mut Transcript::new("test"), &[u64::MAX], &blinding, 64).unwrap();
+        assert_eq!(proof.ipp.l.len(), 6);
+        assert_eq!(proof.verify(&mut Transcript::new("test"), &commitments, 64), Ok(()));
+    }
+}
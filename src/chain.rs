@@ -0,0 +1,452 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+
+use crate::address::Network;
+use crate::block::{Block, BlockHeader};
+use crate::coinbase::{COINBASE_MATURITY, HALVING_INTERVAL};
+use crate::pow::{self, RETARGET_INTERVAL, TARGET_TIMESPAN};
+use crate::tx::{OutPoint, TxOut};
+
+/// The consensus and p2p constants that differ between networks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Params {
+    pub network: Network,
+    pub genesis: BlockHeader,
+    /// Compact form of the easiest target a block may have
+    pub pow_limit_bits: u32,
+    pub retarget_interval: u32,
+    pub target_timespan: u32,
+    /// Testnet lets a block use the easiest target once 20 minutes pass
+    /// without one
+    pub allow_min_difficulty_blocks: bool,
+    /// Regtest keeps the genesis target forever
+    pub no_retargeting: bool,
+    pub halving_interval: u32,
+    /// Heights from which BIP-34 (height in coinbase), BIP-65 (CLTV),
+    /// BIP-66 (strict DER), BIP-68/112/113 (CSV) and BIP-141 (segwit) are
+    /// enforced
+    pub bip34_height: u32,
+    pub bip65_height: u32,
+    pub bip66_height: u32,
+    pub csv_height: u32,
+    pub segwit_height: u32,
+    /// None where taproot activated by version bits signalling rather
+    /// than from a fixed height
+    pub taproot_height: Option<u32>,
+    pub magic: [u8; 4],
+    pub default_port: u16,
+}
+
+impl Params {
+    /// Bitcoin Core's chain parameters for `network`
+    pub fn new(network: Network) -> Self {
+        let (bip34_height, bip65_height, bip66_height, csv_height, segwit_height, taproot_height) = match network {
+            Network::Mainnet => (227_931, 388_381, 363_725, 419_328, 481_824, Some(709_632)),
+            Network::Testnet => (21_111, 581_885, 330_776, 770_112, 834_624, None),
+            Network::Signet => (1, 1, 1, 1, 1, Some(0)),
+            Network::Regtest => (1, 1, 1, 1, 0, Some(0)),
+        };
+        Self {
+            network,
+            genesis: BlockHeader::genesis(network),
+            pow_limit_bits: network.pow_limit_bits(),
+            retarget_interval: RETARGET_INTERVAL,
+            target_timespan: TARGET_TIMESPAN,
+            allow_min_difficulty_blocks: matches!(network, Network::Testnet | Network::Regtest),
+            no_retargeting: network == Network::Regtest,
+            halving_interval: match network {
+                Network::Regtest => 150,
+                _ => HALVING_INTERVAL,
+            },
+            bip34_height,
+            bip65_height,
+            bip66_height,
+            csv_height,
+            segwit_height,
+            taproot_height,
+            magic: network.magic(),
+            default_port: network.default_port(),
+        }
+    }
+
+    pub fn pow_limit(&self) -> BigUint {
+        pow::bits_to_target(self.pow_limit_bits).unwrap()
+    }
+
+    /// Average seconds between blocks the retargeting aims for
+    pub fn target_spacing(&self) -> u32 {
+        self.target_timespan / self.retarget_interval
+    }
+
+    pub fn segwit_active(&self, height: u32) -> bool {
+        height >= self.segwit_height
+    }
+
+    pub fn taproot_active(&self, height: u32) -> bool {
+        self.taproot_height.is_some_and(|activation| height >= activation)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UtxoError {
+    /// An input spends an output that doesn't exist or is already spent
+    MissingInput(OutPoint),
+    /// An output would overwrite an unspent one with the same outpoint
+    /// (BIP-30)
+    DuplicateOutput(OutPoint),
+    /// A coinbase output spent before COINBASE_MATURITY confirmations
+    ImmatureCoinbase(OutPoint),
+    /// The undo data doesn't belong to the block or the set isn't at it
+    UndoMismatch(OutPoint),
+}
+
+impl Display for UtxoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (message, outpoint) = match self {
+            UtxoError::MissingInput(outpoint) => ("missing or spent input", outpoint),
+            UtxoError::DuplicateOutput(outpoint) => ("duplicate output", outpoint),
+            UtxoError::ImmatureCoinbase(outpoint) => ("immature coinbase spend", outpoint),
+            UtxoError::UndoMismatch(outpoint) => ("undo data does not match at", outpoint),
+        };
+        let mut txid = outpoint.txid;
+        txid.reverse();
+        write!(f, "{} {}:{}", message, hex::encode(txid), outpoint.vout)
+    }
+}
+
+impl std::error::Error for UtxoError {}
+
+/// An unspent output with what validation needs to know about its origin
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coin {
+    pub output: TxOut,
+    /// Height of the block that created it
+    pub height: u32,
+    pub is_coinbase: bool,
+}
+
+/// The coins a block spent, in the order it spent them, so the block can
+/// be disconnected again. Blocks only reference outputs, so once spent the
+/// outputs are gone without this
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockUndo {
+    pub spent: Vec<(OutPoint, Coin)>,
+}
+
+/// The set of unspent transaction outputs at some chain tip
+#[derive(Debug, Clone, Default)]
+pub struct UtxoSet {
+    coins: HashMap<OutPoint, Coin>,
+}
+
+impl UtxoSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.coins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&Coin> {
+        self.coins.get(outpoint)
+    }
+
+    pub fn contains(&self, outpoint: &OutPoint) -> bool {
+        self.coins.contains_key(outpoint)
+    }
+
+    /// Sum of all unspent amounts
+    pub fn total_amount(&self) -> u64 {
+        self.coins.values().map(|coin| coin.output.amount).sum()
+    }
+
+    /// Connects the block at `height`: each transaction in turn spends its
+    /// inputs and adds its outputs, so later transactions may spend earlier
+    /// ones of the same block. Provably unspendable OP_RETURN outputs are
+    /// never added. On error the set is left as it was
+    pub fn apply_block(&mut self, block: &Block, height: u32) -> Result<BlockUndo, UtxoError> {
+        let mut undo = BlockUndo::default();
+        let mut added = Vec::new();
+        let res = self.apply_transactions(block, height, &mut undo, &mut added);
+        if let Err(e) = res {
+            // restore first: outputs both added and spent are then removed
+            self.coins.extend(undo.spent);
+            for outpoint in added {
+                self.coins.remove(&outpoint);
+            }
+            return Err(e);
+        }
+        Ok(undo)
+    }
+
+    fn apply_transactions(
+        &mut self,
+        block: &Block,
+        height: u32,
+        undo: &mut BlockUndo,
+        added: &mut Vec<OutPoint>,
+    ) -> Result<(), UtxoError> {
+        for tx in &block.txdata {
+            let is_coinbase = tx.is_coinbase();
+            if !is_coinbase {
+                for input in &tx.inputs {
+                    let outpoint = input.previous_output;
+                    let coin = self.coins.get(&outpoint).ok_or(UtxoError::MissingInput(outpoint))?;
+                    if coin.is_coinbase && height.saturating_sub(coin.height) < COINBASE_MATURITY {
+                        return Err(UtxoError::ImmatureCoinbase(outpoint));
+                    }
+                    let coin = self.coins.remove(&outpoint).unwrap();
+                    undo.spent.push((outpoint, coin));
+                }
+            }
+            let txid = tx.txid();
+            for (vout, output) in tx.outputs.iter().enumerate() {
+                if output.script_pubkey.is_op_return() {
+                    continue;
+                }
+                let outpoint = OutPoint {
+                    txid,
+                    vout: vout as u32,
+                };
+                if self.coins.contains_key(&outpoint) {
+                    return Err(UtxoError::DuplicateOutput(outpoint));
+                }
+                self.coins.insert(
+                    outpoint,
+                    Coin {
+                        output: output.clone(),
+                        height,
+                        is_coinbase,
+                    },
+                );
+                added.push(outpoint);
+            }
+        }
+        Ok(())
+    }
+
+    /// Disconnects the tip block that `apply_block` returned `undo` for,
+    /// removing its outputs and restoring what it spent. Outputs the block
+    /// both created and spent just disappear
+    pub fn undo_block(&mut self, block: &Block, undo: &BlockUndo) -> Result<(), UtxoError> {
+        let created: HashSet<OutPoint> = block
+            .txdata
+            .iter()
+            .flat_map(|tx| {
+                let txid = tx.txid();
+                tx.outputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, output)| !output.script_pubkey.is_op_return())
+                    .map(move |(vout, _)| OutPoint {
+                        txid,
+                        vout: vout as u32,
+                    })
+            })
+            .collect();
+        let spent: HashSet<OutPoint> = undo.spent.iter().map(|(outpoint, _)| *outpoint).collect();
+        // check everything first so a mismatch leaves the set untouched
+        if let Some(outpoint) = created.difference(&spent).find(|o| !self.coins.contains_key(o)) {
+            return Err(UtxoError::UndoMismatch(*outpoint));
+        }
+        if let Some(outpoint) = spent.difference(&created).find(|o| self.coins.contains_key(o)) {
+            return Err(UtxoError::UndoMismatch(*outpoint));
+        }
+
+        for outpoint in &created {
+            self.coins.remove(outpoint);
+        }
+        for (outpoint, coin) in &undo.spent {
+            if !created.contains(outpoint) {
+                self.coins.insert(*outpoint, coin.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod chain_tests {
+    use super::*;
+    use crate::address::Network;
+    use crate::block::BlockHeader;
+    use crate::coinbase::{coinbase, subsidy};
+    use crate::script::{Opcode, Script};
+    use crate::tx::{Transaction, TxIn};
+
+    #[test]
+    fn test_params() {
+        let mainnet = Params::new(Network::Mainnet);
+        assert_eq!(
+            mainnet.genesis.id(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(mainnet.target_spacing(), 600);
+        assert_eq!(mainnet.pow_limit(), pow::max_target());
+        assert!(!mainnet.segwit_active(481_823) && mainnet.segwit_active(481_824));
+        assert!(mainnet.taproot_active(709_632));
+        assert!(!Params::new(Network::Testnet).taproot_active(u32::MAX));
+
+        for network in [Network::Mainnet, Network::Testnet, Network::Signet, Network::Regtest] {
+            let params = Params::new(network);
+            assert!(pow::check_pow_with_limit(
+                &params.genesis.hash(),
+                params.genesis.bits,
+                &params.pow_limit()
+            ));
+            assert_eq!(params.magic, network.magic());
+        }
+        let regtest = Params::new(Network::Regtest);
+        assert!(regtest.no_retargeting && regtest.segwit_active(0));
+        assert_eq!((regtest.halving_interval, regtest.default_port), (150, 18444));
+    }
+
+    fn block(txdata: Vec<Transaction>) -> Block {
+        Block {
+            header: BlockHeader::genesis(Network::Regtest),
+            txdata,
+        }
+    }
+
+    fn reward(height: u32) -> Transaction {
+        let output = TxOut {
+            amount: subsidy(height),
+            script_pubkey: Script::p2wpkh(&[height as u8; 20]),
+        };
+        coinbase(height, &[], vec![output]).unwrap()
+    }
+
+    fn spend(outpoints: &[OutPoint], amounts: &[u64]) -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: outpoints
+                .iter()
+                .map(|&previous_output| TxIn {
+                    previous_output,
+                    script_sig: Script::new(),
+                    sequence: u32::MAX,
+                    witness: Vec::new(),
+                })
+                .collect(),
+            outputs: amounts
+                .iter()
+                .map(|&amount| TxOut {
+                    amount,
+                    script_pubkey: Script::p2wpkh(&[0xaa; 20]),
+                })
+                .collect(),
+            locktime: 0,
+        }
+    }
+
+    #[test]
+    fn test_apply_and_undo() {
+        let mut utxos = UtxoSet::new();
+        let first = block(vec![reward(1)]);
+        let first_coin = OutPoint {
+            txid: first.txdata[0].txid(),
+            vout: 0,
+        };
+        utxos.apply_block(&first, 1).unwrap();
+        assert_eq!(utxos.get(&first_coin).unwrap().height, 1);
+        assert!(utxos.get(&first_coin).unwrap().is_coinbase);
+
+        // spends the mature coinbase, then one of the new outputs in the
+        // same block; the OP_RETURN output is never added
+        let pay = spend(&[first_coin], &[30 * 100_000_000, 20 * 100_000_000]);
+        let mut chained = spend(
+            &[OutPoint {
+                txid: pay.txid(),
+                vout: 1,
+            }],
+            &[19 * 100_000_000],
+        );
+        chained.outputs.push(TxOut {
+            amount: 0,
+            script_pubkey: Script::new().push_opcode(Opcode::Return),
+        });
+        let second = block(vec![reward(101), pay.clone(), chained.clone()]);
+        let before = utxos.clone();
+        let undo = utxos.apply_block(&second, 101).unwrap();
+
+        assert_eq!(undo.spent.len(), 2);
+        assert!(!utxos.contains(&first_coin));
+        assert!(utxos.contains(&OutPoint {
+            txid: pay.txid(),
+            vout: 0
+        }));
+        assert!(!utxos.contains(&OutPoint {
+            txid: pay.txid(),
+            vout: 1
+        }));
+        assert!(utxos.contains(&OutPoint {
+            txid: chained.txid(),
+            vout: 0
+        }));
+        assert!(!utxos.contains(&OutPoint {
+            txid: chained.txid(),
+            vout: 1
+        }));
+        assert_eq!(utxos.len(), 3);
+        assert_eq!(utxos.total_amount(), subsidy(1) + subsidy(101) - 100_000_000);
+
+        utxos.undo_block(&second, &undo).unwrap();
+        assert_eq!(utxos.coins, before.coins);
+        // undoing twice doesn't match the set anymore
+        assert!(matches!(
+            utxos.undo_block(&second, &undo),
+            Err(UtxoError::UndoMismatch(_))
+        ));
+        assert_eq!(utxos.coins, before.coins);
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let mut utxos = UtxoSet::new();
+        let first = block(vec![reward(1)]);
+        let first_coin = OutPoint {
+            txid: first.txdata[0].txid(),
+            vout: 0,
+        };
+        utxos.apply_block(&first, 1).unwrap();
+        let before = utxos.coins.clone();
+
+        assert_eq!(
+            utxos.apply_block(&block(vec![reward(100), spend(&[first_coin], &[100])]), 100),
+            Err(UtxoError::ImmatureCoinbase(first_coin))
+        );
+        // a double spend, after an output was both created and spent
+        let pay = spend(&[first_coin], &[100, 200]);
+        let chained = spend(
+            &[OutPoint {
+                txid: pay.txid(),
+                vout: 1,
+            }],
+            &[150],
+        );
+        let double = spend(&[first_coin], &[99]);
+        assert_eq!(
+            utxos.apply_block(&block(vec![reward(101), pay, chained, double]), 101),
+            Err(UtxoError::MissingInput(first_coin))
+        );
+        let unknown = OutPoint { txid: [7; 32], vout: 0 };
+        assert_eq!(
+            utxos.apply_block(&block(vec![reward(101), spend(&[unknown], &[1])]), 101),
+            Err(UtxoError::MissingInput(unknown))
+        );
+        // the same coinbase again would overwrite the unspent first one
+        assert_eq!(
+            utxos.apply_block(&first, 1),
+            Err(UtxoError::DuplicateOutput(first_coin))
+        );
+        // failed blocks leave no trace
+        assert_eq!(utxos.coins, before);
+    }
+}
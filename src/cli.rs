@@ -0,0 +1,458 @@
+use std::fmt::Display;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use num_bigint::BigUint;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::address::{self, Network};
+use crate::crypto::hash::sha256;
+use crate::crypto::schnorr;
+use crate::curves::secp256k1::ecc::{KeyError, PrivateKey, PublicKey, Signature, SignatureError};
+use crate::encoding::decode::DecodeError;
+use crate::script::Script;
+use crate::sign::{sign_p2pkh_input, sign_p2wpkh_input, SignError};
+use crate::taproot::{TaprootError, TaprootSpendInfo};
+use crate::tx::{Transaction, TxError, TxOut};
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliError {
+    InvalidHex(String),
+    InvalidArgument(String),
+    Moneda(Error),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::InvalidHex(what) => write!(f, "{} is not valid hex", what),
+            CliError::InvalidArgument(msg) => write!(f, "{}", msg),
+            CliError::Moneda(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<KeyError> for CliError {
+    fn from(e: KeyError) -> Self {
+        CliError::Moneda(e.into())
+    }
+}
+
+impl From<DecodeError<SignatureError>> for CliError {
+    fn from(e: DecodeError<SignatureError>) -> Self {
+        CliError::Moneda(e.into())
+    }
+}
+
+impl From<SignError> for CliError {
+    fn from(e: SignError) -> Self {
+        CliError::Moneda(e.into())
+    }
+}
+
+impl From<TaprootError> for CliError {
+    fn from(e: TaprootError) -> Self {
+        CliError::Moneda(e.into())
+    }
+}
+
+impl From<TxError> for CliError {
+    fn from(e: TxError) -> Self {
+        CliError::Moneda(e.into())
+    }
+}
+
+impl From<DecodeError<TxError>> for CliError {
+    fn from(e: DecodeError<TxError>) -> Self {
+        CliError::Moneda(e.into())
+    }
+}
+
+/// Keys, addresses, signatures and transactions from the command line
+#[derive(Debug, Parser)]
+#[command(name = "moneda", version)]
+pub struct Cli {
+    /// Network for addresses
+    #[arg(long, global = true, value_enum, default_value_t = NetworkArg::Mainnet)]
+    network: NetworkArg,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum NetworkArg {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl From<NetworkArg> for Network {
+    fn from(network: NetworkArg) -> Self {
+        match network {
+            NetworkArg::Mainnet => Network::Mainnet,
+            NetworkArg::Testnet => Network::Testnet,
+            NetworkArg::Signet => Network::Signet,
+            NetworkArg::Regtest => Network::Regtest,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum AddressType {
+    P2pkh,
+    P2wpkh,
+    P2tr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Scheme {
+    /// DER ECDSA over the SHA-256 of the message
+    Ecdsa,
+    /// BIP-340 over the message itself
+    Schnorr,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate a secret key and print it with its public key and addresses
+    Keygen,
+    /// Derive an address from a public key: SEC1 hex, or x-only for p2tr
+    Address {
+        public_key: String,
+        #[arg(long = "type", value_enum, default_value_t = AddressType::P2wpkh)]
+        address_type: AddressType,
+    },
+    /// Sign a message with a hex secret key
+    Sign {
+        #[arg(long)]
+        key: String,
+        message: String,
+        #[arg(long, value_enum, default_value_t = Scheme::Ecdsa)]
+        scheme: Scheme,
+    },
+    /// Verify a message signature: SEC1 public key for ECDSA, x-only for Schnorr
+    Verify {
+        #[arg(long)]
+        public_key: String,
+        message: String,
+        signature: String,
+        #[arg(long, value_enum, default_value_t = Scheme::Ecdsa)]
+        scheme: Scheme,
+    },
+    /// Sign a P2PKH or P2WPKH input of a raw transaction and print the result
+    SignTx {
+        tx: String,
+        #[arg(long)]
+        input: usize,
+        #[arg(long)]
+        key: String,
+        /// The spent output's script_pubkey
+        #[arg(long)]
+        script_pubkey: String,
+        /// The spent output's amount in satoshis
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Print a raw transaction's fields
+    DecodeTx { tx: String },
+    /// Print a script as asm
+    DecodeScript { script: String },
+}
+
+/// Parses the process arguments, runs the command and prints its output
+pub fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub fn run(cli: Cli) -> Result<String, CliError> {
+    let network = Network::from(cli.network);
+    match cli.command {
+        Command::Keygen => keygen(network),
+        Command::Address {
+            public_key,
+            address_type,
+        } => derive_address(&public_key, address_type, network),
+        Command::Sign { key, message, scheme } => sign(&key, &message, scheme),
+        Command::Verify {
+            public_key,
+            message,
+            signature,
+            scheme,
+        } => verify(&public_key, &message, &signature, scheme),
+        Command::SignTx {
+            tx,
+            input,
+            key,
+            script_pubkey,
+            amount,
+        } => sign_tx(&tx, input, &key, &script_pubkey, amount),
+        Command::DecodeTx { tx } => decode_tx(&tx),
+        Command::DecodeScript { script } => Ok(Script::from_hex(&script)?.to_asm()),
+    }
+}
+
+fn keygen(network: Network) -> Result<String, CliError> {
+    let key = loop {
+        let mut bytes = [0_u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        if let Ok(key) = PrivateKey::from_bytes(&bytes) {
+            break key;
+        }
+    };
+    let public_key = key.public_key();
+    let xonly = hex::encode(schnorr::xonly(&public_key));
+    Ok([
+        format!("secret: {}", hex::encode(key.to_bytes())),
+        format!("public key: {}", hex::encode(public_key.sec(true))),
+        format!("x-only public key: {}", xonly),
+        format!("p2pkh: {}", address::p2pkh(&public_key, network)),
+        format!("p2wpkh: {}", address::p2wpkh(&public_key, network)),
+        format!("p2tr: {}", derive_address(&xonly, AddressType::P2tr, network)?),
+    ]
+    .join("\n"))
+}
+
+fn derive_address(public_key: &str, address_type: AddressType, network: Network) -> Result<String, CliError> {
+    match address_type {
+        AddressType::P2pkh => Ok(address::p2pkh(&parse_public_key(public_key)?, network)),
+        AddressType::P2wpkh => Ok(address::p2wpkh(&parse_public_key(public_key)?, network)),
+        AddressType::P2tr => {
+            let internal_key = array(&decode_hex(public_key, "public key")?, "x-only public key")?;
+            let spend_info = TaprootSpendInfo::new(internal_key, None)?;
+            address::from_script_pubkey(&spend_info.script_pubkey(), network)
+                .ok_or_else(|| CliError::InvalidArgument("no address for the output".to_string()))
+        }
+    }
+}
+
+fn sign(key: &str, message: &str, scheme: Scheme) -> Result<String, CliError> {
+    let key = parse_private_key(key)?;
+    match scheme {
+        Scheme::Ecdsa => Ok(hex::encode(
+            key.sign(&BigUint::from_bytes_be(&sha256(message.as_bytes()))).der(),
+        )),
+        Scheme::Schnorr => {
+            let mut aux_rand = [0_u8; 32];
+            OsRng.fill_bytes(&mut aux_rand);
+            Ok(hex::encode(schnorr::sign(&key, message.as_bytes(), &aux_rand)))
+        }
+    }
+}
+
+fn verify(public_key: &str, message: &str, signature: &str, scheme: Scheme) -> Result<String, CliError> {
+    let signature = decode_hex(signature, "signature")?;
+    let valid = match scheme {
+        Scheme::Ecdsa => {
+            let z = BigUint::from_bytes_be(&sha256(message.as_bytes()));
+            parse_public_key(public_key)?.verify(&z, &Signature::parse_der(&signature)?)
+        }
+        Scheme::Schnorr => {
+            let public_key = array(&decode_hex(public_key, "public key")?, "x-only public key")?;
+            schnorr::verify(&public_key, message.as_bytes(), &array(&signature, "signature")?)
+        }
+    };
+    Ok(if valid { "valid" } else { "invalid" }.to_string())
+}
+
+fn sign_tx(tx: &str, input: usize, key: &str, script_pubkey: &str, amount: u64) -> Result<String, CliError> {
+    let mut tx = Transaction::from_hex(tx)?;
+    if input >= tx.inputs.len() {
+        return Err(CliError::InvalidArgument(format!(
+            "input {} out of range for {} inputs",
+            input,
+            tx.inputs.len()
+        )));
+    }
+    let key = parse_private_key(key)?;
+    let prevout = TxOut {
+        amount,
+        script_pubkey: Script::from_hex(script_pubkey)?,
+    };
+    if prevout.script_pubkey.is_p2pkh() {
+        sign_p2pkh_input(&mut tx, input, &prevout, &key)?;
+    } else if matches!(prevout.script_pubkey.witness_program(), Some((0, program)) if program.len() == 20) {
+        sign_p2wpkh_input(&mut tx, input, &prevout, &key)?;
+    } else {
+        return Err(CliError::InvalidArgument(
+            "only P2PKH and P2WPKH outputs can be signed".to_string(),
+        ));
+    }
+    Ok(tx.to_hex())
+}
+
+fn decode_tx(tx: &str) -> Result<String, CliError> {
+    let tx = Transaction::from_hex(tx)?;
+    let mut lines = vec![
+        format!("txid: {}", tx.id()),
+        format!("version: {}", tx.version),
+        format!("size: {} bytes, {} vbytes", tx.serialize().len(), tx.vsize()),
+        format!("locktime: {}", tx.locktime),
+    ];
+    for (i, input) in tx.inputs.iter().enumerate() {
+        let mut txid = input.previous_output.txid;
+        txid.reverse();
+        lines.push(format!(
+            "input {}: {}:{}",
+            i,
+            hex::encode(txid),
+            input.previous_output.vout
+        ));
+        lines.push(format!("  script_sig: {}", input.script_sig.to_asm()));
+        lines.push(format!("  sequence: {:#010x}", input.sequence));
+        if !input.witness.is_empty() {
+            let items: Vec<String> = input.witness.iter().map(hex::encode).collect();
+            lines.push(format!("  witness: {}", items.join(" ")));
+        }
+    }
+    for (i, output) in tx.outputs.iter().enumerate() {
+        lines.push(format!("output {}: {} sats", i, output.amount));
+        lines.push(format!("  script_pubkey: {}", output.script_pubkey.to_asm()));
+        if let Some(address) = address::from_script_pubkey(&output.script_pubkey, Network::Mainnet) {
+            lines.push(format!("  address: {}", address));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+fn decode_hex(s: &str, what: &str) -> Result<Vec<u8>, CliError> {
+    hex::decode(s).map_err(|_| CliError::InvalidHex(what.to_string()))
+}
+
+fn array<const N: usize>(bytes: &[u8], what: &str) -> Result<[u8; N], CliError> {
+    bytes
+        .try_into()
+        .map_err(|_| CliError::InvalidArgument(format!("{} must be {} bytes", what, N)))
+}
+
+fn parse_private_key(s: &str) -> Result<PrivateKey, CliError> {
+    Ok(PrivateKey::from_bytes(&decode_hex(s, "secret key")?)?)
+}
+
+fn parse_public_key(s: &str) -> Result<PublicKey, CliError> {
+    Ok(PublicKey::parse(&decode_hex(s, "public key")?)?)
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+
+    const SECRET: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+    const PUBLIC: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    fn moneda(args: &[&str]) -> Result<String, CliError> {
+        let cli = Cli::try_parse_from(std::iter::once("moneda").chain(args.iter().copied())).unwrap();
+        run(cli)
+    }
+
+    #[test]
+    fn test_keys_and_addresses() {
+        let output = moneda(&["keygen", "--network", "testnet"]).unwrap();
+        assert_eq!(output.lines().count(), 6);
+        assert!(output.lines().any(|line| line.starts_with("p2wpkh: tb1q")));
+
+        assert_eq!(
+            moneda(&["address", PUBLIC]).unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert_eq!(
+            moneda(&["address", PUBLIC, "--type", "p2pkh"]).unwrap(),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+        );
+        assert!(
+            moneda(&["address", &PUBLIC[2..], "--type", "p2tr", "--network", "regtest"])
+                .unwrap()
+                .starts_with("bcrt1p")
+        );
+        assert_eq!(
+            moneda(&["address", "zz"]),
+            Err(CliError::InvalidHex("public key".to_string()))
+        );
+        assert_eq!(
+            moneda(&["address", "02"]),
+            Err(CliError::Moneda(Error::Key(KeyError::InvalidEncoding)))
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        for scheme in ["ecdsa", "schnorr"] {
+            let public = if scheme == "ecdsa" { PUBLIC } else { &PUBLIC[2..] };
+            let signature = moneda(&["sign", "--key", SECRET, "hello", "--scheme", scheme]).unwrap();
+            let verify = |message| {
+                moneda(&[
+                    "verify",
+                    "--public-key",
+                    public,
+                    message,
+                    &signature,
+                    "--scheme",
+                    scheme,
+                ])
+                .unwrap()
+            };
+            assert_eq!(verify("hello"), "valid");
+            assert_eq!(verify("goodbye"), "invalid");
+        }
+    }
+
+    #[test]
+    fn test_transactions() {
+        // one input spending a P2WPKH output of the secret 1's key, one output
+        let unsigned = "0200000001".to_string()
+            + &"11".repeat(32)
+            + "0000000000ffffffff01e803000000000000160014751e76e8199196d454941c45d1b3a323f1433bd600000000";
+        let script_pubkey = "0014751e76e8199196d454941c45d1b3a323f1433bd6";
+        let args = [
+            "sign-tx",
+            &unsigned,
+            "--input",
+            "0",
+            "--key",
+            SECRET,
+            "--script-pubkey",
+            script_pubkey,
+            "--amount",
+            "2000",
+        ];
+        let signed = moneda(&args).unwrap();
+        let tx = Transaction::from_hex(&signed).unwrap();
+        assert_eq!(tx.inputs[0].witness.len(), 2);
+
+        let decoded = moneda(&["decode-tx", &signed]).unwrap();
+        assert!(decoded.contains("output 0: 1000 sats"));
+        assert!(decoded.contains("address: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"));
+        assert!(decoded.contains("witness: "));
+
+        let wrong_input = [
+            "sign-tx",
+            &unsigned,
+            "--input",
+            "1",
+            "--key",
+            SECRET,
+            "--script-pubkey",
+            script_pubkey,
+            "--amount",
+            "2000",
+        ];
+        assert!(matches!(moneda(&wrong_input), Err(CliError::InvalidArgument(_))));
+        assert_eq!(
+            moneda(&["decode-script", script_pubkey]).unwrap(),
+            "OP_0 OP_PUSHBYTES_20 751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt::Display;
+
+use crate::crypto::hash::hash256;
+use crate::merkle::{DoubleSha256, MerkleTree, OddNode};
+use crate::script::{decode_num, Instruction, Opcode, Script};
+use crate::tx::{OutPoint, Transaction, TxIn, TxOut};
+
+/// Satoshis per bitcoin
+pub const COIN: u64 = 100_000_000;
+pub const INITIAL_SUBSIDY: u64 = 50 * COIN;
+pub const HALVING_INTERVAL: u32 = 210_000;
+/// Confirmations before a coinbase output may be spent
+pub const COINBASE_MATURITY: u32 = 100;
+/// Consensus sanity bound on any amount. Fewer coins will ever exist, as
+/// the subsidy rounds down at each halving
+pub const MAX_MONEY: u64 = 21_000_000 * COIN;
+
+/// Prefix of the BIP-141 witness commitment output's script, after OP_RETURN
+const WITNESS_COMMITMENT_HEADER: [u8; 4] = [0xaa, 0x21, 0xa9, 0xed];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoinbaseError {
+    /// Consensus limits the coinbase script_sig to 2..=100 bytes
+    ScriptSigTooLong(usize),
+}
+
+impl Display for CoinbaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoinbaseError::ScriptSigTooLong(len) => write!(f, "coinbase script_sig of {} bytes exceeds 100", len),
+        }
+    }
+}
+
+impl std::error::Error for CoinbaseError {}
+
+/// Halvings that happened at or before `height`
+pub fn halvings(height: u32) -> u32 {
+    height / HALVING_INTERVAL
+}
+
+/// New coins the block at `height` may create, on top of its fees
+pub fn subsidy(height: u32) -> u64 {
+    // shifting by 64 or more would be undefined; the subsidy is long gone
+    INITIAL_SUBSIDY.checked_shr(halvings(height)).unwrap_or(0)
+}
+
+/// Height of the next block whose subsidy is halved, or None once the
+/// subsidy is zero for good
+pub fn next_halving_height(height: u32) -> Option<u32> {
+    if subsidy(height) == 0 {
+        return None;
+    }
+    (halvings(height) + 1).checked_mul(HALVING_INTERVAL)
+}
+
+/// Coins created by the blocks up to and including `height`
+pub fn total_subsidy(height: u32) -> u64 {
+    (0..=halvings(height))
+        .map(|epoch| {
+            let first = epoch * HALVING_INTERVAL;
+            let last = height.min(first.saturating_add(HALVING_INTERVAL - 1));
+            (last - first + 1) as u64 * subsidy(first)
+        })
+        .sum()
+}
+
+/// A coinbase transaction for the block at `height`. The script_sig starts
+/// with the height as BIP-34 requires, followed by `extra` (extra nonce,
+/// pool tag); a trailing OP_0 pads it to the two byte minimum
+pub fn coinbase(height: u32, extra: &[u8], outputs: Vec<TxOut>) -> Result<Transaction, CoinbaseError> {
+    let mut script_sig = Script::new().push_int(height as i64);
+    if !extra.is_empty() {
+        script_sig = script_sig.push_slice(extra);
+    }
+    if script_sig.len() < 2 {
+        script_sig = script_sig.push_opcode(Opcode::Op0);
+    }
+    if script_sig.len() > 100 {
+        return Err(CoinbaseError::ScriptSigTooLong(script_sig.len()));
+    }
+    Ok(Transaction {
+        version: 1,
+        inputs: vec![TxIn {
+            previous_output: OutPoint::NULL,
+            script_sig,
+            sequence: u32::MAX,
+            witness: Vec::new(),
+        }],
+        outputs,
+        locktime: 0,
+    })
+}
+
+/// The block height a BIP-34 coinbase commits to
+pub fn coinbase_height(tx: &Transaction) -> Option<u32> {
+    if !tx.is_coinbase() {
+        return None;
+    }
+    let height = match tx.inputs[0].script_sig.instructions().next()?.ok()? {
+        Instruction::Op(op) => op.small_int()? as i64,
+        Instruction::Push(_, data) => decode_num(data)?,
+    };
+    u32::try_from(height).ok()
+}
+
+/// Merkle root of the block's wtxids, `wtxids` being those of the
+/// transactions after the coinbase. The coinbase can't commit to itself,
+/// so it counts as all zeros
+pub fn witness_merkle_root(wtxids: &[[u8; 32]]) -> [u8; 32] {
+    let mut leaves = vec![[0_u8; 32]];
+    leaves.extend_from_slice(wtxids);
+    MerkleTree::<DoubleSha256>::from_leaves(&leaves, OddNode::Duplicate)
+        .unwrap()
+        .root()
+}
+
+/// hash256(witness root || reserved value), what the coinbase commits to
+pub fn witness_commitment_hash(witness_root: &[u8; 32], reserved: &[u8; 32]) -> [u8; 32] {
+    let mut data = [0_u8; 64];
+    data[..32].copy_from_slice(witness_root);
+    data[32..].copy_from_slice(reserved);
+    hash256(&data)
+}
+
+/// Commits a segwit coinbase to the block's witnesses (BIP-141): the
+/// witness reserved value goes in its witness, and an OP_RETURN output
+/// commits to it and to the merkle root of the block's wtxids.
+/// `wtxids` are those of the other transactions, in block order
+pub fn add_witness_commitment(coinbase: &mut Transaction, wtxids: &[[u8; 32]]) {
+    let reserved = [0_u8; 32];
+    let mut commitment = WITNESS_COMMITMENT_HEADER.to_vec();
+    commitment.extend_from_slice(&witness_commitment_hash(&witness_merkle_root(wtxids), &reserved));
+    coinbase.inputs[0].witness = vec![reserved.to_vec()];
+    coinbase.outputs.push(TxOut {
+        amount: 0,
+        script_pubkey: Script::new().push_opcode(Opcode::Return).push_slice(&commitment),
+    });
+}
+
+/// The commitment hash of a coinbase's witness commitment output. If
+/// several outputs match, the last one counts
+pub fn witness_commitment(coinbase: &Transaction) -> Option<[u8; 32]> {
+    coinbase.outputs.iter().rev().find_map(|output| {
+        let script = output.script_pubkey.as_bytes();
+        if script.len() >= 38
+            && script[..2] == [Opcode::Return.to_u8(), 0x24]
+            && script[2..6] == WITNESS_COMMITMENT_HEADER
+        {
+            script[6..38].try_into().ok()
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod coinbase_tests {
+    use super::*;
+
+    #[test]
+    fn test_subsidy_schedule() {
+        assert_eq!(subsidy(0), 50 * COIN);
+        assert_eq!(subsidy(209_999), 50 * COIN);
+        assert_eq!(subsidy(210_000), 25 * COIN);
+        assert_eq!(subsidy(840_000), 3 * COIN + COIN / 8);
+        // the last satoshi-per-block epoch, then nothing
+        assert_eq!(subsidy(6_929_999), 1);
+        assert_eq!(subsidy(6_930_000), 0);
+        assert_eq!(subsidy(u32::MAX), 0);
+
+        assert_eq!(next_halving_height(0), Some(210_000));
+        assert_eq!(next_halving_height(840_000), Some(1_050_000));
+        assert_eq!(next_halving_height(6_929_999), Some(6_930_000));
+        assert_eq!(next_halving_height(6_930_000), None);
+
+        assert_eq!(total_subsidy(0), 50 * COIN);
+        assert_eq!(total_subsidy(209_999), 210_000 * 50 * COIN);
+        assert_eq!(total_subsidy(210_000), 210_000 * 50 * COIN + 25 * COIN);
+        // the supply cap actually reached
+        assert_eq!(total_subsidy(u32::MAX), 2_099_999_997_690_000);
+        assert!(total_subsidy(u32::MAX) < MAX_MONEY);
+    }
+
+    #[test]
+    fn test_coinbase_height() {
+        let outputs = vec![TxOut {
+            amount: subsidy(1),
+            script_pubkey: Script::p2wpkh(&[0; 20]),
+        }];
+        for (height, script_sig) in [
+            (1, "5100"),
+            (16, "6000"),
+            (17, "0111"),
+            (128, "028000"),
+            (500_000, "0320a107"),
+        ] {
+            let tx = coinbase(height, &[], outputs.clone()).unwrap();
+            assert!(tx.is_coinbase());
+            assert_eq!(tx.inputs[0].script_sig.to_hex(), script_sig);
+            assert_eq!(coinbase_height(&tx), Some(height));
+        }
+
+        let tagged = coinbase(500_000, b"/moneda/", outputs.clone()).unwrap();
+        assert_eq!(tagged.inputs[0].script_sig.to_hex(), "0320a107082f6d6f6e6564612f");
+        assert_eq!(
+            coinbase(1, &[0; 98], outputs),
+            Err(CoinbaseError::ScriptSigTooLong(101))
+        );
+    }
+
+    #[test]
+    fn test_witness_commitment() {
+        // commitment from rust-bitcoin
+        let mut tx = coinbase(1, &[], Vec::new()).unwrap();
+        add_witness_commitment(&mut tx, &[[1; 32], [2; 32]]);
+        assert_eq!(tx.inputs[0].witness, vec![vec![0; 32]]);
+        assert_eq!(
+            tx.outputs[0].script_pubkey.to_hex(),
+            "6a24aa21a9ed64597292c9ef95f52a3a296796e8b11715d33c54fdd3330910766ed416adf114"
+        );
+        assert_eq!(
+            witness_commitment(&tx).map(hex::encode).as_deref(),
+            Some("64597292c9ef95f52a3a296796e8b11715d33c54fdd3330910766ed416adf114")
+        );
+        assert_eq!(witness_commitment(&coinbase(1, &[], Vec::new()).unwrap()), None);
+    }
+}
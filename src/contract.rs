@@ -0,0 +1,184 @@
+use num_bigint::{BigInt, BigUint};
+use sha2::Sha256;
+
+use crate::crypto::hash::{sha256, tagged_hash};
+use crate::crypto::hmac_drbg::HmacDrbg;
+use crate::crypto::schnorr;
+use crate::curves::secp256k1::ecc::{order, to_32_bytes, KeyError, PrivateKey, PublicKey, Signature};
+
+/// t = H(P || c), binding the contract c to the point P. Adding t G to P
+/// commits to c: opening it takes P and c, and nothing else opens it
+pub fn commitment_tweak(point: &PublicKey, contract: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(&tagged_hash(
+        "moneda/contract",
+        &[&point.sec(true)[..], contract].concat(),
+    )) % order()
+}
+
+/// Pay-to-contract: P' = P + H(P || c) G. Paying to P' proves the payer
+/// knew c, and only the owner of P can spend it
+pub fn pay_to_contract(key: &PublicKey, contract: &[u8]) -> Result<PublicKey, KeyError> {
+    key.add_tweak(&commitment_tweak(key, contract))
+}
+
+/// The secret for `pay_to_contract` of this key's public key
+pub fn pay_to_contract_secret(key: &PrivateKey, contract: &[u8]) -> Result<PrivateKey, KeyError> {
+    key.add_tweak(&commitment_tweak(&key.public_key(), contract))
+}
+
+pub fn verify_pay_to_contract(key: &PublicKey, contract: &[u8], tweaked: &PublicKey) -> bool {
+    pay_to_contract(key, contract).is_ok_and(|p| p == *tweaked)
+}
+
+/// BIP-340 sign-to-contract: the nonce point R0 is tweaked into
+/// R = R0 + H(R0 || c) G, so the signature commits to c at no extra size.
+/// Returns the signature and R0, which opens the commitment
+pub fn schnorr_sign_to_contract(
+    private_key: &PrivateKey,
+    msg: &[u8],
+    contract: &[u8],
+    aux_rand: &[u8; 32],
+) -> ([u8; 64], PublicKey) {
+    // the contract goes into the nonce: the same R0 under two contracts
+    // would give two signatures whose nonces differ by a known amount
+    let data = [&private_key.to_bytes()[..], msg, &sha256(contract), aux_rand].concat();
+    let k0 = BigUint::from_bytes_be(&tagged_hash("moneda/s2c/nonce", &data)) % order();
+    schnorr_sign_with_original_nonce(private_key, msg, &k0, contract)
+        .expect("nonce is nonzero with overwhelming probability")
+}
+
+/// Sign-to-contract from a given original nonce k0, so R0 can be fixed
+/// before the contract is known
+pub fn schnorr_sign_with_original_nonce(
+    private_key: &PrivateKey,
+    msg: &[u8],
+    k0: &BigUint,
+    contract: &[u8],
+) -> Result<([u8; 64], PublicKey), KeyError> {
+    let (k, original) = tweak_nonce(k0, contract)?;
+    Ok((schnorr::sign_with_nonce(private_key, msg, &k), original))
+}
+
+/// Whether R0 and c open the commitment in the signature's nonce. Only
+/// checks the commitment; the signature itself is checked with
+/// `schnorr::verify`
+pub fn verify_schnorr_contract(sig: &[u8; 64], original_nonce: &PublicKey, contract: &[u8]) -> bool {
+    // signing negates k for an odd-y R, which leaves x alone
+    tweaked_nonce(original_nonce, contract).is_some_and(|r| to_32_bytes(&r.point().x().unwrap().num()) == sig[..32])
+}
+
+/// ECDSA sign-to-contract, with the RFC 6979 nonce personalized by the
+/// contract's hash. Returns the signature and R0
+pub fn ecdsa_sign_to_contract(private_key: &PrivateKey, z: &BigUint, contract: &[u8]) -> (Signature, PublicKey) {
+    let z = z % order();
+    let mut drbg = HmacDrbg::<Sha256>::new(
+        &private_key.to_bytes(),
+        &to_32_bytes(&BigInt::from(z.clone())),
+        &sha256(contract),
+    );
+    loop {
+        let mut k_bytes = [0_u8; 32];
+        drbg.generate(&mut k_bytes, &[]).unwrap();
+        if let Some(res) = ecdsa_sign_with_original_nonce(private_key, &z, &BigUint::from_bytes_be(&k_bytes), contract)
+        {
+            return res;
+        }
+    }
+}
+
+/// None if k0 or the tweaked nonce is out of range
+pub fn ecdsa_sign_with_original_nonce(
+    private_key: &PrivateKey,
+    z: &BigUint,
+    k0: &BigUint,
+    contract: &[u8],
+) -> Option<(Signature, PublicKey)> {
+    let (k, original) = tweak_nonce(k0, contract).ok()?;
+    Some((private_key.sign_with_nonce(z, &k)?, original))
+}
+
+/// Whether R0 and c open the commitment: r = x(R0 + H(R0 || c) G) mod n
+pub fn verify_ecdsa_contract(signature: &Signature, original_nonce: &PublicKey, contract: &[u8]) -> bool {
+    tweaked_nonce(original_nonce, contract)
+        .is_some_and(|r| r.point().x().unwrap().num().to_biguint().unwrap() % order() == *signature.r())
+}
+
+/// k = k0 + H(R0 || c), along with R0 = k0 G
+fn tweak_nonce(k0: &BigUint, contract: &[u8]) -> Result<(BigUint, PublicKey), KeyError> {
+    let k0 = PrivateKey::from_bytes(&to_32_bytes(&BigInt::from(k0.clone())))?;
+    let original = k0.public_key();
+    let k = k0.add_tweak(&commitment_tweak(&original, contract))?;
+    Ok((k.secret().clone(), original))
+}
+
+fn tweaked_nonce(original_nonce: &PublicKey, contract: &[u8]) -> Option<PublicKey> {
+    original_nonce
+        .add_tweak(&commitment_tweak(original_nonce, contract))
+        .ok()
+}
+
+#[cfg(test)]
+mod contract_tests {
+    use super::*;
+
+    fn key(byte: u8) -> PrivateKey {
+        PrivateKey::from_bytes(&[byte; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_pay_to_contract() {
+        let owner = key(0x11);
+        let tweaked = pay_to_contract(&owner.public_key(), b"invoice #42").unwrap();
+        assert!(verify_pay_to_contract(&owner.public_key(), b"invoice #42", &tweaked));
+        assert!(!verify_pay_to_contract(&owner.public_key(), b"invoice #43", &tweaked));
+        assert!(!verify_pay_to_contract(
+            &key(0x12).public_key(),
+            b"invoice #42",
+            &tweaked
+        ));
+        // the owner can spend
+        assert_eq!(
+            pay_to_contract_secret(&owner, b"invoice #42").unwrap().public_key(),
+            tweaked
+        );
+    }
+
+    #[test]
+    fn test_schnorr_sign_to_contract() {
+        let signer = key(0x22);
+        let public = schnorr::xonly(&signer.public_key());
+        let contract = sha256(b"timestamped document");
+        let (sig, original) = schnorr_sign_to_contract(&signer, b"msg", &contract, &[0; 32]);
+        assert!(schnorr::verify(&public, b"msg", &sig));
+        assert!(verify_schnorr_contract(&sig, &original, &contract));
+        assert!(!verify_schnorr_contract(&sig, &original, b"other document"));
+        assert!(!verify_schnorr_contract(&sig, &key(0x33).public_key(), &contract));
+
+        // a plain signature commits to nothing
+        let plain = schnorr::sign(&signer, b"msg", &[0; 32]);
+        assert!(!verify_schnorr_contract(&plain, &original, &contract));
+
+        // different contracts give unrelated nonces
+        let (other, other_original) = schnorr_sign_to_contract(&signer, b"msg", b"other", &[0; 32]);
+        assert_ne!(original, other_original);
+        assert_ne!(sig[..32], other[..32]);
+    }
+
+    #[test]
+    fn test_ecdsa_sign_to_contract() {
+        let signer = key(0x44);
+        let z = BigUint::from_bytes_be(&sha256(b"msg"));
+        let (signature, original) = ecdsa_sign_to_contract(&signer, &z, b"contract");
+        assert!(signer.public_key().verify(&z, &signature));
+        assert!(signature.is_low_s());
+        assert!(verify_ecdsa_contract(&signature, &original, b"contract"));
+        assert!(!verify_ecdsa_contract(&signature, &original, b"contracts"));
+        assert!(!verify_ecdsa_contract(&signer.sign(&z), &original, b"contract"));
+
+        let k0 = BigUint::from(12345_u32);
+        let (signature, original) = ecdsa_sign_with_original_nonce(&signer, &z, &k0, b"contract").unwrap();
+        assert_eq!(original, PrivateKey::from(k0).unwrap().public_key());
+        assert!(verify_ecdsa_contract(&signature, &original, b"contract"));
+        assert!(ecdsa_sign_with_original_nonce(&signer, &z, &order(), b"contract").is_none());
+    }
+}
@@ -0,0 +1,598 @@
+use std::fmt::Display;
+
+// Authenticated encryption with associated data: the ciphertext hides the
+// plaintext, and the tag, appended to it, authenticates both the
+// ciphertext and the associated data, which travels in the clear. A nonce
+// must never repeat under one key; callers that derive a fresh key per
+// message, like ECIES, can use the zero nonce.
+//
+// Both ciphers are the standard constructions written out, as the SHA-1 in
+// `hash` is: ChaCha20-Poly1305 (RFC 8439) and AES-256-GCM (NIST SP
+// 800-38D). The tag comparison doesn't exit early, but nothing here
+// is hardened against timing beyond that
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 12;
+pub const TAG_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AeadError {
+    /// Shorter than a tag
+    Truncated,
+    /// The tag doesn't match: the wrong key, or tampered data
+    AuthenticationFailed,
+}
+
+impl Display for AeadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AeadError::Truncated => write!(f, "ciphertext is shorter than a tag"),
+            AeadError::AuthenticationFailed => write!(f, "ciphertext failed authentication"),
+        }
+    }
+}
+
+impl std::error::Error for AeadError {}
+
+/// An AEAD with 256-bit keys, 96-bit nonces and 128-bit tags
+pub trait Aead {
+    fn new(key: &[u8; KEY_LEN]) -> Self;
+
+    /// The ciphertext with the tag appended
+    fn encrypt(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8>;
+
+    fn decrypt(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError>;
+}
+
+/// The ciphertext and the tag after it
+fn split_tag(ciphertext: &[u8]) -> Result<(&[u8], &[u8]), AeadError> {
+    if ciphertext.len() < TAG_LEN {
+        return Err(AeadError::Truncated);
+    }
+    Ok(ciphertext.split_at(ciphertext.len() - TAG_LEN))
+}
+
+/// Compares every byte, so the time taken doesn't say where they differ
+fn check_tag(expected: &[u8; TAG_LEN], tag: &[u8]) -> Result<(), AeadError> {
+    let diff = expected.iter().zip(tag).fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return Err(AeadError::AuthenticationFailed);
+    }
+    Ok(())
+}
+
+/// ChaCha20-Poly1305 as in RFC 8439, also the AEAD of BIP 324
+#[derive(Clone)]
+pub struct ChaCha20Poly1305 {
+    key: [u8; KEY_LEN],
+}
+
+impl ChaCha20Poly1305 {
+    /// The Poly1305 tag over the padded aad and ciphertext and their lengths
+    fn tag(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_LEN] {
+        // the one-time Poly1305 key is the start of keystream block 0
+        let block = chacha20_block(&self.key, 0, nonce);
+        let mut mac = Poly1305::new(block[..32].try_into().expect("32 bytes"));
+        mac.update_padded(aad);
+        mac.update_padded(ciphertext);
+        let mut lengths = [0_u8; 16];
+        lengths[..8].copy_from_slice(&(aad.len() as u64).to_le_bytes());
+        lengths[8..].copy_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+        mac.update_padded(&lengths);
+        mac.finalize()
+    }
+}
+
+impl Aead for ChaCha20Poly1305 {
+    fn new(key: &[u8; KEY_LEN]) -> Self {
+        Self { key: *key }
+    }
+
+    fn encrypt(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut out = plaintext.to_vec();
+        chacha20_xor(&self.key, 1, nonce, &mut out);
+        let tag = self.tag(nonce, aad, &out);
+        out.extend(tag);
+        out
+    }
+
+    fn decrypt(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        let (ciphertext, tag) = split_tag(ciphertext)?;
+        check_tag(&self.tag(nonce, aad, ciphertext), tag)?;
+        let mut out = ciphertext.to_vec();
+        chacha20_xor(&self.key, 1, nonce, &mut out);
+        Ok(out)
+    }
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+/// One 64-byte block of keystream: the RFC 8439 variant, with a 32-bit
+/// block counter and a 96-bit nonce
+fn chacha20_block(key: &[u8; KEY_LEN], counter: u32, nonce: &[u8; NONCE_LEN]) -> [u8; 64] {
+    let le = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().expect("4 bytes"));
+    let mut state = [0_u32; 16];
+    // "expand 32-byte k"
+    state[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+    for (word, chunk) in state[4..12].iter_mut().zip(key.chunks_exact(4)) {
+        *word = le(chunk);
+    }
+    state[12] = counter;
+    for (word, chunk) in state[13..].iter_mut().zip(nonce.chunks_exact(4)) {
+        *word = le(chunk);
+    }
+
+    let mut working = state;
+    for _ in 0..10 {
+        // a column round, then a diagonal round
+        quarter_round(&mut working, 0, 4, 8, 12);
+        quarter_round(&mut working, 1, 5, 9, 13);
+        quarter_round(&mut working, 2, 6, 10, 14);
+        quarter_round(&mut working, 3, 7, 11, 15);
+        quarter_round(&mut working, 0, 5, 10, 15);
+        quarter_round(&mut working, 1, 6, 11, 12);
+        quarter_round(&mut working, 2, 7, 8, 13);
+        quarter_round(&mut working, 3, 4, 9, 14);
+    }
+
+    let mut block = [0_u8; 64];
+    for (i, chunk) in block.chunks_exact_mut(4).enumerate() {
+        chunk.copy_from_slice(&working[i].wrapping_add(state[i]).to_le_bytes());
+    }
+    block
+}
+
+/// XORs `data` with the keystream from block `counter` on
+fn chacha20_xor(key: &[u8; KEY_LEN], counter: u32, nonce: &[u8; NONCE_LEN], data: &mut [u8]) {
+    for (i, chunk) in data.chunks_mut(64).enumerate() {
+        let block = chacha20_block(key, counter.wrapping_add(i as u32), nonce);
+        for (byte, k) in chunk.iter_mut().zip(block) {
+            *byte ^= k;
+        }
+    }
+}
+
+/// The Poly1305 one-time authenticator: the message's 16-byte blocks as
+/// coefficients of a polynomial evaluated at r mod 2^130 - 5, plus s.
+/// The accumulator is five 26-bit limbs, so products fit in a u64
+struct Poly1305 {
+    r: [u32; 5],
+    s: [u32; 4],
+    h: [u32; 5],
+}
+
+const LIMB_MASK: u32 = 0x3ff_ffff;
+
+impl Poly1305 {
+    fn new(key: &[u8; 32]) -> Self {
+        let le = |i: usize| u32::from_le_bytes(key[i..i + 4].try_into().expect("4 bytes"));
+        // r is clamped: the top four bits of every fourth byte and the
+        // bottom two of bytes 4, 8 and 12 are cleared
+        let r = [
+            le(0) & 0x3ff_ffff,
+            (le(3) >> 2) & 0x3ff_ff03,
+            (le(6) >> 4) & 0x3ff_c0ff,
+            (le(9) >> 6) & 0x3f0_3fff,
+            (le(12) >> 8) & 0x00f_ffff,
+        ];
+        let s = [le(16), le(20), le(24), le(28)];
+        Self { r, s, h: [0; 5] }
+    }
+
+    /// Absorbs one block, with the 2^128 bit set for full blocks
+    fn block(&mut self, block: &[u8; 16], high_bit: u32) {
+        let le = |i: usize| u32::from_le_bytes(block[i..i + 4].try_into().expect("4 bytes"));
+        let h = &mut self.h;
+        h[0] += le(0) & LIMB_MASK;
+        h[1] += (le(3) >> 2) & LIMB_MASK;
+        h[2] += (le(6) >> 4) & LIMB_MASK;
+        h[3] += (le(9) >> 6) & LIMB_MASK;
+        h[4] += (le(12) >> 8) | high_bit;
+
+        // h * r, where limbs past 2^130 wrap around times 5
+        let [r0, r1, r2, r3, r4] = self.r.map(u64::from);
+        let [s1, s2, s3, s4] = [r1 * 5, r2 * 5, r3 * 5, r4 * 5];
+        let [h0, h1, h2, h3, h4] = h.map(u64::from);
+        let mut d = [
+            h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1,
+            h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2,
+            h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3,
+            h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4,
+            h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0,
+        ];
+        for i in 0..4 {
+            d[i + 1] += d[i] >> 26;
+            d[i] &= LIMB_MASK as u64;
+        }
+        let carry = d[4] >> 26;
+        d[4] &= LIMB_MASK as u64;
+        d[0] += carry * 5;
+        d[1] += d[0] >> 26;
+        d[0] &= LIMB_MASK as u64;
+        *h = d.map(|limb| limb as u32);
+    }
+
+    /// Absorbs `data` zero-padded to a multiple of 16 bytes, which is how
+    /// the AEAD feeds its inputs to Poly1305
+    fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut block = [0_u8; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.block(&block, 1 << 24);
+        }
+    }
+
+    /// For a message that isn't block-aligned: the last partial block
+    /// gets a 1 byte after it instead of the 2^128 bit
+    #[cfg(test)]
+    fn update(&mut self, data: &[u8]) {
+        let mut chunks = data.chunks_exact(16);
+        for chunk in &mut chunks {
+            self.block(chunk.try_into().expect("16 bytes"), 1 << 24);
+        }
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            let mut block = [0_u8; 16];
+            block[..rest.len()].copy_from_slice(rest);
+            block[rest.len()] = 1;
+            self.block(&block, 0);
+        }
+    }
+
+    fn finalize(self) -> [u8; TAG_LEN] {
+        let mut h = self.h;
+        // carry fully, then subtract p if h >= p, which h + 5 overflowing
+        // 2^130 shows
+        for i in 0..4 {
+            h[i + 1] += h[i] >> 26;
+            h[i] &= LIMB_MASK;
+        }
+        h[0] += (h[4] >> 26) * 5;
+        h[4] &= LIMB_MASK;
+        h[1] += h[0] >> 26;
+        h[0] &= LIMB_MASK;
+
+        let mut g = [0_u32; 5];
+        let mut carry = 5;
+        for i in 0..5 {
+            g[i] = h[i] + carry;
+            carry = g[i] >> 26;
+            g[i] &= LIMB_MASK;
+        }
+        if carry == 1 {
+            h = g;
+        }
+
+        // h mod 2^128 as four 32-bit words, plus s
+        let words = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8),
+        ];
+        let mut tag = [0_u8; TAG_LEN];
+        let mut carry = 0_u64;
+        for (i, chunk) in tag.chunks_exact_mut(4).enumerate() {
+            let sum = words[i] as u64 + self.s[i] as u64 + carry;
+            chunk.copy_from_slice(&(sum as u32).to_le_bytes());
+            carry = sum >> 32;
+        }
+        tag
+    }
+}
+
+/// AES-256 in Galois/Counter mode, the AEAD of TLS and most hardware
+#[derive(Clone)]
+pub struct Aes256Gcm {
+    round_keys: [[u8; 16]; ROUNDS + 1],
+    /// The GHASH key, the encryption of the zero block
+    h: u128,
+}
+
+impl Aes256Gcm {
+    /// The counter block for a 96-bit nonce: the nonce, then a 32-bit
+    /// big-endian counter that starts at 1 for the tag and 2 for the data
+    fn counter_block(nonce: &[u8; NONCE_LEN], counter: u32) -> [u8; 16] {
+        let mut block = [0_u8; 16];
+        block[..12].copy_from_slice(nonce);
+        block[12..].copy_from_slice(&counter.to_be_bytes());
+        block
+    }
+
+    fn ctr_xor(&self, nonce: &[u8; NONCE_LEN], data: &mut [u8]) {
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            let keystream = aes_encrypt(
+                &self.round_keys,
+                &Self::counter_block(nonce, 2_u32.wrapping_add(i as u32)),
+            );
+            for (byte, k) in chunk.iter_mut().zip(keystream) {
+                *byte ^= k;
+            }
+        }
+    }
+
+    fn tag(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_LEN] {
+        let mut x = 0_u128;
+        for data in [aad, ciphertext] {
+            for chunk in data.chunks(16) {
+                let mut block = [0_u8; 16];
+                block[..chunk.len()].copy_from_slice(chunk);
+                x = gf_mul(x ^ u128::from_be_bytes(block), self.h);
+            }
+        }
+        let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+        x = gf_mul(x ^ lengths, self.h);
+        let mask = u128::from_be_bytes(aes_encrypt(&self.round_keys, &Self::counter_block(nonce, 1)));
+        (x ^ mask).to_be_bytes()
+    }
+}
+
+impl Aead for Aes256Gcm {
+    fn new(key: &[u8; KEY_LEN]) -> Self {
+        let round_keys = expand_key(key);
+        let h = u128::from_be_bytes(aes_encrypt(&round_keys, &[0; 16]));
+        Self { round_keys, h }
+    }
+
+    fn encrypt(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut out = plaintext.to_vec();
+        self.ctr_xor(nonce, &mut out);
+        let tag = self.tag(nonce, aad, &out);
+        out.extend(tag);
+        out
+    }
+
+    fn decrypt(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        let (ciphertext, tag) = split_tag(ciphertext)?;
+        check_tag(&self.tag(nonce, aad, ciphertext), tag)?;
+        let mut out = ciphertext.to_vec();
+        self.ctr_xor(nonce, &mut out);
+        Ok(out)
+    }
+}
+
+/// Multiplication in GF(2^128) as GCM defines it: bit 0 is the most
+/// significant bit of the block, and the modulus is x^128 + x^7 + x^2 + x + 1
+fn gf_mul(x: u128, y: u128) -> u128 {
+    let mut z = 0;
+    let mut v = y;
+    for i in 0..128 {
+        if (x >> (127 - i)) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ (0xe1 << 120) } else { v >> 1 };
+    }
+    z
+}
+
+const ROUNDS: usize = 14;
+
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76, 0xca, 0x82, 0xc9,
+    0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0, 0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f,
+    0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15, 0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07,
+    0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75, 0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3,
+    0x29, 0xe3, 0x2f, 0x84, 0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58,
+    0xcf, 0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8, 0x51, 0xa3,
+    0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2, 0xcd, 0x0c, 0x13, 0xec, 0x5f,
+    0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73, 0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88,
+    0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb, 0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac,
+    0x62, 0x91, 0x95, 0xe4, 0x79, 0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a,
+    0xae, 0x08, 0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a, 0x70,
+    0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e, 0xe1, 0xf8, 0x98, 0x11,
+    0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf, 0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42,
+    0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+/// Multiplication by x in GF(2^8) mod x^8 + x^4 + x^3 + x + 1
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// The AES-256 key schedule: 15 round keys from the 8 words of the key
+fn expand_key(key: &[u8; KEY_LEN]) -> [[u8; 16]; ROUNDS + 1] {
+    let mut words = [[0_u8; 4]; 4 * (ROUNDS + 1)];
+    for (word, chunk) in words.iter_mut().zip(key.chunks_exact(4)) {
+        word.copy_from_slice(chunk);
+    }
+    let mut rcon = 1_u8;
+    for i in 8..words.len() {
+        let mut temp = words[i - 1];
+        if i % 8 == 0 {
+            temp = [
+                SBOX[temp[1] as usize] ^ rcon,
+                SBOX[temp[2] as usize],
+                SBOX[temp[3] as usize],
+                SBOX[temp[0] as usize],
+            ];
+            rcon = xtime(rcon);
+        } else if i % 8 == 4 {
+            temp = temp.map(|b| SBOX[b as usize]);
+        }
+        for j in 0..4 {
+            words[i][j] = words[i - 8][j] ^ temp[j];
+        }
+    }
+    let mut round_keys = [[0_u8; 16]; ROUNDS + 1];
+    for (round_key, chunk) in round_keys.iter_mut().zip(words.chunks_exact(4)) {
+        round_key.copy_from_slice(chunk.as_flattened());
+    }
+    round_keys
+}
+
+/// Encrypts one block. The state is column-major: byte 4c + r is row r of
+/// column c
+fn aes_encrypt(round_keys: &[[u8; 16]; ROUNDS + 1], block: &[u8; 16]) -> [u8; 16] {
+    let add_round_key = |state: &mut [u8; 16], key: &[u8; 16]| {
+        for (byte, k) in state.iter_mut().zip(key) {
+            *byte ^= k;
+        }
+    };
+    let mut state = *block;
+    add_round_key(&mut state, &round_keys[0]);
+    for (round, key) in round_keys.iter().enumerate().skip(1) {
+        // SubBytes and ShiftRows: row r rotates left by r
+        let mut shifted = [0_u8; 16];
+        for c in 0..4 {
+            for r in 0..4 {
+                shifted[4 * c + r] = SBOX[state[4 * ((c + r) % 4) + r] as usize];
+            }
+        }
+        state = shifted;
+        // MixColumns, except in the last round
+        if round != ROUNDS {
+            for column in state.chunks_exact_mut(4) {
+                let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+                let all = a0 ^ a1 ^ a2 ^ a3;
+                column[0] ^= all ^ xtime(a0 ^ a1);
+                column[1] ^= all ^ xtime(a1 ^ a2);
+                column[2] ^= all ^ xtime(a2 ^ a3);
+                column[3] ^= all ^ xtime(a3 ^ a0);
+            }
+        }
+        add_round_key(&mut state, key);
+    }
+    state
+}
+
+#[cfg(test)]
+mod aead_tests {
+    use super::*;
+
+    fn hex_array<const N: usize>(s: &str) -> [u8; N] {
+        hex::decode(s).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_chacha20_block() {
+        // RFC 8439 section 2.3.2
+        let key: [u8; 32] = std::array::from_fn(|i| i as u8);
+        let block = chacha20_block(&key, 1, &hex_array("000000090000004a00000000"));
+        assert_eq!(
+            hex::encode(block),
+            "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+             d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+        );
+    }
+
+    #[test]
+    fn test_poly1305() {
+        // RFC 8439 section 2.5.2
+        let mut mac = Poly1305::new(&hex_array(
+            "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b",
+        ));
+        mac.update(b"Cryptographic Forum Research Group");
+        assert_eq!(hex::encode(mac.finalize()), "a8061dc1305136c6c22b8baf0c0127a9");
+
+        // RFC 8439 A.3 #11: h reaches p exactly and must wrap to zero
+        let mut mac = Poly1305::new(&hex_array(
+            "0100000000000000000000000000000000000000000000000000000000000000",
+        ));
+        mac.update(
+            &hex::decode(
+                "fffffffffffffffffffffffffffffffffbfefefefefefefefefefefefefefefe01010101010101010101010101010101",
+            )
+            .unwrap(),
+        );
+        assert_eq!(hex::encode(mac.finalize()), "00000000000000000000000000000000");
+    }
+
+    #[test]
+    fn test_chacha20_poly1305() {
+        // RFC 8439 section 2.8.2
+        let key: [u8; 32] = std::array::from_fn(|i| 0x80 + i as u8);
+        let nonce = hex_array("070000004041424344454647");
+        let aad = hex::decode("50515253c0c1c2c3c4c5c6c7").unwrap();
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let cipher = ChaCha20Poly1305::new(&key);
+        let sealed = cipher.encrypt(&nonce, &aad, plaintext);
+        assert_eq!(
+            hex::encode(&sealed),
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b\
+             1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116\
+             1ae10b594f09e26a7e902ecbd0600691"
+        );
+        assert_eq!(cipher.decrypt(&nonce, &aad, &sealed).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_aes256_gcm() {
+        // the AES-256 test cases 14 and 16 of the GCM specification
+        let cipher = Aes256Gcm::new(&[0; 32]);
+        assert_eq!(
+            hex::encode(cipher.encrypt(&[0; 12], b"", b"")),
+            "530f8afbc74536b9a963b4f1c4cb738b"
+        );
+        assert_eq!(
+            hex::encode(cipher.encrypt(&[0; 12], b"", &[0; 16])),
+            "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919"
+        );
+
+        let cipher = Aes256Gcm::new(&hex_array(
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+        ));
+        let nonce = hex_array("cafebabefacedbaddecaf888");
+        let aad = hex::decode("feedfacedeadbeeffeedfacedeadbeefabaddad2").unwrap();
+        let plaintext = hex::decode(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        )
+        .unwrap();
+        let sealed = cipher.encrypt(&nonce, &aad, &plaintext);
+        assert_eq!(
+            hex::encode(&sealed),
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662\
+             76fc6ece0f4e1768cddf8853bb2d551b"
+        );
+        assert_eq!(cipher.decrypt(&nonce, &aad, &sealed).unwrap(), plaintext);
+    }
+
+    fn rejects_tampering<A: Aead>() {
+        let cipher = A::new(&[7; 32]);
+        let nonce = [1; 12];
+        let sealed = cipher.encrypt(&nonce, b"header", b"payload");
+        assert_eq!(cipher.decrypt(&nonce, b"header", &sealed).unwrap(), b"payload");
+
+        let mut flipped = sealed.clone();
+        flipped[0] ^= 1;
+        assert_eq!(
+            cipher.decrypt(&nonce, b"header", &flipped),
+            Err(AeadError::AuthenticationFailed)
+        );
+        assert_eq!(
+            cipher.decrypt(&nonce, b"footer", &sealed),
+            Err(AeadError::AuthenticationFailed)
+        );
+        assert_eq!(
+            cipher.decrypt(&[2; 12], b"header", &sealed),
+            Err(AeadError::AuthenticationFailed)
+        );
+        assert_eq!(
+            A::new(&[8; 32]).decrypt(&nonce, b"header", &sealed),
+            Err(AeadError::AuthenticationFailed)
+        );
+        assert_eq!(
+            cipher.decrypt(&nonce, b"header", &sealed[..15]),
+            Err(AeadError::Truncated)
+        );
+        assert_eq!(
+            cipher.decrypt(&nonce, b"", &cipher.encrypt(&nonce, b"", b"")).unwrap(),
+            b""
+        );
+    }
+
+    #[test]
+    fn test_tampering() {
+        rejects_tampering::<ChaCha20Poly1305>();
+        rejects_tampering::<Aes256Gcm>();
+    }
+}
@@ -0,0 +1,128 @@
+use num_bigint::BigInt;
+
+use crate::crypto::kdf::{hkdf_sha256, KdfError};
+use crate::curves::secp256k1::ecc::{to_32_bytes, KeyError, PrivateKey, PublicKey};
+use crate::ecdsa::{SigningKey, VerifyingKey};
+
+/// Elliptic-curve Diffie-Hellman: both parties arrive at the same point
+/// a * B = b * A without revealing their secrets
+pub fn shared_point(private_key: &PrivateKey, public_key: &PublicKey) -> PublicKey {
+    let point = BigInt::from(private_key.secret().clone()) * public_key.point().clone();
+    PublicKey::from(point).expect("secp256k1 has prime order")
+}
+
+/// The x coordinate of the shared point, the raw secret most protocols hash
+/// or feed into a KDF
+pub fn shared_secret(private_key: &PrivateKey, public_key: &PublicKey) -> [u8; 32] {
+    let point = shared_point(private_key, public_key);
+    to_32_bytes(&point.point().x().unwrap().num())
+}
+
+/// Turns the shared secret into symmetric key material with HKDF-SHA256.
+/// `label` binds the output to one purpose, so keys for different uses of
+/// the same key pair are independent
+pub fn derive_key(
+    private_key: &PrivateKey,
+    public_key: &PublicKey,
+    label: &[u8],
+    out: &mut [u8],
+) -> Result<(), KdfError> {
+    hkdf_sha256(b"", &shared_secret(private_key, public_key), label, out)
+}
+
+/// ECDH on a curve of the registry, with the ECDSA key types standing in
+/// for any key pair: the x coordinate of d Q as `field_len` bytes, which is
+/// the premaster secret of RFC 4492 and RFC 7027
+pub fn named_shared_secret(private_key: &SigningKey, public_key: &VerifyingKey) -> Result<Vec<u8>, KeyError> {
+    let curve = private_key.curve();
+    if public_key.curve() != curve {
+        return Err(KeyError::CurveMismatch);
+    }
+    let point = BigInt::from(private_key.secret().clone()) * public_key.point().clone();
+    // n is prime and Q is not infinity, so d Q isn't either
+    Ok(curve.coordinate_bytes(point.x().unwrap()))
+}
+
+#[cfg(test)]
+mod ecdh_tests {
+    use super::*;
+    use crate::named_curve::named_curve;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_shared_secret_agrees() {
+        let alice = PrivateKey::from(BigUint::from(0xa11ce_u32)).unwrap();
+        let bob = PrivateKey::from(BigUint::from(0xb0b_u32)).unwrap();
+
+        assert_eq!(
+            shared_point(&alice, &bob.public_key()),
+            shared_point(&bob, &alice.public_key())
+        );
+        assert_eq!(
+            shared_secret(&alice, &bob.public_key()),
+            shared_secret(&bob, &alice.public_key())
+        );
+    }
+
+    #[test]
+    fn test_shared_point_is_product() {
+        let alice = PrivateKey::from(BigUint::from(3_u8)).unwrap();
+        let bob = PrivateKey::from(BigUint::from(5_u8)).unwrap();
+
+        assert_eq!(
+            shared_point(&alice, &bob.public_key()),
+            PrivateKey::from(BigUint::from(15_u8)).unwrap().public_key()
+        );
+    }
+
+    #[test]
+    fn test_derive_key_labels() {
+        let alice = PrivateKey::from(BigUint::from(0xa11ce_u32)).unwrap();
+        let bob = PrivateKey::from(BigUint::from(0xb0b_u32)).unwrap();
+
+        let mut a = [0_u8; 32];
+        let mut b = [0_u8; 32];
+        derive_key(&alice, &bob.public_key(), b"encryption", &mut a).unwrap();
+        derive_key(&bob, &alice.public_key(), b"encryption", &mut b).unwrap();
+        assert_eq!(a, b);
+
+        derive_key(&bob, &alice.public_key(), b"authentication", &mut b).unwrap();
+        assert_ne!(a, b);
+        assert_ne!(a, shared_secret(&alice, &bob.public_key()));
+    }
+
+    #[test]
+    fn test_brainpool() {
+        // RFC 7027 A.1
+        let curve = named_curve("brainpoolP256r1").unwrap();
+        let key = |d: &str| SigningKey::from_bytes(curve, &hex::decode(d).unwrap()).unwrap();
+        let alice = key("81db1ee100150ff2ea338d708271be38300cb54241d79950f77b063039804f1d");
+        let bob = key("55e40bc41e37e3e2ad25c3c6654511ffa8474a91a0032087593852d3e7d76bd3");
+        assert_eq!(
+            hex::encode(alice.verifying_key().sec(false)),
+            "0444106e913f92bc02a1705d9953a8414db95e1aaa49e81d9e85f929a8e3100be5\
+             8ab4846f11caccb73ce49cbdd120f5a900a69fd32c272223f789ef10eb089bdc"
+        );
+        let secret = named_shared_secret(&alice, &bob.verifying_key()).unwrap();
+        assert_eq!(
+            hex::encode(&secret),
+            "89afc39d41d3b327814b80940b042590f96556ec91e6ae7939bce31f3a18bf2b"
+        );
+        assert_eq!(named_shared_secret(&bob, &alice.verifying_key()), Ok(secret));
+
+        // computed with OpenSSL
+        let curve = named_curve("brainpoolP384r1").unwrap();
+        let alice = SigningKey::new(curve, BigUint::from(0xa11ce_u32)).unwrap();
+        let bob = SigningKey::new(curve, BigUint::from(0xb0b_u32)).unwrap();
+        assert_eq!(
+            hex::encode(named_shared_secret(&alice, &bob.verifying_key()).unwrap()),
+            "279d1fede9cc8927e730c188e8f38e36d9a9d454cc3d8ada102c33fe986e779712bce994a62fd15afc63f196761ddf31"
+        );
+
+        let p256 = SigningKey::new(named_curve("P-256").unwrap(), BigUint::from(1_u8)).unwrap();
+        assert_eq!(
+            named_shared_secret(&alice, &p256.verifying_key()),
+            Err(KeyError::CurveMismatch)
+        );
+    }
+}
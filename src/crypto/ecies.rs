@@ -0,0 +1,119 @@
+use std::fmt::Display;
+
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::aead::{Aead, AeadError, KEY_LEN};
+use crate::crypto::ecdh::derive_key;
+use crate::curves::secp256k1::ecc::{KeyError, PrivateKey, PublicKey};
+
+// ECIES on secp256k1: the sender makes a one-off key pair, and ECDH
+// between it and the recipient's key, through HKDF, gives the AEAD key.
+// The message is the ephemeral public key, compressed, then the AEAD
+// ciphertext, with the ephemeral key as associated data. Every message
+// gets a fresh key, so the zero nonce is safe. Either AEAD works; both
+// sides have to agree on which
+
+const LABEL: &[u8] = b"moneda/ecies";
+const EPHEMERAL_LEN: usize = 33;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EciesError {
+    /// The message doesn't start with a valid compressed public key
+    InvalidEphemeralKey(KeyError),
+    Aead(AeadError),
+}
+
+impl Display for EciesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EciesError::InvalidEphemeralKey(e) => write!(f, "invalid ephemeral key: {}", e),
+            EciesError::Aead(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EciesError {}
+
+impl From<AeadError> for EciesError {
+    fn from(e: AeadError) -> Self {
+        EciesError::Aead(e)
+    }
+}
+
+fn cipher<A: Aead>(private_key: &PrivateKey, public_key: &PublicKey) -> A {
+    let mut key = [0_u8; KEY_LEN];
+    derive_key(private_key, public_key, LABEL, &mut key).expect("32 bytes is a valid HKDF output length");
+    A::new(&key)
+}
+
+/// Encrypts `plaintext` so only the holder of `public_key`'s secret can
+/// read it
+pub fn encrypt<A: Aead, R: RngCore + CryptoRng>(public_key: &PublicKey, plaintext: &[u8], rng: &mut R) -> Vec<u8> {
+    let ephemeral = PrivateKey::generate(rng);
+    let mut out = ephemeral.public_key().sec(true);
+    let sealed = cipher::<A>(&ephemeral, public_key).encrypt(&[0; 12], &out, plaintext);
+    out.extend(sealed);
+    out
+}
+
+pub fn decrypt<A: Aead>(private_key: &PrivateKey, message: &[u8]) -> Result<Vec<u8>, EciesError> {
+    let (ephemeral, sealed) = message.split_at(message.len().min(EPHEMERAL_LEN));
+    let ephemeral_key = PublicKey::parse(ephemeral).map_err(EciesError::InvalidEphemeralKey)?;
+    Ok(cipher::<A>(private_key, &ephemeral_key).decrypt(&[0; 12], ephemeral, sealed)?)
+}
+
+#[cfg(test)]
+mod ecies_tests {
+    use super::*;
+    use crate::crypto::aead::{Aes256Gcm, ChaCha20Poly1305, TAG_LEN};
+    use crate::crypto::rng::DeterministicRng;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = DeterministicRng::new([0xec; 32]);
+        let recipient = PrivateKey::from(BigUint::from(0xa11ce_u32)).unwrap();
+        let message = encrypt::<ChaCha20Poly1305, _>(&recipient.public_key(), b"attack at dawn", &mut rng);
+        assert_eq!(message.len(), EPHEMERAL_LEN + 14 + TAG_LEN);
+        assert_eq!(
+            decrypt::<ChaCha20Poly1305>(&recipient, &message).unwrap(),
+            b"attack at dawn"
+        );
+
+        let message = encrypt::<Aes256Gcm, _>(&recipient.public_key(), b"attack at dawn", &mut rng);
+        assert_eq!(decrypt::<Aes256Gcm>(&recipient, &message).unwrap(), b"attack at dawn");
+        // the ciphers aren't interchangeable
+        assert_eq!(
+            decrypt::<ChaCha20Poly1305>(&recipient, &message),
+            Err(EciesError::Aead(AeadError::AuthenticationFailed))
+        );
+    }
+
+    #[test]
+    fn test_rejects() {
+        let mut rng = DeterministicRng::new([0xed; 32]);
+        let recipient = PrivateKey::from(BigUint::from(0xa11ce_u32)).unwrap();
+        let message = encrypt::<ChaCha20Poly1305, _>(&recipient.public_key(), b"attack at dawn", &mut rng);
+
+        let eavesdropper = PrivateKey::from(BigUint::from(0xe7e_u32)).unwrap();
+        assert_eq!(
+            decrypt::<ChaCha20Poly1305>(&eavesdropper, &message),
+            Err(EciesError::Aead(AeadError::AuthenticationFailed))
+        );
+        // swapping in another ephemeral key changes the associated data
+        let mut swapped = message.clone();
+        swapped[..EPHEMERAL_LEN].copy_from_slice(&eavesdropper.public_key().sec(true));
+        assert_eq!(
+            decrypt::<ChaCha20Poly1305>(&recipient, &swapped),
+            Err(EciesError::Aead(AeadError::AuthenticationFailed))
+        );
+        assert_eq!(
+            decrypt::<ChaCha20Poly1305>(&recipient, &message[..40]),
+            Err(EciesError::Aead(AeadError::Truncated))
+        );
+        assert_eq!(
+            decrypt::<ChaCha20Poly1305>(&recipient, &message[..20]),
+            Err(EciesError::InvalidEphemeralKey(KeyError::InvalidEncoding))
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::sync::OnceLock;
+
+use num_bigint::{BigInt, BigUint, Sign};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::crypto::x25519::prime;
+use crate::curves::secp256k1::ecc::KeyError;
+
+// Ed25519 (RFC 8032): Schnorr signatures on the twisted Edwards curve
+// -x^2 + y^2 = 1 + d x^2 y^2, birationally equivalent to X25519's
+// Curve25519 and over the same field. Points are in extended coordinates
+// (X : Y : Z : T) with x = X/Z, y = Y/Z and x y = T/Z, where one formula
+// adds any two points, doubling included. Keys and signatures are the
+// RFC's bytes; nonces are derived from the key and message, so signing
+// needs no randomness
+
+/// The prime order of the base point, 2^252 + 27742317777372353535851937790883648493
+pub fn order() -> BigUint {
+    (BigUint::from(1_u8) << 252_u32) + BigUint::parse_bytes(b"14def9dea2f79cd65812631a5cf5d3ed", 16).unwrap()
+}
+
+fn element(n: impl Into<BigInt>) -> FieldElement {
+    FieldElement::reduce(n.into(), prime()).expect("p is a valid modulus")
+}
+
+struct Constants {
+    d: FieldElement,
+    /// A square root of -1, 2^((p - 1) / 4)
+    sqrt_m1: FieldElement,
+    base: EdwardsPoint,
+}
+
+fn constants() -> &'static Constants {
+    static CONSTANTS: OnceLock<Constants> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        let d = element(-121665) / element(121666);
+        let sqrt_m1 = element(2).pow((prime() - 1_u8) / 4_u8);
+        // the base point has y = 4/5 and even x
+        let mut encoded = (element(4) / element(5)).num().to_bytes_le().1;
+        encoded.resize(32, 0);
+        let base = decode(&encoded.try_into().expect("32 bytes"), &d, &sqrt_m1).expect("the base point decodes");
+        Constants { d, sqrt_m1, base }
+    })
+}
+
+#[derive(Clone, Debug)]
+struct EdwardsPoint {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement,
+    t: FieldElement,
+}
+
+impl EdwardsPoint {
+    fn identity() -> Self {
+        Self {
+            x: element(0),
+            y: element(1),
+            z: element(1),
+            t: element(0),
+        }
+    }
+
+    /// RFC 8032 section 5.1.4, complete for a = -1
+    fn add(&self, other: &Self) -> Self {
+        let d2 = constants().d.clone() * BigInt::from(2);
+        let a = (self.y.clone() - self.x.clone()) * (other.y.clone() - other.x.clone());
+        let b = (self.y.clone() + self.x.clone()) * (other.y.clone() + other.x.clone());
+        let c = self.t.clone() * d2 * other.t.clone();
+        let d = self.z.clone() * BigInt::from(2) * other.z.clone();
+        let (e, f, g, h) = (b.clone() - a.clone(), d.clone() - c.clone(), d + c, b + a);
+        Self {
+            x: e.clone() * f.clone(),
+            y: g.clone() * h.clone(),
+            t: e * h,
+            z: f * g,
+        }
+    }
+
+    fn mul(&self, k: &BigUint) -> Self {
+        let mut res = Self::identity();
+        for i in (0..k.bits()).rev() {
+            res = res.add(&res);
+            if k.bit(i) {
+                res = res.add(self);
+            }
+        }
+        res
+    }
+
+    /// y little-endian, with the parity of x in the top bit
+    fn encode(&self) -> [u8; 32] {
+        let z_inv = element(1) / self.z.clone();
+        let (x, y) = (self.x.clone() * z_inv.clone(), self.y.clone() * z_inv);
+        let mut bytes = [0_u8; 32];
+        let (_, le) = y.num().to_bytes_le();
+        bytes[..le.len()].copy_from_slice(&le);
+        bytes[31] |= (x.is_odd() as u8) << 7;
+        bytes
+    }
+}
+
+/// RFC 8032 section 5.1.3: recovers x from x^2 = (y^2 - 1) / (d y^2 + 1),
+/// rejecting y >= p, non-squares, and -0
+fn decode(bytes: &[u8; 32], d: &FieldElement, sqrt_m1: &FieldElement) -> Result<EdwardsPoint, KeyError> {
+    let sign = bytes[31] >> 7 == 1;
+    let mut y_bytes = *bytes;
+    y_bytes[31] &= 127;
+    let y = FieldElement::from(BigInt::from_bytes_le(Sign::Plus, &y_bytes), prime())
+        .map_err(|_| KeyError::InvalidEncoding)?;
+
+    let y2 = y.clone() * y.clone();
+    let u = y2.clone() - element(1);
+    let v = d.clone() * y2 + element(1);
+    // a candidate root, u v^3 (u v^7)^((p - 5) / 8), since p = 5 mod 8
+    let v3 = v.clone() * v.clone() * v.clone();
+    let mut x = u.clone() * v3.clone() * (u.clone() * v3.clone() * v3 * v.clone()).pow((prime() - 5_u8) / 8_u8);
+    let vx2 = v * x.clone() * x.clone();
+    if vx2 == element(0) - u.clone() {
+        x = x * sqrt_m1.clone();
+    } else if vx2 != u {
+        return Err(KeyError::InvalidEncoding);
+    }
+    if x.is_zero() && sign {
+        return Err(KeyError::InvalidEncoding);
+    }
+    if x.is_odd() != sign {
+        x = element(0) - x;
+    }
+    let t = x.clone() * y.clone();
+    Ok(EdwardsPoint { x, y, z: element(1), t })
+}
+
+/// SHA-512 of the parts, little-endian, mod the order
+fn hash_to_scalar(parts: &[&[u8]]) -> BigUint {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    BigUint::from_bytes_le(&hasher.finalize()) % order()
+}
+
+fn encode_scalar(s: &BigUint) -> [u8; 32] {
+    let mut bytes = [0_u8; 32];
+    let le = s.to_bytes_le();
+    bytes[..le.len()].copy_from_slice(&le);
+    bytes
+}
+
+/// An Ed25519 private key: a 32-byte seed, hashed into the secret scalar
+/// and the prefix that nonces are derived from
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigningKey {
+    seed: [u8; 32],
+    scalar: BigUint,
+    prefix: [u8; 32],
+    verifying_key: VerifyingKey,
+}
+
+impl SigningKey {
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let h: [u8; 64] = Sha512::digest(seed).into();
+        // clamped like X25519's scalars: a multiple of 8 with bit 254 set
+        let mut scalar = [0_u8; 32];
+        scalar.copy_from_slice(&h[..32]);
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+        let scalar = BigUint::from_bytes_le(&scalar);
+        let point = constants().base.mul(&scalar);
+        let verifying_key = VerifyingKey { bytes: point.encode() };
+        Self {
+            seed,
+            scalar,
+            prefix: h[32..].try_into().expect("32 bytes"),
+            verifying_key,
+        }
+    }
+
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut seed = [0_u8; 32];
+        rng.fill_bytes(&mut seed);
+        Self::from_seed(seed)
+    }
+
+    pub fn seed(&self) -> &[u8; 32] {
+        &self.seed
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.verifying_key
+    }
+
+    /// R || S, with r = H(prefix || M), R = r B and S = r + H(R || A || M) s
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        let r = hash_to_scalar(&[&self.prefix, message]);
+        let big_r = constants().base.mul(&r).encode();
+        let k = hash_to_scalar(&[&big_r, &self.verifying_key.bytes, message]);
+        let s = (r + k * &self.scalar) % order();
+        let mut signature = [0_u8; 64];
+        signature[..32].copy_from_slice(&big_r);
+        signature[32..].copy_from_slice(&encode_scalar(&s));
+        signature
+    }
+}
+
+/// An Ed25519 public key, the encoding of a valid point
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyingKey {
+    bytes: [u8; 32],
+}
+
+impl VerifyingKey {
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self, KeyError> {
+        let constants = constants();
+        decode(bytes, &constants.d, &constants.sqrt_m1)?;
+        Ok(Self { bytes: *bytes })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.bytes
+    }
+
+    /// Checks S B = R + H(R || A || M) A, without the cofactor, as
+    /// OpenSSH and libsodium do. S must be below the order
+    pub fn verify(&self, message: &[u8], signature: &[u8; 64]) -> bool {
+        let constants = constants();
+        let (r_bytes, s_bytes) = signature.split_at(32);
+        let s = BigUint::from_bytes_le(s_bytes);
+        if s >= order() {
+            return false;
+        }
+        let Ok(r) = decode(r_bytes.try_into().expect("32 bytes"), &constants.d, &constants.sqrt_m1) else {
+            return false;
+        };
+        let a = decode(&self.bytes, &constants.d, &constants.sqrt_m1).expect("checked when constructed");
+        let k = hash_to_scalar(&[r_bytes, &self.bytes, message]);
+        constants.base.mul(&s).encode() == r.add(&a.mul(&k)).encode()
+    }
+}
+
+#[cfg(test)]
+mod ed25519_tests {
+    use super::*;
+    use crate::crypto::rng::DeterministicRng;
+
+    #[test]
+    fn test_rfc8032_vectors() {
+        // section 7.1, tests 1 to 3
+        for (seed, public_key, message, signature) in [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "",
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                "72",
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ),
+            (
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+                "af82",
+                "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+            ),
+        ] {
+            let key = SigningKey::from_seed(hex::decode(seed).unwrap().try_into().unwrap());
+            assert_eq!(hex::encode(key.verifying_key().to_bytes()), public_key);
+            let message = hex::decode(message).unwrap();
+            let sig = key.sign(&message);
+            assert_eq!(hex::encode(sig), signature);
+            assert!(key.verifying_key().verify(&message, &sig));
+        }
+    }
+
+    #[test]
+    fn test_rejects() {
+        let key = SigningKey::generate(&mut DeterministicRng::new([0xed; 32]));
+        let public_key = key.verifying_key();
+        let sig = key.sign(b"message");
+        assert!(public_key.verify(b"message", &sig));
+        assert!(!public_key.verify(b"massage", &sig));
+        assert!(!SigningKey::from_seed([1; 32]).verifying_key().verify(b"message", &sig));
+
+        // S + L verifies the same equation, but isn't canonical
+        let mut malleated = sig;
+        let s = BigUint::from_bytes_le(&sig[32..]) + order();
+        malleated[32..].copy_from_slice(&s.to_bytes_le()[..32]);
+        assert!(!public_key.verify(b"message", &malleated));
+
+        // y = 2 has no x, and y = p isn't canonical
+        let mut two = [0_u8; 32];
+        two[0] = 2;
+        assert_eq!(VerifyingKey::from_bytes(&two), Err(KeyError::InvalidEncoding));
+        let p: [u8; 32] = encode_scalar(&prime().to_biguint().unwrap());
+        assert_eq!(VerifyingKey::from_bytes(&p), Err(KeyError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_matches_x25519() {
+        // the Montgomery u of the public point is (1 + y) / (1 - y), so the
+        // same clamped scalar gives the X25519 public key
+        let key = SigningKey::from_seed([7; 32]);
+        let y = BigInt::from_bytes_le(Sign::Plus, &{
+            let mut y = key.verifying_key().to_bytes();
+            y[31] &= 127;
+            y
+        });
+        let u = (element(1) + element(y.clone())) / (element(1) - element(y));
+        assert_eq!(
+            u.num(),
+            BigInt::from_bytes_le(
+                Sign::Plus,
+                &crate::crypto::x25519::public_key(&encode_scalar(&key.scalar))
+            )
+        );
+    }
+}
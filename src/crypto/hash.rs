@@ -0,0 +1,359 @@
+#[cfg(feature = "sha256-explained")]
+pub mod sha256_explained;
+
+use hmac::{Hmac, Mac};
+use num_bigint::BigUint;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256, Sha512};
+use sha3::Keccak256;
+
+use crate::curves::secp256k1::context::context;
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// SHA256(SHA256(data)), used for txids, block hashes and Base58Check checksums
+pub fn hash256(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+pub fn ripemd160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(data).into()
+}
+
+/// RIPEMD160(SHA256(data)), the digest behind P2PKH and P2WPKH addresses
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    ripemd160(&sha256(data))
+}
+
+/// Keccak-256 as Ethereum uses it: the original padding, not SHA3-256's
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// SHA-1, only needed because OP_SHA1 is still a valid opcode. Broken for
+/// collisions, so nothing else should use it
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0_u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, word) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(word);
+        }
+    }
+
+    let mut res = [0_u8; 20];
+    for (chunk, word) in res.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    res
+}
+
+/// Tags used by BIP340 Schnorr signatures and BIP341/342 taproot, whose
+/// midstates are computed once and reused
+pub const KNOWN_TAGS: [&str; 7] = [
+    "BIP0340/challenge",
+    "BIP0340/aux",
+    "BIP0340/nonce",
+    "TapLeaf",
+    "TapBranch",
+    "TapTweak",
+    "TapSighash",
+];
+
+pub(crate) fn midstate(tag: &str) -> Sha256 {
+    let tag_hash = sha256(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    hasher
+}
+
+/// A SHA256 engine that has already absorbed SHA256(tag) || SHA256(tag).
+/// That prefix is exactly one block, so cloning the cached engine skips
+/// compressing it again
+pub fn tagged_hasher(tag: &str) -> Sha256 {
+    match context().midstate(tag) {
+        Some(hasher) => hasher.clone(),
+        None => midstate(tag),
+    }
+}
+
+/// BIP340 tagged hash: SHA256(SHA256(tag) || SHA256(tag) || data)
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let mut hasher = tagged_hasher(tag);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// Hashes a message to an integer modulo a curve order as in SEC 1 §4.1.3:
+/// digests longer than the order are truncated to its leftmost bits before
+/// the reduction, so any `Digest` works with any curve
+pub fn hash_message<D: Digest>(message: &[u8], order: &BigUint) -> BigUint {
+    let digest = D::digest(message);
+    let mut e = BigUint::from_bytes_be(&digest);
+    let digest_bits = digest.len() as u64 * 8;
+    if digest_bits > order.bits() {
+        e >>= digest_bits - order.bits();
+    }
+    e % order
+}
+
+pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// 32-bit MurmurHash3 (x86 variant). Not cryptographic: BIP-37 bloom
+/// filters only need it to be fast and well spread
+pub fn murmur3_32(seed: u32, data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut h = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        h ^= mix(u32::from_le_bytes(block.try_into().unwrap()));
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        h ^= mix(tail.iter().rev().fold(0, |k, &b| (k << 8) | b as u32));
+    }
+
+    h ^= data.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85ebca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2ae35);
+    h ^ (h >> 16)
+}
+
+/// SipHash-2-4 keyed with (k0, k1), a fast keyed hash BIP-158 uses to map
+/// filter elements. Keyed by block hash, so collisions can't be precomputed
+pub fn siphash24(k0: u64, k1: u64, data: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+    let round = |v: &mut [u64; 4]| {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    };
+    let mut compress = |m: u64| {
+        v[3] ^= m;
+        round(&mut v);
+        round(&mut v);
+        v[0] ^= m;
+    };
+
+    let mut blocks = data.chunks_exact(8);
+    for block in &mut blocks {
+        compress(u64::from_le_bytes(block.try_into().unwrap()));
+    }
+    // the last block holds the remaining bytes and the length's low byte
+    let tail = blocks.remainder();
+    compress(tail.iter().rev().fold(0, |m, &b| (m << 8) | b as u64) | ((data.len() as u64) << 56));
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[cfg(test)]
+mod hash_tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            hex::encode(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_hash256() {
+        assert_eq!(
+            hex::encode(hash256(b"hello")),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+        assert_eq!(
+            hex::encode(hash256(b"")),
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+        );
+    }
+
+    #[test]
+    fn test_hash160() {
+        assert_eq!(hex::encode(hash160(b"")), "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb");
+    }
+
+    #[test]
+    fn test_sha1() {
+        assert_eq!(hex::encode(sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(hex::encode(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        // two padding blocks
+        assert_eq!(
+            hex::encode(sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            hex::encode(keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        // the ERC-20 transfer selector is its first four bytes
+        assert_eq!(hex::encode(&keccak256(b"transfer(address,uint256)")[..4]), "a9059cbb");
+    }
+
+    #[test]
+    fn test_murmur3() {
+        // Bitcoin Core's hash_tests, as (expected, seed, data)
+        let vectors = [
+            (0x00000000, 0x00000000, ""),
+            (0x6a396f08, 0xfba4c795, ""),
+            (0x81f16f39, 0xffffffff, ""),
+            (0x514e28b7, 0x00000000, "00"),
+            (0xea3f0b17, 0xfba4c795, "00"),
+            (0xfd6cf10d, 0x00000000, "ff"),
+            (0x16c6b7ab, 0x00000000, "0011"),
+            (0x8eb51c3d, 0x00000000, "001122"),
+            (0xb4471bf8, 0x00000000, "00112233"),
+            (0xe2301fa8, 0x00000000, "0011223344"),
+            (0xfc2e4a15, 0x00000000, "001122334455"),
+            (0xb074502c, 0x00000000, "00112233445566"),
+            (0x8034d2a0, 0x00000000, "0011223344556677"),
+            (0xb4698def, 0x00000000, "001122334455667788"),
+        ];
+        for (expected, seed, data) in vectors {
+            assert_eq!(murmur3_32(seed, &hex::decode(data).unwrap()), expected, "{}", data);
+        }
+    }
+
+    #[test]
+    fn test_siphash24() {
+        // from the SipHash paper's reference vectors, key 00..0f
+        let (k0, k1) = (0x0706050403020100, 0x0f0e0d0c0b0a0908);
+        assert_eq!(siphash24(k0, k1, &[]), 0x726fdb47dd0e0e31);
+        let data: Vec<u8> = (0..15).collect();
+        assert_eq!(siphash24(k0, k1, &data), 0xa129ca6149be45e5);
+        let data: Vec<u8> = (0..63).collect();
+        assert_eq!(siphash24(k0, k1, &data), 0x958a324ceb064572);
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        // leaf hash of the script OP_TRUE with leaf version 0xc0
+        assert_eq!(
+            hex::encode(tagged_hash("TapLeaf", &[0xc0, 0x01, 0x51])),
+            "a85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d675"
+        );
+        assert_eq!(
+            hex::encode(tagged_hash("moneda/test", b"abc")),
+            "23a6e2d154b7054061dafce0b871dfe58832cc7a0679e9d363866e6b5f58708c"
+        );
+    }
+
+    #[test]
+    fn test_tagged_hash_midstates() {
+        for tag in KNOWN_TAGS {
+            let tag_hash = sha256(tag.as_bytes());
+            let mut data = tag_hash.to_vec();
+            data.extend_from_slice(&tag_hash);
+            data.extend_from_slice(b"abc");
+            assert_eq!(tagged_hash(tag, b"abc"), sha256(&data), "{}", tag);
+        }
+        assert_eq!(
+            hex::encode(tagged_hash("BIP0340/challenge", b"abc")),
+            "770a5b7e7c304bbcc3ea107343ff951dd404312ef418db0c3b94e2ebfbb50087"
+        );
+    }
+
+    #[test]
+    fn test_hash_message() {
+        let n = crate::curves::secp256k1::ecc::order();
+
+        assert_eq!(
+            hash_message::<Sha256>(b"moneda", &n),
+            BigUint::parse_bytes(b"82d92aefc1d5990e64f63cd5cd0e2c4f8004032b71b17f7d6171a4b0a09f205b", 16).unwrap()
+        );
+        // SHA-512 is truncated to the 256 leftmost bits
+        assert_eq!(
+            hash_message::<Sha512>(b"moneda", &n),
+            BigUint::parse_bytes(b"21c4e8f290ac40e1c045cdf2428fc79b5a7e5a5d13654985015b1be995b6553b", 16).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_hash_message_small_order() {
+        // 17 has 5 bits, so the top 5 bits of the digest (31) are reduced to 14
+        assert_eq!(
+            hash_message::<Sha256>(b"moneda2", &BigUint::from(17_u8)),
+            BigUint::from(14_u8)
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha512() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex::encode(hmac_sha512(b"Jefe", b"what do ya want for nothing?")),
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+             9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! SHA-256 written out step by step (FIPS 180-4 §6.2), so the hash the crate
+//! depends on can be read and single-stepped rather than taken on faith.
+//! Use `sha2` for anything that isn't teaching.
+
+/// First 32 bits of the fractional parts of the cube roots of the first 64 primes
+pub const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5, 0xd807aa98,
+    0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8,
+    0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819,
+    0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+    0xc67178f2,
+];
+
+/// First 32 bits of the fractional parts of the square roots of the first 8 primes
+pub const H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// "choose": each bit of x picks the bit from y (1) or z (0)
+pub fn ch(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) ^ (!x & z)
+}
+
+/// "majority": each output bit is the majority vote of the three inputs
+pub fn maj(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) ^ (x & z) ^ (y & z)
+}
+
+pub fn big_sigma0(x: u32) -> u32 {
+    x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22)
+}
+
+pub fn big_sigma1(x: u32) -> u32 {
+    x.rotate_right(6) ^ x.rotate_right(11) ^ x.rotate_right(25)
+}
+
+pub fn small_sigma0(x: u32) -> u32 {
+    x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3)
+}
+
+pub fn small_sigma1(x: u32) -> u32 {
+    x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10)
+}
+
+/// Appends 0x80, zeros up to 56 mod 64 bytes and the bit length as a
+/// big-endian u64, so the message fills whole 64-byte blocks
+pub fn pad(message: &[u8]) -> Vec<u8> {
+    let mut res = message.to_vec();
+    res.push(0x80);
+    while res.len() % 64 != 56 {
+        res.push(0);
+    }
+    res.extend_from_slice(&((message.len() as u64) * 8).to_be_bytes());
+    res
+}
+
+/// Expands a block into 64 words: 16 taken directly, 48 mixed from earlier ones
+pub fn message_schedule(block: &[u8; 64]) -> [u32; 64] {
+    let mut w = [0_u32; 64];
+    for (t, word) in block.chunks(4).enumerate() {
+        w[t] = u32::from_be_bytes(word.try_into().unwrap());
+    }
+    for t in 16..64 {
+        w[t] = small_sigma1(w[t - 2])
+            .wrapping_add(w[t - 7])
+            .wrapping_add(small_sigma0(w[t - 15]))
+            .wrapping_add(w[t - 16]);
+    }
+    w
+}
+
+/// The eight working variables a..h after a round
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundState {
+    pub a: u32,
+    pub b: u32,
+    pub c: u32,
+    pub d: u32,
+    pub e: u32,
+    pub f: u32,
+    pub g: u32,
+    pub h: u32,
+}
+
+impl RoundState {
+    pub fn from(h: [u32; 8]) -> Self {
+        Self {
+            a: h[0],
+            b: h[1],
+            c: h[2],
+            d: h[3],
+            e: h[4],
+            f: h[5],
+            g: h[6],
+            h: h[7],
+        }
+    }
+
+    pub fn to_array(self) -> [u32; 8] {
+        [self.a, self.b, self.c, self.d, self.e, self.f, self.g, self.h]
+    }
+
+    /// One round: two temporaries computed from the state, the schedule
+    /// word and the round constant are folded into a and e, everything
+    /// else shifts down one position
+    pub fn round(self, k: u32, w: u32) -> (Self, u32, u32) {
+        let t1 = self
+            .h
+            .wrapping_add(big_sigma1(self.e))
+            .wrapping_add(ch(self.e, self.f, self.g))
+            .wrapping_add(k)
+            .wrapping_add(w);
+        let t2 = big_sigma0(self.a).wrapping_add(maj(self.a, self.b, self.c));
+        let next = Self {
+            a: t1.wrapping_add(t2),
+            b: self.a,
+            c: self.b,
+            d: self.c,
+            e: self.d.wrapping_add(t1),
+            f: self.e,
+            g: self.f,
+            h: self.g,
+        };
+        (next, t1, t2)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundTrace {
+    pub round: usize,
+    pub w: u32,
+    pub t1: u32,
+    pub t2: u32,
+    /// Working variables after this round
+    pub state: RoundState,
+}
+
+fn compress_inner(h: [u32; 8], block: &[u8; 64], mut trace: Option<&mut Vec<RoundTrace>>) -> [u32; 8] {
+    let w = message_schedule(block);
+    let mut state = RoundState::from(h);
+    for t in 0..64 {
+        let (next, t1, t2) = state.round(K[t], w[t]);
+        state = next;
+        if let Some(trace) = trace.as_mut() {
+            trace.push(RoundTrace {
+                round: t,
+                w: w[t],
+                t1,
+                t2,
+                state,
+            });
+        }
+    }
+    // Davies-Meyer feed-forward: add the input chaining value back in
+    let mut res = h;
+    for (r, s) in res.iter_mut().zip(state.to_array()) {
+        *r = r.wrapping_add(s);
+    }
+    res
+}
+
+/// The compression function: mixes one block into the chaining value
+pub fn compress(h: [u32; 8], block: &[u8; 64]) -> [u32; 8] {
+    compress_inner(h, block, None)
+}
+
+/// Like `compress`, also returning the state after each of the 64 rounds
+pub fn compress_traced(h: [u32; 8], block: &[u8; 64]) -> ([u32; 8], Vec<RoundTrace>) {
+    let mut trace = Vec::with_capacity(64);
+    let res = compress_inner(h, block, Some(&mut trace));
+    (res, trace)
+}
+
+fn digest(h: [u32; 8]) -> [u8; 32] {
+    let mut res = [0_u8; 32];
+    for (chunk, word) in res.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    res
+}
+
+pub fn sha256(message: &[u8]) -> [u8; 32] {
+    let h = pad(message)
+        .chunks(64)
+        .fold(H0, |h, block| compress(h, block.try_into().unwrap()));
+    digest(h)
+}
+
+/// Hashes the message, returning one 64-round trace per block
+pub fn sha256_traced(message: &[u8]) -> ([u8; 32], Vec<Vec<RoundTrace>>) {
+    let mut traces = Vec::new();
+    let mut h = H0;
+    for block in pad(message).chunks(64) {
+        let (next, trace) = compress_traced(h, block.try_into().unwrap());
+        h = next;
+        traces.push(trace);
+    }
+    (digest(h), traces)
+}
+
+#[cfg(test)]
+mod sha256_explained_tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_sha2() {
+        for len in [0, 1, 55, 56, 63, 64, 65, 1000] {
+            let message: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            assert_eq!(
+                sha256(&message),
+                crate::crypto::hash::sha256(&message),
+                "length {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        assert_eq!(pad(b"abc").len(), 64);
+        assert_eq!(pad(&[0; 55]).len(), 64);
+        assert_eq!(pad(&[0; 56]).len(), 128);
+        assert_eq!(&pad(b"abc")[56..], &24_u64.to_be_bytes());
+    }
+
+    #[test]
+    fn test_abc_rounds() {
+        // intermediate values from the FIPS 180-2 "abc" example
+        let (hash, traces) = sha256_traced(b"abc");
+        assert_eq!(traces.len(), 1);
+
+        let first = traces[0][0].state.to_array();
+        assert_eq!(
+            first,
+            [0x5d6aebcd, 0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xfa2a4622, 0x510e527f, 0x9b05688c, 0x1f83d9ab]
+        );
+        let last = traces[0][63].state.to_array();
+        assert_eq!(
+            last,
+            [0x506e3058, 0xd39a2165, 0x04d24d6c, 0xb85e2ce9, 0x5ef50f24, 0xfb121210, 0x948d25b6, 0x961f4894]
+        );
+
+        assert_eq!(
+            hex::encode(hash),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::{Mac, SimpleHmac};
+use rand::{CryptoRng, RngCore};
+use sha2::Digest;
+
+/// SP 800-90A limits: reseed after 2^48 requests, at most 2^19 bits per request
+const RESEED_INTERVAL: u64 = 1 << 48;
+const MAX_REQUEST_BYTES: usize = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrbgError {
+    ReseedRequired,
+    RequestTooLarge(usize),
+}
+
+impl Display for DrbgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrbgError::ReseedRequired => write!(f, "reseed interval exhausted"),
+            DrbgError::RequestTooLarge(n) => write!(f, "cannot generate {} bytes in one request", n),
+        }
+    }
+}
+
+impl std::error::Error for DrbgError {}
+
+/// HMAC_DRBG from NIST SP 800-90A, the generator underneath RFC 6979
+/// deterministic nonces
+#[derive(Clone)]
+pub struct HmacDrbg<D> {
+    k: Vec<u8>,
+    v: Vec<u8>,
+    reseed_counter: u64,
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest + BlockSizeUser + Clone> HmacDrbg<D> {
+    /// Instantiate: K = 0x00.., V = 0x01.., then update(entropy || nonce || personalization)
+    pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Self {
+        let size = <D as Digest>::output_size();
+        let mut drbg = Self {
+            k: vec![0x00; size],
+            v: vec![0x01; size],
+            reseed_counter: 1,
+            digest: PhantomData,
+        };
+        drbg.update(&[entropy, nonce, personalization]);
+        drbg
+    }
+
+    fn hmac(&self, data: &[&[u8]]) -> Vec<u8> {
+        let mut mac = <SimpleHmac<D> as Mac>::new_from_slice(&self.k).expect("HMAC accepts keys of any size");
+        for d in data {
+            mac.update(d);
+        }
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn update(&mut self, provided: &[&[u8]]) {
+        let mut input: Vec<&[u8]> = vec![&self.v, &[0x00]];
+        input.extend_from_slice(provided);
+        self.k = self.hmac(&input);
+        self.v = self.hmac(&[&self.v]);
+
+        if provided.iter().any(|p| !p.is_empty()) {
+            let mut input: Vec<&[u8]> = vec![&self.v, &[0x01]];
+            input.extend_from_slice(provided);
+            self.k = self.hmac(&input);
+            self.v = self.hmac(&[&self.v]);
+        }
+    }
+
+    pub fn reseed(&mut self, entropy: &[u8], additional: &[u8]) {
+        self.update(&[entropy, additional]);
+        self.reseed_counter = 1;
+    }
+
+    /// Fills `out`, mixing in optional additional input before and after
+    pub fn generate(&mut self, out: &mut [u8], additional: &[u8]) -> Result<(), DrbgError> {
+        if self.reseed_counter > RESEED_INTERVAL {
+            return Err(DrbgError::ReseedRequired);
+        }
+        if out.len() > MAX_REQUEST_BYTES {
+            return Err(DrbgError::RequestTooLarge(out.len()));
+        }
+        if !additional.is_empty() {
+            self.update(&[additional]);
+        }
+        for chunk in out.chunks_mut(self.v.len()) {
+            self.v = self.hmac(&[&self.v]);
+            chunk.copy_from_slice(&self.v[..chunk.len()]);
+        }
+        self.update(&[additional]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    pub fn reseed_counter(&self) -> u64 {
+        self.reseed_counter
+    }
+}
+
+/// Lets a seeded DRBG stand in wherever an RNG is expected, e.g. for
+/// reproducible tests. Panics once a reseed is required
+impl<D: Digest + BlockSizeUser + Clone> RngCore for HmacDrbg<D> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0_u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0_u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest).expect("HMAC_DRBG must be reseeded")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        for chunk in dest.chunks_mut(MAX_REQUEST_BYTES) {
+            self.generate(chunk, &[]).map_err(rand::Error::new)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: Digest + BlockSizeUser + Clone> CryptoRng for HmacDrbg<D> {}
+
+#[cfg(test)]
+mod hmac_drbg_tests {
+    use super::*;
+    use sha2::Sha256;
+
+    #[test]
+    fn test_rfc6979_nonces() {
+        // RFC 6979 seeds the DRBG with int2octets(x) || bits2octets(h1); these
+        // are the well known secp256k1 nonces for key 1 and "Satoshi Nakamoto"
+        let x = hex::decode("0000000000000000000000000000000000000000000000000000000000000001").unwrap();
+        let h1 = hex::decode("a0dc65ffca799873cbea0ac274015b9526505daaaed385155425f7337704883e").unwrap();
+        let mut drbg = HmacDrbg::<Sha256>::new(&x, &h1, &[]);
+        let mut k = [0_u8; 32];
+
+        drbg.generate(&mut k, &[]).unwrap();
+        assert_eq!(
+            hex::encode(k),
+            "8f8a276c19f4149656b280621e358cce24f5f52542772691ee69063b74f15d15"
+        );
+
+        // a rejected candidate continues with K = HMAC(V || 0x00), V = HMAC(V)
+        drbg.generate(&mut k, &[]).unwrap();
+        assert_eq!(
+            hex::encode(k),
+            "f15fb763a6bcbbacbde0a6a9ae2a02482bd92f3e75a50b357bd551ddd771045e"
+        );
+    }
+
+    #[test]
+    fn test_reseed_and_additional_input() {
+        let mut drbg = HmacDrbg::<Sha256>::new(b"entropy", b"nonce", b"pers");
+        let mut out = [0_u8; 40];
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(
+            hex::encode(out),
+            "df6ed256feb7c48abef85c4ce40b72b16b55be56c2e1e427ff1285a10710fec0259615b69a2d6972"
+        );
+        assert_eq!(drbg.reseed_counter(), 2);
+
+        drbg.reseed(b"reseed", &[]);
+        let mut out = [0_u8; 16];
+        drbg.generate(&mut out, b"add").unwrap();
+        assert_eq!(hex::encode(out), "f3388c9020108550bf008f692013b72f");
+        assert_eq!(drbg.reseed_counter(), 2);
+    }
+
+    #[test]
+    fn test_request_too_large() {
+        let mut drbg = HmacDrbg::<Sha256>::new(b"entropy", b"nonce", &[]);
+        let mut out = vec![0_u8; MAX_REQUEST_BYTES + 1];
+
+        assert_eq!(
+            drbg.generate(&mut out, &[]),
+            Err(DrbgError::RequestTooLarge(MAX_REQUEST_BYTES + 1))
+        );
+        drbg.fill_bytes(&mut out);
+    }
+
+    #[test]
+    fn test_rng_is_deterministic() {
+        let mut a = HmacDrbg::<Sha256>::new(b"seed", &[], &[]);
+        let mut b = HmacDrbg::<Sha256>::new(b"seed", &[], &[]);
+
+        assert_eq!(a.next_u64(), b.next_u64());
+        assert_ne!(a.next_u64(), HmacDrbg::<Sha256>::new(b"other", &[], &[]).next_u64());
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::Display;
+
+use hkdf::Hkdf;
+use sha2::{Sha256, Sha512};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KdfError {
+    /// scrypt cost parameters outside what the algorithm allows
+    InvalidParams,
+    InvalidOutputLength(usize),
+}
+
+impl Display for KdfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KdfError::InvalidParams => write!(f, "invalid scrypt parameters"),
+            KdfError::InvalidOutputLength(l) => write!(f, "invalid output length {}", l),
+        }
+    }
+}
+
+impl std::error::Error for KdfError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pbkdf2Params {
+    pub rounds: u32,
+}
+
+impl Pbkdf2Params {
+    /// Used by BIP39 and Electrum seed stretching
+    pub const SEED: Self = Self { rounds: 2048 };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScryptParams {
+    /// log2 of the CPU/memory cost N
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl ScryptParams {
+    /// BIP38 encrypted private keys: N = 16384, r = 8, p = 8
+    pub const BIP38: Self = Self { log_n: 14, r: 8, p: 8 };
+}
+
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], params: Pbkdf2Params, out: &mut [u8]) {
+    pbkdf2::pbkdf2_hmac::<Sha512>(password, salt, params.rounds, out);
+}
+
+pub fn scrypt(password: &[u8], salt: &[u8], params: ScryptParams, out: &mut [u8]) -> Result<(), KdfError> {
+    let params =
+        scrypt::Params::new(params.log_n, params.r, params.p, out.len()).map_err(|_| KdfError::InvalidParams)?;
+    scrypt::scrypt(password, salt, &params, out).map_err(|_| KdfError::InvalidOutputLength(out.len()))
+}
+
+/// HKDF-Extract (RFC 5869): concentrates the entropy of `ikm` into a
+/// pseudorandom key. An empty salt acts as a string of zeros
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+    let (prk, _) = Hkdf::<Sha256>::extract(Some(salt), ikm);
+    prk.into()
+}
+
+/// HKDF-Expand (RFC 5869): stretches a pseudorandom key into `out`, with
+/// `info` separating keys derived for different purposes. At most
+/// 255 * 32 bytes can be produced
+pub fn hkdf_expand(prk: &[u8; 32], info: &[u8], out: &mut [u8]) -> Result<(), KdfError> {
+    let hk = Hkdf::<Sha256>::from_prk(prk).map_err(|_| KdfError::InvalidParams)?;
+    hk.expand(info, out)
+        .map_err(|_| KdfError::InvalidOutputLength(out.len()))
+}
+
+/// Extract-then-expand with HKDF-SHA256
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], out: &mut [u8]) -> Result<(), KdfError> {
+    hkdf_expand(&hkdf_extract(salt, ikm), info, out)
+}
+
+#[cfg(test)]
+mod kdf_tests {
+    use super::*;
+
+    #[test]
+    fn test_pbkdf2_bip39_seed() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mut seed = [0_u8; 64];
+        pbkdf2_hmac_sha512(mnemonic.as_bytes(), b"mnemonicTREZOR", Pbkdf2Params::SEED, &mut seed);
+
+        assert_eq!(
+            hex::encode(seed),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e5349553\
+             1f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+    }
+
+    #[test]
+    fn test_scrypt_rfc7914() {
+        let mut out = [0_u8; 64];
+        scrypt(b"", b"", ScryptParams { log_n: 4, r: 1, p: 1 }, &mut out).unwrap();
+        assert_eq!(
+            hex::encode(out),
+            "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+             fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"
+        );
+
+        scrypt(b"password", b"NaCl", ScryptParams { log_n: 10, r: 8, p: 16 }, &mut out).unwrap();
+        assert_eq!(
+            hex::encode(out),
+            "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162\
+             2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"
+        );
+    }
+
+    #[test]
+    fn test_scrypt_invalid_params() {
+        let mut out = [0_u8; 32];
+
+        assert_eq!(
+            scrypt(b"", b"", ScryptParams { log_n: 4, r: 0, p: 1 }, &mut out),
+            Err(KdfError::InvalidParams)
+        );
+        assert_eq!(
+            scrypt(b"", b"", ScryptParams { log_n: 4, r: 1, p: 1 }, &mut []),
+            Err(KdfError::InvalidParams)
+        );
+    }
+
+    #[test]
+    fn test_hkdf_rfc5869() {
+        let ikm = [0x0b_u8; 22];
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+
+        let prk = hkdf_extract(&salt, &ikm);
+        assert_eq!(
+            hex::encode(prk),
+            "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"
+        );
+
+        let mut okm = [0_u8; 42];
+        hkdf_expand(&prk, &info, &mut okm).unwrap();
+        assert_eq!(
+            hex::encode(okm),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+
+        // test case 3: no salt, no info
+        hkdf_sha256(b"", &ikm, b"", &mut okm).unwrap();
+        assert_eq!(
+            hex::encode(okm),
+            "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8"
+        );
+    }
+
+    #[test]
+    fn test_hkdf_output_too_long() {
+        let mut out = vec![0_u8; 255 * 32 + 1];
+        assert_eq!(
+            hkdf_sha256(b"", b"ikm", b"", &mut out),
+            Err(KdfError::InvalidOutputLength(255 * 32 + 1))
+        );
+        assert!(hkdf_sha256(b"", b"ikm", b"", &mut out[..255 * 32]).is_ok());
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::Display;
+
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::aead::{Aead, AeadError, ChaCha20Poly1305, KEY_LEN, NONCE_LEN};
+use crate::crypto::kdf::{scrypt, KdfError, ScryptParams};
+
+// A secret, such as a seed or an extended private key, encrypted under a
+// password for storage: scrypt stretches the password into a
+// ChaCha20-Poly1305 key. The header (version, scrypt parameters and salt)
+// is the associated data, so weakening the parameters in a stored file
+// makes it fail to decrypt rather than letting the password be guessed
+// faster. The serialized form is
+//
+//   version (1) | log_n (1) | r (4) | p (4) | salt (16) | nonce (12) | ciphertext and tag
+
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = 1 + 1 + 4 + 4 + SALT_LEN;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeystoreError {
+    Truncated,
+    UnknownVersion(u8),
+    Kdf(KdfError),
+    /// Usually a wrong password
+    Aead(AeadError),
+}
+
+impl Display for KeystoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeystoreError::Truncated => write!(f, "keystore is truncated"),
+            KeystoreError::UnknownVersion(v) => write!(f, "unknown keystore version {}", v),
+            KeystoreError::Kdf(e) => write!(f, "{}", e),
+            KeystoreError::Aead(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+impl From<KdfError> for KeystoreError {
+    fn from(e: KdfError) -> Self {
+        KeystoreError::Kdf(e)
+    }
+}
+
+impl From<AeadError> for KeystoreError {
+    fn from(e: AeadError) -> Self {
+        KeystoreError::Aead(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keystore {
+    params: ScryptParams,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    pub fn encrypt<R: RngCore + CryptoRng>(
+        password: &str,
+        secret: &[u8],
+        params: ScryptParams,
+        rng: &mut R,
+    ) -> Result<Self, KeystoreError> {
+        let mut salt = [0_u8; SALT_LEN];
+        let mut nonce = [0_u8; NONCE_LEN];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+        let mut keystore = Self {
+            params,
+            salt,
+            nonce,
+            ciphertext: Vec::new(),
+        };
+        keystore.ciphertext = keystore.cipher(password)?.encrypt(&nonce, &keystore.header(), secret);
+        Ok(keystore)
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<Vec<u8>, KeystoreError> {
+        Ok(self
+            .cipher(password)?
+            .decrypt(&self.nonce, &self.header(), &self.ciphertext)?)
+    }
+
+    pub fn params(&self) -> ScryptParams {
+        self.params
+    }
+
+    fn cipher(&self, password: &str) -> Result<ChaCha20Poly1305, KdfError> {
+        let mut key = [0_u8; KEY_LEN];
+        scrypt(password.as_bytes(), &self.salt, self.params, &mut key)?;
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = vec![VERSION, self.params.log_n];
+        header.extend(self.params.r.to_be_bytes());
+        header.extend(self.params.p.to_be_bytes());
+        header.extend(self.salt);
+        header
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header();
+        bytes.extend(self.nonce);
+        bytes.extend(&self.ciphertext);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KeystoreError> {
+        if bytes.len() < HEADER_LEN + NONCE_LEN {
+            return Err(KeystoreError::Truncated);
+        }
+        if bytes[0] != VERSION {
+            return Err(KeystoreError::UnknownVersion(bytes[0]));
+        }
+        let be = |i: usize| u32::from_be_bytes(bytes[i..i + 4].try_into().expect("4 bytes"));
+        Ok(Self {
+            params: ScryptParams {
+                log_n: bytes[1],
+                r: be(2),
+                p: be(6),
+            },
+            salt: bytes[10..HEADER_LEN].try_into().expect("16 bytes"),
+            nonce: bytes[HEADER_LEN..HEADER_LEN + NONCE_LEN].try_into().expect("12 bytes"),
+            ciphertext: bytes[HEADER_LEN + NONCE_LEN..].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod keystore_tests {
+    use super::*;
+    use crate::crypto::rng::DeterministicRng;
+
+    // cheap parameters, so the tests don't spend their time in scrypt
+    const PARAMS: ScryptParams = ScryptParams { log_n: 4, r: 8, p: 1 };
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = DeterministicRng::new([0x5e; 32]);
+        let seed = [0xab_u8; 64];
+        let keystore = Keystore::encrypt("correct horse", &seed, PARAMS, &mut rng).unwrap();
+        assert_eq!(keystore.decrypt("correct horse").unwrap(), seed);
+        assert_eq!(
+            keystore.decrypt("battery staple"),
+            Err(KeystoreError::Aead(AeadError::AuthenticationFailed))
+        );
+
+        let bytes = keystore.to_bytes();
+        assert_eq!(bytes.len(), HEADER_LEN + NONCE_LEN + 64 + 16);
+        assert_eq!(Keystore::from_bytes(&bytes), Ok(keystore));
+    }
+
+    #[test]
+    fn test_rejects() {
+        let mut rng = DeterministicRng::new([0x5f; 32]);
+        let bytes = Keystore::encrypt("pw", b"secret", PARAMS, &mut rng).unwrap().to_bytes();
+
+        // the header is authenticated, so cheaper parameters don't decrypt
+        let mut weakened = bytes.clone();
+        weakened[1] = 2;
+        let keystore = Keystore::from_bytes(&weakened).unwrap();
+        assert_eq!(keystore.params().log_n, 2);
+        assert_eq!(
+            keystore.decrypt("pw"),
+            Err(KeystoreError::Aead(AeadError::AuthenticationFailed))
+        );
+
+        let mut future = bytes.clone();
+        future[0] = 2;
+        assert_eq!(Keystore::from_bytes(&future), Err(KeystoreError::UnknownVersion(2)));
+        assert_eq!(Keystore::from_bytes(&bytes[..30]), Err(KeystoreError::Truncated));
+        let truncated = Keystore::from_bytes(&bytes[..HEADER_LEN + NONCE_LEN + 4]).unwrap();
+        assert_eq!(truncated.decrypt("pw"), Err(KeystoreError::Aead(AeadError::Truncated)));
+    }
+}
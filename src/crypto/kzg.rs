@@ -0,0 +1,368 @@
+use std::fmt::Display;
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::Zero;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::arithmetic::polynomial::Polynomial;
+use crate::bn254::curve::{g1_generator, g1_infinity, g1_neg, G2Point};
+use crate::bn254::curve_order;
+use crate::bn254::pairing::pairing_check;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::ecc::multi_scalar_mul;
+#[cfg(feature = "parallel")]
+use crate::curves::secp256k1::ecc::par_multi_scalar_mul;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KzgError {
+    /// The setup only has powers of τ up to `max`
+    DegreeTooLarge {
+        degree: usize,
+        max: usize,
+    },
+    /// A multi-point opening needs as many powers of τ in G2 as points
+    TooManyPoints {
+        count: usize,
+        max: usize,
+    },
+    DuplicatePoint,
+    /// Polynomials and points must be over the BN254 scalar field
+    WrongField,
+}
+
+impl Display for KzgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KzgError::DegreeTooLarge { degree, max } => write!(f, "degree {} exceeds the setup's {}", degree, max),
+            KzgError::TooManyPoints { count, max } => write!(f, "{} points exceed the setup's {}", count, max),
+            KzgError::DuplicatePoint => write!(f, "opening points must be distinct"),
+            KzgError::WrongField => write!(f, "not in the BN254 scalar field"),
+        }
+    }
+}
+
+impl std::error::Error for KzgError {}
+
+/// An element of the BN254 scalar field, reduced from any integer
+pub fn fr(num: impl Into<BigInt>) -> FieldElement {
+    FieldElement::reduce(num.into(), curve_order()).unwrap()
+}
+
+/// The structured reference string: τⁱ G1 up to the maximum degree and
+/// τⁱ G2 up to the maximum number of points opened at once
+#[derive(Debug, Clone)]
+pub struct Setup {
+    g1_powers: Vec<Point>,
+    g2_powers: Vec<G2Point>,
+}
+
+/// f(z) = y, with the witness [q(τ)]1 for q = (f - y) / (X - z)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opening {
+    pub point: FieldElement,
+    pub value: FieldElement,
+    pub witness: Point,
+}
+
+/// f(z_i) = y_i at several points, with one witness [q(τ)]1 for
+/// q = (f - I) / Z, I interpolating the values and Z vanishing on the points
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiOpening {
+    pub points: Vec<FieldElement>,
+    pub values: Vec<FieldElement>,
+    pub witness: Point,
+}
+
+impl Setup {
+    /// A setup from a fresh random τ, for testing only: a real one comes
+    /// from a ceremony, since anyone who knows τ can open a commitment to
+    /// any value
+    pub fn generate(max_degree: usize, max_points: usize) -> Self {
+        Self::generate_with_rng(max_degree, max_points, &mut OsRng)
+    }
+
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(max_degree: usize, max_points: usize, rng: &mut R) -> Self {
+        let r = curve_order();
+        let tau = loop {
+            let mut bytes = [0_u8; 32];
+            rng.fill_bytes(&mut bytes);
+            let tau = BigInt::from_bytes_be(Sign::Plus, &bytes);
+            if !tau.is_zero() && tau < r {
+                break tau;
+            }
+        };
+        Self::from_secret(&tau, max_degree, max_points)
+    }
+
+    pub fn from_secret(tau: &BigInt, max_degree: usize, max_points: usize) -> Self {
+        let r = curve_order();
+        let powers: Vec<BigInt> = (0..=max_degree.max(max_points) as u32)
+            .map(|i| tau.modpow(&BigInt::from(i), &r))
+            .collect();
+        Self {
+            g1_powers: powers[..=max_degree]
+                .iter()
+                .map(|t| t.clone() * g1_generator())
+                .collect(),
+            g2_powers: powers[..=max_points]
+                .iter()
+                .map(|t| t.clone() * G2Point::generator())
+                .collect(),
+        }
+    }
+
+    pub fn max_degree(&self) -> usize {
+        self.g1_powers.len() - 1
+    }
+
+    /// [f(τ)]1
+    pub fn commit(&self, poly: &Polynomial) -> Result<Point, KzgError> {
+        if *poly.prime() != curve_order() {
+            return Err(KzgError::WrongField);
+        }
+        let degree = poly.degree().unwrap_or(0);
+        if degree > self.max_degree() {
+            return Err(KzgError::DegreeTooLarge {
+                degree,
+                max: self.max_degree(),
+            });
+        }
+        if poly.is_zero() {
+            return Ok(g1_infinity());
+        }
+        let scalars: Vec<BigUint> = poly.coeffs().iter().map(|c| c.num().to_biguint().unwrap()).collect();
+        Ok(multi_scalar_mul(&scalars, &self.g1_powers[..scalars.len()]))
+    }
+
+    pub fn open(&self, poly: &Polynomial, point: &FieldElement) -> Result<Opening, KzgError> {
+        if *poly.prime() != curve_order() || point.prime() != curve_order() {
+            return Err(KzgError::WrongField);
+        }
+        let value = poly.evaluate(point);
+        let divisor = Polynomial::vanishing(curve_order(), std::slice::from_ref(point));
+        let constant = Polynomial::new(curve_order(), vec![value.clone()]).map_err(|_| KzgError::WrongField)?;
+        let (quotient, _) = (poly.clone() - constant).div_rem(&divisor);
+        Ok(Opening {
+            point: point.clone(),
+            value,
+            witness: self.commit(&quotient)?,
+        })
+    }
+
+    /// e(C - y G1 + z W, G2) = e(W, τ G2), which holds when
+    /// f(τ) - y = q(τ) (τ - z)
+    pub fn verify(&self, commitment: &Point, opening: &Opening) -> bool {
+        self.verify_batch(&[(commitment.clone(), opening.clone())])
+    }
+
+    /// Checks many openings with two pairings in all, by a random linear
+    /// combination of their equations that a false opening survives with
+    /// negligible probability
+    pub fn verify_batch(&self, openings: &[(Point, Opening)]) -> bool {
+        self.verify_batch_with_rng(openings, &mut OsRng)
+    }
+
+    /// `verify_batch` drawing the weights from `rng`, e.g. a seeded
+    /// `DeterministicRng` to make a run reproducible
+    pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(&self, openings: &[(Point, Opening)], rng: &mut R) -> bool {
+        self.verify_batch_with(openings, multi_scalar_mul, rng)
+    }
+
+    /// `verify_batch` with the multi-scalar multiplications spread over
+    /// rayon's thread pool
+    #[cfg(feature = "parallel")]
+    pub fn par_verify_batch(&self, openings: &[(Point, Opening)]) -> bool {
+        self.par_verify_batch_with_rng(openings, &mut OsRng)
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn par_verify_batch_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        openings: &[(Point, Opening)],
+        rng: &mut R,
+    ) -> bool {
+        self.verify_batch_with(openings, par_multi_scalar_mul, rng)
+    }
+
+    fn verify_batch_with<R: RngCore + CryptoRng>(
+        &self,
+        openings: &[(Point, Opening)],
+        msm: fn(&[BigUint], &[Point]) -> Point,
+        rng: &mut R,
+    ) -> bool {
+        if openings.is_empty() {
+            return true;
+        }
+        let r = curve_order();
+        let mut lhs_scalars = Vec::new();
+        let mut lhs_points = Vec::new();
+        let mut witness_scalars = Vec::new();
+        let mut witnesses = Vec::new();
+        let mut value_sum = BigInt::zero();
+        for (commitment, opening) in openings {
+            if opening.point.prime() != r || opening.value.prime() != r {
+                return false;
+            }
+            let weight = if openings.len() == 1 {
+                BigInt::from(1)
+            } else {
+                random_scalar(rng)
+            };
+            value_sum = (value_sum + &weight * opening.value.num()) % &r;
+            // weight (C + z W)
+            lhs_scalars.push(weight.to_biguint().unwrap());
+            lhs_points.push(commitment.clone());
+            lhs_scalars.push((&weight * opening.point.num() % &r).to_biguint().unwrap());
+            lhs_points.push(opening.witness.clone());
+            witness_scalars.push(weight.to_biguint().unwrap());
+            witnesses.push(opening.witness.clone());
+        }
+        lhs_scalars.push(((&r - value_sum) % &r).to_biguint().unwrap());
+        lhs_points.push(g1_generator());
+        let lhs = msm(&lhs_scalars, &lhs_points);
+        let witness = msm(&witness_scalars, &witnesses);
+        pairing_check(&[(lhs, -self.g2_powers[0].clone()), (witness, self.g2_powers[1].clone())])
+    }
+
+    pub fn open_multi(&self, poly: &Polynomial, points: &[FieldElement]) -> Result<MultiOpening, KzgError> {
+        if points.len() >= self.g2_powers.len() {
+            return Err(KzgError::TooManyPoints {
+                count: points.len(),
+                max: self.g2_powers.len() - 1,
+            });
+        }
+        if *poly.prime() != curve_order() || points.iter().any(|z| z.prime() != curve_order()) {
+            return Err(KzgError::WrongField);
+        }
+        let values: Vec<FieldElement> = points.iter().map(|z| poly.evaluate(z)).collect();
+        let evaluations: Vec<_> = points.iter().cloned().zip(values.iter().cloned()).collect();
+        let interpolation = Polynomial::interpolate(curve_order(), &evaluations).ok_or(KzgError::DuplicatePoint)?;
+        let (quotient, _) = (poly.clone() - interpolation).div_rem(&Polynomial::vanishing(curve_order(), points));
+        Ok(MultiOpening {
+            points: points.to_vec(),
+            values,
+            witness: self.commit(&quotient)?,
+        })
+    }
+
+    /// e(C - [I(τ)]1, G2) = e(W, [Z(τ)]2)
+    pub fn verify_multi(&self, commitment: &Point, opening: &MultiOpening) -> bool {
+        let r = curve_order();
+        if opening.points.len() != opening.values.len()
+            || opening.points.len() >= self.g2_powers.len()
+            || opening.points.iter().chain(&opening.values).any(|x| x.prime() != r)
+        {
+            return false;
+        }
+        let evaluations: Vec<_> = opening
+            .points
+            .iter()
+            .cloned()
+            .zip(opening.values.iter().cloned())
+            .collect();
+        let Some(interpolation) = Polynomial::interpolate(r.clone(), &evaluations) else {
+            return false;
+        };
+        let Ok(interpolated) = self.commit(&interpolation) else {
+            return false;
+        };
+        let vanishing = Polynomial::vanishing(r, &opening.points);
+        let vanishing_g2 = vanishing
+            .coeffs()
+            .iter()
+            .zip(&self.g2_powers)
+            .fold(G2Point::infinity(), |acc, (c, g)| acc + c.num() * g.clone());
+        let lhs = commitment.clone() + g1_neg(&interpolated);
+        pairing_check(&[
+            (lhs, -self.g2_powers[0].clone()),
+            (opening.witness.clone(), vanishing_g2),
+        ])
+    }
+}
+
+fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> BigInt {
+    let mut bytes = [0_u8; 16];
+    rng.fill_bytes(&mut bytes);
+    BigInt::from_bytes_be(Sign::Plus, &bytes)
+}
+
+#[cfg(test)]
+mod kzg_tests {
+    use super::*;
+    use crate::crypto::rng::DeterministicRng;
+
+    fn poly(coeffs: &[i64]) -> Polynomial {
+        Polynomial::from_ints(curve_order(), coeffs)
+    }
+
+    #[test]
+    fn test_open() {
+        let tau = BigInt::from(1234567);
+        let setup = Setup::from_secret(&tau, 4, 2);
+        let f = poly(&[3, 0, -2, 5]);
+        let commitment = setup.commit(&f).unwrap();
+        assert_eq!(commitment, f.evaluate(&fr(tau)).num() * g1_generator());
+
+        let opening = setup.open(&f, &fr(10)).unwrap();
+        assert_eq!(opening.value, fr(4803));
+        assert!(setup.verify(&commitment, &opening));
+        let wrong = Opening {
+            value: fr(4804),
+            ..opening.clone()
+        };
+        assert!(!setup.verify(&commitment, &wrong));
+        let g = poly(&[3, 0, -2, 6]);
+        let other = setup.commit(&g).unwrap();
+        assert!(!setup.verify(&other, &opening));
+
+        let second = setup.open(&g, &fr(-1)).unwrap();
+        assert!(setup.verify_batch(&[(commitment.clone(), opening.clone()), (other.clone(), second.clone())]));
+        #[cfg(feature = "parallel")]
+        {
+            assert!(setup.par_verify_batch(&[(commitment.clone(), opening.clone()), (other.clone(), second.clone())]));
+            assert!(!setup.par_verify_batch(&[(commitment.clone(), opening.clone()), (other.clone(), wrong.clone())]));
+        }
+        assert!(!setup.verify_batch(&[(commitment.clone(), opening.clone()), (other.clone(), wrong.clone())]));
+        // the same weights every run
+        let mut rng = DeterministicRng::new([0x4b; 32]);
+        assert!(setup.verify_batch_with_rng(
+            &[(commitment.clone(), opening.clone()), (other.clone(), second)],
+            &mut rng
+        ));
+        assert!(!setup.verify_batch_with_rng(&[(commitment.clone(), opening), (other, wrong)], &mut rng));
+
+        assert_eq!(
+            setup.commit(&poly(&[1, 1, 1, 1, 1, 1])),
+            Err(KzgError::DegreeTooLarge { degree: 5, max: 4 })
+        );
+        let other_field = Polynomial::from_ints(BigInt::from(97), &[1]);
+        assert_eq!(setup.commit(&other_field), Err(KzgError::WrongField));
+        assert_eq!(setup.open(&other_field, &fr(10)), Err(KzgError::WrongField));
+    }
+
+    #[test]
+    fn test_open_multi() {
+        let setup = Setup::generate(4, 2);
+        let f = poly(&[7, -1, 0, 2, 1]);
+        let commitment = setup.commit(&f).unwrap();
+        let opening = setup.open_multi(&f, &[fr(2), fr(5)]).unwrap();
+        assert_eq!(opening.values, vec![fr(37), fr(877)]);
+        assert!(setup.verify_multi(&commitment, &opening));
+        let mut wrong = opening.clone();
+        wrong.values[1] = fr(878);
+        assert!(!setup.verify_multi(&commitment, &wrong));
+
+        assert_eq!(setup.open_multi(&f, &[fr(1), fr(1)]), Err(KzgError::DuplicatePoint));
+        assert_eq!(
+            setup.open_multi(&f, &[fr(1), fr(2), fr(3)]),
+            Err(KzgError::TooManyPoints { count: 3, max: 2 })
+        );
+        let other_field = Polynomial::from_ints(BigInt::from(97), &[1, 2]);
+        assert_eq!(
+            setup.open_multi(&other_field, &[fr(1), fr(2)]),
+            Err(KzgError::WrongField)
+        );
+    }
+}
@@ -0,0 +1,16 @@
+pub mod aead;
+pub mod ecdh;
+pub mod ecies;
+pub mod ed25519;
+pub mod hash;
+pub mod hmac_drbg;
+pub mod kdf;
+pub mod keystore;
+pub mod kzg;
+pub mod poseidon;
+pub mod prime;
+pub mod rng;
+pub mod schnorr;
+pub mod ssh;
+pub mod transcript;
+pub mod x25519;
@@ -0,0 +1,396 @@
+use std::fmt::Display;
+
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::crypto::hash::sha256;
+use crate::curves::secp256k1::ecc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoseidonError {
+    ZeroRate,
+    ZeroCapacity,
+    /// Full rounds are split evenly around the partial rounds
+    OddFullRounds(usize),
+    /// No small exponent is coprime to p - 1, so x^alpha isn't a permutation
+    NoSBox,
+    /// The prime is too small to build a width-t Cauchy MDS matrix
+    PrimeTooSmall,
+    WrongWidth {
+        expected: usize,
+        got: usize,
+    },
+    WrongField,
+}
+
+impl Display for PoseidonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoseidonError::ZeroRate => write!(f, "rate must be at least 1"),
+            PoseidonError::ZeroCapacity => write!(f, "capacity must be at least 1"),
+            PoseidonError::OddFullRounds(r) => write!(f, "full rounds must be even, got {}", r),
+            PoseidonError::NoSBox => write!(f, "no s-box exponent is coprime to p - 1"),
+            PoseidonError::PrimeTooSmall => write!(f, "prime too small for the state width"),
+            PoseidonError::WrongWidth { expected, got } => {
+                write!(f, "state has {} elements, expected {}", got, expected)
+            }
+            PoseidonError::WrongField => write!(f, "element is not in the permutation's field"),
+        }
+    }
+}
+
+impl std::error::Error for PoseidonError {}
+
+/// A Poseidon instance: state width, round counts, s-box exponent and the
+/// constants derived from them.
+///
+/// Round constants are drawn from SHA-256 over the instance description
+/// rather than the reference Grain LFSR, and the MDS matrix is the Cauchy
+/// matrix 1 / (i + t + j). Outputs therefore won't match other Poseidon
+/// deployments even over the same field.
+#[derive(Debug, Clone)]
+pub struct PoseidonParams {
+    prime: BigInt,
+    rate: usize,
+    capacity: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    alpha: BigInt,
+    round_constants: Vec<Vec<FieldElement>>,
+    mds: Vec<Vec<FieldElement>>,
+}
+
+impl PoseidonParams {
+    pub fn new(
+        prime: BigInt,
+        rate: usize,
+        capacity: usize,
+        full_rounds: usize,
+        partial_rounds: usize,
+    ) -> Result<Self, PoseidonError> {
+        if rate == 0 {
+            return Err(PoseidonError::ZeroRate);
+        }
+        if capacity == 0 {
+            return Err(PoseidonError::ZeroCapacity);
+        }
+        if !full_rounds.is_multiple_of(2) {
+            return Err(PoseidonError::OddFullRounds(full_rounds));
+        }
+        let width = rate + capacity;
+        if prime <= BigInt::from(2 * width) {
+            return Err(PoseidonError::PrimeTooSmall);
+        }
+
+        // smallest odd alpha >= 3 with gcd(alpha, p - 1) = 1
+        let order = &prime - 1_u8;
+        let alpha = (3_u32..=31)
+            .step_by(2)
+            .map(BigInt::from)
+            .find(|a| a.gcd(&order).is_one())
+            .ok_or(PoseidonError::NoSBox)?;
+
+        let mut params = Self {
+            prime,
+            rate,
+            capacity,
+            full_rounds,
+            partial_rounds,
+            alpha,
+            round_constants: Vec::new(),
+            mds: Vec::new(),
+        };
+        params.round_constants = params.generate_round_constants();
+        params.mds = params.cauchy_mds();
+        Ok(params)
+    }
+
+    /// Rate 2, capacity 1 over the secp256k1 base field: 8 full and 56
+    /// partial rounds with alpha = 5
+    pub fn secp256k1() -> Self {
+        Self::new(ecc::prime(), 2, 1, 8, 56).unwrap()
+    }
+
+    pub fn width(&self) -> usize {
+        self.rate + self.capacity
+    }
+
+    pub fn rate(&self) -> usize {
+        self.rate
+    }
+
+    pub fn alpha(&self) -> &BigInt {
+        &self.alpha
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.full_rounds + self.partial_rounds
+    }
+
+    pub fn round_constants(&self) -> &[Vec<FieldElement>] {
+        &self.round_constants
+    }
+
+    pub fn mds(&self) -> &[Vec<FieldElement>] {
+        &self.mds
+    }
+
+    /// Reduces `num`; `new` already rejected primes too small for a field
+    fn element(&self, num: BigInt) -> FieldElement {
+        FieldElement::reduce(num, self.prime.clone()).unwrap()
+    }
+
+    /// SHA-256 in counter mode over the instance description, rejecting
+    /// samples >= p so the constants are uniform in the field
+    fn generate_round_constants(&self) -> Vec<Vec<FieldElement>> {
+        let mut seed = b"moneda/poseidon".to_vec();
+        seed.extend_from_slice(&self.prime.to_bytes_be().1);
+        for n in [self.width(), self.full_rounds, self.partial_rounds] {
+            seed.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+
+        let bits = self.prime.bits();
+        let blocks = bits.div_ceil(256);
+        let mask = (BigInt::one() << bits) - 1_u8;
+        let mut counter = 0_u64;
+        let mut sample = || loop {
+            let mut bytes = Vec::with_capacity(blocks as usize * 32);
+            for _ in 0..blocks {
+                let mut data = seed.clone();
+                data.extend_from_slice(&counter.to_be_bytes());
+                counter += 1;
+                bytes.extend_from_slice(&sha256(&data));
+            }
+            let num = BigInt::from_bytes_be(Sign::Plus, &bytes) & &mask;
+            if num < self.prime {
+                return num;
+            }
+        };
+
+        (0..self.rounds())
+            .map(|_| (0..self.width()).map(|_| self.element(sample())).collect())
+            .collect()
+    }
+
+    /// M[i][j] = 1 / (x_i + y_j) with x_i = i and y_j = t + j. Every square
+    /// submatrix of a Cauchy matrix is invertible, which is what MDS means
+    fn cauchy_mds(&self) -> Vec<Vec<FieldElement>> {
+        let t = self.width();
+        (0..t)
+            .map(|i| {
+                (0..t)
+                    .map(|j| self.element(BigInt::one()) / self.element(BigInt::from(i + t + j)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn sbox(&self, x: FieldElement) -> FieldElement {
+        x.pow(self.alpha.clone())
+    }
+
+    fn mix(&self, state: &[FieldElement]) -> Vec<FieldElement> {
+        self.mds
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(state)
+                    .fold(self.element(BigInt::zero()), |acc, (m, s)| acc + m.clone() * s.clone())
+            })
+            .collect()
+    }
+
+    /// The Poseidon permutation: R_F / 2 full rounds, R_P partial rounds
+    /// (s-box on the first element only), R_F / 2 full rounds. Each round is
+    /// add-constants, s-box, MDS mix
+    pub fn permute(&self, state: &mut [FieldElement]) -> Result<(), PoseidonError> {
+        if state.len() != self.width() {
+            return Err(PoseidonError::WrongWidth {
+                expected: self.width(),
+                got: state.len(),
+            });
+        }
+        if state.iter().any(|s| s.prime() != self.prime) {
+            return Err(PoseidonError::WrongField);
+        }
+
+        let half = self.full_rounds / 2;
+        for (round, constants) in self.round_constants.iter().enumerate() {
+            let full = round < half || round >= half + self.partial_rounds;
+            let mut next: Vec<FieldElement> = state
+                .iter()
+                .zip(constants)
+                .map(|(s, c)| s.clone() + c.clone())
+                .collect();
+            if full {
+                next = next.into_iter().map(|s| self.sbox(s)).collect();
+            } else {
+                next[0] = self.sbox(next[0].clone());
+            }
+            state.clone_from_slice(&self.mix(&next));
+        }
+        Ok(())
+    }
+
+    /// Sponge hash of a variable-length input to one field element
+    pub fn hash(&self, inputs: &[FieldElement]) -> Result<FieldElement, PoseidonError> {
+        let mut sponge = Sponge::new(self);
+        sponge.absorb(inputs)?;
+        sponge.squeeze(1).map(|mut out| out.remove(0))
+    }
+}
+
+/// A duplex sponge over the Poseidon permutation. The rate part of the state
+/// takes input and gives output; the capacity part is never touched directly
+#[derive(Debug, Clone)]
+pub struct Sponge<'a> {
+    params: &'a PoseidonParams,
+    state: Vec<FieldElement>,
+    /// Elements absorbed into the current rate block
+    absorbed: usize,
+    /// Elements of the current rate block not yet squeezed
+    available: usize,
+}
+
+impl<'a> Sponge<'a> {
+    pub fn new(params: &'a PoseidonParams) -> Self {
+        let mut state = vec![params.element(BigInt::zero()); params.width()];
+        // domain separation: a 2^64 tag in the capacity marks variable-length
+        // hashing, as in the Poseidon paper
+        state[params.rate] = params.element((BigInt::one() << 64) % &params.prime);
+        Self {
+            params,
+            state,
+            absorbed: 0,
+            available: 0,
+        }
+    }
+
+    pub fn absorb(&mut self, inputs: &[FieldElement]) -> Result<(), PoseidonError> {
+        for input in inputs {
+            if input.prime() != self.params.prime {
+                return Err(PoseidonError::WrongField);
+            }
+            if self.absorbed == self.params.rate {
+                self.params.permute(&mut self.state)?;
+                self.absorbed = 0;
+            }
+            self.state[self.absorbed] = self.state[self.absorbed].clone() + input.clone();
+            self.absorbed += 1;
+            self.available = 0;
+        }
+        Ok(())
+    }
+
+    pub fn squeeze(&mut self, count: usize) -> Result<Vec<FieldElement>, PoseidonError> {
+        let mut res = Vec::with_capacity(count);
+        while res.len() < count {
+            if self.available == 0 {
+                // pad with a single 1 so inputs differing only by trailing
+                // zeros don't collide
+                if self.absorbed < self.params.rate {
+                    let one = self.params.element(BigInt::one());
+                    self.state[self.absorbed] = self.state[self.absorbed].clone() + one;
+                }
+                self.params.permute(&mut self.state)?;
+                self.absorbed = self.params.rate;
+                self.available = self.params.rate;
+            }
+            res.push(self.state[self.params.rate - self.available].clone());
+            self.available -= 1;
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod poseidon_tests {
+    use super::*;
+
+    fn elements(params: &PoseidonParams, nums: &[u32]) -> Vec<FieldElement> {
+        nums.iter().map(|n| params.element(BigInt::from(*n))).collect()
+    }
+
+    #[test]
+    fn test_params() {
+        let params = PoseidonParams::secp256k1();
+        assert_eq!(params.width(), 3);
+        // p - 1 is divisible by 3 for secp256k1, so the cube map isn't a permutation
+        assert_eq!(params.alpha(), &BigInt::from(5_u8));
+        assert_eq!(params.round_constants().len(), 64);
+
+        let t = params.width();
+        for (i, row) in params.mds().iter().enumerate() {
+            for (j, m) in row.iter().enumerate() {
+                let denom = params.element(BigInt::from(i + t + j));
+                assert_eq!(m.clone() * denom, params.element(BigInt::one()));
+            }
+        }
+
+        assert_eq!(
+            PoseidonParams::new(ecc::prime(), 0, 1, 8, 56).unwrap_err(),
+            PoseidonError::ZeroRate
+        );
+        assert_eq!(
+            PoseidonParams::new(ecc::prime(), 2, 1, 7, 56).unwrap_err(),
+            PoseidonError::OddFullRounds(7)
+        );
+        assert_eq!(
+            PoseidonParams::new(BigInt::from(5_u8), 2, 1, 8, 56).unwrap_err(),
+            PoseidonError::PrimeTooSmall
+        );
+        // p = 31: p - 1 = 30 shares a factor with 3 and 5, alpha falls to 7
+        assert_eq!(
+            PoseidonParams::new(BigInt::from(31_u8), 2, 1, 8, 8).unwrap().alpha(),
+            &BigInt::from(7_u8)
+        );
+    }
+
+    #[test]
+    fn test_permute() {
+        let params = PoseidonParams::secp256k1();
+        let mut a = elements(&params, &[0, 1, 2]);
+        let mut b = elements(&params, &[0, 1, 3]);
+        params.permute(&mut a).unwrap();
+        params.permute(&mut b).unwrap();
+        assert_ne!(a, b);
+
+        let mut again = elements(&params, &[0, 1, 2]);
+        params.permute(&mut again).unwrap();
+        assert_eq!(a, again);
+
+        let mut short = elements(&params, &[0, 1]);
+        assert_eq!(
+            params.permute(&mut short).unwrap_err(),
+            PoseidonError::WrongWidth { expected: 3, got: 2 }
+        );
+    }
+
+    #[test]
+    fn test_hash() {
+        let params = PoseidonParams::secp256k1();
+        let h = params.hash(&elements(&params, &[1, 2, 3])).unwrap();
+        assert_eq!(h, params.hash(&elements(&params, &[1, 2, 3])).unwrap());
+        assert_ne!(h, params.hash(&elements(&params, &[1, 2])).unwrap());
+        // padding keeps trailing zeros significant
+        assert_ne!(
+            params.hash(&elements(&params, &[1])).unwrap(),
+            params.hash(&elements(&params, &[1, 0])).unwrap()
+        );
+        assert_ne!(
+            params.hash(&[]).unwrap(),
+            params.hash(&elements(&params, &[0])).unwrap()
+        );
+
+        let mut sponge = Sponge::new(&params);
+        sponge.absorb(&elements(&params, &[1, 2, 3])).unwrap();
+        let out = sponge.squeeze(3).unwrap();
+        assert_eq!(out[0], h);
+        assert_ne!(out[1], out[2]);
+
+        let other = FieldElement::from(BigInt::from(1_u8), BigInt::from(223_u8)).unwrap();
+        assert_eq!(params.hash(&[other]).unwrap_err(), PoseidonError::WrongField);
+    }
+}
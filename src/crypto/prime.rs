@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+/// Miller–Rabin rounds for generated primes: each round lets a composite
+/// through with probability at most 1/4
+pub const MILLER_RABIN_ROUNDS: usize = 40;
+
+/// `factor` divides by every odd number below this before switching to
+/// Pollard's rho
+const TRIAL_DIVISION_BOUND: u64 = 1 << 16;
+
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+/// Trial division by small primes, then Miller–Rabin with `rounds` random
+/// bases. Never wrong for primes; wrong for a composite with probability
+/// at most 4^-rounds
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    for p in SMALL_PRIMES {
+        if *n == BigUint::from(p) {
+            return true;
+        }
+        if (n % p).is_zero() {
+            return false;
+        }
+    }
+    if *n < BigUint::from(2_u8) {
+        return false;
+    }
+
+    // n - 1 = d 2^s with d odd
+    let n_minus_one = n - 1_u8;
+    let s = n_minus_one.trailing_zeros().unwrap();
+    let d = &n_minus_one >> s;
+    let two = BigUint::from(2_u8);
+    'witness: for _ in 0..rounds {
+        // a in [2, n - 2]
+        let a = random_below(&(n - 3_u8), &mut OsRng) + 2_u8;
+        let mut x = a.modpow(&d, n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = x.modpow(&two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// A random prime of exactly `bits` bits, with the top two set so that the
+/// product of two of them has exactly 2 `bits`
+pub fn random_prime<R: RngCore + CryptoRng>(bits: u64, rng: &mut R) -> BigUint {
+    assert!(bits >= 8, "primes need at least 8 bits");
+    loop {
+        let mut candidate = random_bits(bits, rng);
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(bits - 2, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime(&candidate, MILLER_RABIN_ROUNDS) {
+            return candidate;
+        }
+    }
+}
+
+/// The prime factors of `n` with their exponents, smallest first: trial
+/// division below `TRIAL_DIVISION_BOUND`, then Pollard's rho on what's
+/// left until every part is a probable prime. Quick while the second
+/// largest factor has up to about 25 digits, as for p - 1 of the curves'
+/// primes
+pub fn factor(n: &BigUint) -> Vec<(BigUint, u32)> {
+    assert!(!n.is_zero(), "zero has no factorization");
+    let mut factors = BTreeMap::new();
+    let mut rest = n.clone();
+    let mut d = 2_u64;
+    while d < TRIAL_DIVISION_BOUND && BigUint::from(d * d) <= rest {
+        while (&rest % d).is_zero() {
+            rest /= d;
+            *factors.entry(BigUint::from(d)).or_insert(0) += 1;
+        }
+        d += if d == 2 { 1 } else { 2 };
+    }
+    // nothing below d divides what's left, so below d^2 it's prime
+    let mut parts = vec![rest];
+    while let Some(part) = parts.pop() {
+        if part.is_one() {
+            continue;
+        }
+        if part < BigUint::from(d * d) || is_probable_prime(&part, MILLER_RABIN_ROUNDS) {
+            *factors.entry(part).or_insert(0) += 1;
+            continue;
+        }
+        let divisor = pollard_rho(&part).expect("part is composite");
+        parts.push(&part / &divisor);
+        parts.push(divisor);
+    }
+    factors.into_iter().collect()
+}
+
+/// A nontrivial factor of `n`, or None when it's 1 or prime. Pollard's rho
+/// iterates x -> x^2 + c mod n until two values collide mod an unknown
+/// prime factor p, which by the birthday bound takes about sqrt(p) steps,
+/// and gcd(x - y, n) then reveals p. Floyd's cycle finding keeps x at
+/// twice y's step count; a collision mod n itself means a new c
+pub fn pollard_rho(n: &BigUint) -> Option<BigUint> {
+    if *n <= BigUint::from(3_u8) || is_probable_prime(n, MILLER_RABIN_ROUNDS) {
+        return None;
+    }
+    if n.is_even() {
+        return Some(BigUint::from(2_u8));
+    }
+    (1_u32..).find_map(|c| {
+        let f = |x: &BigUint| (x * x + c) % n;
+        let (mut x, mut y) = (BigUint::from(2_u8), BigUint::from(2_u8));
+        loop {
+            x = f(&x);
+            y = f(&f(&y));
+            let d = if x > y { &x - &y } else { &y - &x }.gcd(n);
+            if d == *n {
+                return None;
+            }
+            if !d.is_one() {
+                return Some(d);
+            }
+        }
+    })
+}
+
+/// A factor p of `n` for which p - 1 divides `bound`!, by Pollard's p - 1
+/// method: a = 2^(k!) mod n is then 1 mod p by Fermat, so gcd(a - 1, n)
+/// reveals p. None if no such factor turns up, or if every factor does
+/// at the same step. Primes with smooth p - 1 are why RSA keys were once
+/// required to use "strong" primes
+pub fn pollard_p_minus_1(n: &BigUint, bound: u64) -> Option<BigUint> {
+    let mut a = BigUint::from(2_u8);
+    for k in 2..=bound {
+        a = a.modpow(&BigUint::from(k), n);
+        let d = (&a + n - 1_u8).gcd(n);
+        if d == *n {
+            return None;
+        }
+        if !d.is_one() {
+            return Some(d);
+        }
+    }
+    None
+}
+
+/// Uniform in [0, bound), by rejection
+pub fn random_below<R: RngCore + CryptoRng>(bound: &BigUint, rng: &mut R) -> BigUint {
+    assert!(!bound.is_zero(), "empty range");
+    loop {
+        let candidate = random_bits(bound.bits(), rng);
+        if candidate < *bound {
+            return candidate;
+        }
+    }
+}
+
+/// A uniform element of Z*_n
+pub fn random_unit<R: RngCore + CryptoRng>(n: &BigUint, rng: &mut R) -> BigUint {
+    loop {
+        let r = random_below(n, rng);
+        if !r.is_zero() && r.gcd(n).is_one() {
+            return r;
+        }
+    }
+}
+
+fn random_bits<R: RngCore + CryptoRng>(bits: u64, rng: &mut R) -> BigUint {
+    let mut bytes = vec![0_u8; bits.div_ceil(8) as usize];
+    rng.fill_bytes(&mut bytes);
+    let excess = bytes.len() as u64 * 8 - bits;
+    if let Some(first) = bytes.first_mut() {
+        *first &= 0xff >> excess;
+    }
+    BigUint::from_bytes_be(&bytes)
+}
+
+#[cfg(test)]
+mod prime_tests {
+    use super::*;
+
+    #[test]
+    fn test_is_probable_prime() {
+        let primes = [2_u64, 3, 97, 7919, 1_000_000_007, 18_446_744_073_709_551_557];
+        for p in primes {
+            assert!(is_probable_prime(&BigUint::from(p), 20), "{}", p);
+        }
+        // Carmichael numbers fool the Fermat test but not Miller–Rabin
+        let composites = [
+            0_u64,
+            1,
+            4,
+            561,
+            1105,
+            41041,
+            3_215_031_751,
+            1_000_000_007 * 998_244_353,
+        ];
+        for n in composites {
+            assert!(!is_probable_prime(&BigUint::from(n), 20), "{}", n);
+        }
+        let mersenne = (BigUint::one() << 127) - 1_u8;
+        assert!(is_probable_prime(&mersenne, 20));
+        assert!(!is_probable_prime(&((BigUint::one() << 128) - 1_u8), 20));
+    }
+
+    #[test]
+    fn test_factor() {
+        let pairs =
+            |n: &BigUint| -> Vec<(String, u32)> { factor(n).into_iter().map(|(p, e)| (p.to_string(), e)).collect() };
+        assert_eq!(pairs(&BigUint::one()), vec![]);
+        assert_eq!(
+            pairs(&BigUint::from(222_u8)),
+            vec![("2".into(), 1), ("3".into(), 1), ("37".into(), 1)]
+        );
+        assert_eq!(pairs(&BigUint::from(1024_u16)), vec![("2".into(), 10)]);
+        assert_eq!(
+            pairs(&BigUint::from(7919_u16 * 3)),
+            vec![("3".into(), 1), ("7919".into(), 1)]
+        );
+
+        // secp256k1's p - 1, whose last factor is a 72-digit prime
+        let p_minus_one = crate::curves::secp256k1::ecc::prime().to_biguint().unwrap() - 1_u8;
+        let large = "205115282021455665897114700593932402728804164701536103180137503955397371";
+        let expected = [("2", 1), ("3", 1), ("7", 1), ("13441", 1), (large, 1)].map(|(p, e)| (p.to_string(), e));
+        assert_eq!(pairs(&p_minus_one), expected);
+
+        // rho splits what trial division leaves of 2^128 - 1
+        let factors = factor(&((BigUint::one() << 128) - 1_u8));
+        let primes = [
+            "3",
+            "5",
+            "17",
+            "257",
+            "641",
+            "65537",
+            "274177",
+            "6700417",
+            "67280421310721",
+        ];
+        assert_eq!(factors, primes.map(|p| (p.parse().unwrap(), 1)));
+        assert_eq!(
+            pairs(&BigUint::from(1_000_000_007_u64 * 998_244_353)),
+            vec![("998244353".into(), 1), ("1000000007".into(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_pollard() {
+        let n = BigUint::from(1_000_000_007_u64 * 998_244_353);
+        let d = pollard_rho(&n).unwrap();
+        assert!(d == BigUint::from(998_244_353_u32) || d == BigUint::from(1_000_000_007_u32));
+        assert_eq!(pollard_rho(&BigUint::from(1_000_000_007_u32)), None);
+        assert_eq!(pollard_rho(&BigUint::one()), None);
+        assert_eq!(pollard_rho(&BigUint::from(1024_u16)), Some(BigUint::from(2_u8)));
+
+        // 1000000007 - 1 = 2 * 500000003, 998244353 - 1 = 2^23 * 7 * 17
+        assert_eq!(pollard_p_minus_1(&n, 100), Some(BigUint::from(998_244_353_u32)));
+        assert_eq!(pollard_p_minus_1(&n, 16), None);
+    }
+
+    #[test]
+    fn test_random_prime() {
+        let p = random_prime(128, &mut OsRng);
+        assert_eq!(p.bits(), 128);
+        assert!(p.bit(126));
+        assert!(is_probable_prime(&p, 20));
+        let bound = BigUint::from(10_u8);
+        assert!((0..50).all(|_| random_below(&bound, &mut OsRng) < bound));
+    }
+}
@@ -0,0 +1,107 @@
+use num_bigint::BigUint;
+use num_traits::Zero;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::curves::secp256k1::ecc::order;
+
+// Everything that draws randomness takes any `RngCore + CryptoRng`, with
+// the functions that don't defaulting to OsRng. Passing a seeded
+// DeterministicRng instead makes keys, proofs and ciphertexts repeat from
+// run to run, for examples, doc tests and fixtures
+
+/// ChaCha20 keyed by a 32-byte seed. Anyone who knows the seed knows every
+/// output, so never for real keys
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeterministicRng(ChaCha20Rng);
+
+impl DeterministicRng {
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self(ChaCha20Rng::from_seed(seed))
+    }
+}
+
+impl SeedableRng for DeterministicRng {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::new(seed)
+    }
+}
+
+impl RngCore for DeterministicRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for DeterministicRng {}
+
+/// Uniform in [1, n) for the secp256k1 order n, by rejection
+pub fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> BigUint {
+    let n = order();
+    loop {
+        let mut bytes = [0_u8; 32];
+        rng.fill_bytes(&mut bytes);
+        let k = BigUint::from_bytes_be(&bytes);
+        if !k.is_zero() && k < n {
+            return k;
+        }
+    }
+}
+
+#[cfg(test)]
+mod rng_tests {
+    use super::*;
+    use crate::curves::secp256k1::ecc::PrivateKey;
+    use crate::sigma::{prove_dlog_with_rng, verify_dlog};
+
+    #[test]
+    fn test_reproducible() {
+        let mut a = DeterministicRng::seed_from_u64(2480);
+        let mut b = DeterministicRng::seed_from_u64(2480);
+        let mut c = DeterministicRng::seed_from_u64(2481);
+        let (x, y, z) = (random_scalar(&mut a), random_scalar(&mut b), random_scalar(&mut c));
+        assert_eq!(x, y);
+        assert_ne!(x, z);
+        assert!(!x.is_zero() && x < order());
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_reproducible_keys_and_proofs() {
+        let run = |seed| {
+            let mut rng = DeterministicRng::seed_from_u64(seed);
+            let key = PrivateKey::generate(&mut rng);
+            let g = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+            let proof = prove_dlog_with_rng(&key, &g, &mut rng);
+            (key, proof)
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1).0, run(2).0);
+        let (key, proof) = run(1);
+        assert!(verify_dlog(&proof, &key.public_key()));
+    }
+
+    #[test]
+    fn test_chacha20_stream() {
+        // the first block of ChaCha20 under the all-zero key and nonce,
+        // from RFC 7539 section 2.3.2's test vector set (A.1 #1)
+        let mut rng = DeterministicRng::new([0; 32]);
+        let mut block = [0_u8; 16];
+        rng.fill_bytes(&mut block);
+        assert_eq!(hex::encode(block), "76b8e0ada0f13d90405d6ae55386bd28");
+    }
+}
@@ -0,0 +1,493 @@
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::hash::tagged_hash;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::backend;
+#[cfg(feature = "parallel")]
+use crate::curves::secp256k1::ecc::par_multi_scalar_mul;
+use crate::curves::secp256k1::ecc::{
+    generator, multi_scalar_mul, order, prime, to_32_bytes, KeyError, PrivateKey, PublicKey,
+};
+use crate::trace::{trace_event, trace_span};
+
+fn has_even_y(public_key: &PublicKey) -> bool {
+    !public_key.point().y().unwrap().is_odd()
+}
+
+fn x_bytes(public_key: &PublicKey) -> [u8; 32] {
+    to_32_bytes(&public_key.point().x().unwrap().num())
+}
+
+fn scalar_bytes(n: &BigUint) -> [u8; 32] {
+    to_32_bytes(&BigInt::from(n.clone()))
+}
+
+/// BIP-340 public keys are just x: the point with that x and even y
+pub fn xonly(public_key: &PublicKey) -> [u8; 32] {
+    x_bytes(public_key)
+}
+
+/// The even-y point with the given x coordinate
+pub fn lift_x(x: &[u8; 32]) -> Result<PublicKey, KeyError> {
+    let mut sec = [0x02; 33];
+    sec[1..].copy_from_slice(x);
+    PublicKey::parse(&sec)
+}
+
+/// The secret for the even-y version of the key's point: d if d * G has
+/// even y, n - d otherwise
+pub fn even_y_secret(private_key: &PrivateKey) -> BigUint {
+    if has_even_y(&private_key.public_key()) {
+        private_key.secret().clone()
+    } else {
+        order() - private_key.secret()
+    }
+}
+
+/// BIP-340 signing. `aux_rand` is mixed into the nonce to protect against
+/// side channels and fault attacks; the signature stays correct for any value
+pub fn sign(private_key: &PrivateKey, msg: &[u8], aux_rand: &[u8; 32]) -> [u8; 64] {
+    let n = order();
+    let d = even_y_secret(private_key);
+    let p = xonly(&private_key.public_key());
+
+    let mut t = scalar_bytes(&d);
+    for (t, a) in t.iter_mut().zip(tagged_hash("BIP0340/aux", aux_rand)) {
+        *t ^= a;
+    }
+    let nonce = tagged_hash("BIP0340/nonce", &[&t[..], &p, msg].concat());
+    let k0 = BigUint::from_bytes_be(&nonce) % &n;
+    // only happens with negligible probability
+    assert!(!k0.is_zero(), "BIP-340 nonce is zero");
+    sign_with_nonce(private_key, msg, &k0)
+}
+
+/// BIP-340 signing with a caller-chosen nonce k0 in [1, n), for protocols
+/// that derive or tweak it themselves. Reusing k0 for two messages
+/// reveals the key
+pub fn sign_with_nonce(private_key: &PrivateKey, msg: &[u8], k0: &BigUint) -> [u8; 64] {
+    let n = order();
+    assert!(!k0.is_zero() && *k0 < n, "nonce not in range [1, n)");
+    let d = even_y_secret(private_key);
+    let p = xonly(&private_key.public_key());
+    trace_span!("schnorr.sign", public_key = %hex::encode(p), msg_len = msg.len());
+    let r_point = PublicKey::from(backend::mul_generator(k0)).expect("nonce is in [1, n)");
+    let k = if has_even_y(&r_point) { k0.clone() } else { &n - k0 };
+    let r = x_bytes(&r_point);
+    let e = challenge(&r, &p, msg);
+
+    let mut sig = [0_u8; 64];
+    sig[..32].copy_from_slice(&r);
+    sig[32..].copy_from_slice(&scalar_bytes(&((k + e * d) % &n)));
+    sig
+}
+
+fn challenge(r: &[u8; 32], p: &[u8; 32], msg: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(&tagged_hash("BIP0340/challenge", &[&r[..], p, msg].concat())) % order()
+}
+
+/// BIP-340 verification: R = s * G - e * P must have even y and x = r
+pub fn verify(public_key: &[u8; 32], msg: &[u8], sig: &[u8; 64]) -> bool {
+    trace_span!("schnorr.verify", public_key = %hex::encode(public_key), msg_len = msg.len());
+    let valid = is_valid(public_key, msg, sig);
+    trace_event!(valid);
+    valid
+}
+
+fn is_valid(public_key: &[u8; 32], msg: &[u8], sig: &[u8; 64]) -> bool {
+    let Ok(p) = lift_x(public_key) else {
+        return false;
+    };
+    let n = order();
+    let r = BigUint::from_bytes_be(&sig[..32]);
+    let s = BigUint::from_bytes_be(&sig[32..]);
+    if BigInt::from(r.clone()) >= prime() || s >= n {
+        return false;
+    }
+    let e = challenge(sig[..32].try_into().unwrap(), public_key, msg);
+    let r_point = backend::mul_add(&s, &((&n - e) % &n), p.point());
+    match (r_point.x(), r_point.y()) {
+        (Some(x), Some(y)) => !y.is_odd() && x.num().to_biguint().unwrap() == r,
+        _ => false,
+    }
+}
+
+/// R, P, s and e of one signature in a batch, or None when its encoding
+/// alone rules it out
+fn batch_terms(public_key: &[u8; 32], msg: &[u8], sig: &[u8; 64]) -> Option<(Point, Point, BigUint, BigUint)> {
+    let p = lift_x(public_key).ok()?;
+    let r = lift_x(sig[..32].try_into().unwrap()).ok()?;
+    let s = BigUint::from_bytes_be(&sig[32..]);
+    if s >= order() {
+        return None;
+    }
+    let e = challenge(sig[..32].try_into().unwrap(), public_key, msg);
+    Some((r.point().clone(), p.point().clone(), s, e))
+}
+
+/// With weights a_1 = 1 and random a_2, a_3, ..., every signature holds when
+/// (sum a_i s_i) G = sum a_i R_i + sum a_i e_i P_i, up to a false batch
+/// passing with probability about 2^-128
+fn batch_equation<R: RngCore + CryptoRng>(
+    terms: Vec<(Point, Point, BigUint, BigUint)>,
+    msm: fn(&[BigUint], &[Point]) -> Point,
+    rng: &mut R,
+) -> bool {
+    let n = order();
+    let mut scalars = Vec::with_capacity(2 * terms.len() + 1);
+    let mut points = Vec::with_capacity(2 * terms.len() + 1);
+    let mut s_sum = BigUint::zero();
+    for (i, (r, p, s, e)) in terms.into_iter().enumerate() {
+        let weight = if i == 0 { BigUint::one() } else { random_weight(rng) };
+        s_sum = (s_sum + &weight * s) % &n;
+        scalars.push((&weight * e) % &n);
+        points.push(p);
+        scalars.push(weight);
+        points.push(r);
+    }
+    scalars.push((&n - s_sum) % &n);
+    points.push(generator());
+    msm(&scalars, &points).is_infinity()
+}
+
+fn random_weight<R: RngCore + CryptoRng>(rng: &mut R) -> BigUint {
+    let mut bytes = [0_u8; 16];
+    rng.fill_bytes(&mut bytes);
+    BigUint::from_bytes_be(&bytes)
+}
+
+/// BIP-340 batch verification of (public key, message, signature) triples:
+/// one multi-scalar multiplication instead of a verification each. True
+/// only if every signature is valid, and for the empty batch
+pub fn verify_batch(items: &[([u8; 32], &[u8], [u8; 64])]) -> bool {
+    verify_batch_with_rng(items, &mut OsRng)
+}
+
+/// `verify_batch` drawing the weights from `rng`, e.g. a seeded
+/// `DeterministicRng` to make a run reproducible
+pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(items: &[([u8; 32], &[u8], [u8; 64])], rng: &mut R) -> bool {
+    trace_span!("schnorr.verify_batch", count = items.len());
+    let terms: Option<Vec<_>> = items.iter().map(|(p, msg, sig)| batch_terms(p, msg, sig)).collect();
+    let valid = terms.is_some_and(|terms| batch_equation(terms, multi_scalar_mul, rng));
+    trace_event!(valid);
+    valid
+}
+
+/// `verify_batch` with the decoding and the multi-scalar multiplication
+/// spread over rayon's thread pool
+#[cfg(feature = "parallel")]
+pub fn par_verify_batch(items: &[([u8; 32], &[u8], [u8; 64])]) -> bool {
+    par_verify_batch_with_rng(items, &mut OsRng)
+}
+
+#[cfg(feature = "parallel")]
+pub fn par_verify_batch_with_rng<R: RngCore + CryptoRng>(items: &[([u8; 32], &[u8], [u8; 64])], rng: &mut R) -> bool {
+    use rayon::prelude::*;
+
+    trace_span!("schnorr.verify_batch", count = items.len());
+    let terms: Option<Vec<_>> = items.par_iter().map(|(p, msg, sig)| batch_terms(p, msg, sig)).collect();
+    let valid = terms.is_some_and(|terms| batch_equation(terms, par_multi_scalar_mul, rng));
+    trace_event!(valid);
+    valid
+}
+
+#[cfg(test)]
+mod schnorr_tests {
+    use super::*;
+    use crate::crypto::rng::DeterministicRng;
+
+    fn key(hex: &str) -> PrivateKey {
+        PrivateKey::from_bytes(&hex::decode(hex).unwrap()).unwrap()
+    }
+
+    fn array<const N: usize>(hex: &str) -> [u8; N] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_bip340_vectors() {
+        let cases = [
+            (
+                // vector 0
+                "0000000000000000000000000000000000000000000000000000000000000003",
+                "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca8215\
+                 25f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0",
+            ),
+            (
+                // vector 1
+                "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef",
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de3341\
+                 8906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a",
+            ),
+            (
+                // vector 2
+                "c90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b14e5c9",
+                "dd308afec5777e13121fa72b9cc1b7cc0139715309b086c960e18fd969774eb8",
+                "c87aa53824b4d7ae2eb035a2b5bbbccc080e76cdc6d1692c4b0b62d798e6d906",
+                "7e2d58d8b3bcdf1abadec7829054f90dda9805aab56c77333024b9d0a508b75c",
+                "5831aaeed7b44bb74e5eab94ba9d4294c49bcf2a60728d8b4c200f50dd313c1b\
+                 ab745879a5ad954a72c45a91c3a51d3c7adea98d82f8481e0e1e03674a6f3fb7",
+            ),
+            (
+                // vector 3
+                "0b432b2677937381aef05bb02a66ecd012773062cf3fa2549e44f58ed2401710",
+                "25d1dff95105f5253c4022f628a996ad3a0d95fbf21d468a1b33f8c160d8f517",
+                "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "7eb0509757e246f19449885651611cb965ecc1a187dd51b64fda1edc9637d5ec\
+                 97582b9cb13db3933705b32ba982af5af25fd78881ebb32771fc5922efc66ea3",
+            ),
+            (
+                // vector 15
+                "0340034003400340034003400340034003400340034003400340034003400340",
+                "778caa53b4393ac467774d09497a87224bf9fab6f6e68b23086497324d6fd117",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "",
+                "71535db165ecd9fbbc046e5ffaea61186bb6ad436732fccc25291a55895464cf\
+                 6069ce26bf03466228f19a3a62db8a649f2d560fac652827d1af0574e427ab63",
+            ),
+            (
+                // vector 16
+                "0340034003400340034003400340034003400340034003400340034003400340",
+                "778caa53b4393ac467774d09497a87224bf9fab6f6e68b23086497324d6fd117",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "11",
+                "08a20a0afef64124649232e0693c583ab1b9934ae63b4c3511f3ae1134c6a303\
+                 ea3173bfea6683bd101fa5aa5dbc1996fe7cacfc5a577d33ec14564cec2bacbf",
+            ),
+            (
+                // vector 17
+                "0340034003400340034003400340034003400340034003400340034003400340",
+                "778caa53b4393ac467774d09497a87224bf9fab6f6e68b23086497324d6fd117",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "0102030405060708090a0b0c0d0e0f1011",
+                "5130f39a4059b43bc7cac09a19ece52b5d8699d1a71e3c52da9afdb6b50ac370\
+                 c4a482b77bf960f8681540e25b6771ece1e5a37fd80e5a51897c5566a97ea5a5",
+            ),
+            (
+                // vector 18
+                "0340034003400340034003400340034003400340034003400340034003400340",
+                "778caa53b4393ac467774d09497a87224bf9fab6f6e68b23086497324d6fd117",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "9999999999999999999999999999999999999999999999999999999999999999\
+                 9999999999999999999999999999999999999999999999999999999999999999\
+                 9999999999999999999999999999999999999999999999999999999999999999\
+                 99999999",
+                "403b12b0d8555a344175ea7ec746566303321e5dbfa8be6f091635163eca79a8\
+                 585ed3e3170807e7c03b720fc54c7b23897fcba0e9d0b4a06894cfd249f22367",
+            ),
+        ];
+        for (secret, public, aux, msg, expected) in cases {
+            let private_key = key(secret);
+            assert_eq!(hex::encode(xonly(&private_key.public_key())), public);
+            let msg = hex::decode(msg).unwrap();
+            let sig = sign(&private_key, &msg, &array(aux));
+            assert_eq!(hex::encode(sig), expected);
+            assert!(verify(&array(public), &msg, &sig));
+        }
+    }
+
+    #[test]
+    fn test_bip340_verification_vectors() {
+        // the vectors without a secret key. 5 and 14 have public keys that
+        // don't parse, so any message and signature will do
+        let cases = [
+            // vector 4: r with leading zeros
+            (
+                "d69c3509bb99e412e68b0fe8544e72837dfa30746d8be2aa65975f29d22dc7b9",
+                "4df3c3f68fcc83b27e9d42c90431a72499f17875c81a599b566c9889b9696703",
+                "00000000000000000000003b78ce563f89a0ed9414f5aa28ad0d96d6795f9c63\
+             76afb1548af603b3eb45c9f8207dee1060cb71c04e80f593060b07d28308d7f4",
+                true,
+            ),
+            // vector 5: public key not on the curve
+            (
+                "eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de3341\
+             8906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a",
+                false,
+            ),
+            // vector 6: R has odd y
+            (
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556\
+             3cc27944640ac607cd107ae10923d9ef7a73c643e166be5ebeafa34b1ac553e2",
+                false,
+            ),
+            // vector 7: negated message
+            (
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "1fa62e331edbc21c394792d2ab1100a7b432b013df3f6ff4f99fcb33e0e1515f\
+             28890b3edb6e7189b630448b515ce4f8622a954cfe545735aaea5134fccdb2bd",
+                false,
+            ),
+            // vector 8: negated s
+            (
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "6cff5c3ba86c69ea4b7376f31a9bcb4f74c1976089b2d9963da2e5543e177769\
+             961764b3aa9b2ffcb6ef947b6887a226e8d7c93e00c5ed0c1834ff0d0c2e6da6",
+                false,
+            ),
+            // vector 9: s G - e P is infinity
+            (
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "0000000000000000000000000000000000000000000000000000000000000000\
+             123dda8328af9c23a94c1feecfd123ba4fb73476f0d594dcb65c6425bd186051",
+                false,
+            ),
+            // vector 10: s G - e P is infinity
+            (
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "0000000000000000000000000000000000000000000000000000000000000001\
+             7615fbaf5ae28864013c099742deadb4dba87f11ac6754f93780d5a1837cf197",
+                false,
+            ),
+            // vector 11: r is not the x of a point
+            (
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "4a298dacae57395a15d0795ddbfd1dcb564da82b0f269bc70a74f8220429ba1d\
+             69e89b4c5564d00349106b8497785dd7d1d713a8ae82b32fa79d5f7fc407d39b",
+                false,
+            ),
+            // vector 12: r = p
+            (
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f\
+             69e89b4c5564d00349106b8497785dd7d1d713a8ae82b32fa79d5f7fc407d39b",
+                false,
+            ),
+            // vector 13: s = n
+            (
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "6cff5c3ba86c69ea4b7376f31a9bcb4f74c1976089b2d9963da2e5543e177769\
+             fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+                false,
+            ),
+            // vector 14: public key x = p + 1
+            (
+                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc30",
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de3341\
+             8906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a",
+                false,
+            ),
+        ];
+        for (i, (public, msg, sig, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                verify(&array(public), &hex::decode(msg).unwrap(), &array(sig)),
+                expected,
+                "vector {}",
+                i + 4
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects() {
+        let private_key = key("b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef");
+        let public = xonly(&private_key.public_key());
+        let msg = [7_u8; 32];
+        let sig = sign(&private_key, &msg, &[0; 32]);
+        assert!(verify(&public, &msg, &sig));
+
+        assert!(!verify(&public, &[8_u8; 32], &sig));
+        let mut bad = sig;
+        bad[63] ^= 1;
+        assert!(!verify(&public, &msg, &bad));
+        // r = p is out of range
+        let mut bad = sig;
+        bad[..32].copy_from_slice(&to_32_bytes(&prime()));
+        assert!(!verify(&public, &msg, &bad));
+        // x = 5 is not on the curve
+        let mut not_on_curve = [0_u8; 32];
+        not_on_curve[31] = 5;
+        assert!(!verify(&not_on_curve, &msg, &sig));
+    }
+
+    #[test]
+    fn test_odd_y_key() {
+        // 3 * G has even y; a key whose point has odd y signs with n - d
+        let private_key = (1_u32..)
+            .map(|i| PrivateKey::from(BigUint::from(i)).unwrap())
+            .find(|k| !has_even_y(&k.public_key()))
+            .unwrap();
+        assert_eq!(even_y_secret(&private_key), order() - private_key.secret());
+
+        let public = xonly(&private_key.public_key());
+        let sig = sign(&private_key, b"variable length message", &[1; 32]);
+        assert!(verify(&public, b"variable length message", &sig));
+        assert_eq!(
+            lift_x(&public).unwrap().point().x(),
+            private_key.public_key().point().x()
+        );
+    }
+
+    fn batch() -> Vec<([u8; 32], &'static [u8], [u8; 64])> {
+        let msgs: [&'static [u8]; 4] = [b"", b"a", b"batch", &[0xff; 32]];
+        (0..4_u32)
+            .map(|i| {
+                let private_key = PrivateKey::from(BigUint::from(1000 + i)).unwrap();
+                let msg = msgs[i as usize];
+                (
+                    xonly(&private_key.public_key()),
+                    msg,
+                    sign(&private_key, msg, &[i as u8; 32]),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let items = batch();
+        assert!(verify_batch(&items));
+        assert!(verify_batch(&items[..1]));
+        assert!(verify_batch(&[]));
+
+        let mut bad = items.clone();
+        bad[2].2[63] ^= 1;
+        assert!(!verify_batch(&bad));
+        // a valid signature for another message
+        let mut swapped = items.clone();
+        swapped[1].1 = b"b";
+        assert!(!verify_batch(&swapped));
+        // s = n is out of range
+        let mut bad = items.clone();
+        bad[3].2[32..].copy_from_slice(&scalar_bytes(&order()));
+        assert!(!verify_batch(&bad));
+
+        // the same weights every run
+        let mut rng = DeterministicRng::new([0x5c; 32]);
+        assert!(verify_batch_with_rng(&items, &mut rng));
+        assert!(!verify_batch_with_rng(&swapped, &mut rng));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_verify_batch() {
+        let items = batch();
+        assert!(par_verify_batch(&items));
+        assert!(par_verify_batch(&[]));
+        let mut bad = items;
+        bad[0].2[0] ^= 1;
+        assert!(!par_verify_batch(&bad));
+    }
+}
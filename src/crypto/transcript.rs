@@ -0,0 +1,103 @@
+use num_bigint::{BigInt, BigUint, Sign};
+use sha2::{Digest, Sha256};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::crypto::hash::tagged_hasher;
+use crate::curves::secp256k1::ecc::{order, to_32_bytes, PublicKey};
+
+/// A running hash of everything a proof's verifier would have seen, from
+/// which Fiat-Shamir challenges are drawn. Each message is framed by its
+/// label and length, so different message sequences can't collide, and
+/// every challenge is absorbed back in so later ones depend on it
+#[derive(Clone)]
+pub struct Transcript {
+    hasher: Sha256,
+}
+
+impl Transcript {
+    /// `protocol` domain-separates transcripts of different proofs
+    pub fn new(protocol: &str) -> Self {
+        Self {
+            hasher: tagged_hasher(protocol),
+        }
+    }
+
+    pub fn append(&mut self, label: &str, message: &[u8]) {
+        self.absorb(label.as_bytes());
+        self.absorb(message);
+    }
+
+    /// A point by its compressed encoding
+    pub fn append_point(&mut self, label: &str, point: &PublicKey) {
+        self.append(label, &point.sec(true));
+    }
+
+    /// A scalar as 32 big-endian bytes
+    pub fn append_scalar(&mut self, label: &str, scalar: &BigUint) {
+        self.append(label, &to_32_bytes(&BigInt::from(scalar.clone())));
+    }
+
+    fn absorb(&mut self, bytes: &[u8]) {
+        self.hasher.update((bytes.len() as u64).to_be_bytes());
+        self.hasher.update(bytes);
+    }
+
+    pub fn challenge_bytes(&mut self, label: &str) -> [u8; 32] {
+        self.absorb(label.as_bytes());
+        let challenge: [u8; 32] = self.hasher.clone().finalize().into();
+        self.absorb(&challenge);
+        challenge
+    }
+
+    /// A challenge modulo the secp256k1 order. Reducing 256 bits modulo an
+    /// order this close to 2^256 leaves no measurable bias
+    pub fn challenge_scalar(&mut self, label: &str) -> BigUint {
+        BigUint::from_bytes_be(&self.challenge_bytes(label)) % order()
+    }
+
+    /// A challenge in the field of `prime`, for primes up to 256 bits.
+    /// Reduces 512 bits so the result is close to uniform for any of them
+    pub fn challenge_field_element(&mut self, label: &str, prime: &BigInt) -> FieldElement {
+        let mut wide = self.challenge_bytes(label).to_vec();
+        wide.extend_from_slice(&self.challenge_bytes(label));
+        FieldElement::reduce(BigInt::from_bytes_be(Sign::Plus, &wide), prime.clone()).expect("prime is at least 2")
+    }
+}
+
+#[cfg(test)]
+mod transcript_tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript() {
+        let challenge = |messages: &[(&str, &[u8])]| {
+            let mut transcript = Transcript::new("moneda/test");
+            for (label, message) in messages {
+                transcript.append(label, message);
+            }
+            transcript.challenge_bytes("c")
+        };
+        assert_eq!(challenge(&[("a", b"xy")]), challenge(&[("a", b"xy")]));
+        // framing keeps the boundaries
+        assert_ne!(challenge(&[("a", b"xy")]), challenge(&[("a", b"x"), ("", b"y")]));
+        assert_ne!(challenge(&[("a", b"xy")]), challenge(&[("ax", b"y")]));
+        assert_ne!(Transcript::new("moneda/other").challenge_bytes("c"), challenge(&[]));
+
+        let mut transcript = Transcript::new("moneda/test");
+        let first = transcript.challenge_bytes("c");
+        assert_ne!(transcript.challenge_bytes("c"), first);
+
+        let mut transcript = Transcript::new("moneda/test");
+        transcript.append_point(
+            "P",
+            &crate::curves::secp256k1::ecc::PrivateKey::from(BigUint::from(1_u8))
+                .unwrap()
+                .public_key(),
+        );
+        transcript.append_scalar("x", &BigUint::from(7_u8));
+        assert!(transcript.challenge_scalar("c") < order());
+        let prime = BigInt::from(101_u8);
+        let element = transcript.challenge_field_element("f", &prime);
+        assert!(element.num() < prime && element.prime() == prime);
+    }
+}
@@ -0,0 +1,211 @@
+use num_bigint::{BigInt, BigUint, Sign};
+use rand::{CryptoRng, RngCore};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::curves::secp256k1::ecc::KeyError;
+
+// X25519 (RFC 7748) is Diffie-Hellman on the Montgomery form of
+// Curve25519, v^2 = u^3 + 486662 u^2 + u over p = 2^255 - 19, using only
+// u coordinates. Keys and outputs are 32 little-endian bytes, and any
+// 32 bytes are a valid secret: clamping turns them into a multiple of the
+// cofactor 8 with the top bit fixed, so small-subgroup components drop
+// out and the ladder always runs the same number of steps
+
+/// The u coordinate of the base point, 9
+pub const BASEPOINT: [u8; 32] = {
+    let mut u = [0_u8; 32];
+    u[0] = 9;
+    u
+};
+
+/// (A - 2) / 4 for A = 486662, the constant of the ladder's doubling
+const A24: u32 = 121665;
+
+pub fn prime() -> BigInt {
+    (BigInt::from(1_u8) << 255_u32) - 19_u8
+}
+
+/// Clears the low three bits and the top bit and sets bit 254
+pub fn clamp(scalar: &[u8; 32]) -> BigUint {
+    let mut k = *scalar;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+    BigUint::from_bytes_le(&k)
+}
+
+/// Masks the top bit, as RFC 7748 requires, and reduces the rest mod p,
+/// so non-canonical encodings are accepted
+pub fn decode_u(u: &[u8; 32]) -> FieldElement {
+    let mut u = *u;
+    u[31] &= 127;
+    FieldElement::reduce(BigInt::from_bytes_le(Sign::Plus, &u), prime()).expect("p is a valid modulus")
+}
+
+fn encode_u(u: &FieldElement) -> [u8; 32] {
+    let mut bytes = [0_u8; 32];
+    let (_, le) = u.num().to_bytes_le();
+    bytes[..le.len()].copy_from_slice(&le);
+    bytes
+}
+
+/// The X25519 function: the u coordinate of the clamped `scalar` times
+/// the point with u coordinate `u`. It is all zeros when `u` has small
+/// order, which `shared_secret` rejects
+pub fn x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    let k = clamp(scalar);
+    let x1 = decode_u(u);
+    let p = prime();
+    let one = FieldElement::from(BigInt::from(1_u8), p.clone()).expect("1 < p");
+    let zero = FieldElement::from(BigInt::from(0_u8), p.clone()).expect("0 < p");
+
+    // the Montgomery ladder of RFC 7748 section 5, keeping (x2 : z2) = k' P
+    // and (x3 : z3) = (k' + 1) P for the bits k' of k read so far
+    let (mut x2, mut z2) = (one.clone(), zero);
+    let (mut x3, mut z3) = (x1.clone(), one);
+    let mut swap = false;
+    for t in (0..255).rev() {
+        let bit = k.bit(t);
+        if swap != bit {
+            std::mem::swap(&mut x2, &mut x3);
+            std::mem::swap(&mut z2, &mut z3);
+        }
+        swap = bit;
+
+        let a = x2.clone() + z2.clone();
+        let aa = a.clone() * a.clone();
+        let b = x2 - z2;
+        let bb = b.clone() * b.clone();
+        let e = aa.clone() - bb.clone();
+        let c = x3.clone() + z3.clone();
+        let d = x3 - z3;
+        let da = d * a;
+        let cb = c * b;
+        let sum = da.clone() + cb.clone();
+        let diff = da - cb;
+        x3 = sum.clone() * sum;
+        z3 = x1.clone() * (diff.clone() * diff);
+        z2 = e.clone() * (aa.clone() + e * BigInt::from(A24));
+        x2 = aa * bb;
+    }
+    if swap {
+        std::mem::swap(&mut x2, &mut x3);
+        std::mem::swap(&mut z2, &mut z3);
+    }
+    // z2 is zero for the point at infinity, and dividing by it gives zero
+    encode_u(&(x2 / z2))
+}
+
+/// A uniform 32-byte secret. Clamping happens when it's used
+pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> [u8; 32] {
+    let mut secret = [0_u8; 32];
+    rng.fill_bytes(&mut secret);
+    secret
+}
+
+pub fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    x25519(secret, &BASEPOINT)
+}
+
+/// The shared secret with the holder of `public_key`, rejecting the
+/// all-zero output of a small-order point, which would let the peer
+/// force a known secret
+pub fn shared_secret(secret: &[u8; 32], public_key: &[u8; 32]) -> Result<[u8; 32], KeyError> {
+    let shared = x25519(secret, public_key);
+    if shared == [0; 32] {
+        return Err(KeyError::SmallOrderPoint);
+    }
+    Ok(shared)
+}
+
+#[cfg(test)]
+mod x25519_tests {
+    use super::*;
+    use crate::crypto::rng::DeterministicRng;
+
+    fn bytes(s: &str) -> [u8; 32] {
+        hex::decode(s).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_rfc7748_vectors() {
+        // section 5.2; the second u has its top bit set, which is masked
+        for (scalar, u, out) in [
+            (
+                "a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+                "e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c",
+                "c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552",
+            ),
+            (
+                "4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d",
+                "e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493",
+                "95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957",
+            ),
+        ] {
+            assert_eq!(hex::encode(x25519(&bytes(scalar), &bytes(u))), out);
+        }
+    }
+
+    #[test]
+    fn test_rfc7748_iterated() {
+        // k = X25519(k, u), u = the old k, starting from both at 9
+        let (mut k, mut u) = (BASEPOINT, BASEPOINT);
+        for i in 1..=1000 {
+            (k, u) = (x25519(&k, &u), k);
+            if i == 1 {
+                assert_eq!(
+                    hex::encode(k),
+                    "422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079"
+                );
+            }
+        }
+        assert_eq!(
+            hex::encode(k),
+            "684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51"
+        );
+    }
+
+    #[test]
+    fn test_rfc7748_key_agreement() {
+        // section 6.1
+        let alice = bytes("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = bytes("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        assert_eq!(
+            hex::encode(public_key(&alice)),
+            "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"
+        );
+        assert_eq!(
+            hex::encode(public_key(&bob)),
+            "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f"
+        );
+        let shared = "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742";
+        assert_eq!(hex::encode(shared_secret(&alice, &public_key(&bob)).unwrap()), shared);
+        assert_eq!(hex::encode(shared_secret(&bob, &public_key(&alice)).unwrap()), shared);
+
+        let mut rng = DeterministicRng::new([0x25; 32]);
+        let (carol, dave) = (generate(&mut rng), generate(&mut rng));
+        assert_eq!(
+            shared_secret(&carol, &public_key(&dave)),
+            shared_secret(&dave, &public_key(&carol))
+        );
+    }
+
+    #[test]
+    fn test_small_order_points() {
+        let secret = bytes("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        // 0, 1, and a point of order 8, plus p + 1 as a non-canonical 1
+        for u in [
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "e0eb7a7c3b41b8ae1656e3faf19fc46ada098deb9c32b1fd866205165f49b800",
+            "eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        ] {
+            assert_eq!(shared_secret(&secret, &bytes(u)), Err(KeyError::SmallOrderPoint));
+        }
+        // clamping ignores the bits it fixes
+        let mut tweaked = secret;
+        tweaked[0] |= 7;
+        tweaked[31] ^= 128;
+        assert_eq!(public_key(&tweaked), public_key(&secret));
+    }
+}
@@ -0,0 +1,35 @@
+// Elliptic curves: the short Weierstrass group law over any prime field,
+// secp256k1 with its keys, signatures and precomputation, and a registry
+// of the named curves other protocols use, with ECDSA over any of them
+
+pub(crate) mod ec_point;
+
+pub use crate::named_curve::{
+    curve_by_oid, named_curve, p256, CurveParams, NamedCurve, BRAINPOOL_P256R1, BRAINPOOL_P384R1, CURVES, P256,
+    SECP256K1,
+};
+pub use ec_point::{AdditionCase, AdditionTrace, Curve, Point, PointError};
+
+/// The curve of Bitcoin and Ethereum: constants, keys, ECDSA and
+/// multi-scalar multiplication
+pub mod secp256k1 {
+    pub mod backend;
+    pub(crate) mod context;
+    pub(crate) mod ecc;
+
+    pub use context::{context, Context};
+    pub use ecc::*;
+}
+
+/// ECDSA with RFC 6979 nonces on the curves of the registry, e.g. P-256
+/// for WebAuthn and TLS
+pub mod ecdsa {
+    pub use crate::ecdsa::*;
+}
+
+/// Keys of the registry's curves as DER and PEM that OpenSSL reads and
+/// writes
+#[cfg(feature = "openssl")]
+pub mod openssl {
+    pub use crate::openssl::*;
+}
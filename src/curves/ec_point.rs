@@ -0,0 +1,722 @@
+use std::fmt::Display;
+use std::ops::{Add, Mul};
+use std::sync::Arc;
+
+use crate::arithmetic::field_element::{FieldElement, FieldError};
+use crate::encoding::hex_fmt::{impl_hex_fmt, readable};
+
+use num_bigint::BigInt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointError {
+    /// The coordinates don't satisfy y² = x³ + ax + b
+    NotOnCurve,
+    /// Only the point at infinity lacks coordinates, and it lacks both
+    IncompleteCoordinates,
+    /// The points are on different curves
+    CurveMismatch,
+    /// Coordinates and coefficients must share a field
+    Field(FieldError),
+}
+
+impl Display for PointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PointError::NotOnCurve => write!(f, "point is not on the curve"),
+            PointError::IncompleteCoordinates => write!(f, "a point needs both coordinates or neither"),
+            PointError::CurveMismatch => write!(f, "points are not on the same curve"),
+            PointError::Field(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for PointError {}
+
+impl From<FieldError> for PointError {
+    fn from(e: FieldError) -> Self {
+        PointError::Field(e)
+    }
+}
+
+/// The curve y² = x³ + ax + b. Points hold it behind an `Arc`, so cloning
+/// a point copies a pointer rather than the coefficients, and points that
+/// share one curve compare curves by address
+#[derive(Debug, PartialEq)]
+pub struct Curve {
+    a: FieldElement,
+    b: FieldElement,
+}
+
+impl Curve {
+    pub fn new(a: FieldElement, b: FieldElement) -> Result<Arc<Self>, PointError> {
+        if a.prime() != b.prime() {
+            return Err(FieldError::FieldMismatch.into());
+        }
+        Ok(Arc::new(Self { a, b }))
+    }
+
+    pub fn a(&self) -> &FieldElement {
+        &self.a
+    }
+
+    pub fn b(&self) -> &FieldElement {
+        &self.b
+    }
+}
+
+/// The same curve: the same allocation, or failing that equal coefficients
+fn same_curve(lhs: &Arc<Curve>, rhs: &Arc<Curve>) -> bool {
+    Arc::ptr_eq(lhs, rhs) || lhs == rhs
+}
+
+#[derive(Debug, Clone)]
+pub struct Point {
+    x: Option<FieldElement>,
+    y: Option<FieldElement>,
+    curve: Arc<Curve>,
+}
+
+impl Point {
+    /// A point on a curve of its own. Points built this way compare their
+    /// curves coefficient by coefficient; `on_curve` shares one
+    pub fn from(
+        x: Option<FieldElement>,
+        y: Option<FieldElement>,
+        a: FieldElement,
+        b: FieldElement,
+    ) -> Result<Self, PointError> {
+        Self::on_curve(x, y, &Curve::new(a, b)?)
+    }
+
+    pub fn on_curve(x: Option<FieldElement>, y: Option<FieldElement>, curve: &Arc<Curve>) -> Result<Self, PointError> {
+        let (a, b) = (&curve.a, &curve.b);
+        match (&x, &y) {
+            (Some(xs), Some(ys)) => {
+                if xs.prime() != a.prime() || ys.prime() != a.prime() {
+                    return Err(FieldError::FieldMismatch.into());
+                }
+                if ys.clone().pow(BigInt::from(2))
+                    != xs.clone().pow(BigInt::from(3)) + a.clone() * xs.clone() + b.clone()
+                {
+                    return Err(PointError::NotOnCurve);
+                }
+            }
+            (None, None) => {}
+            _ => return Err(PointError::IncompleteCoordinates),
+        }
+        Ok(Self {
+            x,
+            y,
+            curve: curve.clone(),
+        })
+    }
+
+    /// The identity of the curve y² = x³ + ax + b
+    pub fn infinity(a: FieldElement, b: FieldElement) -> Result<Self, PointError> {
+        Ok(Self::infinity_on(&Curve::new(a, b)?))
+    }
+
+    pub fn infinity_on(curve: &Arc<Curve>) -> Self {
+        Self {
+            x: None,
+            y: None,
+            curve: curve.clone(),
+        }
+    }
+
+    pub fn x(&self) -> Option<&FieldElement> {
+        self.x.as_ref()
+    }
+
+    pub fn y(&self) -> Option<&FieldElement> {
+        self.y.as_ref()
+    }
+
+    pub fn a(&self) -> &FieldElement {
+        &self.curve.a
+    }
+
+    pub fn b(&self) -> &FieldElement {
+        &self.curve.b
+    }
+
+    pub fn curve(&self) -> &Arc<Curve> {
+        &self.curve
+    }
+
+    pub fn is_on(&self, curve: &Arc<Curve>) -> bool {
+        same_curve(&self.curve, curve)
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.x.is_none() && self.y.is_none()
+    }
+
+    /// The least n > 0 with n P = O, by adding P until the sum is O. That's
+    /// up to about p additions, so this is for the small curves of the
+    /// exercises; the named curves publish their orders
+    pub fn order(&self) -> BigInt {
+        let mut order = BigInt::from(1_u8);
+        let mut multiple = self.clone();
+        while !multiple.is_infinity() {
+            multiple = multiple + self.clone();
+            order += 1_u8;
+        }
+        order
+    }
+
+    /// Compressed SEC1 on any curve: 0x02 or 0x03 for the parity of y,
+    /// then x as wide as the field. The point at infinity is 0x00
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match (&self.x, &self.y) {
+            (Some(x), Some(y)) => {
+                let mut res = vec![if y.is_odd() { 0x03 } else { 0x02 }];
+                res.extend_from_slice(&x.to_bytes());
+                res
+            }
+            _ => vec![0x00],
+        }
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    /// `+` panics on points of different curves, which only a bug can
+    /// produce once points come from `from`. This is the variant for when
+    /// the curves aren't known to agree
+    pub fn checked_add(self, rhs: Self) -> Result<Self, PointError> {
+        if !self.is_on(&rhs.curve) {
+            return Err(PointError::CurveMismatch);
+        }
+        Ok(self + rhs)
+    }
+}
+
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x && self.y == other.y && self.is_on(&other.curve)
+    }
+}
+
+/// `{:#}` puts the curve on the first line and a coordinate on each of
+/// the next, for notebooks and teaching
+impl Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            let (a, b) = (&self.curve.a, &self.curve.b);
+            let curve = format!(
+                "y² = x³ + {}x + {} mod {}",
+                readable(&a.num()),
+                readable(&b.num()),
+                readable(&a.prime())
+            );
+            return match (&self.x, &self.y) {
+                (Some(x), Some(y)) => write!(
+                    f,
+                    "Point on {}\n  x = {}\n  y = {}",
+                    curve,
+                    readable(&x.num()),
+                    readable(&y.num())
+                ),
+                _ => write!(f, "Point at infinity on {}", curve),
+            };
+        }
+        write!(f, "Point({:?},{:?})_{}_{}", self.x, self.y, self.curve.a, self.curve.b)
+    }
+}
+
+impl_hex_fmt!(Point, Point::to_bytes);
+
+/// Which rule of the group law an addition went through
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdditionCase {
+    /// An operand is the point at infinity, so the sum is the other one
+    Identity,
+    /// P + (-P), including P + P when y = 0: the line is vertical and
+    /// meets the curve nowhere else, so the sum is the point at infinity
+    Inverse,
+    /// P + P: the line is the tangent, of slope (3x² + a) / 2y
+    Doubling,
+    /// P + Q with different x: the line is the chord, of slope
+    /// (y2 - y1) / (x2 - x1)
+    Chord,
+}
+
+/// The working of one addition. The slope and its parts are only there for
+/// `Doubling` and `Chord`; the third intersection of the line with the
+/// curve is (x3, -y3), and the sum is its reflection (x3, y3)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdditionTrace {
+    pub case: AdditionCase,
+    pub numerator: Option<FieldElement>,
+    pub denominator: Option<FieldElement>,
+    /// m = numerator / denominator
+    pub slope: Option<FieldElement>,
+    /// x3 = m² - x1 - x2
+    pub x3: Option<FieldElement>,
+    /// y3 = m (x1 - x3) - y1
+    pub y3: Option<FieldElement>,
+}
+
+impl AdditionTrace {
+    fn without_slope(case: AdditionCase) -> Self {
+        Self {
+            case,
+            numerator: None,
+            denominator: None,
+            slope: None,
+            x3: None,
+            y3: None,
+        }
+    }
+}
+
+impl Display for AdditionTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (Some(numerator), Some(denominator), Some(slope), Some(x3), Some(y3)) =
+            (&self.numerator, &self.denominator, &self.slope, &self.x3, &self.y3)
+        else {
+            return match self.case {
+                AdditionCase::Identity => write!(f, "identity: P + O = P"),
+                _ => write!(f, "inverse: vertical line, P + (-P) = O"),
+            };
+        };
+        let (rule, formula) = match self.case {
+            AdditionCase::Doubling => ("doubling", "(3x1² + a) / 2y1"),
+            _ => ("chord", "(y2 - y1) / (x2 - x1)"),
+        };
+        writeln!(
+            f,
+            "{}: m = {} = {} / {} = {}",
+            rule,
+            formula,
+            numerator.num(),
+            denominator.num(),
+            slope.num()
+        )?;
+        writeln!(f, "x3 = m² - x1 - x2 = {}", x3.num())?;
+        write!(f, "y3 = m (x1 - x3) - y1 = {}", y3.num())
+    }
+}
+
+impl Point {
+    /// `+`, also returning which case of the group law it took and the
+    /// values it went through
+    pub fn add_traced(self, rhs: Self) -> (Self, AdditionTrace) {
+        if !self.is_on(&rhs.curve) {
+            panic!("Points {}, {} are not on the same curve", self, rhs);
+        }
+
+        // one of the points is the point at infinit
+        // P + 0 = P or 0 + P = P
+        if self.x.is_none() || self.y.is_none() {
+            return (rhs, AdditionTrace::without_slope(AdditionCase::Identity));
+        }
+        if rhs.x.is_none() || rhs.y.is_none() {
+            return (self, AdditionTrace::without_slope(AdditionCase::Identity));
+        }
+
+        let infinity = || Self::infinity_on(&self.curve);
+
+        // the x coordinate of both points is the same
+        // the line is vertical and doesn't intersect at any other point
+        // so the result is the point at infinity O
+        if self.x == rhs.x && self.y != rhs.y {
+            return (infinity(), AdditionTrace::without_slope(AdditionCase::Inverse));
+        }
+
+        // P1 = P2 with y = 0: the tangent is vertical
+        if self == rhs && self.y.as_ref().is_some_and(|y| y.is_zero()) {
+            return (infinity(), AdditionTrace::without_slope(AdditionCase::Inverse));
+        }
+
+        let (x1, y1) = (self.x.clone().unwrap(), self.y.clone().unwrap());
+        let (x2, y2) = (rhs.x.clone().unwrap(), rhs.y.clone().unwrap());
+        let (case, numerator, denominator) = if self == rhs {
+            // P1 = P2
+            // calculate the tangent to the curve at P1
+            // & find the point at which the line intersects the curve
+            let numerator = BigInt::from(3_u8) * x1.clone().pow(BigInt::from(2_u8)) + self.curve.a.clone();
+            (AdditionCase::Doubling, numerator, BigInt::from(2_u8) * y1.clone())
+        } else {
+            // both points are different
+            (AdditionCase::Chord, y2 - y1.clone(), x2.clone() - x1.clone())
+        };
+
+        let m = numerator.clone() / denominator.clone();
+        let x3 = m.clone().pow(BigInt::from(2_u8)) - x1.clone() - x2;
+        let y3 = m.clone() * (x1 - x3.clone()) - y1;
+
+        let trace = AdditionTrace {
+            case,
+            numerator: Some(numerator),
+            denominator: Some(denominator),
+            slope: Some(m),
+            x3: Some(x3.clone()),
+            y3: Some(y3.clone()),
+        };
+        (
+            Self {
+                x: Some(x3),
+                y: Some(y3),
+                curve: self.curve,
+            },
+            trace,
+        )
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        self.add_traced(rhs).0
+    }
+}
+
+impl Mul<Point> for BigInt {
+    type Output = Point;
+
+    fn mul(self, rhs: Point) -> Self::Output {
+        let mut coef = self;
+        let mut current = rhs.clone();
+        let mut res = Point::infinity_on(&rhs.curve);
+
+        while coef > BigInt::from(0) {
+            if coef.bit(0) {
+                res = res + current.clone();
+            }
+            current = current.clone() + current;
+            coef >>= 1;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod elliptic_curve_point_tests {
+    use super::*;
+
+    #[test]
+    fn point_outside_curve() {
+        let prime = BigInt::from(223);
+        let x = FieldElement::from(BigInt::from(200), prime.clone()).unwrap();
+        let y = FieldElement::from(BigInt::from(119), prime.clone()).unwrap();
+        let a = FieldElement::from(BigInt::from(5), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+        assert_eq!(
+            Point::from(Some(x.clone()), Some(y), a.clone(), b.clone()),
+            Err(PointError::NotOnCurve)
+        );
+        assert_eq!(
+            Point::from(Some(x), None, a.clone(), b.clone()),
+            Err(PointError::IncompleteCoordinates)
+        );
+
+        let other = FieldElement::from(BigInt::from(7), BigInt::from(11)).unwrap();
+        assert_eq!(
+            Point::from(None, None, a.clone(), other.clone()),
+            Err(PointError::Field(FieldError::FieldMismatch))
+        );
+        assert_eq!(
+            Point::infinity(a.clone(), other.clone()),
+            Err(PointError::Field(FieldError::FieldMismatch))
+        );
+        let p = Point::infinity(a, b).unwrap();
+        let q = Point::infinity(other.clone(), other).unwrap();
+        assert_eq!(p.checked_add(q), Err(PointError::CurveMismatch));
+    }
+
+    #[test]
+    fn test_ne() {
+        let prime = BigInt::from(223);
+        let x1 = FieldElement::from(BigInt::from(192), prime.clone()).unwrap();
+        let y1 = FieldElement::from(BigInt::from(105), prime.clone()).unwrap();
+
+        let x2 = FieldElement::from(BigInt::from(17), prime.clone()).unwrap();
+        let y2 = FieldElement::from(BigInt::from(56), prime.clone()).unwrap();
+
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+
+        let p1 = Point::from(Some(x1), Some(y1), a.clone(), b.clone()).unwrap();
+        let p2 = Point::from(Some(x2), Some(y2), a.clone(), b.clone()).unwrap();
+        assert!(p1 != p2);
+    }
+
+    #[test]
+    fn test_add0() {
+        let prime = BigInt::from(223);
+        let x1 = FieldElement::from(BigInt::from(192), prime.clone()).unwrap();
+        let y1 = FieldElement::from(BigInt::from(105), prime.clone()).unwrap();
+
+        let x2 = FieldElement::from(BigInt::from(17), prime.clone()).unwrap();
+        let y2 = FieldElement::from(BigInt::from(56), prime.clone()).unwrap();
+
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+
+        let p1 = Point::from(None, None, a.clone(), b.clone()).unwrap();
+        let p2 = Point::from(Some(x1), Some(y1), a.clone(), b.clone()).unwrap();
+        let p3 = Point::from(Some(x2), Some(y2), a.clone(), b.clone()).unwrap();
+
+        assert_eq!(p1.clone() + p2.clone(), p2.clone());
+        assert_eq!(p1.clone() + p3.clone(), p3.clone());
+        assert_eq!(p2.clone() + p1.clone(), p2.clone());
+    }
+
+    #[test]
+    fn test_add1() {
+        let prime = BigInt::from(223);
+        let x1 = FieldElement::from(BigInt::from(192), prime.clone()).unwrap();
+        let y1 = FieldElement::from(BigInt::from(105), prime.clone()).unwrap();
+
+        let x2 = FieldElement::from(BigInt::from(17), prime.clone()).unwrap();
+        let y2 = FieldElement::from(BigInt::from(56), prime.clone()).unwrap();
+
+        let x3 = FieldElement::from(BigInt::from(170), prime.clone()).unwrap();
+        let y3 = FieldElement::from(BigInt::from(142), prime.clone()).unwrap();
+
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+
+        let p1 = Point::from(Some(x1), Some(y1), a.clone(), b.clone()).unwrap();
+        let p2 = Point::from(Some(x2), Some(y2), a.clone(), b.clone()).unwrap();
+
+        let p3 = Point::from(Some(x3), Some(y3), a.clone(), b.clone()).unwrap();
+
+        assert_eq!(p1 + p2, p3);
+    }
+
+    #[test]
+    fn test_add2() {
+        let prime = BigInt::from(223);
+        let x1 = FieldElement::from(BigInt::from(17), prime.clone()).unwrap();
+        let y1 = FieldElement::from(BigInt::from(56), prime.clone()).unwrap();
+
+        let x2 = FieldElement::from(BigInt::from(13), prime.clone()).unwrap();
+        let y2 = FieldElement::from(BigInt::from(190), prime.clone()).unwrap();
+
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+
+        let p1 = Point::from(Some(x1), Some(y1), a.clone(), b.clone()).unwrap();
+        let p2 = Point::from(Some(x2), Some(y2), a.clone(), b.clone()).unwrap();
+
+        assert_eq!(p1.clone() + p1.clone(), p2);
+    }
+
+    #[test]
+    fn test_add3() {
+        let prime = BigInt::from(223);
+        let x1 = FieldElement::from(BigInt::from(170), prime.clone()).unwrap();
+        let y1 = FieldElement::from(BigInt::from(142), prime.clone()).unwrap();
+
+        let x2 = FieldElement::from(BigInt::from(60), prime.clone()).unwrap();
+        let y2 = FieldElement::from(BigInt::from(139), prime.clone()).unwrap();
+
+        let x3 = FieldElement::from(BigInt::from(220), prime.clone()).unwrap();
+        let y3 = FieldElement::from(BigInt::from(181), prime.clone()).unwrap();
+
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+
+        let p1 = Point::from(Some(x1), Some(y1), a.clone(), b.clone()).unwrap();
+        let p2 = Point::from(Some(x2), Some(y2), a.clone(), b.clone()).unwrap();
+        let p3 = Point::from(Some(x3), Some(y3), a.clone(), b.clone()).unwrap();
+
+        assert_eq!(p1 + p2, p3);
+    }
+
+    #[test]
+    fn test_add4() {
+        let prime = BigInt::from(223);
+        let x1 = FieldElement::from(BigInt::from(47), prime.clone()).unwrap();
+        let y1 = FieldElement::from(BigInt::from(71), prime.clone()).unwrap();
+
+        let x2 = FieldElement::from(BigInt::from(17), prime.clone()).unwrap();
+        let y2 = FieldElement::from(BigInt::from(56), prime.clone()).unwrap();
+
+        let x3 = FieldElement::from(BigInt::from(215), prime.clone()).unwrap();
+        let y3 = FieldElement::from(BigInt::from(68), prime.clone()).unwrap();
+
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+
+        let p1 = Point::from(Some(x1), Some(y1), a.clone(), b.clone()).unwrap();
+        let p2 = Point::from(Some(x2), Some(y2), a.clone(), b.clone()).unwrap();
+        let p3 = Point::from(Some(x3), Some(y3), a.clone(), b.clone()).unwrap();
+
+        assert_eq!(p1 + p2, p3);
+    }
+
+    #[test]
+    fn test_add5() {
+        let prime = BigInt::from(223);
+        let x1 = FieldElement::from(BigInt::from(143), prime.clone()).unwrap();
+        let y1 = FieldElement::from(BigInt::from(98), prime.clone()).unwrap();
+
+        let x2 = FieldElement::from(BigInt::from(76), prime.clone()).unwrap();
+        let y2 = FieldElement::from(BigInt::from(66), prime.clone()).unwrap();
+
+        let x3 = FieldElement::from(BigInt::from(47), prime.clone()).unwrap();
+        let y3 = FieldElement::from(BigInt::from(71), prime.clone()).unwrap();
+
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+
+        let p1 = Point::from(Some(x1), Some(y1), a.clone(), b.clone()).unwrap();
+        let p2 = Point::from(Some(x2), Some(y2), a.clone(), b.clone()).unwrap();
+        let p3 = Point::from(Some(x3), Some(y3), a.clone(), b.clone()).unwrap();
+
+        assert_eq!(p1 + p2, p3);
+    }
+
+    #[test]
+    fn test_scalar_mul() {
+        let prime = BigInt::from(223);
+        let x1 = FieldElement::from(BigInt::from(47), prime.clone()).unwrap();
+        let y1 = FieldElement::from(BigInt::from(71), prime.clone()).unwrap();
+
+        let x2 = FieldElement::from(BigInt::from(194), prime.clone()).unwrap();
+        let y2 = FieldElement::from(BigInt::from(172), prime.clone()).unwrap();
+
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+
+        let p1 = Point::from(Some(x1), Some(y1), a.clone(), b.clone()).unwrap();
+        let p2 = Point::from(Some(x2), Some(y2), a.clone(), b.clone()).unwrap();
+        let s = BigInt::from(17);
+
+        assert_eq!(s * p1, p2);
+    }
+
+    #[test]
+    fn test_shared_curve() {
+        let prime = BigInt::from(223);
+        let element = |n: i32| FieldElement::from(BigInt::from(n), prime.clone()).unwrap();
+        let curve = Curve::new(element(0), element(7)).unwrap();
+        let p = Point::on_curve(Some(element(47)), Some(element(71)), &curve).unwrap();
+        let q = Point::on_curve(Some(element(17)), Some(element(56)), &curve).unwrap();
+
+        // clones and sums point at the one curve
+        let sum = p.clone() + q;
+        assert!(Arc::ptr_eq(sum.curve(), &curve) && Arc::ptr_eq(p.clone().curve(), &curve));
+        assert_eq!(Arc::strong_count(&curve), 3);
+
+        // a curve built separately with the same coefficients is the same curve
+        let separate = Point::from(Some(element(47)), Some(element(71)), element(0), element(7)).unwrap();
+        assert!(!Arc::ptr_eq(separate.curve(), &curve));
+        assert_eq!(separate, p);
+        assert!(p.is_on(separate.curve()));
+        assert_eq!(separate.checked_add(p.clone()), Ok(p.clone() + p.clone()));
+
+        let other = Curve::new(element(5), element(7)).unwrap();
+        assert!(!p.is_on(&other));
+        assert_eq!(
+            Point::on_curve(Some(element(47)), Some(element(71)), &other),
+            Err(PointError::NotOnCurve)
+        );
+        assert_eq!(
+            Curve::new(
+                element(0),
+                FieldElement::from(BigInt::from(7), BigInt::from(11)).unwrap()
+            ),
+            Err(PointError::Field(FieldError::FieldMismatch))
+        );
+    }
+
+    #[test]
+    fn test_point_hex() {
+        let prime = BigInt::from(223);
+        let x = FieldElement::from(BigInt::from(47), prime.clone()).unwrap();
+        let y = FieldElement::from(BigInt::from(71), prime.clone()).unwrap();
+        let a = FieldElement::from(BigInt::from(0), prime.clone()).unwrap();
+        let b = FieldElement::from(BigInt::from(7), prime.clone()).unwrap();
+        let p = Point::from(Some(x), Some(y), a.clone(), b.clone()).unwrap();
+
+        assert_eq!(p.to_hex(), "032f");
+        assert_eq!(format!("{:#X}", p), "0x032F");
+        assert_eq!(format!("{:x}", Point::infinity(a, b).unwrap()), "00");
+    }
+
+    #[test]
+    fn test_add_traced() {
+        let prime = BigInt::from(223);
+        let fe = |n: i64| FieldElement::from(BigInt::from(n), prime.clone()).unwrap();
+        let point = |x: i64, y: i64| Point::from(Some(fe(x)), Some(fe(y)), fe(0), fe(7)).unwrap();
+        let infinity = Point::infinity(fe(0), fe(7)).unwrap();
+
+        // chord: m = (56 - 105) / (17 - 192)
+        let (sum, trace) = point(192, 105).add_traced(point(17, 56));
+        assert_eq!(sum, point(170, 142));
+        assert_eq!(trace.case, AdditionCase::Chord);
+        assert_eq!(
+            (trace.numerator.clone().unwrap(), trace.denominator.clone().unwrap()),
+            (fe(174), fe(48))
+        );
+        assert_eq!(trace.slope.clone().unwrap() * fe(48), fe(174));
+        assert_eq!(
+            (trace.x3.clone().unwrap(), trace.y3.clone().unwrap()),
+            (fe(170), fe(142))
+        );
+        assert!(trace
+            .to_string()
+            .starts_with("chord: m = (y2 - y1) / (x2 - x1) = 174 / 48 = "));
+
+        // doubling: m = 3 * 47² / (2 * 71)
+        let (sum, trace) = point(47, 71).add_traced(point(47, 71));
+        assert_eq!(sum, point(36, 111));
+        assert_eq!(trace.case, AdditionCase::Doubling);
+        assert_eq!(
+            (trace.numerator.unwrap(), trace.denominator.unwrap()),
+            (fe(160), fe(142))
+        );
+
+        let (sum, trace) = point(47, 71).add_traced(point(47, 152));
+        assert_eq!((sum, trace.case), (infinity.clone(), AdditionCase::Inverse));
+        assert!(trace.slope.is_none());
+        assert_eq!(trace.to_string(), "inverse: vertical line, P + (-P) = O");
+        let (sum, trace) = infinity.add_traced(point(47, 71));
+        assert_eq!((sum, trace.case), (point(47, 71), AdditionCase::Identity));
+    }
+
+    #[test]
+    fn test_pretty() {
+        let prime = BigInt::from(223);
+        let element = |n: i64| FieldElement::from(BigInt::from(n), prime.clone()).unwrap();
+        assert_eq!(format!("{:#}", element(192)), "192 (mod 223)");
+        let curve = Curve::new(element(0), element(7)).unwrap();
+        let p = Point::on_curve(Some(element(192)), Some(element(105)), &curve).unwrap();
+        assert_eq!(
+            format!("{:#}", p),
+            "Point on y² = x³ + 0x + 7 mod 223\n  x = 192\n  y = 105"
+        );
+        assert_eq!(
+            format!("{:#}", Point::infinity_on(&curve)),
+            "Point at infinity on y² = x³ + 0x + 7 mod 223"
+        );
+        // the plain form is unchanged
+        assert_eq!(format!("{}", element(192)), "FieldElement_223(192)");
+
+        // past 64 bits, numbers are hex
+        let g = format!("{:#}", crate::curves::secp256k1::ecc::generator());
+        assert!(g.starts_with("Point on y² = x³ + 0x + 7 mod 0xffffffff"));
+        assert!(g.ends_with("\n  y = 0x483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"));
+    }
+
+    #[test]
+    fn test_order() {
+        // y² = x³ + 7 over F_223, whose group has 252 = 2² * 3² * 7 points,
+        // so by Lagrange every order divides 252
+        let prime = BigInt::from(223);
+        let element = |n: i64| FieldElement::from(BigInt::from(n), prime.clone()).unwrap();
+        let curve = Curve::new(element(0), element(7)).unwrap();
+        let point = |x, y| Point::on_curve(Some(element(x)), Some(element(y)), &curve).unwrap();
+        assert_eq!(point(15, 86).order(), BigInt::from(7));
+        assert_eq!(point(47, 71).order(), BigInt::from(21));
+        assert_eq!(Point::infinity_on(&curve).order(), BigInt::from(1));
+        assert_eq!(point(47, 71).order() * point(47, 71), Point::infinity_on(&curve));
+        assert_eq!(BigInt::from(252) % point(192, 105).order(), BigInt::from(0));
+    }
+}
@@ -0,0 +1,24 @@
+use num_bigint::{BigInt, BigUint};
+
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::context::context;
+use crate::curves::secp256k1::ecc::{infinity, order};
+
+pub const NAME: &str = "bigint";
+
+/// One addition per set bit of k from the shared table of 2^i G
+pub fn mul_generator(k: &BigUint) -> Point {
+    let k = k % order();
+    let table = context().generator_table();
+    (0..k.bits())
+        .filter(|&bit| k.bit(bit))
+        .fold(infinity(), |acc, bit| acc + table[bit as usize].clone())
+}
+
+pub fn mul(k: &BigUint, point: &Point) -> Point {
+    BigInt::from(k.clone()) * point.clone()
+}
+
+pub fn mul_add(u: &BigUint, v: &BigUint, point: &Point) -> Point {
+    mul_generator(u) + mul(v, point)
+}
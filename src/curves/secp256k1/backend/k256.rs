@@ -0,0 +1,53 @@
+use k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use k256::elliptic_curve::PrimeField;
+use k256::{AffinePoint, EncodedPoint, ProjectivePoint, Scalar};
+use num_bigint::{BigInt, BigUint};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::context::context;
+use crate::curves::secp256k1::ecc::{infinity, order, prime, to_32_bytes};
+
+pub const NAME: &str = "k256";
+
+pub fn mul_generator(k: &BigUint) -> Point {
+    from_projective(ProjectivePoint::GENERATOR * scalar(k))
+}
+
+pub fn mul(k: &BigUint, point: &Point) -> Point {
+    from_projective(to_projective(point) * scalar(k))
+}
+
+pub fn mul_add(u: &BigUint, v: &BigUint, point: &Point) -> Point {
+    from_projective(ProjectivePoint::GENERATOR * scalar(u) + to_projective(point) * scalar(v))
+}
+
+fn scalar(k: &BigUint) -> Scalar {
+    let bytes = to_32_bytes(&BigInt::from(k % order()));
+    Option::from(Scalar::from_repr(bytes.into())).expect("reduced mod n")
+}
+
+fn to_projective(point: &Point) -> ProjectivePoint {
+    match (point.x(), point.y()) {
+        (Some(x), Some(y)) => {
+            let (x, y) = (to_32_bytes(&x.num()), to_32_bytes(&y.num()));
+            let encoded = EncodedPoint::from_affine_coordinates(&x.into(), &y.into(), false);
+            let affine: Option<AffinePoint> = AffinePoint::from_encoded_point(&encoded).into();
+            affine.expect("a secp256k1 point").into()
+        }
+        _ => ProjectivePoint::IDENTITY,
+    }
+}
+
+fn from_projective(point: ProjectivePoint) -> Point {
+    let encoded = point.to_affine().to_encoded_point(false);
+    match (encoded.x(), encoded.y()) {
+        (Some(x), Some(y)) => {
+            let coordinate =
+                |c: &[u8]| FieldElement::from(BigInt::from(BigUint::from_bytes_be(c)), prime()).expect("below p");
+            Point::on_curve(Some(coordinate(x)), Some(coordinate(y)), context().curve())
+                .expect("k256 points are on the curve")
+        }
+        _ => infinity(),
+    }
+}
@@ -0,0 +1,134 @@
+// secp256k1 scalar multiplication, the bulk of the cost of keys and
+// signatures, behind one API with three implementations:
+//
+// - bigint: `Point` and `FieldElement` arithmetic as written, affine
+//   coordinates over num-bigint. Slow, and every step is readable
+// - native-u64: 4×64-bit limbs and Jacobian coordinates, no dependencies
+// - k256: RustCrypto's constant-time implementation
+//
+// Points go in and come out as `Point`, so the choice never shows in the
+// rest of the crate. Features are additive, so with several enabled the
+// fastest wins: k256, then native-u64, then bigint
+
+// the tests check every backend against bigint, selected or not
+#[cfg(any(not(any(feature = "backend-native-u64", feature = "backend-k256")), test))]
+mod bigint;
+#[cfg(feature = "backend-k256")]
+mod k256;
+#[cfg(any(all(feature = "backend-native-u64", not(feature = "backend-k256")), test))]
+mod native;
+
+use num_bigint::BigUint;
+
+use crate::curves::ec_point::Point;
+
+#[cfg(not(any(feature = "backend-native-u64", feature = "backend-k256")))]
+use self::bigint as selected;
+#[cfg(feature = "backend-k256")]
+use self::k256 as selected;
+#[cfg(all(feature = "backend-native-u64", not(feature = "backend-k256")))]
+use self::native as selected;
+
+/// The backend in use: "bigint", "native-u64" or "k256"
+pub const NAME: &str = selected::NAME;
+
+/// k G
+pub fn mul_generator(k: &BigUint) -> Point {
+    selected::mul_generator(k)
+}
+
+/// k P, for P on secp256k1
+pub fn mul(k: &BigUint, point: &Point) -> Point {
+    selected::mul(k, point)
+}
+
+/// u G + v P, the shape of every signature verification
+pub fn mul_add(u: &BigUint, v: &BigUint, point: &Point) -> Point {
+    selected::mul_add(u, v, point)
+}
+
+#[cfg(test)]
+mod backend_tests {
+    use num_bigint::BigInt;
+
+    use super::*;
+    use crate::curves::secp256k1::ecc::{generator, infinity, order};
+
+    fn scalars() -> Vec<BigUint> {
+        let n = order();
+        vec![
+            BigUint::from(0_u8),
+            BigUint::from(1_u8),
+            BigUint::from(2_u8),
+            BigUint::from(0xdeadbeef_u32),
+            &n - 1_u8,
+            n.clone(),
+            &n + 5_u8,
+            BigUint::from_bytes_be(&[0x5a; 32]),
+            BigUint::from_bytes_be(&[0xff; 32]),
+        ]
+    }
+
+    /// Every compiled backend against the plain arithmetic
+    fn check(
+        name: &str,
+        mul_generator: fn(&BigUint) -> Point,
+        mul: fn(&BigUint, &Point) -> Point,
+        mul_add: fn(&BigUint, &BigUint, &Point) -> Point,
+    ) {
+        let g = generator();
+        let p = BigInt::from(0x1234_5678_u32) * g.clone();
+        for k in scalars() {
+            let expected = BigInt::from(k.clone()) * g.clone();
+            assert_eq!(mul_generator(&k), expected, "{} k G for k = {}", name, k);
+            assert_eq!(
+                mul(&k, &p),
+                BigInt::from(k.clone()) * p.clone(),
+                "{} k P for k = {}",
+                name,
+                k
+            );
+            assert_eq!(mul(&k, &infinity()), infinity(), "{} k O", name);
+            let v = BigUint::from(7_u8) + &k;
+            assert_eq!(
+                mul_add(&k, &v, &p),
+                expected + BigInt::from(v.clone()) * p.clone(),
+                "{} u G + v P",
+                name
+            );
+        }
+        // u G + v P = O, and u G + v G, which adds a point to itself
+        let n = order();
+        assert_eq!(mul_add(&BigUint::from(5_u8), &(&n - 5_u8), &g), infinity(), "{}", name);
+        assert_eq!(
+            mul_add(&BigUint::from(5_u8), &BigUint::from(5_u8), &g),
+            BigInt::from(10_u8) * g.clone(),
+            "{}",
+            name
+        );
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        check(bigint::NAME, bigint::mul_generator, bigint::mul, bigint::mul_add);
+        check(native::NAME, native::mul_generator, native::mul, native::mul_add);
+        #[cfg(feature = "backend-k256")]
+        check(
+            self::k256::NAME,
+            self::k256::mul_generator,
+            self::k256::mul,
+            self::k256::mul_add,
+        );
+    }
+
+    #[test]
+    fn test_selected() {
+        #[cfg(feature = "backend-k256")]
+        assert_eq!(NAME, "k256");
+        #[cfg(all(feature = "backend-native-u64", not(feature = "backend-k256")))]
+        assert_eq!(NAME, "native-u64");
+        #[cfg(not(any(feature = "backend-native-u64", feature = "backend-k256")))]
+        assert_eq!(NAME, "bigint");
+        assert_eq!(mul_generator(&BigUint::from(1_u8)), generator());
+    }
+}
@@ -0,0 +1,470 @@
+use std::sync::OnceLock;
+
+use num_bigint::{BigInt, BigUint};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::context::context;
+use crate::curves::secp256k1::ecc::{infinity, order, prime, to_32_bytes, GX, GY, P};
+
+pub const NAME: &str = "native-u64";
+
+// p = 2^256 - C, so 2^256 ≡ C and a 512-bit product folds down with two
+// multiplications by a 33-bit constant instead of a division
+const C: u64 = 0x1_0000_03d1;
+
+/// An element of F_p as little-endian 64-bit limbs, always below p
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fe([u64; 4]);
+
+impl Fe {
+    const ZERO: Self = Self([0; 4]);
+    const ONE: Self = Self([1, 0, 0, 0]);
+
+    /// Big-endian bytes of a number below p
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut limbs = [0_u64; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = u64::from_be_bytes(bytes[24 - 8 * i..32 - 8 * i].try_into().unwrap());
+        }
+        Self(limbs)
+    }
+
+    fn to_bytes(self) -> [u8; 32] {
+        let mut res = [0_u8; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            res[24 - 8 * i..32 - 8 * i].copy_from_slice(&limb.to_be_bytes());
+        }
+        res
+    }
+
+    fn is_zero(self) -> bool {
+        self == Self::ZERO
+    }
+
+    fn add(self, rhs: Self) -> Self {
+        let (sum, carry) = add_limbs(self.0, rhs.0);
+        // past 2^256, the excess is worth C more
+        let sum = if carry { add_limbs(sum, [C, 0, 0, 0]).0 } else { sum };
+        Self(reduce_once(sum))
+    }
+
+    fn sub(self, rhs: Self) -> Self {
+        let (diff, borrow) = sub_limbs(self.0, rhs.0);
+        // wrapped around 2^256 instead of p: take back the difference
+        Self(if borrow { sub_limbs(diff, [C, 0, 0, 0]).0 } else { diff })
+    }
+
+    fn double(self) -> Self {
+        self.add(self)
+    }
+
+    fn mul(self, rhs: Self) -> Self {
+        self.mul_wide(rhs).reduce()
+    }
+
+    /// The full 512-bit product, left for the caller to reduce
+    fn mul_wide(self, rhs: Self) -> Wide {
+        let mut wide = [0_u64; 8];
+        for i in 0..4 {
+            let mut carry = 0_u128;
+            for j in 0..4 {
+                let v = wide[i + j] as u128 + self.0[i] as u128 * rhs.0[j] as u128 + carry;
+                wide[i + j] = v as u64;
+                carry = v >> 64;
+            }
+            wide[i + 4] = carry as u64;
+        }
+        Wide(wide)
+    }
+
+    fn square(self) -> Self {
+        self.mul(self)
+    }
+
+    /// a^(p - 2)
+    fn invert(self) -> Self {
+        let exponent = sub_limbs(P, [2, 0, 0, 0]).0;
+        let mut res = Self::ONE;
+        for bit in (0..256).rev() {
+            res = res.square();
+            if exponent[bit / 64] >> (bit % 64) & 1 == 1 {
+                res = res.mul(self);
+            }
+        }
+        res
+    }
+}
+
+// Lazy reduction: a formula like r (u - x) - s h takes the difference of
+// two products, and subtracting them unreduced then reducing once saves
+// the reduction of one product. A `Wide` holds such a value below 2^512,
+// congruent to the result mod p. 2^512 ≡ C² mod p, so a borrow out of the
+// top limb is made good with C², the way `Fe::sub` uses C for 2^256
+
+/// A number below 2^512, standing for its residue mod p
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Wide([u64; 8]);
+
+const C_SQUARED: [u64; 8] = {
+    let c2 = C as u128 * C as u128;
+    [c2 as u64, (c2 >> 64) as u64, 0, 0, 0, 0, 0, 0]
+};
+
+impl Wide {
+    fn from_fe(fe: Fe) -> Self {
+        let mut limbs = [0_u64; 8];
+        limbs[..4].copy_from_slice(&fe.0);
+        Self(limbs)
+    }
+
+    /// For `rhs` below p², as products of elements and elements are. A
+    /// wrapped difference is then at least 2^512 - p², far above C², so
+    /// taking C² off can't borrow again
+    fn sub(self, rhs: Self) -> Self {
+        let (diff, borrow) = sub_wide(self.0, rhs.0);
+        Self(if borrow { sub_wide(diff, C_SQUARED).0 } else { diff })
+    }
+
+    fn reduce(self) -> Fe {
+        let wide = self.0;
+        // lo + hi * C, which leaves a top limb of at most 34 bits
+        let mut folded = [0_u64; 4];
+        let mut carry = 0_u128;
+        for i in 0..4 {
+            let v = wide[i] as u128 + wide[i + 4] as u128 * C as u128 + carry;
+            folded[i] = v as u64;
+            carry = v >> 64;
+        }
+        // and once more for the top limb
+        let top = carry * C as u128;
+        let (sum, overflow) = add_limbs(folded, [top as u64, (top >> 64) as u64, 0, 0]);
+        let sum = if overflow { add_limbs(sum, [C, 0, 0, 0]).0 } else { sum };
+        Fe(reduce_once(sum))
+    }
+}
+
+fn sub_wide(a: [u64; 8], b: [u64; 8]) -> ([u64; 8], bool) {
+    let mut res = [0_u64; 8];
+    let mut borrow = false;
+    for i in 0..8 {
+        let (v, b1) = a[i].overflowing_sub(b[i]);
+        let (v, b2) = v.overflowing_sub(borrow as u64);
+        res[i] = v;
+        borrow = b1 || b2;
+    }
+    (res, borrow)
+}
+
+fn add_limbs(a: [u64; 4], b: [u64; 4]) -> ([u64; 4], bool) {
+    let mut res = [0_u64; 4];
+    let mut carry = false;
+    for i in 0..4 {
+        let (v, c1) = a[i].overflowing_add(b[i]);
+        let (v, c2) = v.overflowing_add(carry as u64);
+        res[i] = v;
+        carry = c1 || c2;
+    }
+    (res, carry)
+}
+
+fn sub_limbs(a: [u64; 4], b: [u64; 4]) -> ([u64; 4], bool) {
+    let mut res = [0_u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (v, b1) = a[i].overflowing_sub(b[i]);
+        let (v, b2) = v.overflowing_sub(borrow as u64);
+        res[i] = v;
+        borrow = b1 || b2;
+    }
+    (res, borrow)
+}
+
+/// For values below 2p
+fn reduce_once(a: [u64; 4]) -> [u64; 4] {
+    match sub_limbs(a, P) {
+        (reduced, false) => reduced,
+        (_, true) => a,
+    }
+}
+
+/// (X, Y, Z) for the affine point (X / Z², Y / Z³), which puts off the
+/// inversion every affine addition needs until the very end. Z = 0 is the
+/// point at infinity
+#[derive(Debug, Clone, Copy)]
+struct Jacobian {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+}
+
+impl Jacobian {
+    const INFINITY: Self = Self {
+        x: Fe::ONE,
+        y: Fe::ONE,
+        z: Fe::ZERO,
+    };
+    const GENERATOR: Self = Self {
+        x: Fe(GX),
+        y: Fe(GY),
+        z: Fe::ONE,
+    };
+
+    fn from_point(point: &Point) -> Self {
+        match (point.x(), point.y()) {
+            (Some(x), Some(y)) => Self {
+                x: Fe::from_bytes(&to_32_bytes(&x.num())),
+                y: Fe::from_bytes(&to_32_bytes(&y.num())),
+                z: Fe::ONE,
+            },
+            _ => Self::INFINITY,
+        }
+    }
+
+    fn to_point(self) -> Point {
+        if self.is_infinity() {
+            return infinity();
+        }
+        let z_inv = self.z.invert();
+        let z_inv2 = z_inv.square();
+        let coordinate = |fe: Fe| {
+            FieldElement::from(BigInt::from(BigUint::from_bytes_be(&fe.to_bytes())), prime()).expect("below p")
+        };
+        let x = coordinate(self.x.mul(z_inv2));
+        let y = coordinate(self.y.mul(z_inv2).mul(z_inv));
+        Point::on_curve(Some(x), Some(y), context().curve()).expect("the arithmetic stays on the curve")
+    }
+
+    fn is_infinity(self) -> bool {
+        self.z.is_zero()
+    }
+
+    /// dbl-2009-l, for a = 0
+    fn double(self) -> Self {
+        if self.is_infinity() || self.y.is_zero() {
+            return Self::INFINITY;
+        }
+        let a = self.x.square();
+        let b = self.y.square();
+        let c = b.square();
+        let d = self.x.add(b).square().sub(a).sub(c).double();
+        let e = a.double().add(a);
+        let x = e.square().sub(d.double());
+        let y = e
+            .mul_wide(d.sub(x))
+            .sub(Wide::from_fe(c.double().double().double()))
+            .reduce();
+        let z = self.y.mul(self.z).double();
+        Self { x, y, z }
+    }
+
+    fn add(self, rhs: Self) -> Self {
+        if self.is_infinity() {
+            return rhs;
+        }
+        if rhs.is_infinity() {
+            return self;
+        }
+        let z1z1 = self.z.square();
+        let z2z2 = rhs.z.square();
+        let u1 = self.x.mul(z2z2);
+        let u2 = rhs.x.mul(z1z1);
+        let s1 = self.y.mul(rhs.z).mul(z2z2);
+        let s2 = rhs.y.mul(self.z).mul(z1z1);
+        let h = u2.sub(u1);
+        let r = s2.sub(s1);
+        if h.is_zero() {
+            // the same x: P + P or P + (-P)
+            return if r.is_zero() { self.double() } else { Self::INFINITY };
+        }
+        let h2 = h.square();
+        let h3 = h.mul(h2);
+        let u1h2 = u1.mul(h2);
+        let x = r.square().sub(h3).sub(u1h2.double());
+        let y = r.mul_wide(u1h2.sub(x)).sub(s1.mul_wide(h3)).reduce();
+        let z = self.z.mul(rhs.z).mul(h);
+        Self { x, y, z }
+    }
+}
+
+/// The context's table of 2^i G, converted once
+fn generator_table() -> &'static [Jacobian] {
+    static TABLE: OnceLock<Vec<Jacobian>> = OnceLock::new();
+    TABLE.get_or_init(|| context().generator_table().iter().map(Jacobian::from_point).collect())
+}
+
+pub fn mul_generator(k: &BigUint) -> Point {
+    let k = k % order();
+    let table = generator_table();
+    (0..k.bits())
+        .filter(|&bit| k.bit(bit))
+        .fold(Jacobian::INFINITY, |acc, bit| acc.add(table[bit as usize]))
+        .to_point()
+}
+
+pub fn mul(k: &BigUint, point: &Point) -> Point {
+    let k = k % order();
+    let point = Jacobian::from_point(point);
+    let mut acc = Jacobian::INFINITY;
+    for bit in (0..k.bits()).rev() {
+        acc = acc.double();
+        if k.bit(bit) {
+            acc = acc.add(point);
+        }
+    }
+    acc.to_point()
+}
+
+/// Shamir's trick: one chain of doublings for both products
+pub fn mul_add(u: &BigUint, v: &BigUint, point: &Point) -> Point {
+    let n = order();
+    let (u, v) = (u % &n, v % &n);
+    let g = Jacobian::GENERATOR;
+    let p = Jacobian::from_point(point);
+    let both = g.add(p);
+    let mut acc = Jacobian::INFINITY;
+    for bit in (0..u.bits().max(v.bits())).rev() {
+        acc = acc.double();
+        acc = match (u.bit(bit), v.bit(bit)) {
+            (true, true) => acc.add(both),
+            (true, false) => acc.add(g),
+            (false, true) => acc.add(p),
+            (false, false) => acc,
+        };
+    }
+    acc.to_point()
+}
+
+#[cfg(test)]
+mod native_tests {
+    use num_traits::One;
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    use super::*;
+    use crate::curves::secp256k1::ecc::generator;
+
+    fn to_biguint(limbs: &[u64]) -> BigUint {
+        BigUint::from_slice(
+            &limbs
+                .iter()
+                .flat_map(|&l| [l as u32, (l >> 32) as u32])
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn p() -> BigUint {
+        to_biguint(&P)
+    }
+
+    fn wide(n: &BigUint) -> Wide {
+        let mut limbs = [0_u64; 8];
+        for (limb, digit) in limbs.iter_mut().zip(n.iter_u64_digits()) {
+            *limb = digit;
+        }
+        Wide(limbs)
+    }
+
+    fn fe(rng: &mut StdRng) -> Fe {
+        let mut bytes = [0_u8; 32];
+        rng.fill_bytes(&mut bytes);
+        let n = BigUint::from_bytes_be(&bytes) % p();
+        Fe::from_bytes(&to_32_bytes(&BigInt::from(n)))
+    }
+
+    #[test]
+    fn test_wide_reduce() {
+        let top = (BigUint::one() << 512_u32) - 1_u8;
+        let mut rng = StdRng::seed_from_u64(2477);
+        let mut values = vec![BigUint::from(0_u8), p(), p() * p(), &p() * &p() - 1_u8, top];
+        for _ in 0..200 {
+            let mut bytes = [0_u8; 64];
+            rng.fill_bytes(&mut bytes);
+            values.push(BigUint::from_bytes_be(&bytes));
+        }
+        for n in values {
+            assert_eq!(to_biguint(&wide(&n).reduce().0), &n % p(), "{:x}", n);
+        }
+    }
+
+    #[test]
+    fn test_wide_sub() {
+        let mut rng = StdRng::seed_from_u64(2477);
+        let p_squared = p() * p();
+        for i in 0..200 {
+            let (a, b, c, d) = (fe(&mut rng), fe(&mut rng), fe(&mut rng), fe(&mut rng));
+            // the difference of products borrows about half the time, and
+            // a small minuend makes it borrow every time
+            let lhs = if i % 4 == 0 { Wide::from_fe(a) } else { a.mul_wide(b) };
+            let rhs = c.mul_wide(d);
+            let diff = lhs.sub(rhs);
+            let expected = (to_biguint(&lhs.0) + &p_squared - to_biguint(&rhs.0)) % p();
+            assert_eq!(to_biguint(&diff.reduce().0), expected);
+            let minuend = if i % 4 == 0 { a } else { a.mul(b) };
+            assert_eq!(diff.reduce(), minuend.sub(c.mul(d)));
+        }
+        // the largest subtrahend from zero
+        let max = Fe(sub_limbs(P, [1, 0, 0, 0]).0);
+        let diff = Wide::from_fe(Fe::ZERO).sub(max.mul_wide(max));
+        assert_eq!(diff.reduce(), Fe::ZERO.sub(max.mul(max)));
+    }
+
+    /// The formulas with every product reduced, as before lazy reduction
+    fn double_strict(p: Jacobian) -> Jacobian {
+        if p.is_infinity() || p.y.is_zero() {
+            return Jacobian::INFINITY;
+        }
+        let a = p.x.square();
+        let b = p.y.square();
+        let c = b.square();
+        let d = p.x.add(b).square().sub(a).sub(c).double();
+        let e = a.double().add(a);
+        let x = e.square().sub(d.double());
+        let y = e.mul(d.sub(x)).sub(c.double().double().double());
+        Jacobian {
+            x,
+            y,
+            z: p.y.mul(p.z).double(),
+        }
+    }
+
+    fn add_strict(p: Jacobian, q: Jacobian) -> Jacobian {
+        let z1z1 = p.z.square();
+        let z2z2 = q.z.square();
+        let u1 = p.x.mul(z2z2);
+        let s1 = p.y.mul(q.z).mul(z2z2);
+        let h = q.x.mul(z1z1).sub(u1);
+        let r = q.y.mul(p.z).mul(z1z1).sub(s1);
+        let h2 = h.square();
+        let h3 = h.mul(h2);
+        let u1h2 = u1.mul(h2);
+        let x = r.square().sub(h3).sub(u1h2.double());
+        let y = r.mul(u1h2.sub(x)).sub(s1.mul(h3));
+        Jacobian {
+            x,
+            y,
+            z: p.z.mul(q.z).mul(h),
+        }
+    }
+
+    fn same(p: Jacobian, q: Jacobian) -> bool {
+        (p.x, p.y, p.z) == (q.x, q.y, q.z)
+    }
+
+    #[test]
+    fn test_lazy_formulas() {
+        let g = Jacobian::GENERATOR;
+        let mut p = g.double();
+        let mut q = g;
+        for _ in 0..100 {
+            assert!(same(p.double(), double_strict(p)));
+            assert!(same(p.add(q), add_strict(p, q)));
+            q = p;
+            p = p.add(g).double();
+        }
+        assert_eq!(
+            p.to_point(),
+            BigInt::from(2_u8).pow(101) * generator() + (BigInt::from(2_u8).pow(101) - 2_u8) * generator()
+        );
+    }
+}
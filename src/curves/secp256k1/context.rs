@@ -0,0 +1,137 @@
+use std::sync::{Arc, OnceLock};
+
+use num_bigint::{BigInt, BigUint};
+use sha2::Sha256;
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::crypto::hash::{midstate, KNOWN_TAGS};
+use crate::curves::ec_point::{Curve, Point};
+use crate::curves::secp256k1::ecc::{from_limbs, A, B, GX, GY, N, P};
+
+// Everything signing and verification needs that never changes: the
+// secp256k1 constants, the doublings of the generator and the tagged-hash
+// midstates. Built on first use and shared by every thread after that, so
+// no call pays for parsing hex or hashing a tag twice
+
+/// The secp256k1 precomputation shared by the whole process
+#[derive(Debug)]
+pub struct Context {
+    prime: BigInt,
+    order: BigUint,
+    curve: Arc<Curve>,
+    generator: Point,
+    generator_table: Vec<Point>,
+    midstates: Vec<(&'static str, Sha256)>,
+}
+
+/// The process-wide context, built by whichever thread asks first
+pub fn context() -> &'static Context {
+    static CONTEXT: OnceLock<Context> = OnceLock::new();
+    CONTEXT.get_or_init(Context::new)
+}
+
+impl Context {
+    fn new() -> Self {
+        let prime = BigInt::from(from_limbs(&P));
+        let order = from_limbs(&N);
+        let element = |limbs: [u64; 4]| FieldElement::from_limbs(limbs, P).expect("below p");
+        let curve = Curve::new(element(A), element(B)).expect("one field");
+        let (gx, gy) = (element(GX), element(GY));
+        let generator = Point::on_curve(Some(gx), Some(gy), &curve).expect("the generator is on the curve");
+
+        let mut generator_table = Vec::with_capacity(order.bits() as usize);
+        let mut power = generator.clone();
+        for _ in 0..order.bits() {
+            let next = power.clone() + power.clone();
+            generator_table.push(power);
+            power = next;
+        }
+
+        let midstates = KNOWN_TAGS.iter().map(|&tag| (tag, midstate(tag))).collect();
+        Self {
+            prime,
+            order,
+            curve,
+            generator,
+            generator_table,
+            midstates,
+        }
+    }
+
+    /// p, the size of the base field
+    pub fn prime(&self) -> &BigInt {
+        &self.prime
+    }
+
+    /// n, the order of the generator
+    pub fn order(&self) -> &BigUint {
+        &self.order
+    }
+
+    /// The coefficients a = 0 and b = 7 of y^2 = x^3 + a x + b
+    pub fn curve_params(&self) -> (&FieldElement, &FieldElement) {
+        (self.curve.a(), self.curve.b())
+    }
+
+    /// The curve every secp256k1 point in the crate shares, so checking
+    /// that a point is on it compares two pointers
+    pub fn curve(&self) -> &Arc<Curve> {
+        &self.curve
+    }
+
+    pub fn generator(&self) -> &Point {
+        &self.generator
+    }
+
+    /// 2^i G for every bit of n, so k G is one addition per set bit of k
+    /// and no doublings
+    pub fn generator_table(&self) -> &[Point] {
+        &self.generator_table
+    }
+
+    /// A SHA256 engine that has absorbed SHA256(tag) || SHA256(tag), for
+    /// the tags in `KNOWN_TAGS`
+    pub fn midstate(&self, tag: &str) -> Option<&Sha256> {
+        self.midstates.iter().find(|(t, _)| *t == tag).map(|(_, hasher)| hasher)
+    }
+}
+
+#[cfg(test)]
+mod context_tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_generator_table() {
+        let ctx = context();
+        let table = ctx.generator_table();
+        assert_eq!(table.len(), 256);
+        assert_eq!(table[0], *ctx.generator());
+        assert_eq!(table[1], BigInt::from(2_u8) * ctx.generator().clone());
+        assert_eq!(table[255], (BigInt::from(1_u8) << 255) * ctx.generator().clone());
+        assert_eq!(
+            BigInt::from(ctx.order().clone()) * ctx.generator().clone(),
+            Point::infinity_on(ctx.curve())
+        );
+    }
+
+    #[test]
+    fn test_midstates() {
+        let ctx = context();
+        for tag in KNOWN_TAGS {
+            assert!(ctx.midstate(tag).is_some(), "{}", tag);
+        }
+        assert!(ctx.midstate("moneda/test").is_none());
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let here = context() as *const Context as usize;
+        let elsewhere: Vec<usize> = (0..4)
+            .map(|_| thread::spawn(|| context() as *const Context as usize))
+            .map(|h| h.join().unwrap())
+            .collect();
+        assert!(elsewhere.iter().all(|&p| p == here));
+    }
+}
@@ -0,0 +1,1193 @@
+use std::fmt::Display;
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::crypto::hmac_drbg::HmacDrbg;
+use crate::crypto::rng::random_scalar;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::backend;
+use crate::curves::secp256k1::context::context;
+use crate::encoding::decode::{Context, DecodeError};
+use crate::encoding::hex_fmt::impl_hex_fmt;
+#[cfg(feature = "serde")]
+use crate::encoding::serde_codec::impl_serde_bytes;
+use crate::trace::{trace_event, trace_span};
+use num_bigint::{BigInt, BigUint};
+use num_traits::Zero;
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+
+// secp256k1 domain parameters, y^2 = x^3 + a x + b over F_p, as
+// little-endian 64-bit limbs. Being const they cost nothing at startup and
+// work in const contexts; the context builds its BigInt values from them
+
+/// p = 2^256 - 2^32 - 977
+pub const P: [u64; 4] = [0xffff_fffe_ffff_fc2f, u64::MAX, u64::MAX, u64::MAX];
+/// n, the order of G
+pub const N: [u64; 4] = [
+    0xbfd2_5e8c_d036_4141,
+    0xbaae_dce6_af48_a03b,
+    0xffff_ffff_ffff_fffe,
+    u64::MAX,
+];
+pub const A: [u64; 4] = [0; 4];
+pub const B: [u64; 4] = [7, 0, 0, 0];
+pub const GX: [u64; 4] = [
+    0x59f2_815b_16f8_1798,
+    0x029b_fcdb_2dce_28d9,
+    0x55a0_6295_ce87_0b07,
+    0x79be_667e_f9dc_bbac,
+];
+pub const GY: [u64; 4] = [
+    0x9c47_d08f_fb10_d4b8,
+    0xfd17_b448_a685_5419,
+    0x5da4_fbfc_0e11_08a8,
+    0x483a_da77_26a3_c465,
+];
+
+/// The number little-endian limbs such as `P` stand for
+pub fn from_limbs(limbs: &[u64; 4]) -> BigUint {
+    BigUint::from_bytes_le(&limbs.iter().flat_map(|limb| limb.to_le_bytes()).collect::<Vec<_>>())
+}
+
+pub fn prime() -> BigInt {
+    context().prime().clone()
+}
+
+pub fn order() -> BigUint {
+    context().order().clone()
+}
+
+pub fn generator() -> Point {
+    context().generator().clone()
+}
+
+/// Big-endian, zero-padded 32-byte encoding of a scalar or coordinate
+pub fn to_32_bytes(n: &BigInt) -> [u8; 32] {
+    let (_, bytes) = n.to_bytes_be();
+    let mut res = [0_u8; 32];
+    res[32 - bytes.len()..].copy_from_slice(&bytes);
+    res
+}
+
+/// a^-1 mod n, by Fermat since n is prime
+pub fn inverse_mod_order(a: &BigUint) -> BigUint {
+    let n = order();
+    a.modpow(&(&n - 2_u8), &n)
+}
+
+pub fn infinity() -> Point {
+    Point::infinity_on(context().curve())
+}
+
+/// The sum of `scalars[i] * points[i]`. Straus's method shares one chain of
+/// doublings between all the terms, so a sum of k products costs 256
+/// doublings plus an addition per set bit, instead of k full multiplications.
+/// Works on any curve; the empty sum is secp256k1's point at infinity
+pub fn multi_scalar_mul(scalars: &[BigUint], points: &[Point]) -> Point {
+    assert_eq!(scalars.len(), points.len(), "one scalar per point");
+    let bits = scalars.iter().map(|k| k.bits()).max().unwrap_or(0);
+    let mut acc = match points.first() {
+        Some(p) => Point::infinity_on(p.curve()),
+        None => infinity(),
+    };
+    for bit in (0..bits).rev() {
+        acc = acc.clone() + acc;
+        for (k, point) in scalars.iter().zip(points) {
+            if k.bit(bit) {
+                acc = acc + point.clone();
+            }
+        }
+    }
+    acc
+}
+
+/// `multi_scalar_mul` by Pippenger's bucket method, with the windows
+/// accumulated on separate threads. Each window of c bits drops every point
+/// into the bucket of its digit, then sums the buckets weighted by digit
+/// with two running sums, so k terms cost about k + 2^c additions per window
+#[cfg(feature = "parallel")]
+pub fn par_multi_scalar_mul(scalars: &[BigUint], points: &[Point]) -> Point {
+    use rayon::prelude::*;
+
+    assert_eq!(scalars.len(), points.len(), "one scalar per point");
+    let zero = match points.first() {
+        Some(p) => Point::infinity_on(p.curve()),
+        None => infinity(),
+    };
+    let bits = scalars.iter().map(|k| k.bits()).max().unwrap_or(0);
+    // about log2(k) bits per window balances the bucket sums against the
+    // additions into them
+    let c = (usize::BITS - points.len().leading_zeros()).max(2) as u64;
+    let digit = |k: &BigUint, window: u64| (0..c).filter(|&i| k.bit(window * c + i)).fold(0, |d, i| d | 1 << i);
+
+    let windows: Vec<Point> = (0..bits.div_ceil(c))
+        .into_par_iter()
+        .map(|window| {
+            let mut buckets = vec![zero.clone(); (1 << c) - 1];
+            for (k, point) in scalars.iter().zip(points) {
+                let d = digit(k, window);
+                if d > 0 {
+                    buckets[d - 1] = buckets[d - 1].clone() + point.clone();
+                }
+            }
+            // bucket d ends up in d of the running sums
+            let (mut running, mut sum) = (zero.clone(), zero.clone());
+            for bucket in buckets.into_iter().rev() {
+                running = running + bucket;
+                sum = sum + running.clone();
+            }
+            sum
+        })
+        .collect();
+
+    windows.into_iter().rev().fold(zero, |acc, window| {
+        (0..c).fold(acc, |acc, _| acc.clone() + acc) + window
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyError {
+    /// The tweak is not a valid scalar, i.e. it is >= n (or zero for multiplication)
+    TweakOutOfRange,
+    /// Tweaking produced the zero scalar or the point at infinity
+    IdentityResult,
+    /// The bytes are not a valid SEC1 encoding of a point on the curve
+    InvalidEncoding,
+    /// The secret is zero or not below the curve order
+    InvalidSecret,
+    /// No public key produces the signature with that recovery id
+    RecoveryFailed,
+    /// The keys of a key agreement are on different curves
+    CurveMismatch,
+    /// The peer's point has small order, so the shared secret is all zeros
+    SmallOrderPoint,
+    /// The point is not on secp256k1
+    NotOnCurve,
+    /// The point at infinity, which has no secret
+    Infinity,
+}
+
+impl Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::TweakOutOfRange => write!(f, "tweak is not in the range of the curve order"),
+            KeyError::IdentityResult => write!(f, "tweak produces the identity"),
+            KeyError::InvalidEncoding => write!(f, "invalid SEC1 public key encoding"),
+            KeyError::InvalidSecret => write!(f, "secret is not in range [1, n)"),
+            KeyError::RecoveryFailed => write!(f, "no public key recovers from the signature"),
+            KeyError::CurveMismatch => write!(f, "keys are on different curves"),
+            KeyError::SmallOrderPoint => write!(f, "public key has small order"),
+            KeyError::NotOnCurve => write!(f, "point is not on secp256k1"),
+            KeyError::Infinity => write!(f, "the point at infinity is not a public key"),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+#[derive(Clone, PartialEq)]
+pub struct PrivateKey {
+    secret: BigUint,
+}
+
+/// Shows the public key only, so logging a key never leaks the secret
+impl std::fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrivateKey")
+            .field("public_key", &self.public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PrivateKey {
+    /// Rejects zero and secrets >= n
+    pub fn from(secret: BigUint) -> Result<Self, KeyError> {
+        if secret.is_zero() || secret >= order() {
+            return Err(KeyError::InvalidSecret);
+        }
+        Ok(Self { secret })
+    }
+
+    /// A uniform secret in [1, n). `OsRng` for real keys, a seeded
+    /// `DeterministicRng` for reproducible ones
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self {
+            secret: random_scalar(rng),
+        }
+    }
+
+    /// Parses a big-endian secret, rejecting zero and values >= n
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KeyError> {
+        let secret = BigUint::from_bytes_be(bytes);
+        if bytes.len() != 32 || secret.is_zero() || secret >= order() {
+            return Err(KeyError::InvalidSecret);
+        }
+        Ok(Self { secret })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        to_32_bytes(&BigInt::from(self.secret.clone()))
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, KeyError> {
+        Self::from_bytes(&hex::decode(s).map_err(|_| KeyError::InvalidSecret)?)
+    }
+
+    pub fn secret(&self) -> &BigUint {
+        &self.secret
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            point: backend::mul_generator(&self.secret),
+        }
+    }
+
+    /// Computes (secret + tweak) mod n, as used by BIP32 child derivation and taproot
+    pub fn add_tweak(&self, tweak: &BigUint) -> Result<Self, KeyError> {
+        let n = order();
+        if *tweak >= n {
+            return Err(KeyError::TweakOutOfRange);
+        }
+        let secret = (&self.secret + tweak) % &n;
+        if secret.is_zero() {
+            return Err(KeyError::IdentityResult);
+        }
+        Ok(Self { secret })
+    }
+
+    /// Computes (secret * tweak) mod n. Since n is prime the result is never zero
+    pub fn mul_tweak(&self, tweak: &BigUint) -> Result<Self, KeyError> {
+        let n = order();
+        if tweak.is_zero() || *tweak >= n {
+            return Err(KeyError::TweakOutOfRange);
+        }
+        Ok(Self {
+            secret: (&self.secret * tweak) % &n,
+        })
+    }
+
+    /// ECDSA over the message hash `z`, with the nonce derived
+    /// deterministically from the key and `z` as in RFC 6979. Returns the
+    /// low-s form, as Bitcoin's standardness rules require
+    pub fn sign(&self, z: &BigUint) -> Signature {
+        self.sign_recoverable(z).0
+    }
+
+    /// Like `sign`, also returning the recovery id `PublicKey::recover`
+    /// needs: bit 0 is the parity of R's y, bit 1 whether R's x overflowed n
+    pub fn sign_recoverable(&self, z: &BigUint) -> (Signature, u8) {
+        trace_span!("ecdsa.sign", z = %format_args!("{:x}", z));
+        let trace = self.sign_deterministic(z);
+        (trace.signature(), trace.recid)
+    }
+
+    /// Like `sign`, also returning every intermediate value of the
+    /// computation. For teaching only: the trace holds the nonce
+    pub fn sign_traced(&self, z: &BigUint) -> (Signature, SigningTrace) {
+        let trace = self.sign_deterministic(z);
+        (trace.signature(), trace)
+    }
+
+    fn sign_deterministic(&self, z: &BigUint) -> SigningTrace {
+        let n = order();
+        let z = z % &n;
+        let mut drbg = HmacDrbg::<Sha256>::new(&self.to_bytes(), &to_32_bytes(&BigInt::from(z.clone())), &[]);
+        loop {
+            let mut k_bytes = [0_u8; 32];
+            drbg.generate(&mut k_bytes, &[]).unwrap();
+            if let Some(trace) = self.sign_with_k(&z, &BigUint::from_bytes_be(&k_bytes)) {
+                return trace;
+            }
+        }
+    }
+
+    /// ECDSA with a caller-chosen nonce, for protocols that derive or tweak
+    /// it themselves. None if k is out of range or gives r or s of zero.
+    /// Reusing k for two messages reveals the key
+    pub fn sign_with_nonce(&self, z: &BigUint, k: &BigUint) -> Option<Signature> {
+        trace_span!("ecdsa.sign", z = %format_args!("{:x}", z));
+        self.sign_with_k(&(z % order()), k).map(|trace| trace.signature())
+    }
+
+    fn sign_with_k(&self, z: &BigUint, k: &BigUint) -> Option<SigningTrace> {
+        let n = order();
+        if k.is_zero() || *k >= n {
+            return None;
+        }
+        let point = backend::mul_generator(k);
+        let x = point.x().unwrap().num().to_biguint().unwrap();
+        let r = &x % &n;
+        if r.is_zero() {
+            return None;
+        }
+        let s = (inverse_mod_order(k) * (z + &r * &self.secret)) % &n;
+        if s.is_zero() {
+            return None;
+        }
+        let mut recid = point.y().unwrap().is_odd() as u8 | ((x >= n) as u8) << 1;
+        let signature = Signature {
+            r: r.clone(),
+            s: s.clone(),
+        };
+        // negating s negates R
+        if !signature.is_low_s() {
+            recid ^= 1;
+        }
+        let low_s = signature.normalize_s().s;
+        Some(SigningTrace {
+            z: z.clone(),
+            k: k.clone(),
+            r_point: point,
+            r,
+            s,
+            low_s,
+            recid,
+        })
+    }
+}
+
+/// Each intermediate value of one ECDSA signature, as data to render,
+/// assert on or inspect. It holds the nonce k, which together with the
+/// signature gives away the private key: keep traces of real keys nowhere
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigningTrace {
+    /// The message hash, reduced mod n
+    pub z: BigUint,
+    /// The nonce, from RFC 6979 unless chosen by the caller
+    pub k: BigUint,
+    /// R = k G
+    pub r_point: Point,
+    /// x(R) mod n
+    pub r: BigUint,
+    /// k^-1 (z + r d) mod n, as computed
+    pub s: BigUint,
+    /// s or n - s, whichever is in the lower half
+    pub low_s: BigUint,
+    /// The recovery id of the final signature
+    pub recid: u8,
+}
+
+impl SigningTrace {
+    pub fn signature(&self) -> Signature {
+        Signature {
+            r: self.r.clone(),
+            s: self.low_s.clone(),
+        }
+    }
+
+    /// Whether s was in the upper half and replaced by n - s
+    pub fn s_negated(&self) -> bool {
+        self.s != self.low_s
+    }
+}
+
+impl Display for SigningTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (x, y) = (self.r_point.x().unwrap().num(), self.r_point.y().unwrap().num());
+        writeln!(f, "z = {:064x}", self.z)?;
+        writeln!(f, "k = {:064x}", self.k)?;
+        writeln!(f, "R = k G = ({:064x}, {:064x})", x, y)?;
+        writeln!(f, "r = x(R) mod n = {:064x}", self.r)?;
+        writeln!(f, "s = k^-1 (z + r d) mod n = {:064x}", self.s)?;
+        if self.s_negated() {
+            writeln!(f, "s > n / 2, so s = n - s = {:064x}", self.low_s)?;
+        }
+        write!(f, "recovery id = {}", self.recid)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicKey {
+    point: Point,
+}
+
+impl PublicKey {
+    /// Rejects points of other curves and the point at infinity
+    pub fn from(point: Point) -> Result<Self, KeyError> {
+        if !point.is_on(context().curve()) {
+            return Err(KeyError::NotOnCurve);
+        }
+        if point.is_infinity() {
+            return Err(KeyError::Infinity);
+        }
+        Ok(Self { point })
+    }
+
+    pub fn point(&self) -> &Point {
+        &self.point
+    }
+
+    /// SEC1 serialization: 0x04 || x || y, or 0x02/0x03 || x when compressed
+    pub fn sec(&self, compressed: bool) -> Vec<u8> {
+        let x = self.point.x().unwrap();
+        let y = self.point.y().unwrap();
+        let mut res = Vec::with_capacity(65);
+        if compressed {
+            res.push(if y.is_odd() { 0x03 } else { 0x02 });
+            res.extend_from_slice(&to_32_bytes(&x.num()));
+        } else {
+            res.push(0x04);
+            res.extend_from_slice(&to_32_bytes(&x.num()));
+            res.extend_from_slice(&to_32_bytes(&y.num()));
+        }
+        res
+    }
+
+    /// Compressed SEC1, as wallets and explorers show keys
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.sec(true))
+    }
+
+    /// Either SEC1 form
+    pub fn from_hex(s: &str) -> Result<Self, KeyError> {
+        Self::parse(&hex::decode(s).map_err(|_| KeyError::InvalidEncoding)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, KeyError> {
+        trace_span!("sec1.parse", len = bytes.len());
+        let prime = prime();
+        let coordinate = |b: &[u8]| {
+            let n = BigInt::from_bytes_be(num_bigint::Sign::Plus, b);
+            FieldElement::from(n, prime.clone()).map_err(|_| KeyError::InvalidEncoding)
+        };
+        let curve = context().curve();
+        match (bytes.first(), bytes.len()) {
+            (Some(0x04), 65) => {
+                let x = coordinate(&bytes[1..33])?;
+                let y = coordinate(&bytes[33..])?;
+                let point = Point::on_curve(Some(x), Some(y), curve).map_err(|_| KeyError::InvalidEncoding)?;
+                Ok(Self { point })
+            }
+            (Some(prefix @ (0x02 | 0x03)), 33) => {
+                let x = coordinate(&bytes[1..])?;
+                let alpha = x.clone().pow(BigInt::from(3_u8)) + curve.b().clone();
+                // p = 3 mod 4, so alpha^((p + 1) / 4) is a square root whenever one exists
+                let beta = alpha.clone().pow((prime.clone() + 1_u8) / 4_u8);
+                if beta.clone().pow(BigInt::from(2_u8)) != alpha {
+                    return Err(KeyError::InvalidEncoding);
+                }
+                let y = if beta.is_odd() == (*prefix == 0x03) {
+                    beta
+                } else {
+                    FieldElement::reduce(-beta.num(), prime.clone()).unwrap()
+                };
+                let point = Point::on_curve(Some(x), Some(y), curve).map_err(|_| KeyError::InvalidEncoding)?;
+                Ok(Self { point })
+            }
+            _ => Err(KeyError::InvalidEncoding),
+        }
+    }
+
+    /// Computes P + tweak * G, the public counterpart of `PrivateKey::add_tweak`
+    pub fn add_tweak(&self, tweak: &BigUint) -> Result<Self, KeyError> {
+        if *tweak >= order() {
+            return Err(KeyError::TweakOutOfRange);
+        }
+        let offset = if tweak.is_zero() {
+            infinity()
+        } else {
+            backend::mul_generator(tweak)
+        };
+        let point = self.point.clone() + offset;
+        if point.is_infinity() {
+            return Err(KeyError::IdentityResult);
+        }
+        Ok(Self { point })
+    }
+
+    /// Computes tweak * P, the public counterpart of `PrivateKey::mul_tweak`
+    pub fn mul_tweak(&self, tweak: &BigUint) -> Result<Self, KeyError> {
+        if tweak.is_zero() || *tweak >= order() {
+            return Err(KeyError::TweakOutOfRange);
+        }
+        Ok(Self {
+            point: backend::mul(tweak, &self.point),
+        })
+    }
+
+    /// The key that made `signature` over `z`, given the signer's recovery
+    /// id: P = r^-1 (s R - z G), with R rebuilt from r and the id
+    pub fn recover(z: &BigUint, signature: &Signature, recid: u8) -> Result<Self, KeyError> {
+        trace_span!("ecdsa.recover", z = %format_args!("{:x}", z), recid);
+        let n = order();
+        let (r, s) = (&signature.r, &signature.s);
+        if recid > 3 || r.is_zero() || *r >= n || s.is_zero() || *s >= n {
+            return Err(KeyError::RecoveryFailed);
+        }
+        let x = if recid & 2 != 0 { r + &n } else { r.clone() };
+        if BigInt::from(x.clone()) >= prime() {
+            return Err(KeyError::RecoveryFailed);
+        }
+        let mut sec = vec![0x02 | (recid & 1)];
+        sec.extend_from_slice(&to_32_bytes(&BigInt::from(x)));
+        let big_r = Self::parse(&sec).map_err(|_| KeyError::RecoveryFailed)?.point;
+        let r_inv = inverse_mod_order(r);
+        let u = (&n - (z % &n)) * &r_inv % &n;
+        let v = s * &r_inv % &n;
+        let point = backend::mul_add(&u, &v, &big_r);
+        if point.is_infinity() {
+            return Err(KeyError::RecoveryFailed);
+        }
+        Ok(Self { point })
+    }
+
+    /// Checks that u * G + v * P has x coordinate r, with u = z / s and
+    /// v = r / s. High-s signatures are accepted, as consensus does
+    pub fn verify(&self, z: &BigUint, signature: &Signature) -> bool {
+        trace_span!("ecdsa.verify", z = %format_args!("{:x}", z), public_key = %hex::encode(self.sec(true)));
+        let valid = self.is_valid(z, signature);
+        trace_event!(valid);
+        valid
+    }
+
+    fn is_valid(&self, z: &BigUint, signature: &Signature) -> bool {
+        let n = order();
+        let (r, s) = (&signature.r, &signature.s);
+        if r.is_zero() || *r >= n || s.is_zero() || *s >= n {
+            return false;
+        }
+        let s_inv = inverse_mod_order(s);
+        let u = (z % &n) * &s_inv % &n;
+        let v = r * &s_inv % &n;
+        let total = backend::mul_add(&u, &v, &self.point);
+        match total.x() {
+            Some(x) => x.num().to_biguint().unwrap() % &n == *r,
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Why a signature isn't the strict DER encoding BIP-66 requires
+pub enum SignatureError {
+    Truncated,
+    UnexpectedTag {
+        expected: u8,
+        found: u8,
+    },
+    /// The sequence's length byte against the bytes after it
+    LengthMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// Longer than the largest signature for the curve, 72 bytes for
+    /// secp256k1
+    TooLong(usize),
+    EmptyInteger,
+    NegativeInteger,
+    /// A leading zero byte where the next byte's high bit is clear
+    PaddedInteger,
+    TrailingBytes(usize),
+    InvalidHex,
+}
+
+impl Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureError::Truncated => write!(f, "DER signature is truncated"),
+            SignatureError::UnexpectedTag { expected, found } => {
+                write!(f, "invalid DER tag {:#04x}, expected {:#04x}", found, expected)
+            }
+            SignatureError::LengthMismatch { expected, found } => {
+                write!(
+                    f,
+                    "DER length {} does not match the {} bytes that follow",
+                    expected, found
+                )
+            }
+            SignatureError::TooLong(len) => write!(f, "DER signature of {} bytes is too long", len),
+            SignatureError::EmptyInteger => write!(f, "empty DER integer"),
+            SignatureError::NegativeInteger => write!(f, "negative DER integer"),
+            SignatureError::PaddedInteger => write!(f, "DER integer has needless zero padding"),
+            SignatureError::TrailingBytes(n) => write!(f, "{} trailing bytes in DER signature", n),
+            SignatureError::InvalidHex => write!(f, "invalid DER signature hex"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    r: BigUint,
+    s: BigUint,
+}
+
+impl Signature {
+    pub fn from(r: BigUint, s: BigUint) -> Self {
+        Self { r, s }
+    }
+
+    pub fn r(&self) -> &BigUint {
+        &self.r
+    }
+
+    pub fn s(&self) -> &BigUint {
+        &self.s
+    }
+
+    /// (r, s) and (r, n - s) both verify; BIP-62 picks s <= n / 2 to remove
+    /// that malleability
+    pub fn is_low_s(&self) -> bool {
+        self.s <= order() >> 1
+    }
+
+    pub fn normalize_s(self) -> Self {
+        if self.is_low_s() {
+            self
+        } else {
+            Self {
+                s: order() - &self.s,
+                r: self.r,
+            }
+        }
+    }
+
+    /// 0x30 len 0x02 len(r) r 0x02 len(s) s, with each integer as short as
+    /// possible but padded with 0x00 when its top bit is set
+    pub fn der(&self) -> Vec<u8> {
+        fn integer(n: &BigUint) -> Vec<u8> {
+            let mut bytes = n.to_bytes_be();
+            if bytes[0] & 0x80 != 0 {
+                bytes.insert(0, 0);
+            }
+            let mut res = vec![0x02, bytes.len() as u8];
+            res.extend_from_slice(&bytes);
+            res
+        }
+        let mut body = integer(&self.r);
+        body.extend_from_slice(&integer(&self.s));
+        let mut res = vec![0x30, body.len() as u8];
+        res.extend_from_slice(&body);
+        res
+    }
+
+    /// DER, as it appears in a scriptSig or witness without the sighash byte
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.der())
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, DecodeError<SignatureError>> {
+        Self::parse_der(&hex::decode(s).map_err(|_| SignatureError::InvalidHex)?)
+    }
+
+    /// Errors point at the offending byte, inside `r` or `s` if it's in
+    /// one of the integers
+    pub fn parse_der(bytes: &[u8]) -> Result<Self, DecodeError<SignatureError>> {
+        Self::parse_der_sized(bytes, 32)
+    }
+
+    /// For a curve with `scalar_len`-byte scalars, which bounds the length
+    pub(crate) fn parse_der_sized(bytes: &[u8], scalar_len: usize) -> Result<Self, DecodeError<SignatureError>> {
+        trace_span!("der.parse", len = bytes.len());
+        fn fail<T>(kind: SignatureError, offset: usize) -> Result<T, DecodeError<SignatureError>> {
+            Err(DecodeError::new(kind).at(offset))
+        }
+
+        /// The INTEGER at `start`, and where it ends
+        fn integer(bytes: &[u8], start: usize) -> Result<(BigUint, usize), DecodeError<SignatureError>> {
+            let &[tag, len, ..] = &bytes[start..] else {
+                return fail(SignatureError::Truncated, bytes.len());
+            };
+            if tag != 0x02 {
+                return fail(
+                    SignatureError::UnexpectedTag {
+                        expected: 0x02,
+                        found: tag,
+                    },
+                    start,
+                );
+            }
+            let end = start + 2 + len as usize;
+            let Some(int) = bytes.get(start + 2..end) else {
+                return fail(SignatureError::Truncated, bytes.len());
+            };
+            match int {
+                [] => fail(SignatureError::EmptyInteger, start + 1),
+                [first, ..] if first & 0x80 != 0 => fail(SignatureError::NegativeInteger, start + 2),
+                [0, second, ..] if second & 0x80 == 0 => fail(SignatureError::PaddedInteger, start + 2),
+                _ => Ok((BigUint::from_bytes_be(int), end)),
+            }
+        }
+
+        let &[tag, len, ..] = bytes else {
+            return fail(SignatureError::Truncated, bytes.len());
+        };
+        if tag != 0x30 {
+            return fail(
+                SignatureError::UnexpectedTag {
+                    expected: 0x30,
+                    found: tag,
+                },
+                0,
+            );
+        }
+        if len as usize != bytes.len() - 2 {
+            return fail(
+                SignatureError::LengthMismatch {
+                    expected: len as usize,
+                    found: bytes.len() - 2,
+                },
+                1,
+            );
+        }
+        // two INTEGERs, each with a padding byte at most
+        if bytes.len() > 2 + 2 * (2 + scalar_len + 1) {
+            return fail(SignatureError::TooLong(bytes.len()), 0);
+        }
+        let (r, end) = integer(bytes, 2).context("r")?;
+        let (s, end) = integer(bytes, end).context("s")?;
+        if end != bytes.len() {
+            return fail(SignatureError::TrailingBytes(bytes.len() - end), end);
+        }
+        Ok(Self { r, s })
+    }
+}
+
+// the secret for private keys, compressed SEC1 for public keys, DER for
+// signatures
+impl_hex_fmt!(PrivateKey, |key| key.to_bytes().to_vec());
+impl_hex_fmt!(PublicKey, |key| key.sec(true));
+impl_hex_fmt!(Signature, Signature::der);
+
+/// r and s in hex, `{:#}` on a line each
+impl Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return write!(f, "Signature\n  r = {:#x}\n  s = {:#x}", self.r, self.s);
+        }
+        write!(f, "Signature(r={:#x}, s={:#x})", self.r, self.s)
+    }
+}
+
+// compressed SEC1 for keys, DER for signatures
+#[cfg(feature = "serde")]
+impl_serde_bytes!(PublicKey, |key| key.sec(true), PublicKey::parse);
+#[cfg(feature = "serde")]
+impl_serde_bytes!(Signature, Signature::der, Signature::parse_der);
+
+#[cfg(test)]
+mod ecc_tests {
+    use super::*;
+
+    #[test]
+    fn test_limb_constants() {
+        let hex = |limbs: &[u64; 4]| format!("{:064x}", from_limbs(limbs));
+        assert_eq!(
+            hex(&P),
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f"
+        );
+        assert_eq!(
+            hex(&N),
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141"
+        );
+        assert_eq!(
+            hex(&GX),
+            "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+        assert_eq!(
+            hex(&GY),
+            "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"
+        );
+        assert_eq!(
+            (from_limbs(&A), from_limbs(&B)),
+            (BigUint::from(0_u8), BigUint::from(7_u8))
+        );
+        assert_eq!(BigInt::from(from_limbs(&P)), prime());
+        assert_eq!(from_limbs(&N), order());
+
+        // usable where only constants are, like the square root in `parse`
+        const { assert!(P[0] % 4 == 3) };
+        let (x, y) = (generator().x().unwrap().clone(), generator().y().unwrap().clone());
+        assert_eq!(
+            (x, y),
+            (
+                FieldElement::from_limbs(GX, P).unwrap(),
+                FieldElement::from_limbs(GY, P).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_generator_order() {
+        let n = BigInt::from(order());
+        assert!((n * generator()).is_infinity());
+    }
+
+    #[test]
+    fn test_public_key() {
+        let key = PrivateKey::from(BigUint::from(7_u8)).unwrap();
+        let prime = prime();
+        let x = BigInt::parse_bytes(b"5cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc", 16).unwrap();
+        let y = BigInt::parse_bytes(b"6aebca40ba255960a3178d6d861a54dba813d0b813fde7b5a5082628087264da", 16).unwrap();
+        let (a, b) = context().curve_params();
+        let (a, b) = (a.clone(), b.clone());
+        let expected = Point::from(
+            Some(FieldElement::from(x, prime.clone()).unwrap()),
+            Some(FieldElement::from(y, prime).unwrap()),
+            a,
+            b,
+        )
+        .unwrap();
+
+        assert_eq!(key.public_key(), PublicKey::from(expected).unwrap());
+    }
+
+    #[test]
+    fn test_add_tweak() {
+        let key = PrivateKey::from(BigUint::from(0xdeadbeef_u32)).unwrap();
+        let tweak = BigUint::from(0x1234_5678_u32);
+
+        let tweaked = key.add_tweak(&tweak).unwrap();
+        assert_eq!(tweaked.secret(), &BigUint::from(0xdeadbeef_u32 as u64 + 0x1234_5678));
+        assert_eq!(key.public_key().add_tweak(&tweak).unwrap(), tweaked.public_key());
+    }
+
+    #[test]
+    fn test_add_tweak_wraps() {
+        let key = PrivateKey::from(order() - 1_u8).unwrap();
+        let tweaked = key.add_tweak(&BigUint::from(2_u8)).unwrap();
+
+        assert_eq!(tweaked.secret(), &BigUint::from(1_u8));
+        assert_eq!(
+            key.public_key().add_tweak(&BigUint::from(2_u8)).unwrap().point(),
+            &generator()
+        );
+    }
+
+    #[test]
+    fn test_add_tweak_identity() {
+        let key = PrivateKey::from(BigUint::from(5_u8)).unwrap();
+        let tweak = order() - 5_u8;
+
+        assert_eq!(key.add_tweak(&tweak), Err(KeyError::IdentityResult));
+        assert_eq!(key.public_key().add_tweak(&tweak), Err(KeyError::IdentityResult));
+    }
+
+    #[test]
+    fn test_add_tweak_out_of_range() {
+        let key = PrivateKey::from(BigUint::from(5_u8)).unwrap();
+
+        assert_eq!(key.add_tweak(&order()), Err(KeyError::TweakOutOfRange));
+        assert_eq!(key.public_key().add_tweak(&order()), Err(KeyError::TweakOutOfRange));
+    }
+
+    #[test]
+    fn test_mul_tweak() {
+        let key = PrivateKey::from(order() - 3_u8).unwrap();
+        let tweak = BigUint::from(3_u8);
+
+        // (-3) * 3 = -9 mod n
+        let tweaked = key.mul_tweak(&tweak).unwrap();
+        assert_eq!(tweaked.secret(), &(order() - 9_u8));
+        assert_eq!(key.public_key().mul_tweak(&tweak).unwrap(), tweaked.public_key());
+    }
+
+    #[test]
+    fn test_mul_tweak_out_of_range() {
+        let key = PrivateKey::from(BigUint::from(5_u8)).unwrap();
+
+        assert_eq!(key.mul_tweak(&BigUint::zero()), Err(KeyError::TweakOutOfRange));
+        assert_eq!(key.public_key().mul_tweak(&order()), Err(KeyError::TweakOutOfRange));
+    }
+
+    #[test]
+    fn test_sec() {
+        let key = PrivateKey::from(BigUint::from(5000_u32)).unwrap().public_key();
+        let uncompressed = "04ffe558e388852f0120e46af2d1b370f85854a8eb0841811ece0e3e03d282d57c\
+                            315dc72890a4f10a1481c031b03b351b0dc79901ca18a00cf009dbdb157a1d10";
+        let compressed = "02ffe558e388852f0120e46af2d1b370f85854a8eb0841811ece0e3e03d282d57c";
+
+        assert_eq!(hex::encode(key.sec(false)), uncompressed);
+        assert_eq!(hex::encode(key.sec(true)), compressed);
+        assert_eq!(PublicKey::parse(&hex::decode(uncompressed).unwrap()), Ok(key.clone()));
+        assert_eq!(PublicKey::parse(&hex::decode(compressed).unwrap()), Ok(key));
+    }
+
+    #[test]
+    fn test_sec_odd_y() {
+        let key = PrivateKey::from(BigUint::from(2019_u32).pow(5)).unwrap().public_key();
+        let compressed = "02933ec2d2b111b92737ec12f1c5d20f3233a0ad21cd8b36d0bca7a0cfa5cb8701";
+        let sec = key.sec(true);
+
+        assert_eq!(hex::encode(&sec), compressed);
+        assert_eq!(PublicKey::parse(&sec), Ok(key));
+
+        let key = PrivateKey::from(BigUint::from(0xdeadbeef12345_u64))
+            .unwrap()
+            .public_key();
+        let sec = key.sec(true);
+        assert_eq!(sec[0], 0x03);
+        assert_eq!(PublicKey::parse(&sec), Ok(key));
+    }
+
+    #[test]
+    fn test_sec_invalid() {
+        let mut bytes = hex::decode("02ffe558e388852f0120e46af2d1b370f85854a8eb0841811ece0e3e03d282d57c").unwrap();
+        assert_eq!(PublicKey::parse(&bytes[..32]), Err(KeyError::InvalidEncoding));
+
+        bytes[0] = 0x05;
+        assert_eq!(PublicKey::parse(&bytes), Err(KeyError::InvalidEncoding));
+
+        // x = 5 has no corresponding y on secp256k1
+        let mut bytes = [0_u8; 33];
+        bytes[0] = 0x02;
+        bytes[32] = 5;
+        assert_eq!(PublicKey::parse(&bytes), Err(KeyError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let key = PrivateKey::from_bytes(&to_32_bytes(&BigInt::from(12345_u32))).unwrap();
+        assert_eq!(key.secret(), &BigUint::from(12345_u32));
+        assert_eq!(PrivateKey::from_bytes(&key.to_bytes()), Ok(key));
+
+        assert_eq!(PrivateKey::from_bytes(&[0_u8; 32]), Err(KeyError::InvalidSecret));
+        assert_eq!(
+            PrivateKey::from_bytes(&order().to_bytes_be()),
+            Err(KeyError::InvalidSecret)
+        );
+        assert_eq!(PrivateKey::from_bytes(&[1_u8; 31]), Err(KeyError::InvalidSecret));
+    }
+
+    #[test]
+    fn test_secret_out_of_range() {
+        assert_eq!(PrivateKey::from(BigUint::zero()), Err(KeyError::InvalidSecret));
+        assert_eq!(PrivateKey::from(order()), Err(KeyError::InvalidSecret));
+        assert_eq!(PrivateKey::from(order() + 1_u8), Err(KeyError::InvalidSecret));
+        assert_eq!(
+            PrivateKey::from(order() - 1_u8).map(|key| key.secret().clone()),
+            Ok(order() - 1_u8)
+        );
+    }
+
+    #[test]
+    fn test_debug_redacts_secret() {
+        let key = PrivateKey::from(BigUint::from(0x5ec2e7_u32)).unwrap();
+        let debug = format!("{:?}", key);
+
+        assert!(debug.starts_with("PrivateKey { public_key: PublicKey"));
+        assert!(debug.ends_with(", .. }"));
+        assert!(!debug.contains("secret"));
+        assert!(!debug.contains(&0x5ec2e7_u32.to_string()));
+    }
+
+    #[test]
+    fn test_public_key_invalid() {
+        assert_eq!(PublicKey::from(infinity()), Err(KeyError::Infinity));
+        // (47, 71) on y² = x³ + 7 over F_223
+        let element = |n: u8| FieldElement::from(BigInt::from(n), BigInt::from(223)).unwrap();
+        let toy = Point::from(Some(element(47)), Some(element(71)), element(0), element(7)).unwrap();
+        assert_eq!(PublicKey::from(toy), Err(KeyError::NotOnCurve));
+    }
+
+    #[test]
+    fn test_sign_rfc6979() {
+        // key 1 signing sha256("Satoshi Nakamoto"); the nonce is the
+        // RFC 6979 one checked in hmac_drbg_tests
+        let key = PrivateKey::from(BigUint::from(1_u8)).unwrap();
+        let z = BigUint::from_bytes_be(&crate::crypto::hash::sha256(b"Satoshi Nakamoto"));
+        let sig = key.sign(&z);
+
+        assert_eq!(
+            hex::encode(sig.der()),
+            "3045022100934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8\
+             02202442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5"
+        );
+        assert!(sig.is_low_s());
+        assert!(key.public_key().verify(&z, &sig));
+        assert_eq!(key.sign(&z), sig);
+    }
+
+    #[test]
+    fn test_recover() {
+        for secret in [1_u32, 7, 0xdeadbeef] {
+            let key = PrivateKey::from(BigUint::from(secret)).unwrap();
+            for message in [&b"abc"[..], b"Satoshi Nakamoto"] {
+                let z = BigUint::from_bytes_be(&crate::crypto::hash::sha256(message));
+                let (sig, recid) = key.sign_recoverable(&z);
+                assert_eq!(sig, key.sign(&z));
+                assert_eq!(PublicKey::recover(&z, &sig, recid), Ok(key.public_key()));
+                // the other parity gives some other key, or none
+                assert_ne!(PublicKey::recover(&z, &sig, recid ^ 1), Ok(key.public_key()));
+            }
+        }
+        let sig = Signature::from(BigUint::from(1_u8), BigUint::from(1_u8));
+        assert_eq!(
+            PublicKey::recover(&BigUint::zero(), &sig, 4),
+            Err(KeyError::RecoveryFailed)
+        );
+        // r + n is beyond the field
+        assert_eq!(
+            PublicKey::recover(&BigUint::zero(), &sig, 2),
+            Err(KeyError::RecoveryFailed)
+        );
+    }
+
+    #[test]
+    fn test_multi_scalar_mul() {
+        let points: Vec<Point> = [3_u32, 5, 7].iter().map(|&k| BigInt::from(k) * generator()).collect();
+        let scalars = [BigUint::from(11_u8), BigUint::zero(), order() - 1_u8];
+        // 11 * 3 + 0 * 5 - 7
+        assert_eq!(multi_scalar_mul(&scalars, &points), BigInt::from(26_u8) * generator());
+        assert!(multi_scalar_mul(&[], &[]).is_infinity());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_multi_scalar_mul() {
+        let points: Vec<Point> = (1..=20_u32).map(|k| BigInt::from(k * k) * generator()).collect();
+        let scalars: Vec<BigUint> = (0..20_u8)
+            .map(|i| BigUint::from_bytes_be(&[i.wrapping_mul(37) | 1; 32]) % order())
+            .collect();
+        assert_eq!(
+            par_multi_scalar_mul(&scalars, &points),
+            multi_scalar_mul(&scalars, &points)
+        );
+        assert_eq!(
+            par_multi_scalar_mul(&scalars[..1], &points[..1]),
+            multi_scalar_mul(&scalars[..1], &points[..1])
+        );
+        assert!(par_multi_scalar_mul(&[BigUint::zero()], &points[..1]).is_infinity());
+        assert!(par_multi_scalar_mul(&[], &[]).is_infinity());
+    }
+
+    #[test]
+    fn test_sign_traced() {
+        let key = PrivateKey::from(BigUint::from(0xc0ffee_u32)).unwrap();
+        let n = order();
+        let mut negated = false;
+        for z in 1..=8_u32 {
+            let z = BigUint::from(z);
+            let (signature, trace) = key.sign_traced(&z);
+            assert_eq!(signature, key.sign(&z));
+            assert_eq!(trace.signature(), signature);
+            assert_eq!(trace.r_point, BigInt::from(trace.k.clone()) * generator());
+            assert_eq!(trace.r, trace.r_point.x().unwrap().num().to_biguint().unwrap() % &n);
+            assert_eq!(&trace.s * &trace.k % &n, (&z + &trace.r * key.secret()) % &n);
+            assert_eq!(
+                trace.low_s,
+                if trace.s_negated() {
+                    &n - &trace.s
+                } else {
+                    trace.s.clone()
+                }
+            );
+            assert_eq!(
+                PublicKey::recover(&z, &signature, trace.recid).unwrap(),
+                key.public_key()
+            );
+            negated |= trace.s_negated();
+
+            let rendered = trace.to_string();
+            assert!(rendered.starts_with(&format!("z = {:064x}\nk = ", z)));
+            assert!(rendered.contains(&format!("r = x(R) mod n = {:064x}", trace.r)));
+            assert_eq!(rendered.contains("so s = n - s"), trace.s_negated());
+        }
+        assert!(negated, "no high s among the samples");
+    }
+
+    #[test]
+    fn test_verify() {
+        let key = PrivateKey::from(BigUint::from(0xc0ffee_u32)).unwrap();
+        let z = BigUint::from(0xabcdef_u32);
+        let sig = key.sign(&z);
+        let public_key = key.public_key();
+
+        assert!(public_key.verify(&z, &sig));
+        assert!(!public_key.verify(&(z.clone() + 1_u8), &sig));
+        assert!(!PrivateKey::from(BigUint::from(2_u8))
+            .unwrap()
+            .public_key()
+            .verify(&z, &sig));
+
+        // the high-s twin is still valid
+        let high = Signature::from(sig.r().clone(), order() - sig.s());
+        assert!(!high.is_low_s());
+        assert!(public_key.verify(&z, &high));
+        assert_eq!(high.normalize_s(), sig);
+
+        assert!(!public_key.verify(&z, &Signature::from(BigUint::zero(), sig.s().clone())));
+        assert!(!public_key.verify(&z, &Signature::from(sig.r().clone(), order())));
+    }
+
+    #[test]
+    fn test_der() {
+        let sig = Signature::from(BigUint::from(0x80_u8), BigUint::from(0x7f_u8));
+        assert_eq!(hex::encode(sig.der()), "30070202008002017f");
+        assert_eq!(Signature::parse_der(&sig.der()), Ok(sig));
+
+        for (bad, kind, offset) in [
+            ("", SignatureError::Truncated, 0),
+            ("3006020200800201", SignatureError::Truncated, 8),
+            ("300702020001020101", SignatureError::PaddedInteger, 4),
+            ("30060201010201ff", SignatureError::NegativeInteger, 7),
+            ("3006020101020001", SignatureError::EmptyInteger, 6),
+            ("30080201010201010000", SignatureError::TrailingBytes(2), 8),
+            (
+                "3007020101020101",
+                SignatureError::LengthMismatch { expected: 7, found: 6 },
+                1,
+            ),
+            (
+                "3106020101020101",
+                SignatureError::UnexpectedTag {
+                    expected: 0x30,
+                    found: 0x31,
+                },
+                0,
+            ),
+            (
+                "3006020101030101",
+                SignatureError::UnexpectedTag {
+                    expected: 0x02,
+                    found: 0x03,
+                },
+                5,
+            ),
+        ] {
+            let err = Signature::parse_der(&hex::decode(bad).unwrap()).unwrap_err();
+            assert_eq!((err.kind(), err.offset()), (&kind, Some(offset)), "{}", bad);
+        }
+        let err = Signature::parse_der(&hex::decode("30060201010201ff").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "negative DER integer at byte 7 in s");
+    }
+
+    #[test]
+    fn test_hex() {
+        let key = PrivateKey::from(BigUint::from(1_u8)).unwrap();
+        let g = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        assert_eq!(format!("{:x}", key), format!("{:064x}", 1));
+        assert_eq!(format!("{:x}", key.public_key()), g);
+        assert_eq!(format!("{:#X}", key.public_key()), format!("0x{}", g.to_uppercase()));
+        assert_eq!(PrivateKey::from_hex(&key.to_hex()), Ok(key.clone()));
+        assert_eq!(PublicKey::from_hex(g), Ok(key.public_key()));
+        assert_eq!(
+            PublicKey::from_hex(&hex::encode(key.public_key().sec(false))),
+            Ok(key.public_key())
+        );
+
+        let sig = Signature::from(BigUint::from(0x80_u8), BigUint::from(0x7f_u8));
+        assert_eq!(format!("{:x}", sig), "30070202008002017f");
+        assert_eq!(format!("{}", sig), "Signature(r=0x80, s=0x7f)");
+        assert_eq!(format!("{:#}", sig), "Signature\n  r = 0x80\n  s = 0x7f");
+        assert_eq!(Signature::from_hex(&sig.to_hex()), Ok(sig));
+
+        assert_eq!(PrivateKey::from_hex("zz"), Err(KeyError::InvalidSecret));
+        assert_eq!(PrivateKey::from_hex(&"00".repeat(32)), Err(KeyError::InvalidSecret));
+        assert_eq!(PublicKey::from_hex(&g[1..]), Err(KeyError::InvalidEncoding));
+        assert_eq!(
+            Signature::from_hex("30").unwrap_err().into_kind(),
+            SignatureError::Truncated
+        );
+        assert_eq!(
+            Signature::from_hex("3").unwrap_err().into_kind(),
+            SignatureError::InvalidHex
+        );
+    }
+}
@@ -0,0 +1,688 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::address::{self, Network};
+use crate::bip32::{Bip32Error, ExtendedPublicKey, HARDENED};
+use crate::crypto::hash::hash160;
+use crate::crypto::schnorr::lift_x;
+use crate::curves::secp256k1::ecc::PublicKey;
+use crate::multisig::{Multisig, MultisigError};
+use crate::script::Script;
+use crate::taproot::tweak_public_key;
+
+/// Characters a descriptor may contain, ordered so the checksum catches
+/// the most common typos (BIP-380)
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DescriptorError {
+    InvalidCharacter(char),
+    InvalidChecksum {
+        expected: String,
+        got: String,
+    },
+    /// A malformed expression, saying what was wrong
+    Syntax(String),
+    UnknownFunction(String),
+    InvalidKey(String),
+    /// Extended public keys can't derive hardened steps or wildcards
+    HardenedDerivation(String),
+    /// Segwit only takes compressed keys, taproot x-only ones as well, and
+    /// multisig only compressed ones
+    KeyNotAllowed(String),
+    Bip32(Bip32Error),
+    Multisig(MultisigError),
+    /// Bare multisig has no address format
+    NoAddress,
+}
+
+impl Display for DescriptorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DescriptorError::InvalidCharacter(c) => write!(f, "invalid descriptor character {:?}", c),
+            DescriptorError::InvalidChecksum { expected, got } => {
+                write!(f, "descriptor checksum {} does not match {}", got, expected)
+            }
+            DescriptorError::Syntax(e) => write!(f, "invalid descriptor: {}", e),
+            DescriptorError::UnknownFunction(name) => write!(f, "unknown descriptor function {}", name),
+            DescriptorError::InvalidKey(key) => write!(f, "invalid key {}", key),
+            DescriptorError::HardenedDerivation(key) => write!(f, "cannot derive hardened steps of {}", key),
+            DescriptorError::KeyNotAllowed(key) => write!(f, "key {} is not allowed here", key),
+            DescriptorError::Bip32(e) => write!(f, "{}", e),
+            DescriptorError::Multisig(e) => write!(f, "{}", e),
+            DescriptorError::NoAddress => write!(f, "descriptor has no address"),
+        }
+    }
+}
+
+impl std::error::Error for DescriptorError {}
+
+impl From<Bip32Error> for DescriptorError {
+    fn from(e: Bip32Error) -> Self {
+        DescriptorError::Bip32(e)
+    }
+}
+
+impl From<MultisigError> for DescriptorError {
+    fn from(e: MultisigError) -> Self {
+        DescriptorError::Multisig(e)
+    }
+}
+
+fn polymod(c: u64, value: u64) -> u64 {
+    const GENERATOR: [u64; 5] = [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd];
+    let top = c >> 35;
+    let mut c = ((c & 0x7ffffffff) << 5) ^ value;
+    for (i, g) in GENERATOR.iter().enumerate() {
+        if (top >> i) & 1 == 1 {
+            c ^= g;
+        }
+    }
+    c
+}
+
+/// The 8-character BIP-380 checksum of a descriptor without its `#`
+pub fn checksum(descriptor: &str) -> Result<String, DescriptorError> {
+    let mut c = 1;
+    let mut class = 0;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let position = INPUT_CHARSET.find(ch).ok_or(DescriptorError::InvalidCharacter(ch))? as u64;
+        // the low 5 bits go in directly, the group of 32 three at a time
+        c = polymod(c, position & 31);
+        class = class * 3 + (position >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8)
+        .map(|i| CHECKSUM_CHARSET[((c >> (5 * (7 - i))) & 31) as usize] as char)
+        .collect())
+}
+
+/// Where the keys of a script expression may come from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Context {
+    /// pkh, sh and bare scripts: uncompressed keys allowed
+    Legacy,
+    Segwit,
+    Taproot,
+}
+
+/// The `[fingerprint/path]` prefix recording which master key and path a
+/// key was derived from, for signers to find it
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyOrigin {
+    pub fingerprint: [u8; 4],
+    pub path: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DescriptorKey {
+    /// A SEC-encoded key, or an x-only one inside tr()
+    Single { origin: Option<KeyOrigin>, key: Vec<u8> },
+    /// An xpub followed by unhardened steps, and `/*` when ranged
+    Extended {
+        origin: Option<KeyOrigin>,
+        xpub: Box<ExtendedPublicKey>,
+        path: Vec<u32>,
+        wildcard: bool,
+    },
+}
+
+fn parse_path<'a>(steps: impl Iterator<Item = &'a str>) -> Result<Vec<u32>, DescriptorError> {
+    steps
+        .map(|step| {
+            let (number, hardened) = match step.strip_suffix(['\'', 'h']) {
+                Some(number) => (number, true),
+                None => (step, false),
+            };
+            match number.parse::<u32>() {
+                Ok(n) if n < HARDENED && number.bytes().all(|b| b.is_ascii_digit()) => {
+                    Ok(if hardened { n + HARDENED } else { n })
+                }
+                _ => Err(DescriptorError::Syntax(format!("invalid path step {}", step))),
+            }
+        })
+        .collect()
+}
+
+fn write_path(f: &mut std::fmt::Formatter<'_>, path: &[u32]) -> std::fmt::Result {
+    for &step in path {
+        if step >= HARDENED {
+            write!(f, "/{}'", step - HARDENED)?;
+        } else {
+            write!(f, "/{}", step)?;
+        }
+    }
+    Ok(())
+}
+
+impl DescriptorKey {
+    fn parse(s: &str, context: Context) -> Result<Self, DescriptorError> {
+        let (origin, key) = match s.strip_prefix('[') {
+            Some(rest) => {
+                let (origin, key) = rest
+                    .split_once(']')
+                    .ok_or_else(|| DescriptorError::Syntax(format!("unclosed origin in {}", s)))?;
+                let mut steps = origin.split('/');
+                let fingerprint = steps
+                    .next()
+                    .and_then(|fp| hex::decode(fp).ok())
+                    .and_then(|fp| fp.try_into().ok());
+                let fingerprint =
+                    fingerprint.ok_or_else(|| DescriptorError::Syntax(format!("invalid fingerprint in {}", s)))?;
+                (
+                    Some(KeyOrigin {
+                        fingerprint,
+                        path: parse_path(steps)?,
+                    }),
+                    key,
+                )
+            }
+            None => (None, s),
+        };
+
+        if let Ok(bytes) = hex::decode(key) {
+            let valid = match bytes.len() {
+                32 if context == Context::Taproot => lift_x(&bytes.clone().try_into().unwrap()).is_ok(),
+                33 => matches!(bytes[0], 0x02 | 0x03) && PublicKey::parse(&bytes).is_ok(),
+                65 if context == Context::Legacy => PublicKey::parse(&bytes).is_ok(),
+                32 | 65 => return Err(DescriptorError::KeyNotAllowed(key.to_string())),
+                _ => false,
+            };
+            if !valid {
+                return Err(DescriptorError::InvalidKey(key.to_string()));
+            }
+            return Ok(DescriptorKey::Single { origin, key: bytes });
+        }
+
+        let mut steps = key.split('/');
+        let xpub = ExtendedPublicKey::parse(steps.next().unwrap_or_default())
+            .map_err(|_| DescriptorError::InvalidKey(key.to_string()))?;
+        let mut steps: Vec<&str> = steps.collect();
+        let wildcard = match steps.last() {
+            Some(&"*") => true,
+            Some(&"*'") | Some(&"*h") => return Err(DescriptorError::HardenedDerivation(key.to_string())),
+            _ => false,
+        };
+        if wildcard {
+            steps.pop();
+        }
+        let path = parse_path(steps.into_iter())?;
+        if path.iter().any(|&step| step >= HARDENED) {
+            return Err(DescriptorError::HardenedDerivation(key.to_string()));
+        }
+        Ok(DescriptorKey::Extended {
+            origin,
+            xpub: Box::new(xpub),
+            path,
+            wildcard,
+        })
+    }
+
+    pub fn origin(&self) -> Option<&KeyOrigin> {
+        match self {
+            DescriptorKey::Single { origin, .. } | DescriptorKey::Extended { origin, .. } => origin.as_ref(),
+        }
+    }
+
+    pub fn is_ranged(&self) -> bool {
+        matches!(self, DescriptorKey::Extended { wildcard: true, .. })
+    }
+
+    /// The key's encoding at `index`, which only ranged keys use.
+    /// Extended keys derive compressed keys
+    pub fn derive(&self, index: u32) -> Result<Vec<u8>, DescriptorError> {
+        match self {
+            DescriptorKey::Single { key, .. } => Ok(key.clone()),
+            DescriptorKey::Extended {
+                xpub, path, wildcard, ..
+            } => {
+                let mut child = xpub.derive_path(path)?;
+                if *wildcard {
+                    child = child.derive_child(index)?;
+                }
+                Ok(child.public_key.sec(true))
+            }
+        }
+    }
+
+    fn derive_public_key(&self, index: u32) -> Result<PublicKey, DescriptorError> {
+        let key = self.derive(index)?;
+        PublicKey::parse(&key).map_err(|_| DescriptorError::InvalidKey(hex::encode(&key)))
+    }
+
+    fn derive_xonly(&self, index: u32) -> Result<[u8; 32], DescriptorError> {
+        let key = self.derive(index)?;
+        let x = if key.len() == 33 { &key[1..] } else { &key[..] };
+        Ok(x.try_into().unwrap())
+    }
+}
+
+impl Display for DescriptorKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(origin) = self.origin() {
+            write!(f, "[{}", hex::encode(origin.fingerprint))?;
+            write_path(f, &origin.path)?;
+            write!(f, "]")?;
+        }
+        match self {
+            DescriptorKey::Single { key, .. } => write!(f, "{}", hex::encode(key)),
+            DescriptorKey::Extended {
+                xpub, path, wildcard, ..
+            } => {
+                write!(f, "{}", xpub)?;
+                write_path(f, path)?;
+                if *wildcard {
+                    write!(f, "/*")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The keys of multi() or sortedmulti()
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiKeys {
+    pub required: usize,
+    pub keys: Vec<DescriptorKey>,
+    /// sortedmulti: BIP-67 order of the derived keys, at every index
+    pub sorted: bool,
+}
+
+impl MultiKeys {
+    fn parse(name: &str, args: &str, context: Context) -> Result<Self, DescriptorError> {
+        let mut args = args.split(',');
+        let required = args
+            .next()
+            .and_then(|k| k.parse().ok())
+            .ok_or_else(|| DescriptorError::Syntax(format!("invalid threshold in {}", name)))?;
+        let keys = args
+            .map(|key| {
+                let key = DescriptorKey::parse(key, context)?;
+                match &key {
+                    DescriptorKey::Single { key: bytes, .. } if bytes.len() != 33 => {
+                        Err(DescriptorError::KeyNotAllowed(hex::encode(bytes)))
+                    }
+                    _ => Ok(key),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            required,
+            keys,
+            sorted: name == "sortedmulti",
+        })
+    }
+
+    pub fn multisig(&self, index: u32) -> Result<Multisig, DescriptorError> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| key.derive_public_key(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let multisig = if self.sorted {
+            Multisig::sorted(self.required, keys)
+        } else {
+            Multisig::new(self.required, keys)
+        };
+        Ok(multisig?)
+    }
+}
+
+impl Display for MultiKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}({}",
+            if self.sorted { "sortedmulti" } else { "multi" },
+            self.required
+        )?;
+        for key in &self.keys {
+            write!(f, ",{}", key)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// An output script descriptor (BIP-380): which scripts a wallet watches
+/// and how to derive them
+#[derive(Debug, Clone, PartialEq)]
+pub enum Descriptor {
+    Pkh(DescriptorKey),
+    Wpkh(DescriptorKey),
+    ShWpkh(DescriptorKey),
+    /// Key-path-only taproot
+    Tr(DescriptorKey),
+    Multi(MultiKeys),
+    ShMulti(MultiKeys),
+    WshMulti(MultiKeys),
+    ShWshMulti(MultiKeys),
+}
+
+/// Splits `name(args)` into its parts
+fn call(s: &str) -> Result<(&str, &str), DescriptorError> {
+    let (name, rest) = s
+        .split_once('(')
+        .ok_or_else(|| DescriptorError::Syntax(format!("expected a function in {}", s)))?;
+    let args = rest
+        .strip_suffix(')')
+        .ok_or_else(|| DescriptorError::Syntax(format!("unbalanced parentheses in {}", s)))?;
+    Ok((name, args))
+}
+
+impl Descriptor {
+    /// Parses a descriptor, checking the checksum if there is one. Keys
+    /// may write hardened steps with ' or h
+    pub fn parse(s: &str) -> Result<Self, DescriptorError> {
+        let descriptor = match s.split_once('#') {
+            Some((descriptor, got)) => {
+                let expected = checksum(descriptor)?;
+                if got != expected {
+                    return Err(DescriptorError::InvalidChecksum {
+                        expected,
+                        got: got.to_string(),
+                    });
+                }
+                descriptor
+            }
+            None => {
+                checksum(s)?;
+                s
+            }
+        };
+
+        let (name, args) = call(descriptor)?;
+        let res = match name {
+            "pkh" => Descriptor::Pkh(DescriptorKey::parse(args, Context::Legacy)?),
+            "wpkh" => Descriptor::Wpkh(DescriptorKey::parse(args, Context::Segwit)?),
+            "tr" if args.contains(',') => {
+                return Err(DescriptorError::Syntax(
+                    "tr() script trees are not supported".to_string(),
+                ));
+            }
+            "tr" => Descriptor::Tr(DescriptorKey::parse(args, Context::Taproot)?),
+            "multi" | "sortedmulti" => Descriptor::Multi(MultiKeys::parse(name, args, Context::Legacy)?),
+            "sh" => match call(args)? {
+                ("wpkh", key) => Descriptor::ShWpkh(DescriptorKey::parse(key, Context::Segwit)?),
+                (inner @ ("multi" | "sortedmulti"), keys) => {
+                    Descriptor::ShMulti(MultiKeys::parse(inner, keys, Context::Legacy)?)
+                }
+                ("wsh", inner) => match call(inner)? {
+                    (inner @ ("multi" | "sortedmulti"), keys) => {
+                        Descriptor::ShWshMulti(MultiKeys::parse(inner, keys, Context::Segwit)?)
+                    }
+                    (inner, _) => return Err(DescriptorError::UnknownFunction(inner.to_string())),
+                },
+                (inner, _) => return Err(DescriptorError::UnknownFunction(inner.to_string())),
+            },
+            "wsh" => match call(args)? {
+                (inner @ ("multi" | "sortedmulti"), keys) => {
+                    Descriptor::WshMulti(MultiKeys::parse(inner, keys, Context::Segwit)?)
+                }
+                (inner, _) => return Err(DescriptorError::UnknownFunction(inner.to_string())),
+            },
+            _ => return Err(DescriptorError::UnknownFunction(name.to_string())),
+        };
+        // catches thresholds and scripts too large for P2SH up front
+        res.script_pubkey(0)?;
+        Ok(res)
+    }
+
+    /// Whether the descriptor has a wildcard, so each index gives a
+    /// different script
+    pub fn is_ranged(&self) -> bool {
+        match self {
+            Descriptor::Pkh(key) | Descriptor::Wpkh(key) | Descriptor::ShWpkh(key) | Descriptor::Tr(key) => {
+                key.is_ranged()
+            }
+            Descriptor::Multi(multi)
+            | Descriptor::ShMulti(multi)
+            | Descriptor::WshMulti(multi)
+            | Descriptor::ShWshMulti(multi) => multi.keys.iter().any(DescriptorKey::is_ranged),
+        }
+    }
+
+    /// The output script at `index`; non-ranged descriptors ignore it
+    pub fn script_pubkey(&self, index: u32) -> Result<Script, DescriptorError> {
+        Ok(match self {
+            Descriptor::Pkh(key) => Script::p2pkh(&hash160(&key.derive(index)?)),
+            Descriptor::Wpkh(key) => Script::p2wpkh(&hash160(&key.derive(index)?)),
+            Descriptor::ShWpkh(key) => {
+                let redeem_script = Script::p2wpkh(&hash160(&key.derive(index)?));
+                Script::p2sh(&hash160(redeem_script.as_bytes()))
+            }
+            Descriptor::Tr(key) => {
+                let internal = key.derive_xonly(index)?;
+                let (output_key, _) = tweak_public_key(&internal, None)
+                    .map_err(|_| DescriptorError::InvalidKey(hex::encode(internal)))?;
+                Script::p2tr(&output_key)
+            }
+            Descriptor::Multi(multi) => multi.multisig(index)?.script(),
+            Descriptor::ShMulti(multi) => multi.multisig(index)?.p2sh_script_pubkey()?,
+            Descriptor::WshMulti(multi) => multi.multisig(index)?.p2wsh_script_pubkey(),
+            Descriptor::ShWshMulti(multi) => {
+                let redeem_script = multi.multisig(index)?.p2wsh_script_pubkey();
+                Script::p2sh(&hash160(redeem_script.as_bytes()))
+            }
+        })
+    }
+
+    /// The output scripts for a range of indexes, for chain scanning
+    pub fn script_pubkeys(&self, indexes: Range<u32>) -> Result<Vec<Script>, DescriptorError> {
+        indexes.map(|index| self.script_pubkey(index)).collect()
+    }
+
+    pub fn address(&self, index: u32, network: Network) -> Result<String, DescriptorError> {
+        address::from_script_pubkey(&self.script_pubkey(index)?, network).ok_or(DescriptorError::NoAddress)
+    }
+}
+
+impl Display for Descriptor {
+    /// The normalized descriptor, with ' for hardened steps and its checksum
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let body = match self {
+            Descriptor::Pkh(key) => format!("pkh({})", key),
+            Descriptor::Wpkh(key) => format!("wpkh({})", key),
+            Descriptor::ShWpkh(key) => format!("sh(wpkh({}))", key),
+            Descriptor::Tr(key) => format!("tr({})", key),
+            Descriptor::Multi(multi) => multi.to_string(),
+            Descriptor::ShMulti(multi) => format!("sh({})", multi),
+            Descriptor::WshMulti(multi) => format!("wsh({})", multi),
+            Descriptor::ShWshMulti(multi) => format!("sh(wsh({}))", multi),
+        };
+        write!(f, "{}#{}", body, checksum(&body).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+#[cfg(test)]
+mod descriptor_tests {
+    use super::*;
+
+    // BIP-32 test vector 1 master key
+    const XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+    const KEY1: &str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+    const KEY2: &str = "03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb";
+
+    #[test]
+    fn test_checksum() {
+        // from BIP-380
+        assert_eq!(checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert_eq!(
+            checksum("raw(deadbeef)\u{e9}"),
+            Err(DescriptorError::InvalidCharacter('\u{e9}'))
+        );
+    }
+
+    #[test]
+    fn test_derivation() {
+        // vectors from rust-miniscript
+        let cases: [(String, &str, &[&str]); 5] = [
+            (
+                format!("pkh([d34db33f/44'/0'/0']{}/1/*)", XPUB),
+                "65q0dqxx",
+                &[
+                    "1NwEtFZ6Td7cpKaJtYoeryS6avP2TUkSMh",
+                    "1LWYcEi1bUMfiCoXJwTXF4q8iRGnqhb8jr",
+                ],
+            ),
+            (
+                format!("wpkh({}/0/*)", XPUB),
+                "wvk84d79",
+                &[
+                    "bc1qp5wfcq48h6d63wyy9qz0awtpfqwwv4sma86mhz",
+                    "bc1qc6xkeyekth5xe7lsey3qgxm55nypxe7dfpfawu",
+                ],
+            ),
+            (
+                format!("tr({}/0/*)", XPUB),
+                "svstklww",
+                &[
+                    "bc1pgfhzycz8pckwsdspf04hn6rps5tpapgrchtzx5f3k8wlvqhmxu6qrdt83a",
+                    "bc1pv5srxjcvr0fnghvaa40ec906y66vyjhrppkcr8r8yh2cnxc83cnqwkmntv",
+                ],
+            ),
+            (
+                format!("wsh(sortedmulti(2,{}/0/*,{},{}))", XPUB, KEY2, KEY1),
+                "xnw4gy2p",
+                &[
+                    "bc1q4pvcd0s3wtu7uqm3mlylvsglp9qaztwdqgxjug2av84rxu59xcaqgy26wn",
+                    "bc1qh3xr49j07d2sasru30tldj0rw9gzemmwnyu46fkj0dlvl4e396sqjz3v2l",
+                ],
+            ),
+            (
+                format!("sh(wpkh({}))", KEY1),
+                "la26f59y",
+                &["3FWHHE3RVgyv5vYmMrcoRdA25uugWvQbso"],
+            ),
+        ];
+        for (body, sum, addresses) in cases {
+            let descriptor = Descriptor::parse(&format!("{}#{}", body, sum)).unwrap();
+            assert_eq!(descriptor.to_string(), format!("{}#{}", body, sum));
+            assert_eq!(descriptor.is_ranged(), addresses.len() > 1);
+            for (address, index) in addresses.iter().zip([0, 5]) {
+                assert_eq!(descriptor.address(index, Network::Mainnet).unwrap(), *address);
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_keys() {
+        let uncompressed = "04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd5b8dec5235a0fa8722476\
+                            c7709c02559e3aa73aa03918ba2d492eea75abea235";
+        let cases = [
+            (
+                format!("sh(multi(1,{},{}))#c40vlyqd", KEY1, KEY2),
+                "3D8Xu8hVcrhT3wfzHbysQYSg81e5dg1Ppp",
+            ),
+            (
+                format!("sh(wsh(multi(2,{},{})))#8r4n666h", KEY1, KEY2),
+                "34EJkGisfhUsuXfkJCDrFdUhvgtAYkrRDB",
+            ),
+            (
+                format!("pkh({})#9907vvwz", uncompressed),
+                "1HZwkjkeaoZfTSaJxDw6aKkxp45agDiEzN",
+            ),
+            (
+                format!("tr({})#k6ze7ncu", &KEY1[2..]),
+                "bc1pet7ep3czdu9k4wvdlz2fp5p8x2yp7t6ttyqg2c6cmh0lgeuu9lasmp9hsg",
+            ),
+        ];
+        for (s, address) in cases {
+            let descriptor = Descriptor::parse(&s).unwrap();
+            assert!(!descriptor.is_ranged());
+            assert_eq!(descriptor.to_string(), s);
+            assert_eq!(descriptor.address(0, Network::Mainnet).unwrap(), address);
+        }
+
+        let bare = Descriptor::parse(&format!("multi(1,{},{})#ypt8793m", KEY1, KEY2)).unwrap();
+        assert_eq!(
+            bare.script_pubkey(0).unwrap().to_hex(),
+            format!("5121{}21{}52ae", KEY1, KEY2)
+        );
+        assert_eq!(bare.address(0, Network::Mainnet), Err(DescriptorError::NoAddress));
+
+        assert_eq!(
+            Descriptor::parse(&format!("wpkh({})", uncompressed)),
+            Err(DescriptorError::KeyNotAllowed(uncompressed.to_string()))
+        );
+        assert!(matches!(
+            Descriptor::parse(&format!("pkh({})", &KEY1[2..])),
+            Err(DescriptorError::KeyNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalization() {
+        // h is read as ' but the checksum covers the original spelling
+        let with_h = format!("wpkh([d34db33f/84h/0h/0h]{}/0/*)", XPUB);
+        let descriptor = Descriptor::parse(&with_h).unwrap();
+        assert_eq!(
+            descriptor.to_string(),
+            format!("wpkh([d34db33f/84'/0'/0']{}/0/*)#zpuvqnlw", XPUB)
+        );
+        assert_eq!(
+            descriptor.script_pubkeys(0..2).unwrap(),
+            Descriptor::parse(&format!("wpkh({}/0/*)", XPUB))
+                .unwrap()
+                .script_pubkeys(0..2)
+                .unwrap()
+        );
+        let Descriptor::Wpkh(key) = descriptor else {
+            panic!("expected wpkh")
+        };
+        let origin = KeyOrigin {
+            fingerprint: [0xd3, 0x4d, 0xb3, 0x3f],
+            path: vec![84 + HARDENED, HARDENED, HARDENED],
+        };
+        assert_eq!(key.origin(), Some(&origin));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let wpkh = format!("wpkh({}/0/*)", XPUB);
+        assert_eq!(
+            Descriptor::parse(&format!("{}#wvk84d7q", wpkh)),
+            Err(DescriptorError::InvalidChecksum {
+                expected: "wvk84d79".to_string(),
+                got: "wvk84d7q".to_string()
+            })
+        );
+        for (s, error) in [
+            (format!("wpkh({}/0'/*)", XPUB), "hardened"),
+            (format!("wpkh({}/0/*')", XPUB), "hardened"),
+            (format!("wpkh({}/0/x)", XPUB), "syntax"),
+            (format!("wpkh([d34db3/0]{})", KEY1), "syntax"),
+            (format!("wpkh({}", KEY1), "syntax"),
+            (format!("tr({},pk({}))", KEY1, KEY2), "syntax"),
+            (format!("combo({})", KEY1), "unknown"),
+            (format!("wsh(wpkh({}))", KEY1), "unknown"),
+            (format!("wpkh({}00)", KEY1), "key"),
+            (format!("sh(multi(3,{},{}))", KEY1, KEY2), "multisig"),
+        ] {
+            let res = Descriptor::parse(&s);
+            let matched = match error {
+                "hardened" => matches!(res, Err(DescriptorError::HardenedDerivation(_))),
+                "syntax" => matches!(res, Err(DescriptorError::Syntax(_))),
+                "unknown" => matches!(res, Err(DescriptorError::UnknownFunction(_))),
+                "key" => matches!(res, Err(DescriptorError::InvalidKey(_))),
+                _ => matches!(res, Err(DescriptorError::Multisig(_))),
+            };
+            assert!(matched, "{}: {:?}", s, res);
+        }
+    }
+}
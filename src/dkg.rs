@@ -0,0 +1,379 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::Zero;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::rng::random_scalar;
+use crate::crypto::transcript::Transcript;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::ecc::{generator, order, PublicKey};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DkgError {
+    /// Need 1 <= threshold <= participants
+    InvalidParameters { threshold: u32, participants: u32 },
+    /// Indices run from 1 to the number of participants
+    InvalidIndex(u32),
+    /// The dealer's proof of knowledge of its constant term fails, which
+    /// everyone sees, so all disqualify it
+    InvalidProof(u32),
+    /// The dealer's commitments don't match the threshold
+    InvalidCommitments(u32),
+    /// The share the dealer sent doesn't match its commitments; the
+    /// recipient broadcasts a `Complaint`
+    InvalidShare(u32),
+    /// A qualified dealer whose share this participant never accepted
+    MissingShare(u32),
+    /// Fewer qualified dealers than the threshold
+    TooFewQualified(usize),
+}
+
+impl Display for DkgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DkgError::InvalidParameters {
+                threshold,
+                participants,
+            } => {
+                write!(f, "invalid threshold {} of {} participants", threshold, participants)
+            }
+            DkgError::InvalidIndex(i) => write!(f, "invalid participant index {}", i),
+            DkgError::InvalidProof(i) => write!(f, "participant {} has an invalid proof of knowledge", i),
+            DkgError::InvalidCommitments(i) => write!(f, "participant {} committed to the wrong degree", i),
+            DkgError::InvalidShare(i) => write!(f, "share from participant {} does not match its commitments", i),
+            DkgError::MissingShare(i) => write!(f, "no valid share from participant {}", i),
+            DkgError::TooFewQualified(n) => write!(f, "only {} qualified participants", n),
+        }
+    }
+}
+
+impl std::error::Error for DkgError {}
+
+/// What a dealer broadcasts in the first round: Feldman commitments to its
+/// polynomial's coefficients, and a Schnorr proof that it knows the
+/// constant term, so nobody can pick theirs to cancel the others' out
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dealing {
+    pub dealer: u32,
+    pub commitments: Vec<PublicKey>,
+    pub proof: (PublicKey, BigUint),
+}
+
+/// Broadcast by `accuser` when `dealer`'s share fails verification. The
+/// dealer answers by publishing the share (`Participant::reveal`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complaint {
+    pub accuser: u32,
+    pub dealer: u32,
+}
+
+/// A participant's result: its share of the joint secret, which is never
+/// assembled, and the public data all participants agree on
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyShare {
+    pub index: u32,
+    pub threshold: u32,
+    pub secret_share: BigUint,
+    pub group_key: PublicKey,
+    /// secret_share * G for every participant, to check partial signatures
+    pub verification_shares: BTreeMap<u32, PublicKey>,
+}
+
+/// One party's side of a Pedersen DKG: each party deals a Feldman VSS of a
+/// random secret and the joint secret is the sum over the qualified
+/// dealers. Any `threshold` shares can interpolate it
+#[derive(Debug, Clone)]
+pub struct Participant {
+    index: u32,
+    threshold: u32,
+    participants: u32,
+    coefficients: Vec<BigUint>,
+    /// Verified shares and commitments by dealer
+    accepted: BTreeMap<u32, (BigUint, Vec<PublicKey>)>,
+}
+
+impl Participant {
+    /// Picks a random polynomial of degree threshold - 1
+    pub fn new(index: u32, threshold: u32, participants: u32) -> Result<Self, DkgError> {
+        Self::new_with_rng(index, threshold, participants, &mut OsRng)
+    }
+
+    pub fn new_with_rng<R: RngCore + CryptoRng>(
+        index: u32,
+        threshold: u32,
+        participants: u32,
+        rng: &mut R,
+    ) -> Result<Self, DkgError> {
+        if threshold == 0 || threshold > participants {
+            return Err(DkgError::InvalidParameters {
+                threshold,
+                participants,
+            });
+        }
+        if index == 0 || index > participants {
+            return Err(DkgError::InvalidIndex(index));
+        }
+        let coefficients = (0..threshold).map(|_| random_scalar(rng)).collect();
+        Ok(Self {
+            index,
+            threshold,
+            participants,
+            coefficients,
+            accepted: BTreeMap::new(),
+        })
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The round one broadcast
+    pub fn dealing(&self) -> Dealing {
+        self.dealing_with_rng(&mut OsRng)
+    }
+
+    pub fn dealing_with_rng<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Dealing {
+        let commitments: Vec<PublicKey> = self.coefficients.iter().map(scalar_point).collect();
+        let k = random_scalar(rng);
+        let r = scalar_point(&k);
+        let c = proof_challenge(self.index, &commitments[0], &r);
+        let s = (k + c * &self.coefficients[0]) % order();
+        Dealing {
+            dealer: self.index,
+            commitments,
+            proof: (r, s),
+        }
+    }
+
+    /// f(recipient), sent to the recipient over a private channel
+    pub fn share_for(&self, recipient: u32) -> BigUint {
+        evaluate(&self.coefficients, recipient)
+    }
+
+    /// The answer to a complaint: the disputed share, made public
+    pub fn reveal(&self, complaint: &Complaint) -> BigUint {
+        self.share_for(complaint.accuser)
+    }
+
+    /// Checks a dealing and the share it sent us. Our own share counts too.
+    /// An `InvalidShare` error means we should broadcast a complaint
+    pub fn receive(&mut self, dealing: &Dealing, share: &BigUint) -> Result<(), DkgError> {
+        verify_dealing(dealing, self.threshold, self.participants)?;
+        if !verify_share(dealing, self.index, share) {
+            return Err(DkgError::InvalidShare(dealing.dealer));
+        }
+        self.accepted
+            .insert(dealing.dealer, (share.clone(), dealing.commitments.clone()));
+        Ok(())
+    }
+
+    /// Sums the shares from the qualified dealers, those whose dealings
+    /// verified and who answered every complaint with a valid share
+    pub fn finish(&self, qualified: &[u32]) -> Result<KeyShare, DkgError> {
+        if qualified.len() < self.threshold as usize {
+            return Err(DkgError::TooFewQualified(qualified.len()));
+        }
+        let n = order();
+        let mut secret_share = BigUint::zero();
+        let mut commitments = Vec::with_capacity(qualified.len());
+        for dealer in qualified {
+            let (share, dealer_commitments) = self.accepted.get(dealer).ok_or(DkgError::MissingShare(*dealer))?;
+            secret_share = (secret_share + share) % &n;
+            commitments.push(dealer_commitments);
+        }
+        // the joint polynomial's commitments are the sums of the dealers'
+        let joint: Vec<PublicKey> = (0..self.threshold as usize)
+            .map(|k| sum_points(commitments.iter().map(|c| c[k].point().clone())))
+            .collect::<Option<_>>()
+            .ok_or(DkgError::TooFewQualified(qualified.len()))?;
+        let verification_shares = (1..=self.participants)
+            .map(|j| Some((j, evaluate_commitments(&joint, j)?)))
+            .collect::<Option<_>>()
+            .ok_or(DkgError::TooFewQualified(qualified.len()))?;
+        Ok(KeyShare {
+            index: self.index,
+            threshold: self.threshold,
+            secret_share,
+            group_key: joint[0].clone(),
+            verification_shares,
+        })
+    }
+}
+
+/// Checks a dealing's shape and proof of knowledge, which every
+/// participant can do from the broadcast alone
+pub fn verify_dealing(dealing: &Dealing, threshold: u32, participants: u32) -> Result<(), DkgError> {
+    if dealing.dealer == 0 || dealing.dealer > participants {
+        return Err(DkgError::InvalidIndex(dealing.dealer));
+    }
+    if dealing.commitments.len() != threshold as usize {
+        return Err(DkgError::InvalidCommitments(dealing.dealer));
+    }
+    let (r, s) = &dealing.proof;
+    let c = proof_challenge(dealing.dealer, &dealing.commitments[0], r);
+    let expected = r.point().clone() + BigInt::from(c) * dealing.commitments[0].point().clone();
+    if *s >= order() || s.is_zero() || BigInt::from(s.clone()) * generator() != expected {
+        return Err(DkgError::InvalidProof(dealing.dealer));
+    }
+    Ok(())
+}
+
+/// Feldman's check: share * G = sum of recipient^k * C_k
+pub fn verify_share(dealing: &Dealing, recipient: u32, share: &BigUint) -> bool {
+    *share < order()
+        && !share.is_zero()
+        && evaluate_commitments(&dealing.commitments, recipient).is_some_and(|expected| expected == scalar_point(share))
+}
+
+/// Lagrange coefficient at zero for `index` among `signers`, so that the
+/// secret is the sum of coefficient * share over any threshold-sized set
+pub fn lagrange_coefficient(index: u32, signers: &[u32]) -> BigUint {
+    let n = order();
+    let mut numerator = BigUint::from(1_u8);
+    let mut denominator = BigUint::from(1_u8);
+    for &j in signers.iter().filter(|&&j| j != index) {
+        numerator = numerator * j % &n;
+        // j - index, mod n
+        denominator = denominator * ((BigUint::from(j) + &n - index) % &n) % &n;
+    }
+    numerator * denominator.modpow(&(&n - 2_u8), &n) % &n
+}
+
+fn scalar_point(k: &BigUint) -> PublicKey {
+    PublicKey::from(BigInt::from(k.clone()) * generator()).expect("scalar is in [1, n)")
+}
+
+fn proof_challenge(dealer: u32, commitment: &PublicKey, r: &PublicKey) -> BigUint {
+    let mut transcript = Transcript::new("moneda/dkg/proof");
+    transcript.append("dealer", &dealer.to_be_bytes());
+    transcript.append_point("C", commitment);
+    transcript.append_point("R", r);
+    transcript.challenge_scalar("c")
+}
+
+/// f(x) by Horner's rule
+fn evaluate(coefficients: &[BigUint], x: u32) -> BigUint {
+    let n = order();
+    coefficients
+        .iter()
+        .rev()
+        .fold(BigUint::zero(), |acc, a| (acc * x + a) % &n)
+}
+
+/// f(x) * G from the commitments to f's coefficients
+fn evaluate_commitments(commitments: &[PublicKey], x: u32) -> Option<PublicKey> {
+    let n = order();
+    let mut power = BigUint::from(1_u8);
+    let terms = commitments.iter().map(|c| {
+        let term = BigInt::from(power.clone()) * c.point().clone();
+        power = &power * x % &n;
+        term
+    });
+    sum_points(terms)
+}
+
+/// None if the sum is the point at infinity
+fn sum_points(points: impl IntoIterator<Item = Point>) -> Option<PublicKey> {
+    let sum = points.into_iter().reduce(|acc, p| acc + p)?;
+    PublicKey::from(sum).ok()
+}
+
+#[cfg(test)]
+mod dkg_tests {
+    use super::*;
+
+    /// Runs every dealing past every participant, returning the complaints
+    fn deal(parties: &mut [Participant], dealings: &[Dealing], shares: impl Fn(u32, u32) -> BigUint) -> Vec<Complaint> {
+        let mut complaints = Vec::new();
+        for party in parties.iter_mut() {
+            for dealing in dealings {
+                match party.receive(dealing, &shares(dealing.dealer, party.index())) {
+                    Err(DkgError::InvalidShare(dealer)) => complaints.push(Complaint {
+                        accuser: party.index(),
+                        dealer,
+                    }),
+                    res => res.unwrap(),
+                }
+            }
+        }
+        complaints
+    }
+
+    #[test]
+    fn test_dkg() {
+        let mut parties: Vec<Participant> = (1..=3).map(|i| Participant::new(i, 2, 3).unwrap()).collect();
+        let dealers = parties.clone();
+        let dealings: Vec<Dealing> = dealers.iter().map(Participant::dealing).collect();
+        let complaints = deal(&mut parties, &dealings, |dealer, recipient| {
+            dealers[dealer as usize - 1].share_for(recipient)
+        });
+        assert!(complaints.is_empty());
+
+        let shares: Vec<KeyShare> = parties.iter().map(|p| p.finish(&[1, 2, 3]).unwrap()).collect();
+        let group_key = &shares[0].group_key;
+        assert!(shares
+            .iter()
+            .all(|s| s.group_key == *group_key && s.verification_shares == shares[0].verification_shares));
+        for share in &shares {
+            assert_eq!(
+                share.verification_shares[&share.index],
+                scalar_point(&share.secret_share)
+            );
+        }
+        // any two shares interpolate the same secret, behind the group key
+        let n = order();
+        for signers in [[1, 2], [1, 3], [2, 3]] {
+            let secret = signers.iter().fold(BigUint::zero(), |acc, &i| {
+                (acc + lagrange_coefficient(i, &signers) * &shares[i as usize - 1].secret_share) % &n
+            });
+            assert_eq!(scalar_point(&secret), *group_key);
+        }
+    }
+
+    #[test]
+    fn test_complaints() {
+        let mut parties: Vec<Participant> = (1..=3).map(|i| Participant::new(i, 2, 3).unwrap()).collect();
+        let dealers = parties.clone();
+        let dealings: Vec<Dealing> = dealers.iter().map(Participant::dealing).collect();
+        // dealer 3 sends party 1 a bad share
+        let complaints = deal(&mut parties, &dealings, |dealer, recipient| {
+            let share = dealers[dealer as usize - 1].share_for(recipient);
+            if (dealer, recipient) == (3, 1) {
+                share + 1_u8
+            } else {
+                share
+            }
+        });
+        assert_eq!(complaints, [Complaint { accuser: 1, dealer: 3 }]);
+
+        // an honest dealer's revealed share checks out and the accuser takes it
+        let revealed = dealers[2].reveal(&complaints[0]);
+        assert!(verify_share(&dealings[2], 1, &revealed));
+        parties[0].receive(&dealings[2], &revealed).unwrap();
+        let key = parties[0].finish(&[1, 2, 3]).unwrap().group_key;
+        assert_eq!(parties[1].finish(&[1, 2, 3]).unwrap().group_key, key);
+
+        // a dealer that can't answer is disqualified by everyone
+        assert!(!verify_share(&dealings[2], 1, &(revealed + 1_u8)));
+        let without = parties[1].finish(&[1, 2]).unwrap().group_key;
+        assert_ne!(without, key);
+        assert_eq!(parties[2].finish(&[1, 2]).unwrap().group_key, without);
+        assert_eq!(parties[1].finish(&[1]), Err(DkgError::TooFewQualified(1)));
+
+        let mut forged = dealings[1].clone();
+        forged.commitments[0] = dealings[0].commitments[0].clone();
+        assert_eq!(verify_dealing(&forged, 2, 3), Err(DkgError::InvalidProof(2)));
+        assert_eq!(verify_dealing(&dealings[1], 3, 3), Err(DkgError::InvalidCommitments(2)));
+        assert_eq!(Participant::new(4, 2, 3).unwrap_err(), DkgError::InvalidIndex(4));
+        assert_eq!(
+            Participant::new(1, 4, 3).unwrap_err(),
+            DkgError::InvalidParameters {
+                threshold: 4,
+                participants: 3
+            }
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use num_bigint::{BigInt, BigUint};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::rng::random_scalar;
+use crate::crypto::transcript::Transcript;
+use crate::curves::secp256k1::ecc::{order, PrivateKey, PublicKey};
+
+/// The claim log_G(A) = log_H(B): the same secret x gives A = x G and
+/// B = x H. With H a recipient's key and B a shared secret, it shows an
+/// ECDH result is correct without revealing x
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub g: PublicKey,
+    pub h: PublicKey,
+    pub a: PublicKey,
+    pub b: PublicKey,
+}
+
+impl Statement {
+    pub fn new(x: &PrivateKey, g: &PublicKey, h: &PublicKey) -> Self {
+        Self {
+            g: g.clone(),
+            h: h.clone(),
+            a: mul(x.secret(), g),
+            b: mul(x.secret(), h),
+        }
+    }
+
+    fn append_to(&self, transcript: &mut Transcript) {
+        for (label, point) in [("G", &self.g), ("H", &self.h), ("A", &self.a), ("B", &self.b)] {
+            transcript.append_point(label, point);
+        }
+    }
+}
+
+/// The prover's first message: the nonce k times both bases
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commitment {
+    pub r1: PublicKey,
+    pub r2: PublicKey,
+}
+
+/// The prover's state between committing and responding. Answering two
+/// different challenges with it would reveal x, so responding consumes it
+pub struct Prover {
+    x: BigUint,
+    k: BigUint,
+}
+
+impl Prover {
+    pub fn commit(x: &PrivateKey, statement: &Statement) -> (Self, Commitment) {
+        Self::commit_with_rng(x, statement, &mut OsRng)
+    }
+
+    pub fn commit_with_rng<R: RngCore + CryptoRng>(
+        x: &PrivateKey,
+        statement: &Statement,
+        rng: &mut R,
+    ) -> (Self, Commitment) {
+        let k = random_scalar(rng);
+        let commitment = Commitment {
+            r1: mul(&k, &statement.g),
+            r2: mul(&k, &statement.h),
+        };
+        (
+            Self {
+                x: x.secret().clone(),
+                k,
+            },
+            commitment,
+        )
+    }
+
+    /// s = k + c x
+    pub fn respond(self, challenge: &BigUint) -> BigUint {
+        (self.k + challenge * self.x) % order()
+    }
+}
+
+/// The verifier's side of the interactive protocol: with a fresh random
+/// challenge after the commitment, s G = R1 + c A and s H = R2 + c B
+pub fn random_challenge() -> BigUint {
+    random_scalar(&mut OsRng)
+}
+
+pub fn verify_response(statement: &Statement, commitment: &Commitment, challenge: &BigUint, s: &BigUint) -> bool {
+    let n = order();
+    if *s >= n || *challenge >= n {
+        return false;
+    }
+    let check = |base: &PublicKey, r: &PublicKey, p: &PublicKey| {
+        let lhs = BigInt::from(s.clone()) * base.point().clone();
+        let rhs = r.point().clone() + BigInt::from(challenge.clone()) * p.point().clone();
+        lhs == rhs
+    };
+    check(&statement.g, &commitment.r1, &statement.a) && check(&statement.h, &commitment.r2, &statement.b)
+}
+
+/// Non-interactive proof: the challenge is a hash of the statement and
+/// commitment, so it can be sent as (c, s) and R1, R2 recomputed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proof {
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+impl Proof {
+    /// `transcript` may already hold context the proof should be bound to
+    pub fn prove(x: &PrivateKey, statement: &Statement, mut transcript: Transcript) -> Self {
+        let (prover, commitment) = Prover::commit(x, statement);
+        let c = challenge(&mut transcript, statement, &commitment);
+        let s = prover.respond(&c);
+        Self { c, s }
+    }
+
+    pub fn verify(&self, statement: &Statement, mut transcript: Transcript) -> bool {
+        let n = order();
+        if self.s >= n || self.c >= n {
+            return false;
+        }
+        // R = s base - c P
+        let neg_c = BigInt::from((&n - &self.c) % &n);
+        let recompute = |base: &PublicKey, p: &PublicKey| {
+            PublicKey::from(BigInt::from(self.s.clone()) * base.point().clone() + neg_c.clone() * p.point().clone())
+                .ok()
+        };
+        let (Some(r1), Some(r2)) = (
+            recompute(&statement.g, &statement.a),
+            recompute(&statement.h, &statement.b),
+        ) else {
+            return false;
+        };
+        challenge(&mut transcript, statement, &Commitment { r1, r2 }) == self.c
+    }
+}
+
+fn challenge(transcript: &mut Transcript, statement: &Statement, commitment: &Commitment) -> BigUint {
+    statement.append_to(transcript);
+    transcript.append_point("R1", &commitment.r1);
+    transcript.append_point("R2", &commitment.r2);
+    transcript.challenge_scalar("c")
+}
+
+fn mul(k: &BigUint, point: &PublicKey) -> PublicKey {
+    PublicKey::from(BigInt::from(k.clone()) * point.point().clone()).expect("scalar is in [1, n)")
+}
+
+#[cfg(test)]
+mod dleq_tests {
+    use super::*;
+    use crate::crypto::ecdh::shared_point;
+
+    fn setup() -> (PrivateKey, Statement) {
+        let x = PrivateKey::from_bytes(&[0x11; 32]).unwrap();
+        let recipient = PrivateKey::from_bytes(&[0x22; 32]).unwrap().public_key();
+        let g = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+        let statement = Statement::new(&x, &g, &recipient);
+        // B is the ECDH secret between x and the recipient
+        assert_eq!(statement.b, shared_point(&x, &recipient));
+        (x, statement)
+    }
+
+    #[test]
+    fn test_interactive() {
+        let (x, statement) = setup();
+        let (prover, commitment) = Prover::commit(&x, &statement);
+        let c = random_challenge();
+        let s = prover.respond(&c);
+        assert!(verify_response(&statement, &commitment, &c, &s));
+        assert!(!verify_response(
+            &statement,
+            &commitment,
+            &c,
+            &((s.clone() + 1_u8) % order())
+        ));
+
+        // a different secret for B fails
+        let other = PrivateKey::from_bytes(&[0x33; 32]).unwrap();
+        let mut false_statement = statement.clone();
+        false_statement.b = Statement::new(&other, &statement.g, &statement.h).b;
+        let (prover, commitment) = Prover::commit(&x, &false_statement);
+        let s = prover.respond(&c);
+        assert!(!verify_response(&false_statement, &commitment, &c, &s));
+    }
+
+    #[test]
+    fn test_fiat_shamir() {
+        let (x, statement) = setup();
+        let proof = Proof::prove(&x, &statement, Transcript::new("moneda/dleq"));
+        assert!(proof.verify(&statement, Transcript::new("moneda/dleq")));
+
+        // bound to the transcript's context
+        let mut bound = Transcript::new("moneda/dleq");
+        bound.append("session", b"1");
+        assert!(!proof.verify(&statement, bound));
+
+        let mut swapped = statement.clone();
+        std::mem::swap(&mut swapped.a, &mut swapped.b);
+        assert!(!proof.verify(&swapped, Transcript::new("moneda/dleq")));
+        let tampered = Proof {
+            c: proof.c.clone(),
+            s: (proof.s.clone() + 1_u8) % order(),
+        };
+        assert!(!tampered.verify(&statement, Transcript::new("moneda/dleq")));
+    }
+}
@@ -1,4 +0,0 @@
-use crate::ec_point::Point;
-use crate::field_element::FieldElement;
-use num_bigint::BigUint;
-
@@ -0,0 +1,324 @@
+use hmac::digest::core_api::BlockSizeUser;
+use num_bigint::{BigInt, BigUint};
+use num_traits::Zero;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::crypto::hmac_drbg::HmacDrbg;
+use crate::crypto::prime::random_below;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::ecc::{KeyError, Signature, SignatureError};
+use crate::encoding::decode::DecodeError;
+use crate::named_curve::{pad, NamedCurve};
+
+// ECDSA over any curve of the registry, for P-256 and the other curves
+// protocols outside Bitcoin use. Unlike `ecc`, signatures keep the s the
+// arithmetic gives: low-s is a Bitcoin rule, and other verifiers accept
+// either half. Hashes go in as bytes, since turning one into a scalar
+// depends on the curve's order (bits2int in RFC 6979)
+
+/// An ECDSA private key on a registered curve
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigningKey {
+    curve: &'static NamedCurve,
+    secret: BigUint,
+}
+
+impl SigningKey {
+    /// Rejects secrets of zero and those not below n
+    pub fn new(curve: &'static NamedCurve, secret: BigUint) -> Result<Self, KeyError> {
+        if secret.is_zero() || secret >= *curve.order() {
+            return Err(KeyError::InvalidSecret);
+        }
+        Ok(Self { curve, secret })
+    }
+
+    pub fn generate<R: RngCore + CryptoRng>(curve: &'static NamedCurve, rng: &mut R) -> Self {
+        let secret = random_below(&(curve.order() - 1_u8), rng) + 1_u8;
+        Self { curve, secret }
+    }
+
+    /// A big-endian secret of exactly `scalar_len` bytes
+    pub fn from_bytes(curve: &'static NamedCurve, bytes: &[u8]) -> Result<Self, KeyError> {
+        if bytes.len() != curve.scalar_len() {
+            return Err(KeyError::InvalidSecret);
+        }
+        Self::new(curve, BigUint::from_bytes_be(bytes))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        pad(&self.secret, self.curve.scalar_len())
+    }
+
+    pub fn curve(&self) -> &'static NamedCurve {
+        self.curve
+    }
+
+    pub fn secret(&self) -> &BigUint {
+        &self.secret
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey {
+            curve: self.curve,
+            point: self.curve.mul_generator(&self.secret),
+        }
+    }
+
+    /// ECDSA over the message digest `hash`, with the nonce from RFC 6979.
+    /// The HMAC in there uses the hash the digest came from, told apart by
+    /// length: SHA-384 and SHA-512 digests use theirs, all others SHA-256
+    pub fn sign(&self, hash: &[u8]) -> Signature {
+        match hash.len() {
+            48 => self.sign_rfc6979::<Sha384>(hash),
+            64 => self.sign_rfc6979::<Sha512>(hash),
+            _ => self.sign_rfc6979::<Sha256>(hash),
+        }
+    }
+
+    fn sign_rfc6979<D: Digest + BlockSizeUser + Clone>(&self, hash: &[u8]) -> Signature {
+        let n = self.curve.order();
+        let z = bits2int(hash, n);
+        let len = self.curve.scalar_len();
+        let mut drbg = HmacDrbg::<D>::new(&self.to_bytes(), &pad(&(&z % n), len), &[]);
+        loop {
+            let mut k_bytes = vec![0_u8; len];
+            drbg.generate(&mut k_bytes, &[]).unwrap();
+            if let Some(signature) = self.sign_with_k(&z, &bits2int(&k_bytes, n)) {
+                return signature;
+            }
+        }
+    }
+
+    /// ECDSA with a caller-chosen nonce. None if k is out of range or
+    /// gives r or s of zero. Reusing k for two messages reveals the key
+    pub fn sign_with_nonce(&self, hash: &[u8], k: &BigUint) -> Option<Signature> {
+        self.sign_with_k(&bits2int(hash, self.curve.order()), k)
+    }
+
+    fn sign_with_k(&self, z: &BigUint, k: &BigUint) -> Option<Signature> {
+        let n = self.curve.order();
+        if k.is_zero() || k >= n {
+            return None;
+        }
+        let point = self.curve.mul_generator(k);
+        let r = point.x().unwrap().num().to_biguint().unwrap() % n;
+        if r.is_zero() {
+            return None;
+        }
+        let k_inv = k.modpow(&(n - 2_u8), n);
+        let s = k_inv * (z + &r * &self.secret) % n;
+        if s.is_zero() {
+            return None;
+        }
+        Some(Signature::from(r, s))
+    }
+}
+
+/// An ECDSA public key on a registered curve
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyingKey {
+    curve: &'static NamedCurve,
+    point: Point,
+}
+
+impl VerifyingKey {
+    /// Rejects points on other curves and the point at infinity
+    pub fn from_point(curve: &'static NamedCurve, point: Point) -> Result<Self, KeyError> {
+        if !point.is_on(curve.curve()) || point.is_infinity() {
+            return Err(KeyError::InvalidEncoding);
+        }
+        Ok(Self { curve, point })
+    }
+
+    /// Either SEC1 form
+    pub fn parse(curve: &'static NamedCurve, bytes: &[u8]) -> Result<Self, KeyError> {
+        Ok(Self {
+            curve,
+            point: curve.parse_sec(bytes)?,
+        })
+    }
+
+    pub fn sec(&self, compressed: bool) -> Vec<u8> {
+        self.curve.sec(&self.point, compressed)
+    }
+
+    pub fn curve(&self) -> &'static NamedCurve {
+        self.curve
+    }
+
+    pub fn point(&self) -> &Point {
+        &self.point
+    }
+
+    /// Checks that u G + v P has x coordinate r mod n, with u = z / s and
+    /// v = r / s
+    pub fn verify(&self, hash: &[u8], signature: &Signature) -> bool {
+        let n = self.curve.order();
+        let (r, s) = (signature.r(), signature.s());
+        if r.is_zero() || r >= n || s.is_zero() || s >= n {
+            return false;
+        }
+        let s_inv = s.modpow(&(n - 2_u8), n);
+        let u = bits2int(hash, n) * &s_inv % n;
+        let v = r * &s_inv % n;
+        let total = self.curve.mul_generator(&u) + BigInt::from(v) * self.point.clone();
+        match total.x() {
+            Some(x) => x.num().to_biguint().unwrap() % n == *r,
+            None => false,
+        }
+    }
+}
+
+/// A DER signature on `curve`. `Signature::parse_der` caps the length for
+/// 32-byte scalars, as Bitcoin does, which rejects signatures on the
+/// 384-bit curves
+pub fn parse_der(curve: &NamedCurve, bytes: &[u8]) -> Result<Signature, DecodeError<SignatureError>> {
+    Signature::parse_der_sized(bytes, curve.scalar_len())
+}
+
+/// The leftmost bits of `bytes`, as many as n has (RFC 6979 section 2.3.2)
+fn bits2int(bytes: &[u8], n: &BigUint) -> BigUint {
+    let z = BigUint::from_bytes_be(bytes);
+    let (len, qlen) = (bytes.len() as u64 * 8, n.bits());
+    if len > qlen {
+        z >> (len - qlen)
+    } else {
+        z
+    }
+}
+
+#[cfg(test)]
+mod ecdsa_tests {
+    use super::*;
+    use crate::crypto::rng::DeterministicRng;
+    use crate::curves::secp256k1::ecc::PrivateKey;
+    use crate::named_curve::{named_curve, p256};
+
+    fn hex_int(s: &str) -> BigUint {
+        BigUint::parse_bytes(s.as_bytes(), 16).unwrap()
+    }
+
+    // RFC 6979 A.2.5: P-256, messages "sample" and "test"
+    const X: &str = "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
+    const UX: &str = "60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6";
+    const UY: &str = "7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299";
+
+    #[test]
+    fn test_rfc6979_p256() {
+        let key = SigningKey::from_bytes(p256(), &hex::decode(X).unwrap()).unwrap();
+        let public_key = key.verifying_key();
+        assert_eq!(hex::encode(public_key.sec(false)), format!("04{}{}", UX, UY));
+
+        for (message, r, s) in [
+            (
+                "sample",
+                "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716",
+                "f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8",
+            ),
+            (
+                "test",
+                "f1abb023518351cd71d881567b1ea663ed3efcf6c5132b354f28d3b0b7d38367",
+                "019f4113742a2b14bd25926b49c649155f267e60d3814b4c0cc84250e46f0083",
+            ),
+        ] {
+            let hash = Sha256::digest(message);
+            let signature = key.sign(&hash);
+            assert_eq!(signature, Signature::from(hex_int(r), hex_int(s)), "{}", message);
+            assert!(public_key.verify(&hash, &signature));
+            assert!(!public_key.verify(&Sha256::digest("other"), &signature));
+        }
+
+        // with SHA-512, whose digest is longer than n
+        let signature = key.sign(&Sha512::digest("sample"));
+        assert_eq!(
+            signature.r(),
+            &hex_int("8496a60b5e9b47c825488827e0495b0e3fa109ec4568fd3f8d1097678eb97f00")
+        );
+        assert!(public_key.verify(&Sha512::digest("sample"), &signature));
+    }
+
+    #[test]
+    fn test_matches_secp256k1() {
+        // on secp256k1 the generic code signs as `ecc` does, up to low-s
+        let secp256k1 = named_curve("secp256k1").unwrap();
+        let mut rng = DeterministicRng::new([0x82; 32]);
+        for _ in 0..4 {
+            let key = SigningKey::generate(secp256k1, &mut rng);
+            let hash = Sha256::digest(key.to_bytes());
+            let expected = PrivateKey::from(key.secret().clone())
+                .unwrap()
+                .sign(&BigUint::from_bytes_be(&hash));
+            let signature = key.sign(&hash);
+            assert_eq!(signature.clone().normalize_s(), expected);
+            assert!(key.verifying_key().verify(&hash, &signature));
+        }
+    }
+
+    #[test]
+    fn test_brainpool() {
+        // signatures made by OpenSSL with the secret 0xa11ce
+        for (name, hash, r, s) in [
+            (
+                "brainpoolP256r1",
+                Sha256::digest("brainpool").to_vec(),
+                "66819f9435589658ec4d3361fe4bcb16daedd642cff534920b51cf2edaf4b821",
+                "65d76a48fd427b3dde7b22e83f7ac5d76b76117ad4655e2af254e7464b820447",
+            ),
+            (
+                "brainpoolP384r1",
+                Sha384::digest("brainpool").to_vec(),
+                "91e36d8bceb98385d3eaffff5ecd561e9738e058a13feb6825410de4837590b19eda683fc3cc56c5c07f78fb5c2ac65",
+                "16c45ecbfdbb02854222c86675300907f1d2e31b0a279b9f95e1b2ff4690f3a9621bada0735d4313ac2bc6f2ebeb6adf",
+            ),
+        ] {
+            let key = SigningKey::new(named_curve(name).unwrap(), BigUint::from(0xa11ce_u32)).unwrap();
+            let public_key = key.verifying_key();
+            assert!(
+                public_key.verify(&hash, &Signature::from(hex_int(r), hex_int(s))),
+                "{}",
+                name
+            );
+
+            let signature = key.sign(&hash);
+            assert!(public_key.verify(&hash, &signature));
+            assert!(!public_key.verify(&hash[1..], &signature));
+            assert_eq!(parse_der(key.curve(), &signature.der()), Ok(signature));
+        }
+    }
+
+    #[test]
+    fn test_keys() {
+        let curve = p256();
+        assert_eq!(
+            SigningKey::new(curve, curve.order().clone()),
+            Err(KeyError::InvalidSecret)
+        );
+        assert_eq!(SigningKey::from_bytes(curve, &[1; 31]), Err(KeyError::InvalidSecret));
+
+        let key = SigningKey::generate(curve, &mut DeterministicRng::new([1; 32]));
+        assert_eq!(SigningKey::from_bytes(curve, &key.to_bytes()), Ok(key.clone()));
+        let public_key = key.verifying_key();
+        assert_eq!(
+            VerifyingKey::parse(curve, &public_key.sec(true)),
+            Ok(public_key.clone())
+        );
+        assert_eq!(
+            VerifyingKey::from_point(curve, curve.infinity()),
+            Err(KeyError::InvalidEncoding)
+        );
+
+        // a secp256k1 point is not a P-256 key
+        let secp256k1 = named_curve("secp256k1").unwrap();
+        let other = secp256k1.generator().clone();
+        assert_eq!(VerifyingKey::from_point(curve, other), Err(KeyError::InvalidEncoding));
+
+        // signatures out of range, and with s negated, which is still valid
+        let hash = Sha256::digest(b"msg");
+        let signature = key.sign(&hash);
+        let n = curve.order();
+        assert!(!public_key.verify(&hash, &Signature::from(signature.r() + n, signature.s().clone())));
+        assert!(public_key.verify(&hash, &Signature::from(signature.r().clone(), n - signature.s())));
+        assert_eq!(key.sign_with_nonce(&hash, n), None);
+    }
+}
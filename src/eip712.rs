@@ -0,0 +1,454 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::crypto::hash::keccak256;
+use crate::curves::secp256k1::ecc::PrivateKey;
+use crate::ethereum::{Address, RecoverableSignature};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Eip712Error {
+    UnknownType(String),
+    /// A struct value lacks one of its type's fields
+    MissingField(String),
+    /// The value doesn't fit the declared type, e.g. a string for a uint or
+    /// a number out of the type's range
+    TypeMismatch(String),
+}
+
+impl Display for Eip712Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Eip712Error::UnknownType(name) => write!(f, "unknown type {}", name),
+            Eip712Error::MissingField(name) => write!(f, "missing field {}", name),
+            Eip712Error::TypeMismatch(name) => write!(f, "value does not match type {}", name),
+        }
+    }
+}
+
+impl std::error::Error for Eip712Error {}
+
+/// A value of some EIP-712 type; which one is up to the struct field it
+/// fills
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Uint(BigUint),
+    Int(BigInt),
+    Bool(bool),
+    Address(Address),
+    /// bytes1 to bytes32
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+    Array(Vec<Value>),
+    /// Field values by name, in any order
+    Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn structure(fields: &[(&str, Value)]) -> Self {
+        Value::Struct(
+            fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+/// Struct definitions by name, each an ordered list of (field, type)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Types {
+    structs: BTreeMap<String, Vec<(String, String)>>,
+}
+
+impl Types {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(mut self, name: &str, fields: &[(&str, &str)]) -> Self {
+        let fields = fields
+            .iter()
+            .map(|(field, ty)| (field.to_string(), ty.to_string()))
+            .collect();
+        self.structs.insert(name.to_string(), fields);
+        self
+    }
+
+    /// `ty` with its array dimensions stripped
+    fn base(ty: &str) -> &str {
+        ty.split('[').next().unwrap()
+    }
+
+    fn dependencies(&self, name: &str, found: &mut BTreeSet<String>) -> Result<(), Eip712Error> {
+        let fields = self
+            .structs
+            .get(name)
+            .ok_or_else(|| Eip712Error::UnknownType(name.to_string()))?;
+        for (_, ty) in fields {
+            let base = Self::base(ty);
+            if self.structs.contains_key(base) && found.insert(base.to_string()) {
+                self.dependencies(base, found)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `Name(type field,...)` for the struct, followed by the structs it
+    /// references, sorted by name
+    pub fn encode_type(&self, name: &str) -> Result<String, Eip712Error> {
+        let mut found = BTreeSet::new();
+        self.dependencies(name, &mut found)?;
+        found.remove(name);
+        let mut res = String::new();
+        for ty in std::iter::once(name).chain(found.iter().map(String::as_str)) {
+            let fields: Vec<String> = self.structs[ty]
+                .iter()
+                .map(|(field, ty)| format!("{} {}", ty, field))
+                .collect();
+            res.push_str(&format!("{}({})", ty, fields.join(",")));
+        }
+        Ok(res)
+    }
+
+    pub fn type_hash(&self, name: &str) -> Result<[u8; 32], Eip712Error> {
+        Ok(keccak256(self.encode_type(name)?.as_bytes()))
+    }
+
+    /// keccak256(typeHash || encodeData(value))
+    pub fn hash_struct(&self, name: &str, value: &Value) -> Result<[u8; 32], Eip712Error> {
+        let fields = self
+            .structs
+            .get(name)
+            .ok_or_else(|| Eip712Error::UnknownType(name.to_string()))?;
+        let mut data = self.type_hash(name)?.to_vec();
+        for (field, ty) in fields {
+            let value = value
+                .field(field)
+                .ok_or_else(|| Eip712Error::MissingField(field.clone()))?;
+            data.extend_from_slice(&self.encode_value(ty, value)?);
+        }
+        Ok(keccak256(&data))
+    }
+
+    /// One 32-byte word per value: atomic types padded, dynamic ones and
+    /// arrays hashed, structs by their struct hash
+    fn encode_value(&self, ty: &str, value: &Value) -> Result<[u8; 32], Eip712Error> {
+        let mismatch = || Eip712Error::TypeMismatch(ty.to_string());
+        if let Some(element) = ty.strip_suffix(']') {
+            let (element, len) = element.rsplit_once('[').ok_or_else(mismatch)?;
+            let Value::Array(items) = value else {
+                return Err(mismatch());
+            };
+            if !len.is_empty() && len.parse() != Ok(items.len()) {
+                return Err(mismatch());
+            }
+            let mut data = Vec::with_capacity(32 * items.len());
+            for item in items {
+                data.extend_from_slice(&self.encode_value(element, item)?);
+            }
+            return Ok(keccak256(&data));
+        }
+        if self.structs.contains_key(ty) {
+            return self.hash_struct(ty, value);
+        }
+        let mut word = [0_u8; 32];
+        match (ty, value) {
+            ("string", Value::String(s)) => word = keccak256(s.as_bytes()),
+            ("bytes", Value::Bytes(bytes)) => word = keccak256(bytes),
+            ("bool", Value::Bool(b)) => word[31] = *b as u8,
+            ("address", Value::Address(address)) => word[12..].copy_from_slice(&address.0),
+            (_, Value::Uint(n)) if ty.starts_with("uint") => {
+                if n.bits() > int_width(&ty[4..]).ok_or_else(|| Eip712Error::UnknownType(ty.to_string()))? {
+                    return Err(mismatch());
+                }
+                let bytes = n.to_bytes_be();
+                word[32 - bytes.len()..].copy_from_slice(&bytes);
+            }
+            (_, Value::Int(n)) if ty.starts_with("int") => {
+                let bits = int_width(&ty[3..]).ok_or_else(|| Eip712Error::UnknownType(ty.to_string()))?;
+                let bound = BigInt::from(1_u8) << (bits - 1);
+                if *n >= bound || *n < -bound {
+                    return Err(mismatch());
+                }
+                // two's complement over 256 bits
+                let unsigned = if n.sign() == Sign::Minus {
+                    (BigInt::from(1_u8) << 256) + n
+                } else {
+                    n.clone()
+                };
+                let (_, bytes) = unsigned.to_bytes_be();
+                word[32 - bytes.len()..].copy_from_slice(&bytes);
+            }
+            (_, Value::FixedBytes(bytes)) if ty.starts_with("bytes") => match ty[5..].parse::<usize>() {
+                Ok(len @ 1..=32) if len == bytes.len() => word[..len].copy_from_slice(bytes),
+                Ok(1..=32) => return Err(mismatch()),
+                _ => return Err(Eip712Error::UnknownType(ty.to_string())),
+            },
+            ("string" | "bytes" | "bool" | "address", _) => return Err(mismatch()),
+            _ if ty.starts_with("uint") || ty.starts_with("int") || ty.starts_with("bytes") => return Err(mismatch()),
+            _ => return Err(Eip712Error::UnknownType(ty.to_string())),
+        }
+        Ok(word)
+    }
+}
+
+/// Width of a uintN/intN suffix: a multiple of 8 up to 256
+fn int_width(suffix: &str) -> Option<u64> {
+    match suffix.parse() {
+        Ok(bits @ 8..=256) if bits % 8 == 0 => Some(bits),
+        _ => None,
+    }
+}
+
+/// The EIP712Domain fields; only those that are set go into its type
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Domain {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub chain_id: Option<u64>,
+    pub verifying_contract: Option<Address>,
+    pub salt: Option<[u8; 32]>,
+}
+
+impl Domain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn verifying_contract(mut self, address: Address) -> Self {
+        self.verifying_contract = Some(address);
+        self
+    }
+
+    pub fn salt(mut self, salt: [u8; 32]) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// hashStruct of the domain, mixed into every signing hash so a
+    /// signature for one dapp or chain is useless to another
+    pub fn separator(&self) -> [u8; 32] {
+        let mut fields = Vec::new();
+        let mut values = Vec::new();
+        if let Some(name) = &self.name {
+            fields.push(("name", "string"));
+            values.push(("name", Value::String(name.clone())));
+        }
+        if let Some(version) = &self.version {
+            fields.push(("version", "string"));
+            values.push(("version", Value::String(version.clone())));
+        }
+        if let Some(chain_id) = self.chain_id {
+            fields.push(("chainId", "uint256"));
+            values.push(("chainId", Value::Uint(chain_id.into())));
+        }
+        if let Some(address) = self.verifying_contract {
+            fields.push(("verifyingContract", "address"));
+            values.push(("verifyingContract", Value::Address(address)));
+        }
+        if let Some(salt) = self.salt {
+            fields.push(("salt", "bytes32"));
+            values.push(("salt", Value::FixedBytes(salt.to_vec())));
+        }
+        Types::new()
+            .add("EIP712Domain", &fields)
+            .hash_struct("EIP712Domain", &Value::structure(&values))
+            .expect("the domain's values match its type")
+    }
+}
+
+/// A message for `eth_signTypedData`: the primary struct with the types
+/// it uses, under a domain
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedData {
+    pub types: Types,
+    pub primary_type: String,
+    pub domain: Domain,
+    pub message: Value,
+}
+
+impl TypedData {
+    pub fn new(types: Types, primary_type: &str, domain: Domain, message: Value) -> Self {
+        Self {
+            types,
+            primary_type: primary_type.to_string(),
+            domain,
+            message,
+        }
+    }
+
+    /// keccak256(0x19 0x01 || domainSeparator || hashStruct(message))
+    pub fn signing_hash(&self) -> Result<[u8; 32], Eip712Error> {
+        let mut data = vec![0x19, 0x01];
+        data.extend_from_slice(&self.domain.separator());
+        data.extend_from_slice(&self.types.hash_struct(&self.primary_type, &self.message)?);
+        Ok(keccak256(&data))
+    }
+
+    pub fn sign(&self, key: &PrivateKey) -> Result<RecoverableSignature, Eip712Error> {
+        Ok(RecoverableSignature::sign(key, &self.signing_hash()?))
+    }
+
+    /// Whether `signer` signed this message
+    pub fn verify(&self, signature: &RecoverableSignature, signer: &Address) -> Result<bool, Eip712Error> {
+        Ok(signature
+            .recover_address(&self.signing_hash()?)
+            .is_ok_and(|address| address == *signer))
+    }
+}
+
+#[cfg(test)]
+mod eip712_tests {
+    use super::*;
+
+    fn person(name: &str, wallet: &str) -> Value {
+        Value::structure(&[
+            ("name", Value::String(name.to_string())),
+            ("wallet", Value::Address(Address::parse(wallet).unwrap())),
+        ])
+    }
+
+    /// The example from EIP-712
+    fn mail() -> TypedData {
+        let types = Types::new()
+            .add("Person", &[("name", "string"), ("wallet", "address")])
+            .add("Mail", &[("from", "Person"), ("to", "Person"), ("contents", "string")]);
+        let domain = Domain::new()
+            .name("Ether Mail")
+            .version("1")
+            .chain_id(1)
+            .verifying_contract(Address::parse("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC").unwrap());
+        let message = Value::structure(&[
+            ("from", person("Cow", "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826")),
+            ("to", person("Bob", "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB")),
+            ("contents", Value::String("Hello, Bob!".to_string())),
+        ]);
+        TypedData::new(types, "Mail", domain, message)
+    }
+
+    #[test]
+    fn test_mail() {
+        let data = mail();
+        assert_eq!(
+            data.types.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            hex::encode(data.types.type_hash("Mail").unwrap()),
+            "a0cedeb2dc280ba39b857546d74f5549c3a1d7bdc2dd96bf881f76108e23dac2"
+        );
+        assert_eq!(
+            hex::encode(data.domain.separator()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            hex::encode(data.types.hash_struct("Mail", &data.message).unwrap()),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+        assert_eq!(
+            hex::encode(data.signing_hash().unwrap()),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+
+        let key = PrivateKey::from_bytes(&keccak256(b"cow")).unwrap();
+        let cow = Address::from_public_key(&key.public_key());
+        assert_eq!(cow.to_string(), "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826");
+        let signature = data.sign(&key).unwrap();
+        assert_eq!(
+            hex::encode(signature.to_bytes()),
+            "4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b915621c"
+        );
+        assert_eq!(data.verify(&signature, &cow), Ok(true));
+
+        let mut tampered = data.clone();
+        tampered.domain.chain_id = Some(5);
+        assert_eq!(tampered.verify(&signature, &cow), Ok(false));
+    }
+
+    #[test]
+    fn test_encode_values() {
+        let types = Types::new().add(
+            "Order",
+            &[
+                ("amounts", "uint256[]"),
+                ("pair", "bytes4[2]"),
+                ("delta", "int8"),
+                ("id", "uint8"),
+            ],
+        );
+        let order = |delta: i32, id: u32| {
+            Value::structure(&[
+                (
+                    "amounts",
+                    Value::Array(vec![Value::Uint(1_u8.into()), Value::Uint(BigUint::from(1_u8) << 200)]),
+                ),
+                (
+                    "pair",
+                    Value::Array(vec![Value::FixedBytes(vec![1; 4]), Value::FixedBytes(vec![2; 4])]),
+                ),
+                ("delta", Value::Int(delta.into())),
+                ("id", Value::Uint(id.into())),
+            ])
+        };
+        assert!(types.hash_struct("Order", &order(-128, 255)).is_ok());
+        assert_ne!(
+            types.hash_struct("Order", &order(-1, 0)),
+            types.hash_struct("Order", &order(1, 0))
+        );
+        assert_eq!(types.encode_value("int8", &Value::Int((-1).into())), Ok([0xff; 32]));
+
+        assert_eq!(
+            types.hash_struct("Order", &order(128, 0)),
+            Err(Eip712Error::TypeMismatch("int8".to_string()))
+        );
+        assert_eq!(
+            types.hash_struct("Order", &order(0, 256)),
+            Err(Eip712Error::TypeMismatch("uint8".to_string()))
+        );
+        assert_eq!(
+            types.hash_struct("Missing", &order(0, 0)),
+            Err(Eip712Error::UnknownType("Missing".to_string()))
+        );
+        assert_eq!(
+            types.hash_struct("Order", &Value::structure(&[])),
+            Err(Eip712Error::MissingField("amounts".to_string()))
+        );
+        assert_eq!(
+            types.encode_value("bytes4[2]", &Value::Array(vec![Value::FixedBytes(vec![1; 4])])),
+            Err(Eip712Error::TypeMismatch("bytes4[2]".to_string()))
+        );
+        assert_eq!(
+            types.encode_value("uint7", &Value::Uint(1_u8.into())),
+            Err(Eip712Error::UnknownType("uint7".to_string()))
+        );
+        assert_eq!(
+            types.encode_value("bool", &Value::String("true".to_string())),
+            Err(Eip712Error::TypeMismatch("bool".to_string()))
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use std::fmt::Display;
+
+use hmac::{Hmac, Mac};
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use sha2::Sha512;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::address::Network;
+use crate::bip32::{Bip32Error, ExtendedPrivateKey, HARDENED, VPRV, ZPRV};
+use crate::crypto::hash::sha256;
+use crate::crypto::kdf::{pbkdf2_hmac_sha512, Pbkdf2Params};
+use crate::wallet::Template;
+use crate::wordlist;
+
+/// Electrum 2.0+ seeds encode their wallet type in the prefix of
+/// HMAC-SHA512("Seed version", seed) instead of carrying a checksum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeedType {
+    Standard,
+    Segwit,
+    TwoFactor,
+    TwoFactorSegwit,
+}
+
+impl SeedType {
+    pub fn prefix(self) -> &'static str {
+        match self {
+            SeedType::Standard => "01",
+            SeedType::Segwit => "100",
+            SeedType::TwoFactor => "101",
+            SeedType::TwoFactorSegwit => "102",
+        }
+    }
+
+    fn detect(normalized: &str) -> Option<Self> {
+        let version = hex::encode(seed_version(normalized));
+        [
+            SeedType::Standard,
+            SeedType::Segwit,
+            SeedType::TwoFactor,
+            SeedType::TwoFactorSegwit,
+        ]
+        .into_iter()
+        .find(|t| version.starts_with(t.prefix()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElectrumError {
+    /// The seed's version hash matches none of the known prefixes
+    InvalidVersion,
+    /// 2FA seeds restore TrustedCoin multisig wallets, which aren't supported
+    UnsupportedSeedType(SeedType),
+    Bip32(Bip32Error),
+}
+
+impl Display for ElectrumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ElectrumError::InvalidVersion => write!(f, "not an Electrum seed"),
+            ElectrumError::UnsupportedSeedType(t) => write!(f, "{:?} seeds are not supported", t),
+            ElectrumError::Bip32(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ElectrumError {}
+
+impl From<Bip32Error> for ElectrumError {
+    fn from(e: Bip32Error) -> Self {
+        ElectrumError::Bip32(e)
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    const CJK: [(u32, u32); 19] = [
+        (0x4e00, 0x9fff),
+        (0x3400, 0x4dbf),
+        (0x20000, 0x2a6df),
+        (0x2a700, 0x2b73f),
+        (0x2b740, 0x2b81f),
+        (0xf900, 0xfaff),
+        (0x2f800, 0x2fa1f),
+        (0x3000, 0x303f),
+        (0x3040, 0x309f),
+        (0x30a0, 0x30ff),
+        (0x31f0, 0x31ff),
+        (0xac00, 0xd7af),
+        (0x1100, 0x11ff),
+        (0xa960, 0xa97f),
+        (0xd7b0, 0xd7ff),
+        (0x3130, 0x318f),
+        (0x3190, 0x319f),
+        (0x3100, 0x312f),
+        (0x31a0, 0x31bf),
+    ];
+    CJK.iter().any(|&(lo, hi)| (lo..=hi).contains(&(c as u32)))
+}
+
+/// Electrum's normalize_text: NFKD, lowercase, strip accents, collapse
+/// whitespace and drop spaces between CJK characters
+pub fn normalize(text: &str) -> String {
+    let lowered: String = text.nfkd().collect::<String>().to_lowercase();
+    let stripped: String = lowered.chars().filter(|&c| !is_combining_mark(c)).collect();
+    let collapsed: Vec<char> = stripped
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect();
+    collapsed
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| {
+            !(c == ' ' && i > 0 && i + 1 < collapsed.len() && is_cjk(collapsed[i - 1]) && is_cjk(collapsed[i + 1]))
+        })
+        .map(|(_, &c)| c)
+        .collect()
+}
+
+fn seed_version(normalized: &str) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(b"Seed version").expect("HMAC accepts keys of any size");
+    mac.update(normalized.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// Whether the words also happen to form a valid BIP39 mnemonic
+fn is_bip39_checksum_valid(words: &[&str]) -> bool {
+    if words.is_empty() || !words.len().is_multiple_of(3) {
+        return false;
+    }
+    let mut n = BigUint::zero();
+    for word in words {
+        match wordlist::index_of(word) {
+            Some(i) => n = n * 2048_u32 + i,
+            None => return false,
+        }
+    }
+    let checksum_bits = words.len() * 11 / 33;
+    let entropy_len = (words.len() * 11 - checksum_bits) / 8;
+    let checksum = (&n & BigUint::from((1_u32 << checksum_bits) - 1)).to_u32().unwrap();
+    let entropy = (n >> checksum_bits).to_bytes_be();
+    let mut padded = vec![0_u8; entropy_len - entropy.len()];
+    padded.extend(entropy);
+    (sha256(&padded)[0] >> (8 - checksum_bits)) as u32 == checksum
+}
+
+/// Little-endian base-2048 encoding used by Electrum's Mnemonic.mnemonic_encode
+fn mnemonic_encode(i: &BigUint) -> String {
+    let words = wordlist::english();
+    let mut i = i.clone();
+    let mut res = Vec::new();
+    while !i.is_zero() {
+        res.push(words[(&i % 2048_u32).to_usize().unwrap()]);
+        i /= 2048_u32;
+    }
+    res.join(" ")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElectrumSeed {
+    mnemonic: String,
+    seed_type: SeedType,
+}
+
+impl ElectrumSeed {
+    /// Validates a mnemonic by its version prefix. Pre-2.0 "old" seeds are
+    /// not recognized
+    pub fn parse(mnemonic: &str) -> Result<Self, ElectrumError> {
+        let mnemonic = normalize(mnemonic);
+        let seed_type = SeedType::detect(&mnemonic).ok_or(ElectrumError::InvalidVersion)?;
+        Ok(Self { mnemonic, seed_type })
+    }
+
+    /// Electrum's make_seed: counts up from `entropy` until the encoding has
+    /// the requested version prefix and isn't accidentally valid BIP39
+    pub fn from_entropy(entropy: &BigUint, seed_type: SeedType) -> Self {
+        let mut i = entropy.clone();
+        loop {
+            i += 1_u8;
+            let mnemonic = mnemonic_encode(&i);
+            let words: Vec<&str> = mnemonic.split(' ').collect();
+            if is_bip39_checksum_valid(&words) {
+                continue;
+            }
+            if SeedType::detect(&mnemonic) == Some(seed_type) {
+                return Self { mnemonic, seed_type };
+            }
+        }
+    }
+
+    /// Generates a fresh 12-word seed from 132 bits of OS randomness
+    pub fn generate(seed_type: SeedType) -> Self {
+        Self::generate_with_rng(seed_type, &mut OsRng)
+    }
+
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(seed_type: SeedType, rng: &mut R) -> Self {
+        // keep the top word non-zero so the mnemonic always has 12 words
+        let lower_bound = BigUint::from(1_u8) << 121;
+        let mut bytes = [0_u8; 17];
+        loop {
+            rng.fill_bytes(&mut bytes);
+            bytes[0] &= 0x0f;
+            let entropy = BigUint::from_bytes_be(&bytes);
+            if entropy >= lower_bound {
+                return Self::from_entropy(&entropy, seed_type);
+            }
+        }
+    }
+
+    pub fn mnemonic(&self) -> &str {
+        &self.mnemonic
+    }
+
+    pub fn seed_type(&self) -> SeedType {
+        self.seed_type
+    }
+
+    /// PBKDF2-HMAC-SHA512 with salt "electrum" || passphrase and 2048 rounds
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let salt = format!("electrum{}", normalize(passphrase));
+        let mut res = [0_u8; 64];
+        pbkdf2_hmac_sha512(self.mnemonic.as_bytes(), salt.as_bytes(), Pbkdf2Params::SEED, &mut res);
+        res
+    }
+
+    /// Account key per Electrum's scheme: the master key for standard
+    /// wallets and m/0' with zprv/vprv versions for segwit wallets
+    pub fn account_key(&self, passphrase: &str, network: Network) -> Result<ExtendedPrivateKey, ElectrumError> {
+        let master = ExtendedPrivateKey::from_seed(&self.to_seed(passphrase), network)?;
+        match self.seed_type {
+            SeedType::Standard => Ok(master),
+            SeedType::Segwit => {
+                let mut account = master.derive_child(HARDENED)?;
+                account.version = match network {
+                    Network::Mainnet => ZPRV,
+                    Network::Testnet | Network::Signet | Network::Regtest => VPRV,
+                };
+                Ok(account)
+            }
+            t => Err(ElectrumError::UnsupportedSeedType(t)),
+        }
+    }
+
+    /// Address template matching the seed type, for use with `WatchOnly`
+    pub fn template(&self, network: Network) -> Result<Template, ElectrumError> {
+        match self.seed_type {
+            SeedType::Standard => Ok(Template::pkh(network)),
+            SeedType::Segwit => Ok(Template::wpkh(network)),
+            t => Err(ElectrumError::UnsupportedSeedType(t)),
+        }
+    }
+}
+
+impl Display for ElectrumSeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.mnemonic)
+    }
+}
+
+#[cfg(test)]
+mod electrum_tests {
+    use super::*;
+    use crate::wallet::{Chain, WatchOnly};
+
+    const SEGWIT_SEED: &str = "wild father tree among universe such mobile favorite target dynamic credit identify";
+
+    #[test]
+    fn test_parse_segwit() {
+        let seed = ElectrumSeed::parse(SEGWIT_SEED).unwrap();
+
+        assert_eq!(seed.seed_type(), SeedType::Segwit);
+        assert_eq!(
+            hex::encode(seed.to_seed("")),
+            "aac2a6302e48577ab4b46f23dbae0774e2e62c796f797d0a1b5faeb528301e30\
+             64342dafb79069e7c4c6b8c38ae11d7a973bec0d4f70626f8cc5184a8d0b0756"
+        );
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let seed = ElectrumSeed::parse(SEGWIT_SEED).unwrap();
+
+        assert_eq!(
+            hex::encode(seed.to_seed("Did you ever hear the tragedy of Darth Plagueis the Wise?")),
+            "4aa29f2aeb0127efb55138ab9e7be83b36750358751906f86c662b21a1ea1370\
+             f949e6d1a12fa56d3d93cadda93038c76ac8118597364e46f5156fde6183c82f"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(
+            ElectrumSeed::parse(
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+            ),
+            Err(ElectrumError::InvalidVersion)
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  Wild   FATHERé tree "), "wild fathere tree");
+        assert_eq!(normalize("あいう えお abc"), "あいうえお abc");
+        assert_eq!(
+            ElectrumSeed::parse(&SEGWIT_SEED.to_uppercase().replace(' ', "   "))
+                .unwrap()
+                .mnemonic(),
+            SEGWIT_SEED
+        );
+    }
+
+    #[test]
+    fn test_from_entropy() {
+        let entropy = BigUint::parse_bytes(b"1234567890abcdef1234567890abcdef1", 16).unwrap();
+
+        let segwit = ElectrumSeed::from_entropy(&entropy, SeedType::Segwit);
+        assert_eq!(
+            segwit.mnemonic(),
+            "wing vibrant luxury joy member emotion wasp rubber candy owner pencil banana"
+        );
+        assert_eq!(ElectrumSeed::parse(segwit.mnemonic()), Ok(segwit));
+
+        let standard = ElectrumSeed::from_entropy(&entropy, SeedType::Standard);
+        assert_eq!(
+            standard.mnemonic(),
+            "among vicious luxury joy member emotion wasp rubber candy owner pencil banana"
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        let seed = ElectrumSeed::generate(SeedType::Standard);
+
+        assert_eq!(seed.mnemonic().split(' ').count(), 12);
+        assert_eq!(ElectrumSeed::parse(seed.mnemonic()), Ok(seed));
+    }
+
+    #[test]
+    fn test_bip39_checksum() {
+        let words: Vec<&str> =
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+                .split(' ')
+                .collect();
+        assert!(is_bip39_checksum_valid(&words));
+        assert!(!is_bip39_checksum_valid(&SEGWIT_SEED.split(' ').collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn test_standard_wallet() {
+        let seed =
+            ElectrumSeed::parse("cycle rocket west magnet parrot shuffle foot correct salt library feed song").unwrap();
+        let account = seed.account_key("", Network::Mainnet).unwrap().to_public().unwrap();
+        assert_eq!(
+            account.to_string(),
+            "xpub661MyMwAqRbcFWohJWt7PHsFEJfZAvw9ZxwQoDa4SoMgsDDM1T7WK3u9E4edkC4ugRnZ8E4xDZRpk8Rnts3Nbt97dPwT52CwBdDWroaZf8U"
+        );
+
+        let wallet = WatchOnly::new(account, seed.template(Network::Mainnet).unwrap(), 20).unwrap();
+        assert_eq!(
+            wallet.address(Chain::Receive, 0).unwrap(),
+            "1NNkttn1YvVGdqBW4PR6zvc3Zx3H5owKRf"
+        );
+        assert_eq!(
+            wallet.address(Chain::Change, 0).unwrap(),
+            "1KSezYMhAJMWqFbVFB2JshYg69UpmEXR4D"
+        );
+    }
+
+    #[test]
+    fn test_segwit_wallet() {
+        let seed = ElectrumSeed::parse("bitter grass shiver impose acquire brush forget axis eager alone wine silver")
+            .unwrap();
+        let account = seed.account_key("", Network::Mainnet).unwrap().to_public().unwrap();
+        assert_eq!(
+            account.to_string(),
+            "zpub6nsHdRuY92FsMKdbn9BfjBCG6X8pyhCibNP6uDvpnw2cyrVhecvHRMa3Ne8kdJZxjxgwnpbHLkcR4bfnhHy6auHPJyDTQ3kianeuVLdkCYQ"
+        );
+
+        let wallet = WatchOnly::new(account, seed.template(Network::Mainnet).unwrap(), 20).unwrap();
+        assert_eq!(
+            wallet.address(Chain::Receive, 0).unwrap(),
+            "bc1q3g5tmkmlvxryhh843v4dz026avatc0zzr6h3af"
+        );
+        assert_eq!(
+            wallet.address(Chain::Change, 0).unwrap(),
+            "bc1qdy94n2q5qcp0kg7v9yzwe6wvfkhnvyzje7nx2p"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt::Display;
+
+use crate::crypto::hash::hash256;
+
+const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Base58Error {
+    InvalidCharacter(char),
+    /// Base58Check payloads must carry at least the 4 checksum bytes
+    TooShort,
+    InvalidChecksum,
+}
+
+impl Display for Base58Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Base58Error::InvalidCharacter(c) => write!(f, "invalid base58 character {:?}", c),
+            Base58Error::TooShort => write!(f, "base58check payload is too short"),
+            Base58Error::InvalidChecksum => write!(f, "base58check checksum mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for Base58Error {}
+
+pub fn encode(data: &[u8]) -> String {
+    // every leading zero byte is encoded as a leading '1'
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+
+    // repeated division by 58 on a little-endian base58 digit buffer
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let mut res = String::with_capacity(zeros + digits.len());
+    res.extend(std::iter::repeat_n('1', zeros));
+    res.extend(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char));
+    res
+}
+
+pub fn decode(s: &str) -> Result<Vec<u8>, Base58Error> {
+    let zeros = s.chars().take_while(|&c| c == '1').count();
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len());
+    for c in s.chars().skip(zeros) {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or(Base58Error::InvalidCharacter(c))? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut res = vec![0_u8; zeros];
+    res.extend(bytes.iter().rev());
+    Ok(res)
+}
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let digest = hash256(data);
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+/// Base58 with a 4-byte double-SHA256 checksum appended, as used for
+/// legacy addresses, WIF and extended keys
+pub fn encode_check(data: &[u8]) -> String {
+    let mut payload = data.to_vec();
+    payload.extend_from_slice(&checksum(data));
+    encode(&payload)
+}
+
+pub fn decode_check(s: &str) -> Result<Vec<u8>, Base58Error> {
+    let mut payload = decode(s)?;
+    if payload.len() < 4 {
+        return Err(Base58Error::TooShort);
+    }
+    let check = payload.split_off(payload.len() - 4);
+    if check != checksum(&payload) {
+        return Err(Base58Error::InvalidChecksum);
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod base58_tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let data = hex::decode("7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d").unwrap();
+        assert_eq!(encode(&data), "9MA8fRQrT4u8Zj8ZRd6MAiiyaxb2Y1CMpvVkHQu5hVM6");
+
+        let data = hex::decode("0000287fb4cd").unwrap();
+        assert_eq!(encode(&data), "11233QC4");
+    }
+
+    #[test]
+    fn test_decode_roundtrip() {
+        let data = hex::decode("00eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c").unwrap();
+        assert_eq!(decode(&encode(&data)).unwrap(), data);
+        assert_eq!(decode(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_decode_invalid_character() {
+        assert_eq!(decode("1O"), Err(Base58Error::InvalidCharacter('O')));
+    }
+
+    #[test]
+    fn test_check_roundtrip() {
+        // P2PKH address for hash160 of the compressed generator point
+        let payload = hex::decode("00751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let address = encode_check(&payload);
+
+        assert_eq!(address, "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+        assert_eq!(decode_check(&address).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_check_invalid_checksum() {
+        assert_eq!(
+            decode_check("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMJ"),
+            Err(Base58Error::InvalidChecksum)
+        );
+        assert_eq!(decode_check("1"), Err(Base58Error::TooShort));
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::Display;
+
+const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Base64Error {
+    InvalidCharacter(char),
+    /// Not a multiple of 4 characters, or padding anywhere but the end
+    InvalidPadding,
+    /// The last character carries bits that don't fit in the output
+    NonCanonical,
+}
+
+impl Display for Base64Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Base64Error::InvalidCharacter(c) => write!(f, "invalid base64 character {:?}", c),
+            Base64Error::InvalidPadding => write!(f, "invalid base64 padding"),
+            Base64Error::NonCanonical => write!(f, "non-canonical base64 encoding"),
+        }
+    }
+}
+
+impl std::error::Error for Base64Error {}
+
+/// Standard RFC 4648 base64 with padding, as PSBTs are exchanged
+pub fn encode(data: &[u8]) -> String {
+    let mut res = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+    res
+}
+
+pub fn decode(s: &str) -> Result<Vec<u8>, Base64Error> {
+    let bytes = s.as_bytes();
+    if !bytes.len().is_multiple_of(4) {
+        return Err(Base64Error::InvalidPadding);
+    }
+    let padding = bytes.iter().rev().take_while(|&&b| b == b'=').count();
+    if padding > 2 {
+        return Err(Base64Error::InvalidPadding);
+    }
+
+    let mut res = Vec::with_capacity(bytes.len() / 4 * 3);
+    for (i, chunk) in bytes.chunks(4).enumerate() {
+        let last = i == bytes.len() / 4 - 1;
+        let data_len = if last { 4 - padding } else { 4 };
+        let mut n = 0_u32;
+        for (j, &c) in chunk.iter().enumerate() {
+            let value = if j < data_len {
+                ALPHABET.iter().position(|&a| a == c)
+            } else {
+                None
+            };
+            match value {
+                Some(v) => n |= (v as u32) << (18 - 6 * j),
+                None if c == b'=' && j >= data_len => {}
+                None if c == b'=' => return Err(Base64Error::InvalidPadding),
+                None => return Err(Base64Error::InvalidCharacter(c as char)),
+            }
+        }
+        let out = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        let out_len = data_len - 1;
+        if out[out_len..].iter().any(|&b| b != 0) {
+            return Err(Base64Error::NonCanonical);
+        }
+        res.extend_from_slice(&out[..out_len]);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod base64_tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (data, encoded) in vectors {
+            assert_eq!(encode(data.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), data.as_bytes());
+        }
+        assert_eq!(encode(&[0xfb, 0xff, 0xbf]), "+/+/");
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode("Zm9"), Err(Base64Error::InvalidPadding));
+        assert_eq!(decode("Zg=a"), Err(Base64Error::InvalidPadding));
+        assert_eq!(decode("Z==="), Err(Base64Error::InvalidPadding));
+        assert_eq!(decode("Zg==Zm9v"), Err(Base64Error::InvalidPadding));
+        assert_eq!(decode("Zm9-"), Err(Base64Error::InvalidCharacter('-')));
+        // "Zh==" decodes to 'f' but sets bits the padding should have zeroed
+        assert_eq!(decode("Zh=="), Err(Base64Error::NonCanonical));
+    }
+}
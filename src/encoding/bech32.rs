@@ -0,0 +1,282 @@
+use std::fmt::Display;
+
+const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+/// The checksum constant distinguishes BIP173 bech32 from BIP350 bech32m
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bech32Error {
+    MixedCase,
+    MissingSeparator,
+    InvalidLength,
+    InvalidCharacter(char),
+    InvalidChecksum,
+    /// A 5-bit group outside 0..32 or non-zero padding when regrouping bits
+    InvalidData,
+    InvalidHrp,
+    InvalidWitnessVersion(u8),
+    InvalidProgramLength(usize),
+    /// Witness v0 must use bech32, later versions bech32m
+    WrongVariant,
+}
+
+impl Display for Bech32Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bech32Error::MixedCase => write!(f, "bech32 string mixes upper and lower case"),
+            Bech32Error::MissingSeparator => write!(f, "bech32 string has no separator"),
+            Bech32Error::InvalidLength => write!(f, "bech32 string has an invalid length"),
+            Bech32Error::InvalidCharacter(c) => write!(f, "invalid bech32 character {:?}", c),
+            Bech32Error::InvalidChecksum => write!(f, "bech32 checksum mismatch"),
+            Bech32Error::InvalidData => write!(f, "invalid bech32 data"),
+            Bech32Error::InvalidHrp => write!(f, "unexpected human readable part"),
+            Bech32Error::InvalidWitnessVersion(v) => write!(f, "invalid witness version {}", v),
+            Bech32Error::InvalidProgramLength(l) => write!(f, "invalid witness program length {}", l),
+            Bech32Error::WrongVariant => write!(f, "checksum variant does not match witness version"),
+        }
+    }
+}
+
+impl std::error::Error for Bech32Error {}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for &v in values {
+        let top = chk >> 25;
+        chk = (chk & 0x1ffffff) << 5 ^ v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut res: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    res.push(0);
+    res.extend(hrp.bytes().map(|b| b & 31));
+    res
+}
+
+fn create_checksum(hrp: &str, data: &[u8], variant: Variant) -> [u8; 6] {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; 6]);
+    let pm = polymod(&values) ^ variant.constant();
+    let mut res = [0_u8; 6];
+    for (i, r) in res.iter_mut().enumerate() {
+        *r = ((pm >> (5 * (5 - i))) & 31) as u8;
+    }
+    res
+}
+
+/// Encodes 5-bit groups under the given human readable part
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> Result<String, Bech32Error> {
+    if data.iter().any(|&d| d > 31) {
+        return Err(Bech32Error::InvalidData);
+    }
+    let hrp = hrp.to_lowercase();
+    let mut res = format!("{}1", hrp);
+    for &d in data.iter().chain(create_checksum(&hrp, data, variant).iter()) {
+        res.push(CHARSET[d as usize] as char);
+    }
+    Ok(res)
+}
+
+/// Decodes a bech32 or bech32m string into its lowercase hrp and 5-bit groups
+pub fn decode(s: &str) -> Result<(String, Vec<u8>, Variant), Bech32Error> {
+    decode_with_limit(s, 90)
+}
+
+/// Like `decode`, for formats that allow strings longer than the 90
+/// characters of BIP-173. The checksum only guarantees error detection up
+/// to 1023 characters
+pub fn decode_with_limit(s: &str, max_len: usize) -> Result<(String, Vec<u8>, Variant), Bech32Error> {
+    if s.chars().any(|c| c.is_ascii_lowercase()) && s.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(Bech32Error::MixedCase);
+    }
+    if s.len() > max_len {
+        return Err(Bech32Error::InvalidLength);
+    }
+    let s = s.to_lowercase();
+    let pos = s.rfind('1').ok_or(Bech32Error::MissingSeparator)?;
+    if pos == 0 || pos + 7 > s.len() {
+        return Err(Bech32Error::InvalidLength);
+    }
+    let (hrp, rest) = (&s[..pos], &s[pos + 1..]);
+    if hrp.chars().any(|c| !(33..=126).contains(&(c as u32))) {
+        return Err(Bech32Error::InvalidHrp);
+    }
+
+    let mut data = Vec::with_capacity(rest.len());
+    for c in rest.chars() {
+        let d = CHARSET
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or(Bech32Error::InvalidCharacter(c))?;
+        data.push(d as u8);
+    }
+
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    let variant = match polymod(&values) {
+        c if c == Variant::Bech32.constant() => Variant::Bech32,
+        c if c == Variant::Bech32m.constant() => Variant::Bech32m,
+        _ => return Err(Bech32Error::InvalidChecksum),
+    };
+
+    data.truncate(data.len() - 6);
+    Ok((hrp.to_string(), data, variant))
+}
+
+/// Regroups a bit stream from `from`-bit to `to`-bit words
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Bech32Error> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let maxv: u32 = (1 << to) - 1;
+    let mut res = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return Err(Bech32Error::InvalidData);
+        }
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            res.push(((acc >> bits) & maxv) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            res.push(((acc << (to - bits)) & maxv) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & maxv) != 0 {
+        return Err(Bech32Error::InvalidData);
+    }
+    Ok(res)
+}
+
+/// Encodes a segwit output as a BIP173/BIP350 address
+pub fn encode_segwit(hrp: &str, version: u8, program: &[u8]) -> Result<String, Bech32Error> {
+    check_witness_program(version, program)?;
+    let variant = if version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    };
+    let mut data = vec![version];
+    data.extend(convert_bits(program, 8, 5, true)?);
+    encode(hrp, &data, variant)
+}
+
+/// Decodes a segwit address, returning the witness version and program
+pub fn decode_segwit(hrp: &str, address: &str) -> Result<(u8, Vec<u8>), Bech32Error> {
+    let (found_hrp, data, variant) = decode(address)?;
+    if found_hrp != hrp.to_lowercase() {
+        return Err(Bech32Error::InvalidHrp);
+    }
+    let (&version, data) = data.split_first().ok_or(Bech32Error::InvalidLength)?;
+    let program = convert_bits(data, 5, 8, false)?;
+    check_witness_program(version, &program)?;
+    let expected = if version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    };
+    if variant != expected {
+        return Err(Bech32Error::WrongVariant);
+    }
+    Ok((version, program))
+}
+
+fn check_witness_program(version: u8, program: &[u8]) -> Result<(), Bech32Error> {
+    if version > 16 {
+        return Err(Bech32Error::InvalidWitnessVersion(version));
+    }
+    if program.len() < 2 || program.len() > 40 || (version == 0 && program.len() != 20 && program.len() != 32) {
+        return Err(Bech32Error::InvalidProgramLength(program.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod bech32_tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_checksums() {
+        // BIP173 and BIP350 valid test vectors
+        for s in [
+            "A12UEL5L",
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+        ] {
+            assert_eq!(decode(s).unwrap().2, Variant::Bech32, "{}", s);
+        }
+        for s in [
+            "A1LQFN3A",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+        ] {
+            assert_eq!(decode(s).unwrap().2, Variant::Bech32m, "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_invalid_strings() {
+        assert_eq!(decode("pzry9x0s0muk"), Err(Bech32Error::MissingSeparator));
+        assert_eq!(decode("1pzry9x0s0muk"), Err(Bech32Error::InvalidLength));
+        assert_eq!(decode("x1b4n0q5v"), Err(Bech32Error::InvalidCharacter('b')));
+        assert_eq!(decode("A1G7SGD8"), Err(Bech32Error::InvalidChecksum));
+        assert_eq!(decode("a12UEL5L"), Err(Bech32Error::MixedCase));
+    }
+
+    #[test]
+    fn test_segwit_roundtrip() {
+        let program = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let address = encode_segwit("bc", 0, &program).unwrap();
+
+        assert_eq!(address, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        assert_eq!(decode_segwit("bc", &address).unwrap(), (0, program));
+    }
+
+    #[test]
+    fn test_segwit_taproot() {
+        let program = hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        let address = encode_segwit("bc", 1, &program).unwrap();
+
+        assert_eq!(
+            address,
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"
+        );
+        assert_eq!(decode_segwit("bc", &address).unwrap(), (1, program));
+    }
+
+    #[test]
+    fn test_segwit_wrong_variant() {
+        let program = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let mut data = vec![0];
+        data.extend(convert_bits(&program, 8, 5, true).unwrap());
+        let address = encode("bc", &data, Variant::Bech32m).unwrap();
+
+        assert_eq!(decode_segwit("bc", &address), Err(Bech32Error::WrongVariant));
+        assert_eq!(decode_segwit("tb", &address), Err(Bech32Error::InvalidHrp));
+    }
+}
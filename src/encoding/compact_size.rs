@@ -0,0 +1,182 @@
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+/// Bitcoin Core refuses to deserialize sizes above this (MAX_SIZE), which
+/// keeps a bogus length prefix from triggering a huge allocation
+pub const MAX_SIZE: u64 = 0x0200_0000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompactSizeError {
+    UnexpectedEof,
+    Io(io::ErrorKind),
+    /// The value was encoded with a longer form than needed
+    NonCanonical(u64),
+    TooLarge(u64),
+}
+
+impl Display for CompactSizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompactSizeError::UnexpectedEof => write!(f, "compact size is truncated"),
+            CompactSizeError::Io(kind) => write!(f, "i/o error reading compact size: {}", kind),
+            CompactSizeError::NonCanonical(n) => write!(f, "non-canonical compact size encoding of {}", n),
+            CompactSizeError::TooLarge(n) => write!(f, "compact size {} exceeds {}", n, MAX_SIZE),
+        }
+    }
+}
+
+impl std::error::Error for CompactSizeError {}
+
+impl From<io::Error> for CompactSizeError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => CompactSizeError::UnexpectedEof,
+            kind => CompactSizeError::Io(kind),
+        }
+    }
+}
+
+/// Number of bytes `n` takes: 1 below 0xfd, then 3, 5 or 9 bytes with a
+/// 0xfd/0xfe/0xff marker followed by a little-endian u16/u32/u64
+pub fn encoded_len(n: u64) -> usize {
+    match n {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+pub fn encode(n: u64) -> Vec<u8> {
+    let mut res = Vec::with_capacity(encoded_len(n));
+    write(&mut res, n).unwrap();
+    res
+}
+
+/// Writes `n`, returning the number of bytes written
+pub fn write<W: Write>(writer: &mut W, n: u64) -> io::Result<usize> {
+    match n {
+        0..=0xfc => writer.write_all(&[n as u8])?,
+        0xfd..=0xffff => {
+            writer.write_all(&[0xfd])?;
+            writer.write_all(&(n as u16).to_le_bytes())?;
+        }
+        0x1_0000..=0xffff_ffff => {
+            writer.write_all(&[0xfe])?;
+            writer.write_all(&(n as u32).to_le_bytes())?;
+        }
+        _ => {
+            writer.write_all(&[0xff])?;
+            writer.write_all(&n.to_le_bytes())?;
+        }
+    }
+    Ok(encoded_len(n))
+}
+
+/// Reads one value, rejecting encodings that aren't the shortest form
+pub fn read<R: Read>(reader: &mut R) -> Result<u64, CompactSizeError> {
+    let mut marker = [0_u8; 1];
+    reader.read_exact(&mut marker)?;
+    let n = match marker[0] {
+        0xfd => {
+            let mut buf = [0_u8; 2];
+            reader.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf) as u64
+        }
+        0xfe => {
+            let mut buf = [0_u8; 4];
+            reader.read_exact(&mut buf)?;
+            u32::from_le_bytes(buf) as u64
+        }
+        0xff => {
+            let mut buf = [0_u8; 8];
+            reader.read_exact(&mut buf)?;
+            u64::from_le_bytes(buf)
+        }
+        n => return Ok(n as u64),
+    };
+    if encoded_len(n) != 1 + (1 << (marker[0] - 0xfc)) {
+        return Err(CompactSizeError::NonCanonical(n));
+    }
+    Ok(n)
+}
+
+/// Like `read`, but for lengths and counts: values above `MAX_SIZE` are
+/// rejected
+pub fn read_size<R: Read>(reader: &mut R) -> Result<usize, CompactSizeError> {
+    let n = read(reader)?;
+    if n > MAX_SIZE {
+        return Err(CompactSizeError::TooLarge(n));
+    }
+    Ok(n as usize)
+}
+
+/// Decodes a value from the front of `bytes`, returning it with the number
+/// of bytes consumed
+pub fn decode(bytes: &[u8]) -> Result<(u64, usize), CompactSizeError> {
+    let mut cursor = bytes;
+    let n = read(&mut cursor)?;
+    Ok((n, bytes.len() - cursor.len()))
+}
+
+#[cfg(test)]
+mod compact_size_tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let cases: [(u64, &str); 8] = [
+            (0, "00"),
+            (0xfc, "fc"),
+            (0xfd, "fdfd00"),
+            (0xffff, "fdffff"),
+            (0x1_0000, "fe00000100"),
+            (0xffff_ffff, "feffffffff"),
+            (0x1_0000_0000, "ff0000000001000000"),
+            (u64::MAX, "ffffffffffffffffff"),
+        ];
+        for (n, encoded) in cases {
+            assert_eq!(hex::encode(encode(n)), encoded);
+            assert_eq!(encoded_len(n), encoded.len() / 2);
+            assert_eq!(decode(&hex::decode(encoded).unwrap()).unwrap(), (n, encoded.len() / 2));
+        }
+    }
+
+    #[test]
+    fn test_non_canonical() {
+        for encoded in [
+            "fd0000",
+            "fdfc00",
+            "fe0000000000",
+            "feffff0000",
+            "ff0000000000000000",
+            "ffffffffff00000000",
+        ] {
+            let bytes = hex::decode(encoded).unwrap();
+            assert!(
+                matches!(decode(&bytes), Err(CompactSizeError::NonCanonical(_))),
+                "{}",
+                encoded
+            );
+        }
+    }
+
+    #[test]
+    fn test_streaming() {
+        let bytes = hex::decode("01fd0001fe00000100ff").unwrap();
+        let mut reader = bytes.as_slice();
+        assert_eq!(read(&mut reader), Ok(1));
+        assert_eq!(read(&mut reader), Ok(0x100));
+        assert_eq!(read(&mut reader), Ok(0x1_0000));
+        assert_eq!(read(&mut reader), Err(CompactSizeError::UnexpectedEof));
+
+        assert_eq!(
+            read_size(&mut hex::decode("fe00000002").unwrap().as_slice()),
+            Ok(MAX_SIZE as usize)
+        );
+        assert_eq!(
+            read_size(&mut hex::decode("fe01000002").unwrap().as_slice()),
+            Err(CompactSizeError::TooLarge(MAX_SIZE + 1))
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::io::{self, Read, Write};
+
+use crate::encoding::compact_size;
+use crate::encoding::decode::{decode_slice, Context, DecodeError};
+use crate::tx::{read_array, TxError};
+
+/// Types with a consensus encoding: what goes on the wire, into blocks and
+/// under hashes. Writes to any `io::Write`, so sockets and files work as
+/// well as buffers
+pub trait ConsensusEncode {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    fn consensus_serialize(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.consensus_encode(&mut res).expect("writing to a Vec can't fail");
+        res
+    }
+}
+
+/// Types that read themselves off a stream of their consensus encoding,
+/// consuming exactly their own bytes so more can follow. `Error` is the
+/// kind of error, which comes with where it happened
+pub trait ConsensusDecode: Sized {
+    type Error: From<TxError>;
+
+    fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError<Self::Error>>;
+
+    /// Decodes all of `bytes`, rejecting trailing bytes
+    fn consensus_deserialize(bytes: &[u8]) -> Result<Self, DecodeError<Self::Error>> {
+        decode_slice(bytes, Self::consensus_decode)
+    }
+}
+
+/// Implements both traits for a type with `write` and `parse` methods
+macro_rules! impl_consensus_codec {
+    ($type:ty, $error:ty) => {
+        impl $crate::encoding::consensus::ConsensusEncode for $type {
+            fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                self.write(writer)
+            }
+        }
+
+        impl $crate::encoding::consensus::ConsensusDecode for $type {
+            type Error = $error;
+
+            fn consensus_decode<R: std::io::Read>(
+                reader: &mut R,
+            ) -> Result<Self, $crate::encoding::decode::DecodeError<$error>> {
+                Self::parse(reader)
+            }
+        }
+    };
+}
+pub(crate) use impl_consensus_codec;
+
+/// A CompactSize ("varint") on its own, as used for counts and lengths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactSize(pub u64);
+
+impl ConsensusEncode for CompactSize {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        compact_size::write(writer, self.0).map(|_| ())
+    }
+}
+
+impl ConsensusDecode for CompactSize {
+    type Error = TxError;
+
+    fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(Self(compact_size::read(reader).map_err(TxError::from)?))
+    }
+}
+
+macro_rules! impl_int_codec {
+    ($($int:ty),*) => {
+        $(
+            impl ConsensusEncode for $int {
+                fn consensus_encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+            }
+
+            impl ConsensusDecode for $int {
+                type Error = TxError;
+
+                fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+                    Ok(<$int>::from_le_bytes(read_array(reader)?))
+                }
+            }
+        )*
+    };
+}
+
+impl_int_codec!(u8, u16, u32, u64, i32, i64);
+
+impl ConsensusEncode for bool {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[*self as u8])
+    }
+}
+
+impl ConsensusDecode for bool {
+    type Error = TxError;
+
+    fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(u8::consensus_decode(reader)? != 0)
+    }
+}
+
+/// Hashes and other fixed-size fields, written as is
+impl<const N: usize> ConsensusEncode for [u8; N] {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self)
+    }
+}
+
+impl<const N: usize> ConsensusDecode for [u8; N] {
+    type Error = TxError;
+
+    fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(read_array(reader)?)
+    }
+}
+
+/// A CompactSize count followed by the elements, so `Vec<u8>` is the
+/// length-prefixed byte string
+impl<T: ConsensusEncode> ConsensusEncode for Vec<T> {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        compact_size::write(writer, self.len() as u64)?;
+        for item in self {
+            item.consensus_encode(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ConsensusDecode> ConsensusDecode for Vec<T> {
+    type Error = T::Error;
+
+    fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError<T::Error>> {
+        // read_size caps the count, and nothing is reserved up front, so a
+        // bogus length runs out of input instead of memory
+        let count = compact_size::read_size(reader).map_err(|e| T::Error::from(TxError::from(e)))?;
+        (0..count)
+            .map(|i| T::consensus_decode(reader).context(format_args!("[{}]", i)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod consensus_tests {
+    use super::*;
+    use crate::block::{Block, BlockHeader};
+    use crate::network::message::{NetworkMessage, RawMessage};
+    use crate::tx::Transaction;
+
+    /// Hands out one byte per read, like a slow socket
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((&byte, rest)), Some(slot)) => {
+                    *slot = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert_eq!(CompactSize(0xfd).consensus_serialize(), [0xfd, 0xfd, 0x00]);
+        assert_eq!(
+            CompactSize::consensus_deserialize(&[0xfe, 1, 0, 0, 0]).map_err(DecodeError::into_kind),
+            Err(TxError::CompactSize(compact_size::CompactSizeError::NonCanonical(1)))
+        );
+        assert_eq!(0x01020304_u32.consensus_serialize(), [4, 3, 2, 1]);
+        assert_eq!(i64::consensus_deserialize(&(-2_i64).to_le_bytes()), Ok(-2));
+        assert_eq!(vec![0xab_u8; 3].consensus_serialize(), [3, 0xab, 0xab, 0xab]);
+        let hashes = vec![[1_u8; 32], [2; 32]];
+        assert_eq!(
+            Vec::<[u8; 32]>::consensus_deserialize(&hashes.consensus_serialize()),
+            Ok(hashes)
+        );
+        assert_eq!(
+            u32::consensus_deserialize(&[1, 2, 3]).unwrap_err().into_kind(),
+            TxError::Truncated
+        );
+        assert_eq!(
+            u8::consensus_deserialize(&[1, 2]).unwrap_err().into_kind(),
+            TxError::TrailingBytes(1)
+        );
+        // a huge count fails on the missing elements, not on allocation
+        let err = Vec::<u64>::consensus_deserialize(&[0xfe, 0, 0, 0, 1, 7, 0, 0, 0, 0, 0, 0, 0]).unwrap_err();
+        assert_eq!(err.to_string(), "transaction is truncated at byte 13 in [1]");
+    }
+
+    #[test]
+    fn test_stream_decoding() {
+        let tx = Transaction::from_hex(
+            "0100000001813f79011acb80925dfe69b3def355fe914bd1d96a3f5f71bf8303c6a989c7d1000000006b483045022100ed81ff\
+             192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f678\
+             01c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278afeffffff\
+             02a135ef01000000001976a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac99c39800000000001976a9141c4bc762\
+             dd5423e332166702cb75f40df79fea1288ac19430600",
+        )
+        .unwrap();
+        let block = Block {
+            header: BlockHeader::genesis(crate::address::Network::Regtest),
+            txdata: vec![tx.clone()],
+        };
+
+        // back to back on one stream, read a byte at a time
+        let mut bytes = tx.consensus_serialize();
+        bytes.extend(block.consensus_serialize());
+        let mut reader = Trickle(&bytes);
+        assert_eq!(Transaction::consensus_decode(&mut reader), Ok(tx.clone()));
+        assert_eq!(Block::consensus_decode(&mut reader), Ok(block));
+        assert!(reader.0.is_empty());
+
+        let raw = RawMessage::new([0xfa, 0xbf, 0xb5, 0xda], NetworkMessage::Ping(7));
+        assert_eq!(RawMessage::consensus_deserialize(&raw.consensus_serialize()), Ok(raw));
+        let trailing = bytes.len() - tx.serialize().len();
+        let err = Transaction::consensus_deserialize(&bytes).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&TxError::TrailingBytes(trailing), Some(tx.serialize().len()))
+        );
+    }
+}
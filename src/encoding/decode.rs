@@ -0,0 +1,191 @@
+use std::fmt::Display;
+use std::io::{self, Read};
+
+use crate::tx::TxError;
+
+// Decoders of binary formats report where they failed along with what
+// went wrong: the byte offset they had read up to, and the fields they
+// were inside, outermost first. Stream parsers name the fields as errors
+// pass through them; the byte-level entry points, which know where the
+// input starts, fill in the offset. So "transaction is truncated at byte
+// 87 in inputs[1].script_sig" points at the bytes to look at without a
+// hex editor
+
+/// A decoding error of kind `E` and where it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError<E> {
+    kind: E,
+    offset: Option<usize>,
+    path: Vec<String>,
+}
+
+impl<E> DecodeError<E> {
+    pub fn new(kind: E) -> Self {
+        Self {
+            kind,
+            offset: None,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> &E {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> E {
+        self.kind
+    }
+
+    /// How many bytes the decoder had read when it failed. Unknown for
+    /// errors straight from a stream parser
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// The fields being decoded, outermost first
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// Records `offset` unless an inner decoder already did
+    pub fn at(mut self, offset: usize) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    /// For an error from decoding a part of the input that starts at
+    /// `start`, makes a known offset count from the start of the whole
+    pub fn shift(mut self, start: usize) -> Self {
+        if let Some(offset) = &mut self.offset {
+            *offset += start;
+        }
+        self
+    }
+
+    /// Adds the field an error passed through, as the next outer one
+    pub fn within(mut self, field: impl Display) -> Self {
+        self.path.insert(0, field.to_string());
+        self
+    }
+
+    /// Wraps the kind into an outer decoder's, keeping the location
+    pub fn map<F>(self, f: impl FnOnce(E) -> F) -> DecodeError<F> {
+        DecodeError {
+            kind: f(self.kind),
+            offset: self.offset,
+            path: self.path,
+        }
+    }
+}
+
+impl<E> From<E> for DecodeError<E> {
+    fn from(kind: E) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl<E: Display> Display for DecodeError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        for (i, field) in self.path.iter().enumerate() {
+            // indices attach to the field before them: inputs[1].script_sig
+            match (i, field.starts_with('[')) {
+                (0, _) => write!(f, " in {}", field)?,
+                (_, true) => write!(f, "{}", field)?,
+                _ => write!(f, ".{}", field)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for DecodeError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.kind)
+    }
+}
+
+/// Names the field a result came from, e.g. `read_u32(r).context("version")?`.
+/// Works on plain error kinds and on errors that already have a location
+pub trait Context<T, E> {
+    fn context(self, field: impl Display) -> Result<T, DecodeError<E>>;
+}
+
+impl<T, E, X: Into<DecodeError<E>>> Context<T, E> for Result<T, X> {
+    fn context(self, field: impl Display) -> Result<T, DecodeError<E>> {
+        self.map_err(|e| e.into().within(field))
+    }
+}
+
+/// Decodes all of `bytes`, locating errors by how far `parse` got and
+/// rejecting trailing bytes
+pub fn decode_slice<'a, T, E: From<TxError>>(
+    bytes: &'a [u8],
+    parse: impl FnOnce(&mut &'a [u8]) -> Result<T, DecodeError<E>>,
+) -> Result<T, DecodeError<E>> {
+    let mut reader = bytes;
+    let res = parse(&mut reader);
+    let offset = bytes.len() - reader.len();
+    let value = res.map_err(|e| e.at(offset))?;
+    if !reader.is_empty() {
+        return Err(DecodeError::new(TxError::TrailingBytes(reader.len()).into()).at(offset));
+    }
+    Ok(value)
+}
+
+/// A reader that counts the bytes read through it, so errors on streams
+/// can be located too
+pub struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod decode_tests {
+    use super::*;
+    use crate::tx::read_u32;
+
+    fn pair(reader: &mut &[u8]) -> Result<(u32, u32), DecodeError<TxError>> {
+        Ok((read_u32(reader).context("first")?, read_u32(reader).context("second")?))
+    }
+
+    #[test]
+    fn test_location() {
+        let err: DecodeError<TxError> = decode_slice(&[1, 0, 0, 0, 2, 0], |r| pair(r).context("pair")).unwrap_err();
+        assert_eq!(*err.kind(), TxError::Truncated);
+        assert_eq!(err.offset(), Some(6));
+        assert_eq!(err.path(), ["pair", "second"]);
+        assert_eq!(err.to_string(), "transaction is truncated at byte 6 in pair.second");
+
+        let err = decode_slice(&[0; 9], pair).unwrap_err();
+        assert_eq!(err.offset(), Some(8));
+        assert_eq!(err.into_kind(), TxError::TrailingBytes(1));
+        assert_eq!(decode_slice(&[0; 8], pair), Ok((0, 0)));
+
+        // an inner offset stays, and shifts into the outer input
+        let err = DecodeError::new(TxError::Truncated).at(3).at(10).shift(100);
+        assert_eq!(err.offset(), Some(103));
+        assert_eq!(DecodeError::new(TxError::Truncated).shift(100).offset(), None);
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+
+// `{:x}` and `{:X}` for types with a canonical byte encoding, so debugging
+// output shows keys, signatures and field elements the way block explorers
+// and other tools do rather than as decimal integers. Like the integer
+// impls, `{:#x}` adds a 0x prefix and widths pad on the left
+
+pub fn fmt_hex(bytes: &[u8], upper: bool, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let hex = if upper {
+        hex::encode_upper(bytes)
+    } else {
+        hex::encode(bytes)
+    };
+    f.pad_integral(true, "0x", &hex)
+}
+
+/// Decimal while it fits in 64 bits, like the toy fields' elements, and
+/// 0x hex beyond, like secp256k1's
+pub fn readable(n: &num_bigint::BigInt) -> String {
+    if n.bits() <= 64 {
+        n.to_string()
+    } else {
+        format!("{:#x}", n)
+    }
+}
+
+/// LowerHex and UpperHex through a byte encoding: `$encode` turns a
+/// `&$type` into bytes
+macro_rules! impl_hex_fmt {
+    ($type:ty, $encode:expr) => {
+        impl std::fmt::LowerHex for $type {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let encode: fn(&$type) -> Vec<u8> = $encode;
+                $crate::encoding::hex_fmt::fmt_hex(&encode(self), false, f)
+            }
+        }
+
+        impl std::fmt::UpperHex for $type {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let encode: fn(&$type) -> Vec<u8> = $encode;
+                $crate::encoding::hex_fmt::fmt_hex(&encode(self), true, f)
+            }
+        }
+    };
+}
+pub(crate) use impl_hex_fmt;
+
+#[cfg(test)]
+mod hex_fmt_tests {
+    struct Bytes(Vec<u8>);
+
+    impl_hex_fmt!(Bytes, |b| b.0.clone());
+
+    #[test]
+    fn test_flags() {
+        let b = Bytes(vec![0x00, 0xab, 0x0c]);
+        assert_eq!(format!("{:x}", b), "00ab0c");
+        assert_eq!(format!("{:X}", b), "00AB0C");
+        assert_eq!(format!("{:#x}", b), "0x00ab0c");
+        assert_eq!(format!("{:>8x}", b), "  00ab0c");
+        assert_eq!(format!("{:#010x}", b), "0x0000ab0c");
+        assert_eq!(format!("{:x}", Bytes(vec![])), "");
+    }
+}
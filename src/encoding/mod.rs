@@ -0,0 +1,10 @@
+pub mod base58;
+pub mod base64;
+pub mod bech32;
+pub mod compact_size;
+pub mod consensus;
+pub mod decode;
+pub mod hex_fmt;
+pub mod rlp;
+#[cfg(feature = "serde")]
+pub mod serde_codec;
@@ -0,0 +1,216 @@
+use std::fmt::Display;
+
+/// Ethereum's Recursive Length Prefix serialization. Everything is a byte
+/// string or a list of items; integers are big-endian byte strings with no
+/// leading zeros, so zero is the empty string
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rlp {
+    Bytes(Vec<u8>),
+    List(Vec<Rlp>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RlpError {
+    Truncated,
+    /// A length prefix longer than needed, or a single byte below 0x80
+    /// wrapped in a string prefix
+    NonCanonical,
+    TrailingBytes(usize),
+    /// Expected a byte string and found a list, or the reverse
+    UnexpectedType,
+    /// An integer with leading zeros or too wide for the target type
+    InvalidInteger,
+}
+
+impl Display for RlpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RlpError::Truncated => write!(f, "RLP item is truncated"),
+            RlpError::NonCanonical => write!(f, "non-canonical RLP length"),
+            RlpError::TrailingBytes(n) => write!(f, "{} trailing bytes after RLP item", n),
+            RlpError::UnexpectedType => write!(f, "unexpected RLP item type"),
+            RlpError::InvalidInteger => write!(f, "invalid RLP integer"),
+        }
+    }
+}
+
+impl std::error::Error for RlpError {}
+
+impl Rlp {
+    /// An unsigned integer in its canonical form
+    pub fn uint(n: u128) -> Self {
+        let bytes = n.to_be_bytes();
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+        Rlp::Bytes(bytes[start..].to_vec())
+    }
+
+    pub fn bytes(bytes: &[u8]) -> Self {
+        Rlp::Bytes(bytes.to_vec())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write(&mut res);
+        res
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Rlp::Bytes(bytes) if bytes.len() == 1 && bytes[0] < 0x80 => out.push(bytes[0]),
+            Rlp::Bytes(bytes) => {
+                write_header(out, 0x80, bytes.len());
+                out.extend_from_slice(bytes);
+            }
+            Rlp::List(items) => {
+                let mut payload = Vec::new();
+                for item in items {
+                    item.write(&mut payload);
+                }
+                write_header(out, 0xc0, payload.len());
+                out.extend_from_slice(&payload);
+            }
+        }
+    }
+
+    /// Decodes exactly one item, rejecting trailing bytes
+    pub fn decode(bytes: &[u8]) -> Result<Self, RlpError> {
+        let (item, rest) = Self::decode_prefix(bytes)?;
+        if !rest.is_empty() {
+            return Err(RlpError::TrailingBytes(rest.len()));
+        }
+        Ok(item)
+    }
+
+    fn decode_prefix(bytes: &[u8]) -> Result<(Self, &[u8]), RlpError> {
+        let (&prefix, rest) = bytes.split_first().ok_or(RlpError::Truncated)?;
+        let (is_list, offset) = match prefix {
+            0x00..=0x7f => return Ok((Rlp::Bytes(vec![prefix]), rest)),
+            0x80..=0xbf => (false, 0x80),
+            0xc0..=0xff => (true, 0xc0),
+        };
+        let (len, rest) = match prefix - offset {
+            short @ 0..=55 => (short as usize, rest),
+            long => {
+                let width = (long - 55) as usize;
+                if rest.len() < width {
+                    return Err(RlpError::Truncated);
+                }
+                let (len_bytes, rest) = rest.split_at(width);
+                if len_bytes[0] == 0 || width > 8 {
+                    return Err(RlpError::NonCanonical);
+                }
+                let len = len_bytes.iter().fold(0_u64, |acc, &b| acc << 8 | b as u64);
+                if len <= 55 {
+                    return Err(RlpError::NonCanonical);
+                }
+                (usize::try_from(len).map_err(|_| RlpError::Truncated)?, rest)
+            }
+        };
+        if rest.len() < len {
+            return Err(RlpError::Truncated);
+        }
+        let (payload, rest) = rest.split_at(len);
+        if !is_list {
+            if len == 1 && payload[0] < 0x80 {
+                return Err(RlpError::NonCanonical);
+            }
+            return Ok((Rlp::Bytes(payload.to_vec()), rest));
+        }
+        let mut items = Vec::new();
+        let mut payload = payload;
+        while !payload.is_empty() {
+            let (item, remaining) = Self::decode_prefix(payload)?;
+            items.push(item);
+            payload = remaining;
+        }
+        Ok((Rlp::List(items), rest))
+    }
+
+    pub fn as_bytes(&self) -> Result<&[u8], RlpError> {
+        match self {
+            Rlp::Bytes(bytes) => Ok(bytes),
+            Rlp::List(_) => Err(RlpError::UnexpectedType),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&[Rlp], RlpError> {
+        match self {
+            Rlp::List(items) => Ok(items),
+            Rlp::Bytes(_) => Err(RlpError::UnexpectedType),
+        }
+    }
+
+    pub fn as_uint(&self) -> Result<u128, RlpError> {
+        let bytes = self.as_bytes()?;
+        if bytes.len() > 16 || bytes.first() == Some(&0) {
+            return Err(RlpError::InvalidInteger);
+        }
+        Ok(bytes.iter().fold(0, |acc, &b| acc << 8 | b as u128))
+    }
+}
+
+/// A short prefix `offset + len` up to 55 bytes, otherwise
+/// `offset + 55 + width` followed by the big-endian length
+fn write_header(out: &mut Vec<u8>, offset: u8, len: usize) {
+    if len <= 55 {
+        out.push(offset + len as u8);
+        return;
+    }
+    let len_bytes = (len as u64).to_be_bytes();
+    let start = len_bytes.iter().position(|&b| b != 0).unwrap();
+    out.push(offset + 55 + (8 - start) as u8);
+    out.extend_from_slice(&len_bytes[start..]);
+}
+
+#[cfg(test)]
+mod rlp_tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        // examples from the Ethereum wiki
+        assert_eq!(Rlp::bytes(b"dog").encode(), b"\x83dog");
+        assert_eq!(
+            Rlp::List(vec![Rlp::bytes(b"cat"), Rlp::bytes(b"dog")]).encode(),
+            b"\xc8\x83cat\x83dog"
+        );
+        assert_eq!(Rlp::bytes(b"").encode(), [0x80]);
+        assert_eq!(Rlp::List(Vec::new()).encode(), [0xc0]);
+        assert_eq!(Rlp::uint(0).encode(), [0x80]);
+        assert_eq!(Rlp::uint(15).encode(), [0x0f]);
+        assert_eq!(Rlp::uint(1024).encode(), [0x82, 0x04, 0x00]);
+        let lorem = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        assert_eq!(Rlp::bytes(lorem).encode()[..2], [0xb8, 0x38]);
+        // the set-theoretical representation of three
+        let empty = || Rlp::List(Vec::new());
+        let three = Rlp::List(vec![
+            empty(),
+            Rlp::List(vec![empty()]),
+            Rlp::List(vec![empty(), Rlp::List(vec![empty()])]),
+        ]);
+        assert_eq!(three.encode(), [0xc7, 0xc0, 0xc1, 0xc0, 0xc3, 0xc0, 0xc1, 0xc0]);
+
+        for item in [
+            three,
+            Rlp::bytes(lorem),
+            Rlp::uint(1024),
+            Rlp::List(vec![Rlp::bytes(&[0xab; 300])]),
+        ] {
+            assert_eq!(Rlp::decode(&item.encode()), Ok(item));
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(Rlp::decode(&[0x83, b'd', b'o']), Err(RlpError::Truncated));
+        assert_eq!(Rlp::decode(&[0x81, 0x05]), Err(RlpError::NonCanonical));
+        assert_eq!(Rlp::decode(&[0xb8, 0x02, 0xaa, 0xbb]), Err(RlpError::NonCanonical));
+        assert_eq!(Rlp::decode(&[0x05, 0x06]), Err(RlpError::TrailingBytes(1)));
+        assert_eq!(Rlp::decode(&[0xc2, 0x83, 0x01]), Err(RlpError::Truncated));
+        assert_eq!(
+            Rlp::decode(&[0x82, 0x00, 0x01]).unwrap().as_uint(),
+            Err(RlpError::InvalidInteger)
+        );
+        assert_eq!(Rlp::decode(&[0xc0]).unwrap().as_bytes(), Err(RlpError::UnexpectedType));
+    }
+}
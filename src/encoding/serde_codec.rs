@@ -0,0 +1,175 @@
+use std::fmt;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+
+// Byte-encoded types serialize as hex in human-readable formats like JSON,
+// which is how they appear everywhere else, and as raw bytes in binary
+// formats, which would only double the size with hex
+
+pub fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&hex::encode(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+pub fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(HexVisitor)
+    } else {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+struct HexVisitor;
+
+impl Visitor<'_> for HexVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a hex string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<u8>, E> {
+        hex::decode(v).map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+
+    // formats without a bytes type hand them over as a sequence
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut res = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            res.push(byte);
+        }
+        Ok(res)
+    }
+}
+
+/// Serialize and Deserialize through a byte encoding: `$encode` turns a
+/// `&$type` into bytes and `$decode` parses them back, with any error
+/// that displays
+macro_rules! impl_serde_bytes {
+    ($type:ty, $encode:expr, $decode:expr) => {
+        impl serde::Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let encode: fn(&$type) -> Vec<u8> = $encode;
+                $crate::encoding::serde_codec::serialize_bytes(&encode(self), serializer)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let bytes = $crate::encoding::serde_codec::deserialize_bytes(deserializer)?;
+                $decode(&bytes[..]).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+pub(crate) use impl_serde_bytes;
+
+/// Serialize and Deserialize as a string in every format, for types whose
+/// canonical form is text: `Display` one way and `$parse` the other
+macro_rules! impl_serde_string {
+    ($type:ty, $parse:expr) => {
+        impl serde::Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+                $parse(&s).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+pub(crate) use impl_serde_string;
+
+#[cfg(test)]
+mod serde_codec_tests {
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Compact, Configure, Readable, Token};
+
+    use crate::bip32::ExtendedPrivateKey;
+    use crate::curves::secp256k1::ecc::{PrivateKey, PublicKey, Signature};
+    use crate::tx::Transaction;
+
+    const PUBLIC: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const TX: &str = "0100000001a15d57094aa7a21a28cb20b59aab8fc7d1149a3bdbcddba9c622e4f5f6a99ece\
+        010000006c493046022100f93bb0e7d8db7bd46e40132d1f8242026e045f03a0efe71bbb8e3f475e970d790221\
+        009337cd7f1f929f00cc6ff01f03729b069a7c21b59b1736ddfee5db5946c5da8c0121033b9b137ee87d5a812d\
+        6f506efdd37f0affa7ffc310711c06c7f3e097c9447c52ffffffff0100e1f505000000001976a9140389035a92\
+        25b3839e2bbf32d826a1e222031fd888ac00000000";
+    const XPRV: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+
+    // serde_test's tokens borrow for 'static
+    fn leak<T: ?Sized>(value: Box<T>) -> &'static T {
+        Box::leak(value)
+    }
+
+    #[test]
+    fn test_keys_and_signatures() {
+        let public = PublicKey::parse(&hex::decode(PUBLIC).unwrap()).unwrap();
+        assert_tokens(&public.clone().readable(), &[Token::Str(PUBLIC)]);
+        assert_tokens(
+            &public.compact(),
+            &[Token::Bytes(leak(hex::decode(PUBLIC).unwrap().into()))],
+        );
+
+        let signature = PrivateKey::from_bytes(&[1; 32]).unwrap().sign(&7_u8.into());
+        let der = signature.der();
+        assert_tokens(
+            &signature.clone().readable(),
+            &[Token::Str(leak(hex::encode(&der).into()))],
+        );
+        assert_tokens(&signature.compact(), &[Token::Bytes(leak(der.into()))]);
+
+        let xprv = ExtendedPrivateKey::parse(XPRV).unwrap();
+        assert_tokens(&xprv.clone().readable(), &[Token::Str(XPRV)]);
+        assert_tokens(&xprv.clone().compact(), &[Token::Str(XPRV)]);
+        let xpub = xprv.to_public().unwrap();
+        assert_tokens(&xpub.clone().readable(), &[Token::Str(leak(xpub.to_string().into()))]);
+
+        assert_de_tokens_error::<Compact<Signature>>(
+            &[Token::Bytes(&[0x30, 0x00])],
+            "DER signature is truncated at byte 2 in r",
+        );
+        assert_de_tokens_error::<Readable<PublicKey>>(
+            &[Token::Str("zz")],
+            "invalid value: string \"zz\", expected a hex string",
+        );
+    }
+
+    #[test]
+    fn test_transaction() {
+        let tx = Transaction::from_hex(TX).unwrap();
+        let bytes = hex::decode(TX).unwrap();
+        assert_tokens(&tx.clone().readable(), &[Token::Str(TX)]);
+        assert_tokens(&tx.clone().compact(), &[Token::Bytes(leak(bytes.clone().into()))]);
+        // formats without a bytes type give a sequence of numbers
+        let mut tokens = vec![Token::Seq { len: Some(bytes.len()) }];
+        tokens.extend(bytes.iter().map(|&b| Token::U8(b)));
+        tokens.push(Token::SeqEnd);
+        assert_de_tokens(&tx.compact(), &tokens);
+    }
+}
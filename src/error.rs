@@ -0,0 +1,165 @@
+use std::fmt::Display;
+
+use crate::encoding::decode::DecodeError;
+
+/// Generates the enum with one variant per module error, along with the
+/// `From` impls that let `?` convert into it and the `Display` and
+/// `source` that pass through to the wrapped error
+macro_rules! crate_error {
+    ($($(#[$meta:meta])* $variant:ident($error:ty),)*) => {
+        /// Any error the crate returns. Each module keeps its own error
+        /// enum, which says exactly what can go wrong there; this one
+        /// wraps them all, so an application that calls into several
+        /// modules can use `?` with a single error type. Non-exhaustive,
+        /// since new modules bring new errors
+        #[derive(Debug, Clone, PartialEq)]
+        #[non_exhaustive]
+        pub enum Error {
+            $($(#[$meta])* $variant($error),)*
+        }
+
+        impl Display for Error {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($(#[$meta])* Error::$variant(e) => write!(f, "{}", e),)*
+                }
+            }
+        }
+
+        impl std::error::Error for Error {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                match self {
+                    $($(#[$meta])* Error::$variant(e) => Some(e),)*
+                }
+            }
+        }
+
+        $(
+            $(#[$meta])*
+            impl From<$error> for Error {
+                fn from(e: $error) -> Self {
+                    Error::$variant(e)
+                }
+            }
+        )*
+    };
+}
+
+crate_error! {
+    Field(crate::arithmetic::field_element::FieldError),
+    Point(crate::curves::ec_point::PointError),
+    Filter(crate::bip158::FilterError),
+    Bip21(crate::bip21::Bip21Error),
+    Bip32(crate::bip32::Bip32Error),
+    Bip47(crate::bip47::Bip47Error),
+    Block(crate::block::BlockError),
+    Bloom(DecodeError<crate::bloom::BloomError>),
+    Builder(crate::builder::BuilderError),
+    RangeProof(crate::bulletproofs::RangeProofError),
+    Utxo(crate::chain::UtxoError),
+    Coinbase(crate::coinbase::CoinbaseError),
+    Aead(crate::crypto::aead::AeadError),
+    Ecies(crate::crypto::ecies::EciesError),
+    Drbg(crate::crypto::hmac_drbg::DrbgError),
+    Kdf(crate::crypto::kdf::KdfError),
+    Keystore(crate::crypto::keystore::KeystoreError),
+    Poseidon(crate::crypto::poseidon::PoseidonError),
+    Ssh(crate::crypto::ssh::SshError),
+    Descriptor(crate::descriptor::DescriptorError),
+    Dkg(crate::dkg::DkgError),
+    Key(crate::curves::secp256k1::ecc::KeyError),
+    Signature(DecodeError<crate::curves::secp256k1::ecc::SignatureError>),
+    Eip712(crate::eip712::Eip712Error),
+    Electrum(crate::electrum::ElectrumError),
+    Base58(crate::encoding::base58::Base58Error),
+    Base64(crate::encoding::base64::Base64Error),
+    Bech32(crate::encoding::bech32::Bech32Error),
+    CompactSize(crate::encoding::compact_size::CompactSizeError),
+    Rlp(crate::encoding::rlp::RlpError),
+    Ethereum(crate::ethereum::EthereumError),
+    Groth16(crate::groth16::Groth16Error),
+    Interpreter(crate::interpreter::InterpreterError),
+    Verify(crate::interpreter::VerifyError),
+    Kzg(crate::crypto::kzg::KzgError),
+    Merkle(crate::merkle::MerkleError),
+    Mining(crate::mining::MiningError),
+    Multisig(crate::multisig::MultisigError),
+    Chain(crate::network::chain::ChainError),
+    Message(DecodeError<crate::network::message::MessageError>),
+    Peer(crate::network::peer::PeerError),
+    Nostr(crate::nostr::NostrError),
+    #[cfg(feature = "openssl")]
+    Openssl(crate::openssl::OpensslError),
+    Paillier(crate::paillier::PaillierError),
+    Pow(crate::pow::PowError),
+    Psbt(DecodeError<crate::psbt::PsbtError>),
+    R1cs(crate::r1cs::R1csError),
+    #[cfg(feature = "rpc")]
+    Rpc(crate::rpc::RpcError),
+    Rsa(crate::rsa::RsaError),
+    Asm(crate::script::AsmError),
+    OpReturn(crate::script::OpReturnError),
+    Script(crate::script::ScriptError),
+    Sighash(crate::sighash::SighashError),
+    Sigma(crate::sigma::SigmaError),
+    Sign(crate::sign::SignError),
+    SilentPayment(crate::silent_payments::SilentPaymentError),
+    Taproot(crate::taproot::TaprootError),
+    Timelock(crate::timelock::TimelockError),
+    Tx(DecodeError<crate::tx::TxError>),
+    CoinSelect(crate::wallet::coin_select::CoinSelectError),
+    Wallet(crate::wallet::WalletError),
+}
+
+// decoders return their errors located, but the same kinds also come
+// from code that isn't decoding anything
+macro_rules! from_unlocated {
+    ($($variant:ident($error:ty),)*) => {
+        $(
+            impl From<$error> for Error {
+                fn from(e: $error) -> Self {
+                    Error::$variant(e.into())
+                }
+            }
+        )*
+    };
+}
+
+from_unlocated! {
+    Bloom(crate::bloom::BloomError),
+    Signature(crate::curves::secp256k1::ecc::SignatureError),
+    Message(crate::network::message::MessageError),
+    Psbt(crate::psbt::PsbtError),
+    Tx(crate::tx::TxError),
+}
+
+#[cfg(test)]
+mod error_tests {
+    use super::*;
+    use crate::curves::secp256k1::ecc::{KeyError, PrivateKey};
+    use crate::encoding::base58::{self, Base58Error};
+    use crate::tx::{Transaction, TxError};
+    use std::error::Error as _;
+
+    fn parse_and_decode(secret: &[u8], encoded: &str) -> Result<Vec<u8>, Error> {
+        PrivateKey::from_bytes(secret)?;
+        Ok(base58::decode_check(encoded)?)
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(parse_and_decode(&[0; 32], ""), Err(Error::Key(KeyError::InvalidSecret)));
+        assert_eq!(
+            parse_and_decode(&[1; 32], "0"),
+            Err(Error::Base58(Base58Error::InvalidCharacter('0')))
+        );
+
+        let err = Error::from(KeyError::InvalidSecret);
+        assert_eq!(err.to_string(), KeyError::InvalidSecret.to_string());
+        assert_eq!(err.source().unwrap().to_string(), KeyError::InvalidSecret.to_string());
+
+        let err = Error::from(Transaction::from_hex("0100").unwrap_err());
+        assert_eq!(err.to_string(), "transaction is truncated at byte 2 in version");
+        assert_eq!(Error::from(TxError::InvalidHex), Error::Tx(TxError::InvalidHex.into()));
+    }
+}
@@ -0,0 +1,65 @@
+// Every error enum in the crate, importable from one place. Each module's
+// enum says exactly what can go wrong there, and `Error` wraps them all for
+// applications that want a single type to use `?` with
+
+pub use crate::arithmetic::field_element::FieldError;
+pub use crate::bip158::FilterError;
+pub use crate::bip21::Bip21Error;
+pub use crate::bip32::Bip32Error;
+pub use crate::bip47::Bip47Error;
+pub use crate::block::BlockError;
+pub use crate::bloom::BloomError;
+pub use crate::builder::BuilderError;
+pub use crate::bulletproofs::RangeProofError;
+pub use crate::chain::UtxoError;
+pub use crate::coinbase::CoinbaseError;
+pub use crate::crypto::aead::AeadError;
+pub use crate::crypto::ecies::EciesError;
+pub use crate::crypto::hmac_drbg::DrbgError;
+pub use crate::crypto::kdf::KdfError;
+pub use crate::crypto::keystore::KeystoreError;
+pub use crate::crypto::kzg::KzgError;
+pub use crate::crypto::poseidon::PoseidonError;
+pub use crate::crypto::ssh::SshError;
+pub use crate::curves::ec_point::PointError;
+pub use crate::curves::secp256k1::ecc::{KeyError, SignatureError};
+pub use crate::descriptor::DescriptorError;
+pub use crate::dkg::DkgError;
+pub use crate::eip712::Eip712Error;
+pub use crate::electrum::ElectrumError;
+pub use crate::encoding::base58::Base58Error;
+pub use crate::encoding::base64::Base64Error;
+pub use crate::encoding::bech32::Bech32Error;
+pub use crate::encoding::compact_size::CompactSizeError;
+pub use crate::encoding::decode::DecodeError;
+pub use crate::encoding::rlp::RlpError;
+pub use crate::error::Error;
+pub use crate::ethereum::EthereumError;
+pub use crate::groth16::Groth16Error;
+pub use crate::interpreter::{InterpreterError, VerifyError};
+pub use crate::merkle::MerkleError;
+pub use crate::mining::MiningError;
+pub use crate::multisig::MultisigError;
+pub use crate::network::chain::ChainError;
+pub use crate::network::message::MessageError;
+pub use crate::network::peer::PeerError;
+pub use crate::nostr::NostrError;
+#[cfg(feature = "openssl")]
+pub use crate::openssl::OpensslError;
+pub use crate::paillier::PaillierError;
+pub use crate::pow::PowError;
+pub use crate::psbt::PsbtError;
+pub use crate::r1cs::R1csError;
+#[cfg(feature = "rpc")]
+pub use crate::rpc::RpcError;
+pub use crate::rsa::RsaError;
+pub use crate::script::{AsmError, OpReturnError, ScriptError};
+pub use crate::sighash::SighashError;
+pub use crate::sigma::SigmaError;
+pub use crate::sign::SignError;
+pub use crate::silent_payments::SilentPaymentError;
+pub use crate::taproot::TaprootError;
+pub use crate::timelock::TimelockError;
+pub use crate::tx::TxError;
+pub use crate::wallet::coin_select::CoinSelectError;
+pub use crate::wallet::WalletError;
@@ -0,0 +1,601 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+use crate::crypto::hash::keccak256;
+use crate::curves::secp256k1::ecc::{KeyError, PrivateKey, PublicKey, Signature};
+use crate::encoding::rlp::{Rlp, RlpError};
+
+/// EIP-2718 type byte of dynamic-fee transactions
+const EIP1559_TX_TYPE: u8 = 0x02;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EthereumError {
+    InvalidAddress(String),
+    /// Mixed-case address whose capitalization isn't its EIP-55 checksum
+    InvalidChecksum(String),
+    Rlp(RlpError),
+    Key(KeyError),
+    /// A `v` that's neither 27/28 nor EIP-155 encoded, or a y parity above 1
+    InvalidV(u64),
+    /// Not a legacy or EIP-1559 transaction, or the wrong number of fields
+    InvalidTransaction,
+}
+
+impl Display for EthereumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EthereumError::InvalidAddress(s) => write!(f, "invalid address {}", s),
+            EthereumError::InvalidChecksum(s) => write!(f, "bad EIP-55 checksum in {}", s),
+            EthereumError::Rlp(e) => write!(f, "{}", e),
+            EthereumError::Key(e) => write!(f, "{}", e),
+            EthereumError::InvalidV(v) => write!(f, "invalid signature v {}", v),
+            EthereumError::InvalidTransaction => write!(f, "invalid transaction encoding"),
+        }
+    }
+}
+
+impl std::error::Error for EthereumError {}
+
+impl From<RlpError> for EthereumError {
+    fn from(e: RlpError) -> Self {
+        EthereumError::Rlp(e)
+    }
+}
+
+impl From<KeyError> for EthereumError {
+    fn from(e: KeyError) -> Self {
+        EthereumError::Key(e)
+    }
+}
+
+/// The last 20 bytes of keccak256 of the uncompressed public key, without
+/// its 0x04 prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Address(pub [u8; 20]);
+
+impl Address {
+    pub fn from_public_key(key: &PublicKey) -> Self {
+        let hash = keccak256(&key.sec(false)[1..]);
+        Self(hash[12..].try_into().unwrap())
+    }
+
+    /// Parses 0x-prefixed hex. All-lowercase and all-uppercase carry no
+    /// checksum; mixed case must match EIP-55
+    pub fn parse(s: &str) -> Result<Self, EthereumError> {
+        let digits = s
+            .strip_prefix("0x")
+            .ok_or_else(|| EthereumError::InvalidAddress(s.to_string()))?;
+        let bytes: [u8; 20] = hex::decode(digits)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| EthereumError::InvalidAddress(s.to_string()))?;
+        let address = Self(bytes);
+        let mixed = digits.chars().any(|c| c.is_ascii_lowercase()) && digits.chars().any(|c| c.is_ascii_uppercase());
+        if mixed && address.to_string() != s {
+            return Err(EthereumError::InvalidChecksum(s.to_string()));
+        }
+        Ok(address)
+    }
+}
+
+/// EIP-55: a hex letter is uppercased when the matching nibble of the
+/// keccak256 of the lowercase hex is 8 or more
+impl Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lower = hex::encode(self.0);
+        let hash = keccak256(lower.as_bytes());
+        let checksummed: String = lower
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = hash[i / 2] >> (4 * (1 - i % 2)) & 0x0f;
+                if nibble >= 8 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+        write!(f, "0x{}", checksummed)
+    }
+}
+
+/// An ECDSA signature with the recovery id that lets anyone get the
+/// signer's key, and so its address, back from the message
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoverableSignature {
+    pub signature: Signature,
+    pub recovery_id: u8,
+}
+
+impl RecoverableSignature {
+    pub fn sign(key: &PrivateKey, hash: &[u8; 32]) -> Self {
+        let (signature, recovery_id) = key.sign_recoverable(&BigUint::from_bytes_be(hash));
+        Self { signature, recovery_id }
+    }
+
+    pub fn recover(&self, hash: &[u8; 32]) -> Result<PublicKey, KeyError> {
+        PublicKey::recover(&BigUint::from_bytes_be(hash), &self.signature, self.recovery_id)
+    }
+
+    pub fn recover_address(&self, hash: &[u8; 32]) -> Result<Address, KeyError> {
+        Ok(Address::from_public_key(&self.recover(hash)?))
+    }
+
+    /// r || s || v with v = 27 + recovery id, as `eth_sign` returns it
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut res = [0_u8; 65];
+        res[..32].copy_from_slice(&to_32_bytes(self.signature.r()));
+        res[32..64].copy_from_slice(&to_32_bytes(self.signature.s()));
+        res[64] = 27 + self.recovery_id;
+        res
+    }
+
+    /// Accepts v as 27/28 or as the bare recovery id
+    pub fn from_bytes(bytes: &[u8; 65]) -> Result<Self, EthereumError> {
+        let recovery_id = match bytes[64] {
+            v @ (0 | 1) => v,
+            v @ (27 | 28) => v - 27,
+            v => return Err(EthereumError::InvalidV(v as u64)),
+        };
+        let signature = Signature::from(
+            BigUint::from_bytes_be(&bytes[..32]),
+            BigUint::from_bytes_be(&bytes[32..64]),
+        );
+        Ok(Self { signature, recovery_id })
+    }
+}
+
+/// keccak256("\x19Ethereum Signed Message:\n" || decimal length || message),
+/// the EIP-191 version 0x45 hash `personal_sign` and `eth_sign` sign. The
+/// prefix keeps a signed message from doubling as a signed transaction
+pub fn hash_personal_message(message: &[u8]) -> [u8; 32] {
+    let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    data.extend_from_slice(message);
+    keccak256(&data)
+}
+
+pub fn personal_sign(key: &PrivateKey, message: &[u8]) -> RecoverableSignature {
+    RecoverableSignature::sign(key, &hash_personal_message(message))
+}
+
+/// The address that signed `hash`, with the ecrecover precompile's rules:
+/// v must be 27 or 28 and r, s in [1, n), high s included. Where Solidity
+/// returns address(0) this returns None
+pub fn ecrecover(hash: &[u8; 32], v: u8, r: &[u8; 32], s: &[u8; 32]) -> Option<Address> {
+    if v != 27 && v != 28 {
+        return None;
+    }
+    let signature = Signature::from(BigUint::from_bytes_be(r), BigUint::from_bytes_be(s));
+    let recovery_id = v - 27;
+    RecoverableSignature { signature, recovery_id }
+        .recover_address(hash)
+        .ok()
+}
+
+fn to_32_bytes(n: &BigUint) -> [u8; 32] {
+    let bytes = n.to_bytes_be();
+    let mut res = [0_u8; 32];
+    res[32 - bytes.len()..].copy_from_slice(&bytes);
+    res
+}
+
+/// Pre-EIP-2718 transaction. With a chain id the signature commits to it
+/// as EIP-155 describes, so it can't be replayed on other chains
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyTransaction {
+    pub nonce: u64,
+    pub gas_price: u128,
+    pub gas_limit: u64,
+    /// None creates a contract
+    pub to: Option<Address>,
+    pub value: u128,
+    pub data: Vec<u8>,
+    pub chain_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<[u8; 32]>,
+}
+
+/// EIP-1559 dynamic-fee transaction, type 2
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eip1559Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: u128,
+    pub max_fee_per_gas: u128,
+    pub gas_limit: u64,
+    pub to: Option<Address>,
+    pub value: u128,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EthTransaction {
+    Legacy(LegacyTransaction),
+    Eip1559(Eip1559Transaction),
+}
+
+fn address_rlp(to: &Option<Address>) -> Rlp {
+    match to {
+        Some(address) => Rlp::bytes(&address.0),
+        None => Rlp::bytes(&[]),
+    }
+}
+
+fn address_from_rlp(item: &Rlp) -> Result<Option<Address>, EthereumError> {
+    match item.as_bytes()? {
+        [] => Ok(None),
+        bytes => Ok(Some(Address(
+            bytes.try_into().map_err(|_| EthereumError::InvalidTransaction)?,
+        ))),
+    }
+}
+
+fn u64_from_rlp(item: &Rlp) -> Result<u64, EthereumError> {
+    u64::try_from(item.as_uint()?).map_err(|_| EthereumError::Rlp(RlpError::InvalidInteger))
+}
+
+fn biguint_rlp(n: &BigUint) -> Rlp {
+    Rlp::Bytes(if n.is_zero() { Vec::new() } else { n.to_bytes_be() })
+}
+
+fn biguint_from_rlp(item: &Rlp) -> Result<BigUint, EthereumError> {
+    let bytes = item.as_bytes()?;
+    if bytes.len() > 32 || bytes.first() == Some(&0) {
+        return Err(EthereumError::Rlp(RlpError::InvalidInteger));
+    }
+    Ok(BigUint::from_bytes_be(bytes))
+}
+
+impl LegacyTransaction {
+    fn fields(&self) -> Vec<Rlp> {
+        vec![
+            Rlp::uint(self.nonce as u128),
+            Rlp::uint(self.gas_price),
+            Rlp::uint(self.gas_limit as u128),
+            address_rlp(&self.to),
+            Rlp::uint(self.value),
+            Rlp::bytes(&self.data),
+        ]
+    }
+
+    /// v = 27 + recovery id, or chain_id * 2 + 35 + recovery id under EIP-155
+    fn v(&self, recovery_id: u8) -> u64 {
+        match self.chain_id {
+            Some(chain_id) => chain_id * 2 + 35 + recovery_id as u64,
+            None => 27 + recovery_id as u64,
+        }
+    }
+}
+
+impl Eip1559Transaction {
+    fn fields(&self) -> Vec<Rlp> {
+        let access_list = self
+            .access_list
+            .iter()
+            .map(|item| {
+                let keys = item.storage_keys.iter().map(|key| Rlp::bytes(key)).collect();
+                Rlp::List(vec![Rlp::bytes(&item.address.0), Rlp::List(keys)])
+            })
+            .collect();
+        vec![
+            Rlp::uint(self.chain_id as u128),
+            Rlp::uint(self.nonce as u128),
+            Rlp::uint(self.max_priority_fee_per_gas),
+            Rlp::uint(self.max_fee_per_gas),
+            Rlp::uint(self.gas_limit as u128),
+            address_rlp(&self.to),
+            Rlp::uint(self.value),
+            Rlp::bytes(&self.data),
+            Rlp::List(access_list),
+        ]
+    }
+
+    fn from_fields(fields: &[Rlp]) -> Result<Self, EthereumError> {
+        let [chain_id, nonce, priority_fee, max_fee, gas_limit, to, value, data, access_list] = fields else {
+            return Err(EthereumError::InvalidTransaction);
+        };
+        let access_list = access_list
+            .as_list()?
+            .iter()
+            .map(|item| {
+                let [address, keys] = item.as_list()? else {
+                    return Err(EthereumError::InvalidTransaction);
+                };
+                let address = address_from_rlp(address)?.ok_or(EthereumError::InvalidTransaction)?;
+                let storage_keys = keys
+                    .as_list()?
+                    .iter()
+                    .map(|key| {
+                        key.as_bytes()?
+                            .try_into()
+                            .map_err(|_| EthereumError::InvalidTransaction)
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(AccessListItem { address, storage_keys })
+            })
+            .collect::<Result<_, EthereumError>>()?;
+        Ok(Self {
+            chain_id: u64_from_rlp(chain_id)?,
+            nonce: u64_from_rlp(nonce)?,
+            max_priority_fee_per_gas: priority_fee.as_uint()?,
+            max_fee_per_gas: max_fee.as_uint()?,
+            gas_limit: u64_from_rlp(gas_limit)?,
+            to: address_from_rlp(to)?,
+            value: value.as_uint()?,
+            data: data.as_bytes()?.to_vec(),
+            access_list,
+        })
+    }
+}
+
+impl EthTransaction {
+    /// The keccak256 hash the sender signs
+    pub fn signing_hash(&self) -> [u8; 32] {
+        match self {
+            EthTransaction::Legacy(tx) => {
+                let mut fields = tx.fields();
+                if let Some(chain_id) = tx.chain_id {
+                    fields.extend([Rlp::uint(chain_id as u128), Rlp::uint(0), Rlp::uint(0)]);
+                }
+                keccak256(&Rlp::List(fields).encode())
+            }
+            EthTransaction::Eip1559(tx) => {
+                let mut payload = vec![EIP1559_TX_TYPE];
+                payload.extend(Rlp::List(tx.fields()).encode());
+                keccak256(&payload)
+            }
+        }
+    }
+
+    pub fn sign(self, key: &PrivateKey) -> SignedTransaction {
+        let signature = RecoverableSignature::sign(key, &self.signing_hash());
+        SignedTransaction { tx: self, signature }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedTransaction {
+    pub tx: EthTransaction,
+    pub signature: RecoverableSignature,
+}
+
+impl SignedTransaction {
+    /// The bytes `eth_sendRawTransaction` takes: the RLP list for legacy
+    /// transactions, the type byte and then the list for typed ones
+    pub fn raw(&self) -> Vec<u8> {
+        let sig = &self.signature.signature;
+        match &self.tx {
+            EthTransaction::Legacy(tx) => {
+                let mut fields = tx.fields();
+                fields.extend([
+                    Rlp::uint(tx.v(self.signature.recovery_id) as u128),
+                    biguint_rlp(sig.r()),
+                    biguint_rlp(sig.s()),
+                ]);
+                Rlp::List(fields).encode()
+            }
+            EthTransaction::Eip1559(tx) => {
+                let mut fields = tx.fields();
+                fields.extend([
+                    Rlp::uint(self.signature.recovery_id as u128),
+                    biguint_rlp(sig.r()),
+                    biguint_rlp(sig.s()),
+                ]);
+                let mut res = vec![EIP1559_TX_TYPE];
+                res.extend(Rlp::List(fields).encode());
+                res
+            }
+        }
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, EthereumError> {
+        match raw.first() {
+            Some(&EIP1559_TX_TYPE) => {
+                let item = Rlp::decode(&raw[1..])?;
+                let [fields @ .., y_parity, r, s] = item.as_list()? else {
+                    return Err(EthereumError::InvalidTransaction);
+                };
+                let recovery_id = match u64_from_rlp(y_parity)? {
+                    v @ (0 | 1) => v as u8,
+                    v => return Err(EthereumError::InvalidV(v)),
+                };
+                Ok(Self {
+                    tx: EthTransaction::Eip1559(Eip1559Transaction::from_fields(fields)?),
+                    signature: RecoverableSignature {
+                        signature: Signature::from(biguint_from_rlp(r)?, biguint_from_rlp(s)?),
+                        recovery_id,
+                    },
+                })
+            }
+            // legacy transactions are RLP lists, which start at 0xc0
+            Some(0xc0..) => {
+                let item = Rlp::decode(raw)?;
+                let [nonce, gas_price, gas_limit, to, value, data, v, r, s] = item.as_list()? else {
+                    return Err(EthereumError::InvalidTransaction);
+                };
+                let (chain_id, recovery_id) = match u64_from_rlp(v)? {
+                    v @ (27 | 28) => (None, (v - 27) as u8),
+                    v @ 35.. => (Some((v - 35) / 2), ((v - 35) % 2) as u8),
+                    v => return Err(EthereumError::InvalidV(v)),
+                };
+                let tx = LegacyTransaction {
+                    nonce: u64_from_rlp(nonce)?,
+                    gas_price: gas_price.as_uint()?,
+                    gas_limit: u64_from_rlp(gas_limit)?,
+                    to: address_from_rlp(to)?,
+                    value: value.as_uint()?,
+                    data: data.as_bytes()?.to_vec(),
+                    chain_id,
+                };
+                Ok(Self {
+                    tx: EthTransaction::Legacy(tx),
+                    signature: RecoverableSignature {
+                        signature: Signature::from(biguint_from_rlp(r)?, biguint_from_rlp(s)?),
+                        recovery_id,
+                    },
+                })
+            }
+            _ => Err(EthereumError::InvalidTransaction),
+        }
+    }
+
+    /// The transaction hash block explorers show
+    pub fn hash(&self) -> [u8; 32] {
+        keccak256(&self.raw())
+    }
+
+    pub fn sender(&self) -> Result<Address, KeyError> {
+        self.signature.recover_address(&self.tx.signing_hash())
+    }
+}
+
+#[cfg(test)]
+mod ethereum_tests {
+    use super::*;
+
+    fn key(byte: u8) -> PrivateKey {
+        PrivateKey::from_bytes(&[byte; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_address() {
+        assert_eq!(
+            Address::from_public_key(&key(0x46).public_key()).to_string(),
+            "0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F"
+        );
+        // EIP-55 examples
+        for s in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            assert_eq!(Address::parse(s).unwrap().to_string(), s);
+            assert_eq!(Address::parse(&s.to_lowercase()).unwrap().to_string(), s);
+        }
+        let wrong = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        assert_eq!(
+            Address::parse(wrong),
+            Err(EthereumError::InvalidChecksum(wrong.to_string()))
+        );
+        assert!(matches!(
+            Address::parse("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"),
+            Err(EthereumError::InvalidAddress(_))
+        ));
+        assert!(matches!(
+            Address::parse("0x5aaeb6"),
+            Err(EthereumError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn test_eip155() {
+        // the example from EIP-155
+        let tx = EthTransaction::Legacy(LegacyTransaction {
+            nonce: 9,
+            gas_price: 20_000_000_000,
+            gas_limit: 21_000,
+            to: Some(Address([0x35; 20])),
+            value: 1_000_000_000_000_000_000,
+            data: Vec::new(),
+            chain_id: Some(1),
+        });
+        assert_eq!(
+            hex::encode(tx.signing_hash()),
+            "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+
+        let signed = tx.sign(&key(0x46));
+        assert_eq!(
+            hex::encode(signed.raw()),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd93\
+             9bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b29\
+             7fb1966a3b6d83"
+        );
+        assert_eq!(signed.sender(), Ok(Address::from_public_key(&key(0x46).public_key())));
+        assert_eq!(SignedTransaction::decode(&signed.raw()), Ok(signed));
+    }
+
+    #[test]
+    fn test_eip1559() {
+        let tx = Eip1559Transaction {
+            chain_id: 1,
+            nonce: 0,
+            max_priority_fee_per_gas: 2_000_000_000,
+            max_fee_per_gas: 100_000_000_000,
+            gas_limit: 60_000,
+            to: Some(Address([0x35; 20])),
+            value: 0,
+            data: hex::decode("a9059cbb").unwrap(),
+            access_list: vec![AccessListItem {
+                address: Address([0x11; 20]),
+                storage_keys: vec![[0x22; 32]],
+            }],
+        };
+        let signed = EthTransaction::Eip1559(tx.clone()).sign(&key(0x46));
+        let raw = signed.raw();
+        assert_eq!(raw[0], 0x02);
+        assert_eq!(signed.sender(), Ok(Address::from_public_key(&key(0x46).public_key())));
+        assert_eq!(SignedTransaction::decode(&raw), Ok(signed.clone()));
+        assert_eq!(signed.hash(), keccak256(&raw));
+
+        // the chain id is signed, so changing it changes the sender
+        let mut replayed = signed.clone();
+        replayed.tx = EthTransaction::Eip1559(Eip1559Transaction { chain_id: 5, ..tx });
+        assert_ne!(replayed.sender(), signed.sender());
+
+        let mut bad_parity = raw.clone();
+        let len = bad_parity.len();
+        bad_parity[len - 67] = 0x02;
+        assert_eq!(SignedTransaction::decode(&bad_parity), Err(EthereumError::InvalidV(2)));
+        assert_eq!(
+            SignedTransaction::decode(&[0x01, 0xc0]),
+            Err(EthereumError::InvalidTransaction)
+        );
+    }
+
+    #[test]
+    fn test_personal_sign() {
+        // from the ethers.js hashMessage docs
+        assert_eq!(
+            hex::encode(hash_personal_message(b"Hello World")),
+            "a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+        );
+
+        let key = key(0x46);
+        let signer = Address::from_public_key(&key.public_key());
+        let hash = hash_personal_message(b"moneda");
+        let bytes = personal_sign(&key, b"moneda").to_bytes();
+        let (r, s) = (bytes[..32].try_into().unwrap(), bytes[32..64].try_into().unwrap());
+        assert_eq!(ecrecover(&hash, bytes[64], &r, &s), Some(signer));
+
+        // high s recovers too, with the other v, as the precompile allows
+        let n = crate::curves::secp256k1::ecc::order();
+        let high_s = to_32_bytes(&(&n - BigUint::from_bytes_be(&s)));
+        assert_eq!(ecrecover(&hash, 55 - bytes[64], &r, &high_s), Some(signer));
+        assert_eq!(ecrecover(&hash, bytes[64] - 27, &r, &s), None);
+        assert_eq!(ecrecover(&hash, bytes[64], &[0; 32], &s), None);
+        assert_eq!(ecrecover(&hash, bytes[64], &r, &to_32_bytes(&n)), None);
+    }
+
+    #[test]
+    fn test_signature_bytes() {
+        let hash = keccak256(b"moneda");
+        let sig = RecoverableSignature::sign(&key(1), &hash);
+        let bytes = sig.to_bytes();
+        assert!(bytes[64] == 27 || bytes[64] == 28);
+        assert_eq!(RecoverableSignature::from_bytes(&bytes), Ok(sig.clone()));
+        assert_eq!(sig.recover(&hash), Ok(key(1).public_key()));
+        let mut bad = bytes;
+        bad[64] = 29;
+        assert_eq!(RecoverableSignature::from_bytes(&bad), Err(EthereumError::InvalidV(29)));
+    }
+}
@@ -0,0 +1,405 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use num_bigint::BigInt;
+
+use crate::arithmetic::field_element::{FieldElement, FieldError};
+use crate::curves::ec_point::{Curve, Point, PointError};
+use crate::curves::secp256k1::ecc;
+
+// Graders for the finite field and elliptic curve exercises of a course in
+// the style of Programming Bitcoin. Each takes the questions with the
+// student's answers, works the answers out with the crate, and returns a
+// `Report` of what was right and wrong, for a grader to score or print.
+// Bad questions (elements outside the field, points off the curve where
+// one is needed) are errors, not wrong answers
+
+/// One question, the crate's answer and the student's
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check<T> {
+    pub question: String,
+    pub expected: T,
+    pub answer: T,
+}
+
+impl<T: PartialEq> Check<T> {
+    pub fn is_correct(&self) -> bool {
+        self.expected == self.answer
+    }
+}
+
+/// The checks of one exercise, in the order of the questions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report<T> {
+    pub checks: Vec<Check<T>>,
+}
+
+impl<T: PartialEq> Report<T> {
+    /// The number of correct answers
+    pub fn score(&self) -> usize {
+        self.checks.iter().filter(|check| check.is_correct()).count()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.score() == self.checks.len()
+    }
+
+    pub fn mistakes(&self) -> impl Iterator<Item = &Check<T>> {
+        self.checks.iter().filter(|check| !check.is_correct())
+    }
+}
+
+/// A line per question, then the score
+impl<T: PartialEq + Display> Display for Report<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for check in &self.checks {
+            if check.is_correct() {
+                writeln!(f, "ok     {} = {}", check.question, check.answer)?;
+            } else {
+                writeln!(
+                    f,
+                    "wrong  {} = {}, not {}",
+                    check.question, check.expected, check.answer
+                )?;
+            }
+        }
+        write!(f, "{}/{} correct", self.score(), self.checks.len())
+    }
+}
+
+/// A point as a student writes it down
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Coordinates {
+    Infinity,
+    Affine(BigInt, BigInt),
+}
+
+impl Coordinates {
+    pub fn affine(x: impl Into<BigInt>, y: impl Into<BigInt>) -> Self {
+        Coordinates::Affine(x.into(), y.into())
+    }
+
+    /// The point with these coordinates on `curve`
+    pub fn to_point(&self, curve: &Arc<Curve>) -> Result<Point, PointError> {
+        match self {
+            Coordinates::Infinity => Ok(Point::infinity_on(curve)),
+            Coordinates::Affine(x, y) => {
+                let prime = curve.a().prime();
+                let x = FieldElement::from(x.clone(), prime.clone())?;
+                let y = FieldElement::from(y.clone(), prime)?;
+                Point::on_curve(Some(x), Some(y), curve)
+            }
+        }
+    }
+}
+
+impl From<&Point> for Coordinates {
+    fn from(point: &Point) -> Self {
+        match (point.x(), point.y()) {
+            (Some(x), Some(y)) => Coordinates::Affine(x.num(), y.num()),
+            _ => Coordinates::Infinity,
+        }
+    }
+}
+
+impl Display for Coordinates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Coordinates::Infinity => write!(f, "O"),
+            Coordinates::Affine(x, y) => write!(f, "({}, {})", x, y),
+        }
+    }
+}
+
+/// The curve y² = x³ + ax + b over the integers mod `prime`, as the
+/// exercises give it
+pub fn toy_curve(
+    a: impl Into<BigInt>,
+    b: impl Into<BigInt>,
+    prime: impl Into<BigInt>,
+) -> Result<Arc<Curve>, PointError> {
+    let prime = prime.into();
+    Curve::new(
+        FieldElement::from(a.into(), prime.clone())?,
+        FieldElement::from(b.into(), prime)?,
+    )
+}
+
+/// An operation on elements of a prime field. Exponents may be negative
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldOp {
+    Add(BigInt, BigInt),
+    Sub(BigInt, BigInt),
+    Mul(BigInt, BigInt),
+    Div(BigInt, BigInt),
+    Pow(BigInt, BigInt),
+}
+
+impl Display for FieldOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldOp::Add(a, b) => write!(f, "{} + {}", a, b),
+            FieldOp::Sub(a, b) => write!(f, "{} - {}", a, b),
+            FieldOp::Mul(a, b) => write!(f, "{} * {}", a, b),
+            FieldOp::Div(a, b) => write!(f, "{} / {}", a, b),
+            FieldOp::Pow(a, e) => write!(f, "{}^{}", a, e),
+        }
+    }
+}
+
+/// Arithmetic in the field of integers mod `prime`, e.g. 3 / 24 mod 31.
+/// Operands must already be reduced
+pub fn field_operations(
+    prime: impl Into<BigInt>,
+    questions: &[(FieldOp, BigInt)],
+) -> Result<Report<BigInt>, FieldError> {
+    let prime = prime.into();
+    let element = |n: &BigInt| FieldElement::from(n.clone(), prime.clone());
+    let mut checks = Vec::new();
+    for (op, answer) in questions {
+        let expected = match op {
+            FieldOp::Add(a, b) => element(a)?.checked_add(element(b)?)?,
+            FieldOp::Sub(a, b) => element(a)?.checked_sub(element(b)?)?,
+            FieldOp::Mul(a, b) => element(a)?.checked_mul(element(b)?)?,
+            FieldOp::Div(a, b) => element(a)?.checked_div(element(b)?)?,
+            FieldOp::Pow(a, e) => element(a)?.pow(e.clone()),
+        };
+        checks.push(Check {
+            question: format!("{} mod {}", op, prime),
+            expected: expected.num(),
+            answer: answer.clone(),
+        });
+    }
+    Ok(Report { checks })
+}
+
+/// Whether each point lies on `curve`
+pub fn on_curve(curve: &Arc<Curve>, questions: &[(Coordinates, bool)]) -> Result<Report<bool>, PointError> {
+    let mut checks = Vec::new();
+    for (point, answer) in questions {
+        let expected = match point.to_point(curve) {
+            Ok(_) => true,
+            Err(PointError::NotOnCurve) => false,
+            Err(e) => return Err(e),
+        };
+        checks.push(Check {
+            question: format!("{} is on the curve", point),
+            expected,
+            answer: *answer,
+        });
+    }
+    Ok(Report { checks })
+}
+
+/// P + Q for points of `curve`
+pub fn additions(
+    curve: &Arc<Curve>,
+    questions: &[(Coordinates, Coordinates, Coordinates)],
+) -> Result<Report<Coordinates>, PointError> {
+    let mut checks = Vec::new();
+    for (p, q, answer) in questions {
+        let sum = p.to_point(curve)?.checked_add(q.to_point(curve)?)?;
+        checks.push(Check {
+            question: format!("{} + {}", p, q),
+            expected: (&sum).into(),
+            answer: answer.clone(),
+        });
+    }
+    Ok(Report { checks })
+}
+
+/// k P for points of `curve`
+pub fn scalar_multiples(
+    curve: &Arc<Curve>,
+    questions: &[(BigInt, Coordinates, Coordinates)],
+) -> Result<Report<Coordinates>, PointError> {
+    let mut checks = Vec::new();
+    for (k, p, answer) in questions {
+        let product = k.clone() * p.to_point(curve)?;
+        checks.push(Check {
+            question: format!("{} {}", k, p),
+            expected: (&product).into(),
+            answer: answer.clone(),
+        });
+    }
+    Ok(Report { checks })
+}
+
+/// The order of each point of `curve`, the least n with n P = O
+pub fn point_orders(curve: &Arc<Curve>, questions: &[(Coordinates, BigInt)]) -> Result<Report<BigInt>, PointError> {
+    let mut checks = Vec::new();
+    for (p, answer) in questions {
+        let order = p.to_point(curve)?.order();
+        checks.push(Check {
+            question: format!("the order of {}", p),
+            expected: order,
+            answer: answer.clone(),
+        });
+    }
+    Ok(Report { checks })
+}
+
+/// k G on secp256k1, the public key of the secret k
+pub fn generator_multiples(questions: &[(BigInt, Coordinates)]) -> Report<Coordinates> {
+    let checks = questions
+        .iter()
+        .map(|(k, answer)| {
+            let product = k.clone() * ecc::generator();
+            Check {
+                question: format!("{} G", k),
+                expected: (&product).into(),
+                answer: answer.clone(),
+            }
+        })
+        .collect();
+    Report { checks }
+}
+
+#[cfg(test)]
+mod exercises_tests {
+    use super::*;
+
+    fn n(s: &str) -> BigInt {
+        BigInt::parse_bytes(s.as_bytes(), 16).unwrap()
+    }
+
+    #[test]
+    fn test_field_operations() {
+        // chapter 1, exercises 2 and 8 mod 57 and 31
+        let report = field_operations(
+            57,
+            &[
+                (FieldOp::Add(44.into(), 33.into()), 20.into()),
+                (FieldOp::Sub(9.into(), 29.into()), 37.into()),
+            ],
+        )
+        .unwrap();
+        assert!(report.passed());
+
+        let report = field_operations(
+            31,
+            &[
+                (FieldOp::Div(3.into(), 24.into()), 4.into()),
+                (FieldOp::Pow(17.into(), (-3).into()), 29.into()),
+                (FieldOp::Pow(4.into(), (-4).into()), 12.into()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(report.score(), 2);
+        let mistake = report.mistakes().next().unwrap();
+        assert_eq!(
+            (mistake.question.as_str(), &mistake.expected),
+            ("4^-4 mod 31", &BigInt::from(4))
+        );
+        assert_eq!(
+            report.to_string(),
+            "ok     3 / 24 mod 31 = 4\nok     17^-3 mod 31 = 29\nwrong  4^-4 mod 31 = 4, not 12\n2/3 correct"
+        );
+
+        assert_eq!(
+            field_operations(31, &[(FieldOp::Add(31.into(), 1.into()), 1.into())]),
+            Err(FieldError::OutOfRange {
+                num: 31.into(),
+                prime: 31.into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_points() {
+        // chapter 3, exercises 1, 2 and 4 on y² = x³ + 7 over F_223
+        let curve = toy_curve(0, 7, 223).unwrap();
+        let report = on_curve(
+            &curve,
+            &[
+                (Coordinates::affine(192, 105), true),
+                (Coordinates::affine(17, 56), true),
+                (Coordinates::affine(200, 119), false),
+                (Coordinates::affine(1, 193), true),
+                (Coordinates::affine(42, 99), true),
+            ],
+        )
+        .unwrap();
+        assert_eq!(report.score(), 4);
+        assert_eq!(report.mistakes().next().unwrap().question, "(42, 99) is on the curve");
+
+        let report = additions(
+            &curve,
+            &[
+                (
+                    Coordinates::affine(170, 142),
+                    Coordinates::affine(60, 139),
+                    Coordinates::affine(220, 181),
+                ),
+                (
+                    Coordinates::affine(47, 71),
+                    Coordinates::affine(17, 56),
+                    Coordinates::affine(215, 68),
+                ),
+                (
+                    Coordinates::affine(143, 98),
+                    Coordinates::affine(76, 66),
+                    Coordinates::affine(47, 71),
+                ),
+                (
+                    Coordinates::affine(47, 71),
+                    Coordinates::affine(47, 152),
+                    Coordinates::Infinity,
+                ),
+            ],
+        )
+        .unwrap();
+        assert!(report.passed());
+
+        let report = scalar_multiples(
+            &curve,
+            &[
+                (2.into(), Coordinates::affine(192, 105), Coordinates::affine(49, 71)),
+                (2.into(), Coordinates::affine(143, 98), Coordinates::affine(64, 168)),
+                (8.into(), Coordinates::affine(47, 71), Coordinates::affine(116, 55)),
+                (21.into(), Coordinates::affine(47, 71), Coordinates::affine(47, 71)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(report.score(), 3);
+        assert_eq!(report.mistakes().next().unwrap().expected, Coordinates::Infinity);
+
+        // exercise 5, the group generated by (15, 86)
+        let report = point_orders(&curve, &[(Coordinates::affine(15, 86), 7.into())]).unwrap();
+        assert!(report.passed());
+
+        assert_eq!(
+            additions(
+                &curve,
+                &[(
+                    Coordinates::affine(42, 99),
+                    Coordinates::Infinity,
+                    Coordinates::Infinity
+                )]
+            ),
+            Err(PointError::NotOnCurve)
+        );
+    }
+
+    #[test]
+    fn test_generator_multiples() {
+        // chapter 3, exercise 5: the public keys of 7 and 1485
+        let report = generator_multiples(&[
+            (
+                7.into(),
+                Coordinates::Affine(
+                    n("5cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc"),
+                    n("6aebca40ba255960a3178d6d861a54dba813d0b813fde7b5a5082628087264da"),
+                ),
+            ),
+            (
+                1485.into(),
+                Coordinates::Affine(
+                    n("c982196a7466fbbbb0e27a940b6af926c1a74d5ad07128c82824a11b5398afda"),
+                    n("7a91f9eae64438afb9ce6448a1c133db2d8fb9254e4546b6f001637d50901f55"),
+                ),
+            ),
+        ]);
+        assert!(report.passed());
+    }
+}
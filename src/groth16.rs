@@ -0,0 +1,420 @@
+use std::fmt::Display;
+
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::bn254::curve::{g1_from_bytes, g1_neg, g1_to_bytes, G2Point};
+use crate::bn254::curve_order;
+use crate::bn254::pairing::pairing_check;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::ecc::multi_scalar_mul;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Groth16Error {
+    /// Off the curve, outside the field or outside the order-r subgroup
+    InvalidPoint,
+    /// Public inputs are elements of the scalar field
+    InvalidScalar,
+    InvalidLength {
+        expected: usize,
+        got: usize,
+    },
+    /// The key has one IC point per public input, plus one
+    PublicInputCount {
+        expected: usize,
+        got: usize,
+    },
+    VerificationFailed,
+    #[cfg(feature = "snarkjs")]
+    InvalidJson(String),
+}
+
+impl Display for Groth16Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Groth16Error::InvalidPoint => write!(f, "invalid curve point"),
+            Groth16Error::InvalidScalar => write!(f, "public input is not in the scalar field"),
+            Groth16Error::InvalidLength { expected, got } => write!(f, "expected {} bytes, got {}", expected, got),
+            Groth16Error::PublicInputCount { expected, got } => {
+                write!(f, "expected {} public inputs, got {}", expected, got)
+            }
+            Groth16Error::VerificationFailed => write!(f, "groth16 proof verification failed"),
+            #[cfg(feature = "snarkjs")]
+            Groth16Error::InvalidJson(e) => write!(f, "invalid snarkjs json: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Groth16Error {}
+
+/// A Groth16 verifying key over BN254. `ic[0] + Σ x_i ic[i+1]` commits to
+/// the public inputs x
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyingKey {
+    pub alpha_g1: Point,
+    pub beta_g2: G2Point,
+    pub gamma_g2: G2Point,
+    pub delta_g2: G2Point,
+    pub ic: Vec<Point>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proof {
+    pub a: Point,
+    pub b: G2Point,
+    pub c: Point,
+}
+
+impl VerifyingKey {
+    /// Checks e(A, B) = e(α, β) e(Σ x_i IC_i, γ) e(C, δ)
+    pub fn verify(&self, public_inputs: &[BigInt], proof: &Proof) -> Result<(), Groth16Error> {
+        if public_inputs.len() + 1 != self.ic.len() {
+            return Err(Groth16Error::PublicInputCount {
+                expected: self.ic.len() - 1,
+                got: public_inputs.len(),
+            });
+        }
+        let r = curve_order();
+        let mut scalars = vec![BigUint::from(1_u8)];
+        for x in public_inputs {
+            if x.sign() == Sign::Minus || *x >= r {
+                return Err(Groth16Error::InvalidScalar);
+            }
+            scalars.push(x.to_biguint().unwrap());
+        }
+        let inputs = multi_scalar_mul(&scalars, &self.ic);
+        let valid = pairing_check(&[
+            (g1_neg(&proof.a), proof.b.clone()),
+            (self.alpha_g1.clone(), self.beta_g2.clone()),
+            (inputs, self.gamma_g2.clone()),
+            (proof.c.clone(), self.delta_g2.clone()),
+        ]);
+        if valid {
+            Ok(())
+        } else {
+            Err(Groth16Error::VerificationFailed)
+        }
+    }
+
+    /// α, β, γ, δ and then the IC points, in the EIP-196/197 encodings
+    /// Solidity verifiers use
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = g1_to_bytes(&self.alpha_g1).to_vec();
+        for point in [&self.beta_g2, &self.gamma_g2, &self.delta_g2] {
+            res.extend_from_slice(&point.to_bytes());
+        }
+        for point in &self.ic {
+            res.extend_from_slice(&g1_to_bytes(point));
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Groth16Error> {
+        // at least one IC point
+        let fixed = 64 + 3 * 128;
+        if bytes.len() < fixed + 64 || !(bytes.len() - fixed).is_multiple_of(64) {
+            let expected = fixed + 64 * ((bytes.len().saturating_sub(fixed) / 64).max(1));
+            return Err(Groth16Error::InvalidLength {
+                expected,
+                got: bytes.len(),
+            });
+        }
+        Ok(Self {
+            alpha_g1: g1(&bytes[..64])?,
+            beta_g2: g2(&bytes[64..192])?,
+            gamma_g2: g2(&bytes[192..320])?,
+            delta_g2: g2(&bytes[320..448])?,
+            ic: bytes[fixed..].chunks(64).map(g1).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl Proof {
+    /// A, B and C in the EIP-196/197 encodings, the 256 bytes a Solidity
+    /// verifier takes
+    pub fn to_bytes(&self) -> [u8; 256] {
+        let mut res = [0_u8; 256];
+        res[..64].copy_from_slice(&g1_to_bytes(&self.a));
+        res[64..192].copy_from_slice(&self.b.to_bytes());
+        res[192..].copy_from_slice(&g1_to_bytes(&self.c));
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Groth16Error> {
+        if bytes.len() != 256 {
+            return Err(Groth16Error::InvalidLength {
+                expected: 256,
+                got: bytes.len(),
+            });
+        }
+        Ok(Self {
+            a: g1(&bytes[..64])?,
+            b: g2(&bytes[64..192])?,
+            c: g1(&bytes[192..])?,
+        })
+    }
+}
+
+fn g1(bytes: &[u8]) -> Result<Point, Groth16Error> {
+    g1_from_bytes(bytes.try_into().unwrap()).ok_or(Groth16Error::InvalidPoint)
+}
+
+fn g2(bytes: &[u8]) -> Result<G2Point, Groth16Error> {
+    G2Point::from_bytes(bytes.try_into().unwrap()).ok_or(Groth16Error::InvalidPoint)
+}
+
+/// Loaders for snarkjs's `verification_key.json`, `proof.json` and
+/// `public.json`, where points are projective coordinates as decimal
+/// strings and G2 coordinates list the real part first
+#[cfg(feature = "snarkjs")]
+pub mod snarkjs {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::curves::secp256k1::ecc::to_32_bytes;
+
+    fn parse(json: &str) -> Result<Value, Groth16Error> {
+        serde_json::from_str(json).map_err(|e| Groth16Error::InvalidJson(e.to_string()))
+    }
+
+    fn field<'a>(value: &'a Value, key: &str) -> Result<&'a Value, Groth16Error> {
+        value
+            .get(key)
+            .ok_or_else(|| Groth16Error::InvalidJson(format!("missing {}", key)))
+    }
+
+    fn number(value: &Value) -> Result<BigInt, Groth16Error> {
+        value
+            .as_str()
+            .and_then(|s| BigInt::parse_bytes(s.as_bytes(), 10))
+            .ok_or_else(|| Groth16Error::InvalidJson(format!("expected a decimal string, got {}", value)))
+    }
+
+    fn array(value: &Value, len: usize) -> Result<&Vec<Value>, Groth16Error> {
+        value
+            .as_array()
+            .filter(|a| a.len() == len)
+            .ok_or_else(|| Groth16Error::InvalidJson(format!("expected an array of {}, got {}", len, value)))
+    }
+
+    /// The bytes of a field element, leaving range checks to the decoder
+    fn bytes(n: &BigInt) -> Result<[u8; 32], Groth16Error> {
+        if n.sign() == Sign::Minus || n.bits() > 256 {
+            return Err(Groth16Error::InvalidPoint);
+        }
+        Ok(to_32_bytes(n))
+    }
+
+    /// [x, y, z] with z = 1, or z = 0 for infinity
+    fn g1_json(value: &Value) -> Result<Point, Groth16Error> {
+        let coords = array(value, 3)?;
+        let z = number(&coords[2])?;
+        if z == BigInt::from(0) {
+            return Ok(crate::bn254::curve::g1_infinity());
+        }
+        if z != BigInt::from(1) {
+            return Err(Groth16Error::InvalidPoint);
+        }
+        let mut encoded = [0_u8; 64];
+        encoded[..32].copy_from_slice(&bytes(&number(&coords[0])?)?);
+        encoded[32..].copy_from_slice(&bytes(&number(&coords[1])?)?);
+        g1(&encoded)
+    }
+
+    /// [[x0, x1], [y0, y1], [z0, z1]] with z = 1 or 0
+    fn g2_json(value: &Value) -> Result<G2Point, Groth16Error> {
+        let coords = array(value, 3)?;
+        let pair = |v: &Value| -> Result<(BigInt, BigInt), Groth16Error> {
+            let p = array(v, 2)?;
+            Ok((number(&p[0])?, number(&p[1])?))
+        };
+        let (x, y, z) = (pair(&coords[0])?, pair(&coords[1])?, pair(&coords[2])?);
+        if z == (BigInt::from(0), BigInt::from(0)) {
+            return Ok(G2Point::infinity());
+        }
+        if z != (BigInt::from(1), BigInt::from(0)) {
+            return Err(Groth16Error::InvalidPoint);
+        }
+        let mut encoded = [0_u8; 128];
+        for (i, c) in [&x.1, &x.0, &y.1, &y.0].into_iter().enumerate() {
+            encoded[32 * i..32 * (i + 1)].copy_from_slice(&bytes(c)?);
+        }
+        g2(&encoded)
+    }
+
+    fn check_protocol(value: &Value) -> Result<(), Groth16Error> {
+        match value.get("protocol").and_then(Value::as_str) {
+            None | Some("groth16") => {}
+            Some(other) => return Err(Groth16Error::InvalidJson(format!("unsupported protocol {}", other))),
+        }
+        match value.get("curve").and_then(Value::as_str) {
+            None | Some("bn128") | Some("bn254") => Ok(()),
+            Some(other) => Err(Groth16Error::InvalidJson(format!("unsupported curve {}", other))),
+        }
+    }
+
+    impl VerifyingKey {
+        pub fn from_snarkjs(json: &str) -> Result<Self, Groth16Error> {
+            let value = parse(json)?;
+            check_protocol(&value)?;
+            let ic = field(&value, "IC")?
+                .as_array()
+                .filter(|ic| !ic.is_empty())
+                .ok_or_else(|| Groth16Error::InvalidJson("IC must be a non-empty array".to_string()))?;
+            Ok(Self {
+                alpha_g1: g1_json(field(&value, "vk_alpha_1")?)?,
+                beta_g2: g2_json(field(&value, "vk_beta_2")?)?,
+                gamma_g2: g2_json(field(&value, "vk_gamma_2")?)?,
+                delta_g2: g2_json(field(&value, "vk_delta_2")?)?,
+                ic: ic.iter().map(g1_json).collect::<Result<_, _>>()?,
+            })
+        }
+    }
+
+    impl Proof {
+        pub fn from_snarkjs(json: &str) -> Result<Self, Groth16Error> {
+            let value = parse(json)?;
+            check_protocol(&value)?;
+            Ok(Self {
+                a: g1_json(field(&value, "pi_a")?)?,
+                b: g2_json(field(&value, "pi_b")?)?,
+                c: g1_json(field(&value, "pi_c")?)?,
+            })
+        }
+    }
+
+    pub fn public_inputs(json: &str) -> Result<Vec<BigInt>, Groth16Error> {
+        parse(json)?
+            .as_array()
+            .ok_or_else(|| Groth16Error::InvalidJson("expected an array of public inputs".to_string()))?
+            .iter()
+            .map(number)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod groth16_tests {
+    use super::*;
+    use crate::bn254::curve::g1_generator;
+    use crate::curves::secp256k1::ecc::to_32_bytes;
+    use num_traits::Zero;
+
+    /// A key from known trapdoors and a proof simulated with them: any C
+    /// satisfying the pairing equation is accepted, which is what lets the
+    /// verifier be tested without a prover
+    fn simulate(inputs: &[BigInt]) -> (VerifyingKey, Proof) {
+        let r = curve_order();
+        let (alpha, beta, gamma, delta) = (BigInt::from(11), BigInt::from(13), BigInt::from(17), BigInt::from(19));
+        let ic: Vec<BigInt> = (0..=inputs.len()).map(|i| BigInt::from(100 + i)).collect();
+        let (a, b) = (BigInt::from(23), BigInt::from(29));
+        // a b = α β + γ (ic_0 + Σ x_i ic_i) + δ c
+        let ic_sum = inputs
+            .iter()
+            .zip(&ic[1..])
+            .fold(ic[0].clone(), |acc, (x, k)| acc + x * k);
+        let rhs = (&a * &b - &alpha * &beta - &gamma * ic_sum) % &r;
+        let c = ((rhs + &r) % &r * delta.modpow(&(&r - 2), &r)) % &r;
+        let g2 = G2Point::generator();
+        let vk = VerifyingKey {
+            alpha_g1: alpha * g1_generator(),
+            beta_g2: beta * g2.clone(),
+            gamma_g2: gamma * g2.clone(),
+            delta_g2: delta * g2.clone(),
+            ic: ic.into_iter().map(|k| k * g1_generator()).collect(),
+        };
+        let proof = Proof {
+            a: a * g1_generator(),
+            b: b * g2,
+            c: c * g1_generator(),
+        };
+        (vk, proof)
+    }
+
+    #[test]
+    fn test_verify() {
+        let inputs = [BigInt::from(33), BigInt::from(7)];
+        let (vk, proof) = simulate(&inputs);
+        assert_eq!(vk.verify(&inputs, &proof), Ok(()));
+        assert_eq!(
+            vk.verify(&[BigInt::from(33), BigInt::from(8)], &proof),
+            Err(Groth16Error::VerificationFailed)
+        );
+        let tampered = Proof {
+            c: proof.c.clone() + g1_generator(),
+            ..proof.clone()
+        };
+        assert_eq!(vk.verify(&inputs, &tampered), Err(Groth16Error::VerificationFailed));
+        assert_eq!(
+            vk.verify(&inputs[..1], &proof),
+            Err(Groth16Error::PublicInputCount { expected: 2, got: 1 })
+        );
+        assert_eq!(
+            vk.verify(&[curve_order(), BigInt::zero()], &proof),
+            Err(Groth16Error::InvalidScalar)
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        let (vk, proof) = simulate(&[BigInt::from(5)]);
+        let vk_bytes = vk.to_bytes();
+        assert_eq!(vk_bytes.len(), 64 + 3 * 128 + 2 * 64);
+        assert_eq!(VerifyingKey::from_bytes(&vk_bytes), Ok(vk));
+        assert_eq!(Proof::from_bytes(&proof.to_bytes()), Ok(proof.clone()));
+
+        assert_eq!(
+            Proof::from_bytes(&[0; 255]),
+            Err(Groth16Error::InvalidLength {
+                expected: 256,
+                got: 255
+            })
+        );
+        assert_eq!(
+            VerifyingKey::from_bytes(&vk_bytes[..vk_bytes.len() - 1]),
+            Err(Groth16Error::InvalidLength {
+                expected: vk_bytes.len() - 64,
+                got: vk_bytes.len() - 1
+            })
+        );
+        let mut bad = proof.to_bytes();
+        bad[..32].copy_from_slice(&to_32_bytes(&crate::bn254::field_modulus()));
+        assert_eq!(Proof::from_bytes(&bad), Err(Groth16Error::InvalidPoint));
+    }
+
+    #[cfg(feature = "snarkjs")]
+    #[test]
+    fn test_snarkjs() {
+        use crate::bn254::fields::Fq2;
+
+        let inputs = [BigInt::from(33)];
+        let (vk, proof) = simulate(&inputs);
+        let g1 = |p: &Point| format!("[\"{}\",\"{}\",\"1\"]", p.x().unwrap().num(), p.y().unwrap().num());
+        let fq2 = |c: &Fq2| format!("[\"{}\",\"{}\"]", c.c0.num(), c.c1.num());
+        let g2 = |p: &G2Point| format!("[{},{},[\"1\",\"0\"]]", fq2(p.x().unwrap()), fq2(p.y().unwrap()));
+        let vk_json = format!(
+            "{{\"protocol\":\"groth16\",\"curve\":\"bn128\",\"nPublic\":1,\"vk_alpha_1\":{},\"vk_beta_2\":{},\
+             \"vk_gamma_2\":{},\"vk_delta_2\":{},\"IC\":[{}]}}",
+            g1(&vk.alpha_g1),
+            g2(&vk.beta_g2),
+            g2(&vk.gamma_g2),
+            g2(&vk.delta_g2),
+            vk.ic.iter().map(g1).collect::<Vec<_>>().join(",")
+        );
+        let proof_json = format!(
+            "{{\"pi_a\":{},\"pi_b\":{},\"pi_c\":{},\"protocol\":\"groth16\",\"curve\":\"bn128\"}}",
+            g1(&proof.a),
+            g2(&proof.b),
+            g1(&proof.c)
+        );
+        let loaded = VerifyingKey::from_snarkjs(&vk_json).unwrap();
+        assert_eq!(loaded, vk);
+        let loaded_proof = Proof::from_snarkjs(&proof_json).unwrap();
+        assert_eq!(loaded_proof, proof);
+        let public = snarkjs::public_inputs("[\"33\"]").unwrap();
+        assert_eq!(loaded.verify(&public, &loaded_proof), Ok(()));
+
+        assert!(matches!(Proof::from_snarkjs("{}"), Err(Groth16Error::InvalidJson(_))));
+        let plonk = proof_json.replace("groth16", "plonk");
+        assert!(matches!(Proof::from_snarkjs(&plonk), Err(Groth16Error::InvalidJson(_))));
+    }
+}
@@ -0,0 +1,1460 @@
+use std::fmt::Display;
+use std::ops::BitOr;
+
+use num_bigint::BigUint;
+
+use crate::chain::Params;
+use crate::crypto::hash::{hash160, hash256, ripemd160, sha1, sha256};
+use crate::crypto::schnorr;
+use crate::curves::secp256k1::ecc::{PublicKey, Signature};
+use crate::encoding::compact_size;
+use crate::script::{decode_num, encode_num, Instruction, Instructions, Opcode, Script, ScriptError};
+use crate::sighash::{EcdsaSighashType, Prevouts, ScriptPath, SighashCache, SighashError, TapSighashType};
+use crate::taproot::{tap_leaf_hash, ControlBlock, LEAF_VERSION_TAPSCRIPT};
+use crate::timelock::{
+    LOCKTIME_THRESHOLD, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG,
+};
+use crate::tx::{Transaction, TxOut};
+
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+/// Counts opcodes above OP_16, plus every key of an executed CHECKMULTISIG
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+/// Main and alt stack together
+pub const MAX_STACK_SIZE: usize = 1000;
+pub const MAX_PUBKEYS_PER_MULTISIG: i64 = 20;
+/// BIP-342: each signature checked uses up this much of the witness size budget
+const VALIDATION_WEIGHT_PER_SIGOP: i64 = 50;
+/// BIP-341: a last witness element starting with this byte is the annex
+const ANNEX_TAG: u8 = 0x50;
+
+/// Which soft forks to enforce, a bit set like bitcoind's script flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VerifyFlags(u32);
+
+impl VerifyFlags {
+    pub const NONE: Self = Self(0);
+    /// BIP-16 pay to script hash
+    pub const P2SH: Self = Self(1 << 0);
+    /// BIP-66 strict DER signatures
+    pub const DERSIG: Self = Self(1 << 2);
+    /// BIP-147: the extra CHECKMULTISIG element must be empty
+    pub const NULLDUMMY: Self = Self(1 << 4);
+    /// BIP-65
+    pub const CHECKLOCKTIMEVERIFY: Self = Self(1 << 9);
+    /// BIP-112
+    pub const CHECKSEQUENCEVERIFY: Self = Self(1 << 10);
+    /// BIP-141 segwit
+    pub const WITNESS: Self = Self(1 << 11);
+    /// BIP-341/342
+    pub const TAPROOT: Self = Self(1 << 17);
+    /// Every rule in force on mainnet today
+    pub const CONSENSUS: Self = Self(
+        Self::P2SH.0
+            | Self::DERSIG.0
+            | Self::NULLDUMMY.0
+            | Self::CHECKLOCKTIMEVERIFY.0
+            | Self::CHECKSEQUENCEVERIFY.0
+            | Self::WITNESS.0
+            | Self::TAPROOT.0,
+    );
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The rules for a block at `height`. Like bitcoind, P2SH, segwit and
+    /// taproot apply from genesis: no earlier block breaks them
+    pub fn at_height(params: &Params, height: u32) -> Self {
+        let mut flags = Self::P2SH | Self::WITNESS | Self::TAPROOT;
+        if height >= params.bip66_height {
+            flags = flags | Self::DERSIG;
+        }
+        if height >= params.bip65_height {
+            flags = flags | Self::CHECKLOCKTIMEVERIFY;
+        }
+        if height >= params.csv_height {
+            flags = flags | Self::CHECKSEQUENCEVERIFY;
+        }
+        if height >= params.segwit_height {
+            flags = flags | Self::NULLDUMMY;
+        }
+        flags
+    }
+}
+
+impl BitOr for VerifyFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// The signature rules a script runs under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigVersion {
+    /// script_sig, script_pubkey and P2SH redeem scripts
+    Base,
+    /// P2WPKH and P2WSH witness scripts
+    WitnessV0,
+    /// BIP-342 taproot leaves
+    Tapscript,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpreterError {
+    /// The script finished with an empty or false top element
+    EvalFalse,
+    OpReturn,
+    ScriptSize(usize),
+    PushSize(usize),
+    OpCount,
+    StackSize,
+    PubkeyCount,
+    SigCount,
+    Verify,
+    EqualVerify,
+    NumEqualVerify,
+    CheckSigVerify,
+    CheckMultiSigVerify,
+    /// Reserved or unknown opcode executed, or OP_VERIF/OP_VERNOTIF anywhere
+    BadOpcode(Opcode),
+    DisabledOpcode(Opcode),
+    InvalidStackOperation,
+    InvalidAltstackOperation,
+    UnbalancedConditional,
+    /// A number operand longer than the opcode allows
+    NumOverflow,
+    NegativeLockTime,
+    UnsatisfiedLockTime,
+    /// Not a strict DER signature (BIP-66)
+    SigDer,
+    /// Non-empty CHECKMULTISIG dummy (BIP-147)
+    SigNullDummy,
+    /// A P2SH script_sig with something other than pushes
+    SigPushOnly,
+    /// A witness script left more or less than one element
+    CleanStack,
+    WitnessProgramWrongLength,
+    WitnessProgramWitnessEmpty,
+    /// The witness doesn't match the program's hash or key
+    WitnessProgramMismatch,
+    /// A native witness program spent with a non-empty script_sig
+    WitnessMalleated,
+    /// A P2SH-wrapped witness program with more than the redeem script push
+    WitnessMalleatedP2sh,
+    /// Witness data on an input that isn't a witness program
+    WitnessUnexpected,
+    SchnorrSigSize,
+    SchnorrSigHashType,
+    SchnorrSig,
+    TaprootWrongControlSize,
+    /// More signatures than the witness size pays for (BIP-342)
+    TapscriptValidationWeight,
+    TapscriptCheckMultiSig,
+    /// Tapscript OP_IF/OP_NOTIF conditions must be empty or 0x01
+    TapscriptMinimalIf,
+    TapscriptEmptyPubkey,
+    Parse(ScriptError),
+    Sighash(SighashError),
+}
+
+impl Display for InterpreterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpreterError::EvalFalse => write!(f, "script evaluated to false"),
+            InterpreterError::OpReturn => write!(f, "OP_RETURN executed"),
+            InterpreterError::ScriptSize(len) => write!(f, "script of {} bytes exceeds {}", len, MAX_SCRIPT_SIZE),
+            InterpreterError::PushSize(len) => write!(f, "push of {} bytes exceeds {}", len, MAX_SCRIPT_ELEMENT_SIZE),
+            InterpreterError::OpCount => write!(f, "more than {} opcodes", MAX_OPS_PER_SCRIPT),
+            InterpreterError::StackSize => write!(f, "more than {} stack elements", MAX_STACK_SIZE),
+            InterpreterError::PubkeyCount => write!(f, "invalid CHECKMULTISIG key count"),
+            InterpreterError::SigCount => write!(f, "invalid CHECKMULTISIG signature count"),
+            InterpreterError::Verify => write!(f, "OP_VERIFY failed"),
+            InterpreterError::EqualVerify => write!(f, "OP_EQUALVERIFY failed"),
+            InterpreterError::NumEqualVerify => write!(f, "OP_NUMEQUALVERIFY failed"),
+            InterpreterError::CheckSigVerify => write!(f, "OP_CHECKSIGVERIFY failed"),
+            InterpreterError::CheckMultiSigVerify => write!(f, "OP_CHECKMULTISIGVERIFY failed"),
+            InterpreterError::BadOpcode(op) => write!(f, "bad opcode {}", op),
+            InterpreterError::DisabledOpcode(op) => write!(f, "disabled opcode {}", op),
+            InterpreterError::InvalidStackOperation => write!(f, "operation on too few stack elements"),
+            InterpreterError::InvalidAltstackOperation => write!(f, "OP_FROMALTSTACK on an empty alt stack"),
+            InterpreterError::UnbalancedConditional => write!(f, "unbalanced conditional"),
+            InterpreterError::NumOverflow => write!(f, "number operand too long"),
+            InterpreterError::NegativeLockTime => write!(f, "negative lock time"),
+            InterpreterError::UnsatisfiedLockTime => write!(f, "lock time not satisfied"),
+            InterpreterError::SigDer => write!(f, "signature is not strict DER"),
+            InterpreterError::SigNullDummy => write!(f, "CHECKMULTISIG dummy is not empty"),
+            InterpreterError::SigPushOnly => write!(f, "P2SH script_sig is not push only"),
+            InterpreterError::CleanStack => write!(f, "witness script did not leave exactly one element"),
+            InterpreterError::WitnessProgramWrongLength => write!(f, "witness v0 program of invalid length"),
+            InterpreterError::WitnessProgramWitnessEmpty => write!(f, "witness program spent with an empty witness"),
+            InterpreterError::WitnessProgramMismatch => write!(f, "witness does not match the program"),
+            InterpreterError::WitnessMalleated => write!(f, "native witness program with a script_sig"),
+            InterpreterError::WitnessMalleatedP2sh => write!(f, "P2SH witness script_sig is not a single push"),
+            InterpreterError::WitnessUnexpected => write!(f, "witness on a non-witness input"),
+            InterpreterError::SchnorrSigSize => write!(f, "Schnorr signature of invalid length"),
+            InterpreterError::SchnorrSigHashType => write!(f, "invalid Schnorr signature hash type"),
+            InterpreterError::SchnorrSig => write!(f, "invalid Schnorr signature"),
+            InterpreterError::TaprootWrongControlSize => write!(f, "control block of invalid length"),
+            InterpreterError::TapscriptValidationWeight => write!(f, "too many signature checks for the witness size"),
+            InterpreterError::TapscriptCheckMultiSig => write!(f, "CHECKMULTISIG is disabled in tapscript"),
+            InterpreterError::TapscriptMinimalIf => write!(f, "tapscript condition is not empty or 0x01"),
+            InterpreterError::TapscriptEmptyPubkey => write!(f, "empty public key in tapscript"),
+            InterpreterError::Parse(e) => write!(f, "{}", e),
+            InterpreterError::Sighash(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for InterpreterError {}
+
+impl From<ScriptError> for InterpreterError {
+    fn from(e: ScriptError) -> Self {
+        InterpreterError::Parse(e)
+    }
+}
+
+impl From<SighashError> for InterpreterError {
+    fn from(e: SighashError) -> Self {
+        InterpreterError::Sighash(e)
+    }
+}
+
+/// State a tapscript carries beyond its stack
+#[derive(Debug, Clone, Default)]
+pub struct ExecData {
+    pub tapleaf_hash: [u8; 32],
+    /// Opcode position of the last executed OP_CODESEPARATOR
+    pub code_separator_pos: u32,
+    pub annex: Option<Vec<u8>>,
+    pub validation_weight_left: i64,
+}
+
+/// Answers the questions a script asks about the transaction spending it:
+/// signatures, lock times and sequences of input `index`
+pub struct TransactionChecker<'a> {
+    cache: &'a SighashCache<'a>,
+    index: usize,
+    prevouts: &'a [TxOut],
+}
+
+impl<'a> TransactionChecker<'a> {
+    /// `prevouts` are the outputs spent by every input, in order
+    pub fn new(cache: &'a SighashCache<'a>, index: usize, prevouts: &'a [TxOut]) -> Self {
+        Self { cache, index, prevouts }
+    }
+
+    /// Legacy and v0 signatures: DER followed by the hash type byte. Segwit
+    /// v0 only understands the six standard hash types, although consensus
+    /// would take any byte there
+    fn check_ecdsa(&self, sig: &[u8], pubkey: &[u8], script_code: &Script, sig_version: SigVersion) -> bool {
+        let Some((&sighash_type, der)) = sig.split_last() else {
+            return false;
+        };
+        let (Ok(signature), Ok(public_key)) = (Signature::parse_der(der), PublicKey::parse(pubkey)) else {
+            return false;
+        };
+        let sighash = match sig_version {
+            SigVersion::Base => self
+                .cache
+                .legacy_signature_hash(self.index, script_code, sighash_type as u32),
+            _ => match EcdsaSighashType::from_u32(sighash_type as u32) {
+                Some(sighash_type) => {
+                    let amount = self.prevouts[self.index].amount;
+                    self.cache
+                        .segwit_v0_signature_hash(self.index, script_code, amount, sighash_type)
+                }
+                None => return false,
+            },
+        };
+        sighash.is_ok_and(|sighash| public_key.verify(&BigUint::from_bytes_be(&sighash), &signature))
+    }
+
+    /// BIP-340 signatures, with the hash type byte only when it isn't
+    /// `Default`. Unlike ECDSA, a bad signature is an error, not false
+    fn check_schnorr(
+        &self,
+        sig: &[u8],
+        pubkey: &[u8; 32],
+        annex: Option<&[u8]>,
+        script_path: Option<ScriptPath>,
+    ) -> Result<(), InterpreterError> {
+        let (sig, sighash_type) = match sig.len() {
+            64 => (sig, TapSighashType::Default),
+            65 if sig[64] != 0 => (
+                &sig[..64],
+                TapSighashType::from_u8(sig[64]).ok_or(InterpreterError::SchnorrSigHashType)?,
+            ),
+            65 => return Err(InterpreterError::SchnorrSigHashType),
+            _ => return Err(InterpreterError::SchnorrSigSize),
+        };
+        let sighash = self
+            .cache
+            .taproot_signature_hash(
+                self.index,
+                &Prevouts::All(self.prevouts),
+                annex,
+                script_path,
+                sighash_type,
+            )
+            .map_err(|e| match e {
+                SighashError::SingleWithoutOutput(_) => InterpreterError::SchnorrSigHashType,
+                e => InterpreterError::Sighash(e),
+            })?;
+        if !schnorr::verify(pubkey, &sighash, sig.try_into().unwrap()) {
+            return Err(InterpreterError::SchnorrSig);
+        }
+        Ok(())
+    }
+
+    /// OP_CHECKLOCKTIMEVERIFY: the transaction's lock time is of the same
+    /// kind and at least `lock_time`, and not disabled by a final sequence
+    fn check_lock_time(&self, lock_time: i64) -> bool {
+        let tx = self.cache.transaction();
+        let tx_lock_time = tx.locktime as i64;
+        let threshold = LOCKTIME_THRESHOLD as i64;
+        if (tx_lock_time < threshold) != (lock_time < threshold) || lock_time > tx_lock_time {
+            return false;
+        }
+        tx.inputs[self.index].sequence != 0xffffffff
+    }
+
+    /// OP_CHECKSEQUENCEVERIFY: the input's BIP-68 relative lock is of the
+    /// same kind and at least `sequence`
+    fn check_sequence(&self, sequence: i64) -> bool {
+        let tx = self.cache.transaction();
+        let tx_sequence = tx.inputs[self.index].sequence as i64;
+        if tx.version < 2 || tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG as i64 != 0 {
+            return false;
+        }
+        let mask = (SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK) as i64;
+        let type_flag = SEQUENCE_LOCKTIME_TYPE_FLAG as i64;
+        let (tx_sequence, sequence) = (tx_sequence & mask, sequence & mask);
+        (tx_sequence < type_flag) == (sequence < type_flag) && sequence <= tx_sequence
+    }
+}
+
+/// Any byte other than zero, or a final 0x80 (negative zero)
+pub fn cast_to_bool(bytes: &[u8]) -> bool {
+    match bytes.split_last() {
+        Some((&last, rest)) => rest.iter().any(|&b| b != 0) || (last != 0 && last != 0x80),
+        None => false,
+    }
+}
+
+fn bool_bytes(b: bool) -> Vec<u8> {
+    if b {
+        vec![1]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Numeric operands are at most 4 bytes, lock times 5
+fn script_num(bytes: &[u8], max_len: usize) -> Result<i64, InterpreterError> {
+    if bytes.len() > max_len {
+        return Err(InterpreterError::NumOverflow);
+    }
+    Ok(decode_num(bytes).expect("at most 5 bytes"))
+}
+
+/// The element `depth` from the top, 1 being the top itself
+fn top(stack: &[Vec<u8>], depth: usize) -> Result<&Vec<u8>, InterpreterError> {
+    stack
+        .len()
+        .checked_sub(depth)
+        .map(|i| &stack[i])
+        .ok_or(InterpreterError::InvalidStackOperation)
+}
+
+fn pop(stack: &mut Vec<Vec<u8>>) -> Result<Vec<u8>, InterpreterError> {
+    stack.pop().ok_or(InterpreterError::InvalidStackOperation)
+}
+
+fn pop_num(stack: &mut Vec<Vec<u8>>) -> Result<i64, InterpreterError> {
+    script_num(&pop(stack)?, 4)
+}
+
+fn require(stack: &[Vec<u8>], n: usize) -> Result<(), InterpreterError> {
+    if stack.len() < n {
+        return Err(InterpreterError::InvalidStackOperation);
+    }
+    Ok(())
+}
+
+/// Disabled in 2010 after a string of bugs; they fail even unexecuted
+fn is_disabled(op: Opcode) -> bool {
+    matches!(
+        op,
+        Opcode::Cat
+            | Opcode::Substr
+            | Opcode::Left
+            | Opcode::Right
+            | Opcode::Invert
+            | Opcode::And
+            | Opcode::Or
+            | Opcode::Xor
+            | Opcode::Mul2
+            | Opcode::Div2
+            | Opcode::Mul
+            | Opcode::Div
+            | Opcode::Mod
+            | Opcode::LShift
+            | Opcode::RShift
+    )
+}
+
+/// BIP-342 OP_SUCCESSx: opcodes that make a tapscript succeed outright,
+/// reserved for future soft forks
+fn is_op_success(byte: u8) -> bool {
+    matches!(byte, 80 | 98 | 126..=129 | 131..=134 | 137 | 138 | 141 | 142 | 149..=153 | 187..=254)
+}
+
+/// Only pushes, OP_RESERVED and OP_1NEGATE through OP_16 included
+fn is_push_only(script: &Script) -> bool {
+    script.instructions().all(|instruction| match instruction {
+        Ok(Instruction::Push(..)) => true,
+        Ok(Instruction::Op(op)) => op.to_u8() <= Opcode::Op16.to_u8(),
+        Err(_) => false,
+    })
+}
+
+/// Legacy scripts can't sign their own signatures, so every push of one is
+/// cut from the script code first. Matches are only looked for at
+/// instruction boundaries
+fn find_and_delete(script: &Script, pattern: &[u8]) -> Script {
+    let bytes = script.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut start = 0;
+    while start < bytes.len() {
+        if bytes[start..].starts_with(pattern) {
+            start += pattern.len();
+            continue;
+        }
+        let mut instructions = Instructions::new(&bytes[start..]);
+        instructions.next();
+        let end = start + instructions.offset();
+        res.extend_from_slice(&bytes[start..end]);
+        start = end;
+    }
+    Script::from_bytes(res)
+}
+
+/// What legacy and v0 signatures commit to: the script after the last
+/// executed OP_CODESEPARATOR
+fn script_code(script: &Script, start: usize, sig_version: SigVersion, signatures: &[Vec<u8>]) -> Script {
+    let mut code = Script::from_bytes(script.as_bytes()[start..].to_vec());
+    if sig_version == SigVersion::Base {
+        for sig in signatures {
+            code = find_and_delete(&code, Script::new().push_slice(sig).as_bytes());
+        }
+    }
+    code
+}
+
+fn check_signature_encoding(sig: &[u8], flags: VerifyFlags) -> Result<(), InterpreterError> {
+    // the empty signature is the standard way to fail a check on purpose
+    if let Some((_, der)) = sig.split_last() {
+        if flags.contains(VerifyFlags::DERSIG) && Signature::parse_der(der).is_err() {
+            return Err(InterpreterError::SigDer);
+        }
+    }
+    Ok(())
+}
+
+/// BIP-342 OP_CHECKSIG: the empty signature is false, anything else has to
+/// be valid. Keys that aren't 32 bytes are left to future soft forks
+fn checksig_tapscript(
+    sig: &[u8],
+    pubkey: &[u8],
+    checker: &TransactionChecker,
+    exec_data: &mut ExecData,
+) -> Result<bool, InterpreterError> {
+    let success = !sig.is_empty();
+    if success {
+        exec_data.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP;
+        if exec_data.validation_weight_left < 0 {
+            return Err(InterpreterError::TapscriptValidationWeight);
+        }
+    }
+    match pubkey.len() {
+        0 => Err(InterpreterError::TapscriptEmptyPubkey),
+        32 if success => {
+            let script_path = ScriptPath {
+                leaf_hash: exec_data.tapleaf_hash,
+                code_separator_pos: exec_data.code_separator_pos,
+            };
+            checker.check_schnorr(
+                sig,
+                pubkey.try_into().unwrap(),
+                exec_data.annex.as_deref(),
+                Some(script_path),
+            )?;
+            Ok(true)
+        }
+        _ => Ok(success),
+    }
+}
+
+/// Runs `script` on `stack`, leaving the result on it
+pub fn eval_script(
+    stack: &mut Vec<Vec<u8>>,
+    script: &Script,
+    flags: VerifyFlags,
+    checker: &TransactionChecker,
+    sig_version: SigVersion,
+    exec_data: &mut ExecData,
+) -> Result<(), InterpreterError> {
+    let tapscript = sig_version == SigVersion::Tapscript;
+    if !tapscript && script.len() > MAX_SCRIPT_SIZE {
+        return Err(InterpreterError::ScriptSize(script.len()));
+    }
+
+    let mut alt_stack: Vec<Vec<u8>> = Vec::new();
+    // one entry per open OP_IF, whether its branch is being executed
+    let mut conditions: Vec<bool> = Vec::new();
+    let mut op_count = 0;
+    let mut code_start = 0;
+    let mut instructions = script.instructions();
+    let mut position = 0;
+    while let Some(instruction) = instructions.next() {
+        let executing = conditions.iter().all(|&c| c);
+        let op = match instruction? {
+            Instruction::Push(_, data) => {
+                if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                    return Err(InterpreterError::PushSize(data.len()));
+                }
+                if executing {
+                    stack.push(data.to_vec());
+                }
+                None
+            }
+            Instruction::Op(op) => Some(op),
+        };
+
+        if let Some(op) = op {
+            if !tapscript && op.to_u8() > Opcode::Op16.to_u8() {
+                op_count += 1;
+                if op_count > MAX_OPS_PER_SCRIPT {
+                    return Err(InterpreterError::OpCount);
+                }
+            }
+            if is_disabled(op) {
+                return Err(InterpreterError::DisabledOpcode(op));
+            }
+            let conditional = matches!(
+                op,
+                Opcode::If | Opcode::NotIf | Opcode::VerIf | Opcode::VerNotIf | Opcode::Else | Opcode::EndIf
+            );
+            if executing || conditional {
+                if let Some(n) = op.small_int() {
+                    stack.push(encode_num(n as i64));
+                } else {
+                    match op {
+                        Opcode::Op1Negate => stack.push(encode_num(-1)),
+                        Opcode::Nop
+                        | Opcode::Nop1
+                        | Opcode::Nop4
+                        | Opcode::Nop5
+                        | Opcode::Nop6
+                        | Opcode::Nop7
+                        | Opcode::Nop8
+                        | Opcode::Nop9
+                        | Opcode::Nop10 => {}
+                        Opcode::CheckLockTimeVerify => {
+                            if flags.contains(VerifyFlags::CHECKLOCKTIMEVERIFY) {
+                                // 5 bytes so times past 2038 fit
+                                let lock_time = script_num(top(stack, 1)?, 5)?;
+                                if lock_time < 0 {
+                                    return Err(InterpreterError::NegativeLockTime);
+                                }
+                                if !checker.check_lock_time(lock_time) {
+                                    return Err(InterpreterError::UnsatisfiedLockTime);
+                                }
+                            }
+                        }
+                        Opcode::CheckSequenceVerify => {
+                            if flags.contains(VerifyFlags::CHECKSEQUENCEVERIFY) {
+                                let sequence = script_num(top(stack, 1)?, 5)?;
+                                if sequence < 0 {
+                                    return Err(InterpreterError::NegativeLockTime);
+                                }
+                                // the disable flag keeps it a NOP for future use
+                                if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG as i64 == 0
+                                    && !checker.check_sequence(sequence)
+                                {
+                                    return Err(InterpreterError::UnsatisfiedLockTime);
+                                }
+                            }
+                        }
+                        Opcode::If | Opcode::NotIf => {
+                            let mut value = false;
+                            if executing {
+                                let condition = stack.pop().ok_or(InterpreterError::UnbalancedConditional)?;
+                                if tapscript && (condition.len() > 1 || (condition.len() == 1 && condition[0] != 1)) {
+                                    return Err(InterpreterError::TapscriptMinimalIf);
+                                }
+                                value = cast_to_bool(&condition) == (op == Opcode::If);
+                            }
+                            conditions.push(value);
+                        }
+                        Opcode::Else => {
+                            let last = conditions.last_mut().ok_or(InterpreterError::UnbalancedConditional)?;
+                            *last = !*last;
+                        }
+                        Opcode::EndIf => {
+                            conditions.pop().ok_or(InterpreterError::UnbalancedConditional)?;
+                        }
+                        Opcode::Verify => {
+                            if !cast_to_bool(&pop(stack)?) {
+                                return Err(InterpreterError::Verify);
+                            }
+                        }
+                        Opcode::Return => return Err(InterpreterError::OpReturn),
+                        Opcode::ToAltStack => alt_stack.push(pop(stack)?),
+                        Opcode::FromAltStack => {
+                            stack.push(alt_stack.pop().ok_or(InterpreterError::InvalidAltstackOperation)?)
+                        }
+                        Opcode::Drop2 => {
+                            require(stack, 2)?;
+                            stack.truncate(stack.len() - 2);
+                        }
+                        Opcode::Dup2 | Opcode::Dup3 => {
+                            let n = if op == Opcode::Dup2 { 2 } else { 3 };
+                            require(stack, n)?;
+                            let items = stack[stack.len() - n..].to_vec();
+                            stack.extend(items);
+                        }
+                        Opcode::Over2 => {
+                            require(stack, 4)?;
+                            let items = stack[stack.len() - 4..stack.len() - 2].to_vec();
+                            stack.extend(items);
+                        }
+                        Opcode::Rot2 => {
+                            require(stack, 6)?;
+                            let len = stack.len();
+                            stack[len - 6..].rotate_left(2);
+                        }
+                        Opcode::Swap2 => {
+                            require(stack, 4)?;
+                            let len = stack.len();
+                            stack[len - 4..].rotate_left(2);
+                        }
+                        Opcode::IfDup => {
+                            let item = top(stack, 1)?.clone();
+                            if cast_to_bool(&item) {
+                                stack.push(item);
+                            }
+                        }
+                        Opcode::Depth => stack.push(encode_num(stack.len() as i64)),
+                        Opcode::Drop => {
+                            pop(stack)?;
+                        }
+                        Opcode::Dup => stack.push(top(stack, 1)?.clone()),
+                        Opcode::Nip => {
+                            require(stack, 2)?;
+                            stack.remove(stack.len() - 2);
+                        }
+                        Opcode::Over => stack.push(top(stack, 2)?.clone()),
+                        Opcode::Pick | Opcode::Roll => {
+                            let n = pop_num(stack)?;
+                            if n < 0 || n as usize >= stack.len() {
+                                return Err(InterpreterError::InvalidStackOperation);
+                            }
+                            let index = stack.len() - 1 - n as usize;
+                            let item = if op == Opcode::Roll {
+                                stack.remove(index)
+                            } else {
+                                stack[index].clone()
+                            };
+                            stack.push(item);
+                        }
+                        Opcode::Rot => {
+                            require(stack, 3)?;
+                            let len = stack.len();
+                            stack[len - 3..].rotate_left(1);
+                        }
+                        Opcode::Swap => {
+                            require(stack, 2)?;
+                            let len = stack.len();
+                            stack.swap(len - 1, len - 2);
+                        }
+                        Opcode::Tuck => {
+                            require(stack, 2)?;
+                            let item = top(stack, 1)?.clone();
+                            stack.insert(stack.len() - 2, item);
+                        }
+                        Opcode::Size => stack.push(encode_num(top(stack, 1)?.len() as i64)),
+                        Opcode::Equal | Opcode::EqualVerify => {
+                            require(stack, 2)?;
+                            let equal = pop(stack)? == pop(stack)?;
+                            if op == Opcode::Equal {
+                                stack.push(bool_bytes(equal));
+                            } else if !equal {
+                                return Err(InterpreterError::EqualVerify);
+                            }
+                        }
+                        Opcode::Add1
+                        | Opcode::Sub1
+                        | Opcode::Negate
+                        | Opcode::Abs
+                        | Opcode::Not
+                        | Opcode::NotEqual0 => {
+                            let n = pop_num(stack)?;
+                            let res = match op {
+                                Opcode::Add1 => n + 1,
+                                Opcode::Sub1 => n - 1,
+                                Opcode::Negate => -n,
+                                Opcode::Abs => n.abs(),
+                                Opcode::Not => (n == 0) as i64,
+                                _ => (n != 0) as i64,
+                            };
+                            stack.push(encode_num(res));
+                        }
+                        Opcode::Add
+                        | Opcode::Sub
+                        | Opcode::BoolAnd
+                        | Opcode::BoolOr
+                        | Opcode::NumEqual
+                        | Opcode::NumEqualVerify
+                        | Opcode::NumNotEqual
+                        | Opcode::LessThan
+                        | Opcode::GreaterThan
+                        | Opcode::LessThanOrEqual
+                        | Opcode::GreaterThanOrEqual
+                        | Opcode::Min
+                        | Opcode::Max => {
+                            require(stack, 2)?;
+                            let b = pop_num(stack)?;
+                            let a = pop_num(stack)?;
+                            let res = match op {
+                                Opcode::Add => a + b,
+                                Opcode::Sub => a - b,
+                                Opcode::BoolAnd => (a != 0 && b != 0) as i64,
+                                Opcode::BoolOr => (a != 0 || b != 0) as i64,
+                                Opcode::NumEqual | Opcode::NumEqualVerify => (a == b) as i64,
+                                Opcode::NumNotEqual => (a != b) as i64,
+                                Opcode::LessThan => (a < b) as i64,
+                                Opcode::GreaterThan => (a > b) as i64,
+                                Opcode::LessThanOrEqual => (a <= b) as i64,
+                                Opcode::GreaterThanOrEqual => (a >= b) as i64,
+                                Opcode::Min => a.min(b),
+                                _ => a.max(b),
+                            };
+                            if op != Opcode::NumEqualVerify {
+                                stack.push(encode_num(res));
+                            } else if res == 0 {
+                                return Err(InterpreterError::NumEqualVerify);
+                            }
+                        }
+                        Opcode::Within => {
+                            require(stack, 3)?;
+                            let max = pop_num(stack)?;
+                            let min = pop_num(stack)?;
+                            let x = pop_num(stack)?;
+                            stack.push(bool_bytes(min <= x && x < max));
+                        }
+                        Opcode::Ripemd160 | Opcode::Sha1 | Opcode::Sha256 | Opcode::Hash160 | Opcode::Hash256 => {
+                            let item = pop(stack)?;
+                            stack.push(match op {
+                                Opcode::Ripemd160 => ripemd160(&item).to_vec(),
+                                Opcode::Sha1 => sha1(&item).to_vec(),
+                                Opcode::Sha256 => sha256(&item).to_vec(),
+                                Opcode::Hash160 => hash160(&item).to_vec(),
+                                _ => hash256(&item).to_vec(),
+                            });
+                        }
+                        Opcode::CodeSeparator => {
+                            code_start = instructions.offset();
+                            exec_data.code_separator_pos = position;
+                        }
+                        Opcode::CheckSig | Opcode::CheckSigVerify => {
+                            require(stack, 2)?;
+                            let pubkey = pop(stack)?;
+                            let sig = pop(stack)?;
+                            let success = if tapscript {
+                                checksig_tapscript(&sig, &pubkey, checker, exec_data)?
+                            } else {
+                                let code = script_code(script, code_start, sig_version, std::slice::from_ref(&sig));
+                                check_signature_encoding(&sig, flags)?;
+                                checker.check_ecdsa(&sig, &pubkey, &code, sig_version)
+                            };
+                            if op == Opcode::CheckSig {
+                                stack.push(bool_bytes(success));
+                            } else if !success {
+                                return Err(InterpreterError::CheckSigVerify);
+                            }
+                        }
+                        Opcode::CheckSigAdd => {
+                            if !tapscript {
+                                return Err(InterpreterError::BadOpcode(op));
+                            }
+                            require(stack, 3)?;
+                            let pubkey = pop(stack)?;
+                            let n = pop_num(stack)?;
+                            let sig = pop(stack)?;
+                            let success = checksig_tapscript(&sig, &pubkey, checker, exec_data)?;
+                            stack.push(encode_num(n + success as i64));
+                        }
+                        Opcode::CheckMultiSig | Opcode::CheckMultiSigVerify => {
+                            if tapscript {
+                                return Err(InterpreterError::TapscriptCheckMultiSig);
+                            }
+                            // from the top: key count, keys, signature count,
+                            // signatures and the dummy an old bug also pops
+                            let key_count = script_num(top(stack, 1)?, 4)?;
+                            if !(0..=MAX_PUBKEYS_PER_MULTISIG).contains(&key_count) {
+                                return Err(InterpreterError::PubkeyCount);
+                            }
+                            let key_count = key_count as usize;
+                            op_count += key_count;
+                            if op_count > MAX_OPS_PER_SCRIPT {
+                                return Err(InterpreterError::OpCount);
+                            }
+                            let sig_count = script_num(top(stack, 2 + key_count)?, 4)?;
+                            if sig_count < 0 || sig_count as usize > key_count {
+                                return Err(InterpreterError::SigCount);
+                            }
+                            let sig_count = sig_count as usize;
+                            require(stack, 3 + key_count + sig_count)?;
+
+                            // both lists start with the last pushed
+                            let len = stack.len();
+                            let keys: Vec<Vec<u8>> = (0..key_count).map(|i| stack[len - 2 - i].clone()).collect();
+                            let sigs: Vec<Vec<u8>> =
+                                (0..sig_count).map(|i| stack[len - 3 - key_count - i].clone()).collect();
+                            let code = script_code(script, code_start, sig_version, &sigs);
+                            let (mut key, mut sig) = (0, 0);
+                            let mut success = true;
+                            while success && sig < sig_count {
+                                check_signature_encoding(&sigs[sig], flags)?;
+                                if checker.check_ecdsa(&sigs[sig], &keys[key], &code, sig_version) {
+                                    sig += 1;
+                                }
+                                key += 1;
+                                // each key is tried once, in order
+                                success = sig_count - sig <= key_count - key;
+                            }
+
+                            stack.truncate(len - 2 - key_count - sig_count);
+                            let dummy = pop(stack)?;
+                            if flags.contains(VerifyFlags::NULLDUMMY) && !dummy.is_empty() {
+                                return Err(InterpreterError::SigNullDummy);
+                            }
+                            if op == Opcode::CheckMultiSig {
+                                stack.push(bool_bytes(success));
+                            } else if !success {
+                                return Err(InterpreterError::CheckMultiSigVerify);
+                            }
+                        }
+                        _ => return Err(InterpreterError::BadOpcode(op)),
+                    }
+                }
+            }
+        }
+
+        if stack.len() + alt_stack.len() > MAX_STACK_SIZE {
+            return Err(InterpreterError::StackSize);
+        }
+        position += 1;
+    }
+
+    if !conditions.is_empty() {
+        return Err(InterpreterError::UnbalancedConditional);
+    }
+    Ok(())
+}
+
+/// Runs a witness script on the rest of the witness, which must leave a
+/// single true element
+fn execute_witness_script(
+    mut stack: Vec<Vec<u8>>,
+    script: &Script,
+    flags: VerifyFlags,
+    checker: &TransactionChecker,
+    sig_version: SigVersion,
+    exec_data: &mut ExecData,
+) -> Result<(), InterpreterError> {
+    if sig_version == SigVersion::Tapscript {
+        // any OP_SUCCESS makes the script valid, before anything else is checked
+        for instruction in script.instructions() {
+            if let Instruction::Op(op) = instruction? {
+                if is_op_success(op.to_u8()) {
+                    return Ok(());
+                }
+            }
+        }
+        if stack.len() > MAX_STACK_SIZE {
+            return Err(InterpreterError::StackSize);
+        }
+    }
+    if let Some(item) = stack.iter().find(|item| item.len() > MAX_SCRIPT_ELEMENT_SIZE) {
+        return Err(InterpreterError::PushSize(item.len()));
+    }
+
+    eval_script(&mut stack, script, flags, checker, sig_version, exec_data)?;
+    if stack.len() != 1 {
+        return Err(InterpreterError::CleanStack);
+    }
+    if !cast_to_bool(&stack[0]) {
+        return Err(InterpreterError::EvalFalse);
+    }
+    Ok(())
+}
+
+/// Serialized size of a witness, which sets a tapscript's signature budget
+fn witness_size(witness: &[Vec<u8>]) -> usize {
+    let items: usize = witness
+        .iter()
+        .map(|item| compact_size::encoded_len(item.len() as u64) + item.len())
+        .sum();
+    compact_size::encoded_len(witness.len() as u64) + items
+}
+
+/// BIP-341: a single signature for the key path, otherwise a script, its
+/// control block and the script's inputs
+fn verify_taproot(
+    mut stack: Vec<Vec<u8>>,
+    output_key: &[u8; 32],
+    flags: VerifyFlags,
+    checker: &TransactionChecker,
+) -> Result<(), InterpreterError> {
+    if stack.is_empty() {
+        return Err(InterpreterError::WitnessProgramWitnessEmpty);
+    }
+    let mut exec_data = ExecData {
+        validation_weight_left: witness_size(&stack) as i64 + VALIDATION_WEIGHT_PER_SIGOP,
+        code_separator_pos: 0xffffffff,
+        ..Default::default()
+    };
+    if stack.len() >= 2 && stack.last().is_some_and(|item| item.first() == Some(&ANNEX_TAG)) {
+        exec_data.annex = stack.pop();
+    }
+
+    if stack.len() == 1 {
+        return checker.check_schnorr(&stack[0], output_key, exec_data.annex.as_deref(), None);
+    }
+    let control = stack.pop().unwrap();
+    let script = Script::from_bytes(stack.pop().unwrap());
+    let control_block = ControlBlock::parse(&control).map_err(|_| InterpreterError::TaprootWrongControlSize)?;
+    if !control_block.verify(output_key, &script) {
+        return Err(InterpreterError::WitnessProgramMismatch);
+    }
+    // other leaf versions are left to future soft forks
+    if control_block.leaf_version != LEAF_VERSION_TAPSCRIPT {
+        return Ok(());
+    }
+    exec_data.tapleaf_hash = tap_leaf_hash(control_block.leaf_version, &script);
+    execute_witness_script(stack, &script, flags, checker, SigVersion::Tapscript, &mut exec_data)
+}
+
+fn verify_witness_program(
+    witness: &[Vec<u8>],
+    version: u8,
+    program: &[u8],
+    flags: VerifyFlags,
+    checker: &TransactionChecker,
+    is_p2sh: bool,
+) -> Result<(), InterpreterError> {
+    let mut stack = witness.to_vec();
+    match (version, program.len()) {
+        (0, 32) => {
+            let witness_script = Script::from_bytes(stack.pop().ok_or(InterpreterError::WitnessProgramWitnessEmpty)?);
+            if sha256(witness_script.as_bytes()) != program {
+                return Err(InterpreterError::WitnessProgramMismatch);
+            }
+            execute_witness_script(
+                stack,
+                &witness_script,
+                flags,
+                checker,
+                SigVersion::WitnessV0,
+                &mut ExecData::default(),
+            )
+        }
+        (0, 20) => {
+            if stack.len() != 2 {
+                return Err(InterpreterError::WitnessProgramMismatch);
+            }
+            let script_code = Script::p2pkh(program.try_into().unwrap());
+            execute_witness_script(
+                stack,
+                &script_code,
+                flags,
+                checker,
+                SigVersion::WitnessV0,
+                &mut ExecData::default(),
+            )
+        }
+        (0, _) => Err(InterpreterError::WitnessProgramWrongLength),
+        // wrapped in P2SH, taproot outputs stay anyone-can-spend
+        (1, 32) if !is_p2sh && flags.contains(VerifyFlags::TAPROOT) => {
+            verify_taproot(stack, program.try_into().unwrap(), flags, checker)
+        }
+        // unknown versions are left to future soft forks
+        _ => Ok(()),
+    }
+}
+
+/// Checks that `script_sig` and `witness` satisfy `script_pubkey`,
+/// following P2SH redeem scripts and witness programs
+pub fn verify_script(
+    script_sig: &Script,
+    script_pubkey: &Script,
+    witness: &[Vec<u8>],
+    flags: VerifyFlags,
+    checker: &TransactionChecker,
+) -> Result<(), InterpreterError> {
+    let mut exec_data = ExecData::default();
+    let mut stack = Vec::new();
+    eval_script(&mut stack, script_sig, flags, checker, SigVersion::Base, &mut exec_data)?;
+    let script_sig_stack = stack.clone();
+    eval_script(
+        &mut stack,
+        script_pubkey,
+        flags,
+        checker,
+        SigVersion::Base,
+        &mut exec_data,
+    )?;
+    if !stack.last().is_some_and(|top| cast_to_bool(top)) {
+        return Err(InterpreterError::EvalFalse);
+    }
+
+    let mut had_witness = false;
+    if flags.contains(VerifyFlags::WITNESS) {
+        if let Some((version, program)) = script_pubkey.witness_program() {
+            had_witness = true;
+            if !script_sig.is_empty() {
+                return Err(InterpreterError::WitnessMalleated);
+            }
+            verify_witness_program(witness, version, program, flags, checker, false)?;
+        }
+    }
+
+    if flags.contains(VerifyFlags::P2SH) && script_pubkey.is_p2sh() {
+        if !is_push_only(script_sig) {
+            return Err(InterpreterError::SigPushOnly);
+        }
+        let mut stack = script_sig_stack;
+        // the hash check above needed it, so it's there
+        let redeem_script = Script::from_bytes(stack.pop().expect("script_sig pushed the redeem script"));
+        eval_script(
+            &mut stack,
+            &redeem_script,
+            flags,
+            checker,
+            SigVersion::Base,
+            &mut exec_data,
+        )?;
+        if !stack.last().is_some_and(|top| cast_to_bool(top)) {
+            return Err(InterpreterError::EvalFalse);
+        }
+
+        if flags.contains(VerifyFlags::WITNESS) {
+            if let Some((version, program)) = redeem_script.witness_program() {
+                had_witness = true;
+                if *script_sig != Script::new().push_slice(redeem_script.as_bytes()) {
+                    return Err(InterpreterError::WitnessMalleatedP2sh);
+                }
+                verify_witness_program(witness, version, program, flags, checker, true)?;
+            }
+        }
+    }
+
+    if flags.contains(VerifyFlags::WITNESS) && !had_witness && !witness.is_empty() {
+        return Err(InterpreterError::WitnessUnexpected);
+    }
+    Ok(())
+}
+
+/// Why `Transaction::verify` rejected a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// One spent output is needed per input
+    PrevoutCount { expected: usize, got: usize },
+    /// Every failing input's index and what its scripts hit
+    Inputs(Vec<(usize, InterpreterError)>),
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::PrevoutCount { expected, got } => write!(f, "expected {} prevouts, got {}", expected, got),
+            VerifyError::Inputs(errors) => {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|(index, e)| format!("input {}: {}", index, e))
+                    .collect();
+                write!(f, "{}", errors.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl Transaction {
+    /// Runs every input's scripts against the output it spends under
+    /// today's consensus rules. `prevouts` are the spent outputs in input
+    /// order; their amounts matter for segwit and taproot signatures
+    pub fn verify(&self, prevouts: &[TxOut]) -> Result<(), VerifyError> {
+        self.verify_with_flags(prevouts, VerifyFlags::CONSENSUS)
+    }
+
+    pub fn verify_with_flags(&self, prevouts: &[TxOut], flags: VerifyFlags) -> Result<(), VerifyError> {
+        if prevouts.len() != self.inputs.len() {
+            return Err(VerifyError::PrevoutCount {
+                expected: self.inputs.len(),
+                got: prevouts.len(),
+            });
+        }
+        let cache = SighashCache::new(self);
+        let errors: Vec<(usize, InterpreterError)> = self
+            .inputs
+            .iter()
+            .zip(prevouts)
+            .enumerate()
+            .filter_map(|(index, (input, prevout))| {
+                let checker = TransactionChecker::new(&cache, index, prevouts);
+                verify_script(
+                    &input.script_sig,
+                    &prevout.script_pubkey,
+                    &input.witness,
+                    flags,
+                    &checker,
+                )
+                .err()
+                .map(|e| (index, e))
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(VerifyError::Inputs(errors));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod interpreter_tests {
+    use super::*;
+    use crate::crypto::schnorr::xonly;
+    use crate::curves::secp256k1::ecc::PrivateKey;
+    use crate::multisig::Multisig;
+    use crate::sign::{
+        schnorr_signature, sign_p2pkh_input, sign_p2sh_multisig_input, sign_p2wpkh_input, sign_p2wsh_multisig_input,
+        sign_taproot_keypath, sign_taproot_script_path,
+    };
+    use crate::taproot::{TapTree, TaprootSpendInfo};
+    use crate::tx::{OutPoint, TxIn};
+
+    fn input(vout: u32, sequence: u32) -> TxIn {
+        TxIn {
+            previous_output: OutPoint { txid: [0x44; 32], vout },
+            script_sig: Script::new(),
+            sequence,
+            witness: Vec::new(),
+        }
+    }
+
+    fn spend(version: u32, inputs: Vec<TxIn>, locktime: u32) -> Transaction {
+        Transaction {
+            version,
+            inputs,
+            outputs: vec![TxOut {
+                amount: 90_000,
+                script_pubkey: Script::p2wpkh(&[0x22; 20]),
+            }],
+            locktime,
+        }
+    }
+
+    /// Runs a bare script as input 0 of `tx`, returning the final stack
+    fn eval_in(tx: &Transaction, script: &Script) -> Result<Vec<Vec<u8>>, InterpreterError> {
+        let prevouts = vec![
+            TxOut {
+                amount: 0,
+                script_pubkey: Script::new()
+            };
+            tx.inputs.len()
+        ];
+        let cache = SighashCache::new(tx);
+        let checker = TransactionChecker::new(&cache, 0, &prevouts);
+        let mut stack = Vec::new();
+        eval_script(
+            &mut stack,
+            script,
+            VerifyFlags::CONSENSUS,
+            &checker,
+            SigVersion::Base,
+            &mut ExecData::default(),
+        )?;
+        Ok(stack)
+    }
+
+    fn eval(script: &Script) -> Result<Vec<Vec<u8>>, InterpreterError> {
+        eval_in(&spend(1, vec![input(0, 0xffffffff)], 0), script)
+    }
+
+    #[test]
+    fn test_eval_script() {
+        let s = |ops: &[Opcode]| ops.iter().fold(Script::new(), |script, &op| script.push_opcode(op));
+
+        let sum = s(&[Opcode::Op2, Opcode::Op3, Opcode::Add, Opcode::Op5, Opcode::Equal]);
+        assert_eq!(eval(&sum).unwrap(), vec![vec![1]]);
+        let branch = s(&[
+            Opcode::Op0,
+            Opcode::If,
+            Opcode::Op2,
+            Opcode::Else,
+            Opcode::Op3,
+            Opcode::EndIf,
+        ]);
+        assert_eq!(eval(&branch).unwrap(), vec![vec![3]]);
+        let roll = s(&[Opcode::Op1, Opcode::Op2, Opcode::Op3, Opcode::Op2, Opcode::Roll]);
+        assert_eq!(eval(&roll).unwrap(), vec![vec![2], vec![3], vec![1]]);
+        let within = Script::new()
+            .push_int(5)
+            .push_int(-1)
+            .push_int(6)
+            .push_opcode(Opcode::Within);
+        assert_eq!(eval(&within).unwrap(), vec![vec![1]]);
+        let hash = Script::new().push_slice(b"abc").push_opcode(Opcode::Sha1);
+        assert_eq!(eval(&hash).unwrap()[0], sha1(b"abc"));
+
+        assert_eq!(
+            eval(&s(&[Opcode::Op1, Opcode::If])),
+            Err(InterpreterError::UnbalancedConditional)
+        );
+        assert_eq!(eval(&s(&[Opcode::Else])), Err(InterpreterError::UnbalancedConditional));
+        assert_eq!(eval(&s(&[Opcode::Add])), Err(InterpreterError::InvalidStackOperation));
+        assert_eq!(
+            eval(&s(&[Opcode::Op1, Opcode::Return])),
+            Err(InterpreterError::OpReturn)
+        );
+        // disabled opcodes and OP_VERIF fail even in a branch that isn't taken
+        let cat = s(&[Opcode::Op0, Opcode::If, Opcode::Cat, Opcode::EndIf]);
+        assert_eq!(eval(&cat), Err(InterpreterError::DisabledOpcode(Opcode::Cat)));
+        let verif = s(&[Opcode::Op0, Opcode::If, Opcode::VerIf, Opcode::EndIf]);
+        assert_eq!(eval(&verif), Err(InterpreterError::BadOpcode(Opcode::VerIf)));
+        let ver = s(&[Opcode::Op0, Opcode::If, Opcode::Ver, Opcode::EndIf, Opcode::Op1]);
+        assert_eq!(eval(&ver).unwrap(), vec![vec![1]]);
+        // arithmetic takes at most 4-byte operands
+        let wide = Script::new().push_slice(&[0, 0, 0, 0, 1]).push_opcode(Opcode::Add1);
+        assert_eq!(eval(&wide), Err(InterpreterError::NumOverflow));
+        let push = Script::new().push_slice(&[0; 521]);
+        assert_eq!(eval(&push), Err(InterpreterError::PushSize(521)));
+        let ops = s(&[Opcode::Nop; 202]);
+        assert_eq!(eval(&ops), Err(InterpreterError::OpCount));
+    }
+
+    #[test]
+    fn test_find_and_delete() {
+        let run = |script: &str, pattern: &str| {
+            find_and_delete(&Script::from_hex(script).unwrap(), &hex::decode(pattern).unwrap()).to_hex()
+        };
+        // bitcoind's script_FindAndDelete cases
+        assert_eq!(run("0302ff030302ff03", "0302ff03"), "");
+        // only at instruction boundaries
+        assert_eq!(run("0302ff030302ff03", "02"), "0302ff030302ff03");
+        assert_eq!(run("0003feed", "03feed"), "00");
+        assert_eq!(run("0003feed", "00"), "03feed");
+        assert_eq!(run("02feed5169", "feed51"), "02feed5169");
+        assert_eq!(run("02feed5169", "02feed51"), "69");
+    }
+
+    #[test]
+    fn test_lock_times() {
+        let cltv = |n: i64| Script::new().push_int(n).push_opcode(Opcode::CheckLockTimeVerify);
+        let tx = spend(1, vec![input(0, 0xfffffffe)], 100);
+        assert!(eval_in(&tx, &cltv(100)).is_ok());
+        assert_eq!(eval_in(&tx, &cltv(101)), Err(InterpreterError::UnsatisfiedLockTime));
+        assert_eq!(eval_in(&tx, &cltv(-1)), Err(InterpreterError::NegativeLockTime));
+        // heights and times don't compare
+        assert_eq!(
+            eval_in(&tx, &cltv(LOCKTIME_THRESHOLD as i64)),
+            Err(InterpreterError::UnsatisfiedLockTime)
+        );
+        // a final sequence disables the lock time
+        let tx = spend(1, vec![input(0, 0xffffffff)], 100);
+        assert_eq!(eval_in(&tx, &cltv(100)), Err(InterpreterError::UnsatisfiedLockTime));
+
+        let csv = |n: i64| Script::new().push_int(n).push_opcode(Opcode::CheckSequenceVerify);
+        let tx = spend(2, vec![input(0, 10)], 0);
+        assert!(eval_in(&tx, &csv(10)).is_ok());
+        assert_eq!(eval_in(&tx, &csv(11)), Err(InterpreterError::UnsatisfiedLockTime));
+        assert_eq!(
+            eval_in(&tx, &csv(10 | SEQUENCE_LOCKTIME_TYPE_FLAG as i64)),
+            Err(InterpreterError::UnsatisfiedLockTime)
+        );
+        // the disable flag turns it back into a NOP
+        assert!(eval_in(&tx, &csv(SEQUENCE_LOCKTIME_DISABLE_FLAG as i64)).is_ok());
+        // BIP-68 sequences need version 2
+        let tx = spend(1, vec![input(0, 10)], 0);
+        assert_eq!(eval_in(&tx, &csv(10)), Err(InterpreterError::UnsatisfiedLockTime));
+    }
+
+    #[test]
+    fn test_verify_transaction() {
+        let key = |n: u32| PrivateKey::from(BigUint::from(n)).unwrap();
+        let (alice, bob, carol) = (key(0xa11ce), key(0xb0b), key(0xca201));
+        let multisig = Multisig::new(2, vec![alice.public_key(), bob.public_key(), carol.public_key()]).unwrap();
+        let leaf = Script::new()
+            .push_slice(&xonly(&bob.public_key()))
+            .push_opcode(Opcode::CheckSig);
+        let spend_info = TaprootSpendInfo::new(xonly(&alice.public_key()), Some(TapTree::leaf(leaf.clone()))).unwrap();
+        let (output_key, _) = crate::taproot::tweak_public_key(&xonly(&carol.public_key()), None).unwrap();
+
+        let prevout = |amount, script_pubkey| TxOut { amount, script_pubkey };
+        let prevouts = vec![
+            prevout(10_000, Script::p2pkh(&hash160(&alice.public_key().sec(true)))),
+            prevout(20_000, Script::p2wpkh(&hash160(&bob.public_key().sec(true)))),
+            prevout(30_000, multisig.p2sh_script_pubkey().unwrap()),
+            prevout(40_000, multisig.p2wsh_script_pubkey()),
+            prevout(50_000, Script::p2tr(&output_key)),
+            prevout(60_000, spend_info.script_pubkey()),
+        ];
+        let mut tx = spend(2, (0..6).map(|vout| input(vout, 0xfffffffd)).collect(), 0);
+        sign_p2pkh_input(&mut tx, 0, &prevouts[0], &alice).unwrap();
+        sign_p2wpkh_input(&mut tx, 1, &prevouts[1], &bob).unwrap();
+        sign_p2sh_multisig_input(&mut tx, 2, &prevouts[2], &multisig, &[alice.clone(), carol.clone()]).unwrap();
+        sign_p2wsh_multisig_input(&mut tx, 3, &prevouts[3], &multisig, &[bob.clone(), carol.clone()]).unwrap();
+        sign_taproot_keypath(&mut tx, 4, &prevouts, &carol, None, TapSighashType::All).unwrap();
+        sign_taproot_script_path(&mut tx, 5, &prevouts, &bob, &spend_info, &leaf, TapSighashType::Default).unwrap();
+        assert_eq!(tx.verify(&prevouts), Ok(()));
+
+        assert_eq!(
+            tx.verify(&prevouts[1..]),
+            Err(VerifyError::PrevoutCount { expected: 6, got: 5 })
+        );
+
+        // segwit and taproot signatures commit to the amounts, legacy ones don't
+        let mut wrong_amounts = prevouts.clone();
+        for prevout in &mut wrong_amounts {
+            prevout.amount += 1;
+        }
+        let errors = vec![
+            (1, InterpreterError::EvalFalse),
+            (3, InterpreterError::EvalFalse),
+            (4, InterpreterError::SchnorrSig),
+            (5, InterpreterError::SchnorrSig),
+        ];
+        assert_eq!(tx.verify(&wrong_amounts), Err(VerifyError::Inputs(errors)));
+
+        // every signature covers the outputs
+        let mut tampered = tx.clone();
+        tampered.outputs[0].amount += 1;
+        let Err(VerifyError::Inputs(errors)) = tampered.verify(&prevouts) else {
+            panic!("tampered transaction verified");
+        };
+        assert_eq!(
+            errors.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+
+        // witness programs must have empty script_sigs, and others no witness
+        let mut malleated = tx.clone();
+        malleated.inputs[1].script_sig = Script::new().push_opcode(Opcode::Op1);
+        malleated.inputs[0].witness = vec![vec![1]];
+        let errors = vec![
+            (0, InterpreterError::WitnessUnexpected),
+            (1, InterpreterError::WitnessMalleated),
+        ];
+        assert_eq!(malleated.verify(&prevouts), Err(VerifyError::Inputs(errors)));
+
+        // without the segwit and taproot flags their outputs are anyone-can-spend
+        let Err(VerifyError::Inputs(errors)) = tampered.verify_with_flags(&prevouts, VerifyFlags::P2SH) else {
+            panic!("tampered legacy inputs verified");
+        };
+        assert_eq!(errors.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_tapscript() {
+        let key = |n: u32| PrivateKey::from(BigUint::from(n)).unwrap();
+        let (internal, alice, bob) = (key(0x7a9), key(0xa11ce), key(0xb0b));
+        let multi_a = Script::new()
+            .push_slice(&xonly(&alice.public_key()))
+            .push_opcode(Opcode::CheckSig)
+            .push_slice(&xonly(&bob.public_key()))
+            .push_opcode(Opcode::CheckSigAdd)
+            .push_int(2)
+            .push_opcode(Opcode::NumEqual);
+        let success = Script::new().push_opcode(Opcode::Reserved);
+        let multisig = Script::new()
+            .push_opcode(Opcode::Op0)
+            .push_opcode(Opcode::Op0)
+            .push_opcode(Opcode::CheckMultiSig);
+        let tree = TapTree::branch(
+            TapTree::leaf(multi_a.clone()),
+            TapTree::branch(TapTree::leaf(success.clone()), TapTree::leaf(multisig.clone())),
+        );
+        let spend_info = TaprootSpendInfo::new(xonly(&internal.public_key()), Some(tree)).unwrap();
+        let prevouts = vec![TxOut {
+            amount: 100_000,
+            script_pubkey: spend_info.script_pubkey(),
+        }];
+        let tx = spend(2, vec![input(0, 0xffffffff)], 0);
+
+        let with_witness = |witness: Vec<Vec<u8>>| {
+            let mut tx = tx.clone();
+            tx.inputs[0].witness = witness;
+            tx.verify(&prevouts)
+        };
+        let script_path = |script: &Script| {
+            let leaf_hash = tap_leaf_hash(LEAF_VERSION_TAPSCRIPT, script);
+            let script_path = ScriptPath {
+                leaf_hash,
+                code_separator_pos: 0xffffffff,
+            };
+            SighashCache::new(&tx)
+                .taproot_signature_hash(
+                    0,
+                    &Prevouts::All(&prevouts),
+                    None,
+                    Some(script_path),
+                    TapSighashType::Default,
+                )
+                .unwrap()
+        };
+        let control = |script: &Script| spend_info.control_block(script).unwrap().serialize();
+
+        let sighash = script_path(&multi_a);
+        let sign = |key: &PrivateKey| schnorr_signature(key, &sighash, TapSighashType::Default);
+        let witness = vec![sign(&bob), sign(&alice), multi_a.as_bytes().to_vec(), control(&multi_a)];
+        assert_eq!(with_witness(witness), Ok(()));
+        // an empty signature counts as a no
+        let witness = vec![sign(&bob), Vec::new(), multi_a.as_bytes().to_vec(), control(&multi_a)];
+        assert_eq!(
+            with_witness(witness),
+            Err(VerifyError::Inputs(vec![(0, InterpreterError::EvalFalse)]))
+        );
+        // any other invalid one fails the script
+        let witness = vec![
+            sign(&alice),
+            sign(&alice),
+            multi_a.as_bytes().to_vec(),
+            control(&multi_a),
+        ];
+        assert_eq!(
+            with_witness(witness),
+            Err(VerifyError::Inputs(vec![(0, InterpreterError::SchnorrSig)]))
+        );
+
+        assert_eq!(
+            with_witness(vec![success.as_bytes().to_vec(), control(&success)]),
+            Ok(())
+        );
+        assert_eq!(
+            with_witness(vec![multisig.as_bytes().to_vec(), control(&multisig)]),
+            Err(VerifyError::Inputs(vec![(0, InterpreterError::TapscriptCheckMultiSig)]))
+        );
+        // the control block has to commit to the script
+        assert_eq!(
+            with_witness(vec![success.as_bytes().to_vec(), control(&multisig)]),
+            Err(VerifyError::Inputs(vec![(0, InterpreterError::WitnessProgramMismatch)]))
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use num_bigint::{BigInt, BigUint};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::curves::ec_point::{AdditionCase, AdditionTrace, Point};
+use crate::curves::secp256k1::ecc::SigningTrace;
+
+// LaTeX for lecture notes and worked solutions, made from the values the
+// code actually computed. Output is math-mode source without the
+// surrounding $ or \[ \], so it drops into inline or display math alike;
+// the traces are `aligned` blocks, which need amsmath. Numbers past 64
+// bits are in typewriter hex, like the `{:#}` display
+
+fn number(n: &BigInt) -> String {
+    if n.bits() <= 64 {
+        n.to_string()
+    } else {
+        format!("\\mathtt{{{:#x}}}", n)
+    }
+}
+
+fn unsigned(n: &BigUint) -> String {
+    number(&BigInt::from(n.clone()))
+}
+
+impl FieldElement {
+    /// "192 \in \mathbb{F}_{223}"
+    pub fn to_latex(&self) -> String {
+        format!(
+            "{} \\in \\mathbb{{F}}_{{{}}}",
+            number(&self.num()),
+            number(&self.prime())
+        )
+    }
+}
+
+impl Point {
+    /// "\left(192, 105\right)", or "\mathcal{O}" for the point at infinity
+    pub fn to_latex(&self) -> String {
+        match (self.x(), self.y()) {
+            (Some(x), Some(y)) => format!("\\left({}, {}\\right)", number(&x.num()), number(&y.num())),
+            _ => "\\mathcal{O}".to_string(),
+        }
+    }
+}
+
+impl AdditionTrace {
+    /// The slope and the coordinates of the sum, one aligned line each,
+    /// reduced mod p; the identity and inverse cases are a single line
+    pub fn to_latex(&self) -> String {
+        let (Some(numerator), Some(denominator), Some(slope), Some(x3), Some(y3)) =
+            (&self.numerator, &self.denominator, &self.slope, &self.x3, &self.y3)
+        else {
+            return match self.case {
+                AdditionCase::Identity => "P + \\mathcal{O} = P".to_string(),
+                _ => "P + (-P) = \\mathcal{O}".to_string(),
+            };
+        };
+        let formula = match self.case {
+            AdditionCase::Doubling => "\\frac{3x_1^2 + a}{2y_1}",
+            _ => "\\frac{y_2 - y_1}{x_2 - x_1}",
+        };
+        let p = number(&slope.prime());
+        [
+            "\\begin{aligned}".to_string(),
+            format!(
+                "m &= {} = \\frac{{{}}}{{{}}} = {} \\pmod{{{}}} \\\\",
+                formula,
+                number(&numerator.num()),
+                number(&denominator.num()),
+                number(&slope.num()),
+                p
+            ),
+            format!("x_3 &= m^2 - x_1 - x_2 = {} \\pmod{{{}}} \\\\", number(&x3.num()), p),
+            format!("y_3 &= m(x_1 - x_3) - y_1 = {} \\pmod{{{}}}", number(&y3.num()), p),
+            "\\end{aligned}".to_string(),
+        ]
+        .join("\n")
+    }
+}
+
+impl SigningTrace {
+    /// The steps of `Display`, one aligned line each
+    pub fn to_latex(&self) -> String {
+        let mut lines = vec![
+            "\\begin{aligned}".to_string(),
+            format!("z &= {} \\\\", unsigned(&self.z)),
+            format!("k &= {} \\\\", unsigned(&self.k)),
+            format!("R &= kG = {} \\\\", self.r_point.to_latex()),
+            format!("r &= x_R \\bmod n = {} \\\\", unsigned(&self.r)),
+            format!("s &= k^{{-1}}(z + rd) \\bmod n = {}", unsigned(&self.s)),
+        ];
+        if self.s_negated() {
+            lines.last_mut().expect("lines").push_str(" \\\\");
+            lines.push(format!(
+                "s &> n / 2, \\text{{ so }} s = n - s = {}",
+                unsigned(&self.low_s)
+            ));
+        }
+        lines.push("\\end{aligned}".to_string());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod latex_tests {
+    use super::*;
+    use crate::curves::ec_point::Curve;
+    use crate::curves::secp256k1::ecc::PrivateKey;
+
+    fn element(n: i64) -> FieldElement {
+        FieldElement::from(BigInt::from(n), BigInt::from(223)).unwrap()
+    }
+
+    #[test]
+    fn test_elements_and_points() {
+        assert_eq!(element(192).to_latex(), "192 \\in \\mathbb{F}_{223}");
+        let curve = Curve::new(element(0), element(7)).unwrap();
+        let p = Point::on_curve(Some(element(192)), Some(element(105)), &curve).unwrap();
+        assert_eq!(p.to_latex(), "\\left(192, 105\\right)");
+        assert_eq!(Point::infinity_on(&curve).to_latex(), "\\mathcal{O}");
+        assert!(crate::curves::secp256k1::ecc::generator()
+            .to_latex()
+            .starts_with("\\left(\\mathtt{0x79be667ef9dcbbac"));
+    }
+
+    #[test]
+    fn test_addition_trace() {
+        // (170, 142) + (60, 139) = (220, 181) and 2 (47, 71) = (36, 111) on
+        // y² = x³ + 7 over F_223
+        let curve = Curve::new(element(0), element(7)).unwrap();
+        let point = |x, y| Point::on_curve(Some(element(x)), Some(element(y)), &curve).unwrap();
+        let (_, trace) = point(170, 142).add_traced(point(60, 139));
+        assert_eq!(
+            trace.to_latex(),
+            "\\begin{aligned}\n\
+             m &= \\frac{y_2 - y_1}{x_2 - x_1} = \\frac{220}{113} = 221 \\pmod{223} \\\\\n\
+             x_3 &= m^2 - x_1 - x_2 = 220 \\pmod{223} \\\\\n\
+             y_3 &= m(x_1 - x_3) - y_1 = 181 \\pmod{223}\n\
+             \\end{aligned}"
+        );
+        let (sum, trace) = point(47, 71).add_traced(point(47, 71));
+        assert_eq!(sum, point(36, 111));
+        assert!(trace.to_latex().contains("m &= \\frac{3x_1^2 + a}{2y_1}"));
+
+        let (_, trace) = point(47, 71).add_traced(point(47, 152));
+        assert_eq!(trace.to_latex(), "P + (-P) = \\mathcal{O}");
+        let (_, trace) = point(47, 71).add_traced(Point::infinity_on(&curve));
+        assert_eq!(trace.to_latex(), "P + \\mathcal{O} = P");
+    }
+
+    #[test]
+    fn test_signing_trace() {
+        let key = PrivateKey::from(BigUint::from(12345_u32)).unwrap();
+        let (signature, trace) = key.sign_traced(&BigUint::from(0x1234_u32));
+        let latex = trace.to_latex();
+        assert!(latex.starts_with("\\begin{aligned}\nz &= 4660 \\\\\nk &= \\mathtt{0x"));
+        assert!(latex.contains(&format!("r &= x_R \\bmod n = \\mathtt{{{:#x}}}", signature.r())));
+        assert_eq!(latex.contains("n - s"), trace.s_negated());
+        assert!(latex.ends_with("\n\\end{aligned}"));
+    }
+}
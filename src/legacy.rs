@@ -0,0 +1,232 @@
+// The flat paths from before the module tree, kept so existing imports
+// still compile. `#[deprecated]` on a module doesn't warn on the paths
+// through it, so each item is a deprecated alias or wrapper of its new home
+
+use num_bigint::{BigInt, BigUint};
+use rand::{CryptoRng, RngCore};
+
+use crate::arithmetic;
+use crate::crypto;
+use crate::curves;
+use crate::curves::secp256k1 as k1;
+
+pub mod field_element {
+    use super::*;
+
+    #[deprecated(note = "use moneda::arithmetic::FieldElement")]
+    pub type FieldElement = arithmetic::FieldElement;
+    #[deprecated(note = "use moneda::arithmetic::FieldError")]
+    pub type FieldError = arithmetic::FieldError;
+}
+
+pub mod polynomial {
+    use super::*;
+
+    #[deprecated(note = "use moneda::arithmetic::Polynomial")]
+    pub type Polynomial = arithmetic::Polynomial;
+}
+
+pub mod ec_point {
+    use super::*;
+
+    #[deprecated(note = "use moneda::curves::PointError")]
+    pub type PointError = curves::PointError;
+    #[deprecated(note = "use moneda::curves::Curve")]
+    pub type Curve = curves::Curve;
+    #[deprecated(note = "use moneda::curves::Point")]
+    pub type Point = curves::Point;
+    #[deprecated(note = "use moneda::curves::AdditionCase")]
+    pub type AdditionCase = curves::AdditionCase;
+    #[deprecated(note = "use moneda::curves::AdditionTrace")]
+    pub type AdditionTrace = curves::AdditionTrace;
+}
+
+pub mod ecc {
+    use super::*;
+    use crate::curves::Point;
+
+    #[deprecated(note = "use moneda::curves::secp256k1::P")]
+    pub const P: [u64; 4] = k1::P;
+    #[deprecated(note = "use moneda::curves::secp256k1::N")]
+    pub const N: [u64; 4] = k1::N;
+    #[deprecated(note = "use moneda::curves::secp256k1::A")]
+    pub const A: [u64; 4] = k1::A;
+    #[deprecated(note = "use moneda::curves::secp256k1::B")]
+    pub const B: [u64; 4] = k1::B;
+    #[deprecated(note = "use moneda::curves::secp256k1::GX")]
+    pub const GX: [u64; 4] = k1::GX;
+    #[deprecated(note = "use moneda::curves::secp256k1::GY")]
+    pub const GY: [u64; 4] = k1::GY;
+
+    #[deprecated(note = "use moneda::curves::secp256k1::from_limbs")]
+    pub fn from_limbs(limbs: &[u64; 4]) -> BigUint {
+        k1::from_limbs(limbs)
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::prime")]
+    pub fn prime() -> BigInt {
+        k1::prime()
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::order")]
+    pub fn order() -> BigUint {
+        k1::order()
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::generator")]
+    pub fn generator() -> Point {
+        k1::generator()
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::to_32_bytes")]
+    pub fn to_32_bytes(n: &BigInt) -> [u8; 32] {
+        k1::to_32_bytes(n)
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::inverse_mod_order")]
+    pub fn inverse_mod_order(a: &BigUint) -> BigUint {
+        k1::inverse_mod_order(a)
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::infinity")]
+    pub fn infinity() -> Point {
+        k1::infinity()
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::multi_scalar_mul")]
+    pub fn multi_scalar_mul(scalars: &[BigUint], points: &[Point]) -> Point {
+        k1::multi_scalar_mul(scalars, points)
+    }
+
+    #[cfg(feature = "parallel")]
+    #[deprecated(note = "use moneda::curves::secp256k1::par_multi_scalar_mul")]
+    pub fn par_multi_scalar_mul(scalars: &[BigUint], points: &[Point]) -> Point {
+        k1::par_multi_scalar_mul(scalars, points)
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::KeyError")]
+    pub type KeyError = k1::KeyError;
+    #[deprecated(note = "use moneda::curves::secp256k1::PrivateKey")]
+    pub type PrivateKey = k1::PrivateKey;
+    #[deprecated(note = "use moneda::curves::secp256k1::SigningTrace")]
+    pub type SigningTrace = k1::SigningTrace;
+    #[deprecated(note = "use moneda::curves::secp256k1::PublicKey")]
+    pub type PublicKey = k1::PublicKey;
+    #[deprecated(note = "use moneda::curves::secp256k1::SignatureError")]
+    pub type SignatureError = k1::SignatureError;
+    #[deprecated(note = "use moneda::curves::secp256k1::Signature")]
+    pub type Signature = k1::Signature;
+}
+
+pub mod context {
+    use super::*;
+
+    #[deprecated(note = "use moneda::curves::secp256k1::Context")]
+    pub type Context = k1::Context;
+
+    #[deprecated(note = "use moneda::curves::secp256k1::context")]
+    pub fn context() -> &'static k1::Context {
+        k1::context()
+    }
+}
+
+pub mod backend {
+    use super::*;
+    use crate::curves::Point;
+
+    #[deprecated(note = "use moneda::curves::secp256k1::backend::NAME")]
+    pub const NAME: &str = k1::backend::NAME;
+
+    #[deprecated(note = "use moneda::curves::secp256k1::backend::mul_generator")]
+    pub fn mul_generator(k: &BigUint) -> Point {
+        k1::backend::mul_generator(k)
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::backend::mul")]
+    pub fn mul(k: &BigUint, point: &Point) -> Point {
+        k1::backend::mul(k, point)
+    }
+
+    #[deprecated(note = "use moneda::curves::secp256k1::backend::mul_add")]
+    pub fn mul_add(u: &BigUint, v: &BigUint, point: &Point) -> Point {
+        k1::backend::mul_add(u, v, point)
+    }
+}
+
+pub mod kzg {
+    use super::*;
+
+    #[deprecated(note = "use moneda::crypto::kzg::KzgError")]
+    pub type KzgError = crypto::kzg::KzgError;
+    #[deprecated(note = "use moneda::crypto::kzg::Setup")]
+    pub type Setup = crypto::kzg::Setup;
+    #[deprecated(note = "use moneda::crypto::kzg::Opening")]
+    pub type Opening = crypto::kzg::Opening;
+    #[deprecated(note = "use moneda::crypto::kzg::MultiOpening")]
+    pub type MultiOpening = crypto::kzg::MultiOpening;
+
+    #[deprecated(note = "use moneda::crypto::kzg::fr")]
+    pub fn fr(num: impl Into<BigInt>) -> arithmetic::FieldElement {
+        crypto::kzg::fr(num)
+    }
+}
+
+pub mod schnorr {
+    use super::*;
+    use crate::curves::secp256k1::{KeyError, PrivateKey, PublicKey};
+
+    #[deprecated(note = "use moneda::crypto::schnorr::xonly")]
+    pub fn xonly(public_key: &PublicKey) -> [u8; 32] {
+        crypto::schnorr::xonly(public_key)
+    }
+
+    #[deprecated(note = "use moneda::crypto::schnorr::lift_x")]
+    pub fn lift_x(x: &[u8; 32]) -> Result<PublicKey, KeyError> {
+        crypto::schnorr::lift_x(x)
+    }
+
+    #[deprecated(note = "use moneda::crypto::schnorr::even_y_secret")]
+    pub fn even_y_secret(private_key: &PrivateKey) -> BigUint {
+        crypto::schnorr::even_y_secret(private_key)
+    }
+
+    #[deprecated(note = "use moneda::crypto::schnorr::sign")]
+    pub fn sign(private_key: &PrivateKey, msg: &[u8], aux_rand: &[u8; 32]) -> [u8; 64] {
+        crypto::schnorr::sign(private_key, msg, aux_rand)
+    }
+
+    #[deprecated(note = "use moneda::crypto::schnorr::sign_with_nonce")]
+    pub fn sign_with_nonce(private_key: &PrivateKey, msg: &[u8], k0: &BigUint) -> [u8; 64] {
+        crypto::schnorr::sign_with_nonce(private_key, msg, k0)
+    }
+
+    #[deprecated(note = "use moneda::crypto::schnorr::verify")]
+    pub fn verify(public_key: &[u8; 32], msg: &[u8], sig: &[u8; 64]) -> bool {
+        crypto::schnorr::verify(public_key, msg, sig)
+    }
+
+    #[deprecated(note = "use moneda::crypto::schnorr::verify_batch")]
+    pub fn verify_batch(items: &[([u8; 32], &[u8], [u8; 64])]) -> bool {
+        crypto::schnorr::verify_batch(items)
+    }
+
+    #[deprecated(note = "use moneda::crypto::schnorr::verify_batch_with_rng")]
+    pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(items: &[([u8; 32], &[u8], [u8; 64])], rng: &mut R) -> bool {
+        crypto::schnorr::verify_batch_with_rng(items, rng)
+    }
+
+    #[cfg(feature = "parallel")]
+    #[deprecated(note = "use moneda::crypto::schnorr::par_verify_batch")]
+    pub fn par_verify_batch(items: &[([u8; 32], &[u8], [u8; 64])]) -> bool {
+        crypto::schnorr::par_verify_batch(items)
+    }
+
+    #[cfg(feature = "parallel")]
+    #[deprecated(note = "use moneda::crypto::schnorr::par_verify_batch_with_rng")]
+    pub fn par_verify_batch_with_rng<R: RngCore + CryptoRng>(
+        items: &[([u8; 32], &[u8], [u8; 64])],
+        rng: &mut R,
+    ) -> bool {
+        crypto::schnorr::par_verify_batch_with_rng(items, rng)
+    }
+}
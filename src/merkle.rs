@@ -0,0 +1,530 @@
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use crate::crypto::hash::hash256;
+use crate::encoding::compact_size;
+use crate::encoding::consensus::impl_consensus_codec;
+use crate::encoding::decode::{Context, DecodeError};
+use crate::tx::{read_array, read_u32, read_var_bytes, TxError};
+
+/// Combines two child hashes into their parent
+pub trait MerkleHasher {
+    fn combine(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32];
+}
+
+/// Bitcoin's node hash: SHA256(SHA256(left || right))
+#[derive(Debug, Clone)]
+pub struct DoubleSha256;
+
+impl MerkleHasher for DoubleSha256 {
+    fn combine(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut data = [0_u8; 64];
+        data[..32].copy_from_slice(left);
+        data[32..].copy_from_slice(right);
+        hash256(&data)
+    }
+}
+
+/// What to do with the last node of a level that has an odd number of nodes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OddNode {
+    /// Pair it with itself, as Bitcoin does. Note this makes [a, b, c] and
+    /// [a, b, c, c] share a root (CVE-2012-2459)
+    Duplicate,
+    /// Carry it up to the next level unchanged
+    Promote,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MerkleError {
+    NoLeaves,
+    IndexOutOfRange(usize),
+    /// More transactions than fit in a block
+    TooManyTransactions(u32),
+    /// The partial tree ran out of bits or hashes, or had some left over
+    InvalidPartialTree,
+    /// Two identical siblings, which could hide a duplicated transaction
+    /// (CVE-2012-2459)
+    IdenticalSiblings,
+    /// The partial tree doesn't commit to the header's merkle root
+    RootMismatch,
+}
+
+impl Display for MerkleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MerkleError::NoLeaves => write!(f, "a merkle tree needs at least one leaf"),
+            MerkleError::IndexOutOfRange(i) => write!(f, "leaf {} is out of range", i),
+            MerkleError::TooManyTransactions(n) => write!(f, "{} transactions do not fit in a block", n),
+            MerkleError::InvalidPartialTree => write!(f, "malformed partial merkle tree"),
+            MerkleError::IdenticalSiblings => write!(f, "partial merkle tree has identical siblings"),
+            MerkleError::RootMismatch => write!(f, "partial merkle tree does not match the merkle root"),
+        }
+    }
+}
+
+impl std::error::Error for MerkleError {}
+
+/// One sibling on the path from a leaf to the root
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofStep {
+    pub hash: [u8; 32],
+    /// Whether the sibling sits to the left of the running hash
+    pub is_left: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerkleProof {
+    pub leaf: [u8; 32],
+    pub index: usize,
+    pub path: Vec<ProofStep>,
+}
+
+impl MerkleProof {
+    pub fn root<H: MerkleHasher>(&self) -> [u8; 32] {
+        self.path.iter().fold(self.leaf, |acc, step| {
+            if step.is_left {
+                H::combine(&step.hash, &acc)
+            } else {
+                H::combine(&acc, &step.hash)
+            }
+        })
+    }
+
+    pub fn verify<H: MerkleHasher>(&self, root: &[u8; 32]) -> bool {
+        self.root::<H>() == *root
+    }
+}
+
+/// Fully materialized merkle tree, `levels[0]` being the leaves
+#[derive(Debug, Clone)]
+pub struct MerkleTree<H> {
+    levels: Vec<Vec<[u8; 32]>>,
+    odd: OddNode,
+    hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    pub fn from_leaves(leaves: &[[u8; 32]], odd: OddNode) -> Result<Self, MerkleError> {
+        Self::build(leaves, odd, |level| {
+            level.chunks(2).map(|pair| Self::parent(pair, odd)).collect()
+        })
+    }
+
+    /// `from_leaves` with each level's hashes spread over rayon's thread
+    /// pool, for trees of many thousands of leaves
+    #[cfg(feature = "parallel")]
+    pub fn par_from_leaves(leaves: &[[u8; 32]], odd: OddNode) -> Result<Self, MerkleError> {
+        use rayon::prelude::*;
+
+        Self::build(leaves, odd, |level| {
+            level.par_chunks(2).map(|pair| Self::parent(pair, odd)).collect()
+        })
+    }
+
+    fn build(
+        leaves: &[[u8; 32]],
+        odd: OddNode,
+        parents: impl Fn(&[[u8; 32]]) -> Vec<[u8; 32]>,
+    ) -> Result<Self, MerkleError> {
+        if leaves.is_empty() {
+            return Err(MerkleError::NoLeaves);
+        }
+        let mut levels = vec![leaves.to_vec()];
+        while levels.last().unwrap().len() > 1 {
+            levels.push(parents(levels.last().unwrap()));
+        }
+        Ok(Self {
+            levels,
+            odd,
+            hasher: PhantomData,
+        })
+    }
+
+    /// The parent of a pair of nodes, or of a level's odd last node
+    fn parent(pair: &[[u8; 32]], odd: OddNode) -> [u8; 32] {
+        match (pair, odd) {
+            ([left, right], _) => H::combine(left, right),
+            ([last], OddNode::Duplicate) => H::combine(last, last),
+            ([last], OddNode::Promote) => *last,
+            _ => unreachable!("chunks of two"),
+        }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels.last().unwrap()[0]
+    }
+
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        &self.levels[0]
+    }
+
+    pub fn proof(&self, index: usize) -> Result<MerkleProof, MerkleError> {
+        if index >= self.leaves().len() {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
+        let mut path = Vec::new();
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = i ^ 1;
+            if sibling < level.len() {
+                path.push(ProofStep {
+                    hash: level[sibling],
+                    is_left: sibling < i,
+                });
+            } else if self.odd == OddNode::Duplicate {
+                path.push(ProofStep {
+                    hash: level[i],
+                    is_left: false,
+                });
+            }
+            i /= 2;
+        }
+        Ok(MerkleProof {
+            leaf: self.levels[0][index],
+            index,
+            path,
+        })
+    }
+}
+
+/// Upper bound on the transactions of a block: 4M weight units at no less
+/// than 240 each
+const MAX_BLOCK_TRANSACTIONS: u32 = 4_000_000 / 240;
+
+/// Matched txids with their positions in the block
+type Matches = Vec<(u32, [u8; 32])>;
+
+/// The BIP-37 partial merkle tree sent in merkleblock messages: a
+/// depth-first walk of the txid tree where one flag bit per visited node
+/// says whether it leads to a matched transaction. Subtrees without matches
+/// are pruned to a single hash, so the tree proves inclusion of the matches
+/// in O(matches * log(n)) space
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialMerkleTree {
+    pub num_transactions: u32,
+    pub bits: Vec<bool>,
+    pub hashes: Vec<[u8; 32]>,
+}
+
+impl PartialMerkleTree {
+    /// Number of nodes at `height` above the leaves
+    fn width(&self, height: u32) -> u32 {
+        (self.num_transactions + (1 << height) - 1) >> height
+    }
+
+    fn height(&self) -> u32 {
+        let mut height = 0;
+        while self.width(height) > 1 {
+            height += 1;
+        }
+        height
+    }
+
+    /// Hash of the node at `height` and `pos`, missing right children
+    /// duplicating their left sibling as in the block's own tree
+    fn calc_hash(&self, height: u32, pos: u32, txids: &[[u8; 32]]) -> [u8; 32] {
+        if height == 0 {
+            return txids[pos as usize];
+        }
+        let left = self.calc_hash(height - 1, pos * 2, txids);
+        let right = if pos * 2 + 1 < self.width(height - 1) {
+            self.calc_hash(height - 1, pos * 2 + 1, txids)
+        } else {
+            left
+        };
+        DoubleSha256::combine(&left, &right)
+    }
+
+    fn build(&mut self, height: u32, pos: u32, txids: &[[u8; 32]], matches: &[bool]) {
+        let start = (pos << height) as usize;
+        let end = (((pos + 1) << height) as usize).min(txids.len());
+        let parent_of_match = matches[start..end].iter().any(|&m| m);
+        self.bits.push(parent_of_match);
+        if height == 0 || !parent_of_match {
+            let hash = self.calc_hash(height, pos, txids);
+            self.hashes.push(hash);
+        } else {
+            self.build(height - 1, pos * 2, txids, matches);
+            if pos * 2 + 1 < self.width(height - 1) {
+                self.build(height - 1, pos * 2 + 1, txids, matches);
+            }
+        }
+    }
+
+    /// Builds the tree for a block's txids (internal byte order), proving
+    /// those whose `matches` flag is set
+    pub fn from_txids(txids: &[[u8; 32]], matches: &[bool]) -> Self {
+        assert_eq!(txids.len(), matches.len(), "one match flag per txid");
+        let mut tree = Self {
+            num_transactions: txids.len() as u32,
+            bits: Vec::new(),
+            hashes: Vec::new(),
+        };
+        let height = tree.height();
+        tree.build(height, 0, txids, matches);
+        tree
+    }
+
+    fn extract(
+        &self,
+        height: u32,
+        pos: u32,
+        bits_used: &mut usize,
+        hashes_used: &mut usize,
+        matches: &mut Matches,
+    ) -> Result<[u8; 32], MerkleError> {
+        let parent_of_match = *self.bits.get(*bits_used).ok_or(MerkleError::InvalidPartialTree)?;
+        *bits_used += 1;
+        if height == 0 || !parent_of_match {
+            let hash = *self.hashes.get(*hashes_used).ok_or(MerkleError::InvalidPartialTree)?;
+            *hashes_used += 1;
+            if height == 0 && parent_of_match {
+                matches.push((pos, hash));
+            }
+            return Ok(hash);
+        }
+        let left = self.extract(height - 1, pos * 2, bits_used, hashes_used, matches)?;
+        let right = if pos * 2 + 1 < self.width(height - 1) {
+            let right = self.extract(height - 1, pos * 2 + 1, bits_used, hashes_used, matches)?;
+            if right == left {
+                return Err(MerkleError::IdenticalSiblings);
+            }
+            right
+        } else {
+            left
+        };
+        Ok(DoubleSha256::combine(&left, &right))
+    }
+
+    /// Walks the tree back up to its root, returning the root and the
+    /// matched txids with their positions in the block. The caller still
+    /// has to compare the root with the block header's
+    pub fn extract_matches(&self) -> Result<([u8; 32], Matches), MerkleError> {
+        if self.num_transactions == 0 {
+            return Err(MerkleError::NoLeaves);
+        }
+        if self.num_transactions > MAX_BLOCK_TRANSACTIONS {
+            return Err(MerkleError::TooManyTransactions(self.num_transactions));
+        }
+        if self.hashes.len() > self.num_transactions as usize || self.bits.len() < self.hashes.len() {
+            return Err(MerkleError::InvalidPartialTree);
+        }
+        let (mut bits_used, mut hashes_used) = (0, 0);
+        let mut matches = Vec::new();
+        let root = self.extract(self.height(), 0, &mut bits_used, &mut hashes_used, &mut matches)?;
+        // only the padding of the last flag byte may go unused
+        if bits_used.div_ceil(8) != self.bits.len().div_ceil(8) || hashes_used != self.hashes.len() {
+            return Err(MerkleError::InvalidPartialTree);
+        }
+        Ok((root, matches))
+    }
+
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        let num_transactions = read_u32(reader).context("num_transactions")?;
+        let hashes = (0..compact_size::read_size(reader).context("hashes")?)
+            .map(|i| read_array(reader).context(format_args!("hashes[{}]", i)))
+            .collect::<Result<_, _>>()?;
+        let flags = read_var_bytes(reader).context("flags")?;
+        let bits = (0..flags.len() * 8).map(|i| flags[i / 8] >> (i % 8) & 1 == 1).collect();
+        Ok(Self {
+            num_transactions,
+            bits,
+            hashes,
+        })
+    }
+
+    /// Flag bits are packed least significant bit first
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.num_transactions.to_le_bytes())?;
+        compact_size::write(writer, self.hashes.len() as u64)?;
+        for hash in &self.hashes {
+            writer.write_all(hash)?;
+        }
+        let mut flags = vec![0_u8; self.bits.len().div_ceil(8)];
+        for (i, _) in self.bits.iter().enumerate().filter(|(_, &bit)| bit) {
+            flags[i / 8] |= 1 << (i % 8);
+        }
+        compact_size::write(writer, flags.len() as u64)?;
+        writer.write_all(&flags)
+    }
+}
+
+impl_consensus_codec!(PartialMerkleTree, TxError);
+
+#[cfg(test)]
+mod merkle_tests {
+    use super::*;
+
+    // displayed (byte-reversed) hex, as block explorers show txids
+    fn reversed(s: &str) -> [u8; 32] {
+        let mut bytes: [u8; 32] = hex::decode(s).unwrap().try_into().unwrap();
+        bytes.reverse();
+        bytes
+    }
+
+    fn block_100000() -> Vec<[u8; 32]> {
+        [
+            "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+            "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+            "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+            "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+        ]
+        .iter()
+        .map(|s| reversed(s))
+        .collect()
+    }
+
+    #[test]
+    fn test_block_root() {
+        let tree = MerkleTree::<DoubleSha256>::from_leaves(&block_100000(), OddNode::Duplicate).unwrap();
+
+        assert_eq!(
+            tree.root(),
+            reversed("f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766")
+        );
+    }
+
+    #[test]
+    fn test_odd_leaves() {
+        let leaves = &block_100000()[..3];
+        let dup = MerkleTree::<DoubleSha256>::from_leaves(leaves, OddNode::Duplicate).unwrap();
+        let promote = MerkleTree::<DoubleSha256>::from_leaves(leaves, OddNode::Promote).unwrap();
+
+        assert_eq!(
+            dup.root(),
+            reversed("fa435470825de273081dcc706b25514c936fa6dc80ab965ce6970d68ddd0b553")
+        );
+        assert_eq!(
+            promote.root(),
+            reversed("feb0c95b99ce524c6fca2582059bc85f65a7d73abfc509de12ee2f80e7740d8b")
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_from_leaves() {
+        let leaves: Vec<[u8; 32]> = (0..1001_u32).map(|i| hash256(&i.to_le_bytes())).collect();
+        for odd in [OddNode::Duplicate, OddNode::Promote] {
+            let tree = MerkleTree::<DoubleSha256>::from_leaves(&leaves, odd).unwrap();
+            let par = MerkleTree::<DoubleSha256>::par_from_leaves(&leaves, odd).unwrap();
+            assert_eq!(par.levels, tree.levels);
+        }
+        assert_eq!(
+            MerkleTree::<DoubleSha256>::par_from_leaves(&[], OddNode::Duplicate).unwrap_err(),
+            MerkleError::NoLeaves
+        );
+    }
+
+    #[test]
+    fn test_single_leaf() {
+        let leaf = [7_u8; 32];
+        let tree = MerkleTree::<DoubleSha256>::from_leaves(&[leaf], OddNode::Duplicate).unwrap();
+
+        assert_eq!(tree.root(), leaf);
+        assert!(tree.proof(0).unwrap().path.is_empty());
+    }
+
+    #[test]
+    fn test_proofs() {
+        let leaves: Vec<[u8; 32]> = (0..5_u8).map(|i| [i; 32]).collect();
+        for odd in [OddNode::Duplicate, OddNode::Promote] {
+            let tree = MerkleTree::<DoubleSha256>::from_leaves(&leaves, odd).unwrap();
+            for i in 0..leaves.len() {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify::<DoubleSha256>(&tree.root()), "{:?} {}", odd, i);
+            }
+        }
+        let tree = MerkleTree::<DoubleSha256>::from_leaves(&leaves, OddNode::Duplicate).unwrap();
+        assert_eq!(
+            hex::encode(tree.root()),
+            "c855157c6d4dda84ba48e08dd32cc7ba1672665610f19d0f8899aee59209e2de"
+        );
+    }
+
+    #[test]
+    fn test_proof_rejects_wrong_leaf() {
+        let tree = MerkleTree::<DoubleSha256>::from_leaves(&block_100000(), OddNode::Duplicate).unwrap();
+        let mut proof = tree.proof(2).unwrap();
+        proof.leaf = [0; 32];
+
+        assert!(!proof.verify::<DoubleSha256>(&tree.root()));
+        assert_eq!(tree.proof(4), Err(MerkleError::IndexOutOfRange(4)));
+    }
+
+    #[test]
+    fn test_no_leaves() {
+        assert!(matches!(
+            MerkleTree::<DoubleSha256>::from_leaves(&[], OddNode::Duplicate),
+            Err(MerkleError::NoLeaves)
+        ));
+    }
+
+    #[test]
+    fn test_partial_tree() {
+        // serializations from rust-bitcoin
+        let txids = block_100000();
+        let cases = [
+            (
+                &txids[..],
+                &[false, true, false, true][..],
+                "0400000004876dd0a3ef4a2816ffd1c12ab649825a958b0ff3bb3d6f3e1250f13ddbf0148cc40297f730dd7b5a99567e\
+                 b8d27b78758f607507c52292d02d4031895b52f2ffc46e239ab7d28e2c019b6d66ad8fae98a56ef1f21aeecb94d1b171\
+                 8186f059631d0cb83721529a062d9675b98d6e5c587e4a770fc84ed00abc5a5de04568a6e9015b",
+            ),
+            (
+                &txids[..3],
+                &[false, false, true],
+                "030000000215b88c5107195bf09eb9da89b83d95b3d070079a3c5c5d3d17d0dcd873fbdaccc46e239ab7d28e2c019b6d\
+                 66ad8fae98a56ef1f21aeecb94d1b1718186f05963010d",
+            ),
+            (
+                &txids[..],
+                &[false; 4],
+                "04000000016657a9252aacd5c0b2940996ecff952228c3067cc38d4885efb5a4ac4247e9f30100",
+            ),
+        ];
+        for (txids, matches, expected) in cases {
+            let tree = PartialMerkleTree::from_txids(txids, matches);
+            let mut bytes = Vec::new();
+            tree.write(&mut bytes).unwrap();
+            assert_eq!(hex::encode(&bytes), expected);
+
+            let parsed = PartialMerkleTree::parse(&mut &bytes[..]).unwrap();
+            let (root, found) = parsed.extract_matches().unwrap();
+            let full = MerkleTree::<DoubleSha256>::from_leaves(txids, OddNode::Duplicate).unwrap();
+            assert_eq!(root, full.root());
+            let expected: Vec<_> = (0..txids.len() as u32)
+                .filter(|&i| matches[i as usize])
+                .map(|i| (i, txids[i as usize]))
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn test_partial_tree_invalid() {
+        let txids = block_100000();
+        let tree = PartialMerkleTree::from_txids(&txids, &[false, true, false, true]);
+
+        let mut short = tree.clone();
+        short.hashes.pop();
+        assert_eq!(short.extract_matches(), Err(MerkleError::InvalidPartialTree));
+        let mut extra = tree.clone();
+        extra.bits.extend([false; 8]);
+        assert_eq!(extra.extract_matches(), Err(MerkleError::InvalidPartialTree));
+        let mut none = tree.clone();
+        none.num_transactions = 0;
+        assert_eq!(none.extract_matches(), Err(MerkleError::NoLeaves));
+        none.num_transactions = 20_000;
+        assert_eq!(none.extract_matches(), Err(MerkleError::TooManyTransactions(20_000)));
+
+        // [a, b, c, c] proving the second c: the duplicate is rejected even
+        // though the root is the same as [a, b, c]
+        let mut duplicated = txids[..3].to_vec();
+        duplicated.push(txids[2]);
+        let tree = PartialMerkleTree::from_txids(&duplicated, &[false, false, false, true]);
+        assert_eq!(tree.extract_matches(), Err(MerkleError::IdenticalSiblings));
+    }
+}
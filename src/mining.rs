@@ -0,0 +1,275 @@
+use std::fmt::Display;
+
+use crate::block::{Block, BlockHeader};
+use crate::coinbase::{self, CoinbaseError};
+use crate::pow::{self, PowError};
+use crate::script::Script;
+use crate::tx::{Transaction, TxOut};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MiningError {
+    Pow(PowError),
+    /// The template's target is zero, which no hash meets
+    ZeroTarget,
+    Coinbase(CoinbaseError),
+    /// Every nonce and extra nonce in range was tried
+    Exhausted,
+}
+
+impl Display for MiningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MiningError::Pow(e) => write!(f, "{}", e),
+            MiningError::ZeroTarget => write!(f, "target is zero"),
+            MiningError::Coinbase(e) => write!(f, "{}", e),
+            MiningError::Exhausted => write!(f, "no nonce in range meets the target"),
+        }
+    }
+}
+
+impl std::error::Error for MiningError {}
+
+impl From<PowError> for MiningError {
+    fn from(e: PowError) -> Self {
+        MiningError::Pow(e)
+    }
+}
+
+impl From<CoinbaseError> for MiningError {
+    fn from(e: CoinbaseError) -> Self {
+        MiningError::Coinbase(e)
+    }
+}
+
+/// Everything needed to assemble a block on top of `prev_blockhash`
+/// except its coinbase, which is built per extra nonce. The payout gets
+/// the subsidy plus `fees`, which the caller totals from the inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockTemplate {
+    pub version: u32,
+    pub prev_blockhash: [u8; 32],
+    pub height: u32,
+    pub time: u32,
+    pub bits: u32,
+    /// Non-coinbase transactions, in block order
+    pub transactions: Vec<Transaction>,
+    pub fees: u64,
+    pub payout: Script,
+    /// Appended to the coinbase script_sig after the extra nonce
+    pub tag: Vec<u8>,
+    /// Largest header nonce tried before moving on to the next extra nonce
+    pub max_nonce: u32,
+}
+
+impl BlockTemplate {
+    pub fn new(prev_blockhash: [u8; 32], height: u32, time: u32, bits: u32, payout: Script) -> Self {
+        Self {
+            // BIP-9 version bits with nothing signalled
+            version: 0x20000000,
+            prev_blockhash,
+            height,
+            time,
+            bits,
+            transactions: Vec::new(),
+            fees: 0,
+            payout,
+            tag: Vec::new(),
+            max_nonce: u32::MAX,
+        }
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn transaction(mut self, tx: Transaction, fee: u64) -> Self {
+        self.transactions.push(tx);
+        self.fees += fee;
+        self
+    }
+
+    pub fn tag(mut self, tag: &[u8]) -> Self {
+        self.tag = tag.to_vec();
+        self
+    }
+
+    pub fn max_nonce(mut self, max_nonce: u32) -> Self {
+        self.max_nonce = max_nonce;
+        self
+    }
+
+    /// The coinbase for one extra nonce: BIP-34 height, the extra nonce as
+    /// 8 little-endian bytes, then the tag. It commits to the witnesses
+    /// when any transaction has them
+    pub fn coinbase(&self, extra_nonce: u64) -> Result<Transaction, CoinbaseError> {
+        let mut extra = extra_nonce.to_le_bytes().to_vec();
+        extra.extend_from_slice(&self.tag);
+        let payout = TxOut {
+            amount: coinbase::subsidy(self.height) + self.fees,
+            script_pubkey: self.payout.clone(),
+        };
+        let mut tx = coinbase::coinbase(self.height, &extra, vec![payout])?;
+        if self.transactions.iter().any(Transaction::has_witness) {
+            let wtxids: Vec<[u8; 32]> = self.transactions.iter().map(|tx| tx.wtxid()).collect();
+            coinbase::add_witness_commitment(&mut tx, &wtxids);
+        }
+        Ok(tx)
+    }
+
+    /// The unmined block for one extra nonce, its header committing to
+    /// the transactions and its nonce zero
+    pub fn block(&self, extra_nonce: u64) -> Result<Block, CoinbaseError> {
+        let mut txdata = vec![self.coinbase(extra_nonce)?];
+        txdata.extend(self.transactions.iter().cloned());
+        let mut block = Block {
+            header: BlockHeader {
+                version: self.version,
+                prev_blockhash: self.prev_blockhash,
+                merkle_root: [0; 32],
+                time: self.time,
+                bits: self.bits,
+                nonce: 0,
+            },
+            txdata,
+        };
+        block.header.merkle_root = block.compute_merkle_root().expect("a block has a coinbase").0;
+        Ok(block)
+    }
+
+    /// Searches nonces up to `max_nonce` for each extra nonce in turn,
+    /// rebuilding the coinbase and merkle root whenever the nonces run out
+    pub fn mine(&self, max_extra_nonce: u64) -> Result<Block, MiningError> {
+        let target = target_bytes(self.bits)?;
+        for extra_nonce in 0..=max_extra_nonce {
+            let mut block = self.block(extra_nonce)?;
+            if search_nonce(&mut block.header, &target, self.max_nonce) {
+                return Ok(block);
+            }
+        }
+        Err(MiningError::Exhausted)
+    }
+}
+
+/// The target as 32 big-endian bytes, so hashes compare bytewise
+fn target_bytes(bits: u32) -> Result<[u8; 32], MiningError> {
+    let target = pow::bits_to_target(bits)?.to_bytes_be();
+    if target == [0] {
+        return Err(MiningError::ZeroTarget);
+    }
+    let mut res = [0_u8; 32];
+    res[32 - target.len()..].copy_from_slice(&target);
+    Ok(res)
+}
+
+/// Sets the header's nonce to the first one up to `max_nonce` whose hash
+/// meets `target`, returning whether there was one
+fn search_nonce(header: &mut BlockHeader, target: &[u8; 32], max_nonce: u32) -> bool {
+    for nonce in 0..=max_nonce {
+        header.nonce = nonce;
+        let mut hash = header.hash();
+        // header hashes are little-endian numbers
+        hash.reverse();
+        if hash <= *target {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod mining_tests {
+    use super::*;
+    use crate::address::Network;
+    use crate::chain::Params;
+    use crate::tx::{OutPoint, TxIn};
+
+    fn spend(vout: u32, witness: bool) -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                previous_output: OutPoint { txid: [7; 32], vout },
+                script_sig: Script::new(),
+                sequence: u32::MAX,
+                witness: if witness {
+                    vec![vec![1; 71], vec![2; 33]]
+                } else {
+                    Vec::new()
+                },
+            }],
+            outputs: vec![TxOut {
+                amount: 10_000,
+                script_pubkey: Script::p2wpkh(&[3; 20]),
+            }],
+            locktime: 0,
+        }
+    }
+
+    #[test]
+    fn test_mine() {
+        let params = Params::new(Network::Regtest);
+        let genesis = &params.genesis;
+        let template = BlockTemplate::new(
+            genesis.hash(),
+            1,
+            genesis.time + 600,
+            genesis.bits,
+            Script::p2wpkh(&[9; 20]),
+        )
+        .transaction(spend(0, false), 1_000)
+        .transaction(spend(1, true), 2_500)
+        .tag(b"/moneda/");
+
+        let block = template.mine(0).unwrap();
+        assert!(pow::check_pow_with_limit(
+            &block.hash(),
+            block.header.bits,
+            &params.pow_limit()
+        ));
+        assert_eq!(block.validate(), Ok(()));
+        assert_eq!(block.header.prev_blockhash, genesis.hash());
+        assert_eq!(coinbase::coinbase_height(&block.txdata[0]), Some(1));
+        assert_eq!(block.txdata[0].outputs[0].amount, coinbase::subsidy(1) + 3_500);
+        assert!(coinbase::witness_commitment(&block.txdata[0]).is_some());
+        assert_eq!(block.txdata[1..], template.transactions[..]);
+    }
+
+    #[test]
+    fn test_extra_nonce() {
+        // one hash in 16 meets this target, so four nonces per extra nonce
+        // usually aren't enough
+        let template =
+            BlockTemplate::new([1; 32], 500_000, 1_600_000_000, 0x200fffff, Script::p2wpkh(&[9; 20])).max_nonce(3);
+        let block = template.mine(1_000).unwrap();
+        let extra_nonce = u64::from_le_bytes(
+            block.txdata[0].inputs[0].script_sig.as_bytes()[5..13]
+                .try_into()
+                .unwrap(),
+        );
+        assert!(block.header.nonce <= 3);
+        assert_eq!(block.validate(), Ok(()));
+        assert!(pow::bits_to_target(0x200fffff).unwrap() >= num_bigint::BigUint::from_bytes_le(&block.hash()));
+        // no earlier extra nonce had a solution
+        for earlier in 0..extra_nonce {
+            let mut header = template.block(earlier).unwrap().header;
+            assert!(!search_nonce(&mut header, &target_bytes(0x200fffff).unwrap(), 3));
+        }
+
+        assert_eq!(
+            BlockTemplate::new([1; 32], 1, 0, 0x03000000, Script::new()).mine(0),
+            Err(MiningError::ZeroTarget)
+        );
+        assert_eq!(
+            BlockTemplate::new([1; 32], 1, 0, 0x1d00ffff, Script::new())
+                .max_nonce(10)
+                .mine(2),
+            Err(MiningError::Exhausted)
+        );
+        assert_eq!(
+            BlockTemplate::new([1; 32], 1, 0, 0x1d00ffff, Script::new())
+                .tag(&[0; 100])
+                .mine(0),
+            Err(MiningError::Coinbase(CoinbaseError::ScriptSigTooLong(111)))
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt::Display;
+
+use crate::address::Network;
+use crate::crypto::hash::{hash160, sha256};
+use crate::curves::secp256k1::ecc::PublicKey;
+use crate::encoding::{base58, bech32};
+use crate::script::{decode_num, Instruction, Opcode, Script};
+
+/// OP_CHECKMULTISIG takes at most 20 keys
+pub const MAX_KEYS: usize = 20;
+/// Consensus limit on a pushed element, which bounds P2SH redeem scripts
+pub const MAX_REDEEM_SCRIPT_SIZE: usize = 520;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MultisigError {
+    /// Needs 1 <= required <= keys <= 20
+    InvalidThreshold { required: usize, keys: usize },
+    /// Too many keys to fit the redeem script in one push (15 compressed)
+    RedeemScriptTooLarge(usize),
+    /// The script isn't `<k> <keys> <n> OP_CHECKMULTISIG` with compressed keys
+    NotMultisig,
+    /// Spending takes exactly `required` signatures
+    SignatureCount { expected: usize, got: usize },
+}
+
+impl Display for MultisigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MultisigError::InvalidThreshold { required, keys } => {
+                write!(f, "invalid {}-of-{} multisig", required, keys)
+            }
+            MultisigError::RedeemScriptTooLarge(len) => {
+                write!(f, "redeem script of {} bytes exceeds {}", len, MAX_REDEEM_SCRIPT_SIZE)
+            }
+            MultisigError::NotMultisig => write!(f, "not a multisig script"),
+            MultisigError::SignatureCount { expected, got } => {
+                write!(f, "expected {} signatures, got {}", expected, got)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MultisigError {}
+
+/// A k-of-n policy over compressed keys, spendable with OP_CHECKMULTISIG
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multisig {
+    required: usize,
+    keys: Vec<PublicKey>,
+}
+
+impl Multisig {
+    /// Keeps the keys in the given order, which the script and every
+    /// spend must then follow
+    pub fn new(required: usize, keys: Vec<PublicKey>) -> Result<Self, MultisigError> {
+        if required == 0 || required > keys.len() || keys.len() > MAX_KEYS {
+            return Err(MultisigError::InvalidThreshold {
+                required,
+                keys: keys.len(),
+            });
+        }
+        Ok(Self { required, keys })
+    }
+
+    /// Sorts the keys by their compressed encoding (BIP-67), so cosigners
+    /// derive the same script whatever order they exchanged keys in
+    pub fn sorted(required: usize, mut keys: Vec<PublicKey>) -> Result<Self, MultisigError> {
+        keys.sort_by_key(|key| key.sec(true));
+        Self::new(required, keys)
+    }
+
+    /// Recognises `<k> <pubkey>... <n> OP_CHECKMULTISIG`
+    pub fn from_script(script: &Script) -> Result<Self, MultisigError> {
+        let instructions: Vec<Instruction> = script
+            .instructions()
+            .collect::<Result<_, _>>()
+            .map_err(|_| MultisigError::NotMultisig)?;
+        let [first, keys @ .., last, Instruction::Op(Opcode::CheckMultiSig)] = &instructions[..] else {
+            return Err(MultisigError::NotMultisig);
+        };
+        let number = |instruction: &Instruction| match instruction {
+            Instruction::Op(op) => op.small_int().map(usize::from),
+            Instruction::Push(_, data) => decode_num(data).and_then(|n| usize::try_from(n).ok()),
+        };
+        let required = number(first).ok_or(MultisigError::NotMultisig)?;
+        let total = number(last).ok_or(MultisigError::NotMultisig)?;
+        let keys = keys
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::Push(_, data) if data.len() == 33 => PublicKey::parse(data).ok(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(MultisigError::NotMultisig)?;
+        if keys.len() != total {
+            return Err(MultisigError::NotMultisig);
+        }
+        let multisig = Self::new(required, keys).map_err(|_| MultisigError::NotMultisig)?;
+        // non-minimal pushes would make a different script
+        if &multisig.script() != script {
+            return Err(MultisigError::NotMultisig);
+        }
+        Ok(multisig)
+    }
+
+    pub fn required(&self) -> usize {
+        self.required
+    }
+
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.keys
+    }
+
+    /// `<k> <pubkey>... <n> OP_CHECKMULTISIG`, the redeem or witness script.
+    /// Thresholds above 16 are pushed as script numbers
+    pub fn script(&self) -> Script {
+        let mut script = Script::new().push_int(self.required as i64);
+        for key in &self.keys {
+            script = script.push_slice(&key.sec(true));
+        }
+        script
+            .push_int(self.keys.len() as i64)
+            .push_opcode(Opcode::CheckMultiSig)
+    }
+
+    /// HASH160 of the redeem script, as committed to by P2SH
+    pub fn script_hash(&self) -> Result<[u8; 20], MultisigError> {
+        let script = self.script();
+        if script.len() > MAX_REDEEM_SCRIPT_SIZE {
+            return Err(MultisigError::RedeemScriptTooLarge(script.len()));
+        }
+        Ok(hash160(script.as_bytes()))
+    }
+
+    /// SHA256 of the witness script, as committed to by P2WSH
+    pub fn witness_script_hash(&self) -> [u8; 32] {
+        sha256(self.script().as_bytes())
+    }
+
+    pub fn p2sh_script_pubkey(&self) -> Result<Script, MultisigError> {
+        Ok(Script::p2sh(&self.script_hash()?))
+    }
+
+    pub fn p2wsh_script_pubkey(&self) -> Script {
+        Script::p2wsh(&self.witness_script_hash())
+    }
+
+    pub fn p2sh_address(&self, network: Network) -> Result<String, MultisigError> {
+        let mut payload = vec![network.p2sh_prefix()];
+        payload.extend_from_slice(&self.script_hash()?);
+        Ok(base58::encode_check(&payload))
+    }
+
+    pub fn p2wsh_address(&self, network: Network) -> String {
+        bech32::encode_segwit(network.hrp(), 0, &self.witness_script_hash())
+            .expect("a 32-byte v0 program is always valid")
+    }
+
+    fn check_signatures(&self, signatures: &[Vec<u8>]) -> Result<(), MultisigError> {
+        if signatures.len() != self.required {
+            return Err(MultisigError::SignatureCount {
+                expected: self.required,
+                got: signatures.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// `OP_0 <sig>... <redeem script>` for a P2SH spend. OP_CHECKMULTISIG
+    /// pops one element more than it uses, so an empty dummy comes first
+    /// (BIP-147 requires it to be empty). The signatures, with their hash
+    /// type bytes, must be in the order of the keys they belong to
+    pub fn script_sig(&self, signatures: &[Vec<u8>]) -> Result<Script, MultisigError> {
+        self.check_signatures(signatures)?;
+        let mut script = Script::new().push_opcode(Opcode::Op0);
+        for signature in signatures {
+            script = script.push_slice(signature);
+        }
+        Ok(script.push_slice(self.script().as_bytes()))
+    }
+
+    /// The P2WSH witness: the empty dummy, the signatures in key order and
+    /// the witness script
+    pub fn witness(&self, signatures: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, MultisigError> {
+        self.check_signatures(signatures)?;
+        let mut witness = vec![Vec::new()];
+        witness.extend(signatures.iter().cloned());
+        witness.push(self.script().as_bytes().to_vec());
+        Ok(witness)
+    }
+}
+
+#[cfg(test)]
+mod multisig_tests {
+    use super::*;
+    use crate::curves::secp256k1::ecc::PrivateKey;
+    use num_bigint::BigUint;
+
+    fn keys(secrets: &[u32]) -> Vec<PublicKey> {
+        secrets
+            .iter()
+            .map(|&s| PrivateKey::from(BigUint::from(s)).unwrap().public_key())
+            .collect()
+    }
+
+    const SCRIPT: &str = "52210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f817982102c6047f9441\
+                          ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee52102f9308a019258c31049344f85f8\
+                          9d5229b531c845836f99b08601f113bce036f953ae";
+
+    #[test]
+    fn test_script_and_addresses() {
+        // vectors from rust-bitcoin
+        let multisig = Multisig::sorted(2, keys(&[3, 1, 2])).unwrap();
+        assert_eq!(multisig.keys(), &keys(&[1, 2, 3])[..]);
+        assert_eq!(multisig.script().to_hex(), SCRIPT);
+        assert_eq!(
+            multisig.p2sh_address(Network::Mainnet).unwrap(),
+            "33hG2q39jRi2NqicRJB4ggY1J8EJm97Szz"
+        );
+        assert_eq!(
+            multisig.p2wsh_address(Network::Mainnet),
+            "bc1qztp0l0rwc8846ardl02fkyrrx43p96j47scz8l7qz3vnfteqc4eqtfqwcm"
+        );
+        assert_eq!(
+            multisig.p2wsh_script_pubkey().to_hex(),
+            "002012c2ffbc6ec1cf5d746dfbd49b1063356212ea55f43023ffc0145934af20c572"
+        );
+        assert_eq!(Multisig::from_script(&Script::from_hex(SCRIPT).unwrap()), Ok(multisig));
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(
+            Multisig::new(0, keys(&[1])),
+            Err(MultisigError::InvalidThreshold { required: 0, keys: 1 })
+        );
+        assert_eq!(
+            Multisig::new(3, keys(&[1, 2])),
+            Err(MultisigError::InvalidThreshold { required: 3, keys: 2 })
+        );
+        let many: Vec<u32> = (1..=21).collect();
+        assert!(Multisig::new(1, keys(&many)).is_err());
+
+        // 15 keys still fit P2SH, 16 only P2WSH
+        let fifteen = Multisig::new(15, keys(&many[..15])).unwrap();
+        assert!(fifteen.p2sh_address(Network::Mainnet).is_ok());
+        let twenty = Multisig::new(17, keys(&many[..20])).unwrap();
+        assert_eq!(twenty.script_hash(), Err(MultisigError::RedeemScriptTooLarge(685)));
+        // 17 and 20 are script numbers rather than small-int opcodes
+        assert_eq!(&twenty.script().as_bytes()[..2], [0x01, 17]);
+        assert_eq!(Multisig::from_script(&twenty.script()), Ok(twenty));
+    }
+
+    #[test]
+    fn test_not_multisig() {
+        let multisig = Multisig::new(1, keys(&[1, 2])).unwrap();
+        let mut bytes = multisig.script().as_bytes().to_vec();
+        let n = bytes.len() - 2;
+        bytes[n] = Opcode::Op3.to_u8();
+        for script in [
+            Script::p2wsh(&[0; 32]),
+            // claims three keys
+            Script::from_bytes(bytes),
+            Script::new()
+                .push_int(1)
+                .push_slice(&keys(&[1])[0].sec(false))
+                .push_int(1)
+                .push_opcode(Opcode::CheckMultiSig),
+        ] {
+            assert_eq!(Multisig::from_script(&script), Err(MultisigError::NotMultisig));
+        }
+    }
+
+    #[test]
+    fn test_spend_assembly() {
+        let multisig = Multisig::new(2, keys(&[1, 2, 3])).unwrap();
+        let signatures = vec![vec![0x30, 0x01], vec![0x30, 0x02]];
+        let witness = multisig.witness(&signatures).unwrap();
+        assert_eq!(witness.len(), 4);
+        assert!(witness[0].is_empty());
+        assert_eq!(witness[3], multisig.script().as_bytes());
+
+        let script_sig = multisig.script_sig(&signatures).unwrap();
+        assert!(script_sig.to_hex().starts_with("00023001023002"));
+        assert!(script_sig.to_hex().ends_with(SCRIPT));
+        assert_eq!(
+            multisig.witness(&signatures[..1]),
+            Err(MultisigError::SignatureCount { expected: 2, got: 1 })
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::sync::{Arc, OnceLock};
+
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::curves::ec_point::{Curve, Point};
+use crate::curves::secp256k1::ecc::KeyError;
+
+// The curves of protocols outside Bitcoin, by name: their domain
+// parameters as the standards print them, and a registry that turns each
+// into field elements and a generator once, on first use. secp256k1 is
+// listed too, so generic code can run on it, though its keys keep the
+// faster types of `ecc`
+
+/// Domain parameters of y^2 = x^3 + a x + b over F_p with a generator
+/// (gx, gy) of prime order n, as big-endian hex
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurveParams {
+    pub name: &'static str,
+    /// Other names the curve goes by, e.g. in OpenSSL or SSH
+    pub aliases: &'static [&'static str],
+    /// The ASN.1 object identifier, dotted, that names the curve in X.509
+    /// and PKCS#8 keys
+    pub oid: &'static str,
+    pub p: &'static str,
+    pub a: &'static str,
+    pub b: &'static str,
+    pub gx: &'static str,
+    pub gy: &'static str,
+    pub n: &'static str,
+}
+
+/// SEC 2 section 2.4.1
+pub const SECP256K1: CurveParams = CurveParams {
+    name: "secp256k1",
+    aliases: &[],
+    oid: "1.3.132.0.10",
+    p: "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+    a: "00",
+    b: "07",
+    gx: "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+    gy: "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+    n: "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+};
+
+/// NIST P-256 from FIPS 186-4 D.1.2.3, the curve of WebAuthn and most TLS
+pub const P256: CurveParams = CurveParams {
+    name: "P-256",
+    aliases: &["secp256r1", "prime256v1", "nistp256"],
+    oid: "1.2.840.10045.3.1.7",
+    p: "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+    a: "ffffffff00000001000000000000000000000000fffffffffffffffffffffffc",
+    b: "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b",
+    gx: "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+    gy: "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5",
+    n: "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+};
+
+/// RFC 5639 section 3.4. The Brainpool curves have a != 0 and primes
+/// without special form, generated verifiably at random
+pub const BRAINPOOL_P256R1: CurveParams = CurveParams {
+    name: "brainpoolP256r1",
+    aliases: &[],
+    oid: "1.3.36.3.3.2.8.1.1.7",
+    p: "a9fb57dba1eea9bc3e660a909d838d726e3bf623d52620282013481d1f6e5377",
+    a: "7d5a0975fc2c3057eef67530417affe7fb8055c126dc5c6ce94a4b44f330b5d9",
+    b: "26dc5c6ce94a4b44f330b5d9bbd77cbf958416295cf7e1ce6bccdc18ff8c07b6",
+    gx: "8bd2aeb9cb7e57cb2c4b482ffc81b7afb9de27e1e3bd23c23a4453bd9ace3262",
+    gy: "547ef835c3dac4fd97f8461a14611dc9c27745132ded8e545c1d54c72f046997",
+    n: "a9fb57dba1eea9bc3e660a909d838d718c397aa3b561a6f7901e0e82974856a7",
+};
+
+/// RFC 5639 section 3.6
+pub const BRAINPOOL_P384R1: CurveParams = CurveParams {
+    name: "brainpoolP384r1",
+    aliases: &[],
+    oid: "1.3.36.3.3.2.8.1.1.11",
+    p: "8cb91e82a3386d280f5d6f7e50e641df152f7109ed5456b412b1da197fb71123acd3a729901d1a71874700133107ec53",
+    a: "7bc382c63d8c150c3c72080ace05afa0c2bea28e4fb22787139165efba91f90f8aa5814a503ad4eb04a8c7dd22ce2826",
+    b: "04a8c7dd22ce28268b39b55416f0447c2fb77de107dcd2a62e880ea53eeb62d57cb4390295dbc9943ab78696fa504c11",
+    gx: "1d1c64f068cf45ffa2a63a81b7c13f6b8847a3e77ef14fe3db7fcafe0cbd10e8e826e03436d646aaef87b2e247d4af1e",
+    gy: "8abe1d7520f9c2a45cb1eb8e95cfd55262b70b29feec5864e19c054ff99129280e4646217791811142820341263c5315",
+    n: "8cb91e82a3386d280f5d6f7e50e641df152f7109ed5456b31f166e6cac0425a7cf3ab6af6b7fc3103b883202e9046565",
+};
+
+/// Every curve `named_curve` knows
+pub const CURVES: &[CurveParams] = &[SECP256K1, P256, BRAINPOOL_P256R1, BRAINPOOL_P384R1];
+
+/// A curve of the registry, ready to compute with
+#[derive(Debug)]
+pub struct NamedCurve {
+    params: CurveParams,
+    prime: BigInt,
+    order: BigUint,
+    curve: Arc<Curve>,
+    generator: Point,
+}
+
+fn registry() -> &'static [NamedCurve] {
+    static REGISTRY: OnceLock<Vec<NamedCurve>> = OnceLock::new();
+    REGISTRY.get_or_init(|| CURVES.iter().map(NamedCurve::new).collect())
+}
+
+/// The registered curve called `name` or one of its aliases, ignoring case
+pub fn named_curve(name: &str) -> Option<&'static NamedCurve> {
+    registry().iter().find(|curve| {
+        let params = &curve.params;
+        params.name.eq_ignore_ascii_case(name) || params.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+    })
+}
+
+/// The registered curve with the dotted object identifier `oid`
+pub fn curve_by_oid(oid: &str) -> Option<&'static NamedCurve> {
+    registry().iter().find(|curve| curve.params.oid == oid)
+}
+
+pub fn p256() -> &'static NamedCurve {
+    named_curve(P256.name).expect("registered")
+}
+
+impl NamedCurve {
+    fn new(params: &CurveParams) -> Self {
+        let int = |hex: &str| BigInt::parse_bytes(hex.as_bytes(), 16).expect("hex parameters");
+        let prime = int(params.p);
+        let element = |hex: &str| FieldElement::from(int(hex), prime.clone()).expect("below p");
+        let curve = Curve::new(element(params.a), element(params.b)).expect("one field");
+        let (gx, gy) = (element(params.gx), element(params.gy));
+        let generator = Point::on_curve(Some(gx), Some(gy), &curve).expect("the generator is on the curve");
+        let order = int(params.n).to_biguint().unwrap();
+        Self {
+            params: *params,
+            prime,
+            order,
+            curve,
+            generator,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.params.name
+    }
+
+    pub fn params(&self) -> &CurveParams {
+        &self.params
+    }
+
+    /// p, the size of the base field
+    pub fn prime(&self) -> &BigInt {
+        &self.prime
+    }
+
+    /// n, the order of the generator
+    pub fn order(&self) -> &BigUint {
+        &self.order
+    }
+
+    pub fn curve(&self) -> &Arc<Curve> {
+        &self.curve
+    }
+
+    pub fn generator(&self) -> &Point {
+        &self.generator
+    }
+
+    pub fn infinity(&self) -> Point {
+        Point::infinity_on(&self.curve)
+    }
+
+    /// Bytes in an encoded coordinate
+    pub fn field_len(&self) -> usize {
+        self.prime.bits().div_ceil(8) as usize
+    }
+
+    /// Bytes in an encoded scalar
+    pub fn scalar_len(&self) -> usize {
+        self.order.bits().div_ceil(8) as usize
+    }
+
+    pub fn mul_generator(&self, k: &BigUint) -> Point {
+        BigInt::from(k.clone()) * self.generator.clone()
+    }
+
+    /// Big-endian, zero-padded to `field_len`
+    pub fn coordinate_bytes(&self, element: &FieldElement) -> Vec<u8> {
+        pad(&element.num().to_biguint().unwrap(), self.field_len())
+    }
+
+    /// SEC1 serialization: 0x04 || x || y, or 0x02/0x03 || x when compressed
+    pub fn sec(&self, point: &Point, compressed: bool) -> Vec<u8> {
+        let (x, y) = (point.x().expect("not infinity"), point.y().expect("not infinity"));
+        let mut res = Vec::with_capacity(1 + 2 * self.field_len());
+        if compressed {
+            res.push(if y.is_odd() { 0x03 } else { 0x02 });
+            res.extend_from_slice(&self.coordinate_bytes(x));
+        } else {
+            res.push(0x04);
+            res.extend_from_slice(&self.coordinate_bytes(x));
+            res.extend_from_slice(&self.coordinate_bytes(y));
+        }
+        res
+    }
+
+    /// Either SEC1 form of a point other than infinity
+    pub fn parse_sec(&self, bytes: &[u8]) -> Result<Point, KeyError> {
+        let len = self.field_len();
+        let coordinate = |b: &[u8]| {
+            FieldElement::from(BigInt::from_bytes_be(Sign::Plus, b), self.prime.clone())
+                .map_err(|_| KeyError::InvalidEncoding)
+        };
+        let (x, y) = match bytes.first() {
+            Some(0x04) if bytes.len() == 1 + 2 * len => {
+                (coordinate(&bytes[1..1 + len])?, coordinate(&bytes[1 + len..])?)
+            }
+            Some(&prefix @ (0x02 | 0x03)) if bytes.len() == 1 + len => {
+                let x = coordinate(&bytes[1..])?;
+                let alpha =
+                    x.clone().pow(BigInt::from(3_u8)) + self.curve.a().clone() * x.clone() + self.curve.b().clone();
+                // every registered p is 3 mod 4, so alpha^((p + 1) / 4) is a
+                // square root whenever one exists
+                let beta = alpha.clone().pow((&self.prime + 1_u8) / 4_u8);
+                if beta.clone().pow(BigInt::from(2_u8)) != alpha {
+                    return Err(KeyError::InvalidEncoding);
+                }
+                let y = if beta.is_odd() == (prefix == 0x03) {
+                    beta
+                } else {
+                    FieldElement::reduce(-beta.num(), self.prime.clone()).unwrap()
+                };
+                (x, y)
+            }
+            _ => return Err(KeyError::InvalidEncoding),
+        };
+        Point::on_curve(Some(x), Some(y), &self.curve).map_err(|_| KeyError::InvalidEncoding)
+    }
+}
+
+// one entry per name, so comparing names compares curves
+impl PartialEq for NamedCurve {
+    fn eq(&self, other: &Self) -> bool {
+        self.params.name == other.params.name
+    }
+}
+
+/// Big-endian, zero-padded to `len` bytes
+pub(crate) fn pad(n: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = n.to_bytes_be();
+    let mut res = vec![0_u8; len.saturating_sub(bytes.len())];
+    res.extend_from_slice(&bytes);
+    res
+}
+
+#[cfg(test)]
+mod named_curve_tests {
+    use super::*;
+    use crate::curves::secp256k1::context::context;
+    use crate::curves::secp256k1::ecc::{PrivateKey, PublicKey};
+
+    #[test]
+    fn test_registry() {
+        assert_eq!(named_curve("prime256v1"), Some(p256()));
+        assert_eq!(named_curve("SECP256R1").unwrap().name(), "P-256");
+        assert_eq!(named_curve("curve25519"), None);
+
+        for params in CURVES {
+            let curve = named_curve(params.name).unwrap();
+            assert_eq!(curve.prime() % 4, BigInt::from(3));
+            assert!(curve.mul_generator(curve.order()).is_infinity());
+            assert_eq!(curve_by_oid(params.oid), Some(curve));
+        }
+        assert_eq!(curve_by_oid("1.2.840.10045.3.1.7"), Some(p256()));
+        assert_eq!(curve_by_oid("1.3.132.0.34"), None);
+
+        // the same secp256k1 as the rest of the crate
+        let secp256k1 = named_curve("secp256k1").unwrap();
+        assert_eq!(secp256k1.prime(), context().prime());
+        assert_eq!(secp256k1.order(), context().order());
+        assert_eq!(secp256k1.generator(), context().generator());
+    }
+
+    #[test]
+    fn test_sec() {
+        let secp256k1 = named_curve("secp256k1").unwrap();
+        let key = PrivateKey::from(BigUint::from(0xdeadbeef_u32)).unwrap().public_key();
+        for compressed in [true, false] {
+            let sec = secp256k1.sec(key.point(), compressed);
+            assert_eq!(sec, key.sec(compressed));
+            assert_eq!(PublicKey::from(secp256k1.parse_sec(&sec).unwrap()).unwrap(), key);
+        }
+
+        // a != 0 on P-256, so decompression needs the a x term
+        let curve = p256();
+        let point = curve.mul_generator(&BigUint::from(7_u8));
+        for compressed in [true, false] {
+            assert_eq!(curve.parse_sec(&curve.sec(&point, compressed)).unwrap(), point);
+        }
+        assert_eq!(
+            curve.parse_sec(&curve.sec(&point, true)[1..]),
+            Err(KeyError::InvalidEncoding)
+        );
+        // x = 2^256 - 1 is not below p
+        let mut beyond = [0xff; 33];
+        beyond[0] = 0x02;
+        assert_eq!(curve.parse_sec(&beyond), Err(KeyError::InvalidEncoding));
+
+        let curve = named_curve("brainpoolP384r1").unwrap();
+        let sec = hex::decode(
+            "0372d703c918f36deef2a0bb5bbb5f65c2a4823e5a760907e358cebca105e6c2c651c3f3205b08eec1ebc0feb17b4cca36",
+        )
+        .unwrap();
+        let point = curve.parse_sec(&sec).unwrap();
+        assert_eq!(point, curve.mul_generator(&BigUint::from(0xb0b_u32)));
+        assert_eq!(curve.sec(&point, true), sec);
+        assert_eq!(curve.sec(&point, false).len(), 97);
+    }
+
+    #[test]
+    fn test_group_law_with_a() {
+        // 2G, from OpenSSL: doubling is where a enters the group law
+        for (name, x, y) in [
+            (
+                "brainpoolP256r1",
+                "743cf1b8b5cd4f2eb55f8aa369593ac436ef044166699e37d51a14c2ce13ea0e",
+                "36ed163337deba9c946fe0bb776529da38df059f69249406892ada097eeb7cd4",
+            ),
+            (
+                "brainpoolP384r1",
+                "2282bc382a2f4dfcb95c3495d7b4fd590ad520b3eb6be4d6ec2f80c4e0f70df87c4ba74a09b553ebb427b58df9d59fca",
+                "0edda83773ac68735768d14a24f37a57ce9bedbc170921ce4d89dd051728fc3eb4b4ea69ab64fc288f1b29502b6e1d30",
+            ),
+        ] {
+            let curve = named_curve(name).unwrap();
+            assert!(!curve.curve().a().is_zero());
+            let g = curve.generator().clone();
+            let double = g.clone() + g.clone();
+            assert_eq!(hex::encode(curve.coordinate_bytes(double.x().unwrap())), x);
+            assert_eq!(hex::encode(curve.coordinate_bytes(double.y().unwrap())), y);
+
+            // (j + k) G = j G + k G, and G + (n - 1) G = infinity
+            let (j, k) = (BigUint::from(0xa11ce_u32), BigUint::from(0xb0b_u32));
+            assert_eq!(
+                curve.mul_generator(&(&j + &k)),
+                curve.mul_generator(&j) + curve.mul_generator(&k)
+            );
+            assert!((g + curve.mul_generator(&(curve.order() - 1_u8))).is_infinity());
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+
+use crate::address::Network;
+use crate::block::BlockHeader;
+use crate::chain::Params;
+use crate::pow;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainError {
+    /// The header at this index of the batch doesn't build on the one before
+    Disconnected(usize),
+    /// The header at this index of the batch doesn't meet its target, or
+    /// claims one easier than the network allows
+    InvalidPow(usize),
+}
+
+impl Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainError::Disconnected(i) => write!(f, "header {} does not connect to the chain", i),
+            ChainError::InvalidPow(i) => write!(f, "header {} has invalid proof of work", i),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+/// The best header chain seen so far, from the network's genesis block.
+/// Only proof of work and linkage are checked: difficulty adjustments,
+/// timestamps and reorgs are left to a full node
+#[derive(Debug, Clone)]
+pub struct HeaderChain {
+    network: Network,
+    pow_limit: BigUint,
+    headers: Vec<BlockHeader>,
+    /// hashes[h] is the hash of headers[h], in internal byte order
+    hashes: Vec<[u8; 32]>,
+}
+
+impl HeaderChain {
+    pub fn new(network: Network) -> Self {
+        let params = Params::new(network);
+        Self {
+            network,
+            pow_limit: params.pow_limit(),
+            hashes: vec![params.genesis.hash()],
+            headers: vec![params.genesis],
+        }
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Height of the tip, the genesis block being at 0
+    pub fn height(&self) -> u32 {
+        self.headers.len() as u32 - 1
+    }
+
+    pub fn tip(&self) -> &BlockHeader {
+        self.headers.last().unwrap()
+    }
+
+    pub fn tip_hash(&self) -> [u8; 32] {
+        *self.hashes.last().unwrap()
+    }
+
+    pub fn header(&self, height: u32) -> Option<&BlockHeader> {
+        self.headers.get(height as usize)
+    }
+
+    pub fn hash(&self, height: u32) -> Option<[u8; 32]> {
+        self.hashes.get(height as usize).copied()
+    }
+
+    /// Hashes for getheaders: the tip and the ten blocks before it, then
+    /// exponentially further apart back to genesis, as Bitcoin Core does
+    pub fn locator(&self) -> Vec<[u8; 32]> {
+        let mut res = Vec::new();
+        let mut height = self.height() as usize;
+        let mut step = 1;
+        loop {
+            res.push(self.hashes[height]);
+            if height == 0 {
+                break;
+            }
+            height = height.saturating_sub(step);
+            if res.len() > 10 {
+                step *= 2;
+            }
+        }
+        res
+    }
+
+    /// Appends a batch of headers following the tip, returning how many
+    /// were added. Either the whole batch is valid and added, or nothing is
+    pub fn connect(&mut self, headers: &[BlockHeader]) -> Result<usize, ChainError> {
+        let mut prev = self.tip_hash();
+        let mut hashes = Vec::with_capacity(headers.len());
+        for (i, header) in headers.iter().enumerate() {
+            if header.prev_blockhash != prev {
+                return Err(ChainError::Disconnected(i));
+            }
+            let hash = header.hash();
+            if !pow::check_pow_with_limit(&hash, header.bits, &self.pow_limit) {
+                return Err(ChainError::InvalidPow(i));
+            }
+            hashes.push(hash);
+            prev = hash;
+        }
+        self.headers.extend_from_slice(headers);
+        self.hashes.extend(hashes);
+        Ok(headers.len())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod chain_tests {
+    use super::*;
+
+    /// Regtest headers on top of `prev`, whose trivial target about one
+    /// nonce in two meets
+    pub(crate) fn mine(prev: &BlockHeader, count: usize) -> Vec<BlockHeader> {
+        let limit = pow::bits_to_target(0x207fffff).unwrap();
+        let mut res: Vec<BlockHeader> = Vec::with_capacity(count);
+        for _ in 0..count {
+            let last = res.last().unwrap_or(prev);
+            let mut header = BlockHeader {
+                version: 0x20000000,
+                prev_blockhash: last.hash(),
+                merkle_root: [0x42; 32],
+                time: last.time + 600,
+                bits: 0x207fffff,
+                nonce: 0,
+            };
+            while !pow::check_pow_with_limit(&header.hash(), header.bits, &limit) {
+                header.nonce += 1;
+            }
+            res.push(header);
+        }
+        res
+    }
+
+    #[test]
+    fn test_connect() {
+        let mut chain = HeaderChain::new(Network::Regtest);
+        let headers = mine(chain.tip(), 5);
+        assert_eq!(chain.connect(&headers[..3]), Ok(3));
+        assert_eq!(chain.height(), 3);
+        assert_eq!(chain.tip_hash(), headers[2].hash());
+        assert_eq!(chain.hash(1), Some(headers[0].hash()));
+
+        // skipping a header, or repeating one, breaks the linkage
+        assert_eq!(chain.connect(&headers[4..]), Err(ChainError::Disconnected(0)));
+        assert_eq!(chain.connect(&headers[2..]), Err(ChainError::Disconnected(0)));
+
+        let mut bad = headers[3..].to_vec();
+        let limit = chain.pow_limit.clone();
+        bad[0].nonce = (0..)
+            .find(|&nonce| {
+                let header = BlockHeader {
+                    nonce,
+                    ..bad[0].clone()
+                };
+                !pow::check_pow_with_limit(&header.hash(), header.bits, &limit)
+            })
+            .unwrap();
+        assert_eq!(chain.connect(&bad), Err(ChainError::InvalidPow(0)));
+        // a failed batch adds nothing
+        assert_eq!(chain.height(), 3);
+        assert_eq!(chain.connect(&headers[3..]), Ok(2));
+
+        // regtest's easy target isn't allowed on mainnet
+        let mut mainnet = HeaderChain::new(Network::Mainnet);
+        let on_mainnet = mine(mainnet.tip(), 1);
+        assert_eq!(mainnet.connect(&on_mainnet), Err(ChainError::InvalidPow(0)));
+    }
+
+    #[test]
+    fn test_locator() {
+        let mut chain = HeaderChain::new(Network::Regtest);
+        assert_eq!(chain.locator(), vec![chain.hash(0).unwrap()]);
+        chain.connect(&mine(chain.tip(), 40)).unwrap();
+        let heights = [40, 39, 38, 37, 36, 35, 34, 33, 32, 31, 30, 29, 27, 23, 15, 0];
+        let expected: Vec<_> = heights.iter().map(|&h| chain.hash(h).unwrap()).collect();
+        assert_eq!(chain.locator(), expected);
+    }
+}
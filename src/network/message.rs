@@ -0,0 +1,578 @@
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+use crate::block::BlockHeader;
+use crate::crypto::hash::hash256;
+use crate::encoding::compact_size;
+use crate::encoding::consensus::{impl_consensus_codec, ConsensusDecode, ConsensusEncode};
+use crate::encoding::decode::{Context, CountingReader, DecodeError};
+use crate::tx::{read_array, read_u32, read_var_bytes, write_var_bytes, TxError};
+
+/// The version we speak: BIP-339 wtxid relay, though nothing here relays
+pub const PROTOCOL_VERSION: u32 = 70016;
+/// Bitcoin Core drops peers sending larger messages
+pub const MAX_PAYLOAD_SIZE: u32 = 4_000_000;
+/// Most headers a headers message may carry
+pub const MAX_HEADERS: usize = 2000;
+
+/// Service bits advertised in version messages and addresses
+pub const NODE_NETWORK: u64 = 1;
+pub const NODE_WITNESS: u64 = 1 << 3;
+
+const HEADER_SIZE: usize = 24;
+const COMMAND_SIZE: usize = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageError {
+    /// Reading the envelope from the stream failed
+    Io(io::ErrorKind),
+    /// The payload doesn't parse as its command's message
+    Payload(TxError),
+    /// The command isn't NUL-padded printable ASCII
+    InvalidCommand,
+    PayloadTooLarge(u32),
+    /// The envelope's checksum isn't the one the payload hashes to
+    ChecksumMismatch {
+        expected: [u8; 4],
+        found: [u8; 4],
+    },
+    InvalidUserAgent,
+    TooManyHeaders(usize),
+    /// A headers entry claimed to carry transactions
+    HeaderWithTransactions,
+}
+
+impl Display for MessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageError::Io(kind) => write!(f, "i/o error reading message: {}", kind),
+            MessageError::Payload(e) => write!(f, "malformed payload: {}", e),
+            MessageError::InvalidCommand => write!(f, "invalid message command"),
+            MessageError::PayloadTooLarge(size) => write!(f, "payload of {} bytes exceeds {}", size, MAX_PAYLOAD_SIZE),
+            MessageError::ChecksumMismatch { expected, found } => {
+                write!(
+                    f,
+                    "payload checksum {} does not match {}",
+                    hex::encode(found),
+                    hex::encode(expected)
+                )
+            }
+            MessageError::InvalidUserAgent => write!(f, "user agent is not valid UTF-8"),
+            MessageError::TooManyHeaders(n) => write!(f, "{} headers exceed {}", n, MAX_HEADERS),
+            MessageError::HeaderWithTransactions => write!(f, "headers message entry has transactions"),
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+impl From<TxError> for MessageError {
+    fn from(e: TxError) -> Self {
+        MessageError::Payload(e)
+    }
+}
+
+impl From<io::Error> for MessageError {
+    fn from(e: io::Error) -> Self {
+        MessageError::Io(e.kind())
+    }
+}
+
+/// A peer's address as carried in version messages: IPv4 addresses are
+/// IPv4-mapped IPv6, and unlike everything else the port is big-endian
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetAddress {
+    pub services: u64,
+    pub ip: [u8; 16],
+    pub port: u16,
+}
+
+impl NetAddress {
+    pub fn new(addr: SocketAddr, services: u64) -> Self {
+        let ip = match addr.ip() {
+            IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+            IpAddr::V6(ip) => ip,
+        };
+        Self {
+            services,
+            ip: ip.octets(),
+            port: addr.port(),
+        }
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        let ip = Ipv6Addr::from(self.ip);
+        match ip.to_ipv4_mapped() {
+            Some(ip) => SocketAddr::new(IpAddr::V4(ip), self.port),
+            None => SocketAddr::new(IpAddr::V6(ip), self.port),
+        }
+    }
+
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(Self {
+            services: u64::from_le_bytes(read_array(reader).context("services")?),
+            ip: read_array(reader).context("ip")?,
+            port: u16::from_be_bytes(read_array(reader).context("port")?),
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.services.to_le_bytes())?;
+        writer.write_all(&self.ip)?;
+        writer.write_all(&self.port.to_be_bytes())
+    }
+}
+
+/// The first message each side of a connection sends
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionMessage {
+    pub version: u32,
+    pub services: u64,
+    /// Unix time of the sender
+    pub timestamp: i64,
+    pub receiver: NetAddress,
+    pub sender: NetAddress,
+    /// Random, to detect connections to ourselves
+    pub nonce: u64,
+    pub user_agent: String,
+    /// Height of the sender's best chain
+    pub start_height: i32,
+    /// Whether to announce transactions before a filter is loaded (BIP-37)
+    pub relay: bool,
+}
+
+impl VersionMessage {
+    /// Trailing bytes are ignored and a missing relay flag means true, as
+    /// older and newer versions respectively send them
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<MessageError>> {
+        let payload = |e: DecodeError<TxError>| e.map(MessageError::Payload);
+        let version = read_u32(reader).context("version").map_err(payload)?;
+        let services = u64::from_le_bytes(read_array(reader).context("services").map_err(payload)?);
+        let timestamp = i64::from_le_bytes(read_array(reader).context("timestamp").map_err(payload)?);
+        let receiver = NetAddress::parse(reader).context("receiver").map_err(payload)?;
+        let sender = NetAddress::parse(reader).context("sender").map_err(payload)?;
+        let nonce = u64::from_le_bytes(read_array(reader).context("nonce").map_err(payload)?);
+        let user_agent = read_var_bytes(reader).context("user_agent").map_err(payload)?;
+        let user_agent = String::from_utf8(user_agent)
+            .map_err(|_| MessageError::InvalidUserAgent)
+            .context("user_agent")?;
+        let start_height = i32::from_le_bytes(read_array(reader).context("start_height").map_err(payload)?);
+        // left at 1 if the stream ends here
+        let mut relay = [1_u8];
+        reader
+            .read(&mut relay)
+            .map_err(TxError::from)
+            .context("relay")
+            .map_err(payload)?;
+        Ok(Self {
+            version,
+            services,
+            timestamp,
+            receiver,
+            sender,
+            nonce,
+            user_agent,
+            start_height,
+            relay: relay[0] != 0,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&self.services.to_le_bytes())?;
+        writer.write_all(&self.timestamp.to_le_bytes())?;
+        self.receiver.write(writer)?;
+        self.sender.write(writer)?;
+        writer.write_all(&self.nonce.to_le_bytes())?;
+        write_var_bytes(writer, self.user_agent.as_bytes())?;
+        writer.write_all(&self.start_height.to_le_bytes())?;
+        writer.write_all(&[self.relay as u8])
+    }
+}
+
+/// Asks for the headers following the first locator hash the peer knows,
+/// up to `stop_hash` or MAX_HEADERS of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetHeadersMessage {
+    pub version: u32,
+    /// Block hashes in internal byte order, newest first, spaced out
+    /// exponentially so a fork point is found in few round trips
+    pub locator_hashes: Vec<[u8; 32]>,
+    /// All zeros to get as many headers as allowed
+    pub stop_hash: [u8; 32],
+}
+
+impl GetHeadersMessage {
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        let version = read_u32(reader).context("version")?;
+        let locator_hashes = (0..compact_size::read_size(reader).context("locator_hashes")?)
+            .map(|i| read_array(reader).context(format_args!("locator_hashes[{}]", i)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            version,
+            locator_hashes,
+            stop_hash: read_array(reader).context("stop_hash")?,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.version.to_le_bytes())?;
+        compact_size::write(writer, self.locator_hashes.len() as u64)?;
+        for hash in &self.locator_hashes {
+            writer.write_all(hash)?;
+        }
+        writer.write_all(&self.stop_hash)
+    }
+}
+
+/// The payloads we understand, anything else being kept as raw bytes
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkMessage {
+    Version(VersionMessage),
+    Verack,
+    Ping(u64),
+    Pong(u64),
+    GetHeaders(GetHeadersMessage),
+    Headers(Vec<BlockHeader>),
+    Unknown { command: String, payload: Vec<u8> },
+}
+
+impl NetworkMessage {
+    pub fn command(&self) -> &str {
+        match self {
+            NetworkMessage::Version(_) => "version",
+            NetworkMessage::Verack => "verack",
+            NetworkMessage::Ping(_) => "ping",
+            NetworkMessage::Pong(_) => "pong",
+            NetworkMessage::GetHeaders(_) => "getheaders",
+            NetworkMessage::Headers(_) => "headers",
+            NetworkMessage::Unknown { command, .. } => command,
+        }
+    }
+
+    pub fn payload(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        match self {
+            NetworkMessage::Version(version) => version.write(&mut res).unwrap(),
+            NetworkMessage::Verack => {}
+            NetworkMessage::Ping(nonce) | NetworkMessage::Pong(nonce) => res.extend_from_slice(&nonce.to_le_bytes()),
+            NetworkMessage::GetHeaders(get_headers) => get_headers.write(&mut res).unwrap(),
+            NetworkMessage::Headers(headers) => {
+                compact_size::write(&mut res, headers.len() as u64).unwrap();
+                for header in headers {
+                    header.write(&mut res).unwrap();
+                    // headers are sent as blocks without transactions
+                    res.push(0);
+                }
+            }
+            NetworkMessage::Unknown { payload, .. } => res.extend_from_slice(payload),
+        }
+        res
+    }
+
+    /// Parses the payload of `command`, rejecting trailing bytes. Errors
+    /// are located within the payload, under the command's name
+    pub fn from_payload(command: &str, payload: &[u8]) -> Result<Self, DecodeError<MessageError>> {
+        let mut reader = payload;
+        let res = Self::parse_payload(command, &mut reader);
+        let offset = payload.len() - reader.len();
+        res.map_err(|e| e.within(command).at(offset))
+    }
+
+    fn parse_payload(command: &str, reader: &mut &[u8]) -> Result<Self, DecodeError<MessageError>> {
+        let payload = |e: DecodeError<TxError>| e.map(MessageError::Payload);
+        let message = match command {
+            "version" => return Ok(NetworkMessage::Version(VersionMessage::parse(reader)?)),
+            "verack" => NetworkMessage::Verack,
+            "ping" => NetworkMessage::Ping(u64::from_le_bytes(
+                read_array(reader).context("nonce").map_err(payload)?,
+            )),
+            "pong" => NetworkMessage::Pong(u64::from_le_bytes(
+                read_array(reader).context("nonce").map_err(payload)?,
+            )),
+            "getheaders" => NetworkMessage::GetHeaders(GetHeadersMessage::parse(reader).map_err(payload)?),
+            "headers" => {
+                let count = compact_size::read_size(reader)
+                    .map_err(TxError::from)
+                    .context("count")
+                    .map_err(payload)?;
+                if count > MAX_HEADERS {
+                    return Err(MessageError::TooManyHeaders(count)).context("count");
+                }
+                let mut headers = Vec::with_capacity(count);
+                for i in 0..count {
+                    headers.push(
+                        BlockHeader::parse(reader)
+                            .context(format_args!("[{}]", i))
+                            .map_err(payload)?,
+                    );
+                    let txn_count = compact_size::read_size(reader).map_err(TxError::from);
+                    if txn_count.context(format_args!("[{}].txn_count", i)).map_err(payload)? != 0 {
+                        return Err(MessageError::HeaderWithTransactions).context(format_args!("[{}].txn_count", i));
+                    }
+                }
+                NetworkMessage::Headers(headers)
+            }
+            _ => {
+                return Ok(NetworkMessage::Unknown {
+                    command: command.to_string(),
+                    payload: reader.to_vec(),
+                });
+            }
+        };
+        if !reader.is_empty() {
+            return Err(MessageError::Payload(TxError::TrailingBytes(reader.len())).into());
+        }
+        Ok(message)
+    }
+}
+
+/// A message in its envelope: network magic, NUL-padded command, payload
+/// length and the first four bytes of the payload's hash256
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawMessage {
+    pub magic: [u8; 4],
+    pub message: NetworkMessage,
+}
+
+impl RawMessage {
+    pub fn new(magic: [u8; 4], message: NetworkMessage) -> Self {
+        Self { magic, message }
+    }
+
+    /// Reads one message off a stream. The magic isn't checked; callers
+    /// compare it with their network's. Error offsets count from the start
+    /// of the message
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, DecodeError<MessageError>> {
+        let mut reader = CountingReader::new(reader);
+        let res = Self::read_counted(&mut reader);
+        res.map_err(|e| e.at(reader.count() as usize))
+    }
+
+    fn read_counted<R: Read>(reader: &mut R) -> Result<Self, DecodeError<MessageError>> {
+        let mut header = [0_u8; HEADER_SIZE];
+        reader
+            .read_exact(&mut header)
+            .map_err(MessageError::from)
+            .context("header")?;
+        let magic = header[..4].try_into().unwrap();
+        let command = parse_command(&header[4..16]).context("command")?;
+        let length = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        if length > MAX_PAYLOAD_SIZE {
+            return Err(MessageError::PayloadTooLarge(length)).context("length");
+        }
+        let mut payload = vec![0_u8; length as usize];
+        reader
+            .read_exact(&mut payload)
+            .map_err(MessageError::from)
+            .context("payload")?;
+        let checksum = hash256(&payload)[..4].try_into().unwrap();
+        if checksum != header[20..] {
+            let found = header[20..].try_into().unwrap();
+            return Err(MessageError::ChecksumMismatch {
+                expected: checksum,
+                found,
+            })
+            .context("checksum");
+        }
+        let message = NetworkMessage::from_payload(&command, &payload).map_err(|e| e.shift(HEADER_SIZE))?;
+        Ok(Self { magic, message })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let payload = self.message.payload();
+        let mut command = [0_u8; COMMAND_SIZE];
+        command[..self.message.command().len()].copy_from_slice(self.message.command().as_bytes());
+        writer.write_all(&self.magic)?;
+        writer.write_all(&command)?;
+        writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+        writer.write_all(&hash256(&payload)[..4])?;
+        writer.write_all(&payload)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write(&mut res).unwrap();
+        res
+    }
+}
+
+fn parse_command(bytes: &[u8]) -> Result<String, MessageError> {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(COMMAND_SIZE);
+    if bytes[len..].iter().any(|&b| b != 0) || !bytes[..len].iter().all(|b| b.is_ascii_graphic()) {
+        return Err(MessageError::InvalidCommand);
+    }
+    Ok(String::from_utf8(bytes[..len].to_vec()).unwrap())
+}
+
+impl_consensus_codec!(NetAddress, TxError);
+impl_consensus_codec!(VersionMessage, MessageError);
+impl_consensus_codec!(GetHeadersMessage, TxError);
+
+impl ConsensusEncode for RawMessage {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write(writer)
+    }
+}
+
+impl ConsensusDecode for RawMessage {
+    type Error = MessageError;
+
+    fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError<MessageError>> {
+        Self::read(reader)
+    }
+}
+
+#[cfg(test)]
+mod message_tests {
+    use super::*;
+    use crate::address::Network;
+
+    #[test]
+    fn test_version_message() {
+        // from rust-bitcoin's tests
+        let s = "721101000100000000000000e6e0845300000000010000000000000000000000000000000000ffff000000000000010000\
+                 0000000000fd87d87eeb4364f22cf54dca59412db7208d47d920cffce83ee8102f5361746f7368693a302e392e39392f2c\
+                 9f040001";
+        let bytes = hex::decode(s).unwrap();
+        let version = VersionMessage::parse(&mut &bytes[..]).unwrap();
+        assert_eq!(version.version, 70002);
+        assert_eq!(version.services, NODE_NETWORK);
+        assert_eq!(version.timestamp, 1401217254);
+        assert_eq!(version.receiver.socket_addr(), "0.0.0.0:0".parse().unwrap());
+        assert_eq!(
+            version.sender.socket_addr(),
+            "[fd87:d87e:eb43:64f2:2cf5:4dca:5941:2db7]:8333".parse().unwrap()
+        );
+        assert_eq!(version.nonce, 16735069437859780935);
+        assert_eq!(version.user_agent, "/Satoshi:0.9.99/");
+        assert_eq!(version.start_height, 302892);
+        assert!(version.relay);
+
+        let mut written = Vec::new();
+        version.write(&mut written).unwrap();
+        assert_eq!(written[..], bytes[..]);
+        // a missing relay flag defaults to true
+        assert!(VersionMessage::parse(&mut &bytes[..bytes.len() - 1]).unwrap().relay);
+        let err = VersionMessage::parse(&mut &bytes[..50]).unwrap_err();
+        assert_eq!(err.kind(), &MessageError::Payload(TxError::Truncated));
+        assert_eq!(err.path(), ["sender", "services"]);
+    }
+
+    #[test]
+    fn test_net_address() {
+        let addr = NetAddress::new("127.0.0.1:8333".parse().unwrap(), NODE_NETWORK | NODE_WITNESS);
+        let mut bytes = Vec::new();
+        addr.write(&mut bytes).unwrap();
+        assert_eq!(
+            hex::encode(&bytes),
+            "090000000000000000000000000000000000ffff7f000001208d"
+        );
+        assert_eq!(
+            NetAddress::parse(&mut &bytes[..]).unwrap().socket_addr(),
+            "127.0.0.1:8333".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_envelope() {
+        // serializations from rust-bitcoin
+        let cases = [
+            (
+                Network::Mainnet,
+                NetworkMessage::Verack,
+                "f9beb4d976657261636b000000000000000000005df6e0e2",
+            ),
+            (
+                Network::Mainnet,
+                NetworkMessage::Ping(0x0102030405060708),
+                "f9beb4d970696e670000000000000000080000003b5a75130807060504030201",
+            ),
+            (
+                Network::Testnet,
+                NetworkMessage::GetHeaders(GetHeadersMessage {
+                    version: PROTOCOL_VERSION,
+                    locator_hashes: vec![BlockHeader::genesis(Network::Mainnet).hash()],
+                    stop_hash: [0; 32],
+                }),
+                "0b110907676574686561646572730000450000001d36fe5380110100016fe28c0ab6f1b372c1a6a246ae63f74f931e83\
+                 65e15a089c68d61900000000000000000000000000000000000000000000000000000000000000000000000000",
+            ),
+            (
+                Network::Regtest,
+                NetworkMessage::Headers(vec![BlockHeader::genesis(Network::Mainnet)]),
+                "fabfb5da686561646572730000000000520000000b0e13eb0101000000000000000000000000000000000000000000000000\
+                 00000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff\
+                 001d1dac2b7c00",
+            ),
+        ];
+        for (network, message, expected) in cases {
+            let raw = RawMessage::new(network.magic(), message);
+            assert_eq!(hex::encode(raw.serialize()), expected);
+            assert_eq!(RawMessage::read(&mut &raw.serialize()[..]).unwrap(), raw);
+        }
+
+        let unknown = RawMessage::new(
+            Network::Mainnet.magic(),
+            NetworkMessage::Unknown {
+                command: "sendheaders".to_string(),
+                payload: Vec::new(),
+            },
+        );
+        assert_eq!(RawMessage::read(&mut &unknown.serialize()[..]).unwrap(), unknown);
+    }
+
+    #[test]
+    fn test_envelope_errors() {
+        let verack = hex::decode("f9beb4d976657261636b000000000000000000005df6e0e2").unwrap();
+        let read = |bytes: &[u8]| RawMessage::read(&mut &bytes[..]).unwrap_err();
+        let err = read(&verack[..20]);
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&MessageError::Io(io::ErrorKind::UnexpectedEof), Some(20))
+        );
+
+        let mut bad = verack.clone();
+        bad[23] ^= 1;
+        assert_eq!(
+            read(&bad).to_string(),
+            "payload checksum 5df6e0e3 does not match 5df6e0e2 at byte 24 in checksum"
+        );
+        let mut bad = verack.clone();
+        bad[11] = b'x';
+        assert_eq!(read(&bad).into_kind(), MessageError::InvalidCommand);
+        let mut bad = verack.clone();
+        bad[16..20].copy_from_slice(&(MAX_PAYLOAD_SIZE + 1).to_le_bytes());
+        assert_eq!(
+            read(&bad).into_kind(),
+            MessageError::PayloadTooLarge(MAX_PAYLOAD_SIZE + 1)
+        );
+
+        let err = NetworkMessage::from_payload("verack", &[0]).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&MessageError::Payload(TxError::TrailingBytes(1)), Some(0))
+        );
+        let mut headers = NetworkMessage::Headers(vec![BlockHeader::genesis(Network::Mainnet)]).payload();
+        *headers.last_mut().unwrap() = 1;
+        let err = NetworkMessage::from_payload("headers", &headers).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "headers message entry has transactions at byte 82 in headers[0].txn_count"
+        );
+        // within a whole message, offsets count the envelope too
+        let message = NetworkMessage::Unknown {
+            command: "headers".to_string(),
+            payload: headers,
+        };
+        let raw = RawMessage::new(Network::Mainnet.magic(), message);
+        assert_eq!(read(&raw.serialize()).offset(), Some(24 + 82));
+        assert_eq!(
+            NetworkMessage::from_payload("headers", &[0xfd, 0xd1, 0x07])
+                .unwrap_err()
+                .into_kind(),
+            MessageError::TooManyHeaders(2001)
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod chain;
+pub mod message;
+pub mod peer;
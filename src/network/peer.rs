@@ -0,0 +1,289 @@
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::address::Network;
+use crate::block::BlockHeader;
+use crate::encoding::decode::DecodeError;
+use crate::network::chain::{ChainError, HeaderChain};
+use crate::network::message::{
+    GetHeadersMessage, MessageError, NetAddress, NetworkMessage, RawMessage, VersionMessage, MAX_HEADERS,
+    PROTOCOL_VERSION,
+};
+
+pub const USER_AGENT: &str = "/moneda:0.1.0/";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for any message before giving up on the peer
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerError {
+    Io(io::ErrorKind),
+    Message(DecodeError<MessageError>),
+    /// The peer is on another network
+    WrongMagic([u8; 4]),
+    /// A message that isn't allowed at this point of the conversation
+    UnexpectedMessage(String),
+    /// The version nonce was our own: we connected to ourselves
+    SelfConnection,
+    /// The peer sent headers that don't extend our chain
+    Chain(ChainError),
+}
+
+impl Display for PeerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeerError::Io(kind) => write!(f, "i/o error talking to peer: {}", kind),
+            PeerError::Message(e) => write!(f, "{}", e),
+            PeerError::WrongMagic(magic) => write!(f, "message for another network ({})", hex::encode(magic)),
+            PeerError::UnexpectedMessage(command) => write!(f, "unexpected {} message", command),
+            PeerError::SelfConnection => write!(f, "connected to ourselves"),
+            PeerError::Chain(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for PeerError {}
+
+impl From<io::Error> for PeerError {
+    fn from(e: io::Error) -> Self {
+        PeerError::Io(e.kind())
+    }
+}
+
+impl From<DecodeError<MessageError>> for PeerError {
+    fn from(e: DecodeError<MessageError>) -> Self {
+        PeerError::Message(e)
+    }
+}
+
+impl From<ChainError> for PeerError {
+    fn from(e: ChainError) -> Self {
+        PeerError::Chain(e)
+    }
+}
+
+/// A connection to one node that has completed the version handshake.
+/// Generic over the stream so tests and proxies can stand in for TCP
+#[derive(Debug)]
+pub struct Peer<S = TcpStream> {
+    stream: S,
+    network: Network,
+    version: VersionMessage,
+}
+
+impl Peer<TcpStream> {
+    /// Connects and performs the handshake, announcing `start_height` as
+    /// the height of our best chain
+    pub fn connect(addr: SocketAddr, network: Network, start_height: i32) -> Result<Self, PeerError> {
+        let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        stream.set_nodelay(true)?;
+        Self::handshake(stream, addr, network, start_height)
+    }
+}
+
+impl<S: Read + Write> Peer<S> {
+    /// Sends our version and waits for the peer's version and verack,
+    /// acknowledging its version. Messages other than those are ignored
+    /// until the handshake is done, as nodes send feature negotiation
+    /// (wtxidrelay, sendaddrv2) in between
+    pub fn handshake(mut stream: S, addr: SocketAddr, network: Network, start_height: i32) -> Result<Self, PeerError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        let nonce = rand::random();
+        let ours = VersionMessage {
+            version: PROTOCOL_VERSION,
+            // we serve nothing
+            services: 0,
+            timestamp,
+            receiver: NetAddress::new(addr, 0),
+            sender: NetAddress::new(SocketAddr::from(([0, 0, 0, 0], 0)), 0),
+            nonce,
+            user_agent: USER_AGENT.to_string(),
+            start_height,
+            relay: false,
+        };
+        RawMessage::new(network.magic(), NetworkMessage::Version(ours)).write(&mut stream)?;
+        stream.flush()?;
+
+        let mut version = None;
+        let mut verack = false;
+        while version.is_none() || !verack {
+            let raw = RawMessage::read(&mut stream)?;
+            if raw.magic != network.magic() {
+                return Err(PeerError::WrongMagic(raw.magic));
+            }
+            match raw.message {
+                NetworkMessage::Version(theirs) if version.is_none() => {
+                    if theirs.nonce == nonce {
+                        return Err(PeerError::SelfConnection);
+                    }
+                    RawMessage::new(network.magic(), NetworkMessage::Verack).write(&mut stream)?;
+                    stream.flush()?;
+                    version = Some(theirs);
+                }
+                NetworkMessage::Verack if version.is_some() => verack = true,
+                message @ (NetworkMessage::Version(_) | NetworkMessage::Verack) => {
+                    return Err(PeerError::UnexpectedMessage(message.command().to_string()));
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            stream,
+            network,
+            version: version.unwrap(),
+        })
+    }
+
+    /// The version message the peer sent
+    pub fn version(&self) -> &VersionMessage {
+        &self.version
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn send(&mut self, message: NetworkMessage) -> Result<(), PeerError> {
+        RawMessage::new(self.network.magic(), message).write(&mut self.stream)?;
+        Ok(self.stream.flush()?)
+    }
+
+    /// The next message from the peer. Pings are answered before being
+    /// returned, so a caller waiting for something else stays connected
+    pub fn receive(&mut self) -> Result<NetworkMessage, PeerError> {
+        let raw = RawMessage::read(&mut self.stream)?;
+        if raw.magic != self.network.magic() {
+            return Err(PeerError::WrongMagic(raw.magic));
+        }
+        if let NetworkMessage::Ping(nonce) = raw.message {
+            self.send(NetworkMessage::Pong(nonce))?;
+        }
+        Ok(raw.message)
+    }
+
+    /// Asks for the headers after the first locator hash the peer knows
+    /// and waits for them, skipping unrelated messages
+    pub fn get_headers(&mut self, locator_hashes: Vec<[u8; 32]>) -> Result<Vec<BlockHeader>, PeerError> {
+        self.send(NetworkMessage::GetHeaders(GetHeadersMessage {
+            version: PROTOCOL_VERSION,
+            locator_hashes,
+            stop_hash: [0; 32],
+        }))?;
+        loop {
+            if let NetworkMessage::Headers(headers) = self.receive()? {
+                return Ok(headers);
+            }
+        }
+    }
+
+    /// Headers-first sync: extends `chain` with the peer's headers until
+    /// it sends a partial batch, returning how many were added. Every batch
+    /// is checked for proof of work and linkage before it's added
+    pub fn sync_headers(&mut self, chain: &mut HeaderChain) -> Result<usize, PeerError> {
+        let mut added = 0;
+        loop {
+            let headers = self.get_headers(chain.locator())?;
+            added += chain.connect(&headers)?;
+            if headers.len() < MAX_HEADERS {
+                return Ok(added);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod peer_tests {
+    use super::*;
+    use crate::network::chain::chain_tests::mine;
+    use crate::network::message::NODE_NETWORK;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Just enough of a node to hand out `headers` over regtest
+    fn serve(listener: TcpListener, headers: Vec<BlockHeader>, magic: [u8; 4]) {
+        let (mut stream, addr) = listener.accept().unwrap();
+        let mut hashes = vec![BlockHeader::genesis(Network::Regtest).hash()];
+        hashes.extend(headers.iter().map(BlockHeader::hash));
+        let send = |stream: &mut TcpStream, message| RawMessage::new(magic, message).write(stream).unwrap();
+
+        let NetworkMessage::Version(theirs) = RawMessage::read(&mut stream).unwrap().message else {
+            panic!("expected version first");
+        };
+        assert_eq!(theirs.user_agent, USER_AGENT);
+        let local = stream.local_addr().unwrap();
+        send(
+            &mut stream,
+            NetworkMessage::Version(VersionMessage {
+                version: PROTOCOL_VERSION,
+                services: NODE_NETWORK,
+                timestamp: 0,
+                receiver: NetAddress::new(addr, 0),
+                sender: NetAddress::new(local, NODE_NETWORK),
+                nonce: 7,
+                user_agent: "/test:0.0.1/".to_string(),
+                start_height: headers.len() as i32,
+                relay: true,
+            }),
+        );
+        send(
+            &mut stream,
+            NetworkMessage::Unknown {
+                command: "wtxidrelay".to_string(),
+                payload: Vec::new(),
+            },
+        );
+        send(&mut stream, NetworkMessage::Verack);
+        send(&mut stream, NetworkMessage::Ping(99));
+
+        while let Ok(raw) = RawMessage::read(&mut stream) {
+            if let NetworkMessage::GetHeaders(request) = raw.message {
+                let start = request
+                    .locator_hashes
+                    .iter()
+                    .find_map(|hash| hashes.iter().position(|h| h == hash))
+                    .unwrap_or(0);
+                let end = (start + MAX_HEADERS).min(headers.len());
+                send(&mut stream, NetworkMessage::Headers(headers[start..end].to_vec()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_handshake_and_sync() {
+        let headers = mine(&BlockHeader::genesis(Network::Regtest), MAX_HEADERS + 5);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = headers.clone();
+        let node = thread::spawn(move || serve(listener, served, Network::Regtest.magic()));
+
+        let mut peer = Peer::connect(addr, Network::Regtest, 0).unwrap();
+        assert_eq!(peer.version().user_agent, "/test:0.0.1/");
+        assert_eq!(peer.version().start_height, MAX_HEADERS as i32 + 5);
+
+        let mut chain = HeaderChain::new(Network::Regtest);
+        assert_eq!(peer.sync_headers(&mut chain), Ok(MAX_HEADERS + 5));
+        assert_eq!(chain.tip_hash(), headers.last().unwrap().hash());
+        // caught up: the next request gets nothing new
+        assert_eq!(peer.sync_headers(&mut chain), Ok(0));
+
+        drop(peer);
+        node.join().unwrap();
+    }
+
+    #[test]
+    fn test_wrong_network() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let node = thread::spawn(move || serve(listener, Vec::new(), Network::Mainnet.magic()));
+        assert_eq!(
+            Peer::connect(addr, Network::Regtest, 0).unwrap_err(),
+            PeerError::WrongMagic(Network::Mainnet.magic())
+        );
+        node.join().unwrap();
+    }
+}
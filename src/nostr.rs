@@ -0,0 +1,270 @@
+use std::fmt::Display;
+
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::hash::sha256;
+use crate::crypto::schnorr;
+use crate::curves::secp256k1::ecc::{KeyError, PrivateKey};
+use crate::encoding::bech32::{self, Bech32Error, Variant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NostrError {
+    Bech32(Bech32Error),
+    /// The string is, say, an nsec where an npub was expected
+    WrongPrefix {
+        expected: &'static str,
+        found: String,
+    },
+    /// npub, nsec and note all carry exactly 32 bytes
+    InvalidLength(usize),
+    Key(KeyError),
+}
+
+impl Display for NostrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NostrError::Bech32(e) => write!(f, "{}", e),
+            NostrError::WrongPrefix { expected, found } => write!(f, "expected {} but found {}", expected, found),
+            NostrError::InvalidLength(len) => write!(f, "expected 32 bytes but found {}", len),
+            NostrError::Key(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for NostrError {}
+
+impl From<Bech32Error> for NostrError {
+    fn from(e: Bech32Error) -> Self {
+        NostrError::Bech32(e)
+    }
+}
+
+impl From<KeyError> for NostrError {
+    fn from(e: KeyError) -> Self {
+        NostrError::Key(e)
+    }
+}
+
+/// NIP-19 bare entities: plain bech32 (not bech32m) over 32 bytes
+fn encode(hrp: &str, bytes: &[u8; 32]) -> String {
+    bech32::encode(hrp, &bech32::convert_bits(bytes, 8, 5, true).unwrap(), Variant::Bech32).unwrap()
+}
+
+fn decode(expected: &'static str, s: &str) -> Result<[u8; 32], NostrError> {
+    let (hrp, data, variant) = bech32::decode(s)?;
+    if hrp != expected {
+        return Err(NostrError::WrongPrefix { expected, found: hrp });
+    }
+    if variant != Variant::Bech32 {
+        return Err(Bech32Error::WrongVariant.into());
+    }
+    let bytes = bech32::convert_bits(&data, 5, 8, false)?;
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| NostrError::InvalidLength(bytes.len()))
+}
+
+/// A BIP-340 x-only public key as an npub
+pub fn encode_npub(xonly: &[u8; 32]) -> String {
+    encode("npub", xonly)
+}
+
+pub fn decode_npub(s: &str) -> Result<[u8; 32], NostrError> {
+    let xonly = decode("npub", s)?;
+    schnorr::lift_x(&xonly)?;
+    Ok(xonly)
+}
+
+pub fn encode_nsec(key: &PrivateKey) -> String {
+    encode("nsec", &key.to_bytes())
+}
+
+pub fn decode_nsec(s: &str) -> Result<PrivateKey, NostrError> {
+    Ok(PrivateKey::from_bytes(&decode("nsec", s)?)?)
+}
+
+/// An event id as a note
+pub fn encode_note(id: &[u8; 32]) -> String {
+    encode("note", id)
+}
+
+pub fn decode_note(s: &str) -> Result<[u8; 32], NostrError> {
+    decode("note", s)
+}
+
+/// A NIP-01 event: signed with BIP-340 under the author's x-only key, over
+/// an id that hashes everything else
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub id: [u8; 32],
+    pub pubkey: [u8; 32],
+    pub created_at: u64,
+    pub kind: u16,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+    pub sig: [u8; 64],
+}
+
+impl Event {
+    pub fn sign(key: &PrivateKey, created_at: u64, kind: u16, tags: Vec<Vec<String>>, content: &str) -> Self {
+        Self::sign_with_rng(key, created_at, kind, tags, content, &mut OsRng)
+    }
+
+    pub fn sign_with_rng<R: RngCore + CryptoRng>(
+        key: &PrivateKey,
+        created_at: u64,
+        kind: u16,
+        tags: Vec<Vec<String>>,
+        content: &str,
+        rng: &mut R,
+    ) -> Self {
+        let pubkey = schnorr::xonly(&key.public_key());
+        let id = event_id(&pubkey, created_at, kind, &tags, content);
+        let mut aux_rand = [0_u8; 32];
+        rng.fill_bytes(&mut aux_rand);
+        let sig = schnorr::sign(key, &id, &aux_rand);
+        Self {
+            id,
+            pubkey,
+            created_at,
+            kind,
+            tags,
+            content: content.to_string(),
+            sig,
+        }
+    }
+
+    /// Whether the id commits to the event's fields and the signature
+    /// over it is the author's
+    pub fn verify(&self) -> bool {
+        self.id == event_id(&self.pubkey, self.created_at, self.kind, &self.tags, &self.content)
+            && schnorr::verify(&self.pubkey, &self.id, &self.sig)
+    }
+}
+
+/// sha256 of the compact JSON `[0,<pubkey hex>,<created_at>,<kind>,<tags>,<content>]`
+pub fn event_id(pubkey: &[u8; 32], created_at: u64, kind: u16, tags: &[Vec<String>], content: &str) -> [u8; 32] {
+    sha256(serialize_event(pubkey, created_at, kind, tags, content).as_bytes())
+}
+
+fn serialize_event(pubkey: &[u8; 32], created_at: u64, kind: u16, tags: &[Vec<String>], content: &str) -> String {
+    let tags: Vec<String> = tags
+        .iter()
+        .map(|tag| format!("[{}]", tag.iter().map(|s| json_string(s)).collect::<Vec<_>>().join(",")))
+        .collect();
+    format!(
+        "[0,\"{}\",{},{},[{}],{}]",
+        hex::encode(pubkey),
+        created_at,
+        kind,
+        tags.join(","),
+        json_string(content)
+    )
+}
+
+/// NIP-01 escapes only these characters, so every client hashes the same
+/// bytes; everything else, non-ASCII included, is written as is
+fn json_string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            '\u{8}' => res.push_str("\\b"),
+            '\u{c}' => res.push_str("\\f"),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod nostr_tests {
+    use super::*;
+
+    #[test]
+    fn test_nip19() {
+        // NIP-19 examples
+        let npub = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+        let xonly = hex::decode("7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e").unwrap();
+        assert_eq!(decode_npub(npub).unwrap().to_vec(), xonly);
+        assert_eq!(encode_npub(&xonly.try_into().unwrap()), npub);
+
+        let nsec = "nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5";
+        let key = decode_nsec(nsec).unwrap();
+        assert_eq!(
+            hex::encode(key.to_bytes()),
+            "67dea2ed018072d675f5415ecfaed7d2597555e202d85b3d65ea4e58d2d92ffa"
+        );
+        assert_eq!(encode_nsec(&key), nsec);
+
+        let id = [0xab; 32];
+        assert_eq!(decode_note(&encode_note(&id)), Ok(id));
+        assert_eq!(
+            decode_npub(nsec),
+            Err(NostrError::WrongPrefix {
+                expected: "npub",
+                found: "nsec".to_string()
+            })
+        );
+        let short = bech32::encode(
+            "note",
+            &bech32::convert_bits(&[1; 20], 8, 5, true).unwrap(),
+            Variant::Bech32,
+        )
+        .unwrap();
+        assert_eq!(decode_note(&short), Err(NostrError::InvalidLength(20)));
+        let bech32m = bech32::encode(
+            "note",
+            &bech32::convert_bits(&id, 8, 5, true).unwrap(),
+            Variant::Bech32m,
+        )
+        .unwrap();
+        assert_eq!(
+            decode_note(&bech32m),
+            Err(NostrError::Bech32(Bech32Error::WrongVariant))
+        );
+    }
+
+    #[test]
+    fn test_event() {
+        let key = PrivateKey::from_bytes(&[0x11; 32]).unwrap();
+        let tags = vec![
+            vec!["e".to_string(), "ab".repeat(32)],
+            vec!["p".to_string(), "cd".repeat(32), "wss://relay".to_string()],
+        ];
+        let event = Event::sign(&key, 1_700_000_000, 1, tags.clone(), "gm \"nostr\"\n\u{1F44B}");
+        assert_eq!(
+            serialize_event(&event.pubkey, event.created_at, event.kind, &event.tags, &event.content),
+            format!(
+                "[0,\"{}\",1700000000,1,[[\"e\",\"{}\"],[\"p\",\"{}\",\"wss://relay\"]],\"gm \\\"nostr\\\"\\n\u{1F44B}\"]",
+                hex::encode(event.pubkey),
+                "ab".repeat(32),
+                "cd".repeat(32)
+            )
+        );
+        assert!(event.verify());
+
+        let mut edited = event.clone();
+        edited.content.push('!');
+        assert!(!edited.verify());
+        let mut forged = event.clone();
+        forged.pubkey = schnorr::xonly(&PrivateKey::from_bytes(&[0x22; 32]).unwrap().public_key());
+        forged.id = event_id(
+            &forged.pubkey,
+            forged.created_at,
+            forged.kind,
+            &forged.tags,
+            &forged.content,
+        );
+        assert!(!forged.verify());
+        assert_eq!(json_string("a\\b\t\u{8}\u{c}\r"), "\"a\\\\b\\t\\b\\f\\r\"");
+    }
+}
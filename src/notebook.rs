@@ -0,0 +1,178 @@
+use crate::arithmetic::field_element::FieldElement;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::ecc::Signature;
+use crate::encoding::hex_fmt::readable;
+use crate::tx::Transaction;
+
+// HTML for Jupyter notebooks on the evcxr kernel. evcxr shows a value by
+// calling its `evcxr_display` method, if it has one, and rendering what
+// that prints between the EVCXR_BEGIN_CONTENT and EVCXR_END_CONTENT
+// markers, so no dependency is needed. The HTML is plain tables with no
+// styling, which notebooks theme themselves
+
+/// `evcxr_display` printing `to_html`
+macro_rules! impl_evcxr_display {
+    ($($type:ty),*) => {
+        $(
+            impl $type {
+                /// Called by evcxr to render the value as HTML
+                pub fn evcxr_display(&self) {
+                    println!("EVCXR_BEGIN_CONTENT text/html\n{}\nEVCXR_END_CONTENT", self.to_html());
+                }
+            }
+        )*
+    };
+}
+
+impl_evcxr_display!(FieldElement, Point, Signature, Transaction);
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// A table with a header row unless `header` is empty, every cell escaped
+/// and in code
+fn table(caption: &str, header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut out = format!("<table><caption>{}</caption>", escape(caption));
+    if !header.is_empty() {
+        let names: String = header.iter().map(|name| format!("<th>{}</th>", name)).collect();
+        out.push_str(&format!("<tr>{}</tr>", names));
+    }
+    for row in rows {
+        out.push_str("<tr>");
+        for cell in row {
+            out.push_str(&format!("<td><code>{}</code></td>", escape(cell)));
+        }
+        out.push_str("</tr>");
+    }
+    out.push_str("</table>");
+    out
+}
+
+impl FieldElement {
+    /// "43 ∈ 𝔽₂₂₃" with the modulus as a subscript
+    pub fn to_html(&self) -> String {
+        format!(
+            "<code>{}</code> ∈ 𝔽<sub>{}</sub>",
+            readable(&self.num()),
+            readable(&self.prime())
+        )
+    }
+}
+
+impl Point {
+    /// The coordinates, captioned with the curve
+    pub fn to_html(&self) -> String {
+        let (a, b) = (self.curve().a(), self.curve().b());
+        let caption = format!(
+            "y² = x³ + {}x + {} over 𝔽_{}",
+            readable(&a.num()),
+            readable(&b.num()),
+            readable(&a.prime())
+        );
+        match (self.x(), self.y()) {
+            (Some(x), Some(y)) => table(&caption, &["x", "y"], &[vec![readable(&x.num()), readable(&y.num())]]),
+            _ => format!("<p>𝒪, the point at infinity on {}</p>", escape(&caption)),
+        }
+    }
+}
+
+impl Signature {
+    pub fn to_html(&self) -> String {
+        table(
+            "Signature",
+            &[],
+            &[
+                vec!["r".to_string(), format!("{:#x}", self.r())],
+                vec!["s".to_string(), format!("{:#x}", self.s())],
+            ],
+        )
+    }
+}
+
+impl Transaction {
+    /// The txid, then a table of inputs and one of outputs
+    pub fn to_html(&self) -> String {
+        let inputs: Vec<Vec<String>> = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let witness: Vec<String> = input.witness.iter().map(hex::encode).collect();
+                vec![
+                    i.to_string(),
+                    input.previous_output.to_string(),
+                    input.script_sig.to_asm(),
+                    witness.join(" "),
+                    format!("{:#010x}", input.sequence),
+                ]
+            })
+            .collect();
+        let outputs: Vec<Vec<String>> = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(i, output)| vec![i.to_string(), output.amount.to_string(), output.script_pubkey.to_asm()])
+            .collect();
+        format!(
+            "<p>Transaction <code>{}</code>, version {}, locktime {}</p>{}{}",
+            self.id(),
+            self.version,
+            self.locktime,
+            table(
+                "Inputs",
+                &["#", "previous output", "script_sig", "witness", "sequence"],
+                &inputs
+            ),
+            table("Outputs", &["#", "amount (sat)", "script_pubkey"], &outputs),
+        )
+    }
+}
+
+#[cfg(test)]
+mod notebook_tests {
+    use num_bigint::{BigInt, BigUint};
+
+    use super::*;
+    use crate::curves::ec_point::Curve;
+
+    #[test]
+    fn test_field_and_points() {
+        let element = |n: i64| FieldElement::from(BigInt::from(n), BigInt::from(223)).unwrap();
+        assert_eq!(element(43).to_html(), "<code>43</code> ∈ 𝔽<sub>223</sub>");
+
+        let curve = Curve::new(element(0), element(7)).unwrap();
+        let point = Point::on_curve(Some(element(192)), Some(element(105)), &curve).unwrap();
+        assert_eq!(
+            point.to_html(),
+            "<table><caption>y² = x³ + 0x + 7 over 𝔽_223</caption><tr><th>x</th><th>y</th></tr>\
+             <tr><td><code>192</code></td><td><code>105</code></td></tr></table>"
+        );
+        assert_eq!(
+            Point::infinity_on(&curve).to_html(),
+            "<p>𝒪, the point at infinity on y² = x³ + 0x + 7 over 𝔽_223</p>"
+        );
+    }
+
+    #[test]
+    fn test_signature_and_transaction() {
+        let signature = Signature::from(BigUint::from(0xabc_u32), BigUint::from(0x123_u32));
+        assert!(signature
+            .to_html()
+            .contains("<td><code>r</code></td><td><code>0xabc</code></td>"));
+
+        // a coinbase input spending to OP_1
+        let tx = Transaction::from_hex(
+            "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0100ffffffff0100f2052a010000000151\
+             00000000",
+        )
+        .unwrap();
+        let html = tx.to_html();
+        assert!(html.starts_with(&format!(
+            "<p>Transaction <code>{}</code>, version 1, locktime 0</p>",
+            tx.id()
+        )));
+        assert!(html.contains("<td><code>5000000000</code></td><td><code>OP_1</code></td>"));
+        assert_eq!(escape("<a & b>"), "&lt;a &amp; b&gt;");
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::prime::{random_prime, random_unit};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaillierError {
+    /// Plaintexts are residues modulo n
+    MessageTooLarge,
+    /// Not a unit modulo n²
+    InvalidCiphertext,
+    /// Randomness must be a unit modulo n
+    InvalidRandomness,
+}
+
+impl Display for PaillierError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaillierError::MessageTooLarge => write!(f, "message must be less than n"),
+            PaillierError::InvalidCiphertext => write!(f, "ciphertext is not a unit modulo n²"),
+            PaillierError::InvalidRandomness => write!(f, "randomness is not a unit modulo n"),
+        }
+    }
+}
+
+impl std::error::Error for PaillierError {}
+
+/// n = pq, with the generator fixed to g = n + 1 so that g^m = 1 + mn
+/// mod n² needs no exponentiation
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicKey {
+    n: BigUint,
+    n_squared: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateKey {
+    public: PublicKey,
+    /// λ = lcm(p - 1, q - 1)
+    lambda: BigUint,
+    /// μ = λ^-1 mod n
+    mu: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ciphertext(BigUint);
+
+impl PrivateKey {
+    /// A key with an n of `bits` bits, from two primes of half the size.
+    /// Primes of equal length guarantee gcd(n, (p - 1)(q - 1)) = 1
+    pub fn generate(bits: u64) -> Self {
+        Self::generate_with_rng(bits, &mut OsRng)
+    }
+
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(bits: u64, rng: &mut R) -> Self {
+        loop {
+            let (p, q) = (random_prime(bits / 2, rng), random_prime(bits / 2, rng));
+            if p != q {
+                return Self::from_primes(&p, &q);
+            }
+        }
+    }
+
+    /// Panics if λ has no inverse modulo n, which distinct primes of the
+    /// same length rule out
+    pub fn from_primes(p: &BigUint, q: &BigUint) -> Self {
+        let n = p * q;
+        let lambda = (p - 1_u8).lcm(&(q - 1_u8));
+        let mu = lambda.modinv(&n).expect("gcd(n, λ) must be 1");
+        Self {
+            public: PublicKey { n_squared: &n * &n, n },
+            lambda,
+            mu,
+        }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+
+    /// m = L(c^λ mod n²) μ mod n, with L(x) = (x - 1) / n
+    pub fn decrypt(&self, c: &Ciphertext) -> Result<BigUint, PaillierError> {
+        let PublicKey { n, n_squared } = &self.public;
+        if c.0 >= *n_squared || !c.0.gcd(n_squared).is_one() {
+            return Err(PaillierError::InvalidCiphertext);
+        }
+        let x = c.0.modpow(&self.lambda, n_squared);
+        Ok((x - 1_u8) / n * &self.mu % n)
+    }
+}
+
+impl PublicKey {
+    pub fn n(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// c = (1 + mn) rⁿ mod n² for a fresh random r
+    pub fn encrypt(&self, m: &BigUint) -> Result<Ciphertext, PaillierError> {
+        self.encrypt_with(m, &random_unit(&self.n, &mut OsRng))
+    }
+
+    /// Encryption with chosen randomness, which must never repeat
+    pub fn encrypt_with(&self, m: &BigUint, r: &BigUint) -> Result<Ciphertext, PaillierError> {
+        if *m >= self.n {
+            return Err(PaillierError::MessageTooLarge);
+        }
+        if r.is_zero() || *r >= self.n || !r.gcd(&self.n).is_one() {
+            return Err(PaillierError::InvalidRandomness);
+        }
+        let g_m = (BigUint::one() + m * &self.n) % &self.n_squared;
+        Ok(Ciphertext(g_m * r.modpow(&self.n, &self.n_squared) % &self.n_squared))
+    }
+
+    /// Enc(a) Enc(b) = Enc(a + b mod n)
+    pub fn add(&self, a: &Ciphertext, b: &Ciphertext) -> Ciphertext {
+        Ciphertext(&a.0 * &b.0 % &self.n_squared)
+    }
+
+    /// Enc(a) (1 + bn) = Enc(a + b mod n), without fresh randomness
+    pub fn add_plain(&self, a: &Ciphertext, b: &BigUint) -> Ciphertext {
+        Ciphertext(&a.0 * (BigUint::one() + b % &self.n * &self.n) % &self.n_squared)
+    }
+
+    /// Enc(a)^k = Enc(k a mod n)
+    pub fn mul_scalar(&self, a: &Ciphertext, k: &BigUint) -> Ciphertext {
+        Ciphertext(a.0.modpow(k, &self.n_squared))
+    }
+
+    /// Multiplies in a fresh encryption of zero, so the result can't be
+    /// linked to the ciphertexts it was computed from
+    pub fn rerandomize(&self, a: &Ciphertext) -> Ciphertext {
+        let r = random_unit(&self.n, &mut OsRng);
+        Ciphertext(&a.0 * r.modpow(&self.n, &self.n_squared) % &self.n_squared)
+    }
+}
+
+impl Ciphertext {
+    pub fn from(c: BigUint) -> Self {
+        Self(c)
+    }
+
+    pub fn value(&self) -> &BigUint {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod paillier_tests {
+    use super::*;
+
+    #[test]
+    fn test_textbook() {
+        // p = 7, q = 11: n = 77, λ = 30, μ = 18
+        let key = PrivateKey::from_primes(&BigUint::from(7_u8), &BigUint::from(11_u8));
+        assert_eq!(
+            (key.lambda.clone(), key.mu.clone()),
+            (BigUint::from(30_u8), BigUint::from(18_u8))
+        );
+        let public = key.public_key();
+        let c = public
+            .encrypt_with(&BigUint::from(42_u8), &BigUint::from(23_u8))
+            .unwrap();
+        assert_eq!(key.decrypt(&c), Ok(BigUint::from(42_u8)));
+        assert_eq!(
+            public.encrypt_with(&BigUint::from(77_u8), &BigUint::from(23_u8)),
+            Err(PaillierError::MessageTooLarge)
+        );
+        assert_eq!(
+            public.encrypt_with(&BigUint::from(1_u8), &BigUint::from(14_u8)),
+            Err(PaillierError::InvalidRandomness)
+        );
+        assert_eq!(
+            key.decrypt(&Ciphertext::from(BigUint::from(49_u8))),
+            Err(PaillierError::InvalidCiphertext)
+        );
+    }
+
+    #[test]
+    fn test_homomorphism() {
+        let key = PrivateKey::generate(512);
+        let public = key.public_key();
+        assert_eq!(public.n().bits(), 512);
+        let (a, b) = (BigUint::from(123_456_u32), BigUint::from(654_321_u32));
+        let (ca, cb) = (public.encrypt(&a).unwrap(), public.encrypt(&b).unwrap());
+        assert_ne!(public.encrypt(&a).unwrap(), ca);
+
+        assert_eq!(key.decrypt(&public.add(&ca, &cb)), Ok(&a + &b));
+        assert_eq!(key.decrypt(&public.add_plain(&ca, &b)), Ok(&a + &b));
+        assert_eq!(
+            key.decrypt(&public.mul_scalar(&ca, &BigUint::from(1000_u32))),
+            Ok(&a * 1000_u32)
+        );
+        let rerandomized = public.rerandomize(&ca);
+        assert_ne!(rerandomized, ca);
+        assert_eq!(key.decrypt(&rerandomized), Ok(a.clone()));
+
+        // sums wrap modulo n
+        let big = public.n() - 1_u8;
+        let c = public.add(
+            &public.encrypt(&big).unwrap(),
+            &public.encrypt(&BigUint::from(2_u8)).unwrap(),
+        );
+        assert_eq!(key.decrypt(&c), Ok(BigUint::one()));
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+
+/// The easiest target mainnet and testnet allow, in compact form
+pub const MAX_TARGET_BITS: u32 = 0x1d00ffff;
+/// Blocks between difficulty adjustments
+pub const RETARGET_INTERVAL: u32 = 2016;
+/// Two weeks: 2016 blocks at one every ten minutes
+pub const TARGET_TIMESPAN: u32 = 14 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PowError {
+    /// The compact sign bit was set on a non-zero mantissa
+    Negative(u32),
+    /// The exponent puts the mantissa beyond 256 bits
+    Overflow(u32),
+}
+
+impl Display for PowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PowError::Negative(bits) => write!(f, "negative compact target {:#010x}", bits),
+            PowError::Overflow(bits) => write!(f, "compact target {:#010x} overflows 256 bits", bits),
+        }
+    }
+}
+
+impl std::error::Error for PowError {}
+
+/// Decodes the header's nBits: a one-byte base-256 exponent and a 23-bit
+/// mantissa with a sign bit, target = mantissa * 256^(exponent - 3)
+pub fn bits_to_target(bits: u32) -> Result<BigUint, PowError> {
+    let exponent = bits >> 24;
+    let mantissa = bits & 0x007f_ffff;
+    if mantissa != 0 && bits & 0x0080_0000 != 0 {
+        return Err(PowError::Negative(bits));
+    }
+    if mantissa != 0 && (exponent > 34 || (mantissa > 0xff && exponent > 33) || (mantissa > 0xffff && exponent > 32)) {
+        return Err(PowError::Overflow(bits));
+    }
+    Ok(if exponent <= 3 {
+        BigUint::from(mantissa >> (8 * (3 - exponent)))
+    } else {
+        BigUint::from(mantissa) << (8 * (exponent - 3))
+    })
+}
+
+/// Encodes a target in compact form, dropping all but its three most
+/// significant bytes. A mantissa that would set the sign bit is shifted
+/// into the next exponent instead
+pub fn target_to_bits(target: &BigUint) -> u32 {
+    let mut size = target.bits().div_ceil(8) as u32;
+    let mut mantissa = if size <= 3 {
+        target.to_u32().unwrap() << (8 * (3 - size))
+    } else {
+        (target >> (8 * (size - 3))).to_u32().unwrap()
+    };
+    if mantissa & 0x0080_0000 != 0 {
+        mantissa >>= 8;
+        size += 1;
+    }
+    (size << 24) | mantissa
+}
+
+pub fn max_target() -> BigUint {
+    bits_to_target(MAX_TARGET_BITS).unwrap()
+}
+
+/// How many times harder than the easiest target `bits` is, as block
+/// explorers show it
+pub fn difficulty(bits: u32) -> Result<f64, PowError> {
+    let target = bits_to_target(bits)?;
+    Ok(max_target().to_f64().unwrap() / target.to_f64().unwrap())
+}
+
+/// Expected number of hashes to meet the target, 2^256 / (target + 1).
+/// Chains are compared by the sum of their blocks' work, not their length
+pub fn work(bits: u32) -> Result<BigUint, PowError> {
+    let target = bits_to_target(bits)?;
+    Ok((BigUint::from(1_u8) << 256) / (target + 1_u8))
+}
+
+/// Whether a block hash (in internal byte order, i.e. little-endian) meets
+/// the target. Zero, negative, overflowing or too easy targets never do
+pub fn check_pow(hash: &[u8; 32], bits: u32) -> bool {
+    check_pow_with_limit(hash, bits, &max_target())
+}
+
+/// Like `check_pow` for networks whose easiest target isn't mainnet's,
+/// such as regtest
+pub fn check_pow_with_limit(hash: &[u8; 32], bits: u32, pow_limit: &BigUint) -> bool {
+    match bits_to_target(bits) {
+        Ok(target) if !target.is_zero() && target <= *pow_limit => BigUint::from_bytes_le(hash) <= target,
+        _ => false,
+    }
+}
+
+pub fn is_retarget_height(height: u32) -> bool {
+    height.is_multiple_of(RETARGET_INTERVAL)
+}
+
+/// The bits for the block after a retarget period, given the bits of the
+/// period's last block and the timestamps of its first and last blocks.
+/// The measured timespan is clamped to a factor of four either way and the
+/// result never gets easier than the maximum target. Like Bitcoin Core this
+/// measures 2015 intervals, not 2016
+pub fn retarget(prev_bits: u32, first_time: u32, last_time: u32) -> Result<u32, PowError> {
+    let actual = (last_time as i64 - first_time as i64).clamp(TARGET_TIMESPAN as i64 / 4, TARGET_TIMESPAN as i64 * 4);
+    let target = bits_to_target(prev_bits)? * actual as u64 / TARGET_TIMESPAN;
+    Ok(target_to_bits(&target.min(max_target())))
+}
+
+#[cfg(test)]
+mod pow_tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_encoding() {
+        // Bitcoin Core's arith_uint256 SetCompact/GetCompact cases
+        let cases: [(u32, u64, u32); 6] = [
+            (0x01003456, 0x00, 0x00000000),
+            (0x01123456, 0x12, 0x01120000),
+            (0x02008000, 0x80, 0x02008000),
+            (0x05009234, 0x92340000, 0x05009234),
+            (0x04123456, 0x12345600, 0x04123456),
+            (0x00123456, 0x00, 0x00000000),
+        ];
+        for (bits, target, canonical) in cases {
+            assert_eq!(bits_to_target(bits).unwrap(), BigUint::from(target), "{:08x}", bits);
+            assert_eq!(target_to_bits(&BigUint::from(target)), canonical);
+        }
+
+        let big = bits_to_target(0x20123456).unwrap();
+        assert_eq!(big, BigUint::from(0x123456_u32) << 232);
+        assert_eq!(target_to_bits(&big), 0x20123456);
+        assert_eq!(
+            bits_to_target(MAX_TARGET_BITS).unwrap(),
+            BigUint::parse_bytes(b"ffff0000000000000000000000000000000000000000000000000000", 16).unwrap()
+        );
+
+        assert_eq!(bits_to_target(0x04923456), Err(PowError::Negative(0x04923456)));
+        assert_eq!(bits_to_target(0xff123456), Err(PowError::Overflow(0xff123456)));
+        assert_eq!(bits_to_target(0x21010000), Err(PowError::Overflow(0x21010000)));
+        // a zero mantissa is zero whatever the sign and exponent
+        assert_eq!(bits_to_target(0xff800000), Ok(BigUint::zero()));
+    }
+
+    #[test]
+    fn test_difficulty_and_work() {
+        // expected values from rust-bitcoin
+        assert_eq!(difficulty(MAX_TARGET_BITS).unwrap(), 1.0);
+        assert!((difficulty(0x1d00d86a).unwrap() - 1.1828995343128408).abs() < 1e-12);
+        assert!((difficulty(0x1b0404cb).unwrap() - 16307.420938523983).abs() < 1e-6);
+
+        assert_eq!(work(MAX_TARGET_BITS).unwrap(), BigUint::from(0x1_0001_0001_u64));
+        assert_eq!(
+            work(0x170331db).unwrap(),
+            BigUint::parse_bytes(b"378410393580124934176862", 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_check_pow() {
+        // the genesis block hash, in internal byte order
+        let mut genesis = hex::decode("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f").unwrap();
+        genesis.reverse();
+        let genesis: [u8; 32] = genesis.try_into().unwrap();
+        assert!(check_pow(&genesis, MAX_TARGET_BITS));
+        assert!(!check_pow(&genesis, 0x1b0404cb));
+        assert!(!check_pow(&genesis, 0x1d01ffff));
+        assert!(!check_pow(&[0; 32], 0x01003456));
+    }
+
+    #[test]
+    fn test_retarget() {
+        // Bitcoin Core's pow_tests: the first real adjustment (block 32256),
+        // the maximum target cap, and both clamps
+        assert!(is_retarget_height(32256));
+        assert!(!is_retarget_height(32255));
+        assert_eq!(retarget(0x1d00ffff, 1261130161, 1262152739), Ok(0x1d00d86a));
+        assert_eq!(retarget(0x1d00ffff, 1231006505, 1233061996), Ok(0x1d00ffff));
+        assert_eq!(retarget(0x1c05a3f4, 1279008237, 1279297671), Ok(0x1c0168fd));
+        assert_eq!(retarget(0x1c387f6f, 1263163443, 1269211443), Ok(0x1d00e1fd));
+        // timestamps may go backwards; that clamps like a very fast period
+        assert_eq!(retarget(0x1c05a3f4, 1279297671, 1279008237), Ok(0x1c0168fd));
+        assert_eq!(retarget(0x04923456, 0, 1), Err(PowError::Negative(0x04923456)));
+    }
+}
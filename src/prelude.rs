@@ -0,0 +1,34 @@
+// The types most programs touch, for a single glob import:
+// `use moneda::prelude::*;`
+
+pub use crate::arithmetic::FieldElement;
+pub use crate::crypto::rng::DeterministicRng;
+pub use crate::crypto::schnorr;
+pub use crate::curves::secp256k1::{PrivateKey, PublicKey, Signature};
+pub use crate::curves::Point;
+pub use crate::errors::Error;
+pub use crate::script::Script;
+pub use crate::tx::{OutPoint, Transaction, TxIn, TxOut};
+
+#[cfg(test)]
+mod prelude_tests {
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    #[test]
+    fn test_prelude() {
+        let key = PrivateKey::from_bytes(&[0x24; 32]).unwrap();
+        let z = BigUint::from(99_u8);
+        assert!(key.public_key().verify(&z, &key.sign(&z)));
+        let sig = schnorr::sign(&key, b"msg", &[0; 32]);
+        assert!(schnorr::verify(&schnorr::xonly(&key.public_key()), b"msg", &sig));
+
+        let err: Error = Transaction::from_bytes(&[]).unwrap_err().into();
+        assert_eq!(err.to_string(), "transaction is truncated at byte 0 in version");
+        // the deprecated flat paths name the same types
+        #[allow(deprecated)]
+        let flat: crate::ecc::PrivateKey = key;
+        let _: &Point = flat.public_key().point();
+    }
+}
@@ -0,0 +1,1245 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use crate::bip32::{Bip32Error, ExtendedPrivateKey};
+use crate::crypto::hash::{hash160, sha256};
+use crate::crypto::schnorr::xonly;
+use crate::curves::secp256k1::ecc::{KeyError, PrivateKey};
+use crate::encoding::base64::{self, Base64Error};
+use crate::encoding::compact_size;
+use crate::encoding::decode::{decode_slice, Context, DecodeError};
+use crate::script::{Instruction, Opcode, Script};
+use crate::sighash::{EcdsaSighashType, Prevouts, SighashCache, SighashError, TapSighashType};
+use crate::sign::{ecdsa_signature, schnorr_signature};
+use crate::taproot::{tweak_private_key, tweak_public_key};
+use crate::tx::{read_var_bytes, write_var_bytes, Transaction, TxError, TxOut};
+
+const MAGIC: &[u8; 5] = b"psbt\xff";
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_GLOBAL_XPUB: u8 = 0x01;
+const PSBT_GLOBAL_VERSION: u8 = 0xfb;
+
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+const PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+const PSBT_IN_BIP32_DERIVATION: u8 = 0x06;
+const PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+const PSBT_IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+const PSBT_IN_TAP_KEY_SIG: u8 = 0x13;
+const PSBT_IN_TAP_BIP32_DERIVATION: u8 = 0x16;
+const PSBT_IN_TAP_INTERNAL_KEY: u8 = 0x17;
+const PSBT_IN_TAP_MERKLE_ROOT: u8 = 0x18;
+
+const PSBT_OUT_REDEEM_SCRIPT: u8 = 0x00;
+const PSBT_OUT_WITNESS_SCRIPT: u8 = 0x01;
+const PSBT_OUT_BIP32_DERIVATION: u8 = 0x02;
+const PSBT_OUT_TAP_INTERNAL_KEY: u8 = 0x05;
+const PSBT_OUT_TAP_BIP32_DERIVATION: u8 = 0x07;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PsbtError {
+    InvalidMagic,
+    Tx(TxError),
+    Base64(Base64Error),
+    DuplicateKey(Vec<u8>),
+    MissingUnsignedTx,
+    /// The unsigned transaction carries script_sigs or witnesses
+    UnsignedTxHasScripts,
+    /// A known key type with the wrong amount of key data
+    InvalidKey(Vec<u8>),
+    /// The value of a known key type doesn't parse
+    InvalidValue(u8),
+    /// Neither a witness nor a non-witness UTXO for this input
+    MissingUtxo(usize),
+    /// The non-witness UTXO, redeem script or witness script doesn't hash
+    /// to what the input spends
+    UtxoMismatch(usize),
+    InvalidSighashType(u32),
+    Sighash(SighashError),
+    Key(KeyError),
+    Bip32(Bip32Error),
+    /// Not enough signatures, or a script the finalizer doesn't know
+    CannotFinalize(usize),
+    NotFinalized(usize),
+}
+
+impl Display for PsbtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PsbtError::InvalidMagic => write!(f, "missing psbt magic bytes"),
+            PsbtError::Tx(e) => write!(f, "{}", e),
+            PsbtError::Base64(e) => write!(f, "{}", e),
+            PsbtError::DuplicateKey(key) => write!(f, "duplicate key {}", hex::encode(key)),
+            PsbtError::MissingUnsignedTx => write!(f, "psbt has no unsigned transaction"),
+            PsbtError::UnsignedTxHasScripts => write!(f, "unsigned transaction has script_sigs or witnesses"),
+            PsbtError::InvalidKey(key) => write!(f, "invalid key {}", hex::encode(key)),
+            PsbtError::InvalidValue(t) => write!(f, "invalid value for key type {:#04x}", t),
+            PsbtError::MissingUtxo(i) => write!(f, "input {} has no utxo", i),
+            PsbtError::UtxoMismatch(i) => write!(f, "utxo data of input {} does not match what it spends", i),
+            PsbtError::InvalidSighashType(t) => write!(f, "invalid sighash type {:#x}", t),
+            PsbtError::Sighash(e) => write!(f, "{}", e),
+            PsbtError::Key(e) => write!(f, "{}", e),
+            PsbtError::Bip32(e) => write!(f, "{}", e),
+            PsbtError::CannotFinalize(i) => write!(f, "cannot finalize input {}", i),
+            PsbtError::NotFinalized(i) => write!(f, "input {} is not finalized", i),
+        }
+    }
+}
+
+impl std::error::Error for PsbtError {}
+
+impl From<TxError> for PsbtError {
+    fn from(e: TxError) -> Self {
+        PsbtError::Tx(e)
+    }
+}
+
+impl From<TxError> for DecodeError<PsbtError> {
+    fn from(e: TxError) -> Self {
+        DecodeError::new(e.into())
+    }
+}
+
+impl From<SighashError> for PsbtError {
+    fn from(e: SighashError) -> Self {
+        PsbtError::Sighash(e)
+    }
+}
+
+impl From<KeyError> for PsbtError {
+    fn from(e: KeyError) -> Self {
+        PsbtError::Key(e)
+    }
+}
+
+/// Where a key came from: the master key's fingerprint and the BIP-32 path
+/// from it, which is what lets a hardware wallet find its own keys
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySource {
+    pub fingerprint: [u8; 4],
+    pub path: Vec<u32>,
+}
+
+impl KeySource {
+    fn parse(value: &[u8], key_type: u8) -> Result<Self, PsbtError> {
+        if value.len() < 4 || !value.len().is_multiple_of(4) {
+            return Err(PsbtError::InvalidValue(key_type));
+        }
+        Ok(Self {
+            fingerprint: value[..4].try_into().unwrap(),
+            path: value[4..]
+                .chunks(4)
+                .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                .collect(),
+        })
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        let mut res = self.fingerprint.to_vec();
+        for index in &self.path {
+            res.extend_from_slice(&index.to_le_bytes());
+        }
+        res
+    }
+}
+
+/// A taproot key's origin, preceded by the hashes of the leaves it signs for
+fn parse_tap_key_origin(value: &[u8], key_type: u8) -> Result<(Vec<[u8; 32]>, KeySource), PsbtError> {
+    let mut reader = value;
+    let count = compact_size::read(&mut reader).map_err(|_| PsbtError::InvalidValue(key_type))?;
+    if (reader.len() as u64) < count * 32 {
+        return Err(PsbtError::InvalidValue(key_type));
+    }
+    let (hashes, rest) = reader.split_at(count as usize * 32);
+    let leaf_hashes = hashes.chunks(32).map(|c| c.try_into().unwrap()).collect();
+    Ok((leaf_hashes, KeySource::parse(rest, key_type)?))
+}
+
+fn serialize_tap_key_origin(leaf_hashes: &[[u8; 32]], source: &KeySource) -> Vec<u8> {
+    let mut res = compact_size::encode(leaf_hashes.len() as u64);
+    for hash in leaf_hashes {
+        res.extend_from_slice(hash);
+    }
+    res.extend_from_slice(&source.serialize());
+    res
+}
+
+fn parse_witness(value: &[u8]) -> Result<Vec<Vec<u8>>, PsbtError> {
+    let parse = |mut reader: &[u8]| -> Result<Vec<Vec<u8>>, TxError> {
+        let witness = (0..compact_size::read_size(&mut reader)?)
+            .map(|_| read_var_bytes(&mut reader))
+            .collect::<Result<_, _>>()?;
+        if !reader.is_empty() {
+            return Err(TxError::TrailingBytes(reader.len()));
+        }
+        Ok(witness)
+    };
+    parse(value).map_err(|_| PsbtError::InvalidValue(PSBT_IN_FINAL_SCRIPTWITNESS))
+}
+
+fn serialize_witness(witness: &[Vec<u8>]) -> Vec<u8> {
+    let mut res = compact_size::encode(witness.len() as u64);
+    for item in witness {
+        write_var_bytes(&mut res, item).unwrap();
+    }
+    res
+}
+
+fn parse_array<const N: usize>(value: &[u8], key_type: u8) -> Result<[u8; N], PsbtError> {
+    value.try_into().map_err(|_| PsbtError::InvalidValue(key_type))
+}
+
+/// Known key types carry either no key data or a fixed-size key
+fn check_key(key: &[u8], lens: &[usize]) -> Result<(), PsbtError> {
+    if !lens.contains(&(key.len() - 1)) {
+        return Err(PsbtError::InvalidKey(key.to_vec()));
+    }
+    Ok(())
+}
+
+/// Where the data of a key and of its value start in the whole PSBT
+#[derive(Clone, Copy)]
+struct PairOffsets {
+    key: usize,
+    value: usize,
+}
+
+impl PairOffsets {
+    /// Errors about the key point at the key, the rest into the value
+    fn locate(self, e: DecodeError<PsbtError>) -> DecodeError<PsbtError> {
+        match e.kind() {
+            PsbtError::InvalidKey(_) => e.at(self.key),
+            _ => e.shift(self.value).at(self.value),
+        }
+    }
+}
+
+/// Raw key-value pairs of one map, keys including their type byte
+type Pairs = Vec<(PairOffsets, Vec<u8>, Vec<u8>)>;
+
+/// One key-value map, up to its 0x00 separator. `bytes` is the whole PSBT
+/// `reader` is reading from, to record where the pairs are
+fn read_map(bytes: &[u8], reader: &mut &[u8]) -> Result<Pairs, DecodeError<PsbtError>> {
+    let mut pairs = Pairs::new();
+    loop {
+        let key = read_var_bytes(reader)?;
+        if key.is_empty() {
+            return Ok(pairs);
+        }
+        let key_offset = bytes.len() - reader.len() - key.len();
+        let value = read_var_bytes(reader)?;
+        if pairs.iter().any(|(_, k, _)| *k == key) {
+            return Err(DecodeError::new(PsbtError::DuplicateKey(key)).at(key_offset));
+        }
+        let offsets = PairOffsets {
+            key: key_offset,
+            value: bytes.len() - reader.len() - value.len(),
+        };
+        pairs.push((offsets, key, value));
+    }
+}
+
+fn write_pair<W: Write>(writer: &mut W, key: &[u8], value: &[u8]) -> io::Result<()> {
+    write_var_bytes(writer, key)?;
+    write_var_bytes(writer, value)
+}
+
+fn keyed(key_type: u8, key_data: &[u8]) -> Vec<u8> {
+    let mut key = vec![key_type];
+    key.extend_from_slice(key_data);
+    key
+}
+
+fn pushes_key(script: &Script, sec: &[u8]) -> bool {
+    script
+        .instructions()
+        .any(|i| matches!(i, Ok(Instruction::Push(_, data)) if data == sec))
+}
+
+/// The witness or script_sig stack satisfying `<key> OP_CHECKSIG` or
+/// `OP_m <keys> OP_n OP_CHECKMULTISIG` with the signatures at hand, minus
+/// the script itself
+fn satisfy(script: &Script, sigs: &BTreeMap<Vec<u8>, Vec<u8>>) -> Option<Vec<Vec<u8>>> {
+    let instructions: Vec<_> = script.instructions().collect::<Result<_, _>>().ok()?;
+    match instructions[..] {
+        [Instruction::Push(_, key), Instruction::Op(Opcode::CheckSig)] => Some(vec![sigs.get(key)?.clone()]),
+        [Instruction::Op(m), ref keys @ .., Instruction::Op(n), Instruction::Op(Opcode::CheckMultiSig)] => {
+            let (m, n) = (m.small_int()? as usize, n.small_int()? as usize);
+            if keys.len() != n {
+                return None;
+            }
+            // CHECKMULTISIG pops one element too many
+            let mut stack = vec![Vec::new()];
+            for key in keys {
+                let Instruction::Push(_, key) = key else {
+                    return None;
+                };
+                if let Some(sig) = sigs.get(*key) {
+                    stack.push(sig.clone());
+                }
+            }
+            stack.truncate(m + 1);
+            (stack.len() == m + 1).then_some(stack)
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PsbtInput {
+    /// The whole transaction being spent from, needed for legacy inputs
+    pub non_witness_utxo: Option<Transaction>,
+    /// Just the output being spent, enough for segwit inputs
+    pub witness_utxo: Option<TxOut>,
+    /// SEC-encoded public key to signature, hash type byte included
+    pub partial_sigs: BTreeMap<Vec<u8>, Vec<u8>>,
+    pub sighash_type: Option<u32>,
+    pub redeem_script: Option<Script>,
+    pub witness_script: Option<Script>,
+    /// SEC-encoded public key to its origin
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>,
+    pub final_script_sig: Option<Script>,
+    pub final_script_witness: Option<Vec<Vec<u8>>>,
+    pub tap_key_sig: Option<Vec<u8>>,
+    /// x-only public key to the leaves it signs for and its origin
+    pub tap_key_origins: BTreeMap<[u8; 32], (Vec<[u8; 32]>, KeySource)>,
+    pub tap_internal_key: Option<[u8; 32]>,
+    pub tap_merkle_root: Option<[u8; 32]>,
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl PsbtInput {
+    fn from_pairs(pairs: Pairs) -> Result<Self, DecodeError<PsbtError>> {
+        let mut input = Self::default();
+        for (offsets, key, value) in pairs {
+            input.insert(key, value).map_err(|e| offsets.locate(e))?;
+        }
+        Ok(input)
+    }
+
+    fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<(), DecodeError<PsbtError>> {
+        let key_type = key[0];
+        match key_type {
+            PSBT_IN_NON_WITNESS_UTXO => {
+                check_key(&key, &[0])?;
+                let tx = Transaction::from_bytes(&value).map_err(|e| e.map(|_| PsbtError::InvalidValue(key_type)))?;
+                self.non_witness_utxo = Some(tx);
+            }
+            PSBT_IN_WITNESS_UTXO => {
+                check_key(&key, &[0])?;
+                let output =
+                    decode_slice(&value, TxOut::parse).map_err(|e| e.map(|_| PsbtError::InvalidValue(key_type)))?;
+                self.witness_utxo = Some(output);
+            }
+            PSBT_IN_PARTIAL_SIG => {
+                check_key(&key, &[33, 65])?;
+                self.partial_sigs.insert(key[1..].to_vec(), value);
+            }
+            PSBT_IN_SIGHASH_TYPE => {
+                check_key(&key, &[0])?;
+                self.sighash_type = Some(u32::from_le_bytes(parse_array(&value, key_type)?));
+            }
+            PSBT_IN_REDEEM_SCRIPT => {
+                check_key(&key, &[0])?;
+                self.redeem_script = Some(Script::from_bytes(value));
+            }
+            PSBT_IN_WITNESS_SCRIPT => {
+                check_key(&key, &[0])?;
+                self.witness_script = Some(Script::from_bytes(value));
+            }
+            PSBT_IN_BIP32_DERIVATION => {
+                check_key(&key, &[33, 65])?;
+                self.bip32_derivation
+                    .insert(key[1..].to_vec(), KeySource::parse(&value, key_type)?);
+            }
+            PSBT_IN_FINAL_SCRIPTSIG => {
+                check_key(&key, &[0])?;
+                self.final_script_sig = Some(Script::from_bytes(value));
+            }
+            PSBT_IN_FINAL_SCRIPTWITNESS => {
+                check_key(&key, &[0])?;
+                self.final_script_witness = Some(parse_witness(&value)?);
+            }
+            PSBT_IN_TAP_KEY_SIG => {
+                check_key(&key, &[0])?;
+                if value.len() != 64 && value.len() != 65 {
+                    return Err(PsbtError::InvalidValue(key_type).into());
+                }
+                self.tap_key_sig = Some(value);
+            }
+            PSBT_IN_TAP_BIP32_DERIVATION => {
+                check_key(&key, &[32])?;
+                let origin = parse_tap_key_origin(&value, key_type)?;
+                self.tap_key_origins.insert(key[1..].try_into().unwrap(), origin);
+            }
+            PSBT_IN_TAP_INTERNAL_KEY => {
+                check_key(&key, &[0])?;
+                self.tap_internal_key = Some(parse_array(&value, key_type)?);
+            }
+            PSBT_IN_TAP_MERKLE_ROOT => {
+                check_key(&key, &[0])?;
+                self.tap_merkle_root = Some(parse_array(&value, key_type)?);
+            }
+            _ => {
+                self.unknown.insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if let Some(tx) = &self.non_witness_utxo {
+            write_pair(writer, &[PSBT_IN_NON_WITNESS_UTXO], &tx.serialize())?;
+        }
+        if let Some(output) = &self.witness_utxo {
+            let mut value = Vec::new();
+            output.write(&mut value)?;
+            write_pair(writer, &[PSBT_IN_WITNESS_UTXO], &value)?;
+        }
+        for (pubkey, sig) in &self.partial_sigs {
+            write_pair(writer, &keyed(PSBT_IN_PARTIAL_SIG, pubkey), sig)?;
+        }
+        if let Some(sighash_type) = self.sighash_type {
+            write_pair(writer, &[PSBT_IN_SIGHASH_TYPE], &sighash_type.to_le_bytes())?;
+        }
+        if let Some(script) = &self.redeem_script {
+            write_pair(writer, &[PSBT_IN_REDEEM_SCRIPT], script.as_bytes())?;
+        }
+        if let Some(script) = &self.witness_script {
+            write_pair(writer, &[PSBT_IN_WITNESS_SCRIPT], script.as_bytes())?;
+        }
+        for (pubkey, source) in &self.bip32_derivation {
+            write_pair(writer, &keyed(PSBT_IN_BIP32_DERIVATION, pubkey), &source.serialize())?;
+        }
+        if let Some(script) = &self.final_script_sig {
+            write_pair(writer, &[PSBT_IN_FINAL_SCRIPTSIG], script.as_bytes())?;
+        }
+        if let Some(witness) = &self.final_script_witness {
+            write_pair(writer, &[PSBT_IN_FINAL_SCRIPTWITNESS], &serialize_witness(witness))?;
+        }
+        if let Some(sig) = &self.tap_key_sig {
+            write_pair(writer, &[PSBT_IN_TAP_KEY_SIG], sig)?;
+        }
+        for (key, (leaf_hashes, source)) in &self.tap_key_origins {
+            let value = serialize_tap_key_origin(leaf_hashes, source);
+            write_pair(writer, &keyed(PSBT_IN_TAP_BIP32_DERIVATION, key), &value)?;
+        }
+        if let Some(key) = &self.tap_internal_key {
+            write_pair(writer, &[PSBT_IN_TAP_INTERNAL_KEY], key)?;
+        }
+        if let Some(root) = &self.tap_merkle_root {
+            write_pair(writer, &[PSBT_IN_TAP_MERKLE_ROOT], root)?;
+        }
+        for (key, value) in &self.unknown {
+            write_pair(writer, key, value)?;
+        }
+        writer.write_all(&[0x00])
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.final_script_sig.is_some() || self.final_script_witness.is_some()
+    }
+
+    /// The origin of a public key, SEC-encoded or x-only
+    pub fn key_source(&self, pubkey: &[u8]) -> Option<&KeySource> {
+        self.bip32_derivation.get(pubkey).or_else(|| {
+            let key: &[u8; 32] = pubkey.try_into().ok()?;
+            self.tap_key_origins.get(key).map(|(_, source)| source)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PsbtOutput {
+    pub redeem_script: Option<Script>,
+    pub witness_script: Option<Script>,
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>,
+    pub tap_internal_key: Option<[u8; 32]>,
+    pub tap_key_origins: BTreeMap<[u8; 32], (Vec<[u8; 32]>, KeySource)>,
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl PsbtOutput {
+    fn from_pairs(pairs: Pairs) -> Result<Self, DecodeError<PsbtError>> {
+        let mut output = Self::default();
+        for (offsets, key, value) in pairs {
+            output.insert(key, value).map_err(|e| offsets.locate(e))?;
+        }
+        Ok(output)
+    }
+
+    fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<(), DecodeError<PsbtError>> {
+        let key_type = key[0];
+        match key_type {
+            PSBT_OUT_REDEEM_SCRIPT => {
+                check_key(&key, &[0])?;
+                self.redeem_script = Some(Script::from_bytes(value));
+            }
+            PSBT_OUT_WITNESS_SCRIPT => {
+                check_key(&key, &[0])?;
+                self.witness_script = Some(Script::from_bytes(value));
+            }
+            PSBT_OUT_BIP32_DERIVATION => {
+                check_key(&key, &[33, 65])?;
+                self.bip32_derivation
+                    .insert(key[1..].to_vec(), KeySource::parse(&value, key_type)?);
+            }
+            PSBT_OUT_TAP_INTERNAL_KEY => {
+                check_key(&key, &[0])?;
+                self.tap_internal_key = Some(parse_array(&value, key_type)?);
+            }
+            PSBT_OUT_TAP_BIP32_DERIVATION => {
+                check_key(&key, &[32])?;
+                let origin = parse_tap_key_origin(&value, key_type)?;
+                self.tap_key_origins.insert(key[1..].try_into().unwrap(), origin);
+            }
+            _ => {
+                self.unknown.insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if let Some(script) = &self.redeem_script {
+            write_pair(writer, &[PSBT_OUT_REDEEM_SCRIPT], script.as_bytes())?;
+        }
+        if let Some(script) = &self.witness_script {
+            write_pair(writer, &[PSBT_OUT_WITNESS_SCRIPT], script.as_bytes())?;
+        }
+        for (pubkey, source) in &self.bip32_derivation {
+            write_pair(writer, &keyed(PSBT_OUT_BIP32_DERIVATION, pubkey), &source.serialize())?;
+        }
+        if let Some(key) = &self.tap_internal_key {
+            write_pair(writer, &[PSBT_OUT_TAP_INTERNAL_KEY], key)?;
+        }
+        for (key, (leaf_hashes, source)) in &self.tap_key_origins {
+            let value = serialize_tap_key_origin(leaf_hashes, source);
+            write_pair(writer, &keyed(PSBT_OUT_TAP_BIP32_DERIVATION, key), &value)?;
+        }
+        for (key, value) in &self.unknown {
+            write_pair(writer, key, value)?;
+        }
+        writer.write_all(&[0x00])
+    }
+
+    /// The origin of a public key, SEC-encoded or x-only
+    pub fn key_source(&self, pubkey: &[u8]) -> Option<&KeySource> {
+        self.bip32_derivation.get(pubkey).or_else(|| {
+            let key: &[u8; 32] = pubkey.try_into().ok()?;
+            self.tap_key_origins.get(key).map(|(_, source)| source)
+        })
+    }
+}
+
+/// A signature produced for one input, before it is stored
+enum InputSignature {
+    Ecdsa { pubkey: Vec<u8>, signature: Vec<u8> },
+    TaprootKey(Vec<u8>),
+}
+
+/// A partially signed transaction (BIP-174 version 0): the unsigned
+/// transaction plus what each signer needs to know about every input and
+/// output, passed between wallets until it can be finalized
+#[derive(Debug, Clone, PartialEq)]
+pub struct Psbt {
+    pub unsigned_tx: Transaction,
+    /// Serialized extended public key to its origin
+    pub xpubs: BTreeMap<Vec<u8>, KeySource>,
+    pub version: u32,
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+    pub inputs: Vec<PsbtInput>,
+    pub outputs: Vec<PsbtOutput>,
+}
+
+impl Psbt {
+    /// The creator role: wraps a transaction whose inputs aren't signed yet
+    pub fn from_unsigned_tx(tx: Transaction) -> Result<Self, PsbtError> {
+        if tx
+            .inputs
+            .iter()
+            .any(|input| !input.script_sig.is_empty() || !input.witness.is_empty())
+        {
+            return Err(PsbtError::UnsignedTxHasScripts);
+        }
+        Ok(Self {
+            inputs: vec![PsbtInput::default(); tx.inputs.len()],
+            outputs: vec![PsbtOutput::default(); tx.outputs.len()],
+            unsigned_tx: tx,
+            xpubs: BTreeMap::new(),
+            version: 0,
+            unknown: BTreeMap::new(),
+        })
+    }
+
+    /// Parses a whole PSBT, locating errors with the map they are in:
+    /// `"global"`, `"inputs[i]"` or `"outputs[i]"`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError<PsbtError>> {
+        decode_slice(bytes, |reader| Self::parse(bytes, reader))
+    }
+
+    fn parse(bytes: &[u8], reader: &mut &[u8]) -> Result<Self, DecodeError<PsbtError>> {
+        *reader = reader.strip_prefix(MAGIC).ok_or(PsbtError::InvalidMagic)?;
+
+        let mut unsigned_tx = None;
+        let mut xpubs = BTreeMap::new();
+        let mut version = 0;
+        let mut unknown = BTreeMap::new();
+        let mut insert = |key: Vec<u8>, value: Vec<u8>| -> Result<(), DecodeError<PsbtError>> {
+            let key_type = key[0];
+            match key_type {
+                PSBT_GLOBAL_UNSIGNED_TX => {
+                    check_key(&key, &[0])?;
+                    let tx = Transaction::from_bytes(&value).map_err(|e| e.map(PsbtError::Tx))?;
+                    unsigned_tx = Some(tx);
+                }
+                PSBT_GLOBAL_XPUB => {
+                    check_key(&key, &[78])?;
+                    xpubs.insert(key[1..].to_vec(), KeySource::parse(&value, key_type)?);
+                }
+                PSBT_GLOBAL_VERSION => {
+                    check_key(&key, &[0])?;
+                    version = u32::from_le_bytes(parse_array(&value, key_type)?);
+                }
+                _ => {
+                    unknown.insert(key, value);
+                }
+            }
+            Ok(())
+        };
+        for (offsets, key, value) in read_map(bytes, reader).context("global")? {
+            insert(key, value).map_err(|e| offsets.locate(e)).context("global")?;
+        }
+        let unsigned_tx = unsigned_tx.ok_or(PsbtError::MissingUnsignedTx).context("global")?;
+
+        let mut psbt = Self::from_unsigned_tx(unsigned_tx)?;
+        psbt.xpubs = xpubs;
+        psbt.version = version;
+        psbt.unknown = unknown;
+        for (i, input) in psbt.inputs.iter_mut().enumerate() {
+            let pairs = read_map(bytes, reader).context(format_args!("inputs[{}]", i))?;
+            *input = PsbtInput::from_pairs(pairs).context(format_args!("inputs[{}]", i))?;
+        }
+        for (i, output) in psbt.outputs.iter_mut().enumerate() {
+            let pairs = read_map(bytes, reader).context(format_args!("outputs[{}]", i))?;
+            *output = PsbtOutput::from_pairs(pairs).context(format_args!("outputs[{}]", i))?;
+        }
+        Ok(psbt)
+    }
+
+    pub fn from_base64(s: &str) -> Result<Self, DecodeError<PsbtError>> {
+        Self::from_bytes(&base64::decode(s).map_err(PsbtError::Base64)?)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = MAGIC.to_vec();
+        write_pair(&mut res, &[PSBT_GLOBAL_UNSIGNED_TX], &self.unsigned_tx.serialize()).unwrap();
+        for (xpub, source) in &self.xpubs {
+            write_pair(&mut res, &keyed(PSBT_GLOBAL_XPUB, xpub), &source.serialize()).unwrap();
+        }
+        if self.version != 0 {
+            write_pair(&mut res, &[PSBT_GLOBAL_VERSION], &self.version.to_le_bytes()).unwrap();
+        }
+        for (key, value) in &self.unknown {
+            write_pair(&mut res, key, value).unwrap();
+        }
+        res.push(0x00);
+        for input in &self.inputs {
+            input.write(&mut res).unwrap();
+        }
+        for output in &self.outputs {
+            output.write(&mut res).unwrap();
+        }
+        res
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.serialize())
+    }
+
+    /// The output input `index` spends, from its witness UTXO or, failing
+    /// that, its non-witness UTXO checked against the outpoint
+    pub fn spent_output(&self, index: usize) -> Result<&TxOut, PsbtError> {
+        let input = &self.inputs[index];
+        if let Some(output) = &input.witness_utxo {
+            return Ok(output);
+        }
+        let tx = input.non_witness_utxo.as_ref().ok_or(PsbtError::MissingUtxo(index))?;
+        let outpoint = &self.unsigned_tx.inputs[index].previous_output;
+        if tx.txid() != outpoint.txid {
+            return Err(PsbtError::UtxoMismatch(index));
+        }
+        tx.outputs
+            .get(outpoint.vout as usize)
+            .ok_or(PsbtError::UtxoMismatch(index))
+    }
+
+    /// Signs input `index` with `key` if it can spend it, without storing
+    /// the signature. Handles P2PKH, P2WPKH and P2WSH, bare or nested in
+    /// P2SH, bare scripts containing the key, and taproot key paths
+    fn input_signature(
+        &self,
+        cache: &SighashCache,
+        index: usize,
+        key: &PrivateKey,
+    ) -> Result<Option<InputSignature>, PsbtError> {
+        let input = &self.inputs[index];
+        if input.is_finalized() {
+            return Ok(None);
+        }
+        let utxo = self.spent_output(index)?;
+        let public_key = key.public_key();
+        let sec = public_key.sec(true);
+
+        if let Some((1, program)) = utxo.script_pubkey.witness_program() {
+            let internal = xonly(&public_key);
+            if program.len() != 32 || input.tap_internal_key != Some(internal) {
+                return Ok(None);
+            }
+            let merkle_root = input.tap_merkle_root.as_ref();
+            let (output_key, _) = tweak_public_key(&internal, merkle_root)?;
+            if program != output_key {
+                return Err(PsbtError::UtxoMismatch(index));
+            }
+            let sighash_type = match input.sighash_type {
+                None => TapSighashType::Default,
+                Some(t) => u8::try_from(t)
+                    .ok()
+                    .and_then(TapSighashType::from_u8)
+                    .ok_or(PsbtError::InvalidSighashType(t))?,
+            };
+            let prevouts = (0..self.inputs.len())
+                .map(|i| self.spent_output(i).cloned())
+                .collect::<Result<Vec<_>, _>>()?;
+            let sighash = cache.taproot_signature_hash(index, &Prevouts::All(&prevouts), None, None, sighash_type)?;
+            let tweaked = tweak_private_key(key, merkle_root)?;
+            return Ok(Some(InputSignature::TaprootKey(schnorr_signature(
+                &tweaked,
+                &sighash,
+                sighash_type,
+            ))));
+        }
+
+        let raw_type = input.sighash_type.unwrap_or(1);
+        let sighash_type = EcdsaSighashType::from_u32(raw_type).ok_or(PsbtError::InvalidSighashType(raw_type))?;
+        let inner = if utxo.script_pubkey.is_p2sh() {
+            match &input.redeem_script {
+                Some(script) if Script::p2sh(&hash160(script.as_bytes())) == utxo.script_pubkey => script,
+                Some(_) => return Err(PsbtError::UtxoMismatch(index)),
+                None => return Ok(None),
+            }
+        } else {
+            &utxo.script_pubkey
+        };
+        let (sighash, pubkey) = match inner.witness_program() {
+            Some((0, program)) if program.len() == 20 => {
+                if program != hash160(&sec) {
+                    return Ok(None);
+                }
+                (
+                    cache.p2wpkh_signature_hash(index, inner, utxo.amount, sighash_type)?,
+                    sec,
+                )
+            }
+            Some((0, program)) if program.len() == 32 => {
+                let Some(witness_script) = &input.witness_script else {
+                    return Ok(None);
+                };
+                if program != sha256(witness_script.as_bytes()) {
+                    return Err(PsbtError::UtxoMismatch(index));
+                }
+                if !pushes_key(witness_script, &sec) {
+                    return Ok(None);
+                }
+                (
+                    cache.p2wsh_signature_hash(index, witness_script, utxo.amount, sighash_type)?,
+                    sec,
+                )
+            }
+            Some(_) => return Ok(None),
+            None => {
+                let Some(pubkey) = [sec, public_key.sec(false)]
+                    .into_iter()
+                    .find(|sec| *inner == Script::p2pkh(&hash160(sec)) || pushes_key(inner, sec))
+                else {
+                    return Ok(None);
+                };
+                (cache.legacy_signature_hash(index, inner, raw_type)?, pubkey)
+            }
+        };
+        let signature = ecdsa_signature(key, &sighash, sighash_type);
+        Ok(Some(InputSignature::Ecdsa { pubkey, signature }))
+    }
+
+    /// The signer role: adds a signature from `key` to every input it can
+    /// spend, returning how many it signed
+    pub fn sign_with_key(&mut self, key: &PrivateKey) -> Result<usize, PsbtError> {
+        let keys = vec![vec![key.clone()]; self.inputs.len()];
+        self.sign_inputs(&keys)
+    }
+
+    /// The signer role for an HD wallet: derives the keys listed in each
+    /// input's derivation paths under `master`'s fingerprint and signs with
+    /// them, returning how many signatures were added
+    pub fn sign(&mut self, master: &ExtendedPrivateKey) -> Result<usize, PsbtError> {
+        let fingerprint = master.fingerprint();
+        let keys = self
+            .inputs
+            .iter()
+            .map(|input| {
+                let sources = input
+                    .bip32_derivation
+                    .values()
+                    .chain(input.tap_key_origins.values().map(|(_, source)| source));
+                sources
+                    .filter(|source| source.fingerprint == fingerprint)
+                    .map(|source| Ok(master.derive_path(&source.path).map_err(PsbtError::Bip32)?.private_key))
+                    .collect::<Result<Vec<_>, PsbtError>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.sign_inputs(&keys)
+    }
+
+    fn sign_inputs(&mut self, keys: &[Vec<PrivateKey>]) -> Result<usize, PsbtError> {
+        let tx = self.unsigned_tx.clone();
+        let cache = SighashCache::new(&tx);
+        let mut signatures = Vec::new();
+        for (index, keys) in keys.iter().enumerate() {
+            for key in keys {
+                if let Some(signature) = self.input_signature(&cache, index, key)? {
+                    signatures.push((index, signature));
+                }
+            }
+        }
+
+        let count = signatures.len();
+        for (index, signature) in signatures {
+            match signature {
+                InputSignature::Ecdsa { pubkey, signature } => {
+                    self.inputs[index].partial_sigs.insert(pubkey, signature);
+                }
+                InputSignature::TaprootKey(signature) => self.inputs[index].tap_key_sig = Some(signature),
+            }
+        }
+        Ok(count)
+    }
+
+    /// The finalizer role: turns the signatures of every input into its
+    /// final script_sig and witness, then drops everything else but the
+    /// UTXOs, as BIP-174 requires
+    pub fn finalize(&mut self) -> Result<(), PsbtError> {
+        for index in 0..self.inputs.len() {
+            if !self.inputs[index].is_finalized() {
+                self.finalize_input(index)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize_input(&mut self, index: usize) -> Result<(), PsbtError> {
+        let utxo = self.spent_output(index)?.clone();
+        let input = &self.inputs[index];
+        let cannot_finalize = PsbtError::CannotFinalize(index);
+
+        let (script_sig, witness) = match utxo.script_pubkey.witness_program() {
+            Some((1, program)) if program.len() == 32 => {
+                let sig = input.tap_key_sig.clone().ok_or(cannot_finalize)?;
+                (Script::new(), vec![sig])
+            }
+            _ => {
+                let (inner, prefix) = if utxo.script_pubkey.is_p2sh() {
+                    let redeem = input.redeem_script.as_ref().ok_or(cannot_finalize.clone())?;
+                    (redeem, Script::new().push_slice(redeem.as_bytes()))
+                } else {
+                    (&utxo.script_pubkey, Script::new())
+                };
+                let key_hash_spend = |hash: &[u8]| {
+                    input
+                        .partial_sigs
+                        .iter()
+                        .find(|(pubkey, _)| hash160(pubkey) == hash)
+                        .map(|(pubkey, sig)| vec![sig.clone(), pubkey.clone()])
+                        .ok_or(cannot_finalize.clone())
+                };
+                match inner.witness_program() {
+                    Some((0, program)) if program.len() == 20 => (prefix, key_hash_spend(program)?),
+                    Some((0, program)) if program.len() == 32 => {
+                        let witness_script = input.witness_script.as_ref().ok_or(cannot_finalize.clone())?;
+                        let mut stack = satisfy(witness_script, &input.partial_sigs).ok_or(cannot_finalize)?;
+                        stack.push(witness_script.as_bytes().to_vec());
+                        (prefix, stack)
+                    }
+                    Some(_) => return Err(cannot_finalize),
+                    None => {
+                        let stack = if inner.is_p2pkh() {
+                            key_hash_spend(&inner.as_bytes()[3..23])?
+                        } else {
+                            satisfy(inner, &input.partial_sigs).ok_or(cannot_finalize)?
+                        };
+                        let script_sig = stack.iter().fold(Script::new(), |script, item| script.push_slice(item));
+                        let script_sig = if utxo.script_pubkey.is_p2sh() {
+                            script_sig.push_slice(inner.as_bytes())
+                        } else {
+                            script_sig
+                        };
+                        (script_sig, Vec::new())
+                    }
+                }
+            }
+        };
+
+        let input = &mut self.inputs[index];
+        *input = PsbtInput {
+            non_witness_utxo: input.non_witness_utxo.take(),
+            witness_utxo: input.witness_utxo.take(),
+            final_script_sig: (!script_sig.is_empty()).then_some(script_sig),
+            final_script_witness: (!witness.is_empty()).then_some(witness),
+            unknown: std::mem::take(&mut input.unknown),
+            ..PsbtInput::default()
+        };
+        Ok(())
+    }
+
+    /// The extractor role: the network transaction, once every input is
+    /// finalized
+    pub fn extract_tx(&self) -> Result<Transaction, PsbtError> {
+        let mut tx = self.unsigned_tx.clone();
+        for (index, (tx_input, input)) in tx.inputs.iter_mut().zip(&self.inputs).enumerate() {
+            if !input.is_finalized() {
+                return Err(PsbtError::NotFinalized(index));
+            }
+            tx_input.script_sig = input.final_script_sig.clone().unwrap_or_default();
+            tx_input.witness = input.final_script_witness.clone().unwrap_or_default();
+        }
+        Ok(tx)
+    }
+}
+
+impl Display for Psbt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_base64())
+    }
+}
+
+#[cfg(test)]
+mod psbt_tests {
+    use super::*;
+    use crate::address::Network;
+    use crate::bip32::HARDENED;
+    use crate::crypto::schnorr;
+    use crate::tx::{OutPoint, TxIn};
+    use num_bigint::BigUint;
+
+    // BIP-174 valid vector 1: one P2PKH input with its non-witness UTXO
+    const BIP174_VALID_1: &str = "cHNidP8BAHUCAAAAASaBcTce3/KF6Tet7qSze3gADAVmy7OtZGQXE8pCFxv2AAAAAAD+////AtPf9QUAAAAAGXapFNDFmQPFusKGh2DpD9UhpGZap2UgiKwA4fUFAAAAABepFDVF5uM7gyxHBQ8k0+65PJwDlIvHh7MuEwAAAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAAAA";
+
+    // BIP-174 valid vector 6: an unknown input key type
+    const BIP174_VALID_6: &str = "70736274ff01003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+        0000000000ffffffff010000000000000000036a010000000000000a0f0102030405060708090f0102030405060708090a0b0c0d0e0f0000";
+
+    /// A one-input, one-output PSBT around the transaction of valid vector 6
+    /// with the given input map
+    fn single_input_psbt(pairs: &[(&[u8], &[u8])]) -> Vec<u8> {
+        let tx = hex::decode(&BIP174_VALID_6[16..16 + 126]).unwrap();
+        let mut res = MAGIC.to_vec();
+        write_pair(&mut res, &[PSBT_GLOBAL_UNSIGNED_TX], &tx).unwrap();
+        res.push(0x00);
+        for (key, value) in pairs {
+            write_pair(&mut res, key, value).unwrap();
+        }
+        res.extend_from_slice(&[0x00, 0x00]);
+        res
+    }
+
+    #[test]
+    fn test_bip174_valid_vectors() {
+        let psbt = Psbt::from_base64(BIP174_VALID_1).unwrap();
+        assert_eq!(psbt.unsigned_tx.locktime, 1257139);
+        assert_eq!(psbt.inputs.len(), 1);
+        assert_eq!(psbt.outputs, vec![PsbtOutput::default(); 2]);
+        let utxo = psbt.spent_output(0).unwrap();
+        assert!(utxo.script_pubkey.is_p2pkh());
+        assert_eq!(utxo.amount, 200_000_000);
+        assert_eq!(psbt.to_string(), BIP174_VALID_1);
+
+        let bytes = hex::decode(BIP174_VALID_6).unwrap();
+        let psbt = Psbt::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            psbt.unsigned_tx.id(),
+            "75c5c9665a570569ad77dd1279e6fd4628a093c4dcbf8d41532614044c14c115"
+        );
+        assert_eq!(
+            psbt.inputs[0].unknown,
+            BTreeMap::from([(
+                hex::decode("0f010203040506070809").unwrap(),
+                hex::decode("0102030405060708090a0b0c0d0e0f").unwrap()
+            )])
+        );
+        assert_eq!(psbt.serialize(), bytes);
+    }
+
+    #[test]
+    fn test_bip174_invalid_vectors() {
+        // a network transaction, no magic
+        let tx = Psbt::from_bytes(&hex::decode(BIP174_VALID_6).unwrap())
+            .unwrap()
+            .unsigned_tx;
+        let kind = |bytes: &[u8]| Psbt::from_bytes(bytes).unwrap_err().into_kind();
+        assert_eq!(kind(&tx.serialize()), PsbtError::InvalidMagic);
+
+        // no unsigned transaction, or one with a script_sig
+        let err = Psbt::from_bytes(b"psbt\xff\x00\x00\x00").unwrap_err();
+        assert_eq!(err.to_string(), "psbt has no unsigned transaction at byte 6 in global");
+        let mut signed = tx.clone();
+        signed.inputs[0].script_sig = Script::new().push_int(1);
+        assert_eq!(Psbt::from_unsigned_tx(signed), Err(PsbtError::UnsignedTxHasScripts));
+
+        // missing output map, trailing bytes
+        let bytes = hex::decode(BIP174_VALID_6).unwrap();
+        let err = Psbt::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.path()),
+            (&PsbtError::Tx(TxError::Truncated), &["outputs[0]".to_string()][..])
+        );
+        let mut extra = bytes.clone();
+        extra.push(0x00);
+        let err = Psbt::from_bytes(&extra).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&PsbtError::Tx(TxError::TrailingBytes(1)), Some(bytes.len()))
+        );
+
+        // the input map starts at byte 72, after the magic and the global
+        // map; a one-byte key's data at 73 and its value's at 75
+        let unknown: (&[u8], &[u8]) = (b"\x0f\x01", b"\x02");
+        let err = Psbt::from_bytes(&single_input_psbt(&[unknown, unknown])).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate key 0f01 at byte 78 in inputs[0]");
+        let err = Psbt::from_bytes(&single_input_psbt(&[(&[PSBT_IN_SIGHASH_TYPE, 0x00], &[1, 0, 0, 0])])).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&PsbtError::InvalidKey(vec![PSBT_IN_SIGHASH_TYPE, 0x00]), Some(73))
+        );
+        let err = Psbt::from_bytes(&single_input_psbt(&[(&[PSBT_IN_SIGHASH_TYPE], &[1, 0, 0])])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value for key type 0x03 at byte 75 in inputs[0]"
+        );
+
+        // BIP-371: a compressed key where an x-only key belongs, and a
+        // 66-byte key path signature
+        assert_eq!(
+            kind(&single_input_psbt(&[(&[PSBT_IN_TAP_INTERNAL_KEY], &[0x02; 33])])),
+            PsbtError::InvalidValue(PSBT_IN_TAP_INTERNAL_KEY)
+        );
+        assert_eq!(
+            kind(&single_input_psbt(&[(&[PSBT_IN_TAP_KEY_SIG], &[0x01; 66])])),
+            PsbtError::InvalidValue(PSBT_IN_TAP_KEY_SIG)
+        );
+
+        // an error inside a non-witness UTXO keeps its place in that
+        // transaction: its script_sig length byte sits 41 bytes in
+        let mut utxo = hex::decode(&BIP174_VALID_6[16..16 + 126]).unwrap();
+        utxo[41] = 0xff;
+        let err = Psbt::from_bytes(&single_input_psbt(&[(&[PSBT_IN_NON_WITNESS_UTXO], &utxo)])).unwrap_err();
+        assert_eq!(err.kind(), &PsbtError::InvalidValue(PSBT_IN_NON_WITNESS_UTXO));
+        assert_eq!(err.path(), ["inputs[0]", "inputs[0]", "script_sig"]);
+    }
+
+    #[test]
+    fn test_sign_finalize_extract() {
+        // P2WPKH, P2PKH, P2SH-P2WPKH, 2-of-2 P2WSH and P2TR inputs, all
+        // derived from the BIP-32 test vector 1 seed; the ECDSA signatures
+        // are the ones rust-bitcoin's signer produces
+        const UNSIGNED: &str =
+            "cHNidP8BAP0VAQIAAAAFAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEAAAAAAP3///8uHS6CjRImzK3v77LuJ6gDRN2H\
+         JpKVMyZdbR6pAxawtAAAAAAA/f///wMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAQAAAAD9////BAQEBAQEBAQEBAQE\
+         BAQEBAQEBAQEBAQEBAQEBAQEBAQCAAAAAP3///8FBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQAAAAAA/f///wJADQMA\
+         AAAAABYAFCIiIiIiIiIiIiIiIiIiIiIiIiIimDQBAAAAAAAWABSu84kLheY87rL21ZUiqnPjWLP8PAAAAABPAQQ1h88DtwbqZIAA\
+         AAAAtDyPafw33jJpHdQI/sni3ziaSoypemuRWNNXrzbLagNyUPuLw2gHLYHxYZ1uHRdMCydvYVq3qLAGYBaPOGMmiBA0Qhk+VAAA\
+         gAEAAIAAAACAAAEBH6CGAQAAAAAAFgAU8l4ZOviMhCY91ecHuhy0fpH2KagiBgIMf03hzHYPwGh3WxUT1n0KeALwtLHGGqhXhOv3\
+         IpBbJxg0Qhk+VAAAgAEAAIAAAACAAAAAAAAAAAAAAQBVAQAAAAEREREREREREREREREREREREREREREREREREREREREREQAAAAAA\
+         /////wFg6gAAAAAAABl2qRRzSD01YQzoPkW65k6ohxTex9QelYisAAAAACIGA9Zzh8yaH3+FsuQaYOmuCPwvR5HoZ49UBQi08fzE\
+         ademGDRCGT4sAACAAQAAgAAAAIAAAAAAAAAAAAABASAwdQAAAAAAABepFP9fFzFjMCpdTn12pxDi3MzRMGnThwEEFgAUO7s0fXkE\
+         sVRoST2654Cus1/fh2wiBgI3Hc/7ITp2MhXrT1pVlBjkVnBuCHwaBkbl/R65nHKWOBg0Qhk+MQAAgAEAAIAAAACAAAAAAAAAAAAA\
+         AQErQJwAAAAAAAAiACCwuDTCs9BL/KXeW9T7ln69vaciFGSk9glHHAVKCI7+WAEFR1IhA6QWc0p3KdLgpPiF9Ok5YYpaRfXzJRFk\
+         EjZTaiQnLZnzIQMJokONqHfTKMENI+mXOQn+MOPE2Ws9nwPQ43AvHqCovVKuIgYDCaJDjah30yjBDSPplzkJ/jDjxNlrPZ8D0ONw\
+         Lx6gqL0cNEIZPjAAAIABAACAAAAAgAIAAIAAAAAAAQAAACIGA6QWc0p3KdLgpPiF9Ok5YYpaRfXzJRFkEjZTaiQnLZnzHDRCGT4w\
+         AACAAQAAgAAAAIACAACAAAAAAAAAAAAAAQErUMMAAAAAAAAiUSAV/UGs3q/PRijASOnPz9u5KvYQAt6Pv9STRgBCseeiwCEWg/AM\
+         l6J6YBXVOs1oaGrq7w8mdWzTsk/vdnQgVm494VQZADRCGT5WAACAAQAAgAAAAIAAAAAAAAAAAAEXIIPwDJeiemAV1TrNaGhq6u8P\
+         JnVs07JP73Z0IFZuPeFUAAAiAgLEcS925J6nk0fFxMtDS6W6etdneP5flCU+tAKNgqPiaBg0Qhk+VAAAgAEAAIAAAACAAQAAAAAA\
+         AAAA";
+        let mut psbt = Psbt::from_base64(UNSIGNED).unwrap();
+        assert_eq!(psbt.to_base64(), UNSIGNED);
+        let master = ExtendedPrivateKey::from_seed(
+            &hex::decode("000102030405060708090a0b0c0d0e0f").unwrap(),
+            Network::Testnet,
+        )
+        .unwrap();
+        assert_eq!(psbt.xpubs.values().next().unwrap().fingerprint, master.fingerprint());
+
+        let p2wpkh_key = hex::decode("020c7f4de1cc760fc068775b1513d67d0a7802f0b4b1c61aa85784ebf722905b27").unwrap();
+        assert_eq!(
+            psbt.inputs[0].key_source(&p2wpkh_key).unwrap().path,
+            vec![84 + HARDENED, 1 + HARDENED, HARDENED, 0, 0]
+        );
+        let internal_key = psbt.inputs[4].tap_internal_key.unwrap();
+        assert_eq!(
+            psbt.inputs[4].key_source(&internal_key).unwrap().path,
+            vec![86 + HARDENED, 1 + HARDENED, HARDENED, 0, 0]
+        );
+
+        assert_eq!(psbt.sign(&master), Ok(6));
+        let expected = [
+            (
+                0,
+                "020c7f4de1cc760fc068775b1513d67d0a7802f0b4b1c61aa85784ebf722905b27",
+                "3045022100beb713a50e418dd03293322f7ed4a93a292ed921e3b5838efb418917d3fe4b2f02203a1662449bd6801562\
+              94f1b29a8c103a13fceb663482979cdba2749fce2106f001",
+            ),
+            (
+                1,
+                "03d67387cc9a1f7f85b2e41a60e9ae08fc2f4791e8678f540508b4f1fcc469d7a6",
+                "3044022057c506364e8c588c120686f7799c153a054737c7249007d692fb176af0db3ad902207373b5e73c8be404359c\
+              f9ea43dcf05696c3d053c9b56e88d42131df03e0333401",
+            ),
+            (
+                2,
+                "02371dcffb213a763215eb4f5a559418e456706e087c1a0646e5fd1eb99c729638",
+                "3045022100fd4c4e563b7028e9094b226357d67a68ad9a3483e4336bd07b744dc5bcc832bb02206bc5077fbfdd468312\
+              2f64fbbe86eca336651082882182b8e721a36a9c84994b01",
+            ),
+            (
+                3,
+                "0309a2438da877d328c10d23e9973909fe30e3c4d96b3d9f03d0e3702f1ea0a8bd",
+                "3044022079864312398bd5ff5cb1692446fc636a2f467e360051e619ffd28095a2c14e82022062259c504161351319ea\
+              8a9a33445e280ef4d592d867ed24b6ffa5c8c20eab9701",
+            ),
+            (
+                3,
+                "03a416734a7729d2e0a4f885f4e939618a5a45f5f32511641236536a24272d99f3",
+                "30440220323dc7b4558f8ab9035d7bc9224231fc8d0d0075b4a66d326dc5e91eea185ee1022068b078a0a3d220f28505\
+              25ba420073e00a9987a212907c112fa6c4fef5412a2701",
+            ),
+        ];
+        for (index, pubkey, sig) in expected {
+            assert_eq!(
+                hex::encode(&psbt.inputs[index].partial_sigs[&hex::decode(pubkey).unwrap()]),
+                sig
+            );
+        }
+
+        // the key path signature verifies against the output key
+        let prevouts: Vec<_> = (0..5).map(|i| psbt.spent_output(i).unwrap().clone()).collect();
+        let sighash = SighashCache::new(&psbt.unsigned_tx)
+            .taproot_signature_hash(4, &Prevouts::All(&prevouts), None, None, TapSighashType::Default)
+            .unwrap();
+        let tap_sig: [u8; 64] = psbt.inputs[4].tap_key_sig.clone().unwrap().try_into().unwrap();
+        let output_key: [u8; 32] = prevouts[4].script_pubkey.as_bytes()[2..].try_into().unwrap();
+        assert!(schnorr::verify(&output_key, &sighash, &tap_sig));
+
+        let signed = Psbt::from_base64(&psbt.to_base64()).unwrap();
+        assert_eq!(signed, psbt);
+        assert_eq!(psbt.extract_tx(), Err(PsbtError::NotFinalized(0)));
+
+        psbt.finalize().unwrap();
+        assert!(psbt
+            .inputs
+            .iter()
+            .all(|input| input.is_finalized() && input.partial_sigs.is_empty()));
+        assert!(psbt.inputs[0].bip32_derivation.is_empty() && psbt.inputs[0].witness_utxo.is_some());
+        // signing again is a no-op once finalized
+        assert_eq!(psbt.clone().sign(&master), Ok(0));
+
+        let tx = psbt.extract_tx().unwrap();
+        let sig = |i: usize, key: &str| signed.inputs[i].partial_sigs[&hex::decode(key).unwrap()].clone();
+        assert_eq!(
+            tx.inputs[0].witness,
+            vec![sig(0, expected[0].1), hex::decode(expected[0].1).unwrap()]
+        );
+        assert_eq!(
+            tx.inputs[1].script_sig,
+            Script::new()
+                .push_slice(&sig(1, expected[1].1))
+                .push_slice(&hex::decode(expected[1].1).unwrap())
+        );
+        assert!(tx.inputs[1].witness.is_empty());
+        let redeem = signed.inputs[2].redeem_script.as_ref().unwrap();
+        assert_eq!(tx.inputs[2].script_sig, Script::new().push_slice(redeem.as_bytes()));
+        assert_eq!(tx.inputs[2].witness.len(), 2);
+        // multisig signatures go in the order of the keys in the script
+        let witness_script = signed.inputs[3].witness_script.as_ref().unwrap();
+        assert_eq!(
+            tx.inputs[3].witness,
+            vec![
+                Vec::new(),
+                sig(3, expected[4].1),
+                sig(3, expected[3].1),
+                witness_script.as_bytes().to_vec()
+            ]
+        );
+        assert!(tx.inputs[3].script_sig.is_empty());
+        assert_eq!(tx.inputs[4].witness, vec![tap_sig.to_vec()]);
+    }
+
+    #[test]
+    fn test_sign_with_key() {
+        let key = PrivateKey::from(BigUint::from(0xc0ffee_u32)).unwrap();
+        let other = PrivateKey::from(BigUint::from(0xbeef_u32)).unwrap();
+        let tx = Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: [0x42; 32],
+                    vout: 1,
+                },
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: Vec::new(),
+            }],
+            outputs: vec![TxOut {
+                amount: 9_000,
+                script_pubkey: Script::p2wpkh(&[0x11; 20]),
+            }],
+            locktime: 0,
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        assert_eq!(psbt.sign_with_key(&key), Err(PsbtError::MissingUtxo(0)));
+        assert_eq!(psbt.finalize(), Err(PsbtError::MissingUtxo(0)));
+
+        let sec = key.public_key().sec(true);
+        let utxo = TxOut {
+            amount: 10_000,
+            script_pubkey: Script::p2wpkh(&hash160(&sec)),
+        };
+        psbt.inputs[0].witness_utxo = Some(utxo.clone());
+        assert_eq!(psbt.sign_with_key(&other), Ok(0));
+        assert_eq!(psbt.finalize(), Err(PsbtError::CannotFinalize(0)));
+
+        psbt.inputs[0].sighash_type = Some(0x04);
+        assert_eq!(psbt.sign_with_key(&key), Err(PsbtError::InvalidSighashType(0x04)));
+        psbt.inputs[0].sighash_type = Some(0x83);
+        assert_eq!(psbt.sign_with_key(&key), Ok(1));
+
+        let sig = &psbt.inputs[0].partial_sigs[&sec];
+        assert_eq!(*sig.last().unwrap(), 0x83);
+        let sighash = SighashCache::new(&psbt.unsigned_tx)
+            .p2wpkh_signature_hash(
+                0,
+                &utxo.script_pubkey,
+                utxo.amount,
+                EcdsaSighashType::SinglePlusAnyoneCanPay,
+            )
+            .unwrap();
+        let signature = crate::curves::secp256k1::ecc::Signature::parse_der(&sig[..sig.len() - 1]).unwrap();
+        assert!(key.public_key().verify(&BigUint::from_bytes_be(&sighash), &signature));
+
+        psbt.finalize().unwrap();
+        assert_eq!(psbt.inputs[0].sighash_type, None);
+        assert_eq!(psbt.extract_tx().unwrap().inputs[0].witness[1], sec);
+    }
+}
@@ -0,0 +1,344 @@
+use std::fmt::Display;
+
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::bn254::curve_order;
+use crate::groth16::VerifyingKey;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum R1csError {
+    /// A variable a constraint or the witness needs has no value yet
+    Unassigned(Variable),
+    /// The index of the first constraint a * b = c that doesn't hold
+    Unsatisfied(usize),
+    /// Values and coefficients must be in the system's field
+    WrongField,
+    /// The verifying key expects a different number of public inputs
+    PublicInputCount { expected: usize, got: usize },
+}
+
+impl Display for R1csError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            R1csError::Unassigned(var) => write!(f, "{:?} has no value", var),
+            R1csError::Unsatisfied(i) => write!(f, "constraint {} is not satisfied", i),
+            R1csError::WrongField => write!(f, "element is not in the constraint system's field"),
+            R1csError::PublicInputCount { expected, got } => {
+                write!(
+                    f,
+                    "verifying key expects {} public inputs, circuit has {}",
+                    expected, got
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for R1csError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    /// The constant 1, always first in the witness
+    One,
+    Public(usize),
+    Private(usize),
+}
+
+/// Σ coeff * var
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LinearCombination {
+    terms: Vec<(Variable, FieldElement)>,
+}
+
+impl LinearCombination {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn term(mut self, var: Variable, coeff: FieldElement) -> Self {
+        self.terms.push((var, coeff));
+        self
+    }
+
+    pub fn terms(&self) -> &[(Variable, FieldElement)] {
+        &self.terms
+    }
+}
+
+/// a * b = c, over linear combinations of the variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint {
+    pub a: LinearCombination,
+    pub b: LinearCombination,
+    pub c: LinearCombination,
+}
+
+/// A rank-1 constraint system: variables, optionally with values, and the
+/// constraints between them. The witness is laid out as Groth16 expects,
+/// the constant 1, then the public inputs, then the private variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintSystem {
+    prime: BigInt,
+    public: Vec<Option<FieldElement>>,
+    private: Vec<Option<FieldElement>>,
+    constraints: Vec<Constraint>,
+}
+
+impl ConstraintSystem {
+    pub fn new(prime: BigInt) -> Self {
+        Self {
+            prime,
+            public: Vec::new(),
+            private: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Over the BN254 scalar field, for circuits checked with Groth16
+    pub fn bn254() -> Self {
+        Self::new(curve_order())
+    }
+
+    /// An element of the system's field, reduced from any integer
+    pub fn element(&self, num: impl Into<BigInt>) -> FieldElement {
+        FieldElement::reduce(num.into(), self.prime.clone()).expect("prime is at least 2")
+    }
+
+    pub fn alloc_input(&mut self, value: Option<FieldElement>) -> Result<Variable, R1csError> {
+        self.check_field(value.as_ref())?;
+        self.public.push(value);
+        Ok(Variable::Public(self.public.len() - 1))
+    }
+
+    pub fn alloc(&mut self, value: Option<FieldElement>) -> Result<Variable, R1csError> {
+        self.check_field(value.as_ref())?;
+        self.private.push(value);
+        Ok(Variable::Private(self.private.len() - 1))
+    }
+
+    /// Sets or replaces a variable's value. Panics for the constant
+    pub fn assign(&mut self, var: Variable, value: FieldElement) -> Result<(), R1csError> {
+        self.check_field(Some(&value))?;
+        match var {
+            Variable::One => panic!("the constant one can't be assigned"),
+            Variable::Public(i) => self.public[i] = Some(value),
+            Variable::Private(i) => self.private[i] = Some(value),
+        }
+        Ok(())
+    }
+
+    pub fn enforce(
+        &mut self,
+        a: LinearCombination,
+        b: LinearCombination,
+        c: LinearCombination,
+    ) -> Result<(), R1csError> {
+        for lc in [&a, &b, &c] {
+            for (_, coeff) in lc.terms() {
+                self.check_field(Some(coeff))?;
+            }
+        }
+        self.constraints.push(Constraint { a, b, c });
+        Ok(())
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    pub fn num_public_inputs(&self) -> usize {
+        self.public.len()
+    }
+
+    pub fn num_variables(&self) -> usize {
+        1 + self.public.len() + self.private.len()
+    }
+
+    /// A variable's position in the witness
+    pub fn index(&self, var: Variable) -> usize {
+        match var {
+            Variable::One => 0,
+            Variable::Public(i) => 1 + i,
+            Variable::Private(i) => 1 + self.public.len() + i,
+        }
+    }
+
+    pub fn value(&self, var: Variable) -> Result<FieldElement, R1csError> {
+        let value = match var {
+            Variable::One => Some(self.element(1)),
+            Variable::Public(i) => self.public[i].clone(),
+            Variable::Private(i) => self.private[i].clone(),
+        };
+        value.ok_or(R1csError::Unassigned(var))
+    }
+
+    pub fn evaluate(&self, lc: &LinearCombination) -> Result<FieldElement, R1csError> {
+        lc.terms().iter().try_fold(self.element(0), |acc, (var, coeff)| {
+            Ok(acc + coeff.clone() * self.value(*var)?)
+        })
+    }
+
+    /// Ok if every constraint holds under the assigned values
+    pub fn is_satisfied(&self) -> Result<(), R1csError> {
+        for (i, constraint) in self.constraints.iter().enumerate() {
+            let (a, b, c) = (
+                self.evaluate(&constraint.a)?,
+                self.evaluate(&constraint.b)?,
+                self.evaluate(&constraint.c)?,
+            );
+            if a * b != c {
+                return Err(R1csError::Unsatisfied(i));
+            }
+        }
+        Ok(())
+    }
+
+    /// The full witness z = (1, public..., private...)
+    pub fn witness(&self) -> Result<Vec<FieldElement>, R1csError> {
+        let public = (0..self.public.len()).map(Variable::Public);
+        let private = (0..self.private.len()).map(Variable::Private);
+        [Variable::One]
+            .into_iter()
+            .chain(public)
+            .chain(private)
+            .map(|var| self.value(var))
+            .collect()
+    }
+
+    /// The public inputs as a Groth16 verifier takes them
+    pub fn public_inputs(&self) -> Result<Vec<BigInt>, R1csError> {
+        (0..self.public.len())
+            .map(|i| Ok(self.value(Variable::Public(i))?.num()))
+            .collect()
+    }
+
+    /// The sparse rows of the A, B and C matrices, as (witness index,
+    /// coefficient) pairs with repeated variables merged
+    pub fn matrices(&self) -> [Vec<Vec<(usize, FieldElement)>>; 3] {
+        let row = |lc: &LinearCombination| {
+            let mut row: Vec<(usize, FieldElement)> = Vec::new();
+            for (var, coeff) in lc.terms() {
+                let index = self.index(*var);
+                match row.iter_mut().find(|(i, _)| *i == index) {
+                    Some((_, existing)) => *existing = existing.clone() + coeff.clone(),
+                    None => row.push((index, coeff.clone())),
+                }
+            }
+            row.retain(|(_, coeff)| !coeff.num().is_zero());
+            row.sort_by_key(|(i, _)| *i);
+            row
+        };
+        [
+            self.constraints.iter().map(|c| row(&c.a)).collect(),
+            self.constraints.iter().map(|c| row(&c.b)).collect(),
+            self.constraints.iter().map(|c| row(&c.c)).collect(),
+        ]
+    }
+
+    /// Whether a Groth16 key could be for this circuit: over BN254 with
+    /// one IC point per public input plus one for the constant
+    pub fn check_verifying_key(&self, vk: &VerifyingKey) -> Result<(), R1csError> {
+        if self.prime != curve_order() {
+            return Err(R1csError::WrongField);
+        }
+        let expected = vk.ic.len().saturating_sub(1);
+        if expected != self.public.len() {
+            return Err(R1csError::PublicInputCount {
+                expected,
+                got: self.public.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_field(&self, value: Option<&FieldElement>) -> Result<(), R1csError> {
+        match value {
+            Some(v) if v.prime() != self.prime => Err(R1csError::WrongField),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod r1cs_tests {
+    use super::*;
+    use crate::bn254::curve::{g1_generator, G2Point};
+
+    /// x³ + x + 5 = out, flattened to sym = x * x, y = sym * x and
+    /// (y + x + 5) * 1 = out
+    fn cubic(x: i64, out: i64) -> (ConstraintSystem, Variable) {
+        let mut cs = ConstraintSystem::bn254();
+        let one = cs.element(1);
+        let out = cs.alloc_input(Some(cs.element(out))).unwrap();
+        let x_var = cs.alloc(Some(cs.element(x))).unwrap();
+        let sym = cs.alloc(Some(cs.element(x * x))).unwrap();
+        let y = cs.alloc(Some(cs.element(x * x * x))).unwrap();
+        let lc = |var| LinearCombination::new().term(var, one.clone());
+        cs.enforce(lc(x_var), lc(x_var), lc(sym)).unwrap();
+        cs.enforce(lc(sym), lc(x_var), lc(y)).unwrap();
+        let sum = lc(y).term(x_var, one.clone()).term(Variable::One, cs.element(5));
+        cs.enforce(sum, lc(Variable::One), lc(out)).unwrap();
+        (cs, x_var)
+    }
+
+    #[test]
+    fn test_cubic() {
+        let (mut cs, x) = cubic(3, 35);
+        assert_eq!(cs.is_satisfied(), Ok(()));
+        assert_eq!(cs.num_variables(), 5);
+        assert_eq!(cs.index(x), 2);
+        assert_eq!(cs.public_inputs(), Ok(vec![BigInt::from(35)]));
+        let witness: Vec<BigInt> = cs.witness().unwrap().iter().map(|v| v.num()).collect();
+        assert_eq!(witness, [1, 35, 3, 9, 27].map(BigInt::from));
+
+        let [a, _, c] = cs.matrices();
+        assert_eq!(a[2], vec![(0, cs.element(5)), (2, cs.element(1)), (4, cs.element(1))]);
+        assert_eq!(c[0], vec![(3, cs.element(1))]);
+
+        // the intermediate values no longer match x = 4
+        cs.assign(x, cs.element(4)).unwrap();
+        assert_eq!(cs.is_satisfied(), Err(R1csError::Unsatisfied(0)));
+        let (cs, _) = cubic(3, 36);
+        assert_eq!(cs.is_satisfied(), Err(R1csError::Unsatisfied(2)));
+
+        let mut cs = ConstraintSystem::new(BigInt::from(97));
+        let unassigned = cs.alloc(None).unwrap();
+        let one = cs.element(1);
+        cs.enforce(
+            LinearCombination::new().term(unassigned, one.clone()),
+            LinearCombination::new().term(Variable::One, one.clone()),
+            LinearCombination::new().term(Variable::One, one),
+        )
+        .unwrap();
+        assert_eq!(cs.is_satisfied(), Err(R1csError::Unassigned(unassigned)));
+        assert_eq!(
+            cs.alloc(Some(FieldElement::from(BigInt::from(1), BigInt::from(7)).unwrap())),
+            Err(R1csError::WrongField)
+        );
+    }
+
+    #[test]
+    fn test_verifying_key() {
+        let (cs, _) = cubic(3, 35);
+        let g2 = G2Point::generator();
+        let mut vk = VerifyingKey {
+            alpha_g1: g1_generator(),
+            beta_g2: g2.clone(),
+            gamma_g2: g2.clone(),
+            delta_g2: g2,
+            ic: vec![g1_generator(), g1_generator()],
+        };
+        assert_eq!(cs.check_verifying_key(&vk), Ok(()));
+        vk.ic.push(g1_generator());
+        assert_eq!(
+            cs.check_verifying_key(&vk),
+            Err(R1csError::PublicInputCount { expected: 2, got: 1 })
+        );
+        assert_eq!(
+            ConstraintSystem::new(BigInt::from(97)).check_verifying_key(&vk),
+            Err(R1csError::WrongField)
+        );
+    }
+}
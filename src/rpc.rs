@@ -0,0 +1,373 @@
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::block::BlockHeader;
+use crate::encoding::base64;
+use crate::encoding::decode::DecodeError;
+use crate::script::Script;
+use crate::tx::{OutPoint, Transaction, TxError};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcError {
+    Io(io::ErrorKind),
+    /// A non-200 reply without a JSON-RPC error in it, e.g. 401 for bad credentials
+    Http(u16),
+    /// The reply isn't the JSON we expected
+    InvalidResponse(String),
+    /// An error reported by the node
+    Node {
+        code: i64,
+        message: String,
+    },
+    Tx(DecodeError<TxError>),
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpcError::Io(kind) => write!(f, "i/o error talking to node: {}", kind),
+            RpcError::Http(status) => write!(f, "node replied with HTTP status {}", status),
+            RpcError::InvalidResponse(reason) => write!(f, "invalid RPC response: {}", reason),
+            RpcError::Node { code, message } => write!(f, "RPC error {}: {}", code, message),
+            RpcError::Tx(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+impl From<io::Error> for RpcError {
+    fn from(e: io::Error) -> Self {
+        RpcError::Io(e.kind())
+    }
+}
+
+impl From<DecodeError<TxError>> for RpcError {
+    fn from(e: DecodeError<TxError>) -> Self {
+        RpcError::Tx(e)
+    }
+}
+
+fn invalid(reason: &str) -> RpcError {
+    RpcError::InvalidResponse(reason.to_string())
+}
+
+/// How to log in to the node: rpcuser/rpcpassword, or the .cookie file
+/// bitcoind writes to its data directory on every start
+#[derive(Debug, Clone, PartialEq)]
+pub enum Auth {
+    UserPass(String, String),
+    CookieFile(PathBuf),
+}
+
+impl Auth {
+    fn header(&self) -> Result<String, RpcError> {
+        let credentials = match self {
+            Auth::UserPass(user, password) => format!("{}:{}", user, password),
+            Auth::CookieFile(path) => std::fs::read_to_string(path)?.trim().to_string(),
+        };
+        Ok(format!("Basic {}", base64::encode(credentials.as_bytes())))
+    }
+}
+
+/// An unspent output found by `scantxoutset`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedUtxo {
+    pub outpoint: OutPoint,
+    pub script_pubkey: Script,
+    pub amount: u64,
+    pub height: u32,
+}
+
+/// A blocking JSON-RPC client for bitcoind, one connection per call
+#[derive(Debug, Clone)]
+pub struct Client {
+    address: SocketAddr,
+    auth: Auth,
+}
+
+/// Hashes are shown reversed, as block explorers and the node do
+fn display_hex(hash: &[u8; 32]) -> String {
+    let mut bytes = *hash;
+    bytes.reverse();
+    hex::encode(bytes)
+}
+
+fn parse_display_hex(s: &str) -> Result<[u8; 32], RpcError> {
+    let mut hash: [u8; 32] = hex::decode(s)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid("expected a 32-byte hex hash"))?;
+    hash.reverse();
+    Ok(hash)
+}
+
+/// Converts a BTC amount to satoshis. JSON numbers are doubles, exact to
+/// well below a satoshi for any possible amount
+fn to_sat(btc: &Value) -> Result<u64, RpcError> {
+    let btc = btc
+        .as_f64()
+        .filter(|btc| *btc >= 0.0)
+        .ok_or_else(|| invalid("expected an amount"))?;
+    Ok((btc * 100_000_000.0).round() as u64)
+}
+
+impl Client {
+    pub fn new(address: SocketAddr, auth: Auth) -> Self {
+        Self { address, auth }
+    }
+
+    /// Sends one request and returns its `result`
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let body = json!({ "jsonrpc": "1.0", "id": "moneda", "method": method, "params": params }).to_string();
+        let mut stream = TcpStream::connect_timeout(&self.address, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {}\r\nAuthorization: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.address,
+            self.auth.header()?,
+            body.len(),
+            body
+        )?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line)?;
+        let status: u16 = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| invalid("malformed HTTP status line"))?;
+        let mut content_length = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+        let mut body = Vec::new();
+        match content_length {
+            Some(len) => {
+                body.resize(len, 0);
+                reader.read_exact(&mut body)?;
+            }
+            None => {
+                reader.read_to_end(&mut body)?;
+            }
+        }
+
+        // bitcoind answers RPC errors with 404 or 500 and a JSON body
+        let Ok(mut reply) = serde_json::from_slice::<Value>(&body) else {
+            return Err(if status == 200 {
+                invalid("body is not JSON")
+            } else {
+                RpcError::Http(status)
+            });
+        };
+        if let Some(error) = reply.get("error").filter(|error| !error.is_null()) {
+            return Err(RpcError::Node {
+                code: error["code"].as_i64().unwrap_or_default(),
+                message: error["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        if status != 200 {
+            return Err(RpcError::Http(status));
+        }
+        Ok(reply["result"].take())
+    }
+
+    fn call_hex(&self, method: &str, params: Value) -> Result<Vec<u8>, RpcError> {
+        let result = self.call(method, params)?;
+        let hex = result.as_str().ok_or_else(|| invalid("expected a hex string"))?;
+        hex::decode(hex).map_err(|_| invalid("expected a hex string"))
+    }
+
+    pub fn get_block_header(&self, hash: &[u8; 32]) -> Result<BlockHeader, RpcError> {
+        let bytes = self.call_hex("getblockheader", json!([display_hex(hash), false]))?;
+        let header = BlockHeader::parse(&mut bytes.as_slice())?;
+        if header.hash() != *hash {
+            return Err(invalid("header does not hash to the requested block"));
+        }
+        Ok(header)
+    }
+
+    /// Needs -txindex unless the transaction is in the mempool
+    pub fn get_raw_transaction(&self, txid: &[u8; 32]) -> Result<Transaction, RpcError> {
+        let tx = Transaction::from_bytes(&self.call_hex("getrawtransaction", json!([display_hex(txid), false]))?)?;
+        if tx.txid() != *txid {
+            return Err(invalid("transaction does not hash to the requested txid"));
+        }
+        Ok(tx)
+    }
+
+    /// Broadcasts `tx` and returns its txid
+    pub fn send_raw_transaction(&self, tx: &Transaction) -> Result<[u8; 32], RpcError> {
+        let result = self.call("sendrawtransaction", json!([tx.to_hex()]))?;
+        parse_display_hex(result.as_str().ok_or_else(|| invalid("expected a txid"))?)
+    }
+
+    /// Finds the unspent outputs matching output descriptors, without a
+    /// wallet. Scans the whole UTXO set, so takes a while
+    pub fn scan_tx_out_set(&self, descriptors: &[&str]) -> Result<Vec<ScannedUtxo>, RpcError> {
+        let result = self.call("scantxoutset", json!(["start", descriptors]))?;
+        let unspents = result["unspents"]
+            .as_array()
+            .ok_or_else(|| invalid("expected unspents"))?;
+        unspents
+            .iter()
+            .map(|utxo| {
+                let txid = parse_display_hex(utxo["txid"].as_str().ok_or_else(|| invalid("expected a txid"))?)?;
+                let vout = utxo["vout"].as_u64().ok_or_else(|| invalid("expected a vout"))? as u32;
+                let script_pubkey = utxo["scriptPubKey"]
+                    .as_str()
+                    .and_then(|script| Script::from_hex(script).ok())
+                    .ok_or_else(|| invalid("expected a script"))?;
+                Ok(ScannedUtxo {
+                    outpoint: OutPoint { txid, vout },
+                    script_pubkey,
+                    amount: to_sat(&utxo["amount"])?,
+                    height: utxo["height"].as_u64().unwrap_or_default() as u32,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod rpc_tests {
+    use super::*;
+    use crate::address::Network;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// A node that answers one request with `status` and `body`, and hands
+    /// back the request it got
+    fn mock_node(status: &'static str, body: String) -> (Client, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(len) = line.strip_prefix("Content-Length: ") {
+                    content_length = len.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body_in = vec![0; content_length];
+            reader.read_exact(&mut body_in).unwrap();
+            request.push_str(&String::from_utf8(body_in).unwrap());
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+            request
+        });
+        (
+            Client::new(address, Auth::UserPass("user".into(), "pass".into())),
+            handle,
+        )
+    }
+
+    #[test]
+    fn test_get_block_header() {
+        let genesis = BlockHeader::genesis(Network::Mainnet);
+        let body = json!({ "result": hex::encode(genesis.serialize()), "error": null, "id": "moneda" });
+        let (client, node) = mock_node("200 OK", body.to_string());
+        assert_eq!(client.get_block_header(&genesis.hash()), Ok(genesis));
+
+        let request = node.join().unwrap();
+        assert!(request.contains("Authorization: Basic dXNlcjpwYXNz\r\n"));
+        assert!(request.contains(r#""method":"getblockheader""#));
+        assert!(
+            request.contains(r#""params":["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",false]"#)
+        );
+    }
+
+    #[test]
+    fn test_node_error() {
+        let body = json!({ "result": null, "error": { "code": -25, "message": "bad-txns-inputs-missingorspent" } });
+        let (client, node) = mock_node("500 Internal Server Error", body.to_string());
+        let tx = Transaction {
+            version: 2,
+            inputs: vec![],
+            outputs: vec![],
+            locktime: 0,
+        };
+        assert_eq!(
+            client.send_raw_transaction(&tx),
+            Err(RpcError::Node {
+                code: -25,
+                message: "bad-txns-inputs-missingorspent".to_string()
+            })
+        );
+        node.join().unwrap();
+
+        let (client, node) = mock_node("401 Unauthorized", String::new());
+        assert_eq!(client.call("getblockcount", json!([])), Err(RpcError::Http(401)));
+        node.join().unwrap();
+    }
+
+    #[test]
+    fn test_scan_tx_out_set() {
+        let txid = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        let body = json!({
+            "result": {
+                "success": true,
+                "unspents": [{
+                    "txid": txid,
+                    "vout": 0,
+                    "scriptPubKey": "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                    "desc": "addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)#8pcqpn6t",
+                    "amount": 0.00012345,
+                    "height": 840000
+                }],
+                "total_amount": 0.00012345
+            },
+            "error": null
+        });
+        let (client, node) = mock_node("200 OK", body.to_string());
+        let utxos = client
+            .scan_tx_out_set(&["addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)"])
+            .unwrap();
+        node.join().unwrap();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(display_hex(&utxos[0].outpoint.txid), txid);
+        assert_eq!(utxos[0].amount, 12_345);
+        assert_eq!(utxos[0].height, 840_000);
+        assert_eq!(
+            utxos[0].script_pubkey,
+            Script::p2wpkh(
+                &hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6")
+                    .unwrap()
+                    .try_into()
+                    .unwrap()
+            )
+        );
+    }
+}
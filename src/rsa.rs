@@ -0,0 +1,453 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::hash::sha256;
+use crate::crypto::prime::{pollard_p_minus_1, pollard_rho, random_prime};
+
+/// The DER DigestInfo prefix PKCS#1 v1.5 puts before a SHA-256 hash
+const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+];
+
+const HASH_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RsaError {
+    /// e has no inverse modulo λ(n), or the primes are equal
+    InvalidKey,
+    /// The representative must be less than n
+    MessageOutOfRange,
+    /// Too long for OAEP or a DigestInfo under this modulus
+    MessageTooLong,
+    /// OAEP decryption failed. Deliberately says nothing about why, since
+    /// telling padding errors apart is what Manger's attack exploits
+    DecryptionError,
+}
+
+impl Display for RsaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RsaError::InvalidKey => write!(f, "invalid rsa key"),
+            RsaError::MessageOutOfRange => write!(f, "message representative out of range"),
+            RsaError::MessageTooLong => write!(f, "message too long"),
+            RsaError::DecryptionError => write!(f, "decryption error"),
+        }
+    }
+}
+
+impl std::error::Error for RsaError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicKey {
+    n: BigUint,
+    e: BigUint,
+}
+
+/// The private exponent along with the CRT parameters, so private
+/// operations work modulo p and q, each half the size of n
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateKey {
+    public: PublicKey,
+    d: BigUint,
+    p: BigUint,
+    q: BigUint,
+    /// d mod (p - 1)
+    dp: BigUint,
+    /// d mod (q - 1)
+    dq: BigUint,
+    /// q^-1 mod p
+    qinv: BigUint,
+}
+
+impl PrivateKey {
+    /// A key with an n of `bits` bits and e = 65537
+    pub fn generate(bits: u64) -> Self {
+        Self::generate_with_rng(bits, &mut OsRng)
+    }
+
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(bits: u64, rng: &mut R) -> Self {
+        let e = BigUint::from(65537_u32);
+        loop {
+            let (p, q) = (random_prime(bits / 2, rng), random_prime(bits - bits / 2, rng));
+            if let Ok(key) = Self::from_primes(&p, &q, &e) {
+                return key;
+            }
+        }
+    }
+
+    /// d = e^-1 mod λ(n), with λ(n) = lcm(p - 1, q - 1)
+    pub fn from_primes(p: &BigUint, q: &BigUint, e: &BigUint) -> Result<Self, RsaError> {
+        if p == q {
+            return Err(RsaError::InvalidKey);
+        }
+        let (p_1, q_1) = (p - 1_u8, q - 1_u8);
+        let d = e.modinv(&p_1.lcm(&q_1)).ok_or(RsaError::InvalidKey)?;
+        let qinv = q.modinv(p).ok_or(RsaError::InvalidKey)?;
+        Ok(Self {
+            public: PublicKey { n: p * q, e: e.clone() },
+            dp: &d % p_1,
+            dq: &d % q_1,
+            d,
+            p: p.clone(),
+            q: q.clone(),
+            qinv,
+        })
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+
+    /// c^d mod n via Garner's recombination: m1 = c^dp mod p,
+    /// m2 = c^dq mod q and m = m2 + q (qinv (m1 - m2) mod p), about four
+    /// times faster than one exponentiation modulo n
+    pub fn raw_decrypt(&self, c: &BigUint) -> Result<BigUint, RsaError> {
+        if *c >= self.public.n {
+            return Err(RsaError::MessageOutOfRange);
+        }
+        let m1 = c.modpow(&self.dp, &self.p);
+        let m2 = c.modpow(&self.dq, &self.q);
+        let h = (&self.qinv * (&m1 + &self.p - &m2 % &self.p)) % &self.p;
+        Ok(m2 + h * &self.q)
+    }
+
+    /// RSAES-OAEP with SHA-256 and MGF1-SHA-256
+    pub fn decrypt_oaep(&self, ciphertext: &[u8], label: &[u8]) -> Result<Vec<u8>, RsaError> {
+        let k = self.public.size();
+        if ciphertext.len() != k || k < 2 * HASH_LEN + 2 {
+            return Err(RsaError::DecryptionError);
+        }
+        let m = self
+            .raw_decrypt(&BigUint::from_bytes_be(ciphertext))
+            .map_err(|_| RsaError::DecryptionError)?;
+        let em = i2osp(&m, k);
+        let (masked_seed, masked_db) = em[1..].split_at(HASH_LEN);
+        let seed = xor(masked_seed, &mgf1(masked_db, HASH_LEN));
+        let db = xor(masked_db, &mgf1(&seed, k - HASH_LEN - 1));
+
+        // checked without early exits, so every malformed message takes
+        // the same path
+        let mut valid = em[0] == 0 && db[..HASH_LEN] == sha256(label);
+        let separator = db[HASH_LEN..].iter().position(|&b| b != 0);
+        valid &= separator.is_some_and(|i| db[HASH_LEN + i] == 0x01);
+        match (valid, separator) {
+            (true, Some(i)) => Ok(db[HASH_LEN + i + 1..].to_vec()),
+            _ => Err(RsaError::DecryptionError),
+        }
+    }
+
+    /// RSASSA-PKCS1-v1_5 with SHA-256: deterministic, the same message
+    /// always gives the same signature
+    pub fn sign_pkcs1v15(&self, message: &[u8]) -> Result<Vec<u8>, RsaError> {
+        let k = self.public.size();
+        let em = pkcs1v15_encode(message, k)?;
+        Ok(i2osp(&self.raw_decrypt(&BigUint::from_bytes_be(&em))?, k))
+    }
+
+    /// RSASSA-PSS with SHA-256, MGF1-SHA-256 and a random 32-byte salt
+    pub fn sign_pss(&self, message: &[u8]) -> Result<Vec<u8>, RsaError> {
+        let mut salt = [0_u8; HASH_LEN];
+        OsRng.fill_bytes(&mut salt);
+        self.sign_pss_with_salt(message, &salt)
+    }
+
+    pub fn sign_pss_with_salt(&self, message: &[u8], salt: &[u8]) -> Result<Vec<u8>, RsaError> {
+        let em_bits = self.public.n.bits() as usize - 1;
+        let em_len = em_bits.div_ceil(8);
+        if em_len < HASH_LEN + salt.len() + 2 {
+            return Err(RsaError::MessageTooLong);
+        }
+        let h = pss_hash(&sha256(message), salt);
+        let mut db = vec![0_u8; em_len - salt.len() - HASH_LEN - 2];
+        db.push(0x01);
+        db.extend_from_slice(salt);
+        let mut masked_db = xor(&db, &mgf1(&h, em_len - HASH_LEN - 1));
+        masked_db[0] &= 0xff >> (8 * em_len - em_bits);
+        let mut em = masked_db;
+        em.extend_from_slice(&h);
+        em.push(0xbc);
+        Ok(i2osp(
+            &self.raw_decrypt(&BigUint::from_bytes_be(&em))?,
+            self.public.size(),
+        ))
+    }
+}
+
+impl PublicKey {
+    pub fn n(&self) -> &BigUint {
+        &self.n
+    }
+
+    pub fn e(&self) -> &BigUint {
+        &self.e
+    }
+
+    /// The modulus length in bytes
+    pub fn size(&self) -> usize {
+        self.n.bits().div_ceil(8) as usize
+    }
+
+    /// Textbook RSA, m^e mod n. Deterministic and malleable, so only for
+    /// building padded schemes on
+    pub fn raw_encrypt(&self, m: &BigUint) -> Result<BigUint, RsaError> {
+        if *m >= self.n {
+            return Err(RsaError::MessageOutOfRange);
+        }
+        Ok(m.modpow(&self.e, &self.n))
+    }
+
+    /// RSAES-OAEP with SHA-256 and MGF1-SHA-256
+    pub fn encrypt_oaep(&self, message: &[u8], label: &[u8]) -> Result<Vec<u8>, RsaError> {
+        let mut seed = [0_u8; HASH_LEN];
+        OsRng.fill_bytes(&mut seed);
+        self.encrypt_oaep_with_seed(message, label, &seed)
+    }
+
+    pub fn encrypt_oaep_with_seed(
+        &self,
+        message: &[u8],
+        label: &[u8],
+        seed: &[u8; HASH_LEN],
+    ) -> Result<Vec<u8>, RsaError> {
+        let k = self.size();
+        if k < 2 * HASH_LEN + 2 || message.len() > k - 2 * HASH_LEN - 2 {
+            return Err(RsaError::MessageTooLong);
+        }
+        // DB = lHash || PS || 0x01 || M
+        let mut db = sha256(label).to_vec();
+        db.resize(k - message.len() - HASH_LEN - 2, 0);
+        db.push(0x01);
+        db.extend_from_slice(message);
+        let masked_db = xor(&db, &mgf1(seed, k - HASH_LEN - 1));
+        let masked_seed = xor(seed, &mgf1(&masked_db, HASH_LEN));
+        let mut em = vec![0_u8];
+        em.extend_from_slice(&masked_seed);
+        em.extend_from_slice(&masked_db);
+        Ok(i2osp(&self.raw_encrypt(&BigUint::from_bytes_be(&em))?, k))
+    }
+
+    pub fn verify_pkcs1v15(&self, message: &[u8], signature: &[u8]) -> bool {
+        let k = self.size();
+        if signature.len() != k {
+            return false;
+        }
+        let (Ok(m), Ok(expected)) = (
+            self.raw_encrypt(&BigUint::from_bytes_be(signature)),
+            pkcs1v15_encode(message, k),
+        ) else {
+            return false;
+        };
+        i2osp(&m, k) == expected
+    }
+
+    pub fn verify_pss(&self, message: &[u8], signature: &[u8]) -> bool {
+        if signature.len() != self.size() {
+            return false;
+        }
+        let Ok(m) = self.raw_encrypt(&BigUint::from_bytes_be(signature)) else {
+            return false;
+        };
+        let em_bits = self.n.bits() as usize - 1;
+        let em_len = em_bits.div_ceil(8);
+        if m.bits() as usize > em_bits || em_len < 2 * HASH_LEN + 2 {
+            return false;
+        }
+        let em = i2osp(&m, em_len);
+        if em[em_len - 1] != 0xbc {
+            return false;
+        }
+        let (masked_db, h) = em[..em_len - 1].split_at(em_len - HASH_LEN - 1);
+        let mut db = xor(masked_db, &mgf1(h, em_len - HASH_LEN - 1));
+        db[0] &= 0xff >> (8 * em_len - em_bits);
+        // PS || 0x01 || salt, for a 32-byte salt
+        let salt_start = db.len() - HASH_LEN;
+        if db[..salt_start - 1].iter().any(|&b| b != 0) || db[salt_start - 1] != 0x01 {
+            return false;
+        }
+        pss_hash(&sha256(message), &db[salt_start..]) == h
+    }
+}
+
+/// EM = 0x00 || 0x01 || FF.. || 0x00 || DigestInfo || H(M)
+fn pkcs1v15_encode(message: &[u8], k: usize) -> Result<Vec<u8>, RsaError> {
+    let t_len = SHA256_DIGEST_INFO.len() + HASH_LEN;
+    if k < t_len + 11 {
+        return Err(RsaError::MessageTooLong);
+    }
+    let mut em = vec![0x00, 0x01];
+    em.resize(k - t_len - 1, 0xff);
+    em.push(0x00);
+    em.extend_from_slice(&SHA256_DIGEST_INFO);
+    em.extend_from_slice(&sha256(message));
+    Ok(em)
+}
+
+/// H(0x00 * 8 || mHash || salt)
+fn pss_hash(message_hash: &[u8; HASH_LEN], salt: &[u8]) -> [u8; HASH_LEN] {
+    let mut m = vec![0_u8; 8];
+    m.extend_from_slice(message_hash);
+    m.extend_from_slice(salt);
+    sha256(&m)
+}
+
+/// MGF1 with SHA-256: hashes of the seed and a 4-byte counter, concatenated
+fn mgf1(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut res = Vec::with_capacity(len + HASH_LEN);
+    let mut counter = 0_u32;
+    while res.len() < len {
+        let mut block = seed.to_vec();
+        block.extend_from_slice(&counter.to_be_bytes());
+        res.extend_from_slice(&sha256(&block));
+        counter += 1;
+    }
+    res.truncate(len);
+    res
+}
+
+/// Recovers the private key from a modulus with a prime p whose p - 1
+/// divides `bound`!, using Pollard's p - 1 method. `generate` makes such a
+/// prime with negligible probability; hand-picked "fast" primes are the
+/// usual culprit
+pub fn break_smooth_modulus(public: &PublicKey, bound: u64) -> Option<PrivateKey> {
+    from_factor(public, &pollard_p_minus_1(&public.n, bound)?)
+}
+
+/// Recovers the private key from a modulus small enough for Pollard's rho,
+/// which takes about sqrt(p) steps: seconds up to around 100 bits, and
+/// never for real key sizes
+pub fn break_small_modulus(public: &PublicKey) -> Option<PrivateKey> {
+    from_factor(public, &pollard_rho(&public.n)?)
+}
+
+fn from_factor(public: &PublicKey, p: &BigUint) -> Option<PrivateKey> {
+    PrivateKey::from_primes(p, &(&public.n / p), &public.e).ok()
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+/// The integer as exactly `len` big-endian bytes
+fn i2osp(x: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = x.to_bytes_be();
+    let mut res = vec![0_u8; len.saturating_sub(bytes.len())];
+    res.extend_from_slice(&bytes);
+    res
+}
+
+#[cfg(test)]
+mod rsa_tests {
+    use super::*;
+    use crate::crypto::prime::is_probable_prime;
+    use crate::crypto::rng::DeterministicRng;
+
+    fn hex_int(s: &str) -> BigUint {
+        BigUint::parse_bytes(s.as_bytes(), 16).unwrap()
+    }
+
+    /// A 1024-bit key and signatures made with OpenSSL over b"moneda"
+    fn openssl_key() -> PrivateKey {
+        let p = hex_int("e022d424b79d6150f7bc371eb0edb85d02b6214dc0a79fa11775c15e0815e0c102d57b7bda4b1b8cb638b69fc04080f5bc2df0818fe757dc5dd4f69914697d47");
+        let q = hex_int("db7c0c8a3a590086cd6196221ffffe44b9e8991fe46e9cd69255cadc958eb37ecbf54ddeff5994dc10fd51da1fcc64abac0ca25455c99611595a8f67f620215d");
+        PrivateKey::from_primes(&p, &q, &BigUint::from(65537_u32)).unwrap()
+    }
+
+    #[test]
+    fn test_openssl_vectors() {
+        let key = openssl_key();
+        let public = key.public_key();
+        assert_eq!(
+            public.n(),
+            &hex_int("c02a6754d2aa7783daf62115add08f07fabb973d432e1984b1168448c26c0547f3a0b59d0b6804cf5d363cb5d0975916ffba501a840a2148ca105dcdd148e803f2c4d6d67b26f5804cd3d10e251e1a3d2bbb0b31c9319bbed44c8681377775671fe7795c7c2d04a6f9b1628ee0d3b928f63dff7c9195321554494d60e658a9cb")
+        );
+        assert_eq!(key.dp, hex_int("6f6800686bb7d74a46891020ba32bec3c47840df83d60d9b25ad0b3b30eba6f668f131daa2eb583b3dcbbcc274eddaebc9e84fb6269007f4e9d2d3ebcce08e3b"));
+        assert_eq!(key.dq, hex_int("90f20b15fe1268d69d2ba50a4437baa38862a33cfc8e4fe5241ea429a85ae50f0782a83a6c47ac96e861627cba2331186e36a9bd282a48808c91d1c2c637d1d"));
+        assert_eq!(key.qinv, hex_int("cc4f4f970704216f3d7ec889072c69ce4b0a67e4cfcf9163c12501fb16ef77139c4d7950c7f2ac2c8e01eaa504c611d13ae3809405462e40ac73695b058c355c"));
+
+        let v15 = hex::decode("26669d159aaaf0c4a5d35124970d88401405e3d3b2175e6d3f1ef0e140c82dc221ddc746b86f507f93b8bd40fbe4d3dedbeeccb2327e12685cd68c8231909a18adf66ab8edecf683016f0185e95e34cff6221440800a1ca713deb1d84726c33f5c2bbefc1446d344c2f85b68ba29a40298f97bff44363547d10060254558214f").unwrap();
+        assert_eq!(key.sign_pkcs1v15(b"moneda").unwrap(), v15);
+        assert!(public.verify_pkcs1v15(b"moneda", &v15));
+        assert!(!public.verify_pkcs1v15(b"monedas", &v15));
+
+        let pss = hex::decode("98a17d26404d303aab4b14d67ae2219d0976f2ac3249952d4687bb6d4876451c9d4b80cbabc9275aa104495ec8971ced31b8be6d93c098a9c7d8d3eb83622a0af1b1b093cc0809eff37bd54f7d6e53e59b2917fe19e32f3b53529a02cc7c2ce371a7f3cdc9481d7066c585a90c33a15f2015467fcf1bf306a082b50b8212d9da").unwrap();
+        assert!(public.verify_pss(b"moneda", &pss));
+        assert!(!public.verify_pss(b"monedas", &pss));
+        assert!(!public.verify_pkcs1v15(b"moneda", &pss));
+
+        let oaep = hex::decode("87ad63d5fabe018217e906ed7e412245595762b7c228cd8124fb2db1c78edb57ad0c9068ba39ba6a0617d009e71f92e85ce38a39aef4e96ca0a171f24511a7a437d6d8cae86b02e0db324c2c37e3c587d1f2baff7bfc85f347895857e1a4602146cf1e34cde156e91eaf39c414ac70bb052505d74376085eed707b3310fb1c61").unwrap();
+        assert_eq!(key.decrypt_oaep(&oaep, b""), Ok(b"moneda".to_vec()));
+        assert_eq!(key.decrypt_oaep(&oaep, b"label"), Err(RsaError::DecryptionError));
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let key = PrivateKey::generate(1024);
+        let public = key.public_key();
+        assert_eq!(public.n().bits(), 1024);
+
+        // CRT agrees with the plain exponentiation
+        let m = BigUint::from(0x1234_5678_u32);
+        let c = public.raw_encrypt(&m).unwrap();
+        assert_eq!(c.modpow(&key.d, public.n()), m);
+        assert_eq!(key.raw_decrypt(&c), Ok(m));
+        assert_eq!(public.raw_encrypt(public.n()), Err(RsaError::MessageOutOfRange));
+
+        let ciphertext = public.encrypt_oaep(b"secret", b"ctx").unwrap();
+        assert_ne!(public.encrypt_oaep(b"secret", b"ctx").unwrap(), ciphertext);
+        assert_eq!(key.decrypt_oaep(&ciphertext, b"ctx"), Ok(b"secret".to_vec()));
+        let mut tampered = ciphertext.clone();
+        tampered[10] ^= 1;
+        assert_eq!(key.decrypt_oaep(&tampered, b"ctx"), Err(RsaError::DecryptionError));
+        // k - 2 hLen - 2 = 62 bytes at most
+        assert!(public.encrypt_oaep(&[0; 62], b"").is_ok());
+        assert_eq!(public.encrypt_oaep(&[0; 63], b""), Err(RsaError::MessageTooLong));
+
+        let signature = key.sign_pss(b"message").unwrap();
+        assert!(public.verify_pss(b"message", &signature));
+        assert_ne!(key.sign_pss(b"message").unwrap(), signature);
+        let mut forged = signature.clone();
+        forged[5] ^= 1;
+        assert!(!public.verify_pss(b"message", &forged));
+        assert!(!openssl_key().public_key().verify_pss(b"message", &signature));
+
+        assert_eq!(
+            PrivateKey::from_primes(&BigUint::from(7_u8), &BigUint::from(7_u8), &BigUint::from(3_u8)),
+            Err(RsaError::InvalidKey)
+        );
+        assert_eq!(
+            PrivateKey::from_primes(&BigUint::from(7_u8), &BigUint::from(13_u8), &BigUint::from(3_u8)),
+            Err(RsaError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_weak_moduli() {
+        let mut rng = DeterministicRng::new([0x94; 32]);
+        let e = BigUint::from(65537_u32);
+
+        // p - 1 = k times the primes below 100
+        let primorial = (2_u32..100)
+            .filter(|&n| is_probable_prime(&n.into(), 20))
+            .fold(BigUint::from(1_u8), |acc, n| acc * n);
+        let p = (1_u32..)
+            .map(|k| &primorial * k + 1_u8)
+            .find(|p| is_probable_prime(p, 20))
+            .unwrap();
+        let q = random_prime(128, &mut rng);
+        let key = PrivateKey::from_primes(&p, &q, &e).unwrap();
+        let broken = break_smooth_modulus(key.public_key(), 1000).unwrap();
+        assert_eq!(broken.public_key(), key.public_key());
+        let c = key.public_key().raw_encrypt(&BigUint::from(42_u8)).unwrap();
+        assert_eq!(broken.raw_decrypt(&c), Ok(BigUint::from(42_u8)));
+        assert!(break_smooth_modulus(&PrivateKey::generate_with_rng(512, &mut rng).public, 1000).is_none());
+
+        let key = PrivateKey::from_primes(&random_prime(32, &mut rng), &random_prime(32, &mut rng), &e).unwrap();
+        let broken = break_small_modulus(key.public_key()).unwrap();
+        assert_eq!(broken.public_key(), key.public_key());
+        assert_eq!(broken.d, key.d);
+    }
+}
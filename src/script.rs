@@ -0,0 +1,875 @@
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+use crate::encoding::compact_size;
+use crate::encoding::consensus::impl_consensus_codec;
+use crate::encoding::decode::DecodeError;
+use crate::encoding::hex_fmt::impl_hex_fmt;
+use crate::tx::TxError;
+
+/// Largest OP_RETURN script_pubkey relayed under Bitcoin Core's default
+/// -datacarriersize, counting the OP_RETURN and push opcodes
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+
+macro_rules! opcodes {
+    ($($variant:ident = $byte:literal => $name:literal,)*) => {
+        /// Every Bitcoin opcode. The 75 direct pushes share `PushBytes`, and
+        /// bytes with no assigned meaning are kept as `Unknown` (tapscript
+        /// treats them as OP_SUCCESSx)
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Opcode {
+            $($variant,)*
+            /// 0x01-0x4b: push the next n bytes
+            PushBytes(u8),
+            Unknown(u8),
+        }
+
+        impl Opcode {
+            pub fn from_u8(byte: u8) -> Self {
+                match byte {
+                    $($byte => Opcode::$variant,)*
+                    0x01..=0x4b => Opcode::PushBytes(byte),
+                    _ => Opcode::Unknown(byte),
+                }
+            }
+
+            pub fn to_u8(self) -> u8 {
+                match self {
+                    $(Opcode::$variant => $byte,)*
+                    Opcode::PushBytes(n) | Opcode::Unknown(n) => n,
+                }
+            }
+        }
+
+        impl Display for Opcode {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $(Opcode::$variant => write!(f, $name),)*
+                    Opcode::PushBytes(n) => write!(f, "OP_PUSHBYTES_{}", n),
+                    Opcode::Unknown(n) => write!(f, "OP_UNKNOWN_{:#04x}", n),
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    Op0 = 0x00 => "OP_0",
+    PushData1 = 0x4c => "OP_PUSHDATA1",
+    PushData2 = 0x4d => "OP_PUSHDATA2",
+    PushData4 = 0x4e => "OP_PUSHDATA4",
+    Op1Negate = 0x4f => "OP_1NEGATE",
+    Reserved = 0x50 => "OP_RESERVED",
+    Op1 = 0x51 => "OP_1",
+    Op2 = 0x52 => "OP_2",
+    Op3 = 0x53 => "OP_3",
+    Op4 = 0x54 => "OP_4",
+    Op5 = 0x55 => "OP_5",
+    Op6 = 0x56 => "OP_6",
+    Op7 = 0x57 => "OP_7",
+    Op8 = 0x58 => "OP_8",
+    Op9 = 0x59 => "OP_9",
+    Op10 = 0x5a => "OP_10",
+    Op11 = 0x5b => "OP_11",
+    Op12 = 0x5c => "OP_12",
+    Op13 = 0x5d => "OP_13",
+    Op14 = 0x5e => "OP_14",
+    Op15 = 0x5f => "OP_15",
+    Op16 = 0x60 => "OP_16",
+    Nop = 0x61 => "OP_NOP",
+    Ver = 0x62 => "OP_VER",
+    If = 0x63 => "OP_IF",
+    NotIf = 0x64 => "OP_NOTIF",
+    VerIf = 0x65 => "OP_VERIF",
+    VerNotIf = 0x66 => "OP_VERNOTIF",
+    Else = 0x67 => "OP_ELSE",
+    EndIf = 0x68 => "OP_ENDIF",
+    Verify = 0x69 => "OP_VERIFY",
+    Return = 0x6a => "OP_RETURN",
+    ToAltStack = 0x6b => "OP_TOALTSTACK",
+    FromAltStack = 0x6c => "OP_FROMALTSTACK",
+    Drop2 = 0x6d => "OP_2DROP",
+    Dup2 = 0x6e => "OP_2DUP",
+    Dup3 = 0x6f => "OP_3DUP",
+    Over2 = 0x70 => "OP_2OVER",
+    Rot2 = 0x71 => "OP_2ROT",
+    Swap2 = 0x72 => "OP_2SWAP",
+    IfDup = 0x73 => "OP_IFDUP",
+    Depth = 0x74 => "OP_DEPTH",
+    Drop = 0x75 => "OP_DROP",
+    Dup = 0x76 => "OP_DUP",
+    Nip = 0x77 => "OP_NIP",
+    Over = 0x78 => "OP_OVER",
+    Pick = 0x79 => "OP_PICK",
+    Roll = 0x7a => "OP_ROLL",
+    Rot = 0x7b => "OP_ROT",
+    Swap = 0x7c => "OP_SWAP",
+    Tuck = 0x7d => "OP_TUCK",
+    Cat = 0x7e => "OP_CAT",
+    Substr = 0x7f => "OP_SUBSTR",
+    Left = 0x80 => "OP_LEFT",
+    Right = 0x81 => "OP_RIGHT",
+    Size = 0x82 => "OP_SIZE",
+    Invert = 0x83 => "OP_INVERT",
+    And = 0x84 => "OP_AND",
+    Or = 0x85 => "OP_OR",
+    Xor = 0x86 => "OP_XOR",
+    Equal = 0x87 => "OP_EQUAL",
+    EqualVerify = 0x88 => "OP_EQUALVERIFY",
+    Reserved1 = 0x89 => "OP_RESERVED1",
+    Reserved2 = 0x8a => "OP_RESERVED2",
+    Add1 = 0x8b => "OP_1ADD",
+    Sub1 = 0x8c => "OP_1SUB",
+    Mul2 = 0x8d => "OP_2MUL",
+    Div2 = 0x8e => "OP_2DIV",
+    Negate = 0x8f => "OP_NEGATE",
+    Abs = 0x90 => "OP_ABS",
+    Not = 0x91 => "OP_NOT",
+    NotEqual0 = 0x92 => "OP_0NOTEQUAL",
+    Add = 0x93 => "OP_ADD",
+    Sub = 0x94 => "OP_SUB",
+    Mul = 0x95 => "OP_MUL",
+    Div = 0x96 => "OP_DIV",
+    Mod = 0x97 => "OP_MOD",
+    LShift = 0x98 => "OP_LSHIFT",
+    RShift = 0x99 => "OP_RSHIFT",
+    BoolAnd = 0x9a => "OP_BOOLAND",
+    BoolOr = 0x9b => "OP_BOOLOR",
+    NumEqual = 0x9c => "OP_NUMEQUAL",
+    NumEqualVerify = 0x9d => "OP_NUMEQUALVERIFY",
+    NumNotEqual = 0x9e => "OP_NUMNOTEQUAL",
+    LessThan = 0x9f => "OP_LESSTHAN",
+    GreaterThan = 0xa0 => "OP_GREATERTHAN",
+    LessThanOrEqual = 0xa1 => "OP_LESSTHANOREQUAL",
+    GreaterThanOrEqual = 0xa2 => "OP_GREATERTHANOREQUAL",
+    Min = 0xa3 => "OP_MIN",
+    Max = 0xa4 => "OP_MAX",
+    Within = 0xa5 => "OP_WITHIN",
+    Ripemd160 = 0xa6 => "OP_RIPEMD160",
+    Sha1 = 0xa7 => "OP_SHA1",
+    Sha256 = 0xa8 => "OP_SHA256",
+    Hash160 = 0xa9 => "OP_HASH160",
+    Hash256 = 0xaa => "OP_HASH256",
+    CodeSeparator = 0xab => "OP_CODESEPARATOR",
+    CheckSig = 0xac => "OP_CHECKSIG",
+    CheckSigVerify = 0xad => "OP_CHECKSIGVERIFY",
+    CheckMultiSig = 0xae => "OP_CHECKMULTISIG",
+    CheckMultiSigVerify = 0xaf => "OP_CHECKMULTISIGVERIFY",
+    Nop1 = 0xb0 => "OP_NOP1",
+    CheckLockTimeVerify = 0xb1 => "OP_CHECKLOCKTIMEVERIFY",
+    CheckSequenceVerify = 0xb2 => "OP_CHECKSEQUENCEVERIFY",
+    Nop4 = 0xb3 => "OP_NOP4",
+    Nop5 = 0xb4 => "OP_NOP5",
+    Nop6 = 0xb5 => "OP_NOP6",
+    Nop7 = 0xb6 => "OP_NOP7",
+    Nop8 = 0xb7 => "OP_NOP8",
+    Nop9 = 0xb8 => "OP_NOP9",
+    Nop10 = 0xb9 => "OP_NOP10",
+    CheckSigAdd = 0xba => "OP_CHECKSIGADD",
+    Invalid = 0xff => "OP_INVALIDOPCODE",
+}
+
+impl Opcode {
+    /// OP_1 through OP_16 as their number
+    pub fn small_int(self) -> Option<u8> {
+        match self.to_u8() {
+            byte @ 0x51..=0x60 => Some(byte - 0x50),
+            _ => None,
+        }
+    }
+
+    pub fn from_small_int(n: u8) -> Option<Self> {
+        match n {
+            0 => Some(Opcode::Op0),
+            1..=16 => Some(Opcode::from_u8(0x50 + n)),
+            _ => None,
+        }
+    }
+
+    /// Inverse of the `Display` names, OP_PUSHBYTES_n and OP_UNKNOWN_0xnn
+    /// included
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(digits) = name.strip_prefix("OP_PUSHBYTES_") {
+            return match digits.parse::<u8>() {
+                // no leading zeros or signs, so every name has one spelling
+                Ok(n @ 1..=0x4b) if n.to_string() == digits => Some(Opcode::PushBytes(n)),
+                _ => None,
+            };
+        }
+        (0..=255)
+            .map(Opcode::from_u8)
+            .find(|op| !matches!(op, Opcode::PushBytes(_)) && op.to_string() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptError {
+    /// A push opcode claims more bytes than the script has left
+    TruncatedPush { offset: usize },
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptError::TruncatedPush { offset } => {
+                write!(f, "push at byte {} runs past the end of the script", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpReturnError {
+    /// An OP_RETURN output over MAX_OP_RETURN_RELAY bytes won't be relayed
+    TooLarge(usize),
+}
+
+impl Display for OpReturnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpReturnError::TooLarge(len) => {
+                write!(f, "OP_RETURN script of {} bytes exceeds {}", len, MAX_OP_RETURN_RELAY)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OpReturnError {}
+
+/// Errors from `Script::from_asm`, each with the index of the offending
+/// whitespace-separated token
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmError {
+    /// Not an opcode name; data has to follow a push opcode
+    UnknownOpcode {
+        position: usize,
+        token: String,
+    },
+    /// A push opcode at the end or followed by another opcode
+    MissingPushData {
+        position: usize,
+    },
+    InvalidHex {
+        position: usize,
+        token: String,
+    },
+    /// OP_PUSHBYTES_n followed by a different number of bytes
+    PushLengthMismatch {
+        position: usize,
+        expected: usize,
+        got: usize,
+    },
+    /// More data than the OP_PUSHDATA variant's length field can express
+    PushTooLarge {
+        position: usize,
+        len: usize,
+    },
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmError::UnknownOpcode { position, token } => write!(f, "unknown opcode {} at token {}", token, position),
+            AsmError::MissingPushData { position } => write!(f, "push at token {} has no data", position),
+            AsmError::InvalidHex { position, token } => write!(f, "invalid hex {} at token {}", token, position),
+            AsmError::PushLengthMismatch {
+                position,
+                expected,
+                got,
+            } => {
+                write!(f, "push at token {} expects {} bytes, got {}", position, expected, got)
+            }
+            AsmError::PushTooLarge { position, len } => {
+                write!(f, "push at token {} cannot carry {} bytes", position, len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// One step of a script: an opcode, or the data a push opcode carries
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction<'a> {
+    Op(Opcode),
+    /// The push opcode is kept so non-minimal pushes can be told apart
+    Push(Opcode, &'a [u8]),
+}
+
+/// Scripts are stored as raw bytes: consensus hashes and signs exactly what
+/// appeared on chain, including non-minimal pushes and unparsable tails,
+/// so the bytes are the source of truth and instructions are a view on them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Script(Vec<u8>);
+
+impl Script {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, TxError> {
+        hex::decode(s).map(Self).map_err(|_| TxError::InvalidHex)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Reads a CompactSize-prefixed script, as found in transactions
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        let len = compact_size::read_size(reader).map_err(TxError::from)?;
+        let mut buf = Vec::new();
+        reader.take(len as u64).read_to_end(&mut buf).map_err(TxError::from)?;
+        if buf.len() != len {
+            return Err(TxError::Truncated.into());
+        }
+        Ok(Self(buf))
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        compact_size::write(writer, self.0.len() as u64)?;
+        writer.write_all(&self.0)
+    }
+
+    pub fn push_opcode(mut self, op: Opcode) -> Self {
+        self.0.push(op.to_u8());
+        self
+    }
+
+    /// Pushes data with the shortest encoding: direct push up to 75 bytes,
+    /// then OP_PUSHDATA1/2/4
+    pub fn push_slice(mut self, data: &[u8]) -> Self {
+        match data.len() {
+            n @ 0..=0x4b => self.0.push(n as u8),
+            n @ 0x4c..=0xff => {
+                self.0.push(Opcode::PushData1.to_u8());
+                self.0.push(n as u8);
+            }
+            n @ 0x100..=0xffff => {
+                self.0.push(Opcode::PushData2.to_u8());
+                self.0.extend_from_slice(&(n as u16).to_le_bytes());
+            }
+            n => {
+                self.0.push(Opcode::PushData4.to_u8());
+                self.0.extend_from_slice(&(n as u32).to_le_bytes());
+            }
+        }
+        self.0.extend_from_slice(data);
+        self
+    }
+
+    /// Pushes a number the way the interpreter expects it: OP_0, OP_1NEGATE
+    /// and OP_1-OP_16 where possible, a minimal script number otherwise
+    pub fn push_int(self, n: i64) -> Self {
+        match n {
+            -1 => self.push_opcode(Opcode::Op1Negate),
+            0..=16 => self.push_opcode(Opcode::from_small_int(n as u8).unwrap()),
+            _ => self.push_slice(&encode_num(n)),
+        }
+    }
+
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions {
+            script: &self.0,
+            offset: 0,
+        }
+    }
+
+    /// Opcode names with pushed data in hex after its push opcode, e.g.
+    /// `OP_0 OP_PUSHBYTES_20 <hex>`. The push opcode is spelled out so the
+    /// exact bytes survive `from_asm`. An unparsable tail shows as `[error]`
+    pub fn to_asm(&self) -> String {
+        let mut tokens = Vec::new();
+        for instruction in self.instructions() {
+            match instruction {
+                Ok(Instruction::Op(op)) | Ok(Instruction::Push(op @ Opcode::Op0, _)) => tokens.push(op.to_string()),
+                Ok(Instruction::Push(op, data)) => {
+                    tokens.push(op.to_string());
+                    // an empty OP_PUSHDATA has nothing to show
+                    if !data.is_empty() {
+                        tokens.push(hex::encode(data));
+                    }
+                }
+                Err(_) => tokens.push("[error]".to_string()),
+            }
+        }
+        tokens.join(" ")
+    }
+
+    /// Parses the output of `to_asm` back into the same bytes. Every token
+    /// is an opcode name, except for the hex data following a push opcode
+    pub fn from_asm(asm: &str) -> Result<Self, AsmError> {
+        let mut bytes = Vec::new();
+        let mut tokens = asm.split_whitespace().enumerate().peekable();
+        while let Some((position, token)) = tokens.next() {
+            let op = Opcode::from_name(token).ok_or_else(|| AsmError::UnknownOpcode {
+                position,
+                token: token.to_string(),
+            })?;
+            bytes.push(op.to_u8());
+            let len_size = match op {
+                Opcode::PushBytes(_) => 0,
+                Opcode::PushData1 => 1,
+                Opcode::PushData2 => 2,
+                Opcode::PushData4 => 4,
+                _ => continue,
+            };
+            let data = match tokens.next_if(|(_, next)| !next.starts_with("OP_")) {
+                Some((data_position, hex)) => hex::decode(hex).map_err(|_| AsmError::InvalidHex {
+                    position: data_position,
+                    token: hex.to_string(),
+                })?,
+                None if len_size > 0 => Vec::new(),
+                None => return Err(AsmError::MissingPushData { position }),
+            };
+            match op {
+                Opcode::PushBytes(n) if data.len() != n as usize => {
+                    return Err(AsmError::PushLengthMismatch {
+                        position,
+                        expected: n as usize,
+                        got: data.len(),
+                    });
+                }
+                Opcode::PushBytes(_) => {}
+                _ if len_size < 4 && data.len() >> (8 * len_size) != 0 => {
+                    return Err(AsmError::PushTooLarge {
+                        position,
+                        len: data.len(),
+                    });
+                }
+                _ => bytes.extend_from_slice(&(data.len() as u32).to_le_bytes()[..len_size]),
+            }
+            bytes.extend_from_slice(&data);
+        }
+        Ok(Self(bytes))
+    }
+
+    /// OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+    pub fn p2pkh(pubkey_hash: &[u8; 20]) -> Self {
+        Self::new()
+            .push_opcode(Opcode::Dup)
+            .push_opcode(Opcode::Hash160)
+            .push_slice(pubkey_hash)
+            .push_opcode(Opcode::EqualVerify)
+            .push_opcode(Opcode::CheckSig)
+    }
+
+    /// OP_HASH160 <20 bytes> OP_EQUAL
+    pub fn p2sh(script_hash: &[u8; 20]) -> Self {
+        Self::new()
+            .push_opcode(Opcode::Hash160)
+            .push_slice(script_hash)
+            .push_opcode(Opcode::Equal)
+    }
+
+    /// OP_0 <20 bytes>
+    pub fn p2wpkh(pubkey_hash: &[u8; 20]) -> Self {
+        Self::new().push_opcode(Opcode::Op0).push_slice(pubkey_hash)
+    }
+
+    /// OP_0 <32-byte SHA256 of the witness script>
+    pub fn p2wsh(script_hash: &[u8; 32]) -> Self {
+        Self::new().push_opcode(Opcode::Op0).push_slice(script_hash)
+    }
+
+    /// OP_1 <32-byte x-only output key>
+    pub fn p2tr(output_key: &[u8; 32]) -> Self {
+        Self::new().push_opcode(Opcode::Op1).push_slice(output_key)
+    }
+
+    /// OP_RETURN followed by one push per slice, for embedding data in a
+    /// zero-value output. Fails if the script would be too large to relay
+    pub fn op_return(pushes: &[&[u8]]) -> Result<Self, OpReturnError> {
+        let script = pushes
+            .iter()
+            .fold(Self::new().push_opcode(Opcode::Return), |script, data| {
+                script.push_slice(data)
+            });
+        if script.len() > MAX_OP_RETURN_RELAY {
+            return Err(OpReturnError::TooLarge(script.len()));
+        }
+        Ok(script)
+    }
+
+    /// Starts with OP_RETURN, so no input can ever spend it and nodes
+    /// leave it out of the UTXO set
+    pub fn is_op_return(&self) -> bool {
+        self.0.first() == Some(&Opcode::Return.to_u8())
+    }
+
+    /// The data pushed after OP_RETURN. None for other scripts, or when
+    /// anything but data pushes follows, OP_1-OP_16 included
+    pub fn op_return_data(&self) -> Option<Vec<&[u8]>> {
+        if !self.is_op_return() {
+            return None;
+        }
+        Instructions {
+            script: &self.0,
+            offset: 1,
+        }
+        .map(|instruction| match instruction {
+            Ok(Instruction::Push(_, data)) => Some(data),
+            _ => None,
+        })
+        .collect()
+    }
+
+    pub fn is_p2pkh(&self) -> bool {
+        let b = &self.0;
+        b.len() == 25 && b[0] == 0x76 && b[1] == 0xa9 && b[2] == 0x14 && b[23] == 0x88 && b[24] == 0xac
+    }
+
+    pub fn is_p2sh(&self) -> bool {
+        let b = &self.0;
+        b.len() == 23 && b[0] == 0xa9 && b[1] == 0x14 && b[22] == 0x87
+    }
+
+    /// A segwit output: a version opcode followed by one 2-40 byte push
+    pub fn witness_program(&self) -> Option<(u8, &[u8])> {
+        let b = &self.0;
+        if b.len() < 4 || b.len() > 42 || b[1] as usize != b.len() - 2 {
+            return None;
+        }
+        let version = match Opcode::from_u8(b[0]) {
+            Opcode::Op0 => 0,
+            op => op.small_int()?,
+        };
+        Some((version, &b[2..]))
+    }
+}
+
+impl From<Vec<u8>> for Script {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+pub struct Instructions<'a> {
+    script: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Instructions<'a> {
+    /// Instructions of raw script bytes, for walking part of a script
+    pub fn new(script: &'a [u8]) -> Self {
+        Self { script, offset: 0 }
+    }
+
+    /// Byte offset of the next instruction, the script length once done
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, ScriptError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.offset;
+        let op = Opcode::from_u8(*self.script.get(start)?);
+        let rest = &self.script[start + 1..];
+        let (len_size, len) = match op {
+            // OP_0 pushes the empty string
+            Opcode::Op0 => (0, Some(0)),
+            Opcode::PushBytes(n) => (0, Some(n as usize)),
+            Opcode::PushData1 => (1, rest.first().map(|&n| n as usize)),
+            Opcode::PushData2 => (2, rest.get(..2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)),
+            Opcode::PushData4 => (
+                4,
+                rest.get(..4)
+                    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize),
+            ),
+            op => {
+                self.offset += 1;
+                return Some(Ok(Instruction::Op(op)));
+            }
+        };
+        let data = len.and_then(|len| rest.get(len_size..len_size + len));
+        match data {
+            Some(data) => {
+                self.offset += 1 + len_size + data.len();
+                Some(Ok(Instruction::Push(op, data)))
+            }
+            None => {
+                // stop after reporting, there's no way to resynchronise
+                self.offset = self.script.len();
+                Some(Err(ScriptError::TruncatedPush { offset: start }))
+            }
+        }
+    }
+}
+
+/// Script numbers: little-endian, sign in the top bit of the last byte,
+/// zero as the empty string
+pub fn encode_num(n: i64) -> Vec<u8> {
+    let mut res = Vec::new();
+    let negative = n < 0;
+    let mut abs = n.unsigned_abs();
+    while abs > 0 {
+        res.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+    if let Some(&last) = res.last() {
+        if last & 0x80 != 0 {
+            res.push(if negative { 0x80 } else { 0 });
+        } else if negative {
+            *res.last_mut().unwrap() |= 0x80;
+        }
+    }
+    res
+}
+
+/// Inverse of `encode_num` for up to 8 bytes; the interpreter applies its own
+/// size limits
+pub fn decode_num(bytes: &[u8]) -> Option<i64> {
+    if bytes.len() > 8 {
+        return None;
+    }
+    let Some((&last, _)) = bytes.split_last() else {
+        return Some(0);
+    };
+    let mut abs: u64 = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let byte = if i == bytes.len() - 1 { byte & 0x7f } else { byte };
+        abs |= (byte as u64) << (8 * i);
+    }
+    let abs = i64::try_from(abs).ok()?;
+    Some(if last & 0x80 != 0 { -abs } else { abs })
+}
+
+impl_consensus_codec!(Script, TxError);
+impl_hex_fmt!(Script, |script| script.as_bytes().to_vec());
+
+#[cfg(test)]
+mod script_tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_round_trip() {
+        for byte in 0..=255_u8 {
+            assert_eq!(Opcode::from_u8(byte).to_u8(), byte);
+        }
+        assert_eq!(Opcode::from_u8(0x76), Opcode::Dup);
+        assert_eq!(Opcode::from_u8(0x14), Opcode::PushBytes(20));
+        assert_eq!(Opcode::from_u8(0xbb), Opcode::Unknown(0xbb));
+        assert_eq!(Opcode::CheckLockTimeVerify.to_string(), "OP_CHECKLOCKTIMEVERIFY");
+        assert_eq!(Opcode::PushBytes(33).to_string(), "OP_PUSHBYTES_33");
+        assert_eq!(Opcode::Op16.small_int(), Some(16));
+        assert_eq!(Opcode::from_small_int(7), Some(Opcode::Op7));
+    }
+
+    #[test]
+    fn test_p2pkh() {
+        let hash: [u8; 20] = hex::decode("0389035a9225b3839e2bbf32d826a1e222031fd8")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let script = Script::p2pkh(&hash);
+        assert_eq!(script.to_hex(), "76a9140389035a9225b3839e2bbf32d826a1e222031fd888ac");
+        assert!(script.is_p2pkh());
+        assert!(!script.is_p2sh());
+        assert_eq!(script.witness_program(), None);
+
+        let ops: Vec<_> = script.instructions().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                Instruction::Op(Opcode::Dup),
+                Instruction::Op(Opcode::Hash160),
+                Instruction::Push(Opcode::PushBytes(20), &hash[..]),
+                Instruction::Op(Opcode::EqualVerify),
+                Instruction::Op(Opcode::CheckSig),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_witness_program() {
+        let script = Script::p2wpkh(&[0xab; 20]);
+        assert_eq!(script.witness_program(), Some((0, &[0xab; 20][..])));
+
+        let taproot = Script::new().push_opcode(Opcode::Op1).push_slice(&[1; 32]);
+        assert_eq!(taproot.witness_program(), Some((1, &[1; 32][..])));
+        assert_eq!(
+            Script::new()
+                .push_opcode(Opcode::Op1)
+                .push_slice(&[1; 41])
+                .witness_program(),
+            None
+        );
+        assert_eq!(Script::p2sh(&[0; 20]).witness_program(), None);
+    }
+
+    #[test]
+    fn test_push_encoding() {
+        assert_eq!(Script::new().push_slice(&[]).to_hex(), "00");
+        assert_eq!(Script::new().push_slice(&[0xaa; 75]).as_bytes()[0], 0x4b);
+        assert_eq!(&Script::new().push_slice(&[0xaa; 76]).as_bytes()[..2], &[0x4c, 76]);
+        assert_eq!(
+            &Script::new().push_slice(&[0xaa; 256]).as_bytes()[..3],
+            &[0x4d, 0x00, 0x01]
+        );
+        assert_eq!(
+            &Script::new().push_slice(&[0xaa; 0x10000]).as_bytes()[..5],
+            &[0x4e, 0x00, 0x00, 0x01, 0x00]
+        );
+
+        for len in [0, 1, 75, 76, 255, 256, 0x10000] {
+            let data = vec![0x5a; len];
+            let script = Script::new().push_slice(&data);
+            let mut ops = script.instructions();
+            match ops.next() {
+                Some(Ok(Instruction::Push(_, pushed))) => assert_eq!(pushed, &data[..]),
+                other => panic!("unexpected {:?}", other),
+            }
+            assert!(ops.next().is_none());
+        }
+
+        assert_eq!(Script::new().push_int(0).to_hex(), "00");
+        assert_eq!(Script::new().push_int(-1).to_hex(), "4f");
+        assert_eq!(Script::new().push_int(16).to_hex(), "60");
+        assert_eq!(Script::new().push_int(17).to_hex(), "0111");
+        assert_eq!(Script::new().push_int(500_000).to_hex(), "0320a107");
+    }
+
+    #[test]
+    fn test_truncated_push() {
+        for hex in ["4c", "4c05aabb", "4d0100", "4e01000000", "05aabb"] {
+            let script = Script::from_hex(hex).unwrap();
+            let res: Result<Vec<_>, _> = script.instructions().collect();
+            assert_eq!(res, Err(ScriptError::TruncatedPush { offset: 0 }), "{}", hex);
+        }
+        // non-minimal push survives intact
+        let script = Script::from_hex("4c0100").unwrap();
+        let ops: Vec<_> = script.instructions().collect::<Result<_, _>>().unwrap();
+        assert_eq!(ops, vec![Instruction::Push(Opcode::PushData1, &[0][..])]);
+    }
+
+    #[test]
+    fn test_op_return() {
+        let script = Script::op_return(&[b"moneda", &[0xab; 32]]).unwrap();
+        assert_eq!(&script.to_hex()[..16], "6a066d6f6e656461");
+        assert!(script.is_op_return());
+        assert_eq!(script.op_return_data(), Some(vec![&b"moneda"[..], &[0xab; 32][..]]));
+
+        // the largest single push that relays is 80 bytes
+        assert_eq!(Script::op_return(&[&[0; 80]]).unwrap().len(), MAX_OP_RETURN_RELAY);
+        assert_eq!(Script::op_return(&[&[0; 81]]), Err(OpReturnError::TooLarge(84)));
+        assert_eq!(Script::op_return(&[]).unwrap().op_return_data(), Some(vec![]));
+
+        assert_eq!(Script::p2wpkh(&[0; 20]).op_return_data(), None);
+        // not push-only, or truncated
+        assert_eq!(Script::from_hex("6a5104aabbccdd").unwrap().op_return_data(), None);
+        assert_eq!(Script::from_hex("6a04aabb").unwrap().op_return_data(), None);
+        assert!(Script::from_hex("6a04aabb").unwrap().is_op_return());
+    }
+
+    #[test]
+    fn test_asm() {
+        // same as rust-bitcoin, which names small numbers OP_PUSHNUM_n
+        let cases = [
+            (
+                "76a914000102030405060708090a0b0c0d0e0f1011121388ac",
+                "OP_DUP OP_HASH160 OP_PUSHBYTES_20 000102030405060708090a0b0c0d0e0f10111213 OP_EQUALVERIFY OP_CHECKSIG",
+            ),
+            ("4c0201024e0100000055", "OP_PUSHDATA1 0102 OP_PUSHDATA4 55"),
+            ("00514f60ba", "OP_0 OP_1 OP_1NEGATE OP_16 OP_CHECKSIGADD"),
+            ("6a0474657374", "OP_RETURN OP_PUSHBYTES_4 74657374"),
+            ("bbff", "OP_UNKNOWN_0xbb OP_INVALIDOPCODE"),
+            ("4c00ac", "OP_PUSHDATA1 OP_CHECKSIG"),
+            ("", ""),
+        ];
+        for (hex, asm) in cases {
+            let script = Script::from_hex(hex).unwrap();
+            assert_eq!(script.to_asm(), asm);
+            assert_eq!(Script::from_asm(asm), Ok(script));
+        }
+        assert_eq!(Script::from_hex("00204c05aa").unwrap().to_asm(), "OP_0 [error]");
+        // extra whitespace is fine
+        assert_eq!(Script::from_asm(" OP_1\n\tOP_2  ").unwrap().to_hex(), "5152");
+    }
+
+    #[test]
+    fn test_asm_errors() {
+        let unknown = |position: usize, token: &str| AsmError::UnknownOpcode {
+            position,
+            token: token.to_string(),
+        };
+        assert_eq!(Script::from_asm("OP_DUP DUP"), Err(unknown(1, "DUP")));
+        assert_eq!(Script::from_asm("OP_1 abcd"), Err(unknown(1, "abcd")));
+        assert_eq!(Script::from_asm("OP_PUSHBYTES_0 00"), Err(unknown(0, "OP_PUSHBYTES_0")));
+        assert_eq!(
+            Script::from_asm("OP_PUSHBYTES_076 00"),
+            Err(unknown(0, "OP_PUSHBYTES_076"))
+        );
+        assert_eq!(Script::from_asm("[error]"), Err(unknown(0, "[error]")));
+        assert_eq!(
+            Script::from_asm("OP_0 OP_PUSHBYTES_2"),
+            Err(AsmError::MissingPushData { position: 1 })
+        );
+        assert_eq!(
+            Script::from_asm("OP_PUSHBYTES_1 OP_1"),
+            Err(AsmError::MissingPushData { position: 0 })
+        );
+        assert_eq!(
+            Script::from_asm("OP_PUSHBYTES_2 abc"),
+            Err(AsmError::InvalidHex {
+                position: 1,
+                token: "abc".to_string()
+            })
+        );
+        assert_eq!(
+            Script::from_asm("OP_PUSHBYTES_2 aabbcc"),
+            Err(AsmError::PushLengthMismatch {
+                position: 0,
+                expected: 2,
+                got: 3
+            })
+        );
+        let big = format!("OP_PUSHDATA1 {}", "00".repeat(256));
+        assert_eq!(
+            Script::from_asm(&big),
+            Err(AsmError::PushTooLarge { position: 0, len: 256 })
+        );
+        assert_eq!(Script::from_asm(&big.replace("DATA1", "DATA2")).unwrap().len(), 3 + 256);
+    }
+
+    #[test]
+    fn test_script_num() {
+        let cases: [(i64, &str); 9] = [
+            (0, ""),
+            (1, "01"),
+            (-1, "81"),
+            (127, "7f"),
+            (128, "8000"),
+            (-128, "8080"),
+            (255, "ff00"),
+            (256, "0001"),
+            (-256, "0081"),
+        ];
+        for (n, encoded) in cases {
+            assert_eq!(hex::encode(encode_num(n)), encoded);
+            assert_eq!(decode_num(&hex::decode(encoded).unwrap()), Some(n));
+        }
+        // negative zero
+        assert_eq!(decode_num(&[0x80]), Some(0));
+    }
+}
@@ -0,0 +1,865 @@
+use std::cell::OnceCell;
+use std::fmt::Display;
+use std::io::Write;
+
+use crate::crypto::hash::{hash256, sha256, tagged_hash};
+use crate::encoding::compact_size;
+use crate::script::{Instruction, Opcode, Script, ScriptError};
+use crate::tx::{Transaction, TxOut};
+
+/// Which parts of the transaction an ECDSA signature commits to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcdsaSighashType {
+    All,
+    None,
+    Single,
+    AllPlusAnyoneCanPay,
+    NonePlusAnyoneCanPay,
+    SinglePlusAnyoneCanPay,
+}
+
+impl EcdsaSighashType {
+    pub fn to_u32(self) -> u32 {
+        match self {
+            EcdsaSighashType::All => 0x01,
+            EcdsaSighashType::None => 0x02,
+            EcdsaSighashType::Single => 0x03,
+            EcdsaSighashType::AllPlusAnyoneCanPay => 0x81,
+            EcdsaSighashType::NonePlusAnyoneCanPay => 0x82,
+            EcdsaSighashType::SinglePlusAnyoneCanPay => 0x83,
+        }
+    }
+
+    /// Only the standard values; consensus accepts any byte and masks it
+    pub fn from_u32(n: u32) -> Option<Self> {
+        match n {
+            0x01 => Some(EcdsaSighashType::All),
+            0x02 => Some(EcdsaSighashType::None),
+            0x03 => Some(EcdsaSighashType::Single),
+            0x81 => Some(EcdsaSighashType::AllPlusAnyoneCanPay),
+            0x82 => Some(EcdsaSighashType::NonePlusAnyoneCanPay),
+            0x83 => Some(EcdsaSighashType::SinglePlusAnyoneCanPay),
+            _ => None,
+        }
+    }
+
+    pub fn anyone_can_pay(self) -> bool {
+        self.to_u32() & 0x80 != 0
+    }
+
+    fn is_single(self) -> bool {
+        self.to_u32() & 0x1f == 0x03
+    }
+
+    fn is_none(self) -> bool {
+        self.to_u32() & 0x1f == 0x02
+    }
+}
+
+/// The BIP-341 hash types. `Default` commits to the same data as `All` but
+/// lets the signature omit the hash type byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapSighashType {
+    Default,
+    All,
+    None,
+    Single,
+    AllPlusAnyoneCanPay,
+    NonePlusAnyoneCanPay,
+    SinglePlusAnyoneCanPay,
+}
+
+impl TapSighashType {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            TapSighashType::Default => 0x00,
+            TapSighashType::All => 0x01,
+            TapSighashType::None => 0x02,
+            TapSighashType::Single => 0x03,
+            TapSighashType::AllPlusAnyoneCanPay => 0x81,
+            TapSighashType::NonePlusAnyoneCanPay => 0x82,
+            TapSighashType::SinglePlusAnyoneCanPay => 0x83,
+        }
+    }
+
+    /// Unlike ECDSA, any other value makes the signature invalid
+    pub fn from_u8(n: u8) -> Option<Self> {
+        match n {
+            0x00 => Some(TapSighashType::Default),
+            0x01 => Some(TapSighashType::All),
+            0x02 => Some(TapSighashType::None),
+            0x03 => Some(TapSighashType::Single),
+            0x81 => Some(TapSighashType::AllPlusAnyoneCanPay),
+            0x82 => Some(TapSighashType::NonePlusAnyoneCanPay),
+            0x83 => Some(TapSighashType::SinglePlusAnyoneCanPay),
+            _ => None,
+        }
+    }
+
+    pub fn anyone_can_pay(self) -> bool {
+        self.to_u8() & 0x80 != 0
+    }
+}
+
+/// The outputs being spent. Taproot signatures commit to every spent
+/// amount and script, except with ANYONECANPAY where only the signer's own
+/// input matters
+#[derive(Debug, Clone, Copy)]
+pub enum Prevouts<'p> {
+    All(&'p [TxOut]),
+    /// The input index and the output it spends
+    One(usize, &'p TxOut),
+}
+
+impl<'p> Prevouts<'p> {
+    fn get(&self, index: usize) -> Option<&'p TxOut> {
+        match *self {
+            Prevouts::All(prevouts) => prevouts.get(index),
+            Prevouts::One(i, prevout) if i == index => Some(prevout),
+            Prevouts::One(..) => None,
+        }
+    }
+}
+
+/// The script being executed in a script-path spend
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScriptPath {
+    pub leaf_hash: [u8; 32],
+    /// Opcode position of the last executed OP_CODESEPARATOR, 0xffffffff if none
+    pub code_separator_pos: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SighashError {
+    InputOutOfRange {
+        index: usize,
+        inputs: usize,
+    },
+    /// p2wpkh_signature_hash was given something other than a v0 20-byte program
+    NotP2wpkh,
+    /// Without ANYONECANPAY every spent output has to be supplied
+    PrevoutsRequired {
+        got: usize,
+        inputs: usize,
+    },
+    /// SIGHASH_SINGLE on an input with no output at the same index
+    SingleWithoutOutput(usize),
+    /// Annexes are marked by a leading 0x50 byte
+    InvalidAnnex,
+}
+
+impl Display for SighashError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SighashError::InputOutOfRange { index, inputs } => {
+                write!(
+                    f,
+                    "input {} out of range for a transaction with {} inputs",
+                    index, inputs
+                )
+            }
+            SighashError::NotP2wpkh => write!(f, "script is not a p2wpkh output"),
+            SighashError::PrevoutsRequired { got, inputs } => {
+                write!(f, "{} prevouts supplied for {} inputs", got, inputs)
+            }
+            SighashError::SingleWithoutOutput(i) => write!(f, "SIGHASH_SINGLE on input {} with no matching output", i),
+            SighashError::InvalidAnnex => write!(f, "annex must start with 0x50"),
+        }
+    }
+}
+
+impl std::error::Error for SighashError {}
+
+/// The script code with every OP_CODESEPARATOR removed, keeping pushes
+/// byte-for-byte (a separator byte inside pushed data isn't an opcode)
+fn remove_code_separators(script: &Script) -> Script {
+    let mut res = Vec::with_capacity(script.len());
+    for instruction in script.instructions() {
+        match instruction {
+            Ok(Instruction::Op(Opcode::CodeSeparator)) => {}
+            Ok(Instruction::Op(op)) => res.push(op.to_u8()),
+            Ok(Instruction::Push(op, data)) => {
+                res.push(op.to_u8());
+                match op {
+                    Opcode::PushData1 => res.push(data.len() as u8),
+                    Opcode::PushData2 => res.extend_from_slice(&(data.len() as u16).to_le_bytes()),
+                    Opcode::PushData4 => res.extend_from_slice(&(data.len() as u32).to_le_bytes()),
+                    _ => {}
+                }
+                res.extend_from_slice(data);
+            }
+            Err(ScriptError::TruncatedPush { offset }) => res.extend_from_slice(&script.as_bytes()[offset..]),
+        }
+    }
+    Script::from_bytes(res)
+}
+
+/// The BIP-143 hashes shared by every input of a transaction
+#[derive(Debug, Clone)]
+struct SegwitCache {
+    prevouts: [u8; 32],
+    sequences: [u8; 32],
+    outputs: [u8; 32],
+}
+
+/// The BIP-341 single-SHA256 hashes shared by every input
+#[derive(Debug, Clone)]
+struct TaprootCache {
+    prevouts: [u8; 32],
+    amounts: [u8; 32],
+    script_pubkeys: [u8; 32],
+    sequences: [u8; 32],
+    outputs: [u8; 32],
+}
+
+/// Computes signature hashes for the inputs of one transaction. The parts
+/// that don't depend on the input are hashed once, on first use, which
+/// keeps signing all n inputs O(n) instead of the legacy O(n^2)
+#[derive(Debug)]
+pub struct SighashCache<'a> {
+    tx: &'a Transaction,
+    segwit: OnceCell<SegwitCache>,
+    taproot: OnceCell<TaprootCache>,
+}
+
+impl<'a> SighashCache<'a> {
+    pub fn new(tx: &'a Transaction) -> Self {
+        Self {
+            tx,
+            segwit: OnceCell::new(),
+            taproot: OnceCell::new(),
+        }
+    }
+
+    pub fn transaction(&self) -> &'a Transaction {
+        self.tx
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), SighashError> {
+        if index >= self.tx.inputs.len() {
+            return Err(SighashError::InputOutOfRange {
+                index,
+                inputs: self.tx.inputs.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The original signature hash, used by every pre-segwit input. The
+    /// transaction is copied with all scripts blanked except this input's,
+    /// which becomes `script_code`, then trimmed according to the hash type.
+    /// `sighash_type` is the raw byte from the signature, since consensus
+    /// accepts non-standard values
+    pub fn legacy_signature_hash(
+        &self,
+        index: usize,
+        script_code: &Script,
+        sighash_type: u32,
+    ) -> Result<[u8; 32], SighashError> {
+        self.check_index(index)?;
+        let base_type = sighash_type & 0x1f;
+        let anyone_can_pay = sighash_type & 0x80 != 0;
+
+        // SIGHASH_SINGLE without a matching output signs the number 1, a bug
+        // kept for consensus compatibility
+        if base_type == 0x03 && index >= self.tx.outputs.len() {
+            let mut one = [0_u8; 32];
+            one[0] = 1;
+            return Ok(one);
+        }
+
+        let mut tx = self.tx.clone();
+        for (i, input) in tx.inputs.iter_mut().enumerate() {
+            input.witness.clear();
+            input.script_sig = if i == index {
+                remove_code_separators(script_code)
+            } else {
+                Script::new()
+            };
+            // NONE and SINGLE let other signers update their sequence numbers
+            if i != index && (base_type == 0x02 || base_type == 0x03) {
+                input.sequence = 0;
+            }
+        }
+        match base_type {
+            0x02 => tx.outputs.clear(),
+            0x03 => {
+                tx.outputs.truncate(index + 1);
+                for output in &mut tx.outputs[..index] {
+                    output.amount = u64::MAX;
+                    output.script_pubkey = Script::new();
+                }
+            }
+            _ => {}
+        }
+        if anyone_can_pay {
+            tx.inputs = vec![tx.inputs.swap_remove(index)];
+        }
+
+        let mut preimage = tx.serialize_legacy();
+        preimage.extend_from_slice(&sighash_type.to_le_bytes());
+        Ok(hash256(&preimage))
+    }
+
+    fn segwit_cache(&self) -> &SegwitCache {
+        self.segwit.get_or_init(|| {
+            let mut prevouts = Vec::new();
+            let mut sequences = Vec::new();
+            for input in &self.tx.inputs {
+                input.previous_output.write(&mut prevouts).unwrap();
+                sequences.extend_from_slice(&input.sequence.to_le_bytes());
+            }
+            let mut outputs = Vec::new();
+            for output in &self.tx.outputs {
+                output.write(&mut outputs).unwrap();
+            }
+            SegwitCache {
+                prevouts: hash256(&prevouts),
+                sequences: hash256(&sequences),
+                outputs: hash256(&outputs),
+            }
+        })
+    }
+
+    fn taproot_cache(&self, prevouts: &[TxOut]) -> &TaprootCache {
+        self.taproot.get_or_init(|| {
+            let mut outpoints = Vec::new();
+            let mut sequences = Vec::new();
+            for input in &self.tx.inputs {
+                input.previous_output.write(&mut outpoints).unwrap();
+                sequences.extend_from_slice(&input.sequence.to_le_bytes());
+            }
+            let mut amounts = Vec::new();
+            let mut script_pubkeys = Vec::new();
+            for prevout in prevouts {
+                amounts.extend_from_slice(&prevout.amount.to_le_bytes());
+                prevout.script_pubkey.write(&mut script_pubkeys).unwrap();
+            }
+            let mut outputs = Vec::new();
+            for output in &self.tx.outputs {
+                output.write(&mut outputs).unwrap();
+            }
+            TaprootCache {
+                prevouts: sha256(&outpoints),
+                amounts: sha256(&amounts),
+                script_pubkeys: sha256(&script_pubkeys),
+                sequences: sha256(&sequences),
+                outputs: sha256(&outputs),
+            }
+        })
+    }
+
+    /// The BIP-341 signature message hash for input `index`. `script_path` is
+    /// `None` for key-path spends; for script-path spends it switches on the
+    /// BIP-342 extension (ext_flag = 1). The shared hashes are cached from
+    /// the first full prevouts list seen, so pass the same one for every input
+    pub fn taproot_signature_hash(
+        &self,
+        index: usize,
+        prevouts: &Prevouts,
+        annex: Option<&[u8]>,
+        script_path: Option<ScriptPath>,
+        sighash_type: TapSighashType,
+    ) -> Result<[u8; 32], SighashError> {
+        self.check_index(index)?;
+        let input = &self.tx.inputs[index];
+        let inputs = self.tx.inputs.len();
+        let base_type = sighash_type.to_u8() & 0x03;
+        if annex.is_some_and(|a| a.first() != Some(&0x50)) {
+            return Err(SighashError::InvalidAnnex);
+        }
+
+        // epoch 0
+        let mut msg = vec![0x00, sighash_type.to_u8()];
+        msg.extend_from_slice(&self.tx.version.to_le_bytes());
+        msg.extend_from_slice(&self.tx.locktime.to_le_bytes());
+
+        if !sighash_type.anyone_can_pay() {
+            let all = match *prevouts {
+                Prevouts::All(all) if all.len() == inputs => all,
+                Prevouts::All(all) => return Err(SighashError::PrevoutsRequired { got: all.len(), inputs }),
+                Prevouts::One(..) => return Err(SighashError::PrevoutsRequired { got: 1, inputs }),
+            };
+            let cache = self.taproot_cache(all);
+            msg.extend_from_slice(&cache.prevouts);
+            msg.extend_from_slice(&cache.amounts);
+            msg.extend_from_slice(&cache.script_pubkeys);
+            msg.extend_from_slice(&cache.sequences);
+        }
+        // NONE and SINGLE skip the output commitment here; Default and ALL
+        // both have low bits 0 or 1
+        if base_type != 0x02 && base_type != 0x03 {
+            let outputs = match self.taproot.get() {
+                Some(cache) => cache.outputs,
+                None => {
+                    let mut outputs = Vec::new();
+                    for output in &self.tx.outputs {
+                        output.write(&mut outputs).unwrap();
+                    }
+                    sha256(&outputs)
+                }
+            };
+            msg.extend_from_slice(&outputs);
+        }
+
+        let spend_type = (script_path.is_some() as u8) * 2 + annex.is_some() as u8;
+        msg.push(spend_type);
+
+        if sighash_type.anyone_can_pay() {
+            let prevout = prevouts
+                .get(index)
+                .ok_or(SighashError::PrevoutsRequired { got: 0, inputs })?;
+            input.previous_output.write(&mut msg).unwrap();
+            msg.extend_from_slice(&prevout.amount.to_le_bytes());
+            prevout.script_pubkey.write(&mut msg).unwrap();
+            msg.extend_from_slice(&input.sequence.to_le_bytes());
+        } else {
+            msg.extend_from_slice(&(index as u32).to_le_bytes());
+        }
+
+        if let Some(annex) = annex {
+            let mut data = Vec::with_capacity(annex.len() + 9);
+            compact_size::write(&mut data, annex.len() as u64).unwrap();
+            data.extend_from_slice(annex);
+            msg.extend_from_slice(&sha256(&data));
+        }
+
+        if base_type == 0x03 {
+            let output = self
+                .tx
+                .outputs
+                .get(index)
+                .ok_or(SighashError::SingleWithoutOutput(index))?;
+            let mut data = Vec::new();
+            output.write(&mut data).unwrap();
+            msg.extend_from_slice(&sha256(&data));
+        }
+
+        if let Some(path) = script_path {
+            msg.extend_from_slice(&path.leaf_hash);
+            // key_version 0
+            msg.push(0x00);
+            msg.extend_from_slice(&path.code_separator_pos.to_le_bytes());
+        }
+
+        Ok(tagged_hash("TapSighash", &msg))
+    }
+
+    /// The BIP-143 digest for input `index`, which spends `value` satoshis
+    /// locked by `script_code`
+    pub fn segwit_v0_signature_hash(
+        &self,
+        index: usize,
+        script_code: &Script,
+        value: u64,
+        sighash_type: EcdsaSighashType,
+    ) -> Result<[u8; 32], SighashError> {
+        self.check_index(index)?;
+        let cache = self.segwit_cache();
+        let zero = [0_u8; 32];
+        let input = &self.tx.inputs[index];
+
+        let hash_prevouts = if sighash_type.anyone_can_pay() {
+            &zero
+        } else {
+            &cache.prevouts
+        };
+        let hash_sequence = if sighash_type.anyone_can_pay() || sighash_type.is_single() || sighash_type.is_none() {
+            zero
+        } else {
+            cache.sequences
+        };
+        let hash_outputs = if !sighash_type.is_single() && !sighash_type.is_none() {
+            cache.outputs
+        } else if sighash_type.is_single() && index < self.tx.outputs.len() {
+            // only the output at the same index
+            let mut output = Vec::new();
+            self.tx.outputs[index].write(&mut output).unwrap();
+            hash256(&output)
+        } else {
+            zero
+        };
+
+        let mut preimage = Vec::new();
+        preimage.write_all(&self.tx.version.to_le_bytes()).unwrap();
+        preimage.write_all(hash_prevouts).unwrap();
+        preimage.write_all(&hash_sequence).unwrap();
+        input.previous_output.write(&mut preimage).unwrap();
+        script_code.write(&mut preimage).unwrap();
+        preimage.write_all(&value.to_le_bytes()).unwrap();
+        preimage.write_all(&input.sequence.to_le_bytes()).unwrap();
+        preimage.write_all(&hash_outputs).unwrap();
+        preimage.write_all(&self.tx.locktime.to_le_bytes()).unwrap();
+        preimage.write_all(&sighash_type.to_u32().to_le_bytes()).unwrap();
+        Ok(hash256(&preimage))
+    }
+
+    /// For P2WPKH (native or nested in P2SH) the script code is the P2PKH
+    /// script of the program's key hash
+    pub fn p2wpkh_signature_hash(
+        &self,
+        index: usize,
+        script_pubkey: &Script,
+        value: u64,
+        sighash_type: EcdsaSighashType,
+    ) -> Result<[u8; 32], SighashError> {
+        let pubkey_hash: &[u8; 20] = match script_pubkey.witness_program() {
+            Some((0, program)) => program.try_into().map_err(|_| SighashError::NotP2wpkh)?,
+            _ => return Err(SighashError::NotP2wpkh),
+        };
+        self.segwit_v0_signature_hash(index, &Script::p2pkh(pubkey_hash), value, sighash_type)
+    }
+
+    /// For P2WSH the script code is the witness script itself
+    pub fn p2wsh_signature_hash(
+        &self,
+        index: usize,
+        witness_script: &Script,
+        value: u64,
+        sighash_type: EcdsaSighashType,
+    ) -> Result<[u8; 32], SighashError> {
+        self.segwit_v0_signature_hash(index, witness_script, value, sighash_type)
+    }
+}
+
+#[cfg(test)]
+mod sighash_tests {
+    use super::*;
+    use crate::taproot::{tap_leaf_hash, LEAF_VERSION_TAPSCRIPT};
+
+    #[test]
+    fn test_legacy_sighash() {
+        // a one-input spend of a p2pkh output, and a two-input variant to
+        // exercise the hash types (expected values from rust-bitcoin)
+        let tx = Transaction::from_hex(
+            "010000000199a24308080ab26e6fb65c4eccfadf76749bb5bfa8cb08f291320b3c21e56f0d0d00000000\
+             ffffffff02408af701000000001976a914507b27411ccf7f16f10297de6cef3f291623eddf88ac809698\
+             00000000001976a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac00000000",
+        )
+        .unwrap();
+        let script_code = Script::from_hex("76a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac").unwrap();
+        let cache = SighashCache::new(&tx);
+        assert_eq!(
+            hex::encode(cache.legacy_signature_hash(0, &script_code, 0x01).unwrap()),
+            "76ffe386f6dc1486f966f9881dc7f1af2237586f72860766929ebb916de6a896"
+        );
+
+        let mut two = tx.clone();
+        two.inputs.push(tx.inputs[0].clone());
+        two.inputs[1].previous_output.vout = 1;
+        two.outputs.truncate(1);
+        let cache = SighashCache::new(&two);
+        let one = "0100000000000000000000000000000000000000000000000000000000000000";
+        for (sighash_type, expected) in [
+            (0x01, "25633ec85327b7d2260641088d9fd42dfb6bd200e58bea6fa6d82e252202d4be"),
+            (0x02, "b0d8942a2642d601b054727983826830ba922217463ffeb35ffa8a2e50fce324"),
+            (0x03, one),
+            (0x81, "2f2adb652c6b05d8c439a4bd1130fbc942842fc6d754364647012cce4fe31470"),
+            (0x82, "20d33ab005d53bdbfd66cd52dcd527ad5337180e32e43c21d84523006c407faf"),
+            (0x83, one),
+        ] {
+            assert_eq!(
+                hex::encode(cache.legacy_signature_hash(1, &script_code, sighash_type).unwrap()),
+                expected,
+                "{:#x}",
+                sighash_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove_code_separators() {
+        let script = Script::from_hex("ab76ab4c01ab0201abac").unwrap();
+        assert_eq!(remove_code_separators(&script).to_hex(), "764c01ab0201abac");
+        let truncated = Script::from_hex("ab4c05ab").unwrap();
+        assert_eq!(remove_code_separators(&truncated).to_hex(), "4c05ab");
+    }
+
+    // test vectors from BIP-143
+
+    #[test]
+    fn test_native_p2wpkh() {
+        let tx = Transaction::from_hex(
+            "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f000000\
+             0000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a01000000\
+             00ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093\
+             510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+        )
+        .unwrap();
+        let script_pubkey = Script::from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap();
+
+        let cache = SighashCache::new(&tx);
+        let sighash = cache
+            .p2wpkh_signature_hash(1, &script_pubkey, 600_000_000, EcdsaSighashType::All)
+            .unwrap();
+        assert_eq!(
+            hex::encode(sighash),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+
+        let segwit = cache.segwit_cache();
+        assert_eq!(
+            hex::encode(segwit.prevouts),
+            "96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37"
+        );
+        assert_eq!(
+            hex::encode(segwit.sequences),
+            "52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b"
+        );
+        assert_eq!(
+            hex::encode(segwit.outputs),
+            "863ef3e1a92afbfdb97f31ad0fc7683ee943e9abcf2501590ff8f6551f47e5e5"
+        );
+
+        assert_eq!(
+            cache.p2wpkh_signature_hash(2, &script_pubkey, 0, EcdsaSighashType::All),
+            Err(SighashError::InputOutOfRange { index: 2, inputs: 2 })
+        );
+        assert_eq!(
+            cache.p2wpkh_signature_hash(1, &Script::p2pkh(&[0; 20]), 0, EcdsaSighashType::All),
+            Err(SighashError::NotP2wpkh)
+        );
+    }
+
+    #[test]
+    fn test_p2sh_p2wpkh() {
+        let tx = Transaction::from_hex(
+            "0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000\
+             0000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac00\
+             08af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000",
+        )
+        .unwrap();
+        let redeem_script = Script::from_hex("001479091972186c449eb1ded22b78e40d009bdf0089").unwrap();
+
+        let cache = SighashCache::new(&tx);
+        let sighash = cache
+            .p2wpkh_signature_hash(0, &redeem_script, 1_000_000_000, EcdsaSighashType::All)
+            .unwrap();
+        assert_eq!(
+            hex::encode(sighash),
+            "64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"
+        );
+    }
+
+    #[test]
+    fn test_p2sh_p2wsh_all_types() {
+        let tx = Transaction::from_hex(
+            "010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000\
+             ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f\
+             05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000",
+        )
+        .unwrap();
+        // 6-of-6 multisig
+        let witness_script = Script::from_hex(
+            "56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28\
+             bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b\
+             9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58\
+             c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b1486\
+             2c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b\
+             56ae",
+        )
+        .unwrap();
+
+        let cache = SighashCache::new(&tx);
+        let cases = [
+            (
+                EcdsaSighashType::All,
+                "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c",
+            ),
+            (
+                EcdsaSighashType::None,
+                "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36",
+            ),
+            (
+                EcdsaSighashType::Single,
+                "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea",
+            ),
+            (
+                EcdsaSighashType::AllPlusAnyoneCanPay,
+                "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e",
+            ),
+            (
+                EcdsaSighashType::NonePlusAnyoneCanPay,
+                "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a",
+            ),
+            (
+                EcdsaSighashType::SinglePlusAnyoneCanPay,
+                "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b",
+            ),
+        ];
+        for (sighash_type, expected) in cases {
+            let sighash = cache
+                .p2wsh_signature_hash(0, &witness_script, 987_654_321, sighash_type)
+                .unwrap();
+            assert_eq!(hex::encode(sighash), expected, "{:?}", sighash_type);
+        }
+    }
+
+    fn taproot_case(
+        tx: &str,
+        prevouts: &str,
+        index: usize,
+        sighash_type: TapSighashType,
+        annex: Option<&str>,
+        script_path: Option<ScriptPath>,
+    ) -> Result<String, SighashError> {
+        let tx = Transaction::from_hex(tx).unwrap();
+        let bytes = hex::decode(prevouts).unwrap();
+        let mut reader = bytes.as_slice();
+        let count = compact_size::read(&mut reader).unwrap();
+        let prevouts: Vec<TxOut> = (0..count).map(|_| TxOut::parse(&mut reader).unwrap()).collect();
+        let annex = annex.map(|a| hex::decode(a).unwrap());
+
+        let cache = SighashCache::new(&tx);
+        let sighash = cache.taproot_signature_hash(
+            index,
+            &Prevouts::All(&prevouts),
+            annex.as_deref(),
+            script_path,
+            sighash_type,
+        )?;
+        Ok(hex::encode(sighash))
+    }
+
+    // vectors from Bitcoin Core's functional test framework
+
+    #[test]
+    fn test_taproot_key_path() {
+        let cases = [
+            (
+                "020000000164eb050a5e3da0c2a65e4786f26d753b7bc69691fabccafb11f7acef36641f1846010000003101b2b404392a22000000000017a9147f2bde86fe78bf68a0544a4f290e12f0b7e0a08c87580200000000000017a91425d11723074ecfb96a0a83c3956bfaf362ae0c908758020000000000001600147e20f938993641de67bb0cdd71682aa34c4d29ad5802000000000000160014c64984dc8761acfa99418bd6bedc79b9287d652d72000000",
+                "01365724000000000023542156b39dab4f8f3508e0432cfb41fab110170acaa2d4c42539cb90a4dc7c093bc500",
+                0,
+                TapSighashType::Default,
+                "33ca0ebfb4a945eeee9569fc0f5040221275f88690b7f8592ada88ce3bdf6703",
+            ),
+            (
+                "0200000002fff49be59befe7566050737910f6ccdc5e749c7f8860ddc140386463d88c5ad0f3000000002cf68eb4a3d67f9d4c079249f7e4f27b8854815cb1ed13842d4fbf395f9e217fd605ee24090100000065235d9203f458520000000000160014b6d48333bb13b4c644e57c43a9a26df3a44b785e58020000000000001976a914eea9461a9e1e3f765d3af3e726162e0229fe3eb688ac58020000000000001976a9143a8869c9f2b5ea1d4ff3aeeb6a8fb2fffb1ad5fe88ac0ad7125c",
+                "02591f220000000000225120f25ad35583ea31998d968871d7de1abd2a52f6fe4178b54ea158274806ff4ece48fb310000000000225120f25ad35583ea31998d968871d7de1abd2a52f6fe4178b54ea158274806ff4ece",
+                1,
+                TapSighashType::All,
+                "626ab955d58c9a8a600a0c580549d06dc7da4e802eb2a531f62a588e430967a8",
+            ),
+            (
+                "0200000001350005f65aa830ced2079df348e2d8c2bdb4f10e2dde6a161d8a07b40d1ad87dae000000001611d0d603d9dc0e000000000017a914459b6d7d6bbb4d8837b4bf7e9a4556f952da2f5c8758020000000000001976a9141dd70e1299ffc2d5b51f6f87de9dfe9398c33cbb88ac58020000000000001976a9141dd70e1299ffc2d5b51f6f87de9dfe9398c33cbb88aca71c1f4f",
+                "01c4811000000000002251201bf9297d0a2968ae6693aadd0fa514717afefd218087a239afb7418e2d22e65c",
+                0,
+                TapSighashType::AllPlusAnyoneCanPay,
+                "dfa9437f9c9a1d1f9af271f79f2f5482f287cdb0d2e03fa92c8a9b216cc6061c",
+            ),
+            (
+                "020000000185bed1a6da2bffbd60ec681a1bfb71c5111d6395b99b3f8b2bf90167111bcb18f5010000007c83ace802ded24a00000000001600142c4698f9f7a773866879755aa78c516fb332af8e5802000000000000160014d38639dfbac4259323b98a472405db0c461b31fa61073747",
+                "0144c84d0000000000225120e3f2107989c88e67296ab2faca930efa2e3a5bd3ff0904835a11c9e807458621",
+                0,
+                TapSighashType::None,
+                "3129de36a5d05fff97ffca31eb75fcccbbbc27b3147a7a36a9e4b45d8b625067",
+            ),
+            (
+                "eb93dbb901028c8515589dac980b6e7f8e4088b77ed866ca0d6d210a7218b6fd0f6b22dd6d7300000000eb4740a9047efc0e0000000000160014913da2128d8fcf292b3691db0e187414aa1783825802000000000000160014913da2128d8fcf292b3691db0e187414aa178382580200000000000017a9143dd27f01c6f7ef9bb9159937b17f17065ed01a0c875802000000000000160014d7630e19df70ada9905ede1722b800c0005f246641000000",
+                "013fed110000000000225120eb536ae8c33580290630fc495046e998086a64f8f33b93b07967d9029b265c55",
+                0,
+                TapSighashType::NonePlusAnyoneCanPay,
+                "2441e8b0e063a2083ee790f14f2045022f07258ddde5ee01de543c9e789d80ae",
+            ),
+            (
+                "02000000017836b409a5fed32211407e44b971591f2032053f14701fb5b3a30c0ff382f2cc9c0100000061ac55f60288fb5600000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ac58020000000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ace4000000",
+                "01efa558000000000022512007071ea3dc7e331b0687d0193d1e6d6ed10e645ef36f10ef8831d5e522ac9e80",
+                0,
+                TapSighashType::Single,
+                "30239345177cadd0e3ea413d49803580abb6cb27971b481b7788a78d35117a88",
+            ),
+            (
+                "0100000001aa6deae89d5e0aaca58714fc76ef6f3c8284224888089232d4e663843ed3ab3eae010000008b6657a60450cb4c0000000000160014a3d42b5413ef0c0701c4702f3cd7d4df222c147058020000000000001976a91430b4ed8723a4ee8992aa2c8814cfe5c3ad0ab9d988ac5802000000000000160014365b1166a6ed0a5e8e9dff17a6d00bbb43454bc758020000000000001976a914bc98c51a84fe7fad5dc380eb8b39586eff47241688ac4f313247",
+                "0107af4e00000000002251202c36d243dfc06cb56a248e62df27ecba7417307511a81ae61aa41c597a929c69",
+                0,
+                TapSighashType::SinglePlusAnyoneCanPay,
+                "bf9c83f26c6dd16449e4921f813f551c4218e86f2ec906ca8611175b41b566df",
+            ),
+        ];
+        for (tx, prevouts, index, sighash_type, expected) in cases {
+            assert_eq!(
+                taproot_case(tx, prevouts, index, sighash_type, None, None).unwrap(),
+                expected,
+                "{:?}",
+                sighash_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_taproot_annex_and_script_path() {
+        assert_eq!(
+            taproot_case(
+                "0200000001df8123752e8f37d132c4e9f1ff7e4f9b986ade9211267e9ebd5fd22a5e718dec6d01000000ce4023b903cb7b23000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787580200000000000017a914afd0d512a2c5c2b40e25669e9cc460303c325b8b87580200000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787f6020000",
+                "01ea49260000000000225120ab5e9800806bf18cb246edcf5fe63441208fe955a4b5a35bbff65f5db622a010",
+                0,
+                TapSighashType::SinglePlusAnyoneCanPay,
+                Some("507b979802e62d397acb29f56743a791894b99372872fc5af06a4f6e8d242d0615cda53062bb20e6ec79756fe39183f0c128adfe85559a8fa042b042c018aa8010143799e44f0893c40e1e"),
+                None,
+            )
+            .unwrap(),
+            "3b003000add359a364a156e73e02846782a59d0d95ca8c4638aaad99f2ef915c"
+        );
+
+        let script =
+            Script::from_hex("20cc4e1107aea1d170c5ff5b6817e1303010049724fb3caa7941792ea9d29b3e2bacab").unwrap();
+        let leaf_hash = tap_leaf_hash(LEAF_VERSION_TAPSCRIPT, &script);
+        assert_eq!(
+            hex::encode(leaf_hash),
+            "15a2530514e399f8b5cf0b3d3112cf5b289eaa3e308ba2071b58392fdc6da68a"
+        );
+        assert_eq!(
+            taproot_case(
+                "020000000189fc651483f9296b906455dd939813bf086b1bbe7c77635e157c8e14ae29062195010000004445b5c7044561320000000000160014331414dbdada7fb578f700f38fb69995fc9b5ab958020000000000001976a914268db0a8104cc6d8afd91233cc8b3d1ace8ac3ef88ac580200000000000017a914ec00dcb368d6a693e11986d265f659d2f59e8be2875802000000000000160014c715799a49a0bae3956df9c17cb4440a673ac0df6f010000",
+                "011bec34000000000022512028055142ea437db73382e991861446040b61dd2185c4891d7daf6893d79f7182",
+                0,
+                TapSighashType::All,
+                None,
+                Some(ScriptPath { leaf_hash, code_separator_pos: 0xffffffff }),
+            )
+            .unwrap(),
+            "d66de5274a60400c7b08c86ba6b7f198f40660079edf53aca89d2a9501317f2e"
+        );
+    }
+
+    #[test]
+    fn test_taproot_errors() {
+        let tx = "02000000017836b409a5fed32211407e44b971591f2032053f14701fb5b3a30c0ff382f2cc9c0100000061ac55f60288fb5600000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ac58020000000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ace4000000";
+        let prevouts = "01efa558000000000022512007071ea3dc7e331b0687d0193d1e6d6ed10e645ef36f10ef8831d5e522ac9e80";
+        assert_eq!(
+            taproot_case(tx, prevouts, 0, TapSighashType::All, Some("00"), None),
+            Err(SighashError::InvalidAnnex)
+        );
+        assert_eq!(
+            taproot_case(tx, "00", 0, TapSighashType::All, None, None),
+            Err(SighashError::PrevoutsRequired { got: 0, inputs: 1 })
+        );
+
+        let mut tx = Transaction::from_hex(tx).unwrap();
+        tx.outputs.clear();
+        let prevout = TxOut {
+            amount: 1,
+            script_pubkey: Script::new(),
+        };
+        let cache = SighashCache::new(&tx);
+        assert_eq!(
+            cache.taproot_signature_hash(
+                0,
+                &Prevouts::One(0, &prevout),
+                None,
+                None,
+                TapSighashType::SinglePlusAnyoneCanPay
+            ),
+            Err(SighashError::SingleWithoutOutput(0))
+        );
+        assert!(cache
+            .taproot_signature_hash(
+                0,
+                &Prevouts::One(0, &prevout),
+                None,
+                None,
+                TapSighashType::NonePlusAnyoneCanPay
+            )
+            .is_ok());
+    }
+}
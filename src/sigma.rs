@@ -0,0 +1,320 @@
+use std::fmt::Display;
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::Zero;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::rng::random_scalar;
+use crate::crypto::transcript::Transcript;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::ecc::{order, PrivateKey, PublicKey};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SigmaError {
+    /// An OR-proof needs at least one branch
+    NoStatements,
+    /// The index of the known branch is past the end of the statements
+    IndexOutOfRange { index: usize, len: usize },
+    /// The secret doesn't open the statement at the given index
+    WrongWitness,
+}
+
+impl Display for SigmaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SigmaError::NoStatements => write!(f, "no statements to prove"),
+            SigmaError::IndexOutOfRange { index, len } => {
+                write!(f, "index {} out of range for {} statements", index, len)
+            }
+            SigmaError::WrongWitness => write!(f, "secret is not the discrete log of the statement"),
+        }
+    }
+}
+
+impl std::error::Error for SigmaError {}
+
+/// The claim P = x G, the relation Schnorr's sigma protocol proves
+/// knowledge of x for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub g: PublicKey,
+    pub p: PublicKey,
+}
+
+impl Statement {
+    pub fn new(g: &PublicKey, p: &PublicKey) -> Self {
+        Self {
+            g: g.clone(),
+            p: p.clone(),
+        }
+    }
+
+    /// R = s G - c P, the commitment that makes (c, s) an accepting
+    /// transcript. Any challenge can be answered this way without x, which
+    /// is what lets an OR-proof fake all but one branch
+    fn simulate(&self, c: &BigUint, s: &BigUint) -> Point {
+        let n = order();
+        let neg_c = BigInt::from((&n - c % &n) % &n);
+        BigInt::from(s.clone()) * self.g.point().clone() + neg_c * self.p.point().clone()
+    }
+
+    fn holds(&self, x: &PrivateKey) -> bool {
+        BigInt::from(x.secret().clone()) * self.g.point().clone() == *self.p.point()
+    }
+}
+
+/// Proof of knowing the discrete log of one of several statements without
+/// revealing which (Cramer–Damgård–Schoenmakers). The prover simulates
+/// every branch but the one it knows, picking their challenges itself,
+/// and the Fiat–Shamir challenge c fixes only the sum: the remaining
+/// branch gets c minus the others and is answered honestly
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrProof {
+    pub challenges: Vec<BigUint>,
+    pub responses: Vec<BigUint>,
+}
+
+impl OrProof {
+    /// `x` must be the discrete log of `statements[index]`. `transcript`
+    /// may already hold context the proof should be bound to
+    pub fn prove(
+        x: &PrivateKey,
+        statements: &[Statement],
+        index: usize,
+        transcript: Transcript,
+    ) -> Result<Self, SigmaError> {
+        Self::prove_with_rng(x, statements, index, transcript, &mut OsRng)
+    }
+
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        x: &PrivateKey,
+        statements: &[Statement],
+        index: usize,
+        mut transcript: Transcript,
+        rng: &mut R,
+    ) -> Result<Self, SigmaError> {
+        if statements.is_empty() {
+            return Err(SigmaError::NoStatements);
+        }
+        if index >= statements.len() {
+            return Err(SigmaError::IndexOutOfRange {
+                index,
+                len: statements.len(),
+            });
+        }
+        if !statements[index].holds(x) {
+            return Err(SigmaError::WrongWitness);
+        }
+        let n = order();
+        let k = random_scalar(rng);
+        let mut challenges = vec![BigUint::zero(); statements.len()];
+        let mut responses = vec![BigUint::zero(); statements.len()];
+        let mut commitments = Vec::with_capacity(statements.len());
+        for (i, statement) in statements.iter().enumerate() {
+            if i == index {
+                commitments.push(BigInt::from(k.clone()) * statement.g.point().clone());
+                continue;
+            }
+            // a simulated commitment at infinity can't be encoded, so
+            // draw again in that (negligible) case
+            loop {
+                let (c, s) = (random_scalar(rng), random_scalar(rng));
+                let r = statement.simulate(&c, &s);
+                if !r.is_infinity() {
+                    (challenges[i], responses[i]) = (c, s);
+                    commitments.push(r);
+                    break;
+                }
+            }
+        }
+        let c = challenge(&mut transcript, statements, &commitments);
+        let others = challenges.iter().fold(BigUint::zero(), |acc, c_i| acc + c_i);
+        challenges[index] = (c + &n - others % &n) % &n;
+        responses[index] = (k + &challenges[index] * x.secret()) % &n;
+        Ok(Self { challenges, responses })
+    }
+
+    /// Every branch's R_i = s_i G_i - c_i P_i is recomputed, and the
+    /// challenges must add up to the hash of the statements and the R_i
+    pub fn verify(&self, statements: &[Statement], mut transcript: Transcript) -> bool {
+        let n = order();
+        if statements.is_empty()
+            || self.challenges.len() != statements.len()
+            || self.responses.len() != statements.len()
+        {
+            return false;
+        }
+        if self.challenges.iter().chain(&self.responses).any(|v| *v >= n) {
+            return false;
+        }
+        let commitments: Vec<Point> = statements
+            .iter()
+            .zip(self.challenges.iter().zip(&self.responses))
+            .map(|(st, (c, s))| st.simulate(c, s))
+            .collect();
+        if commitments.iter().any(|r| r.is_infinity()) {
+            return false;
+        }
+        let sum = self.challenges.iter().fold(BigUint::zero(), |acc, c_i| acc + c_i) % &n;
+        challenge(&mut transcript, statements, &commitments) == sum
+    }
+}
+
+/// A non-interactive Schnorr proof of knowing log_G(P). As a
+/// proof-of-possession for a key going into MuSig or BLS aggregation, it
+/// rules out rogue keys, which their owner can't know the secret of
+#[derive(Debug, Clone, PartialEq)]
+pub struct DlogProof {
+    /// The base the proof is for. Verifiers that expect a particular one
+    /// must check it themselves
+    pub generator: PublicKey,
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+/// Proves knowledge of `secret` for the key `secret` times `generator`
+pub fn prove_dlog(secret: &PrivateKey, generator: &PublicKey) -> DlogProof {
+    prove_dlog_with_rng(secret, generator, &mut OsRng)
+}
+
+pub fn prove_dlog_with_rng<R: RngCore + CryptoRng>(
+    secret: &PrivateKey,
+    generator: &PublicKey,
+    rng: &mut R,
+) -> DlogProof {
+    let statement = Statement::new(
+        generator,
+        &generator.mul_tweak(secret.secret()).expect("secret is in [1, n)"),
+    );
+    let k = random_scalar(rng);
+    let r = PublicKey::from(BigInt::from(k.clone()) * generator.point().clone()).expect("nonce is in [1, n)");
+    let c = dlog_challenge(&statement, &r);
+    let s = (k + &c * secret.secret()) % order();
+    DlogProof {
+        generator: generator.clone(),
+        c,
+        s,
+    }
+}
+
+/// Whether `proof` shows knowledge of log_G(`public`)
+pub fn verify_dlog(proof: &DlogProof, public: &PublicKey) -> bool {
+    let n = order();
+    if proof.c >= n || proof.s >= n {
+        return false;
+    }
+    let statement = Statement::new(&proof.generator, public);
+    let r = statement.simulate(&proof.c, &proof.s);
+    PublicKey::from(r).is_ok_and(|r| dlog_challenge(&statement, &r) == proof.c)
+}
+
+fn dlog_challenge(statement: &Statement, r: &PublicKey) -> BigUint {
+    let mut transcript = Transcript::new("moneda/dlog");
+    transcript.append_point("G", &statement.g);
+    transcript.append_point("P", &statement.p);
+    transcript.append_point("R", r);
+    transcript.challenge_scalar("c")
+}
+
+fn challenge(transcript: &mut Transcript, statements: &[Statement], commitments: &[Point]) -> BigUint {
+    transcript.append("n", &(statements.len() as u64).to_be_bytes());
+    for (statement, r) in statements.iter().zip(commitments) {
+        transcript.append_point("G", &statement.g);
+        transcript.append_point("P", &statement.p);
+        transcript.append_point(
+            "R",
+            &PublicKey::from(r.clone()).expect("commitments are not the identity"),
+        );
+    }
+    transcript.challenge_scalar("c")
+}
+
+#[cfg(test)]
+mod sigma_tests {
+    use super::*;
+
+    fn key(byte: u8) -> PrivateKey {
+        PrivateKey::from_bytes(&[byte; 32]).unwrap()
+    }
+
+    fn statements() -> Vec<Statement> {
+        let g = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+        (1..=3).map(|i| Statement::new(&g, &key(i).public_key())).collect()
+    }
+
+    #[test]
+    fn test_or_proof() {
+        let statements = statements();
+        for index in 0..3 {
+            let proof =
+                OrProof::prove(&key(index as u8 + 1), &statements, index, Transcript::new("moneda/or")).unwrap();
+            assert!(proof.verify(&statements, Transcript::new("moneda/or")));
+            assert!(!proof.verify(&statements, Transcript::new("moneda/other")));
+            assert!(!proof.verify(&statements[..2], Transcript::new("moneda/or")));
+        }
+
+        // a branch with its own base
+        let h = key(9).public_key();
+        let mut mixed = statements.clone();
+        mixed.push(Statement::new(&h, &h.mul_tweak(key(4).secret()).unwrap()));
+        let proof = OrProof::prove(&key(4), &mixed, 3, Transcript::new("moneda/or")).unwrap();
+        assert!(proof.verify(&mixed, Transcript::new("moneda/or")));
+
+        let mut tampered = proof.clone();
+        tampered.challenges.swap(0, 1);
+        assert!(!tampered.verify(&mixed, Transcript::new("moneda/or")));
+        let mut tampered = proof;
+        tampered.responses[2] = (&tampered.responses[2] + 1_u8) % order();
+        assert!(!tampered.verify(&mixed, Transcript::new("moneda/or")));
+    }
+
+    #[test]
+    fn test_dlog_proof() {
+        let g = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+        let x = key(0x42);
+        let public = x.public_key();
+        let proof = prove_dlog(&x, &g);
+        assert!(verify_dlog(&proof, &public));
+        assert!(!verify_dlog(&proof, &key(0x43).public_key()));
+
+        // a proof against another base doesn't carry over
+        let h = key(9).public_key();
+        let proof_h = prove_dlog(&x, &h);
+        assert!(verify_dlog(&proof_h, &h.mul_tweak(x.secret()).unwrap()));
+        assert!(!verify_dlog(
+            &DlogProof {
+                generator: g.clone(),
+                ..proof_h
+            },
+            &public
+        ));
+
+        let tampered = DlogProof {
+            s: (&proof.s + 1_u8) % order(),
+            ..proof.clone()
+        };
+        assert!(!verify_dlog(&tampered, &public));
+        let out_of_range = DlogProof { c: order(), ..proof };
+        assert!(!verify_dlog(&out_of_range, &public));
+    }
+
+    #[test]
+    fn test_prove_errors() {
+        let statements = statements();
+        let t = || Transcript::new("moneda/or");
+        assert_eq!(OrProof::prove(&key(1), &[], 0, t()), Err(SigmaError::NoStatements));
+        assert_eq!(
+            OrProof::prove(&key(1), &statements, 3, t()),
+            Err(SigmaError::IndexOutOfRange { index: 3, len: 3 })
+        );
+        assert_eq!(
+            OrProof::prove(&key(1), &statements, 1, t()),
+            Err(SigmaError::WrongWitness)
+        );
+        assert_eq!(
+            OrProof::prove(&key(7), &statements, 0, t()),
+            Err(SigmaError::WrongWitness)
+        );
+    }
+}
@@ -0,0 +1,704 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::crypto::hash::hash160;
+use crate::crypto::schnorr::{self, xonly};
+use crate::curves::secp256k1::ecc::{KeyError, PrivateKey};
+use crate::multisig::Multisig;
+use crate::script::{Opcode, Script};
+use crate::sighash::{EcdsaSighashType, Prevouts, ScriptPath, SighashCache, SighashError, TapSighashType};
+use crate::taproot::{tap_leaf_hash, tweak_private_key, tweak_public_key, TaprootSpendInfo, LEAF_VERSION_TAPSCRIPT};
+use crate::trace::trace_span;
+use crate::tx::{Transaction, TxOut};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignError {
+    Sighash(SighashError),
+    /// The output being spent isn't locked to this key
+    KeyMismatch,
+    /// The taproot tweak produced an invalid key
+    Tweak(KeyError),
+    /// The script isn't a tapscript leaf of the output's tree
+    UnknownLeaf,
+}
+
+impl Display for SignError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignError::Sighash(e) => write!(f, "{}", e),
+            SignError::KeyMismatch => write!(f, "prevout script does not pay to this key"),
+            SignError::Tweak(e) => write!(f, "taproot tweak failed: {}", e),
+            SignError::UnknownLeaf => write!(f, "script is not a leaf of the taproot tree"),
+        }
+    }
+}
+
+impl std::error::Error for SignError {}
+
+impl From<SighashError> for SignError {
+    fn from(e: SighashError) -> Self {
+        SignError::Sighash(e)
+    }
+}
+
+/// BIP-340 signature with fresh auxiliary randomness, followed by the hash
+/// type byte unless it is `Default`
+pub fn schnorr_signature(private_key: &PrivateKey, sighash: &[u8; 32], sighash_type: TapSighashType) -> Vec<u8> {
+    schnorr_signature_with_rng(private_key, sighash, sighash_type, &mut OsRng)
+}
+
+pub fn schnorr_signature_with_rng<R: RngCore + CryptoRng>(
+    private_key: &PrivateKey,
+    sighash: &[u8; 32],
+    sighash_type: TapSighashType,
+    rng: &mut R,
+) -> Vec<u8> {
+    let mut aux_rand = [0_u8; 32];
+    rng.fill_bytes(&mut aux_rand);
+    let mut signature = schnorr::sign(private_key, sighash, &aux_rand).to_vec();
+    if sighash_type != TapSighashType::Default {
+        signature.push(sighash_type.to_u8());
+    }
+    signature
+}
+
+/// DER signature followed by the hash type byte, as it appears in scripts
+/// and witnesses
+pub fn ecdsa_signature(private_key: &PrivateKey, sighash: &[u8; 32], sighash_type: EcdsaSighashType) -> Vec<u8> {
+    let mut sig = private_key.sign(&BigUint::from_bytes_be(sighash)).der();
+    sig.push(sighash_type.to_u32() as u8);
+    sig
+}
+
+/// Signs input `index`, which spends the P2PKH output `prevout`, with
+/// SIGHASH_ALL and sets its script_sig to `<signature> <pubkey>`. The output
+/// may commit to either the compressed or the uncompressed key
+pub fn sign_p2pkh_input(
+    tx: &mut Transaction,
+    index: usize,
+    prevout: &TxOut,
+    private_key: &PrivateKey,
+) -> Result<(), SignError> {
+    trace_span!("tx.sign_input", index, kind = "p2pkh");
+    let script_pubkey = &prevout.script_pubkey;
+    let public_key = private_key.public_key();
+    let sec = [true, false]
+        .into_iter()
+        .map(|compressed| public_key.sec(compressed))
+        .find(|sec| script_pubkey == &Script::p2pkh(&hash160(sec)))
+        .ok_or(SignError::KeyMismatch)?;
+
+    let sighash_type = EcdsaSighashType::All;
+    let sighash = SighashCache::new(tx).legacy_signature_hash(index, script_pubkey, sighash_type.to_u32())?;
+    let signature = ecdsa_signature(private_key, &sighash, sighash_type);
+    tx.inputs[index].script_sig = Script::new().push_slice(&signature).push_slice(&sec);
+    Ok(())
+}
+
+/// Signs input `index`, which spends the P2WPKH output `prevout`, with the
+/// BIP-143 SIGHASH_ALL digest and sets its witness to `<signature> <pubkey>`.
+/// Segwit only allows compressed keys
+pub fn sign_p2wpkh_input(
+    tx: &mut Transaction,
+    index: usize,
+    prevout: &TxOut,
+    private_key: &PrivateKey,
+) -> Result<(), SignError> {
+    trace_span!("tx.sign_input", index, kind = "p2wpkh");
+    let sec = private_key.public_key().sec(true);
+    if prevout.script_pubkey != Script::p2wpkh(&hash160(&sec)) {
+        return Err(SignError::KeyMismatch);
+    }
+
+    let sighash_type = EcdsaSighashType::All;
+    let sighash =
+        SighashCache::new(tx).p2wpkh_signature_hash(index, &prevout.script_pubkey, prevout.amount, sighash_type)?;
+    let signature = ecdsa_signature(private_key, &sighash, sighash_type);
+    tx.inputs[index].script_sig = Script::new();
+    tx.inputs[index].witness = vec![signature, sec];
+    Ok(())
+}
+
+/// One signature per key of `multisig` that `private_keys` can sign for,
+/// in key order and stopping at the threshold
+fn multisig_signatures(
+    multisig: &Multisig,
+    private_keys: &[PrivateKey],
+    sighash: &[u8; 32],
+    sighash_type: EcdsaSighashType,
+) -> Result<Vec<Vec<u8>>, SignError> {
+    let signatures: Vec<Vec<u8>> = multisig
+        .keys()
+        .iter()
+        .filter_map(|key| private_keys.iter().find(|private_key| private_key.public_key() == *key))
+        .take(multisig.required())
+        .map(|private_key| ecdsa_signature(private_key, sighash, sighash_type))
+        .collect();
+    if signatures.len() < multisig.required() {
+        return Err(SignError::KeyMismatch);
+    }
+    Ok(signatures)
+}
+
+/// Signs input `index`, which spends the P2SH `multisig` output `prevout`,
+/// with SIGHASH_ALL. Needs at least the threshold of `private_keys` among
+/// the multisig keys; the script_sig is `OP_0 <signatures> <redeem script>`
+pub fn sign_p2sh_multisig_input(
+    tx: &mut Transaction,
+    index: usize,
+    prevout: &TxOut,
+    multisig: &Multisig,
+    private_keys: &[PrivateKey],
+) -> Result<(), SignError> {
+    trace_span!("tx.sign_input", index, kind = "p2sh-multisig");
+    if multisig.p2sh_script_pubkey().ok() != Some(prevout.script_pubkey.clone()) {
+        return Err(SignError::KeyMismatch);
+    }
+
+    let sighash_type = EcdsaSighashType::All;
+    let sighash = SighashCache::new(tx).legacy_signature_hash(index, &multisig.script(), sighash_type.to_u32())?;
+    let signatures = multisig_signatures(multisig, private_keys, &sighash, sighash_type)?;
+    tx.inputs[index].script_sig = multisig
+        .script_sig(&signatures)
+        .expect("one signature per required key");
+    Ok(())
+}
+
+/// Signs input `index`, which spends the P2WSH `multisig` output `prevout`,
+/// with the BIP-143 SIGHASH_ALL digest. The witness is the empty dummy,
+/// the signatures and the witness script
+pub fn sign_p2wsh_multisig_input(
+    tx: &mut Transaction,
+    index: usize,
+    prevout: &TxOut,
+    multisig: &Multisig,
+    private_keys: &[PrivateKey],
+) -> Result<(), SignError> {
+    trace_span!("tx.sign_input", index, kind = "p2wsh-multisig");
+    if prevout.script_pubkey != multisig.p2wsh_script_pubkey() {
+        return Err(SignError::KeyMismatch);
+    }
+
+    let sighash_type = EcdsaSighashType::All;
+    let sighash =
+        SighashCache::new(tx).p2wsh_signature_hash(index, &multisig.script(), prevout.amount, sighash_type)?;
+    let signatures = multisig_signatures(multisig, private_keys, &sighash, sighash_type)?;
+    tx.inputs[index].script_sig = Script::new();
+    tx.inputs[index].witness = multisig.witness(&signatures).expect("one signature per required key");
+    Ok(())
+}
+
+/// Signs a taproot key-path spend of input `index`. `prevouts` are the
+/// outputs spent by every input, in order. The internal key is tweaked with
+/// `merkle_root` (None for outputs without a script tree) and must match
+/// the spent P2TR output. The witness is the 64-byte Schnorr signature, with
+/// the hash type appended unless it is `Default`
+pub fn sign_taproot_keypath(
+    tx: &mut Transaction,
+    index: usize,
+    prevouts: &[TxOut],
+    internal_key: &PrivateKey,
+    merkle_root: Option<&[u8; 32]>,
+    sighash_type: TapSighashType,
+) -> Result<(), SignError> {
+    trace_span!("tx.sign_input", index, kind = "taproot-keypath");
+    let internal = xonly(&internal_key.public_key());
+    let (output_key, _) = tweak_public_key(&internal, merkle_root).map_err(SignError::Tweak)?;
+    let prevout = prevouts.get(index).ok_or(SighashError::PrevoutsRequired {
+        got: prevouts.len(),
+        inputs: tx.inputs.len(),
+    })?;
+    if prevout.script_pubkey != Script::p2tr(&output_key) {
+        return Err(SignError::KeyMismatch);
+    }
+
+    let sighash =
+        SighashCache::new(tx).taproot_signature_hash(index, &Prevouts::All(prevouts), None, None, sighash_type)?;
+    let tweaked = tweak_private_key(internal_key, merkle_root).map_err(SignError::Tweak)?;
+    tx.inputs[index].script_sig = Script::new();
+    tx.inputs[index].witness = vec![schnorr_signature(&tweaked, &sighash, sighash_type)];
+    Ok(())
+}
+
+/// Signs a taproot script-path spend of input `index` through the tapscript
+/// leaf `script`, which must be of the form `<key> OP_CHECKSIG`. The key is
+/// used untweaked and the sighash commits to the leaf. The witness is
+/// `<signature> <script> <control block>`
+pub fn sign_taproot_script_path(
+    tx: &mut Transaction,
+    index: usize,
+    prevouts: &[TxOut],
+    private_key: &PrivateKey,
+    spend_info: &TaprootSpendInfo,
+    script: &Script,
+    sighash_type: TapSighashType,
+) -> Result<(), SignError> {
+    trace_span!("tx.sign_input", index, kind = "taproot-script-path");
+    let prevout = prevouts.get(index).ok_or(SighashError::PrevoutsRequired {
+        got: prevouts.len(),
+        inputs: tx.inputs.len(),
+    })?;
+    if prevout.script_pubkey != spend_info.script_pubkey() {
+        return Err(SignError::KeyMismatch);
+    }
+    let control_block = spend_info.control_block(script).ok_or(SignError::UnknownLeaf)?;
+    if *script
+        != Script::new()
+            .push_slice(&xonly(&private_key.public_key()))
+            .push_opcode(Opcode::CheckSig)
+    {
+        return Err(SignError::KeyMismatch);
+    }
+
+    let script_path = ScriptPath {
+        leaf_hash: tap_leaf_hash(LEAF_VERSION_TAPSCRIPT, script),
+        code_separator_pos: 0xffffffff,
+    };
+    let sighash = SighashCache::new(tx).taproot_signature_hash(
+        index,
+        &Prevouts::All(prevouts),
+        None,
+        Some(script_path),
+        sighash_type,
+    )?;
+    tx.inputs[index].script_sig = Script::new();
+    tx.inputs[index].witness = vec![
+        schnorr_signature(private_key, &sighash, sighash_type),
+        script.as_bytes().to_vec(),
+        control_block.serialize(),
+    ];
+    Ok(())
+}
+
+#[cfg(test)]
+mod sign_tests {
+    use super::*;
+    use crate::address::{p2pkh, Network};
+    use crate::curves::secp256k1::ecc::{PublicKey, Signature};
+    use crate::encoding::base58;
+    use crate::script::Instruction;
+    use crate::taproot::{ControlBlock, TapTree};
+    use crate::tx::{OutPoint, TxIn};
+
+    fn p2pkh_script(address: &str) -> Script {
+        let payload = base58::decode_check(address).unwrap();
+        Script::p2pkh(payload[1..].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_sign_testnet_p2pkh() {
+        let key = PrivateKey::from(BigUint::from(8675309_u32)).unwrap();
+        let change = p2pkh(&key.public_key(), Network::Testnet);
+        assert_eq!(change, "mzx5YhAH9kNHtcN481u6WkjeHjYtVeKVh2");
+
+        // spend output 13 of a funding transaction: 0.33 tBTC to a faucet
+        // address, 0.1 tBTC back to ourselves
+        let mut txid = hex::decode("0d6fe5213c0b3291f208cba8bfb59b7476dffacc4e5cb66f6eb20a080843a299").unwrap();
+        txid.reverse();
+        let mut tx = Transaction {
+            version: 1,
+            inputs: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: txid.try_into().unwrap(),
+                    vout: 13,
+                },
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: Vec::new(),
+            }],
+            outputs: vec![
+                TxOut {
+                    amount: 33_000_000,
+                    script_pubkey: p2pkh_script("mnrVtF8DWjMu839VW3rBfgYaAfKk8983Xf"),
+                },
+                TxOut {
+                    amount: 10_000_000,
+                    script_pubkey: p2pkh_script(&change),
+                },
+            ],
+            locktime: 0,
+        };
+        let prevout = TxOut {
+            amount: 44_000_000,
+            script_pubkey: p2pkh_script(&change),
+        };
+
+        sign_p2pkh_input(&mut tx, 0, &prevout, &key).unwrap();
+        assert_eq!(
+            tx.to_hex(),
+            "010000000199a24308080ab26e6fb65c4eccfadf76749bb5bfa8cb08f291320b3c21e56f0d0d0000006b48\
+             3045022100eec9db6e3cc4a0516d5cc3580cd0252067bc92457cd2287ac2f93299c1e5da6502201078c8a7\
+             d99aa7994094336114f75ba0898b9c39391d345b133824f0f959c1c1012103935581e52c354cd2f484fe8e\
+             d83af7a3097005b2f9c60bff71d35bd795f54b67ffffffff02408af701000000001976a914507b27411ccf\
+             7f16f10297de6cef3f291623eddf88ac80969800000000001976a914d52ad7ca9b3d096a38e752c2018e6f\
+             bc40cdf26f88ac00000000"
+        );
+        assert_eq!(
+            tx.id(),
+            "f1306178fba9f32d13e17846f7263c0c9011c5dac94f6fe18a51c46bd9fe9957"
+        );
+
+        // the script_sig verifies against the sighash
+        let pushes: Vec<_> = tx.inputs[0]
+            .script_sig
+            .instructions()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let [Instruction::Push(_, sig), Instruction::Push(_, sec)] = pushes[..] else {
+            panic!("unexpected script_sig {:?}", pushes);
+        };
+        let (sighash_type, der) = sig.split_last().unwrap();
+        assert_eq!(*sighash_type, 0x01);
+        let sighash = SighashCache::new(&tx)
+            .legacy_signature_hash(0, &prevout.script_pubkey, 1)
+            .unwrap();
+        let public_key = PublicKey::parse(sec).unwrap();
+        assert!(public_key.verify(&BigUint::from_bytes_be(&sighash), &Signature::parse_der(der).unwrap()));
+    }
+
+    #[test]
+    fn test_sign_p2pkh_errors() {
+        let key = PrivateKey::from(BigUint::from(8675309_u32)).unwrap();
+        let other = PrivateKey::from(BigUint::from(42_u8)).unwrap();
+        let prevout = TxOut {
+            amount: 1,
+            script_pubkey: Script::p2pkh(&hash160(&other.public_key().sec(true))),
+        };
+        let mut tx = Transaction {
+            version: 1,
+            inputs: vec![TxIn {
+                previous_output: OutPoint::NULL,
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: Vec::new(),
+            }],
+            outputs: Vec::new(),
+            locktime: 0,
+        };
+
+        assert_eq!(
+            sign_p2pkh_input(&mut tx, 0, &prevout, &key),
+            Err(SignError::KeyMismatch)
+        );
+        assert_eq!(
+            sign_p2pkh_input(&mut tx, 1, &prevout, &other),
+            Err(SignError::Sighash(SighashError::InputOutOfRange {
+                index: 1,
+                inputs: 1
+            }))
+        );
+
+        // uncompressed keys are found too
+        let legacy = TxOut {
+            amount: 1,
+            script_pubkey: Script::p2pkh(&hash160(&key.public_key().sec(false))),
+        };
+        sign_p2pkh_input(&mut tx, 0, &legacy, &key).unwrap();
+        let pushes: Vec<_> = tx.inputs[0]
+            .script_sig
+            .instructions()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            pushes[1],
+            Instruction::Push(crate::script::Opcode::PushBytes(65), &key.public_key().sec(false)[..])
+        );
+    }
+
+    #[test]
+    fn test_sign_p2wpkh() {
+        // the native P2WPKH example from BIP-143; only the second input is
+        // segwit, the first (P2PK) is left unsigned
+        let mut tx = Transaction::from_hex(
+            "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f000000\
+             0000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a01000000\
+             00ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093\
+             510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+        )
+        .unwrap();
+        let key = PrivateKey::from_bytes(
+            &hex::decode("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9").unwrap(),
+        )
+        .unwrap();
+        let prevout = TxOut {
+            amount: 600_000_000,
+            script_pubkey: Script::from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap(),
+        };
+
+        sign_p2wpkh_input(&mut tx, 1, &prevout, &key).unwrap();
+        assert!(tx.has_witness());
+        assert_eq!(
+            hex::encode(&tx.inputs[1].witness[0]),
+            "304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c45\
+             18331561406f90300e8f3358f51928d43c212a8caed02de67eebee01"
+        );
+        assert_eq!(
+            hex::encode(&tx.inputs[1].witness[1]),
+            "025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357"
+        );
+        assert!(tx.inputs[1].script_sig.is_empty());
+        // an input without a witness is serialized as an empty stack
+        assert!(tx.to_hex().ends_with(
+            "ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a\
+             0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e\
+             292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000"
+        ));
+        assert_eq!(
+            tx.id(),
+            "3335ffae0df20c5407e8de12b49405c8e912371f00fe4132bfaf95ad49c40243"
+        );
+
+        let p2pkh = TxOut {
+            amount: 1,
+            script_pubkey: Script::p2pkh(&hash160(&key.public_key().sec(true))),
+        };
+        assert_eq!(sign_p2wpkh_input(&mut tx, 1, &p2pkh, &key), Err(SignError::KeyMismatch));
+    }
+
+    #[test]
+    fn test_sign_multisig() {
+        // signed by rust-bitcoin: a 2-of-3 over keys 1, 2 and 3, spent
+        // with keys 1 and 3 from P2WSH and keys 2 and 3 from P2SH
+        let private_keys: Vec<PrivateKey> = (1..=3_u8)
+            .map(|s| PrivateKey::from(BigUint::from(s)).unwrap())
+            .collect();
+        let multisig = Multisig::new(2, private_keys.iter().map(PrivateKey::public_key).collect()).unwrap();
+        let unsigned = Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                previous_output: OutPoint { txid: [1; 32], vout: 0 },
+                script_sig: Script::new(),
+                sequence: u32::MAX,
+                witness: Vec::new(),
+            }],
+            outputs: vec![TxOut {
+                amount: 90_000,
+                script_pubkey: multisig.p2wsh_script_pubkey(),
+            }],
+            locktime: 0,
+        };
+
+        let mut tx = unsigned.clone();
+        let prevout = TxOut {
+            amount: 100_000,
+            script_pubkey: multisig.p2wsh_script_pubkey(),
+        };
+        // the keys may come in any order
+        sign_p2wsh_multisig_input(
+            &mut tx,
+            0,
+            &prevout,
+            &multisig,
+            &[private_keys[2].clone(), private_keys[0].clone()],
+        )
+        .unwrap();
+        assert_eq!(
+            tx.to_hex(),
+            "0200000000010101010101010101010101010101010101010101010101010101010101010101010000000000ffffffff01905f01\
+             000000000022002012c2ffbc6ec1cf5d746dfbd49b1063356212ea55f43023ffc0145934af20c5720400483045022100ca2921\
+             dec80acddc06db6299769652ee457f465087ed3789d636871f8770682902200a200dcd338dfa3958a9fce399b8fb73a26dc4d4\
+             02f732d0c22bd29f7dd06f9801483045022100c65e0c81b75751d54ee339e842e95b68d18b709dd4283c5fe1de5e3cf87a2aa8\
+             0220172e66f80582c62c5fcde1678964c6644ec3b8e4b73140d17e69091547dfef84016952210279be667ef9dcbbac55a06295\
+             ce870b07029bfcdb2dce28d959f2815b16f817982102c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c\
+             709ee52102f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f953ae00000000"
+        );
+
+        let mut tx = unsigned.clone();
+        let prevout = TxOut {
+            amount: 100_000,
+            script_pubkey: multisig.p2sh_script_pubkey().unwrap(),
+        };
+        sign_p2sh_multisig_input(&mut tx, 0, &prevout, &multisig, &private_keys[1..]).unwrap();
+        assert_eq!(
+            tx.to_hex(),
+            "0200000001010101010101010101010101010101010101010101010101010101010101010100000000fdfe0000483045022100\
+             b5906e2c18d1d8707da493a64db8523a314ae2d6c019f8d44ae0110cb123b1000220793ec51b5882f2b449f3f1618e276f8ec5\
+             e3018839520e0d308eb70dc6b1887401483045022100c06e4c0d11e2a7099e9c7a66e39f6fd04bfedc4da241df4acedc677fe1\
+             cc7ff702207d973c543b59be6bbd1511eaae7ba6bdd5a1cece304d91f980f26b159e84dd50014c6952210279be667ef9dcbbac\
+             55a06295ce870b07029bfcdb2dce28d959f2815b16f817982102c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7ab\
+             ac09b95c709ee52102f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f953aeffffffff01905f01\
+             000000000022002012c2ffbc6ec1cf5d746dfbd49b1063356212ea55f43023ffc0145934af20c57200000000"
+        );
+
+        // one key short of the threshold, or the wrong output type
+        let mut tx = unsigned.clone();
+        assert_eq!(
+            sign_p2sh_multisig_input(&mut tx, 0, &prevout, &multisig, &private_keys[2..]),
+            Err(SignError::KeyMismatch)
+        );
+        assert_eq!(
+            sign_p2wsh_multisig_input(&mut tx, 0, &prevout, &multisig, &private_keys),
+            Err(SignError::KeyMismatch)
+        );
+    }
+
+    #[test]
+    fn test_sign_taproot_keypath() {
+        let internal_key = PrivateKey::from(BigUint::from(0x7a9_u32)).unwrap();
+        let internal = xonly(&internal_key.public_key());
+        let root = [0x42; 32];
+        let (key_only, _) = tweak_public_key(&internal, None).unwrap();
+        let (with_tree, _) = tweak_public_key(&internal, Some(&root)).unwrap();
+
+        let prevouts = vec![
+            TxOut {
+                amount: 50_000,
+                script_pubkey: Script::p2tr(&key_only),
+            },
+            TxOut {
+                amount: 70_000,
+                script_pubkey: Script::p2tr(&with_tree),
+            },
+        ];
+        let input = |vout| TxIn {
+            previous_output: OutPoint { txid: [0x11; 32], vout },
+            script_sig: Script::new(),
+            sequence: 0xfffffffd,
+            witness: Vec::new(),
+        };
+        let mut tx = Transaction {
+            version: 2,
+            inputs: vec![input(0), input(1)],
+            outputs: vec![TxOut {
+                amount: 119_000,
+                script_pubkey: Script::p2wpkh(&[0x22; 20]),
+            }],
+            locktime: 0,
+        };
+
+        sign_taproot_keypath(&mut tx, 0, &prevouts, &internal_key, None, TapSighashType::Default).unwrap();
+        sign_taproot_keypath(&mut tx, 1, &prevouts, &internal_key, Some(&root), TapSighashType::All).unwrap();
+        assert_eq!(tx.inputs[0].witness[0].len(), 64);
+        assert_eq!(tx.inputs[1].witness[0].len(), 65);
+        assert_eq!(tx.inputs[1].witness[0][64], 0x01);
+
+        // signatures don't commit to witnesses, so both still verify
+        let cache = SighashCache::new(&tx);
+        for (i, (output_key, sighash_type)) in [(key_only, TapSighashType::Default), (with_tree, TapSighashType::All)]
+            .into_iter()
+            .enumerate()
+        {
+            let sighash = cache
+                .taproot_signature_hash(i, &Prevouts::All(&prevouts), None, None, sighash_type)
+                .unwrap();
+            let sig: [u8; 64] = tx.inputs[i].witness[0][..64].try_into().unwrap();
+            assert!(schnorr::verify(&output_key, &sighash, &sig));
+        }
+
+        // the wrong merkle root gives a different output key
+        assert_eq!(
+            sign_taproot_keypath(&mut tx, 1, &prevouts, &internal_key, None, TapSighashType::All),
+            Err(SignError::KeyMismatch)
+        );
+        assert_eq!(
+            sign_taproot_keypath(&mut tx, 0, &prevouts[..1], &internal_key, None, TapSighashType::All),
+            Err(SignError::Sighash(SighashError::PrevoutsRequired { got: 1, inputs: 2 }))
+        );
+    }
+
+    #[test]
+    fn test_sign_taproot_script_path() {
+        let internal_key = PrivateKey::from(BigUint::from(0x7a9_u32)).unwrap();
+        let alice = PrivateKey::from(BigUint::from(0xa11ce_u32)).unwrap();
+        let bob = PrivateKey::from(BigUint::from(0xb0b_u32)).unwrap();
+        let checksig = |key: &PrivateKey| {
+            Script::new()
+                .push_slice(&xonly(&key.public_key()))
+                .push_opcode(Opcode::CheckSig)
+        };
+        let (alice_leaf, bob_leaf) = (checksig(&alice), checksig(&bob));
+        let tree = TapTree::branch(TapTree::leaf(alice_leaf.clone()), TapTree::leaf(bob_leaf.clone()));
+        let spend_info = TaprootSpendInfo::new(xonly(&internal_key.public_key()), Some(tree)).unwrap();
+
+        let prevouts = vec![TxOut {
+            amount: 80_000,
+            script_pubkey: spend_info.script_pubkey(),
+        }];
+        let mut tx = Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: [0x33; 32],
+                    vout: 0,
+                },
+                script_sig: Script::new(),
+                sequence: 0xffffffff,
+                witness: Vec::new(),
+            }],
+            outputs: vec![TxOut {
+                amount: 79_000,
+                script_pubkey: Script::p2wpkh(&[0x22; 20]),
+            }],
+            locktime: 0,
+        };
+
+        sign_taproot_script_path(
+            &mut tx,
+            0,
+            &prevouts,
+            &bob,
+            &spend_info,
+            &bob_leaf,
+            TapSighashType::Default,
+        )
+        .unwrap();
+        let witness = &tx.inputs[0].witness;
+        assert_eq!(witness.len(), 3);
+        assert_eq!(witness[1], bob_leaf.as_bytes());
+        let control_block = ControlBlock::parse(&witness[2]).unwrap();
+        assert!(control_block.verify(spend_info.output_key(), &bob_leaf));
+        assert_eq!(
+            control_block.merkle_branch,
+            vec![tap_leaf_hash(LEAF_VERSION_TAPSCRIPT, &alice_leaf)]
+        );
+
+        let script_path = ScriptPath {
+            leaf_hash: tap_leaf_hash(LEAF_VERSION_TAPSCRIPT, &bob_leaf),
+            code_separator_pos: 0xffffffff,
+        };
+        let sighash = SighashCache::new(&tx)
+            .taproot_signature_hash(
+                0,
+                &Prevouts::All(&prevouts),
+                None,
+                Some(script_path),
+                TapSighashType::Default,
+            )
+            .unwrap();
+        let sig: [u8; 64] = witness[0][..].try_into().unwrap();
+        assert!(schnorr::verify(&xonly(&bob.public_key()), &sighash, &sig));
+        // a key-path sighash differs, so the signature is bound to the leaf
+        let key_path = SighashCache::new(&tx)
+            .taproot_signature_hash(0, &Prevouts::All(&prevouts), None, None, TapSighashType::Default)
+            .unwrap();
+        assert!(!schnorr::verify(&xonly(&bob.public_key()), &key_path, &sig));
+
+        assert_eq!(
+            sign_taproot_script_path(
+                &mut tx,
+                0,
+                &prevouts,
+                &alice,
+                &spend_info,
+                &bob_leaf,
+                TapSighashType::All
+            ),
+            Err(SignError::KeyMismatch)
+        );
+        assert_eq!(
+            sign_taproot_script_path(
+                &mut tx,
+                0,
+                &prevouts,
+                &bob,
+                &spend_info,
+                &checksig(&internal_key),
+                TapSighashType::All
+            ),
+            Err(SignError::UnknownLeaf)
+        );
+    }
+}
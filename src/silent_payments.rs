@@ -0,0 +1,646 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+
+use crate::address::Network;
+use crate::crypto::ecdh::shared_point;
+use crate::crypto::hash::{hash160, tagged_hash};
+use crate::crypto::schnorr::{even_y_secret, lift_x, xonly};
+use crate::curves::secp256k1::ecc::{order, KeyError, PrivateKey, PublicKey};
+use crate::encoding::bech32::{self, Bech32Error, Variant};
+use crate::script::Script;
+use crate::tx::{OutPoint, Transaction, TxIn, TxOut};
+
+/// BIP-341's unspendable internal key H. Script-path spends from it carry
+/// no usable key, so they don't take part in silent payments
+pub const NUMS_H: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e, 0x07, 0x8a, 0x5a,
+    0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// Silent payment addresses are longer than BIP-173 allows
+const MAX_ADDRESS_LEN: usize = 1023;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SilentPaymentError {
+    Address(Bech32Error),
+    UnknownHrp(String),
+    /// Version 31 is reserved for a backwards incompatible change
+    UnsupportedVersion(u8),
+    /// The address payload isn't two compressed keys
+    InvalidPayload,
+    Key(KeyError),
+    /// No input can be used for the shared secret
+    NoInputs,
+    /// Scanning needs the output spent by every input
+    PrevoutCount {
+        expected: usize,
+        got: usize,
+    },
+}
+
+impl Display for SilentPaymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SilentPaymentError::Address(e) => write!(f, "invalid silent payment address: {}", e),
+            SilentPaymentError::UnknownHrp(hrp) => write!(f, "unknown silent payment hrp {:?}", hrp),
+            SilentPaymentError::UnsupportedVersion(v) => write!(f, "unsupported silent payment version {}", v),
+            SilentPaymentError::InvalidPayload => write!(f, "silent payment address does not hold two keys"),
+            SilentPaymentError::Key(e) => write!(f, "{}", e),
+            SilentPaymentError::NoInputs => write!(f, "no eligible inputs"),
+            SilentPaymentError::PrevoutCount { expected, got } => {
+                write!(f, "expected {} prevouts, got {}", expected, got)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SilentPaymentError {}
+
+impl From<Bech32Error> for SilentPaymentError {
+    fn from(e: Bech32Error) -> Self {
+        SilentPaymentError::Address(e)
+    }
+}
+
+impl From<KeyError> for SilentPaymentError {
+    fn from(e: KeyError) -> Self {
+        SilentPaymentError::Key(e)
+    }
+}
+
+fn hrp(network: Network) -> &'static str {
+    match network {
+        Network::Mainnet => "sp",
+        Network::Testnet | Network::Signet => "tsp",
+        Network::Regtest => "sprt",
+    }
+}
+
+/// A BIP-352 address: the receiver's scan key, used to find payments, and
+/// spend key, which the outputs are derived from
+#[derive(Debug, Clone, PartialEq)]
+pub struct SilentPaymentAddress {
+    scan_key: PublicKey,
+    spend_key: PublicKey,
+    network: Network,
+}
+
+impl SilentPaymentAddress {
+    pub fn new(scan_key: PublicKey, spend_key: PublicKey, network: Network) -> Self {
+        Self {
+            scan_key,
+            spend_key,
+            network,
+        }
+    }
+
+    pub fn scan_key(&self) -> &PublicKey {
+        &self.scan_key
+    }
+
+    pub fn spend_key(&self) -> &PublicKey {
+        &self.spend_key
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Version 0 is exactly two keys. Later versions may append data,
+    /// which a version 0 sender ignores
+    pub fn parse(s: &str) -> Result<Self, SilentPaymentError> {
+        let (found_hrp, data, variant) = bech32::decode_with_limit(s, MAX_ADDRESS_LEN)?;
+        let network = [Network::Mainnet, Network::Testnet, Network::Regtest]
+            .into_iter()
+            .find(|&network| hrp(network) == found_hrp)
+            .ok_or(SilentPaymentError::UnknownHrp(found_hrp))?;
+        if variant != Variant::Bech32m {
+            return Err(Bech32Error::WrongVariant.into());
+        }
+        let (&version, data) = data.split_first().ok_or(Bech32Error::InvalidLength)?;
+        if version == 31 {
+            return Err(SilentPaymentError::UnsupportedVersion(version));
+        }
+        let payload = bech32::convert_bits(data, 5, 8, false)?;
+        if payload.len() < 66 || (version == 0 && payload.len() != 66) {
+            return Err(SilentPaymentError::InvalidPayload);
+        }
+        let key = |bytes: &[u8]| match bytes[0] {
+            0x02 | 0x03 => PublicKey::parse(bytes).map_err(|_| SilentPaymentError::InvalidPayload),
+            _ => Err(SilentPaymentError::InvalidPayload),
+        };
+        Ok(Self {
+            scan_key: key(&payload[..33])?,
+            spend_key: key(&payload[33..66])?,
+            network,
+        })
+    }
+}
+
+impl Display for SilentPaymentAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let payload = [self.scan_key.sec(true), self.spend_key.sec(true)].concat();
+        let mut data = vec![0];
+        data.extend(bech32::convert_bits(&payload, 8, 5, true).expect("bytes always regroup"));
+        let address = bech32::encode(hrp(self.network), &data, Variant::Bech32m).expect("5-bit groups");
+        write!(f, "{}", address)
+    }
+}
+
+/// hash_BIP0352/Label(b_scan || m). Label 0 is reserved for change
+pub fn label_tweak(scan_key: &PrivateKey, m: u32) -> [u8; 32] {
+    tagged_hash("BIP0352/Label", &[&scan_key.to_bytes()[..], &m.to_be_bytes()].concat())
+}
+
+/// Commits the shared secret to the transaction, so reusing input keys
+/// across transactions still gives fresh outputs: the hash of the smallest
+/// outpoint and the sum of the input keys
+pub fn input_hash(outpoints: &[OutPoint], input_key_sum: &PublicKey) -> Result<[u8; 32], SilentPaymentError> {
+    let smallest = outpoints
+        .iter()
+        .map(|outpoint| {
+            let mut bytes = Vec::with_capacity(36);
+            outpoint.write(&mut bytes).unwrap();
+            bytes
+        })
+        .min()
+        .ok_or(SilentPaymentError::NoInputs)?;
+    Ok(tagged_hash(
+        "BIP0352/Inputs",
+        &[&smallest[..], &input_key_sum.sec(true)].concat(),
+    ))
+}
+
+fn scalar(bytes: &[u8; 32]) -> BigUint {
+    BigUint::from_bytes_be(bytes)
+}
+
+fn add_points(a: &PublicKey, b: &PublicKey) -> Option<PublicKey> {
+    PublicKey::from(a.point().clone() + b.point().clone()).ok()
+}
+
+fn negate(key: &PublicKey) -> PublicKey {
+    let mut sec = key.sec(true);
+    sec[0] ^= 1;
+    PublicKey::parse(&sec).unwrap()
+}
+
+/// t_k = hash_BIP0352/SharedSecret(ecdh || k), the tweak of the k-th output
+/// to one scan key
+fn output_tweak(ecdh: &PublicKey, k: u32) -> [u8; 32] {
+    tagged_hash(
+        "BIP0352/SharedSecret",
+        &[&ecdh.sec(true)[..], &k.to_be_bytes()].concat(),
+    )
+}
+
+/// An input the sender signs for. Taproot keys count with even y, as
+/// the spend reveals them
+#[derive(Debug, Clone)]
+pub struct SenderInput {
+    pub outpoint: OutPoint,
+    pub key: PrivateKey,
+    pub taproot: bool,
+}
+
+/// The x-only taproot output keys paying each recipient, in order. Outputs
+/// to the same scan key are numbered so the receiver finds them all with
+/// one ECDH
+pub fn sender_outputs(
+    inputs: &[SenderInput],
+    recipients: &[SilentPaymentAddress],
+) -> Result<Vec<[u8; 32]>, SilentPaymentError> {
+    let mut keys = inputs.iter().map(|input| {
+        if input.taproot {
+            PrivateKey::from(even_y_secret(&input.key)).expect("secret is in [1, n)")
+        } else {
+            input.key.clone()
+        }
+    });
+    let first = keys.next().ok_or(SilentPaymentError::NoInputs)?;
+    let sum = keys.try_fold(first, |sum, key| sum.add_tweak(key.secret()))?;
+    let outpoints: Vec<OutPoint> = inputs.iter().map(|input| input.outpoint).collect();
+    let tweaked = sum.mul_tweak(&scalar(&input_hash(&outpoints, &sum.public_key())?))?;
+
+    let mut counts: HashMap<Vec<u8>, u32> = HashMap::new();
+    recipients
+        .iter()
+        .map(|recipient| {
+            let k = counts.entry(recipient.scan_key.sec(true)).or_default();
+            let ecdh = shared_point(&tweaked, &recipient.scan_key);
+            let output = recipient.spend_key.add_tweak(&scalar(&output_tweak(&ecdh, *k)))?;
+            *k += 1;
+            Ok(xonly(&output))
+        })
+        .collect()
+}
+
+/// The key an input contributes to the shared secret, if its type is one
+/// silent payments use: P2TR key and script paths (unless the internal key
+/// is H), P2WPKH, P2SH-P2WPKH and P2PKH, all with compressed keys
+pub fn input_public_key(input: &TxIn, prevout: &Script) -> Option<PublicKey> {
+    let compressed = |bytes: &[u8]| match bytes.len() {
+        33 => PublicKey::parse(bytes).ok(),
+        _ => None,
+    };
+    match prevout.witness_program() {
+        Some((1, program)) if program.len() == 32 => {
+            let mut witness = &input.witness[..];
+            if let [rest @ .., annex] = witness {
+                if rest.len() > 1 && annex.first() == Some(&0x50) {
+                    witness = rest;
+                }
+            }
+            if let [_, .., control_block] = witness {
+                if control_block.get(1..33) == Some(&NUMS_H[..]) {
+                    return None;
+                }
+            }
+            if witness.is_empty() {
+                return None;
+            }
+            lift_x(program.try_into().unwrap()).ok()
+        }
+        Some((0, program)) if program.len() == 20 => compressed(input.witness.last()?),
+        _ if prevout.is_p2sh() => {
+            let script_sig = input.script_sig.as_bytes();
+            let redeem = Script::from_bytes(script_sig.get(1..)?.to_vec());
+            match redeem.witness_program() {
+                Some((0, program)) if script_sig[0] == 0x16 && program.len() == 20 => compressed(input.witness.last()?),
+                _ => None,
+            }
+        }
+        _ if prevout.is_p2pkh() => {
+            // the key is normally the last push, but a malleated scriptSig
+            // could hide it elsewhere: take the last 33 bytes matching the hash
+            let pubkey_hash = &prevout.as_bytes()[3..23];
+            input
+                .script_sig
+                .as_bytes()
+                .windows(33)
+                .rev()
+                .find(|bytes| hash160(bytes) == pubkey_hash)
+                .and_then(compressed)
+        }
+        _ => None,
+    }
+}
+
+/// A payment found while scanning. The output is spent with the spend key
+/// plus `tweak`, which already includes any label
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundOutput {
+    pub vout: u32,
+    pub output_key: [u8; 32],
+    pub tweak: [u8; 32],
+    pub label: Option<u32>,
+}
+
+impl FoundOutput {
+    pub fn spend_key(&self, spend_key: &PrivateKey) -> Result<PrivateKey, KeyError> {
+        spend_key.add_tweak(&scalar(&self.tweak))
+    }
+}
+
+/// The receiving side: holds the scan secret, which can find payments but
+/// not spend them, and the labels to look for
+#[derive(Debug, Clone)]
+pub struct Receiver {
+    scan_key: PrivateKey,
+    spend_key: PublicKey,
+    /// Label point, by compressed encoding, to its number and tweak
+    labels: HashMap<Vec<u8>, (u32, [u8; 32])>,
+}
+
+impl Receiver {
+    pub fn new(scan_key: PrivateKey, spend_key: PublicKey) -> Self {
+        Self {
+            scan_key,
+            spend_key,
+            labels: HashMap::new(),
+        }
+    }
+
+    /// Also scans for payments to the address with label `m`
+    pub fn with_label(mut self, m: u32) -> Result<Self, SilentPaymentError> {
+        let tweak = label_tweak(&self.scan_key, m);
+        let point = PrivateKey::from_bytes(&tweak)?.public_key();
+        self.labels.insert(point.sec(true), (m, tweak));
+        Ok(self)
+    }
+
+    pub fn address(&self, network: Network) -> SilentPaymentAddress {
+        SilentPaymentAddress::new(self.scan_key.public_key(), self.spend_key.clone(), network)
+    }
+
+    /// The address with spend key B_spend + hash(b_scan || m) * G, which
+    /// tells payments apart without separate scan keys
+    pub fn labelled_address(&self, m: u32, network: Network) -> Result<SilentPaymentAddress, SilentPaymentError> {
+        let spend_key = self.spend_key.add_tweak(&scalar(&label_tweak(&self.scan_key, m)))?;
+        Ok(SilentPaymentAddress::new(
+            self.scan_key.public_key(),
+            spend_key,
+            network,
+        ))
+    }
+
+    /// Finds the taproot outputs of `tx` paying this receiver. `prevouts`
+    /// are the outputs the inputs spend, in input order
+    pub fn scan(&self, tx: &Transaction, prevouts: &[TxOut]) -> Result<Vec<FoundOutput>, SilentPaymentError> {
+        if prevouts.len() != tx.inputs.len() {
+            return Err(SilentPaymentError::PrevoutCount {
+                expected: tx.inputs.len(),
+                got: prevouts.len(),
+            });
+        }
+        let keys: Vec<PublicKey> = tx
+            .inputs
+            .iter()
+            .zip(prevouts)
+            .filter_map(|(input, prevout)| input_public_key(input, &prevout.script_pubkey))
+            .collect();
+        let Some((first, rest)) = keys.split_first() else {
+            return Ok(Vec::new());
+        };
+        let Some(sum) = rest.iter().try_fold(first.clone(), |sum, key| add_points(&sum, key)) else {
+            return Ok(Vec::new());
+        };
+        let outpoints: Vec<OutPoint> = tx.inputs.iter().map(|input| input.previous_output).collect();
+        let tweaked = self.scan_key.mul_tweak(&scalar(&input_hash(&outpoints, &sum)?))?;
+        let ecdh = shared_point(&tweaked, &sum);
+
+        let mut candidates: Vec<(u32, [u8; 32])> = tx
+            .outputs
+            .iter()
+            .enumerate()
+            .filter_map(|(vout, output)| match output.script_pubkey.witness_program() {
+                Some((1, program)) if program.len() == 32 => Some((vout as u32, program.try_into().unwrap())),
+                _ => None,
+            })
+            .collect();
+        let mut found = Vec::new();
+        for k in 0.. {
+            let tweak = output_tweak(&ecdh, k);
+            let output = self.spend_key.add_tweak(&scalar(&tweak))?;
+            let position = candidates.iter().position(|(_, key)| *key == xonly(&output));
+            let matched = match position {
+                Some(position) => Some((position, tweak, None)),
+                None => self.find_labelled(&candidates, &output).map(|(position, m, label)| {
+                    let total = (scalar(&tweak) + scalar(&label)) % order();
+                    let mut tweak = [0; 32];
+                    let bytes = total.to_bytes_be();
+                    tweak[32 - bytes.len()..].copy_from_slice(&bytes);
+                    (position, tweak, Some(m))
+                }),
+            };
+            let Some((position, tweak, label)) = matched else {
+                break;
+            };
+            let (vout, output_key) = candidates.remove(position);
+            found.push(FoundOutput {
+                vout,
+                output_key,
+                tweak,
+                label,
+            });
+        }
+        Ok(found)
+    }
+
+    /// An output is B_spend + t_k * G + label, so subtracting the unlabelled
+    /// output leaves the label point, up to the sign lost to x-only keys
+    fn find_labelled(&self, candidates: &[(u32, [u8; 32])], unlabelled: &PublicKey) -> Option<(usize, u32, [u8; 32])> {
+        if self.labels.is_empty() {
+            return None;
+        }
+        let minus = negate(unlabelled);
+        candidates.iter().enumerate().find_map(|(position, (_, key))| {
+            let output = lift_x(key).ok()?;
+            [add_points(&output, &minus), add_points(&negate(&output), &minus)]
+                .into_iter()
+                .flatten()
+                .find_map(|label| self.labels.get(&label.sec(true)))
+                .map(|&(m, tweak)| (position, m, tweak))
+        })
+    }
+}
+
+#[cfg(test)]
+mod silent_payments_tests {
+    use super::*;
+
+    fn key(s: &str) -> PrivateKey {
+        PrivateKey::from_bytes(&hex::decode(s).unwrap()).unwrap()
+    }
+
+    fn outpoint(txid: &str, vout: u32) -> OutPoint {
+        let mut txid: [u8; 32] = hex::decode(txid).unwrap().try_into().unwrap();
+        txid.reverse();
+        OutPoint { txid, vout }
+    }
+
+    fn receiver() -> (PrivateKey, PrivateKey) {
+        (
+            key("0f694e068028a717f8af6b9411f9a133dd3565258714cc226594b34db90c1f2c"),
+            key("9d6ad855ce3417ef84e836892e5a56392bfba05fa5d97ccea30e266f540e08b3"),
+        )
+    }
+
+    fn inputs(taproot: bool) -> Vec<SenderInput> {
+        vec![
+            SenderInput {
+                outpoint: outpoint("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16", 0),
+                key: key("eadc78165ff1f8ea94ad7cfdc54990738a4c53f6e0507b42154201b8e5dff3b1"),
+                taproot,
+            },
+            SenderInput {
+                outpoint: outpoint("a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d", 0),
+                key: key("93f5ed907ad5b2bdbbdcb6d9116ebc0a4e1f92f910d5260237fa45a9408aad16"),
+                taproot,
+            },
+        ]
+    }
+
+    /// A transaction spending `inputs` as P2WPKH, with the given taproot
+    /// outputs, and the outputs its inputs spend
+    fn p2wpkh_spend(inputs: &[SenderInput], outputs: &[[u8; 32]]) -> (Transaction, Vec<TxOut>) {
+        let tx = Transaction {
+            version: 2,
+            inputs: inputs
+                .iter()
+                .map(|input| TxIn {
+                    previous_output: input.outpoint,
+                    script_sig: Script::new(),
+                    sequence: 0xffffffff,
+                    witness: vec![vec![0x30; 71], input.key.public_key().sec(true)],
+                })
+                .collect(),
+            outputs: outputs
+                .iter()
+                .map(|key| TxOut {
+                    amount: 10_000,
+                    script_pubkey: Script::p2tr(key),
+                })
+                .collect(),
+            locktime: 0,
+        };
+        let prevouts = inputs
+            .iter()
+            .map(|input| TxOut {
+                amount: 20_000,
+                script_pubkey: Script::p2wpkh(&hash160(&input.key.public_key().sec(true))),
+            })
+            .collect();
+        (tx, prevouts)
+    }
+
+    #[test]
+    fn test_address() {
+        let (scan, spend) = receiver();
+        let address = Receiver::new(scan.clone(), spend.public_key()).address(Network::Mainnet);
+        let encoded = "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv";
+        assert_eq!(address.to_string(), encoded);
+        assert_eq!(SilentPaymentAddress::parse(encoded), Ok(address));
+
+        let testnet = SilentPaymentAddress::new(scan.public_key(), spend.public_key(), Network::Testnet);
+        assert!(testnet.to_string().starts_with("tsp1q"));
+        assert_eq!(SilentPaymentAddress::parse(&testnet.to_string()), Ok(testnet));
+        assert_eq!(
+            SilentPaymentAddress::parse("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            Err(SilentPaymentError::UnknownHrp("bc".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_sender_outputs() {
+        // computed independently with libsecp256k1
+        let (scan, spend) = receiver();
+        let address = SilentPaymentAddress::new(scan.public_key(), spend.public_key(), Network::Mainnet);
+        assert_eq!(
+            hex::encode(sender_outputs(&inputs(false), std::slice::from_ref(&address)).unwrap()[0]),
+            "eee78f4383ed1a7147f7beb5bcaf4762d8c11708b77f9df1bbe54eb98c2a8e50"
+        );
+
+        // the first key has odd y, so counts negated as a taproot key
+        let mut taproot = inputs(true);
+        taproot[1].outpoint.vout = 3;
+        taproot[1].key = key("fc8716a97a48ba9a05a98ae47b5cd201a25a7fd5d8b73c203c5f7b6b6b3b6ad7");
+        let outputs = sender_outputs(&taproot, &[address.clone(), address]).unwrap();
+        assert_eq!(
+            hex::encode(outputs[0]),
+            "de88bea8e7ffc9ce1af30d1132f910323c505185aec8eae361670421e749a1fb"
+        );
+        assert_eq!(
+            hex::encode(outputs[1]),
+            "37f29e6dc4edcd466bb3361f9f60511f8041d7113ed2fbb82a098b05c5053904"
+        );
+
+        assert_eq!(sender_outputs(&[], &[]), Err(SilentPaymentError::NoInputs));
+    }
+
+    #[test]
+    fn test_scan() {
+        let (scan, spend) = receiver();
+        let receiver = Receiver::new(scan, spend.public_key());
+        let sender_inputs = inputs(false);
+        let paid = sender_outputs(&sender_inputs, &[receiver.address(Network::Mainnet)]).unwrap();
+        let (tx, prevouts) = p2wpkh_spend(&sender_inputs, &[[7; 32], paid[0]]);
+
+        let found = receiver.scan(&tx, &prevouts).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].vout, found[0].output_key, found[0].label), (1, paid[0], None));
+        assert_eq!(xonly(&found[0].spend_key(&spend).unwrap().public_key()), paid[0]);
+
+        // someone else's payment isn't found
+        let (other, _) = p2wpkh_spend(&inputs(false)[..1], &[paid[0]]);
+        assert!(receiver.scan(&other, &prevouts[..1]).unwrap().is_empty());
+        assert_eq!(
+            receiver.scan(&tx, &prevouts[..1]),
+            Err(SilentPaymentError::PrevoutCount { expected: 2, got: 1 })
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let (scan, spend) = receiver();
+        let receiver = Receiver::new(scan.clone(), spend.public_key()).with_label(1).unwrap();
+        let labelled = receiver.labelled_address(1, Network::Mainnet).unwrap();
+        assert_eq!(
+            hex::encode(label_tweak(&scan, 1)),
+            "6991cb5ce09ab332d7067838bd183f4da1e9b633743f8616a03fb6cdd8956825"
+        );
+        assert_eq!(
+            labelled.to_string(),
+            "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqaxww2fnhrx05cghth75n0qcj59e3e2anscr0q9wyknjxtxycg07y3pevyj"
+        );
+
+        // two payments, one to the labelled address
+        let sender_inputs = inputs(false);
+        let paid = sender_outputs(&sender_inputs, &[labelled, receiver.address(Network::Mainnet)]).unwrap();
+        assert_eq!(
+            hex::encode(paid[0]),
+            "4565eed05cdd32761bb6bf788aff75b87c1e68a35c695e89184fcfcfc85636d4"
+        );
+        let (tx, prevouts) = p2wpkh_spend(&sender_inputs, &paid);
+        let found = receiver.scan(&tx, &prevouts).unwrap();
+        assert_eq!(found.len(), 2);
+        for output in &found {
+            assert_eq!(
+                xonly(&output.spend_key(&spend).unwrap().public_key()),
+                output.output_key
+            );
+        }
+        assert_eq!(found.iter().find(|output| output.vout == 0).unwrap().label, Some(1));
+        assert_eq!(found.iter().find(|output| output.vout == 1).unwrap().label, None);
+    }
+
+    #[test]
+    fn test_input_public_key() {
+        let private = key("eadc78165ff1f8ea94ad7cfdc54990738a4c53f6e0507b42154201b8e5dff3b1");
+        let public = private.public_key();
+        let sec = public.sec(true);
+        let pubkey_hash = hash160(&sec);
+        let input = |script_sig: Script, witness: Vec<Vec<u8>>| TxIn {
+            previous_output: OutPoint::NULL,
+            script_sig,
+            sequence: 0xffffffff,
+            witness,
+        };
+
+        let p2pkh = input(Script::new().push_slice(&[0x30; 71]).push_slice(&sec), vec![]);
+        assert_eq!(
+            input_public_key(&p2pkh, &Script::p2pkh(&pubkey_hash)),
+            Some(public.clone())
+        );
+        let uncompressed = input(Script::new().push_slice(&public.sec(false)), vec![]);
+        assert_eq!(
+            input_public_key(&uncompressed, &Script::p2pkh(&hash160(&public.sec(false)))),
+            None
+        );
+
+        let redeem = Script::p2wpkh(&pubkey_hash);
+        let nested = input(
+            Script::new().push_slice(redeem.as_bytes()),
+            vec![vec![0x30; 71], sec.clone()],
+        );
+        assert_eq!(
+            input_public_key(&nested, &Script::p2sh(&hash160(redeem.as_bytes()))),
+            Some(public.clone())
+        );
+
+        let output_key = xonly(&public);
+        let keypath = input(Script::new(), vec![vec![0; 64]]);
+        assert_eq!(
+            input_public_key(&keypath, &Script::p2tr(&output_key)),
+            lift_x(&output_key).ok()
+        );
+        let mut control_block = vec![0xc0];
+        control_block.extend_from_slice(&NUMS_H);
+        let nums = input(Script::new(), vec![vec![0x51], control_block]);
+        assert_eq!(input_public_key(&nums, &Script::p2tr(&output_key)), None);
+
+        assert_eq!(input_public_key(&keypath, &Script::p2wsh(&[0; 32])), None);
+    }
+}
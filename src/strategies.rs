@@ -0,0 +1,202 @@
+// proptest strategies for the core types, for property tests here and in
+// downstream crates. Values are drawn from the whole domain rather than
+// small numbers, and the secp256k1 ones are on the real curve
+
+use num_bigint::{BigInt, BigUint};
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::arithmetic::field_element::FieldElement;
+use crate::curves::ec_point::Point;
+use crate::curves::secp256k1::ecc::{generator, order, prime, PrivateKey, Signature};
+use crate::script::Script;
+use crate::tx::{OutPoint, Transaction, TxIn, TxOut};
+
+/// An element of F_p for the given prime, uniform up to a negligible bias
+pub fn field_element(prime: BigInt) -> impl Strategy<Value = FieldElement> {
+    let bytes = (prime.bits() as usize).div_ceil(8) + 16;
+    vec(any::<u8>(), bytes).prop_map(move |b| {
+        FieldElement::reduce(BigInt::from(BigUint::from_bytes_be(&b)), prime.clone()).expect("prime is at least 2")
+    })
+}
+
+/// A scalar in [1, n) for secp256k1's order n
+pub fn scalar() -> impl Strategy<Value = BigUint> {
+    any::<[u8; 32]>().prop_map(|b| BigUint::from_bytes_be(&b) % (order() - 1_u8) + 1_u8)
+}
+
+/// k G for a random scalar k, so never the point at infinity
+pub fn point() -> impl Strategy<Value = Point> {
+    scalar().prop_map(|k| BigInt::from(k) * generator())
+}
+
+/// (r, s) with both in [1, n). Almost never a valid signature for
+/// anything; sign with an arbitrary key for those
+pub fn signature() -> impl Strategy<Value = Signature> {
+    (scalar(), scalar()).prop_map(|(r, s)| Signature::from(r, s))
+}
+
+pub fn script() -> impl Strategy<Value = Script> {
+    vec(any::<u8>(), 0..64).prop_map(Script::from_bytes)
+}
+
+pub fn tx_in() -> impl Strategy<Value = TxIn> {
+    (
+        any::<[u8; 32]>(),
+        any::<u32>(),
+        script(),
+        any::<u32>(),
+        vec(vec(any::<u8>(), 0..73), 0..3),
+    )
+        .prop_map(|(txid, vout, script_sig, sequence, witness)| TxIn {
+            previous_output: OutPoint { txid, vout },
+            script_sig,
+            sequence,
+            witness,
+        })
+}
+
+pub fn tx_out() -> impl Strategy<Value = TxOut> {
+    (any::<u64>(), script()).prop_map(|(amount, script_pubkey)| TxOut { amount, script_pubkey })
+}
+
+/// At least one input, since a legacy transaction without inputs reads
+/// as the segwit marker. Each witness may or may not be empty, so both
+/// serializations come up
+pub fn transaction() -> impl Strategy<Value = Transaction> {
+    (any::<u32>(), vec(tx_in(), 1..4), vec(tx_out(), 0..4), any::<u32>()).prop_map(
+        |(version, inputs, outputs, locktime)| Transaction {
+            version,
+            inputs,
+            outputs,
+            locktime,
+        },
+    )
+}
+
+/// Elements of secp256k1's base field
+impl Arbitrary for FieldElement {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        field_element(prime()).boxed()
+    }
+}
+
+impl Arbitrary for Point {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        point().boxed()
+    }
+}
+
+impl Arbitrary for PrivateKey {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        scalar()
+            .prop_map(|secret| PrivateKey::from(secret).expect("scalar is in [1, n)"))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Signature {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        signature().boxed()
+    }
+}
+
+impl Arbitrary for Transaction {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        transaction().boxed()
+    }
+}
+
+#[cfg(test)]
+mod strategies_tests {
+    use super::*;
+    use crate::curves::secp256k1::ecc::{infinity, PublicKey};
+
+    fn zero() -> FieldElement {
+        FieldElement::from(BigInt::from(0_u8), prime()).unwrap()
+    }
+
+    fn one() -> FieldElement {
+        FieldElement::from(BigInt::from(1_u8), prime()).unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn field_laws(a: FieldElement, b: FieldElement, c: FieldElement) {
+            prop_assert_eq!(a.clone() + b.clone(), b.clone() + a.clone());
+            prop_assert_eq!(a.clone() * b.clone(), b.clone() * a.clone());
+            prop_assert_eq!((a.clone() + b.clone()) + c.clone(), a.clone() + (b.clone() + c.clone()));
+            prop_assert_eq!((a.clone() * b.clone()) * c.clone(), a.clone() * (b.clone() * c.clone()));
+            prop_assert_eq!(a.clone() * (b.clone() + c.clone()), a.clone() * b.clone() + a.clone() * c);
+            prop_assert_eq!(a.clone() + zero(), a.clone());
+            prop_assert_eq!(a.clone() * one(), a.clone());
+            prop_assert_eq!(a.clone() - a.clone(), zero());
+            if a != zero() {
+                prop_assert_eq!(a.clone() / a, one());
+            }
+        }
+
+        #[test]
+        fn field_in_other_primes(a in field_element(BigInt::from(223)), b in field_element(BigInt::from(223))) {
+            prop_assert!(a.num() < BigInt::from(223));
+            prop_assert_eq!((a.clone() - b.clone()) + b, a);
+        }
+
+        #[test]
+        fn transaction_round_trip(tx: Transaction) {
+            prop_assert_eq!(Transaction::from_bytes(&tx.serialize()).unwrap(), tx.clone());
+            prop_assert_eq!(Transaction::from_hex(&tx.to_hex()).unwrap(), tx);
+        }
+
+        #[test]
+        fn der_round_trip(signature: Signature) {
+            prop_assert_eq!(Signature::parse_der(&signature.der()).unwrap(), signature);
+        }
+    }
+
+    // point arithmetic is slow enough that a few cases go a long way
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn group_laws(p: Point, q: Point, r: Point) {
+            prop_assert_eq!(p.clone() + q.clone(), q.clone() + p.clone());
+            prop_assert_eq!((p.clone() + q.clone()) + r.clone(), p.clone() + (q + r));
+            prop_assert_eq!(p.clone() + infinity(), p.clone());
+            prop_assert_eq!(BigInt::from(order()) * p, infinity());
+        }
+
+        #[test]
+        fn scalar_mul_distributes(a in scalar(), b in scalar()) {
+            let g = generator();
+            let sum = BigInt::from((&a + &b) % order()) * g.clone();
+            prop_assert_eq!(sum, BigInt::from(a) * g.clone() + BigInt::from(b) * g);
+        }
+
+        #[test]
+        fn keys_and_signatures(key: PrivateKey, z in scalar()) {
+            let public = key.public_key();
+            prop_assert_eq!(PublicKey::parse(&public.sec(true)).unwrap(), public.clone());
+            prop_assert_eq!(PublicKey::parse(&public.sec(false)).unwrap(), public.clone());
+            let signature = key.sign(&z);
+            prop_assert!(public.verify(&z, &signature));
+            prop_assert!(!public.verify(&(z + 1_u8), &signature));
+        }
+    }
+}
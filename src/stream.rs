@@ -0,0 +1,289 @@
+use std::io::{self, BufRead, BufReader, Read};
+
+use crate::block::BlockHeader;
+use crate::encoding::compact_size;
+use crate::encoding::decode::{Context, CountingReader, DecodeError};
+use crate::tx::{Transaction, TxError};
+
+/// Turns a stream of hex text into the bytes it spells, a chunk at a time.
+/// Whitespace between digits is skipped, so one transaction per line works
+pub struct HexReader<R> {
+    inner: BufReader<R>,
+}
+
+impl<R: Read> HexReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: BufReader::new(inner),
+        }
+    }
+
+    /// The next hex digit's value, None at the end of the input
+    fn next_digit(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            let Some(&c) = self.inner.fill_buf()?.first() else {
+                return Ok(None);
+            };
+            self.inner.consume(1);
+            if c.is_ascii_whitespace() {
+                continue;
+            }
+            return match (c as char).to_digit(16) {
+                Some(digit) => Ok(Some(digit as u8)),
+                None => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid hex digit")),
+            };
+        }
+    }
+}
+
+impl<R: Read> Read for HexReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let Some(high) = self.next_digit()? else {
+                return Ok(i);
+            };
+            let Some(low) = self.next_digit()? else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "odd number of hex digits"));
+            };
+            *byte = high << 4 | low;
+        }
+        Ok(buf.len())
+    }
+}
+
+/// Where a long parse has got to, reported after every transaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Bytes consumed so far, block header and counts included
+    pub bytes_read: u64,
+    pub transactions: usize,
+    /// The block's transaction count, unknown for plain dumps
+    pub total: Option<usize>,
+}
+
+/// Decodes transactions one at a time from a reader, holding only the
+/// current one in memory: either raw transactions back to back until the
+/// stream ends, or the transactions of a serialized block. Stops after
+/// the first error, located by the transaction's index and the bytes read
+pub struct TransactionStream<R, F = fn(&Progress)> {
+    reader: CountingReader<R>,
+    /// Transactions left in the block; None for a plain dump
+    remaining: Option<usize>,
+    total: Option<usize>,
+    decoded: usize,
+    progress: F,
+    done: bool,
+}
+
+impl<R: Read> TransactionStream<R> {
+    /// Transactions back to back, as in a mempool dump
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: CountingReader::new(reader),
+            remaining: None,
+            total: None,
+            decoded: 0,
+            progress: |_| {},
+            done: false,
+        }
+    }
+
+    /// The transactions of a block, after reading its header and count
+    pub fn block(reader: R) -> Result<(BlockHeader, Self), DecodeError<TxError>> {
+        let mut stream = Self::new(reader);
+        let header = BlockHeader::parse(&mut stream.reader)
+            .context("header")
+            .map_err(|e| e.at(stream.reader.count() as usize))?;
+        let count = compact_size::read_size(&mut stream.reader)
+            .context("txdata")
+            .map_err(|e| e.at(stream.reader.count() as usize))?;
+        stream.remaining = Some(count);
+        stream.total = Some(count);
+        Ok((header, stream))
+    }
+}
+
+impl<R: Read, F: FnMut(&Progress)> TransactionStream<R, F> {
+    /// Calls `progress` after each decoded transaction
+    pub fn on_progress<G: FnMut(&Progress)>(self, progress: G) -> TransactionStream<R, G> {
+        TransactionStream {
+            reader: self.reader,
+            remaining: self.remaining,
+            total: self.total,
+            decoded: self.decoded,
+            progress,
+            done: self.done,
+        }
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.reader.count()
+    }
+
+    fn decode(&mut self) -> Option<Result<Transaction, DecodeError<TxError>>> {
+        let res = match self.remaining {
+            Some(0) => None,
+            Some(_) => Some(Transaction::parse(&mut self.reader)),
+            None => {
+                // a dump may end between transactions, but not inside one
+                let mut first = [0_u8];
+                match self.reader.read(&mut first) {
+                    Ok(0) => None,
+                    Ok(_) => Some(Transaction::parse(&mut first.as_slice().chain(&mut self.reader))),
+                    Err(e) => Some(Err(TxError::from(e).into())),
+                }
+            }
+        };
+        res.map(|res| {
+            res.context(format_args!("[{}]", self.decoded))
+                .map_err(|e| e.at(self.bytes_read() as usize))
+        })
+    }
+}
+
+impl<R: Read, F: FnMut(&Progress)> Iterator for TransactionStream<R, F> {
+    type Item = Result<Transaction, DecodeError<TxError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.decode();
+        match &res {
+            Some(Ok(_)) => {
+                self.decoded += 1;
+                if let Some(remaining) = &mut self.remaining {
+                    *remaining -= 1;
+                }
+                let progress = Progress {
+                    bytes_read: self.reader.count(),
+                    transactions: self.decoded,
+                    total: self.total,
+                };
+                (self.progress)(&progress);
+            }
+            Some(Err(_)) | None => self.done = true,
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use super::*;
+    use crate::address::Network;
+    use crate::block::Block;
+    use crate::script::Script;
+    use crate::tx::{OutPoint, TxIn, TxOut};
+
+    fn transactions(n: u32) -> Vec<Transaction> {
+        (0..n)
+            .map(|i| Transaction {
+                version: 2,
+                inputs: vec![TxIn {
+                    previous_output: OutPoint {
+                        txid: [i as u8; 32],
+                        vout: i,
+                    },
+                    script_sig: Script::new(),
+                    sequence: 0xfffffffd,
+                    // every other one in the segwit format
+                    witness: if i % 2 == 0 {
+                        vec![vec![i as u8; 72], vec![2; 33]]
+                    } else {
+                        Vec::new()
+                    },
+                }],
+                outputs: vec![TxOut {
+                    amount: 1000 * i as u64,
+                    script_pubkey: Script::p2wpkh(&[i as u8; 20]),
+                }],
+                locktime: i,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let txs = transactions(5);
+        let dump: String = txs.iter().map(|tx| format!("{}\n", tx.to_hex())).collect();
+
+        let mut reports = Vec::new();
+        let decoded: Vec<Transaction> = TransactionStream::new(HexReader::new(dump.as_bytes()))
+            .on_progress(|progress| reports.push(*progress))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(decoded, txs);
+        assert_eq!(reports.len(), 5);
+        assert_eq!(
+            reports[4].bytes_read,
+            txs.iter().map(|tx| tx.serialize().len() as u64).sum::<u64>()
+        );
+        assert_eq!(reports[4].transactions, 5);
+        assert_eq!(reports[4].total, None);
+
+        // a dump cut off inside a transaction is an error, then the stream
+        // ends; this one loses the locktime and the last witness byte
+        let cut = &dump.as_bytes()[..dump.len() - 11];
+        let mut stream = TransactionStream::new(HexReader::new(cut));
+        assert_eq!(stream.by_ref().take(4).count(), 4);
+        let err = stream.next().unwrap().unwrap_err();
+        let offset = cut.iter().filter(|c| c.is_ascii_hexdigit()).count() / 2;
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "transaction is truncated at byte {} in [4].inputs[0].witness[1]",
+                offset
+            )
+        );
+        assert_eq!(stream.next(), None);
+
+        let mut stream = TransactionStream::new(HexReader::new("0200zz".as_bytes()));
+        assert_eq!(stream.next().unwrap().unwrap_err().into_kind(), TxError::InvalidHex);
+        assert_eq!(
+            HexReader::new("abc".as_bytes())
+                .read_to_end(&mut Vec::new())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_block() {
+        let block = Block {
+            header: BlockHeader::genesis(Network::Regtest),
+            txdata: transactions(3),
+        };
+        let bytes = block.serialize();
+
+        let (header, stream) = TransactionStream::block(bytes.as_slice()).unwrap();
+        assert_eq!(header, block.header);
+        let mut last = None;
+        let txs: Vec<Transaction> = stream
+            .on_progress(|progress| last = Some(*progress))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(txs, block.txdata);
+        assert_eq!(
+            last,
+            Some(Progress {
+                bytes_read: bytes.len() as u64,
+                transactions: 3,
+                total: Some(3)
+            })
+        );
+
+        // exactly the block's transactions are read, whatever follows
+        let mut followed = bytes.clone();
+        followed.extend_from_slice(&[0xff; 10]);
+        let (_, stream) = TransactionStream::block(followed.as_slice()).unwrap();
+        assert_eq!(stream.count(), 3);
+
+        let (_, mut stream) = TransactionStream::block(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(stream.by_ref().take(2).all(|tx| tx.is_ok()));
+        let err = stream.next().unwrap().unwrap_err();
+        assert_eq!((err.kind(), err.offset()), (&TxError::Truncated, Some(bytes.len() - 1)));
+        assert_eq!(err.path(), ["[2]", "locktime"]);
+    }
+}
@@ -0,0 +1,355 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+
+use crate::crypto::hash::tagged_hash;
+use crate::crypto::schnorr::{even_y_secret, lift_x, xonly};
+use crate::curves::secp256k1::ecc::{KeyError, PrivateKey};
+use crate::script::Script;
+
+/// Leaf version of BIP-342 tapscript
+pub const LEAF_VERSION_TAPSCRIPT: u8 = 0xc0;
+
+/// tagged_hash("TapLeaf", leaf_version || compact_size(script) || script)
+pub fn tap_leaf_hash(leaf_version: u8, script: &Script) -> [u8; 32] {
+    let mut data = vec![leaf_version];
+    script.write(&mut data).unwrap();
+    tagged_hash("TapLeaf", &data)
+}
+
+/// tagged_hash("TapTweak", P || merkle_root); a key-path-only output
+/// commits to P alone
+pub fn tap_tweak_hash(internal_key: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> [u8; 32] {
+    let mut data = internal_key.to_vec();
+    if let Some(root) = merkle_root {
+        data.extend_from_slice(root);
+    }
+    tagged_hash("TapTweak", &data)
+}
+
+/// The output key Q = P + t * G, returned x-only with whether its y is odd
+/// (the parity bit control blocks carry)
+pub fn tweak_public_key(internal_key: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> Result<([u8; 32], bool), KeyError> {
+    let tweak = BigUint::from_bytes_be(&tap_tweak_hash(internal_key, merkle_root));
+    let output = lift_x(internal_key)?.add_tweak(&tweak)?;
+    Ok((xonly(&output), output.point().y().unwrap().is_odd()))
+}
+
+/// The secret for the output key: the even-y internal secret plus the tweak
+pub fn tweak_private_key(internal_key: &PrivateKey, merkle_root: Option<&[u8; 32]>) -> Result<PrivateKey, KeyError> {
+    let even = PrivateKey::from(even_y_secret(internal_key))?;
+    let tweak = BigUint::from_bytes_be(&tap_tweak_hash(&xonly(&internal_key.public_key()), merkle_root));
+    even.add_tweak(&tweak)
+}
+
+/// tagged_hash("TapBranch", min(a, b) || max(a, b)). Sorting the children
+/// means control blocks don't need to say which side a sibling is on
+pub fn tap_branch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    tagged_hash("TapBranch", &[&left[..], &right[..]].concat())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaprootError {
+    Key(KeyError),
+    /// Wrong length, or more than 128 path elements
+    InvalidControlBlock(usize),
+}
+
+impl Display for TaprootError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaprootError::Key(e) => write!(f, "{}", e),
+            TaprootError::InvalidControlBlock(len) => write!(f, "invalid control block of {} bytes", len),
+        }
+    }
+}
+
+impl std::error::Error for TaprootError {}
+
+impl From<KeyError> for TaprootError {
+    fn from(e: KeyError) -> Self {
+        TaprootError::Key(e)
+    }
+}
+
+/// A tree of spending scripts, built bottom-up from leaves and branches.
+/// Shallower leaves get shorter control blocks, so likelier scripts should
+/// sit nearer the root
+#[derive(Debug, Clone, PartialEq)]
+pub enum TapTree {
+    Leaf { version: u8, script: Script },
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+    /// A tapscript leaf
+    pub fn leaf(script: Script) -> Self {
+        TapTree::Leaf {
+            version: LEAF_VERSION_TAPSCRIPT,
+            script,
+        }
+    }
+
+    pub fn branch(left: TapTree, right: TapTree) -> Self {
+        TapTree::Branch(Box::new(left), Box::new(right))
+    }
+
+    /// The leaf hash of a leaf, the branch hash of a branch; the merkle root
+    /// when called on the whole tree
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            TapTree::Leaf { version, script } => tap_leaf_hash(*version, script),
+            TapTree::Branch(left, right) => tap_branch_hash(&left.hash(), &right.hash()),
+        }
+    }
+
+    /// Every leaf with its merkle path: sibling hashes from the leaf upwards
+    pub fn leaves(&self) -> Vec<(u8, &Script, Vec<[u8; 32]>)> {
+        match self {
+            TapTree::Leaf { version, script } => vec![(*version, script, Vec::new())],
+            TapTree::Branch(left, right) => {
+                let (left_hash, right_hash) = (left.hash(), right.hash());
+                let mut res = left.leaves();
+                for (_, _, path) in res.iter_mut() {
+                    path.push(right_hash);
+                }
+                for (version, script, mut path) in right.leaves() {
+                    path.push(left_hash);
+                    res.push((version, script, path));
+                }
+                res
+            }
+        }
+    }
+}
+
+/// Proves a script is committed to by an output key: the internal key, the
+/// output key's parity and the merkle path from the script's leaf
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlBlock {
+    pub leaf_version: u8,
+    pub output_key_odd: bool,
+    pub internal_key: [u8; 32],
+    pub merkle_branch: Vec<[u8; 32]>,
+}
+
+impl ControlBlock {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = vec![self.leaf_version | self.output_key_odd as u8];
+        res.extend_from_slice(&self.internal_key);
+        for hash in &self.merkle_branch {
+            res.extend_from_slice(hash);
+        }
+        res
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, TaprootError> {
+        let len = bytes.len();
+        if len < 33 || !(len - 33).is_multiple_of(32) || (len - 33) / 32 > 128 {
+            return Err(TaprootError::InvalidControlBlock(len));
+        }
+        Ok(Self {
+            leaf_version: bytes[0] & 0xfe,
+            output_key_odd: bytes[0] & 1 == 1,
+            internal_key: bytes[1..33].try_into().unwrap(),
+            merkle_branch: bytes[33..].chunks(32).map(|c| c.try_into().unwrap()).collect(),
+        })
+    }
+
+    /// Whether `script` under this control block commits to `output_key`,
+    /// as the BIP-341 script-path check does
+    pub fn verify(&self, output_key: &[u8; 32], script: &Script) -> bool {
+        let root = self
+            .merkle_branch
+            .iter()
+            .fold(tap_leaf_hash(self.leaf_version, script), |node, sibling| {
+                tap_branch_hash(&node, sibling)
+            });
+        match tweak_public_key(&self.internal_key, Some(&root)) {
+            Ok((key, odd)) => key == *output_key && odd == self.output_key_odd,
+            Err(_) => false,
+        }
+    }
+}
+
+/// Everything needed to spend a taproot output: the keys, the merkle root
+/// and, for script paths, the tree the control blocks come from
+#[derive(Debug, Clone)]
+pub struct TaprootSpendInfo {
+    internal_key: [u8; 32],
+    tree: Option<TapTree>,
+    merkle_root: Option<[u8; 32]>,
+    output_key: [u8; 32],
+    output_key_odd: bool,
+}
+
+impl TaprootSpendInfo {
+    pub fn new(internal_key: [u8; 32], tree: Option<TapTree>) -> Result<Self, TaprootError> {
+        let merkle_root = tree.as_ref().map(TapTree::hash);
+        let (output_key, output_key_odd) = tweak_public_key(&internal_key, merkle_root.as_ref())?;
+        Ok(Self {
+            internal_key,
+            tree,
+            merkle_root,
+            output_key,
+            output_key_odd,
+        })
+    }
+
+    pub fn internal_key(&self) -> &[u8; 32] {
+        &self.internal_key
+    }
+
+    pub fn merkle_root(&self) -> Option<&[u8; 32]> {
+        self.merkle_root.as_ref()
+    }
+
+    pub fn output_key(&self) -> &[u8; 32] {
+        &self.output_key
+    }
+
+    pub fn script_pubkey(&self) -> Script {
+        Script::p2tr(&self.output_key)
+    }
+
+    /// The control block for a tapscript leaf, if the tree contains it
+    pub fn control_block(&self, script: &Script) -> Option<ControlBlock> {
+        let leaves = self.tree.as_ref()?.leaves();
+        let (leaf_version, _, merkle_branch) = leaves
+            .into_iter()
+            .find(|(version, s, _)| *version == LEAF_VERSION_TAPSCRIPT && *s == script)?;
+        Some(ControlBlock {
+            leaf_version,
+            output_key_odd: self.output_key_odd,
+            internal_key: self.internal_key,
+            merkle_branch,
+        })
+    }
+}
+
+#[cfg(test)]
+mod taproot_tests {
+    use super::*;
+
+    fn array(hex: &str) -> [u8; 32] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_tweak_public_key() {
+        // scriptPubKey vectors from BIP-341
+        let internal = array("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+        assert_eq!(
+            hex::encode(tap_tweak_hash(&internal, None)),
+            "b86e7be8f39bab32a6f2c0443abbc210f0edac0e2c53d501b36b64437d9c6c70"
+        );
+        let (output, odd) = tweak_public_key(&internal, None).unwrap();
+        assert_eq!(
+            hex::encode(output),
+            "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+        );
+        assert!(odd);
+
+        let internal = array("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+        let root = array("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21");
+        let (output, _) = tweak_public_key(&internal, Some(&root)).unwrap();
+        assert_eq!(
+            hex::encode(output),
+            "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+        );
+    }
+
+    #[test]
+    fn test_tweak_private_key() {
+        for secret in [1_u32, 2, 3, 0xdeadbeef] {
+            let key = PrivateKey::from(BigUint::from(secret)).unwrap();
+            let internal = xonly(&key.public_key());
+            let root = [secret as u8; 32];
+            for merkle_root in [None, Some(&root)] {
+                let tweaked = tweak_private_key(&key, merkle_root).unwrap();
+                let (output, _) = tweak_public_key(&internal, merkle_root).unwrap();
+                assert_eq!(xonly(&tweaked.public_key()), output);
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_leaf_control_block() {
+        // BIP-341 scriptPubKey vector 1
+        let internal = array("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+        let script = Script::from_hex("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac").unwrap();
+        let info = TaprootSpendInfo::new(internal, Some(TapTree::leaf(script.clone()))).unwrap();
+
+        assert_eq!(
+            hex::encode(info.merkle_root().unwrap()),
+            "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+        );
+        assert_eq!(
+            info.script_pubkey().to_hex(),
+            "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+        );
+        let control_block = info.control_block(&script).unwrap();
+        assert_eq!(
+            hex::encode(control_block.serialize()),
+            "c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"
+        );
+        assert!(control_block.verify(info.output_key(), &script));
+    }
+
+    #[test]
+    fn test_tree_control_blocks() {
+        // A at depth 1, B and C at depth 2; expected values from rust-bitcoin
+        let internal = array("93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820");
+        let a = Script::from_hex("51").unwrap();
+        let b = Script::from_hex("52").unwrap();
+        let c = Script::from_hex("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac").unwrap();
+        let tree = TapTree::branch(
+            TapTree::leaf(a.clone()),
+            TapTree::branch(TapTree::leaf(b.clone()), TapTree::leaf(c.clone())),
+        );
+        let info = TaprootSpendInfo::new(internal, Some(tree)).unwrap();
+
+        assert_eq!(
+            hex::encode(info.merkle_root().unwrap()),
+            "5b5368ba07b8323c1d1afa439f3cbdfa0034a515a5720acaec484e5ffceb21dd"
+        );
+        assert_eq!(
+            hex::encode(info.output_key()),
+            "f40fa6561293e76b5fd58ab891796ecd93c16aabaa9ed5f9a6352d11e9da6432"
+        );
+
+        let prefix = "c093478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820";
+        let cases = [
+            (&a, "67b333f4782d8140f91f8cb5bc80cdaaabfe230a51c452baddf1e8c29e3810cc"),
+            (
+                &b,
+                "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21\
+                 a85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d675",
+            ),
+            (
+                &c,
+                "c276fef1386890619b80e10a4a328572d97493add269df1a15a7f89f8ae8ec09\
+                 a85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d675",
+            ),
+        ];
+        for (script, path) in cases {
+            let control_block = info.control_block(script).unwrap();
+            let bytes = control_block.serialize();
+            assert_eq!(hex::encode(&bytes), prefix.to_owned() + path);
+            assert_eq!(ControlBlock::parse(&bytes).unwrap(), control_block);
+            assert!(control_block.verify(info.output_key(), script));
+            assert!(!control_block.verify(info.output_key(), &Script::from_hex("53").unwrap()));
+        }
+
+        assert_eq!(info.control_block(&Script::from_hex("53").unwrap()), None);
+        assert_eq!(
+            ControlBlock::parse(&[0xc0; 34]),
+            Err(TaprootError::InvalidControlBlock(34))
+        );
+        assert_eq!(
+            ControlBlock::parse(&[0xc0; 32]),
+            Err(TaprootError::InvalidControlBlock(32))
+        );
+    }
+}
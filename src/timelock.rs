@@ -0,0 +1,457 @@
+use std::fmt::Display;
+
+use crate::curves::secp256k1::ecc::PublicKey;
+use crate::script::{decode_num, Instruction, Opcode, Script};
+
+/// Locktimes below this are block heights, from it on UNIX timestamps
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+/// BIP-68: a sequence with this bit set has no relative lock
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+/// BIP-68: set for a lock in units of 512 seconds, clear for blocks
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0xffff;
+/// Seconds per unit of a time-based relative lock
+pub const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelockError {
+    /// Heights must stay below LOCKTIME_THRESHOLD
+    InvalidHeight(u32),
+    /// Timestamps must be at least LOCKTIME_THRESHOLD
+    InvalidTime(u32),
+    /// More than 0xffff blocks or 512-second intervals
+    RelativeLockTooLong(u32),
+}
+
+impl Display for TimelockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimelockError::InvalidHeight(height) => write!(f, "{} is a timestamp, not a block height", height),
+            TimelockError::InvalidTime(time) => write!(f, "{} is a block height, not a timestamp", time),
+            TimelockError::RelativeLockTooLong(n) => write!(f, "relative lock of {} does not fit 16 bits", n),
+        }
+    }
+}
+
+impl std::error::Error for TimelockError {}
+
+/// A transaction locktime or CLTV argument: the height or time after which
+/// the transaction may be mined. The two kinds never satisfy each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbsoluteLock {
+    Height(u32),
+    /// UNIX timestamp, compared against the median time past
+    Time(u32),
+}
+
+impl AbsoluteLock {
+    pub fn from_height(height: u32) -> Result<Self, TimelockError> {
+        if height >= LOCKTIME_THRESHOLD {
+            return Err(TimelockError::InvalidHeight(height));
+        }
+        Ok(AbsoluteLock::Height(height))
+    }
+
+    pub fn from_time(time: u32) -> Result<Self, TimelockError> {
+        if time < LOCKTIME_THRESHOLD {
+            return Err(TimelockError::InvalidTime(time));
+        }
+        Ok(AbsoluteLock::Time(time))
+    }
+
+    /// Every u32 is a valid locktime; the threshold decides the kind
+    pub fn from_consensus(n: u32) -> Self {
+        if n < LOCKTIME_THRESHOLD {
+            AbsoluteLock::Height(n)
+        } else {
+            AbsoluteLock::Time(n)
+        }
+    }
+
+    pub fn to_consensus(self) -> u32 {
+        match self {
+            AbsoluteLock::Height(n) | AbsoluteLock::Time(n) => n,
+        }
+    }
+
+    /// Whether a transaction `locktime` has reached this lock, as CLTV
+    /// checks against its argument. None if they are of different kinds
+    pub fn is_satisfied_by(self, locktime: AbsoluteLock) -> Option<bool> {
+        match (self, locktime) {
+            (AbsoluteLock::Height(a), AbsoluteLock::Height(b)) | (AbsoluteLock::Time(a), AbsoluteLock::Time(b)) => {
+                Some(b >= a)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A BIP-68 relative lock: blocks or time that must pass after the spent
+/// output confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeLock {
+    Blocks(u16),
+    /// Units of 512 seconds
+    Intervals(u16),
+}
+
+impl RelativeLock {
+    pub fn from_blocks(blocks: u32) -> Result<Self, TimelockError> {
+        let blocks = u16::try_from(blocks).map_err(|_| TimelockError::RelativeLockTooLong(blocks))?;
+        Ok(RelativeLock::Blocks(blocks))
+    }
+
+    /// Rounds up to whole 512-second intervals, so the lock is never
+    /// shorter than asked
+    pub fn from_seconds(seconds: u32) -> Result<Self, TimelockError> {
+        let intervals = seconds.div_ceil(SEQUENCE_LOCKTIME_GRANULARITY);
+        let intervals = u16::try_from(intervals).map_err(|_| TimelockError::RelativeLockTooLong(intervals))?;
+        Ok(RelativeLock::Intervals(intervals))
+    }
+
+    /// Reads an input sequence or CSV argument. None when the disable flag
+    /// is set; bits outside the type flag and the value are ignored, as
+    /// consensus does
+    pub fn from_sequence(sequence: u32) -> Option<Self> {
+        if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return None;
+        }
+        let value = (sequence & SEQUENCE_LOCKTIME_MASK) as u16;
+        Some(if sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+            RelativeLock::Intervals(value)
+        } else {
+            RelativeLock::Blocks(value)
+        })
+    }
+
+    /// The sequence that enforces this lock. The input's transaction needs
+    /// version 2 or later for it to take effect
+    pub fn to_sequence(self) -> u32 {
+        match self {
+            RelativeLock::Blocks(n) => n as u32,
+            RelativeLock::Intervals(n) => SEQUENCE_LOCKTIME_TYPE_FLAG | n as u32,
+        }
+    }
+
+    /// The lock length in seconds, for time-based locks
+    pub fn seconds(self) -> Option<u32> {
+        match self {
+            RelativeLock::Blocks(_) => None,
+            RelativeLock::Intervals(n) => Some(n as u32 * SEQUENCE_LOCKTIME_GRANULARITY),
+        }
+    }
+}
+
+/// A transaction's nLockTime. Zero and any lock in the past leave the
+/// transaction unlocked; the lock only applies if some input's sequence
+/// isn't final
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockTime(u32);
+
+impl LockTime {
+    pub const ZERO: LockTime = LockTime(0);
+
+    pub fn from_height(height: u32) -> Result<Self, TimelockError> {
+        AbsoluteLock::from_height(height).map(Self::from)
+    }
+
+    pub fn from_time(time: u32) -> Result<Self, TimelockError> {
+        AbsoluteLock::from_time(time).map(Self::from)
+    }
+
+    pub fn from_consensus(n: u32) -> Self {
+        Self(n)
+    }
+
+    pub fn to_consensus(self) -> u32 {
+        self.0
+    }
+
+    pub fn lock(self) -> AbsoluteLock {
+        AbsoluteLock::from_consensus(self.0)
+    }
+}
+
+impl From<AbsoluteLock> for LockTime {
+    fn from(lock: AbsoluteLock) -> Self {
+        Self(lock.to_consensus())
+    }
+}
+
+/// An input's nSequence, which carries three independent signals: whether
+/// the transaction's locktime applies, BIP-125 replaceability and a BIP-68
+/// relative lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sequence(u32);
+
+impl Sequence {
+    /// Final: no relative lock, no replacement, and if every input is
+    /// final the transaction's locktime is ignored
+    pub const MAX: Sequence = Sequence(0xffffffff);
+    /// Lets the locktime apply without signalling replaceability
+    pub const ENABLE_LOCKTIME_NO_RBF: Sequence = Sequence(0xfffffffe);
+    /// Signals BIP-125 replaceability, with no relative lock
+    pub const ENABLE_RBF_NO_LOCKTIME: Sequence = Sequence(0xfffffffd);
+
+    /// Enforces `lock` on the input. Such a sequence also enables the
+    /// locktime and signals replaceability
+    pub fn from_relative(lock: RelativeLock) -> Self {
+        Self(lock.to_sequence())
+    }
+
+    pub fn from_consensus(n: u32) -> Self {
+        Self(n)
+    }
+
+    pub fn to_consensus(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_final(self) -> bool {
+        self == Self::MAX
+    }
+
+    /// Whether the transaction's locktime is enforced because of this input
+    pub fn enables_locktime(self) -> bool {
+        !self.is_final()
+    }
+
+    /// BIP-125: any input below 0xfffffffe makes the transaction replaceable
+    pub fn signals_rbf(self) -> bool {
+        self.0 < Self::ENABLE_LOCKTIME_NO_RBF.0
+    }
+
+    /// The BIP-68 lock, which only applies in version 2 transactions
+    pub fn relative_lock(self) -> Option<RelativeLock> {
+        RelativeLock::from_sequence(self.0)
+    }
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self::MAX
+    }
+}
+
+impl From<RelativeLock> for Sequence {
+    fn from(lock: RelativeLock) -> Self {
+        Self::from_relative(lock)
+    }
+}
+
+/// Either kind of lock a script can enforce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timelock {
+    Absolute(AbsoluteLock),
+    Relative(RelativeLock),
+}
+
+impl Timelock {
+    fn opcode(self) -> Opcode {
+        match self {
+            Timelock::Absolute(_) => Opcode::CheckLockTimeVerify,
+            Timelock::Relative(_) => Opcode::CheckSequenceVerify,
+        }
+    }
+
+    fn to_num(self) -> i64 {
+        match self {
+            Timelock::Absolute(lock) => lock.to_consensus() as i64,
+            Timelock::Relative(lock) => lock.to_sequence() as i64,
+        }
+    }
+}
+
+/// `<n> OP_CHECKLOCKTIMEVERIFY OP_DROP` or `<n> OP_CHECKSEQUENCEVERIFY OP_DROP`,
+/// to put in front of a script that should only become spendable once the
+/// lock has passed. The spending transaction must set a matching
+/// locktime or input sequence
+pub fn timelock_prefix(lock: Timelock) -> Script {
+    Script::new()
+        .push_int(lock.to_num())
+        .push_opcode(lock.opcode())
+        .push_opcode(Opcode::Drop)
+}
+
+/// `<n> OP_CHECKLOCKTIMEVERIFY OP_DROP <pubkey> OP_CHECKSIG`: the key can
+/// spend once the chain reaches `lock`
+pub fn cltv_script(lock: AbsoluteLock, public_key: &PublicKey) -> Script {
+    locked_to_key(Timelock::Absolute(lock), public_key)
+}
+
+/// `<n> OP_CHECKSEQUENCEVERIFY OP_DROP <pubkey> OP_CHECKSIG`: the key can
+/// spend once `lock` has passed since the output confirmed
+pub fn csv_script(lock: RelativeLock, public_key: &PublicKey) -> Script {
+    locked_to_key(Timelock::Relative(lock), public_key)
+}
+
+fn locked_to_key(lock: Timelock, public_key: &PublicKey) -> Script {
+    let mut bytes = timelock_prefix(lock).as_bytes().to_vec();
+    bytes.extend_from_slice(
+        Script::new()
+            .push_slice(&public_key.sec(true))
+            .push_opcode(Opcode::CheckSig)
+            .as_bytes(),
+    );
+    Script::from_bytes(bytes)
+}
+
+/// The lock a script starts with, if it starts with a timelock prefix.
+/// CLTV and CSV read at most 5-byte numbers and fail on negative ones
+pub fn parse_timelock_prefix(script: &Script) -> Option<Timelock> {
+    let mut instructions = script.instructions();
+    let n = match instructions.next()?.ok()? {
+        Instruction::Op(op) => op.small_int()? as i64,
+        Instruction::Push(_, data) if data.len() <= 5 => decode_num(data)?,
+        _ => return None,
+    };
+    let n = u32::try_from(n).ok()?;
+    let lock = match instructions.next()?.ok()? {
+        Instruction::Op(Opcode::CheckLockTimeVerify) => Timelock::Absolute(AbsoluteLock::from_consensus(n)),
+        Instruction::Op(Opcode::CheckSequenceVerify) => Timelock::Relative(RelativeLock::from_sequence(n)?),
+        _ => return None,
+    };
+    if instructions.next()?.ok()? != Instruction::Op(Opcode::Drop) {
+        return None;
+    }
+    // a non-minimal push or extra sequence bits would read back differently
+    script
+        .as_bytes()
+        .starts_with(timelock_prefix(lock).as_bytes())
+        .then_some(lock)
+}
+
+/// Recognises the scripts of `cltv_script` and `csv_script`
+pub fn parse_timelocked_key(script: &Script) -> Option<(Timelock, PublicKey)> {
+    let lock = parse_timelock_prefix(script)?;
+    let rest = &script.as_bytes()[timelock_prefix(lock).len()..];
+    match rest {
+        [0x21, key @ .., 0xac] if key.len() == 33 => Some((lock, PublicKey::parse(key).ok()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod timelock_tests {
+    use super::*;
+    use crate::curves::secp256k1::ecc::PrivateKey;
+    use num_bigint::BigUint;
+
+    const KEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[test]
+    fn test_absolute_lock() {
+        assert_eq!(
+            AbsoluteLock::from_consensus(499_999_999),
+            AbsoluteLock::Height(499_999_999)
+        );
+        assert_eq!(
+            AbsoluteLock::from_consensus(500_000_000),
+            AbsoluteLock::Time(500_000_000)
+        );
+        assert_eq!(
+            AbsoluteLock::from_height(500_000_000),
+            Err(TimelockError::InvalidHeight(500_000_000))
+        );
+        assert_eq!(
+            AbsoluteLock::from_time(840_000),
+            Err(TimelockError::InvalidTime(840_000))
+        );
+
+        let lock = AbsoluteLock::from_height(840_000).unwrap();
+        assert_eq!(lock.is_satisfied_by(AbsoluteLock::Height(840_000)), Some(true));
+        assert_eq!(lock.is_satisfied_by(AbsoluteLock::Height(839_999)), Some(false));
+        assert_eq!(lock.is_satisfied_by(AbsoluteLock::Time(1_700_000_000)), None);
+    }
+
+    #[test]
+    fn test_relative_lock() {
+        assert_eq!(RelativeLock::from_blocks(144).unwrap().to_sequence(), 144);
+        assert_eq!(
+            RelativeLock::from_blocks(65_536),
+            Err(TimelockError::RelativeLockTooLong(65_536))
+        );
+        // a day is 168.75 intervals, rounded up
+        let day = RelativeLock::from_seconds(86_400).unwrap();
+        assert_eq!(day, RelativeLock::Intervals(169));
+        assert_eq!(day.to_sequence(), 0x004000a9);
+        assert_eq!(day.seconds(), Some(86_528));
+        assert!(RelativeLock::from_seconds(512 * 65_536).is_err());
+
+        assert_eq!(RelativeLock::from_sequence(0xffffffff), None);
+        assert_eq!(RelativeLock::from_sequence(0xfffffffd), None);
+        // unused bits don't matter
+        assert_eq!(RelativeLock::from_sequence(0x00bf0090), Some(RelativeLock::Blocks(144)));
+        assert_eq!(RelativeLock::from_sequence(0x004000a9), Some(day));
+    }
+
+    #[test]
+    fn test_sequence() {
+        assert!(Sequence::MAX.is_final());
+        assert!(!Sequence::MAX.enables_locktime() && !Sequence::MAX.signals_rbf());
+        assert_eq!(Sequence::default(), Sequence::MAX);
+
+        let no_rbf = Sequence::ENABLE_LOCKTIME_NO_RBF;
+        assert!(no_rbf.enables_locktime() && !no_rbf.signals_rbf());
+        let rbf = Sequence::ENABLE_RBF_NO_LOCKTIME;
+        assert!(rbf.enables_locktime() && rbf.signals_rbf());
+        // the high bits disable relative locks
+        assert_eq!(rbf.relative_lock(), None);
+
+        let csv = Sequence::from(RelativeLock::Blocks(144));
+        assert_eq!(csv.to_consensus(), 144);
+        assert!(csv.signals_rbf() && csv.enables_locktime());
+        assert_eq!(csv.relative_lock(), Some(RelativeLock::Blocks(144)));
+    }
+
+    #[test]
+    fn test_locktime() {
+        assert_eq!(LockTime::default(), LockTime::ZERO);
+        assert_eq!(LockTime::from_height(840_000).unwrap().to_consensus(), 840_000);
+        assert_eq!(
+            LockTime::from_height(500_000_000),
+            Err(TimelockError::InvalidHeight(500_000_000))
+        );
+        assert_eq!(
+            LockTime::from_time(1_700_000_000).unwrap().lock(),
+            AbsoluteLock::Time(1_700_000_000)
+        );
+        assert_eq!(LockTime::from_consensus(1).lock(), AbsoluteLock::Height(1));
+    }
+
+    #[test]
+    fn test_timelock_scripts() {
+        let key = PrivateKey::from(BigUint::from(1_u8)).unwrap().public_key();
+        let cltv = cltv_script(AbsoluteLock::Height(500_000), &key);
+        assert_eq!(cltv.to_hex(), format!("0320a107b17521{}ac", KEY));
+        let csv = csv_script(RelativeLock::Blocks(16), &key);
+        assert_eq!(csv.to_hex(), format!("60b27521{}ac", KEY));
+        // timestamps past 2^31 take five bytes
+        let late = cltv_script(AbsoluteLock::Time(0x80000000), &key);
+        assert_eq!(&late.to_hex()[..14], "050000008000b1");
+
+        for (script, lock) in [
+            (&cltv, Timelock::Absolute(AbsoluteLock::Height(500_000))),
+            (&csv, Timelock::Relative(RelativeLock::Blocks(16))),
+            (&late, Timelock::Absolute(AbsoluteLock::Time(0x80000000))),
+        ] {
+            assert_eq!(parse_timelocked_key(script), Some((lock, key.clone())));
+        }
+        let intervals = Timelock::Relative(RelativeLock::Intervals(169));
+        assert_eq!(parse_timelock_prefix(&timelock_prefix(intervals)), Some(intervals));
+
+        for hex in [
+            // negative
+            "0181b175",
+            // non-minimal push of 16
+            "0110b275",
+            // CSV with the disable flag
+            "050000008000b275",
+            // missing OP_DROP
+            "0320a107b1",
+        ] {
+            assert_eq!(parse_timelock_prefix(&Script::from_hex(hex).unwrap()), None, "{}", hex);
+        }
+        assert_eq!(parse_timelocked_key(&Script::from_hex("0320a107b17551").unwrap()), None);
+    }
+}
@@ -0,0 +1,149 @@
+// `tracing` spans around signing, verification, key derivation and parsing,
+// for applications that embed the crate and want to profile or debug a
+// protocol flow. Fields carry public values only: digests, public keys,
+// indices, lengths and outcomes, never a secret key, nonce or seed.
+//
+// Without the `tracing` feature both macros expand to nothing, and their
+// field expressions are never evaluated
+
+/// Opens a debug-level span that lasts until the end of the enclosing block
+macro_rules! trace_span {
+    ($name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($name $(, $($fields)*)?).entered();
+    };
+}
+pub(crate) use trace_span;
+
+/// Records a debug-level event in the current span
+macro_rules! trace_event {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($args)*);
+    };
+}
+pub(crate) use trace_event;
+
+#[cfg(all(test, feature = "tracing"))]
+mod trace_tests {
+    use std::fmt::Debug;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::address::Network;
+    use crate::bip32::ExtendedPrivateKey;
+    use crate::crypto::schnorr;
+    use crate::curves::secp256k1::ecc::{PrivateKey, PublicKey, Signature};
+
+    /// Every span name and field, as "name field=value ..." lines
+    #[derive(Clone, Default)]
+    struct Recorder {
+        lines: Arc<Mutex<Vec<String>>>,
+        next_id: Arc<AtomicU64>,
+    }
+
+    struct Line(String);
+
+    impl Visit for Line {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut line = Line(span.metadata().name().to_string());
+            span.record(&mut line);
+            self.lines.lock().unwrap().push(line.0);
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut line = Line("event".to_string());
+            event.record(&mut line);
+            self.lines.lock().unwrap().push(line.0);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn recorded(f: impl FnOnce()) -> Vec<String> {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), f);
+        let lines = recorder.lines.lock().unwrap().clone();
+        lines
+    }
+
+    #[test]
+    fn test_spans() {
+        let key = PrivateKey::from_bytes(&[0x5a; 32]).unwrap();
+        let lines = recorded(|| {
+            let signature = key.sign(&7_u8.into());
+            assert!(key.public_key().verify(&7_u8.into(), &signature));
+            Signature::parse_der(&signature.der()).unwrap();
+            PublicKey::parse(&key.public_key().sec(true)).unwrap();
+            let sig = schnorr::sign(&key, b"msg", &[0; 32]);
+            assert!(schnorr::verify(&schnorr::xonly(&key.public_key()), b"msg", &sig));
+            let master = ExtendedPrivateKey::from_seed(&[1; 32], Network::Mainnet).unwrap();
+            master.derive_path(&[0x8000_002c, 0]).unwrap();
+        });
+        let names: Vec<&str> = lines.iter().map(|l| l.split(' ').next().unwrap()).collect();
+        for name in [
+            "ecdsa.sign",
+            "ecdsa.verify",
+            "der.parse",
+            "sec1.parse",
+            "schnorr.sign",
+            "schnorr.verify",
+            "bip32.derive",
+        ] {
+            assert!(names.contains(&name), "no {} span in {:?}", name, lines);
+        }
+        assert!(lines.iter().any(|l| l == "event valid=true"), "{:?}", lines);
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with("bip32.derive index=2147483692 depth=0")),
+            "{:?}",
+            lines
+        );
+    }
+
+    #[test]
+    fn test_no_secrets() {
+        let secret = [0x5a; 32];
+        let key = PrivateKey::from_bytes(&secret).unwrap();
+        let lines = recorded(|| {
+            key.sign(&7_u8.into());
+            schnorr::sign(&key, b"msg", &[0; 32]);
+            ExtendedPrivateKey::from_seed(&secret, Network::Mainnet)
+                .unwrap()
+                .derive_child(0)
+                .unwrap();
+        });
+        let secret_hex = hex::encode(secret);
+        let secret_decimal = key.secret().to_string();
+        assert!(!lines.is_empty());
+        for line in &lines {
+            assert!(
+                !line.contains(&secret_hex) && !line.contains(&secret_decimal),
+                "{}",
+                line
+            );
+        }
+    }
+}
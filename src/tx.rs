@@ -0,0 +1,581 @@
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+use crate::crypto::hash::hash256;
+use crate::encoding::compact_size::{self, CompactSizeError};
+use crate::encoding::consensus::impl_consensus_codec;
+use crate::encoding::decode::{decode_slice, Context, DecodeError};
+use crate::encoding::hex_fmt::impl_hex_fmt;
+#[cfg(feature = "serde")]
+use crate::encoding::serde_codec::impl_serde_bytes;
+use crate::script::{OpReturnError, Script};
+use crate::trace::{trace_event, trace_span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxError {
+    Truncated,
+    CompactSize(CompactSizeError),
+    /// Bytes left over after a complete transaction
+    TrailingBytes(usize),
+    InvalidHex,
+    /// The BIP-144 flag byte after the 0x00 marker wasn't 0x01
+    InvalidFlag(u8),
+    /// The witness flag was set but every witness stack was empty
+    SuperfluousWitness,
+}
+
+impl Display for TxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TxError::Truncated => write!(f, "transaction is truncated"),
+            TxError::CompactSize(e) => write!(f, "{}", e),
+            TxError::TrailingBytes(n) => write!(f, "{} trailing bytes after transaction", n),
+            TxError::InvalidHex => write!(f, "invalid transaction hex"),
+            TxError::InvalidFlag(flag) => write!(f, "invalid segwit flag {:#04x}, expected 0x01", flag),
+            TxError::SuperfluousWitness => write!(f, "witness flag set but no witnesses present"),
+        }
+    }
+}
+
+impl std::error::Error for TxError {}
+
+impl From<io::Error> for TxError {
+    /// Readers report malformed input as InvalidData, as `HexReader` does
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::InvalidData => TxError::InvalidHex,
+            _ => TxError::Truncated,
+        }
+    }
+}
+
+// so compact sizes in transaction-like formats take `.context()` directly
+impl From<CompactSizeError> for DecodeError<TxError> {
+    fn from(e: CompactSizeError) -> Self {
+        DecodeError::new(e.into())
+    }
+}
+
+impl From<CompactSizeError> for TxError {
+    fn from(e: CompactSizeError) -> Self {
+        match e {
+            CompactSizeError::UnexpectedEof => TxError::Truncated,
+            e => TxError::CompactSize(e),
+        }
+    }
+}
+
+pub fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], TxError> {
+    let mut buf = [0_u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+pub fn read_u32<R: Read>(reader: &mut R) -> Result<u32, TxError> {
+    Ok(u32::from_le_bytes(read_array(reader)?))
+}
+
+/// A CompactSize length followed by that many bytes
+pub fn read_var_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, TxError> {
+    let len = compact_size::read_size(reader)?;
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(TxError::Truncated);
+    }
+    Ok(buf)
+}
+
+pub fn write_var_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    compact_size::write(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+/// A reference to an output of an earlier transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutPoint {
+    /// In internal byte order, the reverse of how txids are displayed
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+impl OutPoint {
+    /// What coinbase inputs point at
+    pub const NULL: Self = Self {
+        txid: [0; 32],
+        vout: u32::MAX,
+    };
+
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(Self {
+            txid: read_array(reader).context("txid")?,
+            vout: read_u32(reader).context("vout")?,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.txid)?;
+        writer.write_all(&self.vout.to_le_bytes())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxIn {
+    pub previous_output: OutPoint,
+    pub script_sig: Script,
+    pub sequence: u32,
+    /// Serialized separately from the rest of the input, after all outputs
+    pub witness: Vec<Vec<u8>>,
+}
+
+impl TxIn {
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(Self {
+            previous_output: OutPoint::parse(reader).context("previous_output")?,
+            script_sig: Script::parse(reader).context("script_sig")?,
+            sequence: read_u32(reader).context("sequence")?,
+            witness: Vec::new(),
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.previous_output.write(writer)?;
+        self.script_sig.write(writer)?;
+        writer.write_all(&self.sequence.to_le_bytes())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxOut {
+    /// In satoshis
+    pub amount: u64,
+    pub script_pubkey: Script,
+}
+
+impl TxOut {
+    /// A zero-value output carrying `pushes` after OP_RETURN
+    pub fn op_return(pushes: &[&[u8]]) -> Result<Self, OpReturnError> {
+        Ok(Self {
+            amount: 0,
+            script_pubkey: Script::op_return(pushes)?,
+        })
+    }
+
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        Ok(Self {
+            amount: u64::from_le_bytes(read_array(reader).context("amount")?),
+            script_pubkey: Script::parse(reader).context("script_pubkey")?,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.amount.to_le_bytes())?;
+        self.script_pubkey.write(writer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transaction {
+    pub version: u32,
+    pub inputs: Vec<TxIn>,
+    pub outputs: Vec<TxOut>,
+    pub locktime: u32,
+}
+
+impl Transaction {
+    /// Parses either format. A legacy transaction can't have zero inputs,
+    /// so a zero input count is the BIP-144 marker, followed by the flag
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, DecodeError<TxError>> {
+        trace_span!("tx.parse");
+        let version = read_u32(reader).context("version")?;
+        let mut input_count = compact_size::read_size(reader).context("inputs")?;
+        let segwit = input_count == 0;
+        if segwit {
+            let [flag] = read_array(reader).context("flag")?;
+            if flag != 1 {
+                return Err(TxError::InvalidFlag(flag)).context("flag");
+            }
+            input_count = compact_size::read_size(reader).context("inputs")?;
+        }
+        let mut inputs: Vec<TxIn> = (0..input_count)
+            .map(|i| TxIn::parse(reader).context(format_args!("inputs[{}]", i)))
+            .collect::<Result<_, _>>()?;
+        let outputs: Vec<TxOut> = (0..compact_size::read_size(reader).context("outputs")?)
+            .map(|i| TxOut::parse(reader).context(format_args!("outputs[{}]", i)))
+            .collect::<Result<_, _>>()?;
+        if segwit {
+            for (i, input) in inputs.iter_mut().enumerate() {
+                input.witness = (0..compact_size::read_size(reader).context(format_args!("inputs[{}].witness", i))?)
+                    .map(|j| read_var_bytes(reader).context(format_args!("inputs[{}].witness[{}]", i, j)))
+                    .collect::<Result<_, _>>()?;
+            }
+            if inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(TxError::SuperfluousWitness.into());
+            }
+        }
+        let locktime = read_u32(reader).context("locktime")?;
+        trace_event!(segwit, inputs = inputs.len(), outputs = outputs.len());
+        Ok(Self {
+            version,
+            inputs,
+            outputs,
+            locktime,
+        })
+    }
+
+    /// Parses a whole transaction, rejecting trailing bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError<TxError>> {
+        decode_slice(bytes, Self::parse)
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, DecodeError<TxError>> {
+        Self::from_bytes(&hex::decode(s).map_err(|_| TxError::InvalidHex)?)
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    fn write_format<W: Write>(&self, writer: &mut W, witness: bool) -> io::Result<()> {
+        writer.write_all(&self.version.to_le_bytes())?;
+        if witness {
+            writer.write_all(&[0x00, 0x01])?;
+        }
+        compact_size::write(writer, self.inputs.len() as u64)?;
+        for input in &self.inputs {
+            input.write(writer)?;
+        }
+        compact_size::write(writer, self.outputs.len() as u64)?;
+        for output in &self.outputs {
+            output.write(writer)?;
+        }
+        if witness {
+            for input in &self.inputs {
+                compact_size::write(writer, input.witness.len() as u64)?;
+                for item in &input.witness {
+                    write_var_bytes(writer, item)?;
+                }
+            }
+        }
+        writer.write_all(&self.locktime.to_le_bytes())
+    }
+
+    /// Writes the BIP-144 format when any input has a witness, the legacy
+    /// format otherwise
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_format(writer, self.has_witness())
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write(&mut res).unwrap();
+        res
+    }
+
+    /// The serialization without witness data, which is what the txid commits to
+    pub fn serialize_legacy(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write_format(&mut res, false).unwrap();
+        res
+    }
+
+    /// Base size * 3 + total size, so witness bytes count a quarter as much
+    pub fn weight(&self) -> usize {
+        self.serialize_legacy().len() * 3 + self.serialize().len()
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.serialize())
+    }
+
+    /// hash256 of the serialization without witnesses, in internal byte
+    /// order, so signatures can't change it
+    pub fn txid(&self) -> [u8; 32] {
+        hash256(&self.serialize_legacy())
+    }
+
+    /// hash256 of the full serialization; equals the txid without witnesses
+    pub fn wtxid(&self) -> [u8; 32] {
+        hash256(&self.serialize())
+    }
+
+    /// The txid as block explorers show it: byte-reversed hex
+    pub fn id(&self) -> String {
+        let mut txid = self.txid();
+        txid.reverse();
+        hex::encode(txid)
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output == OutPoint::NULL
+    }
+
+    /// The pushes of each push-only OP_RETURN output, in output order
+    pub fn op_return_data(&self) -> Vec<Vec<&[u8]>> {
+        self.outputs
+            .iter()
+            .filter_map(|output| output.script_pubkey.op_return_data())
+            .collect()
+    }
+}
+
+impl_consensus_codec!(OutPoint, TxError);
+impl_consensus_codec!(TxIn, TxError);
+impl_consensus_codec!(TxOut, TxError);
+impl_consensus_codec!(Transaction, TxError);
+impl_hex_fmt!(Transaction, Transaction::serialize);
+
+impl Display for OutPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut txid = self.txid;
+        txid.reverse();
+        write!(f, "{}:{}", hex::encode(txid), self.vout)
+    }
+}
+
+/// The txid and the counts; `{:#}` lists every field, scripts as asm, for
+/// reading a transaction in a notebook
+impl Display for Transaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        let (inputs, outputs) = (self.inputs.len(), self.outputs.len());
+        if !f.alternate() {
+            return write!(
+                f,
+                "Transaction {} ({} input{}, {} output{})",
+                self.id(),
+                inputs,
+                plural(inputs),
+                outputs,
+                plural(outputs)
+            );
+        }
+        write!(f, "Transaction {}", self.id())?;
+        write!(f, "\n  version {}, locktime {}", self.version, self.locktime)?;
+        for (i, input) in self.inputs.iter().enumerate() {
+            write!(
+                f,
+                "\n  input {}: {} sequence {:#010x}",
+                i, input.previous_output, input.sequence
+            )?;
+            write!(f, "\n    script_sig: {}", input.script_sig.to_asm())?;
+            for item in &input.witness {
+                write!(f, "\n    witness: {}", hex::encode(item))?;
+            }
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            write!(f, "\n  output {}: {} sat", i, output.amount)?;
+            write!(f, "\n    script_pubkey: {}", output.script_pubkey.to_asm())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl_serde_bytes!(Transaction, Transaction::serialize, Transaction::from_bytes);
+
+#[cfg(test)]
+mod tx_tests {
+    use super::*;
+
+    const LEGACY_TX: &str = "0100000001a15d57094aa7a21a28cb20b59aab8fc7d1149a3bdbcddba9c622e4f5f6a99ece\
+        010000006c493046022100f93bb0e7d8db7bd46e40132d1f8242026e045f03a0efe71bbb8e3f475e970d790221\
+        009337cd7f1f929f00cc6ff01f03729b069a7c21b59b1736ddfee5db5946c5da8c0121033b9b137ee87d5a812d\
+        6f506efdd37f0affa7ffc310711c06c7f3e097c9447c52ffffffff0100e1f505000000001976a9140389035a92\
+        25b3839e2bbf32d826a1e222031fd888ac00000000";
+
+    #[test]
+    fn test_parse_legacy() {
+        let tx = Transaction::from_hex(LEGACY_TX).unwrap();
+        assert_eq!(tx.version, 1);
+        assert_eq!(tx.inputs.len(), 1);
+        assert_eq!(tx.outputs.len(), 1);
+        assert_eq!(tx.locktime, 0);
+
+        let mut prev = tx.inputs[0].previous_output.txid;
+        prev.reverse();
+        assert_eq!(
+            hex::encode(prev),
+            "ce9ea9f6f5e422c6a9dbcddb3b9a14d1c78fab9ab520cb281aa2a74a09575da1"
+        );
+        assert_eq!(tx.inputs[0].previous_output.vout, 1);
+        assert_eq!(tx.inputs[0].script_sig.len(), 0x6c);
+        assert_eq!(tx.inputs[0].sequence, 0xffffffff);
+        assert_eq!(tx.outputs[0].amount, 100_000_000);
+        assert_eq!(
+            tx.outputs[0].script_pubkey.to_hex(),
+            "76a9140389035a9225b3839e2bbf32d826a1e222031fd888ac"
+        );
+        assert!(!tx.is_coinbase());
+
+        assert_eq!(tx.to_hex(), LEGACY_TX);
+        assert_eq!(format!("{:x}", tx), LEGACY_TX);
+        assert_eq!(
+            format!("{:x}", tx.outputs[0].script_pubkey),
+            tx.outputs[0].script_pubkey.to_hex()
+        );
+        assert_eq!(
+            tx.id(),
+            "a6eab3c14ab5272a58a5ba91505ba1a4b6d7a3a9fcbd187b6cd99a7b6d548cb7"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let tx = Transaction::from_hex(LEGACY_TX).unwrap();
+        assert_eq!(tx.to_string(), format!("Transaction {} (1 input, 1 output)", tx.id()));
+        let lines: Vec<String> = format!("{:#}", tx).lines().map(String::from).collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[1], "  version 1, locktime 0");
+        assert_eq!(
+            lines[2],
+            "  input 0: ce9ea9f6f5e422c6a9dbcddb3b9a14d1c78fab9ab520cb281aa2a74a09575da1:1 sequence 0xffffffff"
+        );
+        assert!(lines[3].starts_with("    script_sig: OP_PUSHBYTES_73 3046022100f93b"));
+        assert_eq!(lines[4], "  output 0: 100000000 sat");
+        assert_eq!(lines[5], "    script_pubkey: OP_DUP OP_HASH160 OP_PUSHBYTES_20 0389035a9225b3839e2bbf32d826a1e222031fd8 OP_EQUALVERIFY OP_CHECKSIG");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let bytes = hex::decode(LEGACY_TX).unwrap();
+        let err = Transaction::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.path()),
+            (&TxError::Truncated, &["locktime".to_string()][..])
+        );
+        // the script_sig claims 0x6c bytes from byte 42 on
+        let err = Transaction::from_bytes(&bytes[..50]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "transaction is truncated at byte 50 in inputs[0].script_sig"
+        );
+
+        let mut extra = bytes.clone();
+        extra.push(0);
+        let err = Transaction::from_bytes(&extra).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.offset()),
+            (&TxError::TrailingBytes(1), Some(bytes.len()))
+        );
+
+        // the script length prefix 0x6c re-encoded as fd6c00
+        let mut non_canonical = bytes[..41].to_vec();
+        non_canonical.extend_from_slice(&[0xfd, 0x6c, 0x00]);
+        non_canonical.extend_from_slice(&bytes[42..]);
+        let err = Transaction::from_bytes(&non_canonical).unwrap_err();
+        assert_eq!(err.kind(), &TxError::CompactSize(CompactSizeError::NonCanonical(0x6c)));
+        assert_eq!(
+            (err.offset(), err.path().join(".")),
+            (Some(44), "inputs[0].script_sig".to_string())
+        );
+
+        assert_eq!(Transaction::from_hex("zz"), Err(TxError::InvalidHex.into()));
+    }
+
+    const SEGWIT_TX: &str = "02000000000101595895ea20179de87052b4046dfe6fd515860505d6511a9004cf12a1f93cac7c01000000\
+        00ffffffff01deb807000000000017a9140f3444e271620c736808aa7b33e370bd87cb5a078702483045022\
+        100fb60dad8df4af2841adc0346638c16d0b8035f5e3f3753b88db122e70c79f9370220756e6633b17fd271\
+        0e626347d28d60b0a2d6cbb41de51740644b9fb3ba7751040121028fa937ca8cba2197a37c007176ed89410\
+        55d3bcb8627d085e94553e62f057dcc00000000";
+
+    fn reversed_hex(mut hash: [u8; 32]) -> String {
+        hash.reverse();
+        hex::encode(hash)
+    }
+
+    #[test]
+    fn test_op_return_outputs() {
+        let mut tx = Transaction::from_hex(LEGACY_TX).unwrap();
+        assert!(tx.op_return_data().is_empty());
+        tx.outputs.push(TxOut::op_return(&[b"hello", b"world"]).unwrap());
+        tx.outputs.push(TxOut {
+            amount: 0,
+            script_pubkey: Script::from_hex("6a51").unwrap(),
+        });
+        tx.outputs.push(TxOut::op_return(&[&[7; 3]]).unwrap());
+        assert_eq!(tx.outputs[1].amount, 0);
+
+        let tx = Transaction::from_hex(&tx.to_hex()).unwrap();
+        assert_eq!(
+            tx.op_return_data(),
+            vec![vec![&b"hello"[..], &b"world"[..]], vec![&[7; 3][..]]]
+        );
+        assert_eq!(TxOut::op_return(&[&[0; 100]]), Err(OpReturnError::TooLarge(103)));
+    }
+
+    #[test]
+    fn test_parse_segwit() {
+        let tx = Transaction::from_hex(SEGWIT_TX).unwrap();
+        assert!(tx.has_witness());
+        assert_eq!(tx.version, 2);
+        assert_eq!(tx.inputs[0].witness.len(), 2);
+        assert_eq!(tx.inputs[0].witness[0].len(), 72);
+        assert_eq!(
+            hex::encode(&tx.inputs[0].witness[1]),
+            "028fa937ca8cba2197a37c007176ed8941055d3bcb8627d085e94553e62f057dcc"
+        );
+        assert!(tx.inputs[0].script_sig.is_empty());
+
+        assert_eq!(tx.to_hex(), SEGWIT_TX);
+        assert_eq!(
+            tx.id(),
+            "f5864806e3565c34d1b41e716f72609d00b55ea5eac5b924c9719a842ef42206"
+        );
+        assert_eq!(
+            reversed_hex(tx.wtxid()),
+            "80b7d8a82d5d5bf92905b06f2014dd699e03837ca172e3a59d51426ebbe3e7f5"
+        );
+        assert_eq!(tx.weight(), 442);
+        assert_eq!(tx.vsize(), 111);
+
+        let legacy = Transaction::from_hex(LEGACY_TX).unwrap();
+        assert!(!legacy.has_witness());
+        assert_eq!(legacy.txid(), legacy.wtxid());
+        assert_eq!(legacy.weight(), 4 * LEGACY_TX.len() / 2);
+    }
+
+    #[test]
+    fn test_parse_segwit_coinbase() {
+        // first BIP-144 transaction on testnet; its witness is the 32-byte reserved value
+        let raw = "010000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff\
+            3603da1b0e00045503bd5704c7dd8a0d0ced13bb5785010800000000000a636b706f6f6c122f4e696e6a61506f\
+            6f6c2f5345475749542fffffffff02b4e5a212000000001976a914876fbb82ec05caa6af7a3b5e5a983aae6c6c\
+            c6d688ac0000000000000000266a24aa21a9edf91c46b49eb8a29089980f02ee6b57e7d63d33b18b4fddac2bcd\
+            7db2a39837040120000000000000000000000000000000000000000000000000000000000000000000000000";
+        let tx = Transaction::from_hex(raw).unwrap();
+        assert!(tx.is_coinbase());
+        assert_eq!(tx.inputs[0].witness, vec![vec![0_u8; 32]]);
+        assert_eq!(tx.weight(), 780);
+        assert_eq!(tx.to_hex(), raw);
+    }
+
+    #[test]
+    fn test_segwit_errors() {
+        let mut bytes = hex::decode(SEGWIT_TX).unwrap();
+        bytes[5] = 0x02;
+        let err = Transaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid segwit flag 0x02, expected 0x01 at byte 6 in flag"
+        );
+
+        // flag set, single input with an empty witness stack
+        let mut tx = Transaction::from_hex(SEGWIT_TX).unwrap();
+        tx.inputs[0].witness.clear();
+        let mut bytes = tx.serialize_legacy();
+        let locktime = bytes.split_off(bytes.len() - 4);
+        bytes.insert(4, 0x00);
+        bytes.insert(5, 0x01);
+        bytes.push(0x00);
+        bytes.extend_from_slice(&locktime);
+        assert_eq!(
+            Transaction::from_bytes(&bytes).unwrap_err().into_kind(),
+            TxError::SuperfluousWitness
+        );
+    }
+}
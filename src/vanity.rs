@@ -0,0 +1,136 @@
+use std::fmt::Display;
+
+use num_bigint::BigUint;
+
+use crate::address::{p2pkh, Network};
+use crate::curves::secp256k1::ecc::{order, PrivateKey};
+
+// Vanity addresses: P2PKH addresses that start with a chosen string, found
+// by trying keys until one fits. Each character after the leading one
+// multiplies the expected work by about 58.
+//
+// The candidates are start, start + 1, start + 2, ... so the key found is
+// only as secret as `start`, which must come from a proper source of
+// randomness like any other private key
+
+const BASE58: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VanityError {
+    /// Not in the base58 alphabet, which leaves out 0, O, I and l
+    InvalidCharacter(char),
+    /// No P2PKH address of the network starts with this character
+    WrongLeadingCharacter(char),
+}
+
+impl Display for VanityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VanityError::InvalidCharacter(c) => write!(f, "'{}' is not a base58 character", c),
+            VanityError::WrongLeadingCharacter(c) => write!(f, "no address on this network starts with '{}'", c),
+        }
+    }
+}
+
+impl std::error::Error for VanityError {}
+
+fn check_prefix(prefix: &str, network: Network) -> Result<(), VanityError> {
+    if let Some(c) = prefix.chars().find(|&c| !BASE58.contains(c)) {
+        return Err(VanityError::InvalidCharacter(c));
+    }
+    // version 0 encodes to a leading 1, testnet's 0x6f to an m or an n
+    let leading = if network.p2pkh_prefix() == 0 { "1" } else { "mn" };
+    match prefix.chars().next() {
+        Some(c) if !leading.contains(c) => Err(VanityError::WrongLeadingCharacter(c)),
+        _ => Ok(()),
+    }
+}
+
+/// Candidate number i, if its address matches
+fn try_key(start: &BigUint, i: u64, prefix: &str, network: Network) -> Option<(PrivateKey, String)> {
+    let key = PrivateKey::from((start + i) % order()).ok()?;
+    let address = p2pkh(&key.public_key(), network);
+    address.starts_with(prefix).then_some((key, address))
+}
+
+/// Tries up to `attempts` keys from `start` on and returns the first whose
+/// P2PKH address starts with `prefix`, with that address
+pub fn search(
+    prefix: &str,
+    network: Network,
+    start: &PrivateKey,
+    attempts: u64,
+) -> Result<Option<(PrivateKey, String)>, VanityError> {
+    check_prefix(prefix, network)?;
+    Ok((0..attempts).find_map(|i| try_key(start.secret(), i, prefix, network)))
+}
+
+/// `search` over rayon's thread pool. Returns whichever match a thread
+/// finds first, not necessarily the one `search` would
+#[cfg(feature = "parallel")]
+pub fn par_search(
+    prefix: &str,
+    network: Network,
+    start: &PrivateKey,
+    attempts: u64,
+) -> Result<Option<(PrivateKey, String)>, VanityError> {
+    use rayon::prelude::*;
+
+    check_prefix(prefix, network)?;
+    Ok((0..attempts)
+        .into_par_iter()
+        .find_map_any(|i| try_key(start.secret(), i, prefix, network)))
+}
+
+#[cfg(test)]
+mod vanity_tests {
+    use super::*;
+
+    fn start() -> PrivateKey {
+        PrivateKey::from_bytes(&[0x42; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_search() {
+        let (key, address) = search("1M", Network::Mainnet, &start(), 2_000).unwrap().unwrap();
+        assert!(address.starts_with("1M"));
+        assert_eq!(address, p2pkh(&key.public_key(), Network::Mainnet));
+        let offset = (key.secret() - start().secret()) % order();
+        assert!(offset < BigUint::from(2_000_u32));
+
+        // the start key itself matches the empty prefix and its own address
+        let own = p2pkh(&start().public_key(), Network::Mainnet);
+        assert_eq!(search("", Network::Mainnet, &start(), 1).unwrap().unwrap().0, start());
+        assert_eq!(search(&own, Network::Mainnet, &start(), 1).unwrap().unwrap().1, own);
+        assert_eq!(search("1zzzzz", Network::Mainnet, &start(), 0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_prefix() {
+        assert_eq!(
+            search("1O", Network::Mainnet, &start(), 1),
+            Err(VanityError::InvalidCharacter('O'))
+        );
+        assert_eq!(
+            search("3a", Network::Mainnet, &start(), 1),
+            Err(VanityError::WrongLeadingCharacter('3'))
+        );
+        assert_eq!(
+            search("1a", Network::Testnet, &start(), 1),
+            Err(VanityError::WrongLeadingCharacter('1'))
+        );
+        assert!(search("n", Network::Testnet, &start(), 1).is_ok());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_search() {
+        let (key, address) = par_search("1M", Network::Mainnet, &start(), 2_000).unwrap().unwrap();
+        assert!(address.starts_with("1M"));
+        assert_eq!(address, p2pkh(&key.public_key(), Network::Mainnet));
+        assert_eq!(
+            par_search("1l", Network::Mainnet, &start(), 1),
+            Err(VanityError::InvalidCharacter('l'))
+        );
+    }
+}
@@ -0,0 +1,424 @@
+use std::fmt::Display;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::tx::OutPoint;
+
+/// Depth-first search steps Branch-and-Bound may take, as in Bitcoin Core
+const BNB_TOTAL_TRIES: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoinSelectError {
+    /// The candidates' effective values don't cover the target
+    InsufficientFunds { needed: u64, available: u64 },
+    /// Branch-and-Bound found no selection that avoids change
+    NoChangelessSolution,
+}
+
+impl Display for CoinSelectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoinSelectError::InsufficientFunds { needed, available } => {
+                write!(
+                    f,
+                    "insufficient funds: {} sat needed, {} sat available",
+                    needed, available
+                )
+            }
+            CoinSelectError::NoChangelessSolution => write!(f, "no selection avoids change"),
+        }
+    }
+}
+
+impl std::error::Error for CoinSelectError {}
+
+/// A spendable output and the size an input spending it adds to the
+/// transaction, which depends on its script type
+#[derive(Debug, Clone, PartialEq)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    pub amount: u64,
+    /// e.g. 68 for P2WPKH, 148 for P2PKH, 57.5 rounded up for P2TR key path
+    pub input_vsize: u64,
+}
+
+/// Fee for `vsize` virtual bytes at `fee_rate` sat/vB, rounded up
+pub fn fee(vsize: u64, fee_rate: f64) -> u64 {
+    (vsize as f64 * fee_rate).ceil() as u64
+}
+
+impl Utxo {
+    /// What the output contributes once the fee to spend it is paid; can
+    /// be negative for dust at high fee rates
+    pub fn effective_value(&self, fee_rate: f64) -> i64 {
+        self.amount as i64 - fee(self.input_vsize, fee_rate) as i64
+    }
+}
+
+/// What a selection has to pay for, and at which fee rates
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionParams {
+    /// Outputs plus the fee for the transaction without its inputs
+    pub target: u64,
+    /// sat/vB for this transaction
+    pub fee_rate: f64,
+    /// sat/vB expected in the long run, for the cost of spending later
+    /// what isn't spent now
+    pub long_term_fee_rate: f64,
+    pub change_output_vsize: u64,
+    /// vsize of the input that will eventually spend the change
+    pub change_input_vsize: u64,
+    /// Smaller change is dropped to fees instead, typically the dust limit
+    pub min_change: u64,
+}
+
+impl SelectionParams {
+    /// Fee to add a change output now and to spend it later
+    pub fn cost_of_change(&self) -> u64 {
+        fee(self.change_output_vsize, self.fee_rate) + fee(self.change_input_vsize, self.long_term_fee_rate)
+    }
+}
+
+/// The chosen inputs and where the excess over the target goes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub selected: Vec<Utxo>,
+    /// Sum of the selected effective values
+    pub effective_value: u64,
+    /// Change output amount, after paying for the change output itself.
+    /// Zero when the excess is too small and goes to the miner instead
+    pub change: u64,
+    /// How much more this selection costs than an ideal one, in sats:
+    /// fees paid now rather than at the long-term rate, plus the cost of
+    /// change or the excess given up. Lower is better
+    pub waste: i64,
+}
+
+impl Selection {
+    fn new(selected: Vec<Utxo>, params: &SelectionParams) -> Self {
+        let effective_value = selected.iter().map(|u| u.effective_value(params.fee_rate)).sum::<i64>() as u64;
+        let excess = effective_value - params.target;
+        let change_fee = fee(params.change_output_vsize, params.fee_rate);
+        let change = match excess.checked_sub(change_fee) {
+            Some(change) if change >= params.min_change => change,
+            _ => 0,
+        };
+        let waste = waste(&selected, params, excess, change > 0);
+        Self {
+            selected,
+            effective_value,
+            change,
+            waste,
+        }
+    }
+
+    pub fn total_amount(&self) -> u64 {
+        self.selected.iter().map(|u| u.amount).sum()
+    }
+}
+
+/// Waste metric of Bitcoin Core: what spending these inputs now costs over
+/// spending them at the long-term fee rate, plus the cost of change if
+/// there is change, or else the excess over the target given to fees
+pub fn waste(selected: &[Utxo], params: &SelectionParams, excess: u64, has_change: bool) -> i64 {
+    let timing: i64 = selected
+        .iter()
+        .map(|u| fee(u.input_vsize, params.fee_rate) as i64 - fee(u.input_vsize, params.long_term_fee_rate) as i64)
+        .sum();
+    timing
+        + if has_change {
+            params.cost_of_change() as i64
+        } else {
+            excess as i64
+        }
+}
+
+/// Candidates worth spending at the fee rate, and their total effective value
+fn positive(utxos: &[Utxo], params: &SelectionParams) -> (Vec<Utxo>, u64) {
+    let pool: Vec<Utxo> = utxos
+        .iter()
+        .filter(|u| u.effective_value(params.fee_rate) > 0)
+        .cloned()
+        .collect();
+    let available = pool.iter().map(|u| u.effective_value(params.fee_rate) as u64).sum();
+    (pool, available)
+}
+
+/// Spends the largest outputs first until the target is met. Few inputs,
+/// but it tends to grind big coins into change
+pub fn largest_first(utxos: &[Utxo], params: &SelectionParams) -> Result<Selection, CoinSelectError> {
+    let (mut pool, available) = positive(utxos, params);
+    pool.sort_by_key(|u| std::cmp::Reverse(u.effective_value(params.fee_rate)));
+    let mut selected = Vec::new();
+    let mut value = 0;
+    for utxo in pool {
+        if value >= params.target {
+            break;
+        }
+        value += utxo.effective_value(params.fee_rate) as u64;
+        selected.push(utxo);
+    }
+    if value < params.target {
+        return Err(CoinSelectError::InsufficientFunds {
+            needed: params.target,
+            available,
+        });
+    }
+    Ok(Selection::new(selected, params))
+}
+
+/// Branch-and-Bound (Murch, used by Bitcoin Core): a depth-first search
+/// for an input set whose effective value lands between the target and
+/// the target plus the cost of change, so no change output is needed.
+/// Among those found, the one with the least waste wins
+pub fn branch_and_bound(utxos: &[Utxo], params: &SelectionParams) -> Result<Selection, CoinSelectError> {
+    let (mut pool, mut available) = positive(utxos, params);
+    if available < params.target {
+        return Err(CoinSelectError::InsufficientFunds {
+            needed: params.target,
+            available,
+        });
+    }
+    pool.sort_by_key(|u| std::cmp::Reverse(u.effective_value(params.fee_rate)));
+    let values: Vec<u64> = pool.iter().map(|u| u.effective_value(params.fee_rate) as u64).collect();
+    // waste of spending each input now rather than at the long-term rate
+    let timing: Vec<i64> = pool
+        .iter()
+        .map(|u| fee(u.input_vsize, params.fee_rate) as i64 - fee(u.input_vsize, params.long_term_fee_rate) as i64)
+        .collect();
+    let upper = params.target + params.cost_of_change();
+    let high_fee_rate = params.fee_rate > params.long_term_fee_rate;
+
+    let mut selection: Vec<usize> = Vec::new();
+    let mut best: Option<Vec<usize>> = None;
+    let mut best_waste = i64::MAX;
+    let (mut value, mut waste) = (0_u64, 0_i64);
+    let mut index = 0;
+    for _ in 0..BNB_TOTAL_TRIES {
+        let mut backtrack = false;
+        if value + available < params.target || value > upper || (waste > best_waste && high_fee_rate) {
+            backtrack = true;
+        } else if value >= params.target {
+            let total = waste + (value - params.target) as i64;
+            if total <= best_waste {
+                best = Some(selection.clone());
+                best_waste = total;
+            }
+            backtrack = true;
+        }
+
+        if backtrack {
+            let Some(&last) = selection.last() else {
+                break;
+            };
+            // the skipped inputs after the last included one are available
+            // again on its exclusion branch
+            while index > last + 1 {
+                index -= 1;
+                available += values[index];
+            }
+            index = last + 1;
+            value -= values[last];
+            waste -= timing[last];
+            selection.pop();
+        } else {
+            available -= values[index];
+            // excluding an input equal to one just excluded repeats a branch
+            let duplicate = index > 0
+                && selection.last() != Some(&(index - 1))
+                && values[index] == values[index - 1]
+                && timing[index] == timing[index - 1];
+            if !duplicate {
+                selection.push(index);
+                value += values[index];
+                waste += timing[index];
+            }
+            index += 1;
+        }
+    }
+
+    let best = best.ok_or(CoinSelectError::NoChangelessSolution)?;
+    Ok(Selection::new(
+        best.into_iter().map(|i| pool[i].clone()).collect(),
+        params,
+    ))
+}
+
+/// Single Random Draw: adds shuffled outputs until there's enough for the
+/// target and a change output. Random selections make the wallet's coins
+/// harder to fingerprint
+pub fn single_random_draw<R: Rng>(
+    utxos: &[Utxo],
+    params: &SelectionParams,
+    rng: &mut R,
+) -> Result<Selection, CoinSelectError> {
+    let (mut pool, available) = positive(utxos, params);
+    pool.shuffle(rng);
+    let needed = params.target + fee(params.change_output_vsize, params.fee_rate) + params.min_change;
+    let mut selected = Vec::new();
+    let mut value = 0;
+    for utxo in pool {
+        value += utxo.effective_value(params.fee_rate) as u64;
+        selected.push(utxo);
+        if value >= needed {
+            return Ok(Selection::new(selected, params));
+        }
+    }
+    Err(CoinSelectError::InsufficientFunds { needed, available })
+}
+
+/// Runs every algorithm and keeps the least wasteful selection
+pub fn select_coins<R: Rng>(
+    utxos: &[Utxo],
+    params: &SelectionParams,
+    rng: &mut R,
+) -> Result<Selection, CoinSelectError> {
+    let candidates = [
+        branch_and_bound(utxos, params),
+        single_random_draw(utxos, params, rng),
+        largest_first(utxos, params),
+    ];
+    let mut best: Option<Selection> = None;
+    let mut error = None;
+    for candidate in candidates {
+        match candidate {
+            Ok(selection) if best.as_ref().is_none_or(|b| selection.waste < b.waste) => best = Some(selection),
+            Ok(_) => {}
+            Err(e) => error = error.or(Some(e)),
+        }
+    }
+    best.ok_or_else(|| error.unwrap())
+}
+
+#[cfg(test)]
+mod coin_select_tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn utxos(amounts: &[u64]) -> Vec<Utxo> {
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(i, &amount)| Utxo {
+                outpoint: OutPoint {
+                    txid: [i as u8; 32],
+                    vout: 0,
+                },
+                amount,
+                input_vsize: 68,
+            })
+            .collect()
+    }
+
+    fn params(target: u64, fee_rate: f64) -> SelectionParams {
+        SelectionParams {
+            target,
+            fee_rate,
+            long_term_fee_rate: 10.0,
+            change_output_vsize: 31,
+            change_input_vsize: 68,
+            min_change: 294,
+        }
+    }
+
+    fn amounts(selection: &Selection) -> Vec<u64> {
+        let mut res: Vec<u64> = selection.selected.iter().map(|u| u.amount).collect();
+        res.sort_unstable();
+        res
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let pool = utxos(&[5_000, 100_000, 20_000, 60_000]);
+        let selection = largest_first(&pool, &params(120_000, 0.0)).unwrap();
+        assert_eq!(amounts(&selection), [60_000, 100_000]);
+        assert_eq!(selection.change, 40_000);
+
+        // at 10 sat/vB each input costs 680 sats
+        let selection = largest_first(&pool, &params(120_000, 10.0)).unwrap();
+        assert_eq!(selection.effective_value, 160_000 - 2 * 680);
+        assert_eq!(selection.change, 160_000 - 2 * 680 - 120_000 - 310);
+        assert_eq!(selection.waste, params(0, 10.0).cost_of_change() as i64);
+
+        assert_eq!(
+            largest_first(&pool, &params(200_000, 10.0)),
+            Err(CoinSelectError::InsufficientFunds {
+                needed: 200_000,
+                available: 185_000 - 4 * 680
+            })
+        );
+    }
+
+    #[test]
+    fn test_branch_and_bound() {
+        // at the long-term fee rate only the excess counts as waste, so an
+        // exact match wins
+        let pool = utxos(&[1_680, 2_680, 3_680, 4_680, 10_680]);
+        let selection = branch_and_bound(&pool, &params(7_000, 10.0)).unwrap();
+        assert_eq!(selection.effective_value, 7_000);
+        assert_eq!((selection.change, selection.waste), (0, 0));
+
+        // within the cost of change the excess goes to fees
+        let selection = branch_and_bound(&pool, &params(6_800, 10.0)).unwrap();
+        assert_eq!(selection.effective_value, 7_000);
+        assert_eq!((selection.change, selection.waste), (0, 200));
+
+        // above the long-term rate, fewer inputs waste less: 3000 can come
+        // from one input or two, and the one wins
+        let pool = utxos(&[2_360, 3_360, 4_360]);
+        let selection = branch_and_bound(&pool, &params(3_000, 20.0)).unwrap();
+        assert_eq!(amounts(&selection), [4_360]);
+        assert_eq!(selection.waste, 680);
+
+        let no_match = utxos(&[100_000, 200_000]);
+        assert_eq!(
+            branch_and_bound(&no_match, &params(50_000, 10.0)),
+            Err(CoinSelectError::NoChangelessSolution)
+        );
+        assert!(matches!(
+            branch_and_bound(&no_match, &params(500_000, 10.0)),
+            Err(CoinSelectError::InsufficientFunds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_single_random_draw() {
+        let pool = utxos(&[10_000, 20_000, 30_000, 40_000, 50_000]);
+        let params = params(45_000, 5.0);
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let selection = single_random_draw(&pool, &params, &mut rng).unwrap();
+            assert!(selection.change >= params.min_change);
+            assert_eq!(
+                selection.effective_value,
+                params.target + fee(31, 5.0) + selection.change
+            );
+        }
+        assert!(single_random_draw(&pool, &self::params(150_000, 5.0), &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_select_coins() {
+        let pool = utxos(&[1_680, 2_680, 3_680, 4_680, 10_680]);
+        let mut rng = StdRng::seed_from_u64(7);
+        // an exact match beats anything with change
+        let selection = select_coins(&pool, &params(7_000, 10.0), &mut rng).unwrap();
+        assert_eq!(selection.waste, 0);
+        // no changeless solution: fall back to the others
+        let coarse = utxos(&[20_680, 30_680]);
+        let selection = select_coins(&coarse, &params(11_500, 10.0), &mut rng).unwrap();
+        assert!(selection.change > 0);
+        assert!(select_coins(&pool, &params(50_000, 10.0), &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_dust_is_skipped() {
+        // a 500 sat output costs more than it's worth at 10 sat/vB
+        let pool = utxos(&[500, 50_000]);
+        assert_eq!(pool[0].effective_value(10.0), -180);
+        let selection = largest_first(&pool, &params(10_000, 10.0)).unwrap();
+        assert_eq!(amounts(&selection), [50_000]);
+    }
+}
@@ -0,0 +1,315 @@
+pub mod coin_select;
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::address::{self, Network};
+use crate::bip32::{Bip32Error, ExtendedPublicKey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Chain {
+    Receive,
+    Change,
+}
+
+impl Chain {
+    /// Child index of the chain below the account key, as in BIP44
+    pub fn index(self) -> u32 {
+        match self {
+            Chain::Receive => 0,
+            Chain::Change => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptKind {
+    P2pkh,
+    P2wpkh,
+}
+
+/// Describes how account keys turn into addresses, roughly `kind(xpub/<0;1>/*)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Template {
+    pub kind: ScriptKind,
+    pub network: Network,
+}
+
+impl Template {
+    pub fn pkh(network: Network) -> Self {
+        Self {
+            kind: ScriptKind::P2pkh,
+            network,
+        }
+    }
+
+    pub fn wpkh(network: Network) -> Self {
+        Self {
+            kind: ScriptKind::P2wpkh,
+            network,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalletError {
+    Bip32(Bip32Error),
+    ZeroGapLimit,
+    /// Handing out another address would leave more than `gap_limit`
+    /// unused addresses in a row, which a restoring wallet would not find
+    GapLimitReached(Chain),
+}
+
+impl Display for WalletError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletError::Bip32(e) => write!(f, "{}", e),
+            WalletError::ZeroGapLimit => write!(f, "gap limit must be at least 1"),
+            WalletError::GapLimitReached(c) => write!(f, "gap limit reached on the {:?} chain", c),
+        }
+    }
+}
+
+impl std::error::Error for WalletError {}
+
+impl From<Bip32Error> for WalletError {
+    fn from(e: Bip32Error) -> Self {
+        WalletError::Bip32(e)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChainState {
+    last_used: Option<u32>,
+    next_index: u32,
+}
+
+/// Watch-only account: derives addresses from an account-level xpub and
+/// tracks which of them have been used
+#[derive(Debug, Clone)]
+pub struct WatchOnly {
+    account: ExtendedPublicKey,
+    chains: [ExtendedPublicKey; 2],
+    template: Template,
+    gap_limit: u32,
+    state: [ChainState; 2],
+    known: HashMap<String, (Chain, u32)>,
+}
+
+impl WatchOnly {
+    pub fn new(account: ExtendedPublicKey, template: Template, gap_limit: u32) -> Result<Self, WalletError> {
+        if gap_limit == 0 {
+            return Err(WalletError::ZeroGapLimit);
+        }
+        let chains = [
+            account.derive_child(Chain::Receive.index())?,
+            account.derive_child(Chain::Change.index())?,
+        ];
+        Ok(Self {
+            account,
+            chains,
+            template,
+            gap_limit,
+            state: Default::default(),
+            known: HashMap::new(),
+        })
+    }
+
+    pub fn account(&self) -> &ExtendedPublicKey {
+        &self.account
+    }
+
+    pub fn template(&self) -> Template {
+        self.template
+    }
+
+    pub fn gap_limit(&self) -> u32 {
+        self.gap_limit
+    }
+
+    pub fn last_used(&self, chain: Chain) -> Option<u32> {
+        self.state(chain).last_used
+    }
+
+    /// Derives the address at `chain/index` without touching wallet state
+    pub fn address(&self, chain: Chain, index: u32) -> Result<String, WalletError> {
+        let key = self.chains[chain.index() as usize].derive_child(index)?;
+        let network = self.template.network;
+        Ok(match self.template.kind {
+            ScriptKind::P2pkh => address::p2pkh(&key.public_key, network),
+            ScriptKind::P2wpkh => address::p2wpkh(&key.public_key, network),
+        })
+    }
+
+    /// Returns the position of an address this wallet has derived before
+    pub fn lookup(&self, address: &str) -> Option<(Chain, u32)> {
+        self.known.get(address).copied()
+    }
+
+    /// Hands out the next fresh address, refusing to go past the gap limit
+    pub fn next_address(&mut self, chain: Chain) -> Result<String, WalletError> {
+        let state = self.state(chain);
+        let first_unused = state.last_used.map_or(0, |i| i + 1);
+        if state.next_index >= first_unused + self.gap_limit {
+            return Err(WalletError::GapLimitReached(chain));
+        }
+        let index = state.next_index;
+        let address = self.watch(chain, index)?;
+        self.state_mut(chain).next_index = index + 1;
+        Ok(address)
+    }
+
+    /// Records that an address has received funds. Returns false for
+    /// addresses this wallet hasn't derived yet
+    pub fn mark_used(&mut self, address: &str) -> bool {
+        match self.lookup(address) {
+            Some((chain, index)) => {
+                self.record_use(chain, index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Walks both chains past the last known used index until `gap_limit`
+    /// consecutive addresses are reported unused by `is_used`
+    pub fn scan<F: FnMut(&str) -> bool>(&mut self, mut is_used: F) -> Result<(), WalletError> {
+        for chain in [Chain::Receive, Chain::Change] {
+            let mut index = self.state(chain).last_used.map_or(0, |i| i + 1);
+            let mut gap = 0;
+            while gap < self.gap_limit {
+                let address = self.watch(chain, index)?;
+                if is_used(&address) {
+                    self.record_use(chain, index);
+                    gap = 0;
+                } else {
+                    gap += 1;
+                }
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn watch(&mut self, chain: Chain, index: u32) -> Result<String, WalletError> {
+        let address = self.address(chain, index)?;
+        self.known.insert(address.clone(), (chain, index));
+        Ok(address)
+    }
+
+    fn record_use(&mut self, chain: Chain, index: u32) {
+        let state = self.state_mut(chain);
+        state.last_used = Some(state.last_used.map_or(index, |i| i.max(index)));
+        state.next_index = state.next_index.max(index + 1);
+    }
+
+    fn state(&self, chain: Chain) -> &ChainState {
+        &self.state[chain.index() as usize]
+    }
+
+    fn state_mut(&mut self, chain: Chain) -> &mut ChainState {
+        &mut self.state[chain.index() as usize]
+    }
+}
+
+#[cfg(test)]
+mod wallet_tests {
+    use super::*;
+
+    // m/84'/0'/0' and m/44'/0'/0' of the "abandon ... about" mnemonic
+    const BIP84_XPUB: &str = "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V";
+    const BIP44_XPUB: &str = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+
+    fn wallet(xpub: &str, template: Template, gap_limit: u32) -> WatchOnly {
+        WatchOnly::new(ExtendedPublicKey::parse(xpub).unwrap(), template, gap_limit).unwrap()
+    }
+
+    #[test]
+    fn test_addresses() {
+        let wpkh = wallet(BIP84_XPUB, Template::wpkh(Network::Mainnet), 20);
+        assert_eq!(
+            wpkh.address(Chain::Receive, 0).unwrap(),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        assert_eq!(
+            wpkh.address(Chain::Change, 0).unwrap(),
+            "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"
+        );
+
+        let pkh = wallet(BIP44_XPUB, Template::pkh(Network::Mainnet), 20);
+        assert_eq!(
+            pkh.address(Chain::Receive, 0).unwrap(),
+            "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"
+        );
+        assert_eq!(
+            pkh.address(Chain::Change, 1).unwrap(),
+            "13vKxXzHXXd8HquAYdpkJoi9ULVXUgfpS5"
+        );
+    }
+
+    #[test]
+    fn test_next_address_gap_limit() {
+        let mut w = wallet(BIP84_XPUB, Template::wpkh(Network::Mainnet), 2);
+
+        let first = w.next_address(Chain::Receive).unwrap();
+        assert_eq!(first, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(
+            w.next_address(Chain::Receive).unwrap(),
+            "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g"
+        );
+        assert_eq!(
+            w.next_address(Chain::Receive),
+            Err(WalletError::GapLimitReached(Chain::Receive))
+        );
+
+        assert!(w.mark_used(&first));
+        assert_eq!(w.last_used(Chain::Receive), Some(0));
+        assert_eq!(
+            w.next_address(Chain::Receive).unwrap(),
+            "bc1qp59yckz4ae5c4efgw2s5wfyvrz0ala7rgvuz8z"
+        );
+    }
+
+    #[test]
+    fn test_mark_unknown() {
+        let mut w = wallet(BIP84_XPUB, Template::wpkh(Network::Mainnet), 2);
+
+        assert!(!w.mark_used("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"));
+        assert_eq!(w.last_used(Chain::Receive), None);
+    }
+
+    #[test]
+    fn test_scan() {
+        let mut w = wallet(BIP84_XPUB, Template::wpkh(Network::Mainnet), 2);
+        let used = [
+            "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g",
+            "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el",
+        ];
+        w.scan(|a| used.contains(&a)).unwrap();
+
+        assert_eq!(w.last_used(Chain::Receive), Some(1));
+        assert_eq!(w.last_used(Chain::Change), Some(0));
+        assert_eq!(
+            w.lookup("bc1qp59yckz4ae5c4efgw2s5wfyvrz0ala7rgvuz8z"),
+            Some((Chain::Receive, 2))
+        );
+        assert_eq!(
+            w.next_address(Chain::Receive).unwrap(),
+            "bc1qp59yckz4ae5c4efgw2s5wfyvrz0ala7rgvuz8z"
+        );
+        assert_eq!(
+            w.next_address(Chain::Change).unwrap(),
+            "bc1qggnasd834t54yulsep6fta8lpjekv4zj6gv5rf"
+        );
+    }
+
+    #[test]
+    fn test_zero_gap_limit() {
+        let xpub = ExtendedPublicKey::parse(BIP84_XPUB).unwrap();
+
+        assert!(matches!(
+            WatchOnly::new(xpub, Template::wpkh(Network::Mainnet), 0),
+            Err(WalletError::ZeroGapLimit)
+        ));
+    }
+}
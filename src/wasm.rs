@@ -0,0 +1,186 @@
+// JS-facing wrappers: keys, hashes and signatures cross the boundary as
+// hex strings or Uint8Arrays, and errors become thrown JS Errors
+
+use num_bigint::BigUint;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use wasm_bindgen::prelude::*;
+
+use crate::address::{self, Network};
+use crate::crypto::hash;
+use crate::crypto::schnorr;
+use crate::curves::secp256k1::ecc::{PrivateKey, PublicKey, Signature};
+use crate::taproot::TaprootSpendInfo;
+
+fn parse_private_key(secret_hex: &str) -> Result<PrivateKey, JsError> {
+    Ok(PrivateKey::from_bytes(&hex::decode(secret_hex)?)?)
+}
+
+fn parse_public_key(public_hex: &str) -> Result<PublicKey, JsError> {
+    Ok(PublicKey::parse(&hex::decode(public_hex)?)?)
+}
+
+fn parse_network(network: &str) -> Result<Network, JsError> {
+    match network {
+        "mainnet" | "bitcoin" => Ok(Network::Mainnet),
+        "testnet" => Ok(Network::Testnet),
+        "signet" => Ok(Network::Signet),
+        "regtest" => Ok(Network::Regtest),
+        _ => Err(JsError::new(&format!("unknown network {}", network))),
+    }
+}
+
+fn array<const N: usize>(bytes: &[u8], what: &str) -> Result<[u8; N], JsError> {
+    bytes
+        .try_into()
+        .map_err(|_| JsError::new(&format!("{} must be {} bytes", what, N)))
+}
+
+/// A fresh secret key as 64 hex characters
+#[wasm_bindgen(js_name = generatePrivateKey)]
+pub fn generate_private_key() -> String {
+    loop {
+        let mut bytes = [0_u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        if let Ok(key) = PrivateKey::from_bytes(&bytes) {
+            return hex::encode(key.to_bytes());
+        }
+    }
+}
+
+/// The SEC1 public key, 33 bytes compressed or 65 uncompressed, as hex
+#[wasm_bindgen(js_name = publicKey)]
+pub fn public_key(secret_hex: &str, compressed: bool) -> Result<String, JsError> {
+    Ok(hex::encode(parse_private_key(secret_hex)?.public_key().sec(compressed)))
+}
+
+/// The BIP-340 x-only public key as hex
+#[wasm_bindgen(js_name = xonlyPublicKey)]
+pub fn xonly_public_key(secret_hex: &str) -> Result<String, JsError> {
+    Ok(hex::encode(schnorr::xonly(
+        &parse_private_key(secret_hex)?.public_key(),
+    )))
+}
+
+/// A DER ECDSA signature over a 32-byte hash, as hex
+#[wasm_bindgen(js_name = signEcdsa)]
+pub fn sign_ecdsa(secret_hex: &str, hash: &[u8]) -> Result<String, JsError> {
+    let z = BigUint::from_bytes_be(&array::<32>(hash, "hash")?);
+    Ok(hex::encode(parse_private_key(secret_hex)?.sign(&z).der()))
+}
+
+#[wasm_bindgen(js_name = verifyEcdsa)]
+pub fn verify_ecdsa(public_hex: &str, hash: &[u8], signature_hex: &str) -> Result<bool, JsError> {
+    let z = BigUint::from_bytes_be(&array::<32>(hash, "hash")?);
+    let signature = Signature::parse_der(&hex::decode(signature_hex)?)?;
+    Ok(parse_public_key(public_hex)?.verify(&z, &signature))
+}
+
+/// A 64-byte BIP-340 signature as hex, with fresh auxiliary randomness
+#[wasm_bindgen(js_name = signSchnorr)]
+pub fn sign_schnorr(secret_hex: &str, message: &[u8]) -> Result<String, JsError> {
+    let mut aux_rand = [0_u8; 32];
+    OsRng.fill_bytes(&mut aux_rand);
+    Ok(hex::encode(schnorr::sign(
+        &parse_private_key(secret_hex)?,
+        message,
+        &aux_rand,
+    )))
+}
+
+#[wasm_bindgen(js_name = verifySchnorr)]
+pub fn verify_schnorr(xonly_hex: &str, message: &[u8], signature_hex: &str) -> Result<bool, JsError> {
+    let public_key = array::<32>(&hex::decode(xonly_hex)?, "x-only public key")?;
+    let signature = array::<64>(&hex::decode(signature_hex)?, "signature")?;
+    Ok(schnorr::verify(&public_key, message, &signature))
+}
+
+/// A legacy base58 address for a SEC1 public key
+#[wasm_bindgen(js_name = p2pkhAddress)]
+pub fn p2pkh_address(public_hex: &str, network: &str) -> Result<String, JsError> {
+    Ok(address::p2pkh(&parse_public_key(public_hex)?, parse_network(network)?))
+}
+
+/// A native segwit v0 address for a SEC1 public key
+#[wasm_bindgen(js_name = p2wpkhAddress)]
+pub fn p2wpkh_address(public_hex: &str, network: &str) -> Result<String, JsError> {
+    Ok(address::p2wpkh(&parse_public_key(public_hex)?, parse_network(network)?))
+}
+
+/// A key-path-only taproot address for an x-only internal key
+#[wasm_bindgen(js_name = p2trAddress)]
+pub fn p2tr_address(xonly_hex: &str, network: &str) -> Result<String, JsError> {
+    let internal_key = array::<32>(&hex::decode(xonly_hex)?, "x-only public key")?;
+    let spend_info = TaprootSpendInfo::new(internal_key, None)?;
+    address::from_script_pubkey(&spend_info.script_pubkey(), parse_network(network)?)
+        .ok_or_else(|| JsError::new("no address for the output"))
+}
+
+#[wasm_bindgen]
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    hash::sha256(data).to_vec()
+}
+
+/// Double SHA-256, as used for txids and block hashes
+#[wasm_bindgen]
+pub fn hash256(data: &[u8]) -> Vec<u8> {
+    hash::hash256(data).to_vec()
+}
+
+/// RIPEMD-160 of SHA-256, as used for addresses
+#[wasm_bindgen]
+pub fn hash160(data: &[u8]) -> Vec<u8> {
+    hash::hash160(data).to_vec()
+}
+
+#[wasm_bindgen]
+pub fn keccak256(data: &[u8]) -> Vec<u8> {
+    hash::keccak256(data).to_vec()
+}
+
+#[cfg(test)]
+mod wasm_tests {
+    use super::*;
+
+    // JsError can only be built on wasm targets, so the native tests stick
+    // to the paths that succeed
+
+    #[test]
+    fn test_keys_and_signatures() {
+        let secret = generate_private_key();
+        assert_eq!(secret.len(), 64);
+        let public = public_key(&secret, true).unwrap();
+        assert_eq!(public.len(), 66);
+
+        let hash = sha256(b"moneda");
+        let signature = sign_ecdsa(&secret, &hash).unwrap();
+        assert!(verify_ecdsa(&public, &hash, &signature).unwrap());
+        assert!(!verify_ecdsa(&public, &sha256(b"other"), &signature).unwrap());
+
+        let xonly = xonly_public_key(&secret).unwrap();
+        let signature = sign_schnorr(&secret, b"moneda").unwrap();
+        assert!(verify_schnorr(&xonly, b"moneda", &signature).unwrap());
+        assert!(!verify_schnorr(&xonly, b"other", &signature).unwrap());
+    }
+
+    #[test]
+    fn test_addresses() {
+        // the secret 1, whose addresses are well known
+        let secret = "0000000000000000000000000000000000000000000000000000000000000001";
+        let public = public_key(secret, true).unwrap();
+        assert_eq!(
+            p2pkh_address(&public, "mainnet").unwrap(),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+        );
+        assert_eq!(
+            p2wpkh_address(&public, "mainnet").unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        let xonly = xonly_public_key(secret).unwrap();
+        assert!(p2tr_address(&xonly, "regtest").unwrap().starts_with("bcrt1p"));
+        assert_eq!(
+            hex::encode(hash160(&hex::decode(&public).unwrap())),
+            "751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+    }
+}
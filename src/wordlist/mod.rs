@@ -0,0 +1,30 @@
+use std::sync::OnceLock;
+
+/// The BIP39 English wordlist, which Electrum's seed format reuses
+const ENGLISH: &str = include_str!("english.txt");
+
+pub fn english() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| ENGLISH.lines().collect())
+}
+
+/// Position of `word` in the English list, which is sorted
+pub fn index_of(word: &str) -> Option<usize> {
+    english().binary_search(&word).ok()
+}
+
+#[cfg(test)]
+mod wordlist_tests {
+    use super::*;
+
+    #[test]
+    fn test_english() {
+        let words = english();
+
+        assert_eq!(words.len(), 2048);
+        assert_eq!(words[0], "abandon");
+        assert_eq!(words[2047], "zoo");
+        assert_eq!(index_of("zoo"), Some(2047));
+        assert_eq!(index_of("moneda"), None);
+    }
+}
@@ -0,0 +1,273 @@
+"""Generates ecdh_secp256k1_ecpoint_cases.json and x25519_cases.json.
+
+The files follow Wycheproof's ecdh_ecpoint_test_schema.json and
+xdh_comp_schema.json, so the same loader reads them, but they are not
+Wycheproof's: the expected shared secrets come from OpenSSL through
+Python's cryptography package, and a public key is invalid when OpenSSL
+refuses it. The seed is fixed, so a rerun reproduces the files.
+
+    python3 src/wycheproof/generate.py
+"""
+
+import json
+import os
+import random
+
+from cryptography.hazmat.primitives.asymmetric import ec, x25519
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+import cryptography
+from cryptography.hazmat.backends.openssl import backend
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+RNG = random.Random(0x6D6F6E656461)
+
+P = 2**256 - 2**32 - 977
+N = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+P25519 = 2**255 - 19
+# the order of the prime subgroup of Curve25519
+L25519 = 2**252 + 27742317777372353535851937790883648493
+
+
+def rand_bytes(n):
+    return bytes(RNG.getrandbits(8) for _ in range(n))
+
+
+def generator_version():
+    return "moneda generate.py, cryptography %s, %s" % (cryptography.__version__, backend.openssl_version_text())
+
+
+# secp256k1
+
+
+def sec1(x, y, compressed):
+    if compressed:
+        return bytes([2 + (y & 1)]) + x.to_bytes(32, "big")
+    return b"\x04" + x.to_bytes(32, "big") + y.to_bytes(32, "big")
+
+
+def k1_public(d):
+    numbers = ec.derive_private_key(d, ec.SECP256K1()).public_key().public_numbers()
+    return numbers.x, numbers.y
+
+
+def k1_shared(d, public):
+    """OpenSSL's ECDH, or None if it refuses the public key"""
+    try:
+        peer = ec.EllipticCurvePublicKey.from_encoded_point(ec.SECP256K1(), public)
+    except ValueError:
+        return None
+    return ec.derive_private_key(d, ec.SECP256K1()).exchange(ec.ECDH(), peer)
+
+
+def k1_case(comment, flags, d, public, expect_valid):
+    shared = k1_shared(d, public)
+    assert (shared is not None) == expect_valid, comment
+    return {
+        "comment": comment,
+        "flags": flags,
+        "public": public.hex(),
+        "private": d.to_bytes(32, "big").hex(),
+        "shared": shared.hex() if shared else "",
+        "result": "valid" if expect_valid else "invalid",
+    }
+
+
+def k1_cases():
+    cases = []
+    for i in range(8):
+        d = RNG.randrange(1, N)
+        x, y = k1_public(RNG.randrange(1, N))
+        compressed = i % 2 == 1
+        cases.append(k1_case("random %s public key" % ("compressed" if compressed else "uncompressed"), ["Normal"], d, sec1(x, y, compressed), True))
+
+    x, y = k1_public(RNG.randrange(1, N))
+    for d, comment in [(1, "private key 1"), (2, "private key 2"), (N - 1, "private key n - 1")]:
+        cases.append(k1_case(comment, ["EdgeCasePrivateKey"], d, sec1(x, y, False), True))
+
+    gx, gy = k1_public(1)
+    cases.append(k1_case("public key is the generator", ["EdgeCasePublicKey"], RNG.randrange(1, N), sec1(gx, gy, True), True))
+    # the smallest x of a point, and the point with the other y
+    x = next(x for x in range(1, 100) if pow(x**3 + 7, (P - 1) // 2, P) == 1)
+    y = pow(x**3 + 7, (P + 1) // 4, P)
+    cases.append(k1_case("public key with x = %d" % x, ["EdgeCasePublicKey"], RNG.randrange(1, N), sec1(x, y, False), True))
+    cases.append(k1_case("public key with x = %d and odd y" % x, ["EdgeCasePublicKey"], RNG.randrange(1, N), sec1(x, P - y if y % 2 == 0 else y, True), True))
+
+    # a shared x below 2^248, which must keep its leading zero byte
+    x, y = k1_public(RNG.randrange(1, N))
+    while True:
+        d = RNG.randrange(1, N)
+        if k1_shared(d, sec1(x, y, False))[0] == 0:
+            break
+    cases.append(k1_case("shared secret with a leading zero byte", ["EdgeCaseSharedSecret"], d, sec1(x, y, False), True))
+
+    d = RNG.randrange(1, N)
+    x, y = k1_public(RNG.randrange(1, N))
+    cases.append(k1_case("y + 1, off the curve", ["InvalidPublic"], d, sec1(x, (y + 1) % P, False), False))
+    # the point with the smallest x, written with x + p, which still fits
+    x = next(x for x in range(1, 100) if pow(x**3 + 7, (P - 1) // 2, P) == 1)
+    y = pow(x**3 + 7, (P + 1) // 4, P)
+    cases.append(k1_case("x + p for x = %d, out of range" % x, ["InvalidPublic"], d, b"\x04" + (x + P).to_bytes(32, "big") + y.to_bytes(32, "big"), False))
+    cases.append(k1_case("compressed x = p", ["InvalidPublic"], d, b"\x02" + P.to_bytes(32, "big"), False))
+    # x^3 + 7 has no square root, so no point has this x
+    x = next(x for x in range(1, 100) if pow(x**3 + 7, (P - 1) // 2, P) != 1)
+    cases.append(k1_case("compressed x = %d, not the x of a point" % x, ["InvalidCompressedPublic"], d, b"\x03" + x.to_bytes(32, "big"), False))
+    cases.append(k1_case("the point at infinity", ["InvalidPublic"], d, b"\x00", False))
+    x, y = k1_public(RNG.randrange(1, N))
+    cases.append(k1_case("truncated uncompressed point", ["InvalidEncoding"], d, sec1(x, y, False)[:64], False))
+    cases.append(k1_case("compressed point with a trailing byte", ["InvalidEncoding"], d, sec1(x, y, True) + b"\x00", False))
+    cases.append(k1_case("unknown prefix 0x05", ["InvalidEncoding"], d, b"\x05" + sec1(x, y, False)[1:], False))
+    # a secp256r1 public key, whose coordinates are below the secp256k1 p
+    r1 = ec.derive_private_key(RNG.randrange(1, N), ec.SECP256R1()).public_key().public_bytes(Encoding.X962, PublicFormat.UncompressedPoint)
+    cases.append(k1_case("point of secp256r1", ["WrongCurve"], d, r1, False))
+
+    for i, case in enumerate(cases):
+        case["tcId"] = i + 1
+    return {
+        "algorithm": "ECDH",
+        "schema": "ecdh_ecpoint_test_schema.json",
+        "generatorVersion": generator_version(),
+        "numberOfTests": len(cases),
+        "header": [
+            "Not Wycheproof's vectors: cases in its ecdh_ecpoint_test_schema.json",
+            "generated by generate.py, with shared secrets computed by OpenSSL.",
+            "Public keys are SEC1 points, private keys big-endian.",
+        ],
+        "notes": {
+            "Normal": {"bugType": "BASIC", "description": "A random key pair"},
+            "EdgeCasePrivateKey": {"bugType": "EDGE_CASE", "description": "A private key at the edge of [1, n)"},
+            "EdgeCasePublicKey": {"bugType": "EDGE_CASE", "description": "A public key with special coordinates"},
+            "EdgeCaseSharedSecret": {"bugType": "EDGE_CASE", "description": "A shared secret that is short as an integer"},
+            "InvalidPublic": {"bugType": "CONFIDENTIALITY", "description": "The public key is not a point of the curve, which invalid-curve attacks exploit"},
+            "InvalidCompressedPublic": {"bugType": "CONFIDENTIALITY", "description": "The compressed x belongs to no point of the curve"},
+            "InvalidEncoding": {"bugType": "MODIFIED_PARAMETER", "description": "The SEC1 encoding is malformed"},
+            "WrongCurve": {"bugType": "CONFIDENTIALITY", "description": "The public key is a point of another curve"},
+        },
+        "testGroups": [{"type": "EcdhEcpointTest", "curve": "secp256k1", "encoding": "ecpoint", "tests": cases}],
+    }
+
+
+# X25519
+
+
+def ladder(k, u):
+    """RFC 7748's ladder with an unclamped k, to find the small-order points"""
+    x1, x2, z2, x3, z3, swap = u, 1, 0, u, 1, 0
+    for t in reversed(range(max(k.bit_length(), 1))):
+        bit = (k >> t) & 1
+        if swap ^ bit:
+            x2, x3, z2, z3 = x3, x2, z3, z2
+        swap = bit
+        a, b = (x2 + z2) % P25519, (x2 - z2) % P25519
+        aa, bb = a * a % P25519, b * b % P25519
+        e = (aa - bb) % P25519
+        c, d = (x3 + z3) % P25519, (x3 - z3) % P25519
+        da, cb = d * a % P25519, c * b % P25519
+        x3, z3 = (da + cb) ** 2 % P25519, x1 * (da - cb) ** 2 % P25519
+        x2, z2 = aa * bb % P25519, e * (aa + 121665 * e) % P25519
+    if swap:
+        x2, z2 = x3, z3
+    return x2 * pow(z2, P25519 - 2, P25519) % P25519
+
+
+def is_square(n):
+    return n == 0 or pow(n, (P25519 - 1) // 2, P25519) == 1
+
+
+def on_curve(u):
+    return is_square((u**3 + 486662 * u**2 + u) % P25519)
+
+
+def x_shared(private, public):
+    """OpenSSL's X25519, or None if the output is all zeros"""
+    key = x25519.X25519PrivateKey.from_private_bytes(private)
+    try:
+        return key.exchange(x25519.X25519PublicKey.from_public_bytes(public))
+    except ValueError:
+        return None
+
+
+def x_case(comment, flags, private, public):
+    shared = x_shared(private, public)
+    return {
+        "comment": comment,
+        "flags": flags,
+        "public": public.hex(),
+        "private": private.hex(),
+        "shared": shared.hex() if shared else "00" * 32,
+        # RFC 7748 leaves rejecting the all-zero output to the protocol
+        "result": "valid" if shared else "acceptable",
+    }
+
+
+def le(u):
+    return u.to_bytes(32, "little")
+
+
+def x_cases():
+    cases = []
+    for _ in range(6):
+        public = x25519.X25519PrivateKey.from_private_bytes(rand_bytes(32)).public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+        cases.append(x_case("random public key", ["Normal"], rand_bytes(32), public))
+    public = x25519.X25519PrivateKey.from_private_bytes(rand_bytes(32)).public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+    cases.append(x_case("private key of zeros, clamped", ["EdgeCasePrivateKey"], bytes(32), public))
+    cases.append(x_case("private key of ones, clamped", ["EdgeCasePrivateKey"], b"\xff" * 32, public))
+
+    # u on the twist, where X25519 is defined too
+    twist = []
+    while len(twist) < 4:
+        u = RNG.randrange(2, P25519)
+        if not on_curve(u):
+            twist.append(u)
+    twist.append(next(u for u in range(2, 100) if not on_curve(u)))
+    for u in twist:
+        cases.append(x_case("u = %#x on the twist" % u, ["Twist"], rand_bytes(32), le(u)))
+
+    # u >= p is reduced, and the top bit is masked
+    for u in [P25519 + 9, 2**255 - 1]:
+        cases.append(x_case("non-canonical u = %#x" % u, ["NonCanonicalPublic"], rand_bytes(32), le(u)))
+    u = int.from_bytes(public, "little") | 2**255
+    cases.append(x_case("public key with the top bit set", ["PublicKeyWithHighBitSet"], rand_bytes(32), le(u)))
+
+    # the points of order 1, 2, 4 and 8, as l times random points of the
+    # curve and its twist, and their non-canonical encodings
+    small = {0, 1, P25519 - 1}
+    while len(small) < 5:
+        u = RNG.randrange(2, P25519)
+        if on_curve(u):
+            small.add(ladder(L25519, u))
+    for u in sorted(small) + [u + P25519 for u in sorted(small) if u + P25519 < 2**255]:
+        cases.append(x_case("small-order u = %#x" % u, ["LowOrderPublic", "ZeroSharedSecret"], rand_bytes(32), le(u)))
+
+    for i, case in enumerate(cases):
+        case["tcId"] = i + 1
+    return {
+        "algorithm": "XDH",
+        "schema": "xdh_comp_schema.json",
+        "generatorVersion": generator_version(),
+        "numberOfTests": len(cases),
+        "header": [
+            "Not Wycheproof's vectors: cases in its xdh_comp_schema.json",
+            "generated by generate.py, with shared secrets computed by OpenSSL.",
+        ],
+        "notes": {
+            "Normal": {"bugType": "BASIC", "description": "A random key pair"},
+            "EdgeCasePrivateKey": {"bugType": "EDGE_CASE", "description": "A private key that clamping changes"},
+            "Twist": {"bugType": "EDGE_CASE", "description": "The public key is a point of the twist"},
+            "NonCanonicalPublic": {"bugType": "EDGE_CASE", "description": "The public key is u + p, or has the top bit set"},
+            "PublicKeyWithHighBitSet": {"bugType": "EDGE_CASE", "description": "The top bit of the public key is set, which is masked"},
+            "LowOrderPublic": {"bugType": "EDGE_CASE", "description": "The public key has order 1, 2, 4 or 8"},
+            "ZeroSharedSecret": {"bugType": "EDGE_CASE", "description": "The shared secret is all zeros"},
+        },
+        "testGroups": [{"type": "XdhComp", "curve": "curve25519", "tests": cases}],
+    }
+
+
+def write(name, data):
+    with open(os.path.join(HERE, name), "w") as f:
+        json.dump(data, f, indent=2)
+        f.write("\n")
+
+
+if __name__ == "__main__":
+    write("ecdh_secp256k1_ecpoint_cases.json", k1_cases())
+    write("x25519_cases.json", x_cases())
@@ -0,0 +1,191 @@
+use std::fmt::Display;
+
+use serde_json::Value;
+
+// Google's Wycheproof vectors: known-answer tests built around the ways
+// implementations have gone wrong, like BER instead of DER, r or s of zero
+// or n, public keys that stress the Shamir trick and hashes that reduce to
+// special values. Each case has an id, a comment, flags naming the class of
+// bug it probes and an expected result.
+//
+// Vendored is ECDSA over secp256k1 with SHA-256 in its Bitcoin variant,
+// which also rejects high s. The ECDH over secp256k1 and X25519 files are
+// not Wycheproof's own but cases in its schemas, made by generate.py with
+// OpenSSL computing the shared secrets: invalid and off-curve points, bad
+// SEC1 encodings and a point of secp256r1 for ECDH, and twist,
+// non-canonical and small-order points for X25519. Wycheproof's own
+// ecdh_secp256k1_test.json and x25519_test.json run through the same
+// `load` and `cases` once they replace them.
+//
+// Schnorr is out of scope: Wycheproof has no BIP-340 vectors, and the
+// BIP's own vectors run in the schnorr module's tests
+
+const ECDSA_BITCOIN: &str = include_str!("ecdsa_secp256k1_sha256_bitcoin_test.json");
+const ECDH_SECP256K1: &str = include_str!("ecdh_secp256k1_ecpoint_cases.json");
+const X25519: &str = include_str!("x25519_cases.json");
+
+/// One test with the group it belongs to, which holds what its tests share,
+/// such as the public key
+pub struct Case<'a> {
+    group: &'a Value,
+    test: &'a Value,
+}
+
+impl Case<'_> {
+    pub fn id(&self) -> u64 {
+        self.test["tcId"].as_u64().expect("tcId")
+    }
+
+    /// Whether an implementation must accept the input. Wycheproof's third
+    /// result, "acceptable", leaves the choice to the implementation
+    pub fn expected(&self) -> Option<bool> {
+        match self.test["result"].as_str().expect("result") {
+            "valid" => Some(true),
+            "invalid" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.test["flags"].as_array().expect("flags").iter().any(|f| f == flag)
+    }
+
+    /// A hex field of the test itself
+    pub fn hex(&self, key: &str) -> Vec<u8> {
+        hex_field(&self.test[key], key)
+    }
+
+    /// A hex field of the group, at the path `keys`
+    pub fn group_hex(&self, keys: &[&str]) -> Vec<u8> {
+        let value = keys.iter().fold(self.group, |value, key| &value[key]);
+        hex_field(value, &keys.join("."))
+    }
+}
+
+impl Display for Case<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tcId {} ({}, flags {})",
+            self.id(),
+            self.test["comment"],
+            self.test["flags"]
+        )
+    }
+}
+
+fn hex_field(value: &Value, key: &str) -> Vec<u8> {
+    let s = value.as_str().unwrap_or_else(|| panic!("no hex field {}", key));
+    hex::decode(s).unwrap_or_else(|_| panic!("{} is not hex", key))
+}
+
+pub fn load(json: &str) -> Value {
+    serde_json::from_str(json).expect("a Wycheproof file")
+}
+
+/// Every test of every group
+pub fn cases(file: &Value) -> impl Iterator<Item = Case<'_>> {
+    file["testGroups"]
+        .as_array()
+        .expect("testGroups")
+        .iter()
+        .flat_map(|group| {
+            group["tests"]
+                .as_array()
+                .expect("tests")
+                .iter()
+                .map(move |test| Case { group, test })
+        })
+}
+
+#[cfg(test)]
+mod wycheproof_tests {
+    use num_bigint::BigUint;
+
+    use super::*;
+    use crate::crypto::ecdh::shared_secret;
+    use crate::crypto::hash::sha256;
+    use crate::crypto::x25519;
+    use crate::curves::secp256k1::ecc::{KeyError, PrivateKey, PublicKey, Signature};
+
+    /// What a Bitcoin verifier does with a signature off the wire: strict
+    /// DER as BIP-66 requires, low s as BIP-146 requires, then ECDSA
+    fn verify_bitcoin(public_key: &PublicKey, msg: &[u8], der: &[u8]) -> bool {
+        let Ok(signature) = Signature::parse_der(der) else {
+            return false;
+        };
+        signature.is_low_s() && public_key.verify(&BigUint::from_bytes_be(&sha256(msg)), &signature)
+    }
+
+    #[test]
+    fn test_ecdsa_bitcoin() {
+        let file = load(ECDSA_BITCOIN);
+        assert_eq!(file["schema"], "ecdsa_bitcoin_verify_schema.json");
+        let mut count = 0;
+        for case in cases(&file) {
+            let public_key = PublicKey::parse(&case.group_hex(&["publicKey", "uncompressed"]))
+                .unwrap_or_else(|e| panic!("{}: {}", case, e));
+            let valid = verify_bitcoin(&public_key, &case.hex("msg"), &case.hex("sig"));
+            if let Some(expected) = case.expected() {
+                assert_eq!(valid, expected, "{}", case);
+            }
+            count += 1;
+        }
+        assert_eq!(count, file["numberOfTests"].as_u64().unwrap());
+    }
+
+    #[test]
+    fn test_ecdh_secp256k1() {
+        let file = load(ECDH_SECP256K1);
+        assert_eq!(file["schema"], "ecdh_ecpoint_test_schema.json");
+        let mut count = 0;
+        for case in cases(&file) {
+            let private_key = PrivateKey::from(BigUint::from_bytes_be(&case.hex("private")))
+                .unwrap_or_else(|e| panic!("{}: {}", case, e));
+            // a point the parser accepts must give the expected secret
+            let valid = match PublicKey::parse(&case.hex("public")) {
+                Ok(public_key) => {
+                    assert_eq!(
+                        shared_secret(&private_key, &public_key).to_vec(),
+                        case.hex("shared"),
+                        "{}",
+                        case
+                    );
+                    true
+                }
+                Err(_) => false,
+            };
+            if let Some(expected) = case.expected() {
+                assert_eq!(valid, expected, "{}", case);
+            }
+            count += 1;
+        }
+        assert_eq!(count, file["numberOfTests"].as_u64().unwrap());
+    }
+
+    #[test]
+    fn test_x25519() {
+        let file = load(X25519);
+        assert_eq!(file["schema"], "xdh_comp_schema.json");
+        let mut count = 0;
+        for case in cases(&file) {
+            let private: [u8; 32] = case.hex("private").try_into().unwrap();
+            let public: [u8; 32] = case.hex("public").try_into().unwrap();
+            let shared = case.hex("shared");
+            // twist and non-canonical points are valid inputs to X25519
+            assert_eq!(x25519::x25519(&private, &public).to_vec(), shared, "{}", case);
+            let result = x25519::shared_secret(&private, &public);
+            if case.has_flag("ZeroSharedSecret") {
+                // the choice RFC 7748 leaves open: rejected
+                assert_eq!(result, Err(KeyError::SmallOrderPoint), "{}", case);
+            } else {
+                assert_eq!(result.as_ref().map(|s| s.to_vec()), Ok(shared), "{}", case);
+            }
+            if let Some(expected) = case.expected() {
+                assert_eq!(result.is_ok(), expected, "{}", case);
+            }
+            count += 1;
+        }
+        assert_eq!(count, file["numberOfTests"].as_u64().unwrap());
+    }
+}